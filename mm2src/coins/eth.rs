@@ -109,16 +109,16 @@ cfg_wasm32! {
 
 use super::{coin_conf, lp_coinfind_or_err, AsyncMutex, BalanceError, BalanceFut, CheckIfMyPaymentSentArgs,
             CoinBalance, CoinProtocol, CoinTransportMetrics, CoinsContext, ConfirmPaymentInput, EthValidateFeeArgs,
-            FeeApproxStage, FoundSwapTxSpend, HistorySyncState, IguanaPrivKey, MarketCoinOps, MmCoin, MmCoinEnum,
-            MyAddressError, MyWalletAddress, NegotiateSwapContractAddrErr, NumConversError, NumConversResult,
-            PaymentInstructionArgs, PaymentInstructions, PaymentInstructionsErr, PrivKeyBuildPolicy,
-            PrivKeyPolicyNotAllowed, RawTransactionError, RawTransactionFut, RawTransactionRequest, RawTransactionRes,
-            RawTransactionResult, RefundPaymentArgs, RewardTarget, RpcClientType, RpcTransportEventHandler,
-            RpcTransportEventHandlerShared, SearchForSwapTxSpendInput, SendMakerPaymentSpendPreimageInput,
-            SendPaymentArgs, SignEthTransactionParams, SignRawTransactionEnum, SignRawTransactionRequest,
-            SignatureError, SignatureResult, SpendPaymentArgs, SwapOps, SwapTxFeePolicy, TradeFee, TradePreimageError,
-            TradePreimageFut, TradePreimageResult, TradePreimageValue, Transaction, TransactionDetails,
-            TransactionEnum, TransactionErr, TransactionFut, TransactionType, TxMarshalingErr,
+            FeeApproxStage, FoundSwapTxSpend, HistorySyncState, IguanaPrivKey, MakerPaymentSpendForWatcherArgs,
+            MarketCoinOps, MmCoin, MmCoinEnum, MyAddressError, MyWalletAddress, NegotiateSwapContractAddrErr,
+            NumConversError, NumConversResult, PaymentInstructionArgs, PaymentInstructions, PaymentInstructionsErr,
+            PrivKeyBuildPolicy, PrivKeyPolicyNotAllowed, RawTransactionError, RawTransactionFut,
+            RawTransactionRequest, RawTransactionRes, RawTransactionResult, RefundPaymentArgs, RewardTarget,
+            RpcClientType, RpcTransportEventHandler, RpcTransportEventHandlerShared, SearchForSwapTxSpendInput,
+            SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SignEthTransactionParams, SignRawTransactionEnum,
+            SignRawTransactionRequest, SignatureError, SignatureResult, SpendPaymentArgs, SwapOps, SwapTxFeePolicy,
+            TradeFee, TradePreimageError, TradePreimageFut, TradePreimageResult, TradePreimageValue, Transaction,
+            TransactionDetails, TransactionEnum, TransactionErr, TransactionFut, TransactionType, TxMarshalingErr,
             UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs, ValidateInstructionsErr,
             ValidateOtherPubKeyErr, ValidatePaymentError, ValidatePaymentFut, ValidatePaymentInput, VerificationError,
             VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WatcherReward, WatcherRewardError,
@@ -131,11 +131,13 @@ cfg_native! {
     use std::path::PathBuf;
 }
 
+pub(crate) mod block_range;
 pub mod eth_balance_events;
 mod eth_rpc;
 #[cfg(test)] mod eth_tests;
 #[cfg(target_arch = "wasm32")] mod eth_wasm_tests;
-#[cfg(any(test, target_arch = "wasm32"))] mod for_tests;
+#[cfg(any(test, target_arch = "wasm32"))]
+pub(crate) mod for_tests;
 pub(crate) mod nft_swap_v2;
 mod web3_transport;
 use web3_transport::{http_transport::HttpTransportNode, Web3Transport};
@@ -143,6 +145,10 @@ use web3_transport::{http_transport::HttpTransportNode, Web3Transport};
 pub mod eth_hd_wallet;
 use eth_hd_wallet::EthHDWallet;
 
+pub mod eth_sign_message;
+
+pub mod eth_tx_history_v2;
+
 #[path = "eth/v2_activation.rs"] pub mod v2_activation;
 use v2_activation::{build_address_and_priv_key_policy, EthActivationV2Error};
 
@@ -158,6 +164,11 @@ use fee_estimation::eip1559::{block_native::BlocknativeGasApiCaller, infura::Inf
 
 pub mod erc20;
 use erc20::get_token_decimals;
+pub mod erc20_allowances;
+pub mod node_health;
+use node_health::{validate_rpc_quorum, NodeHealth};
+pub mod nonce_tracker;
+use nonce_tracker::PendingEthTx;
 pub(crate) mod eth_swap_v2;
 use eth_swap_v2::{extract_id_from_tx_data, EthPaymentType, PaymentMethod, SpendTxSearchParams};
 
@@ -828,6 +839,10 @@ pub struct EthCoinImpl {
     fallback_swap_contract: Option<Address>,
     contract_supports_watchers: bool,
     web3_instances: AsyncMutex<Vec<Web3Instance>>,
+    /// The minimum number of nodes that must return the same response for swap-critical reads,
+    /// e.g. payment validation, transaction receipts and the swap contract `payments` status.
+    /// `None` means the first node that answers is trusted.
+    rpc_quorum: Option<usize>,
     decimals: u8,
     history_sync_state: Mutex<HistorySyncState>,
     required_confirmations: AtomicU64,
@@ -865,6 +880,8 @@ pub struct EthCoinImpl {
 pub struct Web3Instance {
     web3: Web3<Web3Transport>,
     is_parity: bool,
+    /// Rolling latency/error/lag statistics of the node, shared by all clones of the instance.
+    health: Arc<NodeHealth>,
 }
 
 /// Information about a token that follows the ERC20 protocol on an EVM-based network.
@@ -1141,12 +1158,11 @@ pub async fn withdraw_erc1155(ctx: MmArc, withdraw_type: WithdrawErc1155) -> Wit
         false,
     )
     .await?;
+    // The transaction is tracked once it's broadcast with `send_raw_transaction`, see `EthCoin::track_raw_tx`.
     let address_lock = eth_coin.get_address_lock(my_address.to_string()).await;
     let _nonce_lock = address_lock.lock().await;
     let (nonce, _) = eth_coin
-        .clone()
-        .get_addr_nonce(my_address)
-        .compat()
+        .next_nonce(my_address)
         .timeout_secs(30.)
         .await?
         .map_to_mm(WithdrawError::Transport)?;
@@ -1232,12 +1248,11 @@ pub async fn withdraw_erc721(ctx: MmArc, withdraw_type: WithdrawErc721) -> Withd
     )
     .await?;
 
+    // The transaction is tracked once it's broadcast with `send_raw_transaction`, see `EthCoin::track_raw_tx`.
     let address_lock = eth_coin.get_address_lock(my_address.to_string()).await;
     let _nonce_lock = address_lock.lock().await;
     let (nonce, _) = eth_coin
-        .clone()
-        .get_addr_nonce(my_address)
-        .compat()
+        .next_nonce(my_address)
         .timeout_secs(30.)
         .await?
         .map_to_mm(WithdrawError::Transport)?;
@@ -1464,6 +1479,17 @@ impl SwapOps for EthCoin {
         watcher_reward: bool,
     ) -> Result<[u8; 32], String> {
         let unverified: UnverifiedTransactionWrapper = try_s!(rlp::decode(spend_tx));
+        // Swap v2 watchers extract the maker secret from the taker payment spend.
+        if let Ok(spend_taker_payment) = TAKER_SWAP_V2.function("spendTakerPayment") {
+            if unverified
+                .unsigned()
+                .data()
+                .starts_with(&spend_taker_payment.short_signature())
+            {
+                let spend_tx = try_s!(SignedEthTx::new(unverified));
+                return self.extract_secret_v2_impl(&spend_tx).await;
+            }
+        }
         let function_name = get_function_name("receiverSpend", watcher_reward);
         let function = try_s!(SWAP_CONTRACT.function(&function_name));
 
@@ -1614,6 +1640,57 @@ impl SwapOps for EthCoin {
 
 #[async_trait]
 impl WatcherOps for EthCoin {
+    /// Swap v2 watchers call the `*Watcher` methods of the swap v2 contracts,
+    /// see `eth_swap_v2::eth_watcher_v2` for the preimages format.
+    fn is_supported_by_watchers_v2(&self) -> bool { self.swap_v2_contracts.is_some() }
+
+    async fn sign_refund_for_watcher_v2(&self, args: RefundPaymentArgs<'_>) -> TransactionResult {
+        self.sign_refund_for_watcher_v2_impl(args)
+            .await
+            .map(TransactionEnum::from)
+    }
+
+    async fn sign_maker_payment_spend_for_watcher_v2(
+        &self,
+        args: MakerPaymentSpendForWatcherArgs<'_>,
+    ) -> TransactionResult {
+        self.sign_maker_payment_spend_for_watcher_v2_impl(args)
+            .await
+            .map(TransactionEnum::from)
+    }
+
+    async fn watcher_validate_preimage_v2(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        is_refund: bool,
+    ) -> ValidatePaymentResult<()> {
+        self.watcher_validate_preimage_v2_impl(payment_tx, preimage, is_refund)
+            .await
+    }
+
+    async fn send_preimage_as_watcher_v2(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        secret: Option<&[u8]>,
+    ) -> TransactionResult {
+        self.send_preimage_as_watcher_v2_impl(payment_tx, preimage, secret)
+            .await
+            .map(TransactionEnum::from)
+    }
+
+    async fn watcher_search_for_payment_spend_v2(
+        &self,
+        payment_tx: &[u8],
+        search_from_block: u64,
+    ) -> Result<Option<TransactionEnum>, String> {
+        let spend = self
+            .watcher_search_for_payment_spend_v2_impl(payment_tx, search_from_block)
+            .await?;
+        Ok(spend.map(TransactionEnum::from))
+    }
+
     fn send_maker_payment_spend_preimage(&self, input: SendMakerPaymentSpendPreimageInput) -> TransactionFut {
         Box::new(
             self.watcher_spends_hash_time_locked_payment(input)
@@ -1943,7 +2020,7 @@ impl WatcherOps for EthCoin {
         let fallback_swap_contract = self.fallback_swap_contract;
 
         let fut = async move {
-            let tx_from_rpc = selfi.transaction(TransactionId::Hash(tx.tx_hash())).await?;
+            let tx_from_rpc = selfi.transaction_with_quorum(tx.tx_hash()).await?;
 
             let tx_from_rpc = tx_from_rpc.as_ref().ok_or_else(|| {
                 ValidatePaymentError::TxDoesNotExist(format!("Didn't find provided tx {:?} on ETH node", tx))
@@ -2382,10 +2459,13 @@ impl MarketCoinOps for EthCoin {
         let coin = self.clone();
 
         let fut = async move {
-            coin.send_raw_transaction(bytes.into())
+            let tx_hash = coin
+                .send_raw_transaction(bytes.into())
                 .await
-                .map(|res| format!("{:02x}", res)) // TODO: add 0x hash (use unified hash format for eth wherever it is returned)
-                .map_err(|e| ERRL!("{}", e))
+                .map_err(|e| ERRL!("{}", e))?;
+            coin.track_raw_tx(tx_hash).await;
+            // TODO: add 0x hash (use unified hash format for eth wherever it is returned)
+            Ok(format!("{:02x}", tx_hash))
         };
 
         Box::new(fut.boxed().compat())
@@ -2396,10 +2476,9 @@ impl MarketCoinOps for EthCoin {
 
         let tx = tx.to_owned();
         let fut = async move {
-            coin.send_raw_transaction(tx.into())
-                .await
-                .map(|res| format!("{:02x}", res))
-                .map_err(|e| ERRL!("{}", e))
+            let tx_hash = coin.send_raw_transaction(tx.into()).await.map_err(|e| ERRL!("{}", e))?;
+            coin.track_raw_tx(tx_hash).await;
+            Ok(format!("{:02x}", tx_hash))
         };
 
         Box::new(fut.boxed().compat())
@@ -2615,9 +2694,8 @@ type EthTxFut = Box<dyn Future<Item = SignedEthTx, Error = TransactionErr> + Sen
 
 /// Signs an Eth transaction using `key_pair`.
 ///
-/// This method polls for the latest nonce from the RPC nodes and uses it for the transaction to be signed.
-/// A `nonce_lock` is returned so that the caller doesn't release it until the transaction is sent and the
-/// address nonce is updated on RPC nodes.
+/// This method takes the next nonce from the RPC nodes and the local nonce tracker, see [`EthCoin::next_nonce`].
+/// The caller must hold the address nonce lock until the transaction is sent and tracked.
 #[allow(clippy::too_many_arguments)]
 async fn sign_transaction_with_keypair<'a>(
     coin: &'a EthCoin,
@@ -2629,8 +2707,8 @@ async fn sign_transaction_with_keypair<'a>(
     pay_for_gas_option: &PayForGasOption,
     from_address: Address,
 ) -> Result<(SignedEthTx, Vec<Web3Instance>), TransactionErr> {
-    info!(target: "sign", "get_addr_nonce…");
-    let (nonce, web3_instances_with_latest_nonce) = try_tx_s!(coin.clone().get_addr_nonce(from_address).compat().await);
+    info!(target: "sign", "next_nonce…");
+    let (nonce, web3_instances_with_latest_nonce) = try_tx_s!(coin.next_nonce(from_address).await);
     let tx_type = tx_type_from_pay_for_gas_option!(pay_for_gas_option);
     if !coin.is_tx_type_supported(&tx_type) {
         return Err(TransactionErr::Plain("Eth transaction type not supported".into()));
@@ -2663,9 +2741,18 @@ async fn sign_and_send_transaction_with_keypair(
             .await
     );
     let address_lock = coin.get_address_lock(address.to_string()).await;
-    let _nonce_lock = address_lock.lock().await;
-    let (signed, web3_instances_with_latest_nonce) =
-        sign_transaction_with_keypair(coin, key_pair, value, action, data, gas, &pay_for_gas_option, address).await?;
+    let nonce_lock = address_lock.lock().await;
+    let (signed, web3_instances_with_latest_nonce) = sign_transaction_with_keypair(
+        coin,
+        key_pair,
+        value,
+        action.clone(),
+        data.clone(),
+        gas,
+        &pay_for_gas_option,
+        address,
+    )
+    .await?;
     let bytes = Bytes(rlp::encode(&signed).to_vec());
     info!(target: "sign-and-send", "send_raw_transaction…");
 
@@ -2674,6 +2761,21 @@ async fn sign_and_send_transaction_with_keypair(
         .map(|web3_instance| web3_instance.web3.eth().send_raw_transaction(bytes.clone()));
     try_tx_s!(select_ok(futures).await.map_err(|e| ERRL!("{}", e)), signed);
 
+    let pending_tx = PendingEthTx::new(
+        signed.tx_hash(),
+        signed.unsigned().nonce(),
+        &action,
+        value,
+        data,
+        gas,
+        &pay_for_gas_option,
+    );
+    // Once the nonce is tracked, the next transaction from this address doesn't need to wait for the nodes.
+    // Otherwise the lock is kept until the nodes report the increased nonce, so the nonce isn't reused.
+    if coin.track_pending_tx(address, &pending_tx).await {
+        drop(nonce_lock);
+    }
+
     info!(target: "sign-and-send", "wait_for_tx_appears_on_rpc…");
     coin.wait_for_addr_nonce_increase(address, signed.unsigned().nonce())
         .await;
@@ -4597,15 +4699,26 @@ impl EthCoin {
     /// Also, note that the contract call has to be initiated by my wallet address,
     /// because [`CallRequest::from`] is set to [`EthCoinImpl::my_address`].
     async fn estimate_gas_for_contract_call(&self, contract_addr: Address, call_data: Bytes) -> Web3RpcResult<U256> {
+        let my_address = self.derivation_method.single_addr_or_err().await?;
+        self.estimate_gas_for_contract_call_from(my_address, contract_addr, call_data)
+            .await
+    }
+
+    /// Estimates the gas of the `contract_addr` call made by `from`, which may be any address of the HD wallet.
+    async fn estimate_gas_for_contract_call_from(
+        &self,
+        from: Address,
+        contract_addr: Address,
+        call_data: Bytes,
+    ) -> Web3RpcResult<U256> {
         let coin = self.clone();
-        let my_address = coin.derivation_method.single_addr_or_err().await?;
         let fee_policy_for_estimate = get_swap_fee_policy_for_estimate(self.get_swap_transaction_fee_policy());
         let pay_for_gas_option = coin.get_swap_pay_for_gas_option(fee_policy_for_estimate).await?;
         let eth_value = U256::zero();
         let estimate_gas_req = CallRequest {
             value: Some(eth_value),
             data: Some(call_data),
-            from: Some(my_address),
+            from: Some(from),
             to: Some(contract_addr),
             ..CallRequest::default()
         };
@@ -4650,6 +4763,26 @@ impl EthCoin {
         self.call(request, Some(BlockId::Number(block_number))).await
     }
 
+    /// Same as [`EthCoin::call_request`], but the result must be confirmed by `rpc_quorum` nodes.
+    /// Used for swap-critical reads like the swap contract payment status.
+    pub(crate) async fn call_request_with_quorum(
+        &self,
+        from: Address,
+        to: Address,
+        data: Option<Bytes>,
+        block_number: BlockNumber,
+    ) -> Result<Bytes, web3::Error> {
+        let request = CallRequest {
+            from: Some(from),
+            to: Some(to),
+            data,
+            ..CallRequest::default()
+        };
+
+        self.call_with_quorum(request, Some(BlockId::Number(block_number)))
+            .await
+    }
+
     pub fn allowance(&self, spender: Address) -> Web3RpcFut<U256> {
         let coin = self.clone();
         let fut = async move {
@@ -4657,23 +4790,9 @@ impl EthCoin {
                 EthCoinType::Eth => MmError::err(Web3RpcError::Internal(
                     "'allowance' must not be called for ETH coin".to_owned(),
                 )),
-                EthCoinType::Erc20 { ref token_addr, .. } => {
-                    let function = ERC20_CONTRACT.function("allowance")?;
+                EthCoinType::Erc20 { token_addr, .. } => {
                     let my_address = coin.derivation_method.single_addr_or_err().await?;
-                    let data = function.encode_input(&[Token::Address(my_address), Token::Address(spender)])?;
-
-                    let res = coin
-                        .call_request(my_address, *token_addr, None, Some(data.into()), BlockNumber::Latest)
-                        .await?;
-                    let decoded = function.decode_output(&res.0)?;
-
-                    match decoded[0] {
-                        Token::Uint(number) => Ok(number),
-                        _ => {
-                            let error = format!("Expected U256 as allowance result but got {:?}", decoded);
-                            MmError::err(Web3RpcError::InvalidResponse(error))
-                        },
-                    }
+                    coin.erc20_allowance(token_addr, my_address, spender).await
                 },
                 EthCoinType::Nft { .. } => MmError::err(Web3RpcError::NftProtocolNotSupported),
             }
@@ -4727,17 +4846,7 @@ impl EthCoin {
                     )))
                 },
             };
-            let function = try_tx_s!(ERC20_CONTRACT.function("approve"));
-            let data = try_tx_s!(function.encode_input(&[Token::Address(spender), Token::Uint(amount)]));
-
-            let gas_limit = try_tx_s!(
-                coin.estimate_gas_for_contract_call(token_addr, Bytes::from(data.clone()))
-                    .await
-            );
-
-            coin.sign_and_send_transaction(0.into(), Call(token_addr), data, gas_limit)
-                .compat()
-                .await
+            coin.erc20_approve(token_addr, spender, amount).await
         };
         Box::new(fut.boxed().compat())
     }
@@ -4827,7 +4936,7 @@ impl EthCoin {
                 )));
             }
 
-            let tx_from_rpc = selfi.transaction(TransactionId::Hash(tx.tx_hash())).await?;
+            let tx_from_rpc = selfi.transaction_with_quorum(tx.tx_hash()).await?;
             let tx_from_rpc = tx_from_rpc.as_ref().ok_or_else(|| {
                 ValidatePaymentError::TxDoesNotExist(format!("Didn't find provided tx {:?} on ETH node", tx.tx_hash()))
             })?;
@@ -5096,10 +5205,9 @@ impl EthCoin {
                 .single_addr_or_err()
                 .await
                 .map_err(|e| ERRL!("{}", e))?;
-            coin.call_request(
+            coin.call_request_with_quorum(
                 my_address,
                 swap_contract_address,
-                None,
                 Some(data.into()),
                 // TODO worth reviewing places where we could use BlockNumber::Pending
                 BlockNumber::Latest,
@@ -5927,7 +6035,7 @@ fn validate_fee_impl(coin: EthCoin, validate_fee_args: EthValidateFeeArgs<'_>) -
 
     let fut = async move {
         let expected_value = wei_from_big_decimal(&amount, coin.decimals)?;
-        let tx_from_rpc = coin.transaction(TransactionId::Hash(fee_tx_hash)).await?;
+        let tx_from_rpc = coin.transaction_with_quorum(fee_tx_hash).await?;
 
         let tx_from_rpc = tx_from_rpc.as_ref().ok_or_else(|| {
             ValidatePaymentError::TxDoesNotExist(format!("Didn't find provided tx {:?} on ETH node", fee_tx_hash))
@@ -6395,6 +6503,7 @@ pub async fn eth_coin_from_conf_and_request(
         web3_instances.push(Web3Instance {
             web3,
             is_parity: version.contains("Parity") || version.contains("parity"),
+            health: Default::default(),
         })
     }
 
@@ -6402,6 +6511,13 @@ pub async fn eth_coin_from_conf_and_request(
         return ERR!("Failed to get client version for all urls");
     }
 
+    // param from request should override the config
+    let rpc_quorum = req["rpc_quorum"]
+        .as_u64()
+        .or_else(|| conf["rpc_quorum"].as_u64())
+        .map(|quorum| quorum as usize);
+    let rpc_quorum = try_s!(validate_rpc_quorum(rpc_quorum, web3_instances.len()));
+
     let (coin_type, decimals) = match protocol {
         CoinProtocol::ETH => (EthCoinType::Eth, ETH_DECIMALS),
         CoinProtocol::ERC20 {
@@ -6488,6 +6604,7 @@ pub async fn eth_coin_from_conf_and_request(
         decimals,
         ticker: ticker.into(),
         web3_instances: AsyncMutex::new(web3_instances),
+        rpc_quorum,
         history_sync_state: Mutex::new(initial_history_state),
         swap_txfee_policy: Mutex::new(SwapTxFeePolicy::Internal),
         max_eth_tx_type,
@@ -7307,6 +7424,7 @@ impl EthCoin {
             fallback_swap_contract: self.fallback_swap_contract,
             contract_supports_watchers: self.contract_supports_watchers,
             web3_instances: AsyncMutex::new(self.web3_instances.lock().await.clone()),
+            rpc_quorum: self.rpc_quorum,
             decimals: self.decimals,
             history_sync_state: Mutex::new(self.history_sync_state.lock().unwrap().clone()),
             required_confirmations: AtomicU64::new(
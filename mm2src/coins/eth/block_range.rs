@@ -0,0 +1,67 @@
+//! The size of the block ranges requested by `eth_getLogs` and the similar scans.
+
+use std::cmp;
+
+/// The initial and the maximum number of blocks requested at once.
+const MAX_BLOCK_RANGE: u64 = 10000;
+/// The range isn't shrunk further, the error is not caused by its size then.
+const MIN_BLOCK_RANGE: u64 = 100;
+
+/// Many public RPC providers limit the number of blocks requested by `eth_getLogs` at once.
+/// The range is halved on errors down to [`MIN_BLOCK_RANGE`] and doubled back on successes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct AdaptiveBlockRange {
+    range: u64,
+}
+
+impl Default for AdaptiveBlockRange {
+    fn default() -> Self { AdaptiveBlockRange { range: MAX_BLOCK_RANGE } }
+}
+
+impl AdaptiveBlockRange {
+    /// Returns the last block of the range starting at `from_block`, not further than `last_block`.
+    pub(crate) fn range_end(&self, from_block: u64, last_block: u64) -> u64 {
+        cmp::min(from_block.saturating_add(self.range - 1), last_block)
+    }
+
+    /// Halves the range after an error.
+    /// Returns `false` if the range is the minimum one already, so the request shouldn't be retried right away.
+    pub(crate) fn shrink(&mut self) -> bool {
+        if self.range <= MIN_BLOCK_RANGE {
+            return false;
+        }
+        self.range = cmp::max(self.range / 2, MIN_BLOCK_RANGE);
+        true
+    }
+
+    /// Doubles the range after a successful request.
+    pub(crate) fn grow(&mut self) { self.range = cmp::min(self.range * 2, MAX_BLOCK_RANGE); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_block_range() {
+        let mut block_range = AdaptiveBlockRange::default();
+        assert_eq!(block_range.range_end(1, 100_000), MAX_BLOCK_RANGE);
+        assert_eq!(block_range.range_end(1, 500), 500);
+        assert_eq!(block_range.range_end(u64::MAX - 1, u64::MAX), u64::MAX);
+
+        let mut shrinks = 0;
+        while block_range.shrink() {
+            shrinks += 1;
+        }
+        assert_eq!(shrinks, 7);
+        assert_eq!(block_range.range_end(1, 100_000), MIN_BLOCK_RANGE);
+        assert!(!block_range.shrink());
+
+        block_range.grow();
+        assert_eq!(block_range.range_end(1, 100_000), 2 * MIN_BLOCK_RANGE);
+        for _ in 0..10 {
+            block_range.grow();
+        }
+        assert_eq!(block_range, AdaptiveBlockRange::default());
+    }
+}
@@ -0,0 +1,208 @@
+//! Discovery of the ERC20 allowances granted by my addresses.
+//!
+//! The spenders are found by the `Approval` events emitted for my addresses by any contract,
+//! so the approvals of the tokens that aren't enabled are found too.
+//! The events don't reflect the allowances spent by `transferFrom`,
+//! so the current allowances should be requested from the token contracts.
+
+use super::*;
+use crate::hd_wallet::{HDAddressOps, HDWalletOps};
+use bip32::DerivationPath;
+#[cfg(test)] use mocktopus::macros::*;
+
+/// The `approve(spender, amount)` call made by `owner` to the `token_address` contract.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Erc20Approval {
+    pub token_address: Address,
+    pub owner: Address,
+    pub spender: Address,
+}
+
+impl Erc20Approval {
+    /// Returns `None` if the log isn't an ERC20 `Approval` event.
+    fn from_log(log: &Log) -> Option<Erc20Approval> {
+        // ERC721 `Approval` has the same signature, but the token id is indexed as the 4th topic.
+        if log.is_removed() || log.topics.len() != 3 {
+            return None;
+        }
+        Some(Erc20Approval {
+            token_address: log.address,
+            owner: H160::from(log.topics[1]),
+            spender: H160::from(log.topics[2]),
+        })
+    }
+}
+
+#[cfg_attr(test, mockable)]
+impl EthCoin {
+    /// Returns the unique approvals of the `Approval` events emitted for `owners` within `[from_block, to_block]`.
+    /// The nodes may reject a too wide range, see [`super::block_range::AdaptiveBlockRange`].
+    pub async fn find_erc20_approvals(
+        &self,
+        owners: &HashSet<Address>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Web3RpcResult<HashSet<Erc20Approval>> {
+        let approval_event = ERC20_CONTRACT.event("Approval")?;
+        let owner_topics: Vec<H256> = owners.iter().map(|owner| (*owner).into()).collect();
+        let filter = FilterBuilder::default()
+            .topics(Some(vec![approval_event.signature()]), Some(owner_topics), None, None)
+            .from_block(BlockNumber::Number(from_block.into()))
+            .to_block(BlockNumber::Number(to_block.into()))
+            .build();
+
+        let logs = self.logs(filter).await?;
+        Ok(logs.iter().filter_map(Erc20Approval::from_log).collect())
+    }
+
+    /// Returns the swap contracts that the tokens of this platform coin are approved to, with their names.
+    pub fn swap_contract_spenders(&self) -> Vec<(&'static str, Address)> {
+        let mut spenders = vec![("swap_contract", self.swap_contract_address)];
+        if let Some(fallback_swap_contract) = self.fallback_swap_contract {
+            spenders.push(("fallback_swap_contract", fallback_swap_contract));
+        }
+        if let Some(ref swap_v2_contracts) = self.swap_v2_contracts {
+            spenders.push(("maker_swap_v2_contract", swap_v2_contracts.maker_swap_v2_contract));
+            spenders.push(("taker_swap_v2_contract", swap_v2_contracts.taker_swap_v2_contract));
+        }
+        spenders
+    }
+
+    /// Requests the amount of `token_addr` tokens that `spender` is allowed to withdraw from `owner`.
+    pub async fn erc20_allowance(&self, token_addr: Address, owner: Address, spender: Address) -> Web3RpcResult<U256> {
+        let function = ERC20_CONTRACT.function("allowance")?;
+        let data = function.encode_input(&[Token::Address(owner), Token::Address(spender)])?;
+
+        let res = self
+            .call_request(owner, token_addr, None, Some(data.into()), BlockNumber::Latest)
+            .await?;
+        let decoded = function.decode_output(&res.0)?;
+
+        match decoded[0] {
+            Token::Uint(number) => Ok(number),
+            _ => {
+                let error = format!("Expected U256 as allowance result but got {:?}", decoded);
+                MmError::err(Web3RpcError::InvalidResponse(error))
+            },
+        }
+    }
+
+    /// Approves `spender` to withdraw up to `amount` of `token_addr` tokens from my address.
+    /// Setting the `amount` to zero revokes the approval.
+    pub async fn erc20_approve(
+        &self,
+        token_addr: Address,
+        spender: Address,
+        amount: U256,
+    ) -> Result<SignedEthTx, TransactionErr> {
+        let function = try_tx_s!(ERC20_CONTRACT.function("approve"));
+        let data = try_tx_s!(function.encode_input(&[Token::Address(spender), Token::Uint(amount)]));
+
+        let gas_limit = try_tx_s!(
+            self.estimate_gas_for_contract_call(token_addr, Bytes::from(data.clone()))
+                .await
+        );
+
+        self.sign_and_send_transaction(0.into(), Call(token_addr), data, gas_limit)
+            .compat()
+            .await
+    }
+
+    /// The same as [`EthCoin::erc20_approve`], but the approval is made by `owner`,
+    /// which may be any known address of the HD wallet and not only the enabled one.
+    pub async fn erc20_approve_from(
+        &self,
+        owner: Address,
+        token_addr: Address,
+        spender: Address,
+        amount: U256,
+    ) -> Result<SignedEthTx, TransactionErr> {
+        let key_pair = match self.priv_key_policy {
+            EthPrivKeyPolicy::Iguana(ref key_pair) => key_pair.clone(),
+            EthPrivKeyPolicy::HDWallet { .. } => {
+                let derivation_path = try_tx_s!(self.known_address_derivation_path(owner).await);
+                let raw_priv_key = try_tx_s!(self.priv_key_policy.hd_wallet_derived_priv_key_or_err(&derivation_path));
+                try_tx_s!(KeyPair::from_secret_slice(raw_priv_key.as_slice()))
+            },
+            // The external wallets sign with the enabled address only.
+            _ => {
+                if self.derivation_method.single_addr().await != Some(owner) {
+                    return TX_PLAIN_ERR!("{} is not the enabled address", owner.display_address());
+                }
+                return self.erc20_approve(token_addr, spender, amount).await;
+            },
+        };
+        if key_pair.address() != owner {
+            return TX_PLAIN_ERR!("{} is not my address", owner.display_address());
+        }
+
+        let function = try_tx_s!(ERC20_CONTRACT.function("approve"));
+        let data = try_tx_s!(function.encode_input(&[Token::Address(spender), Token::Uint(amount)]));
+        let gas_limit = try_tx_s!(
+            self.estimate_gas_for_contract_call_from(owner, token_addr, Bytes::from(data.clone()))
+                .await
+        );
+
+        sign_and_send_transaction_with_keypair(self, &key_pair, owner, 0.into(), Call(token_addr), data, gas_limit)
+            .await
+    }
+
+    /// Returns the derivation path of a known address of the HD wallet.
+    async fn known_address_derivation_path(&self, address: Address) -> Result<DerivationPath, String> {
+        let hd_wallet = try_s!(self.derivation_method.hd_wallet_or_err());
+        for (_, hd_account) in hd_wallet.get_accounts().await {
+            for chain in [Bip44Chain::External, Bip44Chain::Internal] {
+                let hd_addresses = try_s!(self.derive_known_addresses(&hd_account, chain).await);
+                if let Some(hd_address) = hd_addresses
+                    .into_iter()
+                    .find(|hd_address| hd_address.address() == address)
+                {
+                    return Ok(hd_address.derivation_path().clone());
+                }
+            }
+        }
+        ERR!("{} is not a known address of the HD wallet", address.display_address())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approval_log(topics: Vec<H256>, removed: Option<bool>) -> Log {
+        Log {
+            address: Address::repeat_byte(1),
+            topics,
+            removed,
+            ..Log::default()
+        }
+    }
+
+    #[test]
+    fn test_erc20_approval_from_log() {
+        let signature = ERC20_CONTRACT.event("Approval").unwrap().signature();
+        let owner = Address::repeat_byte(2);
+        let spender = Address::repeat_byte(3);
+
+        let log = approval_log(vec![signature, owner.into(), spender.into()], None);
+        assert_eq!(
+            Erc20Approval::from_log(&log),
+            Some(Erc20Approval {
+                token_address: Address::repeat_byte(1),
+                owner,
+                spender,
+            })
+        );
+
+        // ERC721 `Approval` of the token id 5.
+        let log = approval_log(
+            vec![signature, owner.into(), spender.into(), H256::from_low_u64_be(5)],
+            None,
+        );
+        assert_eq!(Erc20Approval::from_log(&log), None);
+
+        // The log of a reorged block.
+        let log = approval_log(vec![signature, owner.into(), spender.into()], Some(true));
+        assert_eq!(Erc20Approval::from_log(&log), None);
+    }
+}
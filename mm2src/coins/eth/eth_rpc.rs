@@ -1,12 +1,19 @@
 //! This module serves as an abstraction layer for Ethereum RPCs.
 //! Unlike the built-in functions in web3, this module dynamically
 //! rotates through all transports in case of failures.
+//! Transports are tried in the order of their health score (see `node_health`),
+//! and swap-critical reads can require the agreement of several nodes (`rpc_quorum`).
 
+use super::node_health::sort_by_health;
 use super::web3_transport::FeeHistoryResult;
-use super::{web3_transport::Web3Transport, EthCoin};
+use super::{web3_transport::Web3Transport, EthCoin, Web3Instance};
 use common::{custom_futures::timeout::FutureTimerExt, log::debug};
-use compatible_time::Duration;
+use compatible_time::{Duration, Instant};
+use futures::future::join_all;
+#[cfg(test)] use mocktopus::macros::*;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use web3::error::TransportError;
 use web3::types::{Address, Block, BlockId, BlockNumber, Bytes, CallRequest, FeeHistory, Filter, Log, Proof, SyncState,
                   Trace, TraceFilter, Transaction, TransactionId, TransactionReceipt, TransactionRequest, Work, H256,
                   H520, H64, U256, U64};
@@ -15,50 +22,236 @@ use web3::{helpers, Transport};
 pub(crate) const ETH_RPC_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl EthCoin {
+    /// Sends the request to a single node and records the outcome in the node's health.
+    async fn send_to_instance(
+        &self,
+        client: &Web3Instance,
+        method: &str,
+        params: Vec<jsonrpc_core::Value>,
+    ) -> Result<Value, web3::Error> {
+        let execute_fut = match client.web3.transport() {
+            Web3Transport::Http(http) => http.execute(method, params),
+            Web3Transport::Websocket(socket) => {
+                socket.maybe_spawn_connection_loop(self.clone());
+                socket.execute(method, params)
+            },
+            #[cfg(target_arch = "wasm32")]
+            Web3Transport::Metamask(metamask) => metamask.execute(method, params),
+        };
+
+        let started_at = Instant::now();
+        let result = match execute_fut.timeout(ETH_RPC_REQUEST_TIMEOUT).await {
+            Ok(result) => result,
+            Err(timeout_error) => Err(web3::Error::Transport(TransportError::Message(format!(
+                "Timeout exceed for '{method}' request. Error: {timeout_error}"
+            )))),
+        };
+
+        match &result {
+            Ok(response) => {
+                client.health.record_success(started_at.elapsed());
+                if method == "eth_blockNumber" {
+                    if let Ok(block) = serde_json::from_value::<U64>(response.clone()) {
+                        client.health.record_block(block.as_u64());
+                    }
+                }
+            },
+            Err(err) => {
+                debug!("Request on '{method}' failed. Error: {err}");
+                client.health.record_failure(err.to_string());
+
+                if let Web3Transport::Websocket(socket_transport) = client.web3.transport() {
+                    socket_transport.stop_connection_loop().await;
+                };
+            },
+        }
+
+        result
+    }
+
     async fn try_rpc_send(&self, method: &str, params: Vec<jsonrpc_core::Value>) -> Result<Value, web3::Error> {
         let mut clients = self.web3_instances.lock().await;
+        // Bring the healthiest client to the front of rpc_clients
+        sort_by_health(clients.as_mut_slice(), |client| &*client.health);
 
         let mut error = web3::Error::Unreachable;
-        for (i, client) in clients.clone().into_iter().enumerate() {
-            let execute_fut = match client.web3.transport() {
-                Web3Transport::Http(http) => http.execute(method, params.clone()),
-                Web3Transport::Websocket(socket) => {
-                    socket.maybe_spawn_connection_loop(self.clone());
-                    socket.execute(method, params.clone())
-                },
-                #[cfg(target_arch = "wasm32")]
-                Web3Transport::Metamask(metamask) => metamask.execute(method, params.clone()),
-            };
-
-            match execute_fut.timeout(ETH_RPC_REQUEST_TIMEOUT).await {
-                Ok(Ok(r)) => {
-                    // Bring the live client to the front of rpc_clients
-                    clients.rotate_left(i);
-                    return Ok(r);
-                },
-                Ok(Err(err)) => {
-                    debug!("Request on '{method}' failed. Error: {err}");
-                    error = err;
-
-                    if let Web3Transport::Websocket(socket_transport) = client.web3.transport() {
-                        socket_transport.stop_connection_loop().await;
-                    };
-                },
-                Err(timeout_error) => {
-                    debug!("Timeout exceed for '{method}' request. Error: {timeout_error}",);
-
-                    if let Web3Transport::Websocket(socket_transport) = client.web3.transport() {
-                        socket_transport.stop_connection_loop().await;
-                    };
-                },
-            };
+        for client in clients.iter() {
+            match self.send_to_instance(client, method, params.clone()).await {
+                Ok(r) => return Ok(r),
+                Err(err) => error = err,
+            }
         }
 
         Err(error)
     }
+
+    /// Sends the request to all nodes and returns the response that at least `rpc_quorum` of them agree on.
+    /// The responses are decoded and compared by the fields returned by `vote_key` only,
+    /// so that the fields irrelevant for the caller, which differ between node implementations,
+    /// don't prevent the agreement. The responses that can't be decoded are counted as failed.
+    /// Nodes that answered differently from the quorum are penalized in their health score.
+    /// Behaves like [`EthCoin::try_rpc_send`] if the quorum mode is off.
+    async fn try_rpc_send_quorum<T, K>(
+        &self,
+        method: &str,
+        params: Vec<jsonrpc_core::Value>,
+        vote_key: impl Fn(&T) -> K,
+    ) -> Result<T, web3::Error>
+    where
+        T: DeserializeOwned,
+        K: PartialEq,
+    {
+        let quorum = match self.rpc_quorum {
+            Some(quorum) => quorum,
+            None => {
+                return self
+                    .try_rpc_send(method, params)
+                    .await
+                    .and_then(|t| serde_json::from_value(t).map_err(Into::into))
+            },
+        };
+
+        let clients = self.web3_instances.lock().await.clone();
+        self.send_to_quorum(&clients, quorum, method, params, vote_key).await
+    }
+
+    async fn send_to_quorum<T, K>(
+        &self,
+        clients: &[Web3Instance],
+        quorum: usize,
+        method: &str,
+        params: Vec<jsonrpc_core::Value>,
+        vote_key: impl Fn(&T) -> K,
+    ) -> Result<T, web3::Error>
+    where
+        T: DeserializeOwned,
+        K: PartialEq,
+    {
+        let responses: Vec<Result<T, web3::Error>> = join_all(
+            clients
+                .iter()
+                .map(|client| self.send_to_instance(client, method, params.clone())),
+        )
+        .await
+        .into_iter()
+        .map(|response| response.and_then(|t| serde_json::from_value(t).map_err(Into::into)))
+        .collect();
+        let keys: Vec<Option<K>> = responses
+            .iter()
+            .map(|response| response.as_ref().ok().map(&vote_key))
+            .collect();
+
+        let agreed = match quorum_response_index(&keys, quorum) {
+            Some(agreed) => agreed,
+            None => {
+                let failed = keys.iter().filter(|key| key.is_none()).count();
+                return Err(web3::Error::Transport(TransportError::Message(format!(
+                    "'{method}' quorum of {quorum} is not reached: {failed}/{} responses failed, the rest disagreed",
+                    clients.len()
+                ))));
+            },
+        };
+
+        for (client, key) in clients.iter().zip(keys.iter()) {
+            if matches!(key, Some(key) if keys[agreed].as_ref() != Some(key)) {
+                client.health.record_disagreement(method);
+            }
+        }
+
+        responses
+            .into_iter()
+            .nth(agreed)
+            .unwrap_or(Err(web3::Error::Unreachable))
+    }
+
+    /// Returns the highest block that at least `quorum` of the `clients` have,
+    /// so that the state read at this block can be agreed on by the nodes at different heights.
+    async fn quorum_block_number(&self, clients: &[Web3Instance], quorum: usize) -> Result<U64, web3::Error> {
+        let responses = join_all(
+            clients
+                .iter()
+                .map(|client| self.send_to_instance(client, "eth_blockNumber", vec![])),
+        )
+        .await;
+        let mut blocks: Vec<U64> = responses
+            .into_iter()
+            .flatten()
+            .filter_map(|block| serde_json::from_value(block).ok())
+            .collect();
+        blocks.sort_unstable_by(|a, b| b.cmp(a));
+
+        blocks.get(quorum - 1).copied().ok_or_else(|| {
+            web3::Error::Transport(TransportError::Message(format!(
+                "Only {}/{} nodes returned the block number, the quorum is {quorum}",
+                blocks.len(),
+                clients.len()
+            )))
+        })
+    }
+
+    /// Asks every node for its latest block, so the block lag of each node is up to date,
+    /// and returns the nodes in the order they are going to be used.
+    pub(crate) async fn refresh_nodes_health(&self) -> Vec<Web3Instance> {
+        // The nodes are requested without holding the lock, so that other requests aren't blocked meanwhile.
+        let clients = self.web3_instances.lock().await.clone();
+        join_all(
+            clients
+                .iter()
+                .map(|client| self.send_to_instance(client, "eth_blockNumber", vec![])),
+        )
+        .await;
+
+        // The health is shared by the clones of the instances.
+        let mut clients = self.web3_instances.lock().await;
+        sort_by_health(clients.as_mut_slice(), |client| &*client.health);
+        clients.clone()
+    }
+}
+
+/// Returns the index of the first response whose vote key is shared by at least `quorum` responses.
+/// `None` keys are the failed responses.
+pub(super) fn quorum_response_index<K: PartialEq>(keys: &[Option<K>], quorum: usize) -> Option<usize> {
+    // The index of the first response with the key and the number of the responses with the key.
+    let mut votes: Vec<(usize, usize)> = Vec::new();
+    for (index, key) in keys.iter().enumerate() {
+        let Some(key) = key else { continue };
+        match votes.iter_mut().find(|(first, _)| keys[*first].as_ref() == Some(key)) {
+            Some((_, count)) => *count += 1,
+            None => votes.push((index, 1)),
+        }
+    }
+    votes
+        .into_iter()
+        .find(|(_, count)| *count >= quorum)
+        .map(|(index, _)| index)
+}
+
+/// The transaction fields that the quorum of nodes must agree on.
+/// The block number isn't voted on, as the request isn't pinned to a block,
+/// so the nodes at different heights may not have mined the transaction yet.
+pub(super) fn transaction_vote_key(
+    tx: &Option<Transaction>,
+) -> Option<(H256, Option<Address>, Option<Address>, U256, U256, Bytes)> {
+    tx.as_ref()
+        .map(|tx| (tx.hash, tx.from, tx.to, tx.nonce, tx.value, tx.input.clone()))
+}
+
+/// The receipt fields that the quorum of nodes must agree on.
+pub(super) fn receipt_vote_key(
+    receipt: &Option<TransactionReceipt>,
+) -> Option<(H256, Option<U64>, Option<U64>, Vec<(Address, Vec<H256>, Bytes)>)> {
+    receipt.as_ref().map(|receipt| {
+        let logs = receipt
+            .logs
+            .iter()
+            .map(|log| (log.address, log.topics.clone(), log.data.clone()))
+            .collect();
+        (receipt.transaction_hash, receipt.block_number, receipt.status, logs)
+    })
 }
 
 #[allow(dead_code)]
+#[cfg_attr(test, mockable)]
 impl EthCoin {
     /// Get list of available accounts.
     pub(crate) async fn accounts(&self) -> Result<Vec<Address>, web3::Error> {
@@ -84,6 +277,35 @@ impl EthCoin {
             .and_then(|t| serde_json::from_value(t).map_err(Into::into))
     }
 
+    /// Same as [`EthCoin::call`], but the result must be confirmed by `rpc_quorum` nodes.
+    /// The latest and pending states differ between the nodes at different heights,
+    /// so the call is pinned to the highest block that the quorum of nodes have.
+    pub(crate) async fn call_with_quorum(
+        &self,
+        req: CallRequest,
+        block: Option<BlockId>,
+    ) -> Result<Bytes, web3::Error> {
+        let quorum = match self.rpc_quorum {
+            Some(quorum) => quorum,
+            None => return self.call(req, block).await,
+        };
+
+        let clients = self.web3_instances.lock().await.clone();
+        let block = match block {
+            None | Some(BlockId::Number(BlockNumber::Latest | BlockNumber::Pending)) => {
+                BlockNumber::Number(self.quorum_block_number(&clients, quorum).await?).into()
+            },
+            Some(block) => block,
+        };
+        let req = helpers::serialize(&req);
+        let block = helpers::serialize(&block);
+
+        self.send_to_quorum(&clients, quorum, "eth_call", vec![req, block], |output: &Bytes| {
+            output.clone()
+        })
+        .await
+    }
+
     /// Get coinbase address
     pub(crate) async fn coinbase(&self) -> Result<Address, web3::Error> {
         self.try_rpc_send("eth_coinbase", vec![])
@@ -309,13 +531,20 @@ impl EthCoin {
         result.await.and_then(|t| serde_json::from_value(t).map_err(Into::into))
     }
 
-    /// Get transaction receipt
+    /// Get transaction by hash. The result must be confirmed by `rpc_quorum` nodes.
+    pub(crate) async fn transaction_with_quorum(&self, hash: H256) -> Result<Option<Transaction>, web3::Error> {
+        let hash = helpers::serialize(&hash);
+
+        self.try_rpc_send_quorum("eth_getTransactionByHash", vec![hash], transaction_vote_key)
+            .await
+    }
+
+    /// Get transaction receipt. The result must be confirmed by `rpc_quorum` nodes.
     pub(crate) async fn transaction_receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>, web3::Error> {
         let hash = helpers::serialize(&hash);
 
-        self.try_rpc_send("eth_getTransactionReceipt", vec![hash])
+        self.try_rpc_send_quorum("eth_getTransactionReceipt", vec![hash], receipt_vote_key)
             .await
-            .and_then(|t| serde_json::from_value(t).map_err(Into::into))
     }
 
     /// Get work package
@@ -385,6 +614,25 @@ impl EthCoin {
             .and_then(|t| serde_json::from_value(t).map_err(Into::into))
     }
 
+    /// Signs a message with the EIP-191 prefix.
+    /// Used for the MetaMask policy only since the RPC nodes don't hold our keys.
+    pub(crate) async fn personal_sign(&self, data: Bytes, address: Address) -> Result<H520, web3::Error> {
+        let data = helpers::serialize(&data);
+        let address = helpers::serialize(&address);
+        self.try_rpc_send("personal_sign", vec![data, address])
+            .await
+            .and_then(|t| serde_json::from_value(t).map_err(Into::into))
+    }
+
+    /// Signs EIP-712 typed data given as a JSON string.
+    /// Used for the MetaMask policy only since the RPC nodes don't hold our keys.
+    pub(crate) async fn sign_typed_data_v4(&self, address: Address, typed_data: String) -> Result<H520, web3::Error> {
+        let address = helpers::serialize(&address);
+        self.try_rpc_send("eth_signTypedData_v4", vec![address, Value::String(typed_data)])
+            .await
+            .and_then(|t| serde_json::from_value(t).map_err(Into::into))
+    }
+
     /// Submit hashrate of external miner
     pub(crate) async fn submit_hashrate(&self, rate: U256, id: H256) -> Result<bool, web3::Error> {
         let rate = helpers::serialize(&rate);
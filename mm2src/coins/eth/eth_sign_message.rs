@@ -0,0 +1,386 @@
+//! Signing and verification of arbitrary messages compatible with the `personal_sign` (EIP-191)
+//! and `eth_signTypedData_v4` (EIP-712) wallet methods.
+
+use super::{checksum_address, EthCoin, EthPrivKeyPolicy};
+use crate::rpc_command::sign_eth_message::{EthSignMessageError, EthSignMessageResult, SignEthMessageInProgressStatus,
+                                           SignEthMessageResponse, SignEthMessageTaskHandleShared,
+                                           VerifyEthMessageRequest, VerifyEthMessageResponse};
+use bitcrypto::keccak256;
+use crypto::hw_rpc_task::HwRpcTaskAwaitingStatus;
+use crypto::trezor::trezor_rpc_task::{TrezorRequestStatuses, TrezorRpcTaskProcessor};
+use crypto::{CryptoCtx, HwRpcError};
+use ethereum_types::{Address, H256, U256};
+use ethkey::{sign, Signature};
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_eth::address::address_from_pubkey_uncompressed;
+use mm2_eth::eip712::Eip712;
+use mm2_eth::eip712_encode::{typed_data_hashes, TypedDataHashes};
+use mm2_eth::recovery::recover_pubkey;
+use serde_json::Value as Json;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The data to sign, tagged with the name of the wallet method it's compatible with.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "method", content = "params")]
+pub enum EthSignPayload {
+    /// A 0x-prefixed hex string is signed as raw bytes, any other string as UTF-8 text.
+    #[serde(rename = "personal_sign")]
+    PersonalSign { message: String },
+    #[serde(rename = "eth_signTypedData_v4")]
+    SignTypedDataV4 { typed_data: Eip712<Json, Json> },
+}
+
+impl EthSignPayload {
+    fn personal_message_bytes(message: &str) -> Vec<u8> {
+        message
+            .strip_prefix("0x")
+            .and_then(|hex_str| hex::decode(hex_str).ok())
+            .unwrap_or_else(|| message.as_bytes().to_vec())
+    }
+
+    fn typed_data_hashes(typed_data: &Eip712<Json, Json>) -> EthSignMessageResult<TypedDataHashes> {
+        typed_data_hashes(typed_data.clone()).map_to_mm(|e| EthSignMessageError::InvalidRequest(e.to_string()))
+    }
+
+    /// Rejects the typed data whose `domain.chainId` differs from the coin's `chain_id`,
+    /// as its signature would be valid on that other chain.
+    fn check_chain_id(&self, chain_id: u64) -> EthSignMessageResult<()> {
+        let typed_data = match self {
+            EthSignPayload::PersonalSign { .. } => return Ok(()),
+            EthSignPayload::SignTypedDataV4 { typed_data } => typed_data,
+        };
+        let domain_chain_id = match typed_data.domain.get("chainId") {
+            None | Some(Json::Null) => return Ok(()),
+            Some(Json::Number(number)) => number.as_u64().map(U256::from),
+            Some(Json::String(string)) => match string.strip_prefix("0x") {
+                Some(hex_str) => U256::from_str(hex_str).ok(),
+                None => U256::from_dec_str(string).ok(),
+            },
+            Some(_) => None,
+        };
+        match domain_chain_id {
+            Some(domain_chain_id) if domain_chain_id == U256::from(chain_id) => Ok(()),
+            Some(domain_chain_id) => MmError::err(EthSignMessageError::InvalidRequest(format!(
+                "Typed data domain chainId {} doesn't match the coin chain id {}",
+                domain_chain_id, chain_id
+            ))),
+            None => MmError::err(EthSignMessageError::InvalidRequest(format!(
+                "Invalid typed data domain chainId {}",
+                typed_data.domain["chainId"]
+            ))),
+        }
+    }
+
+    /// Returns the hash that is actually signed.
+    pub fn hash(&self) -> EthSignMessageResult<H256> {
+        match self {
+            EthSignPayload::PersonalSign { message } => {
+                Ok(personal_message_hash(&Self::personal_message_bytes(message)))
+            },
+            EthSignPayload::SignTypedDataV4 { typed_data } => Ok(Self::typed_data_hashes(typed_data)?.signing_hash()),
+        }
+    }
+}
+
+/// `keccak256("\x19Ethereum Signed Message:\n" ‖ len(message) ‖ message)`
+fn personal_message_hash(message: &[u8]) -> H256 {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    H256::from(keccak256(&prefixed).take())
+}
+
+/// Formats the signature as `personal_sign` and `eth_signTypedData_v4` do, i.e. with `v` being 27 or 28.
+fn signature_to_rpc_string(signature: &Signature) -> String {
+    let mut bytes: [u8; 65] = **signature;
+    bytes[64] += 27;
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Parses a 65-byte hex signature with `v` being either 0/1 or 27/28.
+fn signature_from_rpc_str(signature: &str) -> EthSignMessageResult<Signature> {
+    let signature = Signature::from_str(signature.strip_prefix("0x").unwrap_or(signature))
+        .map_to_mm(|e| EthSignMessageError::InvalidSignature(e.to_string()))?;
+    Ok(normalize_signature_v(signature))
+}
+
+/// Converts `v` from 27/28 to 0/1 as `ethkey` expects.
+fn normalize_signature_v(mut signature: Signature) -> Signature {
+    if signature[64] >= 27 {
+        signature[64] -= 27;
+    }
+    signature
+}
+
+fn recover_signer(hash: H256, signature: Signature) -> EthSignMessageResult<Address> {
+    let pubkey = recover_pubkey(hash, signature).mm_err(|e| EthSignMessageError::InvalidSignature(e.to_string()))?;
+    Ok(address_from_pubkey_uncompressed(pubkey))
+}
+
+fn sign_eth_message_response(hash: H256, signature: Signature) -> EthSignMessageResult<SignEthMessageResponse> {
+    let signer = recover_signer(hash, signature)?;
+    Ok(SignEthMessageResponse {
+        signature: signature_to_rpc_string(&signature),
+        signer: checksum_address(&format!("{:#02x}", signer)),
+        hash: format!("{:#02x}", hash),
+    })
+}
+
+/// Signs the payload with the Iguana, HD or MetaMask key policy.
+pub async fn sign_eth_message(
+    coin: &EthCoin,
+    payload: &EthSignPayload,
+) -> EthSignMessageResult<SignEthMessageResponse> {
+    payload.check_chain_id(coin.chain_id)?;
+    let hash = payload.hash()?;
+    let signature = match coin.priv_key_policy {
+        EthPrivKeyPolicy::Iguana(ref key_pair)
+        | EthPrivKeyPolicy::HDWallet {
+            activated_key: ref key_pair,
+            ..
+        } => sign(key_pair.secret(), &hash)?,
+        EthPrivKeyPolicy::Trezor => {
+            return MmError::err(EthSignMessageError::KeyPolicyNotSupported {
+                policy: "Trezor".to_owned(),
+                reason: "use 'task::sign_eth_message::init' instead".to_owned(),
+            })
+        },
+        #[cfg(target_arch = "wasm32")]
+        EthPrivKeyPolicy::Metamask(_) => sign_with_metamask(coin, payload).await?,
+    };
+    sign_eth_message_response(hash, signature)
+}
+
+/// Signs the payload with any key policy, including Trezor that requires user interaction.
+pub async fn sign_eth_message_with_task(
+    ctx: &MmArc,
+    coin: &EthCoin,
+    payload: &EthSignPayload,
+    task_handle: SignEthMessageTaskHandleShared,
+) -> EthSignMessageResult<SignEthMessageResponse> {
+    if !matches!(coin.priv_key_policy, EthPrivKeyPolicy::Trezor) {
+        return sign_eth_message(coin, payload).await;
+    }
+
+    payload.check_chain_id(coin.chain_id)?;
+    let hash = payload.hash()?;
+    let derivation_path = coin
+        .derivation_method
+        .hd_wallet_or_err()?
+        .get_enabled_address()
+        .await
+        .or_mm_err(|| EthSignMessageError::Internal("no enabled address".to_owned()))?
+        .derivation_path;
+
+    let trezor_statuses = TrezorRequestStatuses {
+        on_button_request: SignEthMessageInProgressStatus::FollowHwDeviceInstructions,
+        on_pin_request: HwRpcTaskAwaitingStatus::EnterTrezorPin,
+        on_passphrase_request: HwRpcTaskAwaitingStatus::EnterTrezorPassphrase,
+        on_ready: SignEthMessageInProgressStatus::FollowHwDeviceInstructions,
+    };
+    let sign_processor = Arc::new(TrezorRpcTaskProcessor::new(task_handle.clone(), trezor_statuses));
+    let crypto_ctx = CryptoCtx::from_ctx(ctx)?;
+    let hw_ctx = crypto_ctx
+        .hw_ctx()
+        .or_mm_err(|| EthSignMessageError::HwError(HwRpcError::NoTrezorDeviceAvailable))?;
+    let mut trezor_session = hw_ctx.trezor(sign_processor).await?;
+    task_handle.update_in_progress_status(SignEthMessageInProgressStatus::WaitingForUserToConfirmSigning)?;
+
+    let signature = match payload {
+        // The device adds the EIP-191 prefix itself.
+        EthSignPayload::PersonalSign { message } => {
+            let message = EthSignPayload::personal_message_bytes(message);
+            trezor_session
+                .sign_eth_message(&derivation_path, message, coin.chain_id)
+                .await?
+        },
+        // The device can't parse arbitrary typed data, so only the hashes are signed.
+        EthSignPayload::SignTypedDataV4 { typed_data } => {
+            let hashes = EthSignPayload::typed_data_hashes(typed_data)?;
+            trezor_session
+                .sign_eth_typed_hash(
+                    &derivation_path,
+                    hashes.domain_separator,
+                    hashes.message_hash,
+                    coin.chain_id,
+                )
+                .await?
+        },
+    };
+
+    task_handle.update_in_progress_status(SignEthMessageInProgressStatus::Finishing)?;
+    sign_eth_message_response(hash, signature)
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn sign_with_metamask(coin: &EthCoin, payload: &EthSignPayload) -> EthSignMessageResult<Signature> {
+    let my_address = coin.derivation_method.single_addr_or_err().await?;
+    let signature = match payload {
+        EthSignPayload::PersonalSign { message } => {
+            let message = EthSignPayload::personal_message_bytes(message);
+            coin.personal_sign(message.into(), my_address).await?
+        },
+        EthSignPayload::SignTypedDataV4 { typed_data } => {
+            let typed_data =
+                serde_json::to_string(typed_data).map_to_mm(|e| EthSignMessageError::InvalidRequest(e.to_string()))?;
+            coin.sign_typed_data_v4(my_address, typed_data).await?
+        },
+    };
+    Ok(normalize_signature_v(Signature::from(signature.0)))
+}
+
+/// Recovers the signer of the payload signature and compares it with the expected address if it's specified.
+pub fn verify_eth_message(req: VerifyEthMessageRequest) -> EthSignMessageResult<VerifyEthMessageResponse> {
+    let hash = req.payload.hash()?;
+    let signature = signature_from_rpc_str(&req.signature)?;
+    let signer = recover_signer(hash, signature)?;
+
+    let is_valid = match req.address {
+        Some(address) => {
+            let address = Address::from_str(address.strip_prefix("0x").unwrap_or(&address))
+                .map_to_mm(|e| EthSignMessageError::InvalidRequest(format!("Invalid address: {e}")))?;
+            Some(address == signer)
+        },
+        None => None,
+    };
+    Ok(VerifyEthMessageResponse {
+        signer: checksum_address(&format!("{:#02x}", signer)),
+        is_valid,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRET: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    fn sign_payload(payload: &EthSignPayload) -> SignEthMessageResponse {
+        let secret = ethkey::Secret::from_str(SECRET).unwrap();
+        let hash = payload.hash().unwrap();
+        let signature = sign(&secret, &hash).unwrap();
+        sign_eth_message_response(hash, signature).unwrap()
+    }
+
+    #[test]
+    fn test_personal_message_hash() {
+        // keccak256("\x19Ethereum Signed Message:\n11Hello World")
+        let expected = "0xa1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2";
+        let payload = EthSignPayload::PersonalSign {
+            message: "Hello World".to_owned(),
+        };
+        assert_eq!(format!("{:#02x}", payload.hash().unwrap()), expected);
+
+        let payload = EthSignPayload::PersonalSign {
+            message: format!("0x{}", hex::encode("Hello World")),
+        };
+        assert_eq!(format!("{:#02x}", payload.hash().unwrap()), expected);
+    }
+
+    #[test]
+    fn test_sign_and_verify_personal_message() {
+        let payload = EthSignPayload::PersonalSign {
+            message: "Some data".to_owned(),
+        };
+        let signed = sign_payload(&payload);
+        assert_eq!(signed.signer, "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+        assert_eq!(
+            signed.signature,
+            "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c"
+        );
+
+        let verified = verify_eth_message(VerifyEthMessageRequest {
+            payload,
+            signature: signed.signature,
+            address: Some("0x2c7536e3605d9c16a7a3d7b1898e529396a65c23".to_owned()),
+        })
+        .unwrap();
+        assert_eq!(verified.signer, signed.signer);
+        assert_eq!(verified.is_valid, Some(true));
+    }
+
+    /// The typed data example of EIP-712.
+    fn mail_typed_data(chain_id: Json) -> EthSignPayload {
+        let req = json!({
+            "method": "eth_signTypedData_v4",
+            "params": {
+                "typed_data": {
+                    "primaryType": "Mail",
+                    "domain": {
+                        "name": "Ether Mail",
+                        "version": "1",
+                        "chainId": chain_id,
+                        "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+                    },
+                    "message": {
+                        "from": { "name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826" },
+                        "to": { "name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB" },
+                        "contents": "Hello, Bob!"
+                    },
+                    "types": {
+                        "EIP712Domain": [
+                            { "name": "name", "type": "string" },
+                            { "name": "version", "type": "string" },
+                            { "name": "chainId", "type": "uint256" },
+                            { "name": "verifyingContract", "type": "address" }
+                        ],
+                        "Person": [
+                            { "name": "name", "type": "string" },
+                            { "name": "wallet", "type": "address" }
+                        ],
+                        "Mail": [
+                            { "name": "from", "type": "Person" },
+                            { "name": "to", "type": "Person" },
+                            { "name": "contents", "type": "string" }
+                        ]
+                    }
+                }
+            }
+        });
+        serde_json::from_value(req).unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify_typed_data() {
+        let payload = mail_typed_data(json!(1));
+        let signed = sign_payload(&payload);
+        assert_eq!(
+            signed.hash,
+            "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+        );
+
+        let verified = verify_eth_message(VerifyEthMessageRequest {
+            payload,
+            signature: signed.signature,
+            address: Some("0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB".to_owned()),
+        })
+        .unwrap();
+        assert_eq!(verified.signer, "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23");
+        assert_eq!(verified.is_valid, Some(false));
+    }
+
+    #[test]
+    fn test_typed_data_chain_id() {
+        mail_typed_data(json!(1)).check_chain_id(1).unwrap();
+        mail_typed_data(json!("0x1")).check_chain_id(1).unwrap();
+        mail_typed_data(json!("1")).check_chain_id(1).unwrap();
+        mail_typed_data(Json::Null).check_chain_id(1).unwrap();
+
+        let error = mail_typed_data(json!(1)).check_chain_id(56).unwrap_err().into_inner();
+        assert!(matches!(error, EthSignMessageError::InvalidRequest(_)));
+        let error = mail_typed_data(json!("0x38"))
+            .check_chain_id(1)
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(error, EthSignMessageError::InvalidRequest(_)));
+        let error = mail_typed_data(json!(true)).check_chain_id(1).unwrap_err().into_inner();
+        assert!(matches!(error, EthSignMessageError::InvalidRequest(_)));
+
+        let payload = EthSignPayload::PersonalSign {
+            message: "Some data".to_owned(),
+        };
+        payload.check_chain_id(56).unwrap();
+    }
+}
@@ -14,7 +14,7 @@ use ethkey::public_to_address;
 use futures::compat::Future01CompatExt;
 use mm2_err_handle::prelude::{MapToMmResult, MmError, MmResult};
 use std::convert::TryInto;
-use web3::types::{BlockNumber, TransactionId};
+use web3::types::BlockNumber;
 
 const ETH_TAKER_PAYMENT: &str = "ethTakerPayment";
 const ERC20_TAKER_PAYMENT: &str = "erc20TakerPayment";
@@ -28,7 +28,7 @@ const TAKER_PAYMENT_APPROVE: &str = "takerPaymentApprove";
 ///         uint32 paymentLockTime;
 ///         TakerPaymentState state;
 ///     }
-const TAKER_PAYMENT_STATE_INDEX: usize = 3;
+pub(super) const TAKER_PAYMENT_STATE_INDEX: usize = 3;
 
 struct TakerFundingArgs<'a> {
     dex_fee: U256,
@@ -158,7 +158,7 @@ impl EthCoin {
         validate_amount(&args.trading_amount).map_err(ValidateSwapV2TxError::Internal)?;
         let swap_id = self.etomic_swap_id_v2(args.payment_time_lock, args.maker_secret_hash);
 
-        let tx_from_rpc = self.transaction(TransactionId::Hash(args.funding_tx.tx_hash())).await?;
+        let tx_from_rpc = self.transaction_with_quorum(args.funding_tx.tx_hash()).await?;
         let tx_from_rpc = tx_from_rpc.as_ref().ok_or_else(|| {
             ValidateSwapV2TxError::TxDoesNotExist(format!(
                 "Didn't find provided tx {:?} on ETH node",
@@ -703,7 +703,7 @@ impl EthCoin {
     }
 
     /// Retrieves the payment status from a given smart contract address based on the swap ID and state type.
-    async fn payment_status_v2(
+    pub(super) async fn payment_status_v2(
         &self,
         swap_address: Address,
         swap_id: Token,
@@ -715,13 +715,7 @@ impl EthCoin {
         let function = contract_abi.function(payment_type.as_str())?;
         let data = function.encode_input(&[swap_id])?;
         let bytes = self
-            .call_request(
-                self.my_addr().await,
-                swap_address,
-                None,
-                Some(data.into()),
-                block_number,
-            )
+            .call_request_with_quorum(self.my_addr().await, swap_address, Some(data.into()), block_number)
             .await?;
         let decoded_tokens = function.decode_output(&bytes.0)?;
 
@@ -742,7 +736,7 @@ impl EthCoin {
 }
 
 #[derive(Debug, Display, EnumFromStringify)]
-enum PaymentStatusErr {
+pub(super) enum PaymentStatusErr {
     #[from_stringify("ethabi::Error")]
     #[display(fmt = "ABI error: {}", _0)]
     ABIError(String),
@@ -0,0 +1,640 @@
+//! Swap v2 watchers support of EVM-based coins.
+//!
+//! The swap v2 contracts derive the refunding or spending side from `msg.sender`, so a watcher calls the dedicated
+//! `*Watcher` contract methods instead. They take the HTLC parameters of the side the watcher acts on behalf of
+//! and its signature authorizing the watcher to pay `rewardAmount` of the payment to `msg.sender`,
+//! see [`watcher_v2_authorization_hash`].
+//!
+//! The preimage a swap side sends to the watchers is a transaction calling such method, signed by the swap side
+//! but never broadcast by itself: the watcher sends the call data from its own address.
+//! The maker payment spend preimage has zero `makerSecret`, the watcher inserts it once the maker reveals it.
+
+use super::eth_taker_swap_v2::TAKER_PAYMENT_STATE_INDEX;
+use super::{check_decoded_length, EthPaymentType, PaymentMethod, ZERO_VALUE};
+use crate::coin_errors::{ValidatePaymentError, ValidatePaymentResult};
+use crate::eth::{decode_contract_call, signed_tx_from_web3_tx, wei_from_big_decimal, EthCoin, EthCoinType,
+                 MakerPaymentStateV2, SignedEthTx, TakerPaymentStateV2, ETH_DECIMALS, MAKER_SWAP_V2, TAKER_SWAP_V2};
+use crate::{MakerPaymentSpendForWatcherArgs, MarketCoinOps, ParseCoinAssocTypes, RefundPaymentArgs, TransactionErr};
+use bitcrypto::keccak256;
+use common::now_sec;
+use ethabi::{Contract, Function, Token};
+use ethcore_transaction::{Action, TransactionWrapperBuilder as UnSignedEthTxBuilder, TxType,
+                          UnverifiedTransactionWrapper};
+use ethereum_types::{Address, H256, U256};
+use ethkey::{sign, verify_address, Signature};
+use futures::compat::Future01CompatExt;
+use mm2_err_handle::prelude::{MapToMmResult, MmError};
+use web3::types::{BlockNumber, TransactionId};
+
+const REFUND_TAKER_PAYMENT_TIMELOCK_WATCHER: &str = "refundTakerPaymentTimelockWatcher";
+const REFUND_MAKER_PAYMENT_TIMELOCK_WATCHER: &str = "refundMakerPaymentTimelockWatcher";
+const SPEND_MAKER_PAYMENT_WATCHER: &str = "spendMakerPaymentWatcher";
+
+/// The reward is calculated for the watcher transaction to be mined within this period.
+const WATCHER_REWARD_CALC_TIMEOUT_SEC: u64 = 60;
+
+/// state index for `MakerPayment` structure from `EtomicSwapMakerV2.sol`
+///
+///     struct MakerPayment {
+///         bytes20 paymentHash;
+///         uint32 paymentLockTime;
+///         MakerPaymentState state;
+///     }
+const MAKER_PAYMENT_STATE_INDEX: usize = 2;
+
+/// Swap v2 HTLC parameters decoded from the call data of the payment transaction:
+/// `ethTakerPayment`, `erc20TakerPayment` or `takerPaymentApprove` for taker payments,
+/// `ethMakerPayment` or `erc20MakerPayment` for maker payments.
+struct WatcherV2Payment {
+    payment_type: EthPaymentType,
+    swap_contract: Address,
+    id: Vec<u8>,
+    amount: U256,
+    /// Is zero for maker payments.
+    dex_fee: U256,
+    maker: Address,
+    taker: Address,
+    taker_secret_hash: Vec<u8>,
+    maker_secret_hash: Vec<u8>,
+    token_address: Address,
+}
+
+impl WatcherV2Payment {
+    fn parse(coin: &EthCoin, payment: &SignedEthTx) -> Result<WatcherV2Payment, String> {
+        let contracts = try_s!(coin
+            .swap_v2_contracts
+            .ok_or("Expected swap_v2_contracts to be Some, but found None"));
+        let swap_contract = match payment.unsigned().action() {
+            Action::Call(address) => *address,
+            Action::Create => return ERR!("Payment {:02x} is not a contract call", payment.tx_hash()),
+        };
+        let data = payment.unsigned().data();
+        if data.len() < 4 {
+            return ERR!("Payment {:02x} has no contract call data", payment.tx_hash());
+        }
+
+        if swap_contract == contracts.taker_swap_v2_contract {
+            let function = try_s!(find_function(&TAKER_SWAP_V2, &data[..4], &[
+                "ethTakerPayment",
+                "erc20TakerPayment",
+                "takerPaymentApprove"
+            ]));
+            let decoded = try_s!(decode_contract_call(function, data));
+            let (amount, dex_fee, maker, taker_secret_hash, maker_secret_hash, token_address) =
+                match function.name.as_str() {
+                    "ethTakerPayment" => {
+                        try_s!(check_decoded_length(&decoded, 7));
+                        let dex_fee = try_s!(uint_token(&decoded[1]));
+                        let amount = try_s!(payment
+                            .unsigned()
+                            .value()
+                            .checked_sub(dex_fee)
+                            .ok_or("Underflow occurred while calculating amount"));
+                        (
+                            amount,
+                            dex_fee,
+                            &decoded[2],
+                            &decoded[3],
+                            &decoded[4],
+                            Address::default(),
+                        )
+                    },
+                    "erc20TakerPayment" => {
+                        try_s!(check_decoded_length(&decoded, 9));
+                        let token_address = try_s!(address_token(&decoded[3]));
+                        let (amount, dex_fee) = (try_s!(uint_token(&decoded[1])), try_s!(uint_token(&decoded[2])));
+                        (amount, dex_fee, &decoded[4], &decoded[5], &decoded[6], token_address)
+                    },
+                    _ => {
+                        try_s!(check_decoded_length(&decoded, 7));
+                        let token_address = try_s!(address_token(&decoded[6]));
+                        let (amount, dex_fee) = (try_s!(uint_token(&decoded[1])), try_s!(uint_token(&decoded[2])));
+                        (amount, dex_fee, &decoded[3], &decoded[4], &decoded[5], token_address)
+                    },
+                };
+            return Ok(WatcherV2Payment {
+                payment_type: EthPaymentType::TakerPayments,
+                swap_contract,
+                id: try_s!(fixed_bytes_token(&decoded[0])),
+                amount,
+                dex_fee,
+                maker: try_s!(address_token(maker)),
+                taker: payment.sender(),
+                taker_secret_hash: try_s!(fixed_bytes_token(taker_secret_hash)),
+                maker_secret_hash: try_s!(fixed_bytes_token(maker_secret_hash)),
+                token_address,
+            });
+        }
+
+        if swap_contract == contracts.maker_swap_v2_contract {
+            let function = try_s!(find_function(&MAKER_SWAP_V2, &data[..4], &[
+                "ethMakerPayment",
+                "erc20MakerPayment"
+            ]));
+            let decoded = try_s!(decode_contract_call(function, data));
+            let (amount, taker, taker_secret_hash, maker_secret_hash, token_address) = match function.name.as_str() {
+                "ethMakerPayment" => {
+                    try_s!(check_decoded_length(&decoded, 5));
+                    let amount = payment.unsigned().value();
+                    (amount, &decoded[1], &decoded[2], &decoded[3], Address::default())
+                },
+                _ => {
+                    try_s!(check_decoded_length(&decoded, 7));
+                    let token_address = try_s!(address_token(&decoded[2]));
+                    let amount = try_s!(uint_token(&decoded[1]));
+                    (amount, &decoded[3], &decoded[4], &decoded[5], token_address)
+                },
+            };
+            return Ok(WatcherV2Payment {
+                payment_type: EthPaymentType::MakerPayments,
+                swap_contract,
+                id: try_s!(fixed_bytes_token(&decoded[0])),
+                amount,
+                dex_fee: U256::zero(),
+                maker: payment.sender(),
+                taker: try_s!(address_token(taker)),
+                taker_secret_hash: try_s!(fixed_bytes_token(taker_secret_hash)),
+                maker_secret_hash: try_s!(fixed_bytes_token(maker_secret_hash)),
+                token_address,
+            });
+        }
+
+        ERR!(
+            "Payment {:02x} is sent to unexpected contract {:?}",
+            payment.tx_hash(),
+            swap_contract
+        )
+    }
+
+    fn abi_contract(&self) -> &'static Contract {
+        match self.payment_type {
+            EthPaymentType::MakerPayments => &MAKER_SWAP_V2,
+            EthPaymentType::TakerPayments => &TAKER_SWAP_V2,
+        }
+    }
+
+    /// The watcher method of the timelock refund (`is_refund`) or the maker payment spend.
+    fn watcher_function(&self, is_refund: bool) -> Result<&'static Function, String> {
+        let name = match (self.payment_type, is_refund) {
+            (EthPaymentType::TakerPayments, true) => REFUND_TAKER_PAYMENT_TIMELOCK_WATCHER,
+            (EthPaymentType::TakerPayments, false) => return ERR!("Taker payment can't be spent by a watcher"),
+            (EthPaymentType::MakerPayments, true) => REFUND_MAKER_PAYMENT_TIMELOCK_WATCHER,
+            (EthPaymentType::MakerPayments, false) => SPEND_MAKER_PAYMENT_WATCHER,
+        };
+        Ok(try_s!(self.abi_contract().function(name)))
+    }
+
+    /// The address whose signature authorizes the watcher to refund (`is_refund`) or spend the payment.
+    fn authorizer(&self, is_refund: bool) -> Address {
+        match (self.payment_type, is_refund) {
+            (EthPaymentType::MakerPayments, true) => self.maker,
+            _ => self.taker,
+        }
+    }
+
+    /// Encodes the watcher method call arguments preceding `rewardAmount` and `signature`.
+    /// The `makerSecret` of the maker payment spend is zero until the watcher inserts it.
+    fn watcher_call_tokens(&self, is_refund: bool) -> Vec<Token> {
+        let id = Token::FixedBytes(self.id.clone());
+        let amount = Token::Uint(self.amount);
+        let taker_secret_hash = Token::FixedBytes(self.taker_secret_hash.clone());
+        let token_address = Token::Address(self.token_address);
+        match self.payment_type {
+            EthPaymentType::TakerPayments => vec![
+                id,
+                amount,
+                Token::Uint(self.dex_fee),
+                Token::Address(self.taker),
+                Token::Address(self.maker),
+                taker_secret_hash,
+                Token::FixedBytes(self.maker_secret_hash.clone()),
+                token_address,
+            ],
+            EthPaymentType::MakerPayments => vec![
+                id,
+                amount,
+                Token::Address(self.maker),
+                Token::Address(self.taker),
+                taker_secret_hash,
+                if is_refund {
+                    Token::FixedBytes(self.maker_secret_hash.clone())
+                } else {
+                    Token::FixedBytes(vec![0; 32])
+                },
+                token_address,
+            ],
+        }
+    }
+}
+
+/// Watcher method call decoded from a preimage, see [`EthCoin::sign_watcher_v2_preimage`].
+struct WatcherV2Preimage {
+    function: &'static Function,
+    tokens: Vec<Token>,
+    is_refund: bool,
+    reward_amount: U256,
+    signature: Signature,
+}
+
+impl WatcherV2Preimage {
+    /// Parses the `preimage` and checks that it calls the watcher method with the HTLC parameters of the `payment`.
+    fn parse(payment: &WatcherV2Payment, preimage: &[u8]) -> Result<WatcherV2Preimage, String> {
+        let preimage: UnverifiedTransactionWrapper = try_s!(rlp::decode(preimage));
+        let preimage = try_s!(SignedEthTx::new(preimage));
+        if preimage.unsigned().action() != &Action::Call(payment.swap_contract) {
+            return ERR!(
+                "Preimage must call the payment swap contract {:?}",
+                payment.swap_contract
+            );
+        }
+        let data = preimage.unsigned().data();
+        let refund_function = try_s!(payment.watcher_function(true));
+        let is_refund = data.starts_with(&refund_function.short_signature());
+        let function = try_s!(payment.watcher_function(is_refund));
+        let tokens = try_s!(decode_contract_call(function, data));
+
+        let expected_tokens = payment.watcher_call_tokens(is_refund);
+        try_s!(check_decoded_length(&tokens, expected_tokens.len() + 2));
+        for (index, expected) in expected_tokens.iter().enumerate() {
+            // The maker secret is inserted by the watcher.
+            let is_maker_secret = !is_refund && index == 5;
+            if !is_maker_secret && &tokens[index] != expected {
+                return ERR!(
+                    "Preimage `{}` argument {:?} is invalid, expected {:?}",
+                    function.inputs[index].name,
+                    tokens[index],
+                    expected
+                );
+            }
+        }
+
+        let reward_amount = try_s!(uint_token(&tokens[expected_tokens.len()]));
+        if reward_amount >= payment.amount {
+            return ERR!(
+                "Preimage reward {} is not less than the payment amount {}",
+                reward_amount,
+                payment.amount
+            );
+        }
+        let signature = match &tokens[expected_tokens.len() + 1] {
+            Token::Bytes(bytes) if bytes.len() == 65 => {
+                let mut signature = [0; 65];
+                signature.copy_from_slice(bytes);
+                // The contracts expect `v` being 27 or 28, `ethkey` expects 0 or 1.
+                signature[64] = signature[64].saturating_sub(27);
+                Signature::from(signature)
+            },
+            token => return ERR!("Invalid preimage signature {:?}", token),
+        };
+
+        Ok(WatcherV2Preimage {
+            function,
+            tokens,
+            is_refund,
+            reward_amount,
+            signature,
+        })
+    }
+
+    fn verify_signature(&self, payment: &WatcherV2Payment, chain_id: u64) -> Result<(), String> {
+        let hash = watcher_v2_authorization_hash(
+            payment.swap_contract,
+            chain_id,
+            self.function.short_signature(),
+            &payment.id,
+            self.reward_amount,
+        );
+        let authorizer = payment.authorizer(self.is_refund);
+        if !try_s!(verify_address(&authorizer, &self.signature, &hash)) {
+            return ERR!("Preimage is not signed by {:?}", authorizer);
+        }
+        Ok(())
+    }
+}
+
+/// The hash the HTLC owner signs to authorize a watcher to call the `selector` swap contract method:
+/// `keccak256(swapContract ++ chainId ++ selector ++ id ++ rewardAmount)`,
+/// with `chainId` and `rewardAmount` encoded as `uint256`.
+/// The selector prevents using the refund authorization to spend the payment and vice versa.
+fn watcher_v2_authorization_hash(
+    swap_contract: Address,
+    chain_id: u64,
+    selector: [u8; 4],
+    id: &[u8],
+    reward_amount: U256,
+) -> H256 {
+    let mut chain_id_bytes = [0; 32];
+    U256::from(chain_id).to_big_endian(&mut chain_id_bytes);
+    let mut reward_amount_bytes = [0; 32];
+    reward_amount.to_big_endian(&mut reward_amount_bytes);
+
+    let mut message = Vec::with_capacity(20 + 32 + 4 + 32 + 32);
+    message.extend_from_slice(&swap_contract.0);
+    message.extend_from_slice(&chain_id_bytes);
+    message.extend_from_slice(&selector);
+    message.extend_from_slice(id);
+    message.extend_from_slice(&reward_amount_bytes);
+    H256::from(keccak256(&message).take())
+}
+
+fn find_function<'a>(contract: &'a Contract, selector: &[u8], names: &[&str]) -> Result<&'a Function, String> {
+    for name in names {
+        let function = try_s!(contract.function(name));
+        if function.short_signature() == selector {
+            return Ok(function);
+        }
+    }
+    ERR!(
+        "Unexpected contract call signature {:?}, expected one of {:?}",
+        selector,
+        names
+    )
+}
+
+fn fixed_bytes_token(token: &Token) -> Result<Vec<u8>, String> {
+    token
+        .clone()
+        .into_fixed_bytes()
+        .ok_or_else(|| ERRL!("Expected fixed bytes, got {:?}", token))
+}
+
+fn uint_token(token: &Token) -> Result<U256, String> {
+    token
+        .clone()
+        .into_uint()
+        .ok_or_else(|| ERRL!("Expected uint, got {:?}", token))
+}
+
+fn address_token(token: &Token) -> Result<Address, String> {
+    token
+        .clone()
+        .into_address()
+        .ok_or_else(|| ERRL!("Expected address, got {:?}", token))
+}
+
+impl EthCoin {
+    /// Signs the timelock refund of taker funding, taker payment or maker payment for watchers.
+    /// All the HTLC parameters are decoded from the payment transaction.
+    pub(crate) async fn sign_refund_for_watcher_v2_impl(
+        &self,
+        args: RefundPaymentArgs<'_>,
+    ) -> Result<SignedEthTx, TransactionErr> {
+        let payment: UnverifiedTransactionWrapper = try_tx_s!(rlp::decode(args.payment_tx));
+        let payment = try_tx_s!(SignedEthTx::new(payment));
+        let payment = try_tx_s!(WatcherV2Payment::parse(self, &payment));
+        let my_address = self.my_addr().await;
+        if payment.authorizer(true) != my_address {
+            return Err(TransactionErr::Plain(ERRL!(
+                "The payment can't be refunded by {:?}",
+                my_address
+            )));
+        }
+        self.sign_watcher_v2_preimage(&payment, true).await
+    }
+
+    /// Signs the maker payment spend without the maker secret for watchers.
+    pub(crate) async fn sign_maker_payment_spend_for_watcher_v2_impl(
+        &self,
+        args: MakerPaymentSpendForWatcherArgs<'_>,
+    ) -> Result<SignedEthTx, TransactionErr> {
+        let payment: UnverifiedTransactionWrapper = try_tx_s!(rlp::decode(args.maker_payment_tx));
+        let payment = try_tx_s!(SignedEthTx::new(payment));
+        let payment = try_tx_s!(WatcherV2Payment::parse(self, &payment));
+        let my_address = self.my_addr().await;
+        if !matches!(payment.payment_type, EthPaymentType::MakerPayments)
+            || payment.taker != my_address
+            || payment.maker_secret_hash != args.maker_secret_hash
+            || payment.taker_secret_hash != args.taker_secret_hash
+        {
+            return Err(TransactionErr::Plain(ERRL!(
+                "The payment isn't the maker payment of the swap to {:?}",
+                my_address
+            )));
+        }
+        self.sign_watcher_v2_preimage(&payment, false).await
+    }
+
+    /// Builds the watcher method call and wraps it into a transaction signed by this coin's key.
+    /// The wrapping transaction has zero nonce and gas, it isn't meant to be broadcast.
+    async fn sign_watcher_v2_preimage(
+        &self,
+        payment: &WatcherV2Payment,
+        is_refund: bool,
+    ) -> Result<SignedEthTx, TransactionErr> {
+        let secret = try_tx_s!(self.priv_key_policy.activated_key_or_err()).secret();
+        let function = try_tx_s!(payment.watcher_function(is_refund));
+        let reward_amount = try_tx_s!(self.watcher_v2_reward_amount().await);
+        if reward_amount >= payment.amount {
+            return Err(TransactionErr::Plain(ERRL!(
+                "Watcher reward {} is too large for the payment amount {}",
+                reward_amount,
+                payment.amount
+            )));
+        }
+
+        let hash = watcher_v2_authorization_hash(
+            payment.swap_contract,
+            self.chain_id,
+            function.short_signature(),
+            &payment.id,
+            reward_amount,
+        );
+        let mut signature: [u8; 65] = *try_tx_s!(sign(secret, &hash));
+        signature[64] += 27;
+
+        let mut tokens = payment.watcher_call_tokens(is_refund);
+        tokens.push(Token::Uint(reward_amount));
+        tokens.push(Token::Bytes(signature.to_vec()));
+        let data = try_tx_s!(function.encode_input(&tokens));
+
+        let unsigned = try_tx_s!(UnSignedEthTxBuilder::new(
+            TxType::Legacy,
+            U256::zero(),
+            U256::zero(),
+            Action::Call(payment.swap_contract),
+            U256::from(ZERO_VALUE),
+            data,
+        )
+        .with_gas_price(U256::zero())
+        .build());
+        Ok(try_tx_s!(unsigned.sign(secret, Some(self.chain_id))))
+    }
+
+    /// ETH payments reward the watcher with the current cost of its transaction.
+    /// ERC20 payments carry no reward as the gas cost can't be converted to the token amount.
+    async fn watcher_v2_reward_amount(&self) -> Result<U256, String> {
+        match self.coin_type {
+            EthCoinType::Eth => {
+                let reward = try_s!(
+                    self.get_watcher_reward_amount(now_sec() + WATCHER_REWARD_CALC_TIMEOUT_SEC)
+                        .await
+                );
+                Ok(try_s!(wei_from_big_decimal(&reward, ETH_DECIMALS)))
+            },
+            EthCoinType::Erc20 { .. } => Ok(U256::zero()),
+            EthCoinType::Nft { .. } => ERR!("NFT protocol is not supported for ETH and ERC20 Swaps"),
+        }
+    }
+
+    /// Checks that the `payment_tx` is on chain and the `preimage` is validly signed for the refund path
+    /// (`is_refund`) or the maker payment spend.
+    pub(crate) async fn watcher_validate_preimage_v2_impl(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        is_refund: bool,
+    ) -> ValidatePaymentResult<()> {
+        let payment: UnverifiedTransactionWrapper = rlp::decode(payment_tx)?;
+        let payment =
+            SignedEthTx::new(payment).map_to_mm(|e| ValidatePaymentError::TxDeserializationError(e.to_string()))?;
+        let tx_from_rpc = self.transaction_with_quorum(payment.tx_hash()).await?;
+        let tx_from_rpc = tx_from_rpc.ok_or_else(|| {
+            ValidatePaymentError::TxDoesNotExist(format!("Didn't find provided tx {:?} on ETH node", payment.tx_hash()))
+        })?;
+        if tx_from_rpc.from != Some(payment.sender()) || tx_from_rpc.input.0 != payment.unsigned().data() {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment {:?} doesn't match tx data from rpc {:?}",
+                payment, tx_from_rpc
+            )));
+        }
+
+        let payment = WatcherV2Payment::parse(self, &payment).map_to_mm(ValidatePaymentError::WrongPaymentTx)?;
+        let preimage = WatcherV2Preimage::parse(&payment, preimage).map_to_mm(ValidatePaymentError::WrongPaymentTx)?;
+        if preimage.is_refund != is_refund {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Preimage spends the payment by the wrong path, expected refund: {}",
+                is_refund
+            )));
+        }
+        preimage
+            .verify_signature(&payment, self.chain_id)
+            .map_to_mm(ValidatePaymentError::WrongPaymentTx)
+    }
+
+    /// Sends the watcher method call of the `preimage` from this coin's address.
+    /// The `secret` is inserted into the maker payment spend.
+    pub(crate) async fn send_preimage_as_watcher_v2_impl(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        secret: Option<&[u8]>,
+    ) -> Result<SignedEthTx, TransactionErr> {
+        let payment: UnverifiedTransactionWrapper = try_tx_s!(rlp::decode(payment_tx));
+        let payment = try_tx_s!(SignedEthTx::new(payment));
+        let payment = try_tx_s!(WatcherV2Payment::parse(self, &payment));
+        let mut preimage = try_tx_s!(WatcherV2Preimage::parse(&payment, preimage));
+        match secret {
+            Some(secret) if !preimage.is_refund => preimage.tokens[5] = Token::FixedBytes(secret.to_vec()),
+            None if preimage.is_refund => (),
+            _ => {
+                return Err(TransactionErr::Plain(ERRL!(
+                    "The secret is required to complete the maker payment spend only"
+                )))
+            },
+        }
+        let data = try_tx_s!(preimage.function.encode_input(&preimage.tokens));
+
+        let method = if preimage.is_refund {
+            PaymentMethod::RefundTimelock
+        } else {
+            PaymentMethod::Spend
+        };
+        let gas_limit = try_tx_s!(self
+            .gas_limit_v2
+            .gas_limit(&self.coin_type, payment.payment_type, method));
+        self.sign_and_send_transaction(
+            U256::from(ZERO_VALUE),
+            Action::Call(payment.swap_contract),
+            data,
+            U256::from(gas_limit),
+        )
+        .compat()
+        .await
+    }
+
+    /// Returns the transaction that spent or refunded the swap v2 `payment_tx`, if any.
+    /// The payment state is checked first, so the events are only searched for once the payment is completed.
+    pub(crate) async fn watcher_search_for_payment_spend_v2_impl(
+        &self,
+        payment_tx: &[u8],
+        search_from_block: u64,
+    ) -> Result<Option<SignedEthTx>, String> {
+        let payment: UnverifiedTransactionWrapper = try_s!(rlp::decode(payment_tx));
+        let payment = try_s!(SignedEthTx::new(payment));
+        let payment = try_s!(WatcherV2Payment::parse(self, &payment));
+
+        let (state_index, pending_states, events) = match payment.payment_type {
+            EthPaymentType::MakerPayments => (
+                MAKER_PAYMENT_STATE_INDEX,
+                vec![
+                    MakerPaymentStateV2::Uninitialized as u8,
+                    MakerPaymentStateV2::PaymentSent as u8,
+                ],
+                [
+                    "MakerPaymentSpent",
+                    "MakerPaymentRefundedTimelock",
+                    "MakerPaymentRefundedSecret",
+                ],
+            ),
+            EthPaymentType::TakerPayments => (
+                TAKER_PAYMENT_STATE_INDEX,
+                vec![
+                    TakerPaymentStateV2::Uninitialized as u8,
+                    TakerPaymentStateV2::PaymentSent as u8,
+                    TakerPaymentStateV2::TakerApproved as u8,
+                ],
+                [
+                    "TakerPaymentSpent",
+                    "TakerPaymentRefundedTimelock",
+                    "TakerPaymentRefundedSecret",
+                ],
+            ),
+        };
+        let state = try_s!(
+            self.payment_status_v2(
+                payment.swap_contract,
+                Token::FixedBytes(payment.id.clone()),
+                payment.abi_contract(),
+                payment.payment_type,
+                state_index,
+                BlockNumber::Latest,
+            )
+            .await
+        );
+        if pending_states.iter().any(|pending| state == U256::from(*pending)) {
+            return Ok(None);
+        }
+
+        let current_block = try_s!(self.current_block().compat().await);
+        let mut from_block = search_from_block;
+        while from_block <= current_block {
+            let to_block = std::cmp::min(from_block + self.logs_block_range - 1, current_block);
+            for event_name in events.iter() {
+                let logs = try_s!(
+                    self.events_from_block(
+                        payment.swap_contract,
+                        event_name,
+                        from_block,
+                        Some(to_block),
+                        payment.abi_contract(),
+                    )
+                    .await
+                );
+                let found = logs
+                    .into_iter()
+                    .find(|log| log.data.0.len() >= 32 && log.data.0[..32] == payment.id[..]);
+                if let Some(tx_hash) = found.and_then(|log| log.transaction_hash) {
+                    let tx = try_s!(self.transaction(TransactionId::Hash(tx_hash)).await);
+                    let tx = try_s!(tx.ok_or_else(|| ERRL!("Payment spend {:02x} is not found", tx_hash)));
+                    return Ok(Some(try_s!(signed_tx_from_web3_tx(tx))));
+                }
+            }
+            from_block += self.logs_block_range;
+        }
+        ERR!(
+            "Payment state is {}, but its spend event is not found since block {}",
+            state,
+            search_from_block
+        )
+    }
+}
@@ -15,6 +15,7 @@ use web3::types::{Transaction as Web3Tx, TransactionId};
 
 pub(crate) mod eth_maker_swap_v2;
 pub(crate) mod eth_taker_swap_v2;
+pub(crate) mod eth_watcher_v2;
 
 /// ZERO_VALUE is used to represent a 0 amount in transactions where the value is encoded in the transaction input data.
 /// This is typically used in function calls where the value is not directly transferred with the transaction, such as in
@@ -22,6 +23,7 @@ pub(crate) mod eth_taker_swap_v2;
 /// is provided as part of the input data rather than as an Ether value
 pub(crate) const ZERO_VALUE: u32 = 0;
 
+#[derive(Clone, Copy)]
 pub enum EthPaymentType {
     MakerPayments,
     TakerPayments,
@@ -1038,3 +1038,143 @@ fn test_h256_to_str() {
     let b: BytesJson = h.0.to_vec().into();
     println!("H256=0x{:02x}", b);
 }
+
+#[test]
+fn test_node_health_ordering() {
+    use node_health::{best_known_block, sort_by_health};
+
+    let fast = NodeHealth::default();
+    fast.record_success(Duration::from_millis(50));
+    fast.record_block(100);
+
+    let slow = NodeHealth::default();
+    slow.record_success(Duration::from_millis(800));
+    slow.record_block(100);
+
+    let failing = NodeHealth::default();
+    failing.record_success(Duration::from_millis(10));
+    failing.record_failure("connection refused".to_string());
+    failing.record_block(100);
+
+    let lagging = NodeHealth::default();
+    lagging.record_success(Duration::from_millis(10));
+    lagging.record_block(95);
+
+    let mut nodes = vec![
+        ("lagging", lagging),
+        ("failing", failing),
+        ("slow", slow),
+        ("fast", fast),
+    ];
+    assert_eq!(best_known_block(nodes.iter().map(|(_, health)| health)), Some(100));
+
+    sort_by_health(&mut nodes, |(_, health)| health);
+    let order: Vec<_> = nodes.iter().map(|(name, _)| *name).collect();
+    assert_eq!(order, ["fast", "slow", "failing", "lagging"]);
+
+    let report = nodes[3].1.report("lagging".to_string(), Some(100));
+    assert_eq!(report.block_lag, 5);
+    assert_eq!(report.requests, 1);
+    assert_eq!(report.errors, 0);
+}
+
+#[test]
+fn test_validate_rpc_quorum() {
+    use node_health::validate_rpc_quorum;
+
+    assert_eq!(validate_rpc_quorum(None, 3), Ok(None));
+    assert_eq!(validate_rpc_quorum(Some(1), 3), Ok(None));
+    assert_eq!(validate_rpc_quorum(Some(2), 3), Ok(Some(2)));
+    assert_eq!(validate_rpc_quorum(Some(3), 3), Ok(Some(3)));
+    validate_rpc_quorum(Some(0), 3).unwrap_err();
+    validate_rpc_quorum(Some(4), 3).unwrap_err();
+}
+
+#[test]
+fn test_quorum_response_index() {
+    use eth_rpc::quorum_response_index;
+
+    assert_eq!(quorum_response_index(&[Some(1), Some(1), Some(1)], 2), Some(0));
+    assert_eq!(quorum_response_index(&[Some(1), Some(2), Some(2)], 2), Some(1));
+    assert_eq!(quorum_response_index(&[None, Some(2), None, Some(2)], 2), Some(1));
+    assert_eq!(quorum_response_index(&[Some(1), Some(2), None], 2), None);
+    assert_eq!(quorum_response_index(&[Some(1), Some(1), Some(2)], 3), None);
+    assert_eq!(quorum_response_index::<u8>(&[None, None, None], 2), None);
+}
+
+#[test]
+fn test_quorum_vote_keys() {
+    use eth_rpc::{receipt_vote_key, transaction_vote_key};
+    use web3::types::TransactionReceipt;
+
+    let tx = Web3Transaction {
+        hash: H256::from_low_u64_be(1),
+        block_number: Some(100.into()),
+        from: Some(Address::from_low_u64_be(2)),
+        to: Some(Address::from_low_u64_be(3)),
+        value: 1000.into(),
+        ..Web3Transaction::default()
+    };
+    // The nodes may return different optional fields, which don't matter for the validation.
+    let tx_with_other_fields = Web3Transaction {
+        transaction_index: Some(5.into()),
+        gas_price: Some(1000.into()),
+        ..tx.clone()
+    };
+    assert_eq!(
+        transaction_vote_key(&Some(tx.clone())),
+        transaction_vote_key(&Some(tx_with_other_fields))
+    );
+    // The nodes lagging behind may return the transaction as pending.
+    let pending_tx = Web3Transaction {
+        block_number: None,
+        ..tx.clone()
+    };
+    assert_eq!(
+        transaction_vote_key(&Some(tx.clone())),
+        transaction_vote_key(&Some(pending_tx))
+    );
+    let other_input = Web3Transaction {
+        input: vec![1].into(),
+        ..tx.clone()
+    };
+    assert_ne!(
+        transaction_vote_key(&Some(tx.clone())),
+        transaction_vote_key(&Some(other_input))
+    );
+    assert_ne!(transaction_vote_key(&Some(tx)), transaction_vote_key(&None));
+
+    let receipt = TransactionReceipt {
+        transaction_hash: H256::from_low_u64_be(1),
+        block_number: Some(100.into()),
+        status: Some(1.into()),
+        ..TransactionReceipt::default()
+    };
+    let receipt_with_other_fields = TransactionReceipt {
+        cumulative_gas_used: 21000.into(),
+        ..receipt.clone()
+    };
+    assert_eq!(
+        receipt_vote_key(&Some(receipt.clone())),
+        receipt_vote_key(&Some(receipt_with_other_fields))
+    );
+    let failed_receipt = TransactionReceipt {
+        status: Some(0.into()),
+        ..receipt.clone()
+    };
+    assert_ne!(
+        receipt_vote_key(&Some(receipt.clone())),
+        receipt_vote_key(&Some(failed_receipt))
+    );
+    let receipt_with_logs = TransactionReceipt {
+        logs: vec![Log {
+            address: Address::from_low_u64_be(3),
+            ..Log::default()
+        }],
+        ..receipt.clone()
+    };
+    assert_ne!(
+        receipt_vote_key(&Some(receipt)),
+        receipt_vote_key(&Some(receipt_with_logs))
+    );
+}
@@ -0,0 +1,984 @@
+//! Transaction history V2 of ETH and its ERC20 tokens that doesn't rely on the `trace_filter` API.
+//!
+//! The history is built from the blocks scanned forward in ranges:
+//! * The transactions sent by my addresses are found by bisecting the ranges where the addresses' nonces
+//!   have been increased, their fees and statuses are taken from the receipts.
+//!   Note that the nonces at the past blocks can be requested from archive nodes only
+//!   unless the blocks are within the recent state kept by the node.
+//! * ERC20 transfers from and to my addresses are found by the `Transfer` events of the enabled tokens.
+//!
+//! Incoming ETH transfers don't emit events, so they can't be found this way.
+//!
+//! The tokens' transactions are stored to the platform coin's history with the token contract as the token id.
+//! A token enabled after the scanning has started has its `Transfer` events rescanned from the history start
+//! until it catches up with the platform coin.
+//! Only the blocks with enough confirmations are scanned, so that the history isn't polluted by reorganized blocks.
+//! The last scanned block is persisted to the storage, and the scanning is resumed from the block after it.
+
+use super::block_range::AdaptiveBlockRange;
+use super::*;
+use crate::my_tx_history_v2::{CoinWithTxHistoryV2, MyTxHistoryErrorV2, MyTxHistoryTarget, TxHistoryStorage};
+use crate::tx_history_storage::{GetTxHistoryFilters, WalletId};
+use crate::utxo::tx_history_events::TxHistoryEventStreamer;
+use crate::utxo::utxo_common::utxo_tx_history_v2_common::get_tx_history_filters;
+use crate::CoinWithDerivationMethod;
+use common::log;
+use mm2_event_stream::StreamingManager;
+use std::collections::{BTreeSet, HashSet};
+use web3::types::{Block, TransactionReceipt};
+
+/// The interval between the checks for new blocks, in seconds.
+const ETH_HISTORY_POLL_INTERVAL: f64 = 30.;
+/// The cooldown after an RPC error, in seconds.
+const ETH_HISTORY_ERROR_COOLDOWN: f64 = 10.;
+/// The default number of confirmations a block needs to be scanned.
+pub const ETH_HISTORY_DEFAULT_CONFIRMATIONS: u64 = 12;
+
+#[async_trait]
+impl CoinWithTxHistoryV2 for EthCoin {
+    /// ERC20 tokens share the history of their platform coin.
+    fn history_wallet_id(&self) -> WalletId { WalletId::new(self.platform_ticker().to_owned()) }
+
+    async fn get_tx_history_filters(
+        &self,
+        target: MyTxHistoryTarget,
+    ) -> MmResult<GetTxHistoryFilters, MyTxHistoryErrorV2> {
+        let filters = get_tx_history_filters(self, target).await?;
+        match self.coin_type {
+            EthCoinType::Eth => Ok(filters),
+            EthCoinType::Erc20 { token_addr, .. } => {
+                Ok(filters.with_token_id(format!("{:02x}", erc20_token_id(&token_addr))))
+            },
+            EthCoinType::Nft { .. } => MmError::err(MyTxHistoryErrorV2::NotSupportedFor(self.ticker().to_owned())),
+        }
+    }
+}
+
+/// The token id of the ERC20 transfers in the history is the token contract address.
+fn erc20_token_id(token_addr: &Address) -> BytesJson { BytesJson::from(token_addr.as_bytes().to_vec()) }
+
+/// The transactions and blocks requested while processing a block range.
+#[derive(Default)]
+struct RangeData {
+    transactions: HashMap<H256, Web3Transaction>,
+    receipts: HashMap<H256, TransactionReceipt>,
+    timestamps: HashMap<u64, u64>,
+}
+
+impl RangeData {
+    fn add_block(&mut self, block: &Block<Web3Transaction>) {
+        if let Some(number) = block.number {
+            self.timestamps.insert(number.as_u64(), block.timestamp.into_or_max());
+        }
+    }
+
+    async fn transaction(&mut self, coin: &EthCoin, hash: H256) -> Result<Web3Transaction, String> {
+        if let Some(tx) = self.transactions.get(&hash) {
+            return Ok(tx.clone());
+        }
+        let tx = coin
+            .transaction(TransactionId::Hash(hash))
+            .await
+            .map_err(|e| ERRL!("Error {} on getting transaction {:?}", e, hash))?
+            .ok_or_else(|| ERRL!("No such transaction {:?}", hash))?;
+        self.transactions.insert(hash, tx.clone());
+        Ok(tx)
+    }
+
+    async fn receipt(&mut self, coin: &EthCoin, hash: H256) -> Result<TransactionReceipt, String> {
+        if let Some(receipt) = self.receipts.get(&hash) {
+            return Ok(receipt.clone());
+        }
+        let receipt = coin
+            .transaction_receipt(hash)
+            .await
+            .map_err(|e| ERRL!("Error {} on getting transaction {:?} receipt", e, hash))?
+            .ok_or_else(|| ERRL!("No receipt of transaction {:?}", hash))?;
+        self.receipts.insert(hash, receipt.clone());
+        Ok(receipt)
+    }
+
+    async fn timestamp(&mut self, coin: &EthCoin, block_number: u64) -> Result<u64, String> {
+        if let Some(timestamp) = self.timestamps.get(&block_number) {
+            return Ok(*timestamp);
+        }
+        let block = coin
+            .block(BlockId::Number(BlockNumber::Number(block_number.into())))
+            .await
+            .map_err(|e| ERRL!("Error {} on getting block {} data", e, block_number))?
+            .ok_or_else(|| ERRL!("Block {} is None", block_number))?;
+        let timestamp = block.timestamp.into_or_max();
+        self.timestamps.insert(block_number, timestamp);
+        Ok(timestamp)
+    }
+
+    async fn fee_details(&mut self, coin: &EthCoin, tx: &Web3Transaction) -> Result<EthTxFeeDetails, String> {
+        let receipt = self.receipt(coin, tx.hash).await?;
+        let gas_used = receipt.gas_used.unwrap_or_default();
+        let gas_price = receipt.effective_gas_price.or(tx.gas_price).unwrap_or_default();
+        EthTxFeeDetails::new(
+            gas_used,
+            PayForGasOption::Legacy(LegacyGasPrice { gas_price }),
+            coin.platform_ticker(),
+        )
+        .map_err(|e| ERRL!("{}", e))
+    }
+}
+
+/// Finds the blocks within `[from_block, to_block]` where the nonce of `address` has been increased,
+/// i.e. the blocks containing the transactions sent from the address.
+/// `nonce_before` is the nonce at `from_block - 1`, `nonce_after` is the nonce at `to_block`.
+async fn find_nonce_increase_blocks(
+    coin: &EthCoin,
+    address: Address,
+    from_block: u64,
+    nonce_before: U256,
+    to_block: u64,
+    nonce_after: U256,
+) -> Result<Vec<u64>, String> {
+    let mut blocks = Vec::new();
+    let mut ranges = vec![(from_block, nonce_before, to_block, nonce_after)];
+    while let Some((from, nonce_from, to, nonce_to)) = ranges.pop() {
+        if nonce_from == nonce_to {
+            continue;
+        }
+        if from == to {
+            blocks.push(from);
+            continue;
+        }
+        let mid = from + (to - from) / 2;
+        let nonce_mid = coin
+            .transaction_count(address, Some(BlockNumber::Number(mid.into())))
+            .await
+            .map_err(|e| ERRL!("Error {} on getting nonce of {:?} at block {}", e, address, mid))?;
+        ranges.push((mid + 1, nonce_mid, to, nonce_to));
+        ranges.push((from, nonce_from, mid, nonce_mid));
+    }
+    Ok(blocks)
+}
+
+/// The scanning state of the history loop.
+struct EthHistoryScanner<Storage> {
+    coin: EthCoin,
+    storage: Storage,
+    wallet_id: WalletId,
+    /// The first block that hasn't been scanned yet.
+    next_block: u64,
+    /// The first block that hasn't been scanned for the `Transfer` events of each enabled token.
+    /// A token enabled after the scanning has started has no cursor, so its transfers are rescanned
+    /// from `history_start_block` until it catches up with `next_block`.
+    token_next_blocks: HashMap<Address, u64>,
+    /// The configured start block of the history or the first block scanned in this session.
+    history_start_block: u64,
+    /// The nonces of my addresses at `next_block - 1`.
+    nonces: HashMap<Address, U256>,
+    block_range: AdaptiveBlockRange,
+    /// The number of confirmations a block needs to be scanned, at least 1.
+    confirmations: u64,
+}
+
+impl<Storage: TxHistoryStorage> EthHistoryScanner<Storage> {
+    async fn nonce_before(&self, address: Address, block: u64) -> Result<U256, String> {
+        if let Some(nonce) = self.nonces.get(&address) {
+            return Ok(*nonce);
+        }
+        if block == 0 {
+            return Ok(U256::zero());
+        }
+        self.coin
+            .transaction_count(address, Some(BlockNumber::Number((block - 1).into())))
+            .await
+            .map_err(|e| ERRL!("Error {} on getting nonce of {:?} at block {}", e, address, block - 1))
+    }
+
+    async fn is_stored(&self, internal_id: &BytesJson) -> Result<bool, String> {
+        let stored = self
+            .storage
+            .get_tx_from_history(&self.wallet_id, internal_id)
+            .await
+            .map_err(|e| ERRL!("{:?}", e))?;
+        Ok(stored.is_some())
+    }
+
+    fn enabled_tokens(&self) -> HashMap<Address, (String, u8)> {
+        self.coin
+            .get_erc_tokens_infos()
+            .into_iter()
+            .map(|(ticker, info)| (info.token_address, (ticker, info.decimals)))
+            .collect()
+    }
+
+    /// Returns the new transactions of my addresses within `[from_block, to_block]`
+    /// and the nonces of the addresses at `to_block`.
+    async fn scan_range(
+        &self,
+        my_addresses: &HashSet<Address>,
+        tokens: &HashMap<Address, (String, u8)>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<(Vec<TransactionDetails>, HashMap<Address, U256>), String> {
+        let mut data = RangeData::default();
+        let mut new_nonces = HashMap::with_capacity(my_addresses.len());
+        let mut send_blocks = BTreeSet::new();
+        for address in my_addresses {
+            let nonce_before = self.nonce_before(*address, from_block).await?;
+            let nonce_after = self
+                .coin
+                .transaction_count(*address, Some(BlockNumber::Number(to_block.into())))
+                .await
+                .map_err(|e| ERRL!("Error {} on getting nonce of {:?} at block {}", e, address, to_block))?;
+            let blocks =
+                find_nonce_increase_blocks(&self.coin, *address, from_block, nonce_before, to_block, nonce_after)
+                    .await?;
+            send_blocks.extend(blocks);
+            new_nonces.insert(*address, nonce_after);
+        }
+
+        let mut sent_txs = Vec::new();
+        for block_number in send_blocks {
+            let block = self
+                .coin
+                .block_with_txs(BlockId::Number(BlockNumber::Number(block_number.into())))
+                .await
+                .map_err(|e| ERRL!("Error {} on getting block {} data", e, block_number))?
+                .ok_or_else(|| ERRL!("Block {} is None", block_number))?;
+            data.add_block(&block);
+            for tx in block.transactions {
+                if matches!(tx.from, Some(from) if my_addresses.contains(&from)) {
+                    data.transactions.insert(tx.hash, tx.clone());
+                    sent_txs.push(tx);
+                }
+            }
+        }
+
+        let mut history = Vec::new();
+        for tx in sent_txs {
+            let internal_id = BytesJson::from(tx.hash.as_bytes().to_vec());
+            if self.is_stored(&internal_id).await? {
+                continue;
+            }
+            let details = self.sent_tx_details(&mut data, my_addresses, tokens, tx).await?;
+            history.push(details);
+        }
+
+        let transfers = self
+            .token_transfers(&mut data, my_addresses, tokens, from_block, to_block)
+            .await?;
+        history.extend(transfers);
+
+        Ok((history, new_nonces))
+    }
+
+    /// Returns the new ERC20 transfers of the `tokens` from and to my addresses within `[from_block, to_block]`.
+    async fn token_transfers(
+        &self,
+        data: &mut RangeData,
+        my_addresses: &HashSet<Address>,
+        tokens: &HashMap<Address, (String, u8)>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<TransactionDetails>, String> {
+        let mut history = Vec::new();
+        for log in self.transfer_events(my_addresses, tokens, from_block, to_block).await? {
+            let (tx_hash, log_index) = match (log.transaction_hash, log.log_index) {
+                (Some(tx_hash), Some(log_index)) => (tx_hash, log_index),
+                _ => continue,
+            };
+            let mut internal_id = tx_hash.as_bytes().to_vec();
+            internal_id.extend_from_slice(&log_index.low_u64().to_be_bytes());
+            let internal_id = BytesJson::from(internal_id);
+            if self.is_stored(&internal_id).await? {
+                continue;
+            }
+            if let Some(details) = self
+                .transfer_details(data, my_addresses, tokens, log, internal_id)
+                .await?
+            {
+                history.push(details);
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Requests the `Transfer` events of the enabled tokens sent from or to my addresses.
+    async fn transfer_events(
+        &self,
+        my_addresses: &HashSet<Address>,
+        tokens: &HashMap<Address, (String, u8)>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, String> {
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+        let transfer_event = ERC20_CONTRACT.event("Transfer").map_err(|e| ERRL!("{}", e))?;
+        let my_topics: Vec<H256> = my_addresses.iter().map(|address| (*address).into()).collect();
+        let contracts: Vec<Address> = tokens.keys().copied().collect();
+
+        // Topics of the same position are OR-ed, but the positions are AND-ed,
+        // so the sent and received transfers are requested separately.
+        let sent_filter = FilterBuilder::default()
+            .topics(
+                Some(vec![transfer_event.signature()]),
+                Some(my_topics.clone()),
+                None,
+                None,
+            )
+            .from_block(BlockNumber::Number(from_block.into()))
+            .to_block(BlockNumber::Number(to_block.into()))
+            .address(contracts.clone())
+            .build();
+        let received_filter = FilterBuilder::default()
+            .topics(Some(vec![transfer_event.signature()]), None, Some(my_topics), None)
+            .from_block(BlockNumber::Number(from_block.into()))
+            .to_block(BlockNumber::Number(to_block.into()))
+            .address(contracts)
+            .build();
+
+        let sent = self
+            .coin
+            .logs(sent_filter)
+            .await
+            .map_err(|e| ERRL!("Error {} on eth_getLogs", e))?;
+        let received = self
+            .coin
+            .logs(received_filter)
+            .await
+            .map_err(|e| ERRL!("Error {} on eth_getLogs", e))?;
+
+        // The transfers between my addresses are returned by both requests.
+        let mut unique = HashSet::new();
+        Ok(sent
+            .into_iter()
+            .chain(received)
+            .filter(|log| !log.is_removed() && unique.insert((log.transaction_hash, log.log_index)))
+            .collect())
+    }
+
+    async fn sent_tx_details(
+        &self,
+        data: &mut RangeData,
+        my_addresses: &HashSet<Address>,
+        tokens: &HashMap<Address, (String, u8)>,
+        tx: Web3Transaction,
+    ) -> Result<TransactionDetails, String> {
+        let fee_details = data.fee_details(&self.coin, &tx).await?;
+        let receipt = data.receipt(&self.coin, tx.hash).await?;
+        let block_height = tx.block_number.map(|n| n.as_u64()).unwrap_or_default();
+        let timestamp = data.timestamp(&self.coin, block_height).await?;
+
+        let is_success = receipt.status == Some(U64::from(1u64));
+        let total_amount = u256_to_big_decimal(tx.value, self.coin.decimals).map_err(|e| ERRL!("{}", e))?;
+        let is_token_call = tx.value.is_zero() && matches!(tx.to, Some(to) if tokens.contains_key(&to));
+
+        let (transaction_type, total_amount, spent_by_me, received_by_me) = if is_token_call {
+            // The token transfer itself is stored separately, the platform coin history reflects the fee only.
+            let fee = fee_details.total_fee.clone();
+            (TransactionType::FeeForTokenTx, fee.clone(), fee, BigDecimal::from(0))
+        } else {
+            // ETH is transferred only if the transaction succeeded, the fee is paid anyway.
+            let transferred = if is_success {
+                total_amount.clone()
+            } else {
+                BigDecimal::from(0)
+            };
+            let received_by_me = match tx.to {
+                Some(to) if my_addresses.contains(&to) => transferred.clone(),
+                _ => BigDecimal::from(0),
+            };
+            let spent_by_me = transferred + &fee_details.total_fee;
+            (
+                TransactionType::StandardTransfer,
+                total_amount,
+                spent_by_me,
+                received_by_me,
+            )
+        };
+
+        let from = tx.from.map(|from| from.display_address()).into_iter().collect();
+        let to = tx.to.map(|to| to.display_address()).into_iter().collect();
+        let raw = signed_tx_from_web3_tx(tx)?;
+        Ok(TransactionDetails {
+            my_balance_change: &received_by_me - &spent_by_me,
+            spent_by_me,
+            received_by_me,
+            total_amount,
+            to,
+            from,
+            coin: self.coin.ticker().to_owned(),
+            fee_details: Some(fee_details.into()),
+            block_height,
+            tx: TransactionData::new_signed(
+                BytesJson(rlp::encode(&raw).to_vec()),
+                format!("{:02x}", BytesJson(raw.tx_hash_as_bytes().to_vec())),
+            ),
+            internal_id: BytesJson::from(raw.tx_hash_as_bytes().to_vec()),
+            timestamp,
+            kmd_rewards: None,
+            transaction_type,
+            memo: None,
+        })
+    }
+
+    /// Returns `None` if the event isn't a valid ERC20 transfer of an enabled token.
+    async fn transfer_details(
+        &self,
+        data: &mut RangeData,
+        my_addresses: &HashSet<Address>,
+        tokens: &HashMap<Address, (String, u8)>,
+        log: Log,
+        internal_id: BytesJson,
+    ) -> Result<Option<TransactionDetails>, String> {
+        let (ticker, decimals) = match tokens.get(&log.address) {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        // ERC721 `Transfer` has the same signature, but the token id is indexed as the 4th topic.
+        let (tx_hash, block_number) = match (log.transaction_hash, log.block_number) {
+            (Some(tx_hash), Some(block_number)) if log.topics.len() == 3 && log.data.0.len() == 32 => {
+                (tx_hash, block_number.as_u64())
+            },
+            _ => return Ok(None),
+        };
+
+        let from_addr = H160::from(log.topics[1]);
+        let to_addr = H160::from(log.topics[2]);
+        let total_amount =
+            u256_to_big_decimal(U256::from(log.data.0.as_slice()), *decimals).map_err(|e| ERRL!("{}", e))?;
+        let spent_by_me = if my_addresses.contains(&from_addr) {
+            total_amount.clone()
+        } else {
+            BigDecimal::from(0)
+        };
+        let received_by_me = if my_addresses.contains(&to_addr) {
+            total_amount.clone()
+        } else {
+            BigDecimal::from(0)
+        };
+
+        let tx = data.transaction(&self.coin, tx_hash).await?;
+        // The fee is paid by the sender of the transaction, which may differ from the token sender.
+        let fee_details = match tx.from {
+            Some(sender) if my_addresses.contains(&sender) => Some(data.fee_details(&self.coin, &tx).await?.into()),
+            _ => None,
+        };
+        let timestamp = data.timestamp(&self.coin, block_number).await?;
+        let raw = signed_tx_from_web3_tx(tx)?;
+
+        Ok(Some(TransactionDetails {
+            my_balance_change: &received_by_me - &spent_by_me,
+            spent_by_me,
+            received_by_me,
+            total_amount,
+            to: vec![to_addr.display_address()],
+            from: vec![from_addr.display_address()],
+            coin: ticker.clone(),
+            fee_details,
+            block_height: block_number,
+            tx: TransactionData::new_signed(
+                BytesJson(rlp::encode(&raw).to_vec()),
+                format!("{:02x}", BytesJson(raw.tx_hash_as_bytes().to_vec())),
+            ),
+            internal_id,
+            timestamp,
+            kmd_rewards: None,
+            transaction_type: TransactionType::TokenTransfer(erc20_token_id(&log.address)),
+            memo: None,
+        }))
+    }
+
+    fn set_history_sync_state(&self, state: HistorySyncState) { *self.coin.history_sync_state.lock().unwrap() = state; }
+
+    /// Stores the new transactions and notifies the streaming clients about them once they are stored.
+    async fn add_transactions_to_history(
+        &self,
+        streaming_manager: &StreamingManager,
+        transactions: Vec<TransactionDetails>,
+    ) -> Result<(), String> {
+        if transactions.is_empty() {
+            return Ok(());
+        }
+        let mut by_ticker: HashMap<String, Vec<TransactionDetails>> = HashMap::new();
+        for tx in transactions.iter() {
+            by_ticker.entry(tx.coin.clone()).or_default().push(tx.clone());
+        }
+        self.storage
+            .add_transactions_to_history(&self.wallet_id, transactions)
+            .await
+            .map_err(|e| ERRL!("Error adding {} transactions to history: {:?}", self.coin.ticker(), e))?;
+        for (ticker, txs) in by_ticker {
+            streaming_manager
+                .send_fn(&TxHistoryEventStreamer::derive_streamer_id(&ticker), || txs)
+                .ok();
+        }
+        Ok(())
+    }
+
+    /// Scans the next block range of the tokens that are behind the platform coin.
+    /// Returns `false` if all the enabled tokens have caught up with `next_block`.
+    async fn catch_up_tokens(
+        &mut self,
+        streaming_manager: &StreamingManager,
+        my_addresses: &HashSet<Address>,
+        tokens: &HashMap<Address, (String, u8)>,
+    ) -> Result<bool, String> {
+        let lagging_from = tokens
+            .keys()
+            .filter_map(|token| self.token_next_blocks.get(token).copied())
+            .filter(|token_next_block| *token_next_block < self.next_block)
+            .min();
+        let from_block = match lagging_from {
+            Some(block) => block,
+            None => return Ok(false),
+        };
+        let to_block = self.block_range.range_end(from_block, self.next_block - 1);
+        // The tokens whose cursors are within the range scan its beginning again, the stored transfers are skipped.
+        let lagging: HashMap<Address, (String, u8)> = tokens
+            .iter()
+            .filter(|(token, _)| matches!(self.token_next_blocks.get(*token), Some(block) if *block <= to_block))
+            .map(|(token, info)| (*token, info.clone()))
+            .collect();
+
+        let mut data = RangeData::default();
+        let new_transactions = match self
+            .token_transfers(&mut data, my_addresses, &lagging, from_block, to_block)
+            .await
+        {
+            Ok(transactions) => transactions,
+            Err(e) => {
+                log::warn!(
+                    "Error scanning {} token transfers in blocks {}..={}: {}",
+                    self.coin.ticker(),
+                    from_block,
+                    to_block,
+                    e
+                );
+                if !self.block_range.shrink() {
+                    Timer::sleep(ETH_HISTORY_ERROR_COOLDOWN).await;
+                }
+                return Ok(true);
+            },
+        };
+        self.add_transactions_to_history(streaming_manager, new_transactions)
+            .await?;
+
+        for token in lagging.keys() {
+            self.token_next_blocks.insert(*token, to_block + 1);
+        }
+        self.block_range.grow();
+        self.set_history_sync_state(HistorySyncState::InProgress(json!({
+            "blocks_left": self.next_block - to_block - 1,
+        })));
+        Ok(true)
+    }
+
+    async fn run(mut self, streaming_manager: StreamingManager) {
+        loop {
+            let my_addresses = match self.coin.all_addresses().await {
+                Ok(addresses) => addresses,
+                Err(e) => {
+                    log::error!("Error getting {} addresses: {}", self.coin.ticker(), e);
+                    self.set_history_sync_state(HistorySyncState::Error(json!({
+                        "message": e.to_string(),
+                    })));
+                    return;
+                },
+            };
+
+            let tokens = self.enabled_tokens();
+            for token in tokens.keys() {
+                self.token_next_blocks.entry(*token).or_insert(self.history_start_block);
+            }
+            match self.catch_up_tokens(&streaming_manager, &my_addresses, &tokens).await {
+                Ok(true) => continue,
+                Ok(false) => (),
+                Err(e) => {
+                    log::error!("{}", e);
+                    self.set_history_sync_state(HistorySyncState::Error(json!({ "message": e })));
+                    return;
+                },
+            }
+
+            let current_block = match self.coin.block_number().await {
+                Ok(block) => block.as_u64(),
+                Err(e) => {
+                    log::warn!("Error {} on eth_blockNumber for {}, retrying", e, self.coin.ticker());
+                    Timer::sleep(ETH_HISTORY_ERROR_COOLDOWN).await;
+                    continue;
+                },
+            };
+            // The latest block is confirmed once.
+            let confirmed_block = match (current_block + 1).checked_sub(self.confirmations) {
+                Some(block) if block >= self.next_block => block,
+                _ => {
+                    self.set_history_sync_state(HistorySyncState::Finished);
+                    Timer::sleep(ETH_HISTORY_POLL_INTERVAL).await;
+                    continue;
+                },
+            };
+
+            let to_block = self.block_range.range_end(self.next_block, confirmed_block);
+            let (new_transactions, new_nonces) =
+                match self.scan_range(&my_addresses, &tokens, self.next_block, to_block).await {
+                    Ok(scanned) => scanned,
+                    Err(e) => {
+                        log::warn!(
+                            "Error scanning {} blocks {}..={}: {}",
+                            self.coin.ticker(),
+                            self.next_block,
+                            to_block,
+                            e
+                        );
+                        if !self.block_range.shrink() {
+                            Timer::sleep(ETH_HISTORY_ERROR_COOLDOWN).await;
+                        }
+                        continue;
+                    },
+                };
+
+            if let Err(e) = self
+                .add_transactions_to_history(&streaming_manager, new_transactions)
+                .await
+            {
+                log::error!("{}", e);
+                self.set_history_sync_state(HistorySyncState::Error(json!({ "message": e })));
+                return;
+            }
+            // The transactions are stored first, so the range is scanned again if the node stops in between.
+            if let Err(e) = self.storage.set_scanned_block_height(&self.wallet_id, to_block).await {
+                log::error!("Error saving {} scanned block height: {:?}", self.coin.ticker(), e);
+                self.set_history_sync_state(HistorySyncState::Error(json!({
+                    "message": format!("{:?}", e),
+                })));
+                return;
+            }
+
+            self.nonces = new_nonces;
+            self.next_block = to_block + 1;
+            for token in tokens.keys() {
+                self.token_next_blocks.insert(*token, self.next_block);
+            }
+            self.block_range.grow();
+            if to_block < confirmed_block {
+                self.set_history_sync_state(HistorySyncState::InProgress(json!({
+                    "blocks_left": confirmed_block - to_block,
+                })));
+            } else {
+                self.set_history_sync_state(HistorySyncState::Finished);
+                Timer::sleep(ETH_HISTORY_POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Scans the history of ETH platform coin and its enabled ERC20 tokens and stores it to `storage`.
+/// If the history has never been scanned, the scanning starts from `start_block`
+/// or from the current block if it's not set.
+/// A block is scanned once it has `confirmations`, [`ETH_HISTORY_DEFAULT_CONFIRMATIONS`] if not set.
+pub async fn eth_history_loop(
+    coin: EthCoin,
+    storage: impl TxHistoryStorage,
+    streaming_manager: StreamingManager,
+    start_block: Option<u64>,
+    confirmations: Option<u64>,
+) {
+    let wallet_id = coin.history_wallet_id();
+    let set_error = |e: String| {
+        log::error!("Error initializing {} tx history: {}", coin.ticker(), e);
+        *coin.history_sync_state.lock().unwrap() = HistorySyncState::Error(json!({ "message": e }));
+    };
+
+    if let Err(e) = storage.init(&wallet_id).await {
+        return set_error(format!("{:?}", e));
+    }
+    let next_block = match storage.get_scanned_block_height(&wallet_id).await {
+        Ok(Some(height)) => height + 1,
+        // The history might have been stored before the scanned block height was persisted.
+        Ok(None) => match storage.get_highest_block_height(&wallet_id).await {
+            // The transactions of the highest block might be stored partially, so it's scanned again.
+            Ok(Some(height)) => height as u64,
+            Ok(None) => match start_block {
+                Some(block) => block,
+                None => match coin.block_number().await {
+                    Ok(block) => block.as_u64(),
+                    Err(e) => return set_error(e.to_string()),
+                },
+            },
+            Err(e) => return set_error(format!("{:?}", e)),
+        },
+        Err(e) => return set_error(format!("{:?}", e)),
+    };
+    *coin.history_sync_state.lock().unwrap() = HistorySyncState::NotStarted;
+
+    // The tokens enabled along with the platform coin have been scanned up to its cursor.
+    let token_next_blocks = coin
+        .get_erc_tokens_infos()
+        .into_values()
+        .map(|info| (info.token_address, next_block))
+        .collect();
+    let scanner = EthHistoryScanner {
+        coin,
+        storage,
+        wallet_id,
+        next_block,
+        token_next_blocks,
+        history_start_block: start_block.map_or(next_block, |block| block.min(next_block)),
+        nonces: HashMap::new(),
+        block_range: AdaptiveBlockRange::default(),
+        confirmations: confirmations.unwrap_or(ETH_HISTORY_DEFAULT_CONFIRMATIONS).max(1),
+    };
+    scanner.run(streaming_manager).await
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::eth::for_tests::eth_coin_for_test;
+    use crate::tx_history_storage::TxHistoryStorageBuilder;
+    use common::block_on;
+    use mm2_test_helpers::for_tests::{mm_ctx_with_custom_db, ETH_SEPOLIA_CHAIN_ID};
+    use mocktopus::mocking::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// The blocks of the transactions sent by the test address, a block may contain several of them.
+    const SEND_BLOCKS: [u64; 4] = [3, 7, 7, 15];
+    /// The signed transaction of the EIP-155 example.
+    const EIP155_EXAMPLE_TX: &str = "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    fn nonce_at(block: Option<BlockNumber>) -> U256 {
+        let block = match block {
+            Some(BlockNumber::Number(block)) => block.as_u64(),
+            block => panic!("Unexpected block {:?}", block),
+        };
+        SEND_BLOCKS
+            .iter()
+            .filter(|send_block| **send_block <= block)
+            .count()
+            .into()
+    }
+
+    fn eth_coin() -> EthCoin {
+        eth_coin_for_test(EthCoinType::Eth, &["http://localhost:8545"], None, ETH_SEPOLIA_CHAIN_ID).1
+    }
+
+    #[test]
+    fn test_find_nonce_increase_blocks() {
+        static NONCE_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+        EthCoin::transaction_count.mock_safe(|_, _, block| {
+            NONCE_REQUESTS.fetch_add(1, Ordering::Relaxed);
+            MockResult::Return(Box::pin(futures::future::ok(nonce_at(block))))
+        });
+        let coin = eth_coin();
+        let address = Address::from_low_u64_be(1);
+        let find = |from: u64, to: u64| {
+            let nonce_before = nonce_at(Some(BlockNumber::Number((from - 1).into())));
+            let nonce_after = nonce_at(Some(BlockNumber::Number(to.into())));
+            block_on(find_nonce_increase_blocks(
+                &coin,
+                address,
+                from,
+                nonce_before,
+                to,
+                nonce_after,
+            ))
+            .unwrap()
+        };
+
+        assert_eq!(find(1, 20), vec![3, 7, 15]);
+        assert_eq!(find(7, 7), vec![7]);
+        assert_eq!(find(4, 15), vec![7, 15]);
+
+        // The nonces aren't requested within a range without sent transactions.
+        NONCE_REQUESTS.store(0, Ordering::Relaxed);
+        assert_eq!(find(8, 14), Vec::<u64>::new());
+        assert_eq!(NONCE_REQUESTS.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_transfer_details() {
+        let coin = eth_coin();
+        let ctx = mm_ctx_with_custom_db();
+        let scanner = EthHistoryScanner {
+            wallet_id: coin.history_wallet_id(),
+            coin,
+            storage: TxHistoryStorageBuilder::new(&ctx).build().unwrap(),
+            next_block: 0,
+            token_next_blocks: HashMap::new(),
+            history_start_block: 0,
+            nonces: HashMap::new(),
+            block_range: AdaptiveBlockRange::default(),
+            confirmations: 1,
+        };
+
+        let me = Address::from([0x11; 20]);
+        let other = Address::from([0x22; 20]);
+        let token = Address::from([0x33; 20]);
+        let my_addresses: HashSet<Address> = [me].into_iter().collect();
+        let tokens: HashMap<Address, (String, u8)> = [(token, ("JST".to_owned(), 18))].into_iter().collect();
+
+        let raw_tx = hex::decode(EIP155_EXAMPLE_TX).unwrap();
+        let tx_hash = H256::from(keccak256(&raw_tx).take());
+        let tx = Web3Transaction {
+            hash: tx_hash,
+            nonce: 9.into(),
+            from: Some(other),
+            to: Some(Address::from([0x35; 20])),
+            value: U256::exp10(18),
+            gas_price: Some(20_000_000_000u64.into()),
+            gas: 21000.into(),
+            v: Some(37.into()),
+            r: Some(U256::from_str("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276").unwrap()),
+            s: Some(U256::from_str("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap()),
+            block_number: Some(100.into()),
+            ..Web3Transaction::default()
+        };
+        let receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            gas_used: Some(21000.into()),
+            effective_gas_price: Some(20_000_000_000u64.into()),
+            ..TransactionReceipt::default()
+        };
+        let mut data = RangeData::default();
+        data.transactions.insert(tx_hash, tx.clone());
+        data.receipts.insert(tx_hash, receipt);
+        data.timestamps.insert(100, 1_700_000_000);
+
+        let transfer_signature = ERC20_CONTRACT.event("Transfer").unwrap().signature();
+        let mut amount = [0; 32];
+        U256::from(1_500_000_000_000_000_000u64).to_big_endian(&mut amount);
+        let transfer_log = |contract: Address, topics: Vec<H256>, data: Vec<u8>| Log {
+            address: contract,
+            topics,
+            data: data.into(),
+            transaction_hash: Some(tx_hash),
+            block_number: Some(100.into()),
+            log_index: Some(0.into()),
+            ..Log::default()
+        };
+        let internal_id = BytesJson::from(vec![1; 40]);
+
+        let received = transfer_log(
+            token,
+            vec![transfer_signature, other.into(), me.into()],
+            amount.to_vec(),
+        );
+        let details =
+            block_on(scanner.transfer_details(&mut data, &my_addresses, &tokens, received, internal_id.clone()))
+                .unwrap()
+                .unwrap();
+        assert_eq!(details.coin, "JST");
+        assert_eq!(details.total_amount, "1.5".parse().unwrap());
+        assert_eq!(details.received_by_me, "1.5".parse().unwrap());
+        assert_eq!(details.spent_by_me, 0.into());
+        assert_eq!(details.from, vec![other.display_address()]);
+        assert_eq!(details.to, vec![me.display_address()]);
+        assert_eq!(details.block_height, 100);
+        assert_eq!(details.timestamp, 1_700_000_000);
+        assert_eq!(details.internal_id, internal_id);
+        assert_eq!(
+            details.transaction_type,
+            TransactionType::TokenTransfer(erc20_token_id(&token))
+        );
+        // The fee is paid by the sender of the transaction.
+        assert!(details.fee_details.is_none());
+
+        data.transactions
+            .insert(tx_hash, Web3Transaction { from: Some(me), ..tx });
+        let sent = transfer_log(
+            token,
+            vec![transfer_signature, me.into(), other.into()],
+            amount.to_vec(),
+        );
+        let details = block_on(scanner.transfer_details(&mut data, &my_addresses, &tokens, sent, internal_id.clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(details.spent_by_me, "1.5".parse().unwrap());
+        assert_eq!(details.my_balance_change, "-1.5".parse().unwrap());
+        assert!(details.fee_details.is_some());
+
+        // ERC721 `Transfer` indexes the token id as the 4th topic and has no data.
+        let erc721 = transfer_log(
+            token,
+            vec![transfer_signature, other.into(), me.into(), H256::from_low_u64_be(1)],
+            Vec::new(),
+        );
+        let details =
+            block_on(scanner.transfer_details(&mut data, &my_addresses, &tokens, erc721, internal_id.clone())).unwrap();
+        assert!(details.is_none());
+
+        let unknown_token = transfer_log(
+            other,
+            vec![transfer_signature, other.into(), me.into()],
+            amount.to_vec(),
+        );
+        let details =
+            block_on(scanner.transfer_details(&mut data, &my_addresses, &tokens, unknown_token, internal_id.clone()))
+                .unwrap();
+        assert!(details.is_none());
+
+        let invalid_data = transfer_log(token, vec![transfer_signature, other.into(), me.into()], vec![1; 64]);
+        let details =
+            block_on(scanner.transfer_details(&mut data, &my_addresses, &tokens, invalid_data, internal_id)).unwrap();
+        assert!(details.is_none());
+    }
+
+    #[test]
+    fn test_catch_up_tokens() {
+        static SCANNED_RANGES: Mutex<Vec<(String, String, usize)>> = Mutex::new(Vec::new());
+        EthCoin::logs.mock_safe(|_, filter| {
+            let filter = serde_json::to_value(&filter).unwrap();
+            let contracts = filter["address"]
+                .as_array()
+                .map(|contracts| contracts.len())
+                .unwrap_or(1);
+            SCANNED_RANGES.lock().unwrap().push((
+                filter["fromBlock"].as_str().unwrap().to_owned(),
+                filter["toBlock"].as_str().unwrap().to_owned(),
+                contracts,
+            ));
+            MockResult::Return(Box::pin(futures::future::ok(Vec::new())))
+        });
+
+        let coin = eth_coin();
+        let ctx = mm_ctx_with_custom_db();
+        let old_token = Address::from([0x33; 20]);
+        let new_token = Address::from([0x44; 20]);
+        let mut scanner = EthHistoryScanner {
+            wallet_id: coin.history_wallet_id(),
+            coin,
+            storage: TxHistoryStorageBuilder::new(&ctx).build().unwrap(),
+            next_block: 25_000,
+            token_next_blocks: [(old_token, 25_000)].into_iter().collect(),
+            history_start_block: 5_000,
+            nonces: HashMap::new(),
+            block_range: AdaptiveBlockRange::default(),
+            confirmations: 1,
+        };
+        let streaming_manager = StreamingManager::default();
+        let my_addresses: HashSet<Address> = [Address::from([0x11; 20])].into_iter().collect();
+        let tokens: HashMap<Address, (String, u8)> =
+            [(old_token, ("JST".to_owned(), 18)), (new_token, ("USDT".to_owned(), 6))]
+                .into_iter()
+                .collect();
+
+        // The token enabled after the scanning has started has no cursor and is rescanned from the history start.
+        for token in tokens.keys() {
+            scanner
+                .token_next_blocks
+                .entry(*token)
+                .or_insert(scanner.history_start_block);
+        }
+        while block_on(scanner.catch_up_tokens(&streaming_manager, &my_addresses, &tokens)).unwrap() {}
+
+        // The sent and received transfers are requested for the lagging token only.
+        let expected: Vec<_> = [("0x1388", "0x3a97"), ("0x3a98", "0x61a7")]
+            .iter()
+            .flat_map(|(from, to)| vec![(from.to_string(), to.to_string(), 1); 2])
+            .collect();
+        assert_eq!(*SCANNED_RANGES.lock().unwrap(), expected);
+        assert_eq!(scanner.token_next_blocks[&new_token], 25_000);
+        assert_eq!(scanner.token_next_blocks[&old_token], 25_000);
+    }
+}
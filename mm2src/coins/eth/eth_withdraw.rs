@@ -189,6 +189,7 @@ where
         let coin = self.coin();
         let ticker = coin.deref().ticker.clone();
         let req = self.request().clone();
+        req.ensure_no_utxo_specific_fields(&ticker)?;
 
         let to_addr = coin
             .address_from_str(&req.to)
@@ -252,12 +253,12 @@ where
 
         let (tx_hash, tx_hex) = match coin.priv_key_policy {
             EthPrivKeyPolicy::Iguana(_) | EthPrivKeyPolicy::HDWallet { .. } | EthPrivKeyPolicy::Trezor => {
+                // The transaction is tracked once it's broadcast with `send_raw_transaction`,
+                // see `EthCoin::track_raw_tx`.
                 let address_lock = coin.get_address_lock(my_address.to_string()).await;
                 let _nonce_lock = address_lock.lock().await;
                 let (nonce, _) = coin
-                    .clone()
-                    .get_addr_nonce(my_address)
-                    .compat()
+                    .next_nonce(my_address)
                     .timeout_secs(30.)
                     .await?
                     .map_to_mm(WithdrawError::Transport)?;
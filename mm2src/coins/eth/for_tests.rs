@@ -37,7 +37,11 @@ pub(crate) fn eth_coin_from_keypair(
         };
         let transport = Web3Transport::new_http(node);
         let web3 = Web3::new(transport);
-        web3_instances.push(Web3Instance { web3, is_parity: false });
+        web3_instances.push(Web3Instance {
+            web3,
+            is_parity: false,
+            health: Default::default(),
+        });
     }
     drop_mutability!(web3_instances);
 
@@ -66,6 +70,7 @@ pub(crate) fn eth_coin_from_keypair(
         contract_supports_watchers: false,
         ticker,
         web3_instances: AsyncMutex::new(web3_instances),
+        rpc_quorum: None,
         ctx: ctx.weak(),
         required_confirmations: 1.into(),
         swap_txfee_policy: Mutex::new(SwapTxFeePolicy::Internal),
@@ -6,7 +6,6 @@ use futures::compat::Future01CompatExt;
 use mm2_err_handle::prelude::{MapToMmResult, MmError, MmResult};
 use mm2_number::BigDecimal;
 use num_traits::Signed;
-use web3::types::TransactionId;
 
 use super::ContractType;
 use crate::coin_errors::{ValidatePaymentError, ValidatePaymentResult};
@@ -80,9 +79,7 @@ impl EthCoin {
                 let token_address = args.nft_swap_info.token_address;
                 let maker_address = public_to_address(args.maker_pub);
                 let swap_id = self.etomic_swap_id_v2(args.time_lock, args.maker_secret_hash);
-                let tx_from_rpc = self
-                    .transaction(TransactionId::Hash(args.maker_payment_tx.tx_hash()))
-                    .await?;
+                let tx_from_rpc = self.transaction_with_quorum(args.maker_payment_tx.tx_hash()).await?;
                 let tx_from_rpc = tx_from_rpc.as_ref().ok_or_else(|| {
                     ValidatePaymentError::TxDoesNotExist(format!(
                         "Didn't find provided tx {:?} on ETH node",
@@ -0,0 +1,156 @@
+//! Rolling health statistics of the web3 nodes an [`EthCoin`](super::EthCoin) is connected to.
+//!
+//! Every request routed through `try_rpc_send` updates the latency and error averages of the node that served it,
+//! and `eth_blockNumber` responses update the node's last seen block. The resulting score decides which node
+//! is asked first, so a slow, failing or lagging node is only used once the healthier ones are exhausted.
+
+use compatible_time::Duration;
+use std::cmp::Ordering;
+use std::sync::Mutex;
+
+pub mod rpc;
+
+/// Weight of the newest sample in the exponential moving averages.
+const EWMA_ALPHA: f64 = 0.2;
+/// Score penalty (in milliseconds) of a node whose every recent request failed.
+const ERROR_PENALTY_MS: f64 = 10_000.;
+/// Score penalty (in milliseconds) per block the node lags behind the best known block.
+const BLOCK_LAG_PENALTY_MS: f64 = 1_000.;
+
+#[derive(Clone, Debug, Default)]
+struct NodeHealthState {
+    avg_latency_ms: Option<f64>,
+    error_rate: f64,
+    last_block: Option<u64>,
+    requests: u64,
+    errors: u64,
+    last_error: Option<String>,
+}
+
+impl NodeHealthState {
+    fn push_sample(&mut self, failed: bool) {
+        let sample = if failed { 1. } else { 0. };
+        self.error_rate = EWMA_ALPHA * sample + (1. - EWMA_ALPHA) * self.error_rate;
+        self.requests += 1;
+    }
+}
+
+/// Health statistics of a single node, shared between the clones of its `Web3Instance`.
+#[derive(Debug, Default)]
+pub struct NodeHealth(Mutex<NodeHealthState>);
+
+impl NodeHealth {
+    pub(crate) fn record_success(&self, latency: Duration) {
+        let mut state = self.0.lock().unwrap();
+        let latency_ms = latency.as_secs_f64() * 1000.;
+        state.avg_latency_ms = Some(match state.avg_latency_ms {
+            Some(avg) => EWMA_ALPHA * latency_ms + (1. - EWMA_ALPHA) * avg,
+            None => latency_ms,
+        });
+        state.push_sample(false);
+    }
+
+    pub(crate) fn record_failure(&self, error: String) {
+        let mut state = self.0.lock().unwrap();
+        state.push_sample(true);
+        state.errors += 1;
+        state.last_error = Some(error);
+    }
+
+    /// The node answered, but its response differs from the one the quorum agreed on.
+    /// The request was already counted as a success, so only the error statistics are updated.
+    pub(crate) fn record_disagreement(&self, method: &str) {
+        let mut state = self.0.lock().unwrap();
+        state.error_rate = EWMA_ALPHA + (1. - EWMA_ALPHA) * state.error_rate;
+        state.errors += 1;
+        state.last_error = Some(format!("'{method}' response disagreed with the quorum"));
+    }
+
+    pub(crate) fn record_block(&self, block: u64) {
+        let mut state = self.0.lock().unwrap();
+        // Never move back, a reorg of the tip shouldn't be counted as lag.
+        state.last_block = state.last_block.max(Some(block));
+    }
+
+    pub(crate) fn last_block(&self) -> Option<u64> { self.0.lock().unwrap().last_block }
+
+    /// The lower the better. `best_block` is the highest block seen across all nodes of the coin.
+    pub(crate) fn score(&self, best_block: Option<u64>) -> f64 {
+        let state = self.0.lock().unwrap();
+        let lag = block_lag(state.last_block, best_block);
+        state.avg_latency_ms.unwrap_or_default()
+            + state.error_rate * ERROR_PENALTY_MS
+            + lag as f64 * BLOCK_LAG_PENALTY_MS
+    }
+
+    pub(crate) fn report(&self, node: String, best_block: Option<u64>) -> NodeHealthReport {
+        let score = self.score(best_block);
+        let state = self.0.lock().unwrap().clone();
+        NodeHealthReport {
+            node,
+            score,
+            avg_latency_ms: state.avg_latency_ms,
+            error_rate: state.error_rate,
+            last_block: state.last_block,
+            block_lag: block_lag(state.last_block, best_block),
+            requests: state.requests,
+            errors: state.errors,
+            last_error: state.last_error,
+        }
+    }
+}
+
+fn block_lag(last_block: Option<u64>, best_block: Option<u64>) -> u64 {
+    match (last_block, best_block) {
+        (Some(last), Some(best)) => best.saturating_sub(last),
+        // A node that has never reported its height is neither trusted nor penalized.
+        _ => 0,
+    }
+}
+
+/// Returns the highest block seen by any of the given nodes.
+pub(crate) fn best_known_block<'a>(nodes: impl Iterator<Item = &'a NodeHealth>) -> Option<u64> {
+    nodes.filter_map(NodeHealth::last_block).max()
+}
+
+/// Sorts `items` so that the healthiest node goes first. The sort is stable, so equally scored nodes keep
+/// their order.
+pub(crate) fn sort_by_health<T>(items: &mut [T], health: impl Fn(&T) -> &NodeHealth) {
+    let best_block = best_known_block(items.iter().map(&health));
+    items.sort_by(|a, b| {
+        health(a)
+            .score(best_block)
+            .partial_cmp(&health(b).score(best_block))
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Per-node health as returned by the `get_eth_nodes_health` RPC.
+#[derive(Clone, Debug, Serialize)]
+pub struct NodeHealthReport {
+    pub node: String,
+    /// Selection score of the node, the lower the better.
+    pub score: f64,
+    pub avg_latency_ms: Option<f64>,
+    /// Exponential moving average of the failed requests ratio, from 0 to 1.
+    pub error_rate: f64,
+    pub last_block: Option<u64>,
+    pub block_lag: u64,
+    pub requests: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+/// Checks the `rpc_quorum` setting against the number of nodes the coin is connected to.
+/// A quorum of `1` is the same as no quorum at all.
+pub(crate) fn validate_rpc_quorum(quorum: Option<usize>, nodes: usize) -> Result<Option<usize>, String> {
+    match quorum {
+        Some(0) => Err("rpc_quorum must be at least 1".to_string()),
+        Some(quorum) if quorum > nodes => Err(format!(
+            "rpc_quorum {} is greater than the number of available nodes {}",
+            quorum, nodes
+        )),
+        Some(1) | None => Ok(None),
+        Some(quorum) => Ok(Some(quorum)),
+    }
+}
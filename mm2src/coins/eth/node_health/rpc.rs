@@ -0,0 +1,65 @@
+use super::{best_known_block, NodeHealthReport};
+use crate::{lp_coinfind, MmCoinEnum};
+use common::HttpStatusCode;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::mm_error::{MmError, MmResult};
+
+use http::StatusCode;
+
+#[derive(Deserialize)]
+pub struct GetEthNodesHealthRequest {
+    coin: String,
+}
+
+#[derive(Serialize)]
+pub struct GetEthNodesHealthResponse {
+    /// The minimum number of nodes that must agree on swap-critical reads, `None` if the quorum mode is off.
+    rpc_quorum: Option<usize>,
+    best_block: Option<u64>,
+    /// Nodes in the order they are going to be asked, the healthiest first.
+    nodes: Vec<NodeHealthReport>,
+}
+
+#[derive(Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum GetEthNodesHealthError {
+    CoinNotFound,
+    Internal(String),
+    CoinNotSupported,
+}
+
+impl HttpStatusCode for GetEthNodesHealthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            GetEthNodesHealthError::CoinNotFound => StatusCode::NOT_FOUND,
+            GetEthNodesHealthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            GetEthNodesHealthError::CoinNotSupported => StatusCode::NOT_IMPLEMENTED,
+        }
+    }
+}
+
+/// Pings every node of the coin for its current block and returns the rolling health of each of them.
+pub async fn get_eth_nodes_health(
+    ctx: MmArc,
+    req: GetEthNodesHealthRequest,
+) -> MmResult<GetEthNodesHealthResponse, GetEthNodesHealthError> {
+    let coin = match lp_coinfind(&ctx, &req.coin).await {
+        Ok(Some(MmCoinEnum::EthCoin(coin))) => coin,
+        Ok(Some(_)) => return MmError::err(GetEthNodesHealthError::CoinNotSupported),
+        Ok(None) => return MmError::err(GetEthNodesHealthError::CoinNotFound),
+        Err(e) => return MmError::err(GetEthNodesHealthError::Internal(e)),
+    };
+
+    let instances = coin.refresh_nodes_health().await;
+    let best_block = best_known_block(instances.iter().map(|instance| &*instance.health));
+    let nodes = instances
+        .iter()
+        .map(|instance| instance.health.report(instance.web3.transport().node_uri(), best_block))
+        .collect();
+
+    Ok(GetEthNodesHealthResponse {
+        rpc_quorum: coin.rpc_quorum,
+        best_block,
+        nodes,
+    })
+}
@@ -0,0 +1,531 @@
+//! The persistent tracker of the transactions broadcast by an EVM coin, per sender address.
+//!
+//! Nodes learn about our transaction only once it has propagated to their mempool, and they may drop a transaction
+//! with a too low fee at any moment. Relying on `get_addr_nonce` alone makes concurrent sends from the same address
+//! race for the same nonce, so every transaction signed and sent by the coin is saved here until it's mined.
+//! The next nonce follows the tracked transactions that continue the network pending nonce,
+//! which lets several transactions of the same address be in flight at once.
+//! A tracked transaction the nodes still don't know [`PENDING_TX_EXPIRATION`] seconds after the broadcast
+//! is considered dropped from the mempools, and its nonce is reused.
+//! A stuck transaction can be replaced with higher fees or cancelled, see [`rpc`].
+
+#[cfg(not(target_arch = "wasm32"))] mod sql_nonce_storage;
+#[cfg(target_arch = "wasm32")] mod wasm_nonce_storage;
+
+pub mod rpc;
+
+use super::{BytesJson, Eip1559FeePerGas, EthCoin, LegacyGasPrice, PayForGasOption, Web3Instance, Web3Transaction};
+use crate::{CoinWithDerivationMethod, MarketCoinOps};
+use async_trait::async_trait;
+use common::log::{debug, warn};
+use common::now_sec;
+use derive_more::Display;
+use ethcore_transaction::Action;
+use ethereum_types::{Address, H256, U256};
+use futures::compat::Future01CompatExt;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use web3::types::{BlockNumber, TransactionId};
+
+cfg_native! {
+    use sql_nonce_storage::SqlitePendingTxStorage;
+}
+
+cfg_wasm32! {
+    use wasm_nonce_storage::IDBPendingTxStorage;
+}
+
+/// How long (in seconds) a tracked transaction that is not in the nodes' mempools keeps its nonce reserved.
+/// Covers the propagation of the transaction to the nodes.
+pub const PENDING_TX_EXPIRATION: u64 = 600;
+
+pub type PendingTxStorageResult<T> = MmResult<T, PendingTxStorageError>;
+
+#[derive(Debug, Display)]
+pub enum PendingTxStorageError {
+    #[display(fmt = "Error initializing pending transactions storage: {}", _0)]
+    InitializationError(String),
+    #[display(fmt = "Error saving a pending transaction: {}", _0)]
+    ErrorSaving(String),
+    #[display(fmt = "Error loading pending transactions: {}", _0)]
+    ErrorLoading(String),
+    #[display(fmt = "Error deserializing a pending transaction: {}", _0)]
+    ErrorDeserializing(String),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+/// A transaction broadcast by the coin that is not mined yet.
+/// Keeps everything needed to build a replacement of the transaction.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PendingEthTx {
+    pub tx_hash: H256,
+    pub nonce: u64,
+    /// `None` for contract deployments.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub data: BytesJson,
+    pub gas: U256,
+    /// Set for legacy transactions only.
+    pub gas_price: Option<U256>,
+    /// Set for EIP-1559 transactions only.
+    pub max_fee_per_gas: Option<U256>,
+    /// Set for EIP-1559 transactions only.
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub broadcast_at: u64,
+}
+
+impl PendingEthTx {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        tx_hash: H256,
+        nonce: U256,
+        action: &Action,
+        value: U256,
+        data: Vec<u8>,
+        gas: U256,
+        pay_for_gas_option: &PayForGasOption,
+    ) -> PendingEthTx {
+        let to = match action {
+            Action::Call(address) => Some(*address),
+            Action::Create => None,
+        };
+        let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match pay_for_gas_option {
+            PayForGasOption::Legacy(LegacyGasPrice { gas_price }) => (Some(*gas_price), None, None),
+            PayForGasOption::Eip1559(Eip1559FeePerGas {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            }) => (None, Some(*max_fee_per_gas), Some(*max_priority_fee_per_gas)),
+        };
+        PendingEthTx {
+            tx_hash,
+            nonce: nonce.low_u64(),
+            to,
+            value,
+            data: data.into(),
+            gas,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            broadcast_at: now_sec(),
+        }
+    }
+
+    /// Used for the transactions that were not sent by this coin instance, e.g. broadcast with `send_raw_transaction`.
+    pub(crate) fn from_web3_tx(tx: &Web3Transaction) -> PendingEthTx {
+        let (gas_price, max_fee_per_gas, max_priority_fee_per_gas) = match tx.max_fee_per_gas {
+            Some(max_fee_per_gas) => (None, Some(max_fee_per_gas), tx.max_priority_fee_per_gas),
+            None => (tx.gas_price, None, None),
+        };
+        PendingEthTx {
+            tx_hash: tx.hash,
+            nonce: tx.nonce.low_u64(),
+            to: tx.to,
+            value: tx.value,
+            data: tx.input.0.clone().into(),
+            gas: tx.gas,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            broadcast_at: now_sec(),
+        }
+    }
+
+    pub(crate) fn action(&self) -> Action {
+        match self.to {
+            Some(to) => Action::Call(to),
+            None => Action::Create,
+        }
+    }
+
+    pub(crate) fn pay_for_gas_option(&self) -> Option<PayForGasOption> {
+        match (self.gas_price, self.max_fee_per_gas, self.max_priority_fee_per_gas) {
+            (_, Some(max_fee_per_gas), Some(max_priority_fee_per_gas)) => {
+                Some(PayForGasOption::Eip1559(Eip1559FeePerGas {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                }))
+            },
+            (Some(gas_price), _, _) => Some(PayForGasOption::Legacy(LegacyGasPrice { gas_price })),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+pub trait PendingTxStorageOps {
+    async fn init(&self) -> PendingTxStorageResult<()>;
+
+    /// Loads the tracked transactions of `address` ordered by nonce.
+    async fn load_pending_txs(&self, address: Address) -> PendingTxStorageResult<Vec<PendingEthTx>>;
+
+    /// Saves the transaction, replacing the tracked transaction with the same nonce if any.
+    async fn save_pending_tx(&self, address: Address, tx: &PendingEthTx) -> PendingTxStorageResult<()>;
+
+    /// Removes the transactions of `address` with a nonce lower than `nonce`, i.e. the mined ones.
+    async fn remove_txs_below_nonce(&self, address: Address, nonce: u64) -> PendingTxStorageResult<()>;
+}
+
+/// The storage of the pending transactions of a single platform coin and its tokens,
+/// as they share the nonces of the same addresses.
+pub struct PendingTxStorage {
+    inner: Box<dyn PendingTxStorageOps + Send + Sync>,
+}
+
+impl PendingTxStorage {
+    #[cfg(all(not(test), not(target_arch = "wasm32")))]
+    pub fn new_from_ctx(ctx: &MmArc, platform_ticker: String) -> PendingTxStorageResult<Self> {
+        #[cfg(not(feature = "new-db-arch"))]
+        let maybe_sqlite_connection = ctx.sqlite_connection.get();
+        #[cfg(feature = "new-db-arch")]
+        let maybe_sqlite_connection = ctx.global_db_conn.get();
+        let conn = maybe_sqlite_connection.or_mm_err(|| {
+            PendingTxStorageError::InitializationError("PendingTxStorage's SQL DB is not initialized".to_owned())
+        })?;
+        Ok(PendingTxStorage {
+            inner: Box::new(SqlitePendingTxStorage {
+                ticker: platform_ticker,
+                conn: conn.clone(),
+            }),
+        })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new_from_ctx(ctx: &MmArc, platform_ticker: String) -> PendingTxStorageResult<Self> {
+        Ok(PendingTxStorage {
+            inner: Box::new(IDBPendingTxStorage::new(ctx, platform_ticker)),
+        })
+    }
+
+    #[cfg(all(test, not(target_arch = "wasm32")))]
+    pub fn new_from_ctx(ctx: &MmArc, platform_ticker: String) -> PendingTxStorageResult<Self> {
+        use db_common::sqlite::rusqlite::Connection;
+        use std::sync::{Arc, Mutex};
+
+        let conn = ctx
+            .sqlite_connection
+            .get()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(Mutex::new(Connection::open_in_memory().unwrap())));
+
+        Ok(PendingTxStorage {
+            inner: Box::new(SqlitePendingTxStorage {
+                ticker: platform_ticker,
+                conn,
+            }),
+        })
+    }
+}
+
+#[async_trait]
+impl PendingTxStorageOps for PendingTxStorage {
+    async fn init(&self) -> PendingTxStorageResult<()> { self.inner.init().await }
+
+    async fn load_pending_txs(&self, address: Address) -> PendingTxStorageResult<Vec<PendingEthTx>> {
+        self.inner.load_pending_txs(address).await
+    }
+
+    async fn save_pending_tx(&self, address: Address, tx: &PendingEthTx) -> PendingTxStorageResult<()> {
+        self.inner.save_pending_tx(address, tx).await
+    }
+
+    async fn remove_txs_below_nonce(&self, address: Address, nonce: u64) -> PendingTxStorageResult<()> {
+        self.inner.remove_txs_below_nonce(address, nonce).await
+    }
+}
+
+impl EthCoin {
+    async fn pending_tx_storage(&self) -> PendingTxStorageResult<PendingTxStorage> {
+        let ctx =
+            MmArc::from_weak(&self.ctx).or_mm_err(|| PendingTxStorageError::Internal("MmArc is dropped".to_owned()))?;
+        let storage = PendingTxStorage::new_from_ctx(&ctx, self.platform_ticker().to_owned())?;
+        storage.init().await?;
+        Ok(storage)
+    }
+
+    /// Returns the nonce of the next transaction from `address` and the nodes that reported the highest network nonce.
+    /// The caller must hold the address nonce lock until the transaction is broadcast and tracked,
+    /// see [`EthCoin::track_pending_tx`].
+    pub(crate) async fn next_nonce(&self, address: Address) -> Result<(U256, Vec<Web3Instance>), String> {
+        let (network_nonce, web3_instances) = self.clone().get_addr_nonce(address).compat().await?;
+
+        let nonce = match self.load_pending_txs(address).await {
+            Ok(pending_txs) => next_tracked_nonce(&pending_txs, network_nonce, now_sec()),
+            Err(e) => {
+                warn!(
+                    "Error loading pending {} transactions of {:?}: {}",
+                    self.ticker(),
+                    address,
+                    e
+                );
+                network_nonce
+            },
+        };
+
+        if nonce > network_nonce {
+            debug!(
+                "Nodes report {} nonce {} of {:?}, but there are pending transactions up to {}",
+                self.ticker(),
+                network_nonce,
+                address,
+                nonce - 1
+            );
+        }
+        Ok((nonce, web3_instances))
+    }
+
+    /// Loads the tracked transactions of `address` that are not mined yet, ordered by nonce.
+    /// The mined ones are removed from the storage.
+    pub(crate) async fn load_pending_txs(&self, address: Address) -> PendingTxStorageResult<Vec<PendingEthTx>> {
+        let storage = self.pending_tx_storage().await?;
+        let pending_txs = storage.load_pending_txs(address).await?;
+        if pending_txs.is_empty() {
+            return Ok(pending_txs);
+        }
+
+        let mined_nonce = self
+            .transaction_count(address, Some(BlockNumber::Latest))
+            .await
+            .map_to_mm(|e| PendingTxStorageError::Transport(e.to_string()))?
+            .low_u64();
+        storage.remove_txs_below_nonce(address, mined_nonce).await?;
+
+        Ok(pending_txs.into_iter().filter(|tx| tx.nonce >= mined_nonce).collect())
+    }
+
+    /// Saves the broadcast transaction, so its nonce isn't reused until it's mined.
+    /// Replaces the tracked transaction with the same nonce if any.
+    /// Returns `false` if the transaction couldn't be tracked, then the caller has to keep the address nonce lock
+    /// until the nodes report the increased nonce.
+    pub(crate) async fn track_pending_tx(&self, address: Address, tx: &PendingEthTx) -> bool {
+        let result = match self.pending_tx_storage().await {
+            Ok(storage) => storage.save_pending_tx(address, tx).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Error tracking {} transaction {:?}: {}", self.ticker(), tx.tx_hash, e);
+                false
+            },
+        }
+    }
+
+    /// Tracks the transaction broadcast with `send_raw_transaction`, e.g. the one signed by `withdraw`,
+    /// if it's sent from one of my addresses.
+    /// The transaction is requested from the nodes, as they return all the fields needed to replace it.
+    pub(crate) async fn track_raw_tx(&self, tx_hash: H256) {
+        let tx = match self.transaction(TransactionId::Hash(tx_hash)).await {
+            Ok(Some(tx)) => tx,
+            Ok(None) => {
+                warn!("{} transaction {:?} is not found to track it", self.ticker(), tx_hash);
+                return;
+            },
+            Err(e) => {
+                warn!("Error {} on getting {} transaction {:?}", e, self.ticker(), tx_hash);
+                return;
+            },
+        };
+        let sender = match tx.from {
+            Some(sender) => sender,
+            None => return,
+        };
+        match self.all_addresses().await {
+            Ok(my_addresses) if my_addresses.contains(&sender) => {
+                self.track_pending_tx(sender, &PendingEthTx::from_web3_tx(&tx)).await;
+            },
+            Ok(_) => (),
+            Err(e) => warn!("Error getting {} addresses: {}", self.ticker(), e),
+        }
+    }
+}
+
+/// Returns the nonce following the tracked transactions that continue `network_nonce` without gaps.
+/// The transactions the nodes don't know for longer than [`PENDING_TX_EXPIRATION`] are considered dropped,
+/// so their nonces and the following ones are reused.
+fn next_tracked_nonce(pending_txs: &[PendingEthTx], network_nonce: U256, now: u64) -> U256 {
+    let mut next_nonce = network_nonce;
+    for tx in pending_txs {
+        let nonce = U256::from(tx.nonce);
+        // Already known by the nodes.
+        if nonce < next_nonce {
+            continue;
+        }
+        if nonce > next_nonce || tx.broadcast_at + PENDING_TX_EXPIRATION < now {
+            break;
+        }
+        next_nonce = nonce + 1;
+    }
+    next_nonce
+}
+
+#[cfg(any(test, target_arch = "wasm32"))]
+mod pending_tx_storage_tests {
+    use super::*;
+    use mm2_test_helpers::for_tests::mm_ctx_with_custom_db;
+
+    cfg_wasm32! {
+        use wasm_bindgen_test::*;
+
+        wasm_bindgen_test_configure!(run_in_browser);
+    }
+
+    pub(super) fn pending_tx(nonce: u64, hash: u8) -> PendingEthTx {
+        PendingEthTx {
+            tx_hash: H256::repeat_byte(hash),
+            nonce,
+            to: Some(Address::repeat_byte(1)),
+            value: 1000.into(),
+            data: vec![1, 2, 3].into(),
+            gas: 21000.into(),
+            gas_price: None,
+            max_fee_per_gas: Some(2_000_000_000u64.into()),
+            max_priority_fee_per_gas: Some(1_000_000_000u64.into()),
+            broadcast_at: 1700000000,
+        }
+    }
+
+    async fn test_save_load_remove_pending_txs_impl() {
+        let ctx = mm_ctx_with_custom_db();
+        let address = Address::repeat_byte(7);
+        let other_address = Address::repeat_byte(8);
+
+        let storage = PendingTxStorage::new_from_ctx(&ctx, "ETH".to_owned()).unwrap();
+        storage.init().await.unwrap();
+        assert!(storage.load_pending_txs(address).await.unwrap().is_empty());
+
+        storage.save_pending_tx(address, &pending_tx(5, 1)).await.unwrap();
+        storage.save_pending_tx(address, &pending_tx(3, 2)).await.unwrap();
+        storage.save_pending_tx(other_address, &pending_tx(3, 3)).await.unwrap();
+
+        let other_storage = PendingTxStorage::new_from_ctx(&ctx, "BNB".to_owned()).unwrap();
+        other_storage.init().await.unwrap();
+        other_storage.save_pending_tx(address, &pending_tx(1, 4)).await.unwrap();
+
+        let actual = storage.load_pending_txs(address).await.unwrap();
+        assert_eq!(actual, vec![pending_tx(3, 2), pending_tx(5, 1)]);
+
+        // A replacement takes the place of the transaction with the same nonce.
+        storage.save_pending_tx(address, &pending_tx(3, 5)).await.unwrap();
+        let actual = storage.load_pending_txs(address).await.unwrap();
+        assert_eq!(actual, vec![pending_tx(3, 5), pending_tx(5, 1)]);
+
+        storage.remove_txs_below_nonce(address, 4).await.unwrap();
+        let actual = storage.load_pending_txs(address).await.unwrap();
+        assert_eq!(actual, vec![pending_tx(5, 1)]);
+
+        assert_eq!(storage.load_pending_txs(other_address).await.unwrap(), vec![
+            pending_tx(3, 3)
+        ]);
+        assert_eq!(other_storage.load_pending_txs(address).await.unwrap(), vec![
+            pending_tx(1, 4)
+        ]);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_save_load_remove_pending_txs() { common::block_on(test_save_load_remove_pending_txs_impl()) }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn test_save_load_remove_pending_txs() { test_save_load_remove_pending_txs_impl().await }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::pending_tx_storage_tests::pending_tx;
+    use super::*;
+    use crate::eth::for_tests::eth_coin_for_test;
+    use crate::eth::EthCoinType;
+    use common::block_on;
+    use db_common::sqlite::rusqlite::Connection;
+    use mm2_test_helpers::for_tests::ETH_SEPOLIA_CHAIN_ID;
+    use mocktopus::mocking::*;
+    use std::sync::{Arc, Mutex};
+
+    /// The test coin with a persistent pending transactions storage.
+    pub(super) fn eth_coin_with_db() -> (MmArc, EthCoin) {
+        let (ctx, coin) = eth_coin_for_test(EthCoinType::Eth, &["http://localhost:8545"], None, ETH_SEPOLIA_CHAIN_ID);
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(ctx.sqlite_connection.set(Arc::new(Mutex::new(conn))).is_ok());
+        (ctx, coin)
+    }
+
+    fn pending_tx_at(nonce: u64, broadcast_at: u64) -> PendingEthTx {
+        PendingEthTx {
+            broadcast_at,
+            ..pending_tx(nonce, nonce as u8)
+        }
+    }
+
+    #[test]
+    fn test_next_tracked_nonce() {
+        let now = 1700001000;
+        let recent = now - PENDING_TX_EXPIRATION;
+        let expired = recent - 1;
+
+        assert_eq!(next_tracked_nonce(&[], 5.into(), now), 5.into());
+        // The transactions below the network nonce are known by the nodes already.
+        let pending_txs = [pending_tx_at(3, expired), pending_tx_at(4, recent)];
+        assert_eq!(next_tracked_nonce(&pending_txs, 5.into(), now), 5.into());
+
+        let pending_txs = [
+            pending_tx_at(4, expired),
+            pending_tx_at(5, recent),
+            pending_tx_at(6, now),
+            pending_tx_at(8, now),
+        ];
+        // The transaction 8 doesn't continue the nonces, it's dropped or replaced by another wallet.
+        assert_eq!(next_tracked_nonce(&pending_txs, 5.into(), now), 7.into());
+        assert_eq!(next_tracked_nonce(&pending_txs, 7.into(), now), 7.into());
+        assert_eq!(next_tracked_nonce(&pending_txs, 8.into(), now), 9.into());
+
+        // The expired transaction is dropped by the nodes, its nonce is reused even if the next ones are tracked.
+        let pending_txs = [
+            pending_tx_at(5, recent),
+            pending_tx_at(6, expired),
+            pending_tx_at(7, now),
+        ];
+        assert_eq!(next_tracked_nonce(&pending_txs, 5.into(), now), 6.into());
+    }
+
+    #[test]
+    fn test_next_nonce() {
+        EthCoin::get_addr_nonce
+            .mock_safe(|_, _| MockResult::Return(Box::new(futures01::future::ok((5.into(), vec![])))));
+        EthCoin::transaction_count.mock_safe(|_, _, block| {
+            assert_eq!(block, Some(BlockNumber::Latest));
+            MockResult::Return(Box::pin(futures::future::ok(4.into())))
+        });
+        let (_ctx, coin) = eth_coin_with_db();
+        let address = Address::repeat_byte(7);
+        let other_address = Address::repeat_byte(8);
+        let now = now_sec();
+
+        assert_eq!(block_on(coin.next_nonce(address)).unwrap().0, 5.into());
+
+        for nonce in [3, 4, 5, 6] {
+            assert!(block_on(coin.track_pending_tx(address, &pending_tx_at(nonce, now))));
+        }
+        assert!(block_on(coin.track_pending_tx(other_address, &pending_tx_at(9, now))));
+        assert_eq!(block_on(coin.next_nonce(address)).unwrap().0, 7.into());
+        assert_eq!(block_on(coin.next_nonce(other_address)).unwrap().0, 5.into());
+
+        // The mined transaction is not tracked anymore.
+        let pending_nonces: Vec<_> = block_on(coin.load_pending_txs(address))
+            .unwrap()
+            .into_iter()
+            .map(|tx| tx.nonce)
+            .collect();
+        assert_eq!(pending_nonces, vec![4, 5, 6]);
+
+        // The nodes dropped the transaction 6, its replacement reserves the nonce again.
+        let expired = pending_tx_at(6, now - PENDING_TX_EXPIRATION - 1);
+        assert!(block_on(coin.track_pending_tx(address, &expired)));
+        assert_eq!(block_on(coin.next_nonce(address)).unwrap().0, 6.into());
+        assert!(block_on(coin.track_pending_tx(address, &pending_tx_at(6, now))));
+        assert_eq!(block_on(coin.next_nonce(address)).unwrap().0, 7.into());
+    }
+}
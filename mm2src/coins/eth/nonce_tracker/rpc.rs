@@ -0,0 +1,526 @@
+//! RPCs to inspect the pending transactions of an EVM address and to replace a stuck one.
+//!
+//! A transaction is replaced by another one with the same nonce. Nodes accept the replacement only if it pays
+//! at least [`REPLACEMENT_FEE_BUMP_PERCENT`] more for every fee component, so the replacement fee is never lower
+//! than that, even if the current fee estimate is.
+
+use super::PendingEthTx;
+use crate::eth::{tx_builder_with_pay_for_gas_option, BytesJson, Eip1559FeePerGas, EthCoin, LegacyGasPrice,
+                 PayForGasOption, TxType, UnSignedEthTxBuilder};
+use crate::hd_wallet::DisplayAddress;
+use crate::{lp_coinfind, MarketCoinOps, MmCoinEnum, PayForGasParams};
+use common::HttpStatusCode;
+use ethcore_transaction::Action;
+use ethereum_types::{Address, H256, U256};
+use http::StatusCode;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use web3::types::{Bytes, TransactionId};
+
+/// Minimum increase (in percent) of every fee component of a replacement transaction.
+const REPLACEMENT_FEE_BUMP_PERCENT: u64 = 10;
+
+#[derive(Deserialize)]
+pub struct GetEthPendingTransactionsRequest {
+    coin: String,
+}
+
+#[derive(Serialize)]
+pub struct GetEthPendingTransactionsResponse {
+    address: String,
+    /// Transactions sent by this node that are not mined yet, ordered by nonce.
+    pending_txs: Vec<PendingEthTx>,
+}
+
+#[derive(Deserialize)]
+pub struct ReplaceEthTransactionRequest {
+    pub coin: String,
+    tx_hash: H256,
+    /// The fee of the replacement transaction. If not set, the current fee estimate is used,
+    /// but not less than the minimum fee accepted by the nodes.
+    #[serde(default)]
+    fee: Option<PayForGasParams>,
+}
+
+#[derive(Serialize)]
+pub struct ReplaceEthTransactionResponse {
+    tx_hash: H256,
+    tx_hex: BytesJson,
+    nonce: u64,
+    replaced_tx_hash: H256,
+}
+
+#[derive(Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum EthPendingTxError {
+    #[display(fmt = "No such coin {}", _0)]
+    NoSuchCoin(String),
+    #[display(fmt = "Coin {} is not an EVM coin", _0)]
+    CoinNotSupported(String),
+    #[display(fmt = "Transaction {:?} is not found", _0)]
+    TxNotFound(H256),
+    #[display(fmt = "Transaction {:?} is already mined", _0)]
+    TxAlreadyMined(H256),
+    #[display(fmt = "The fee is too low to replace the transaction: {}", _0)]
+    FeeTooLow(String),
+    #[display(fmt = "The transaction can't be replaced: {}", _0)]
+    ReplacementNotAllowed(String),
+    #[display(fmt = "Invalid request: {}", _0)]
+    InvalidRequest(String),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+impl HttpStatusCode for EthPendingTxError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            EthPendingTxError::NoSuchCoin(_) | EthPendingTxError::TxNotFound(_) => StatusCode::NOT_FOUND,
+            EthPendingTxError::CoinNotSupported(_) => StatusCode::NOT_IMPLEMENTED,
+            EthPendingTxError::TxAlreadyMined(_)
+            | EthPendingTxError::FeeTooLow(_)
+            | EthPendingTxError::ReplacementNotAllowed(_)
+            | EthPendingTxError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            EthPendingTxError::Transport(_) => StatusCode::BAD_GATEWAY,
+            EthPendingTxError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+async fn find_eth_coin(ctx: &MmArc, ticker: &str) -> MmResult<EthCoin, EthPendingTxError> {
+    match lp_coinfind(ctx, ticker).await {
+        Ok(Some(MmCoinEnum::EthCoin(coin))) => Ok(coin),
+        Ok(Some(_)) => MmError::err(EthPendingTxError::CoinNotSupported(ticker.to_owned())),
+        Ok(None) => MmError::err(EthPendingTxError::NoSuchCoin(ticker.to_owned())),
+        Err(e) => MmError::err(EthPendingTxError::Internal(e)),
+    }
+}
+
+async fn my_address(coin: &EthCoin) -> MmResult<Address, EthPendingTxError> {
+    coin.derivation_method
+        .single_addr_or_err()
+        .await
+        .mm_err(|e| EthPendingTxError::InvalidRequest(e.to_string()))
+}
+
+/// Returns the tickers of the coins sending transactions from the same address as `ticker`,
+/// i.e. its platform coin and the activated tokens of the platform.
+pub async fn coins_sharing_address(ctx: &MmArc, ticker: &str) -> MmResult<HashSet<String>, EthPendingTxError> {
+    let coin = find_eth_coin(ctx, ticker).await?;
+    let mut coins: HashSet<_> = coin.get_erc_tokens_infos().into_keys().collect();
+    coins.insert(coin.platform_ticker().to_owned());
+    coins.insert(coin.ticker().to_owned());
+    Ok(coins)
+}
+
+/// Returns the transactions sent from the coin address that are not mined yet.
+pub async fn get_eth_pending_transactions(
+    ctx: MmArc,
+    req: GetEthPendingTransactionsRequest,
+) -> MmResult<GetEthPendingTransactionsResponse, EthPendingTxError> {
+    let coin = find_eth_coin(&ctx, &req.coin).await?;
+    let address = my_address(&coin).await?;
+    let pending_txs = coin
+        .load_pending_txs(address)
+        .await
+        .mm_err(|e| EthPendingTxError::Internal(e.to_string()))?;
+
+    Ok(GetEthPendingTransactionsResponse {
+        address: address.display_address(),
+        pending_txs,
+    })
+}
+
+/// Replaces a pending transaction with the same one paying a higher fee.
+pub async fn speed_up_eth_transaction(
+    ctx: MmArc,
+    req: ReplaceEthTransactionRequest,
+) -> MmResult<ReplaceEthTransactionResponse, EthPendingTxError> {
+    let coin = find_eth_coin(&ctx, &req.coin).await?;
+    replace_transaction(&coin, req.tx_hash, req.fee, |pending_tx, _| {
+        (
+            pending_tx.action(),
+            pending_tx.value,
+            pending_tx.data.0.clone(),
+            pending_tx.gas,
+        )
+    })
+    .await
+}
+
+/// Replaces a pending transaction with a 0-value transfer to the sender itself,
+/// so the original transaction is never mined.
+pub async fn cancel_eth_transaction(
+    ctx: MmArc,
+    req: ReplaceEthTransactionRequest,
+) -> MmResult<ReplaceEthTransactionResponse, EthPendingTxError> {
+    let coin = find_eth_coin(&ctx, &req.coin).await?;
+    let gas = U256::from(coin.gas_limit.eth_send_coins);
+    replace_transaction(&coin, req.tx_hash, req.fee, |_, my_address| {
+        (Action::Call(my_address), U256::zero(), Vec::new(), gas)
+    })
+    .await
+}
+
+async fn replace_transaction<F>(
+    coin: &EthCoin,
+    tx_hash: H256,
+    fee: Option<PayForGasParams>,
+    replacement: F,
+) -> MmResult<ReplaceEthTransactionResponse, EthPendingTxError>
+where
+    F: FnOnce(&PendingEthTx, Address) -> (Action, U256, Vec<u8>, U256),
+{
+    let key_pair = coin
+        .priv_key_policy
+        .activated_key_or_err()
+        .mm_err(|e| EthPendingTxError::InvalidRequest(e.to_string()))?;
+    let my_address = my_address(coin).await?;
+
+    let address_lock = coin.get_address_lock(my_address.to_string()).await;
+    let _nonce_lock = address_lock.lock().await;
+
+    let pending_tx = find_pending_tx(coin, my_address, tx_hash).await?;
+    let pay_for_gas_option = replacement_fee(coin, &pending_tx, fee).await?;
+    let (action, value, data, gas) = replacement(&pending_tx, my_address);
+
+    let tx_type = match pay_for_gas_option {
+        PayForGasOption::Eip1559(..) => TxType::Type2,
+        _ => TxType::Legacy,
+    };
+    if !coin.is_tx_type_supported(&tx_type) {
+        return MmError::err(EthPendingTxError::InvalidRequest(
+            "Eth transaction type not supported".to_owned(),
+        ));
+    }
+    let nonce = U256::from(pending_tx.nonce);
+    let tx_builder = UnSignedEthTxBuilder::new(tx_type, nonce, gas, action.clone(), value, data.clone());
+    let tx_builder = tx_builder_with_pay_for_gas_option(coin, tx_builder, &pay_for_gas_option)
+        .mm_err(|e| EthPendingTxError::Internal(e.to_string()))?;
+    let unsigned = tx_builder
+        .build()
+        .map_to_mm(|e| EthPendingTxError::Internal(e.to_string()))?;
+    let signed = unsigned
+        .sign(key_pair.secret(), Some(coin.chain_id))
+        .map_to_mm(|e| EthPendingTxError::Internal(e.to_string()))?;
+
+    let tx_hex = rlp::encode(&signed).to_vec();
+    coin.send_raw_transaction(Bytes(tx_hex.clone()))
+        .await
+        .map_to_mm(|e| EthPendingTxError::Transport(e.to_string()))?;
+
+    let replacement_tx = PendingEthTx::new(signed.tx_hash(), nonce, &action, value, data, gas, &pay_for_gas_option);
+    // The replacement is broadcast already, but its nonce would be reused by the next transaction if not tracked.
+    if !coin.track_pending_tx(my_address, &replacement_tx).await {
+        return MmError::err(EthPendingTxError::Internal(format!(
+            "Replacement transaction {:?} is broadcast, but couldn't be tracked",
+            signed.tx_hash()
+        )));
+    }
+
+    Ok(ReplaceEthTransactionResponse {
+        tx_hash: signed.tx_hash(),
+        tx_hex: tx_hex.into(),
+        nonce: pending_tx.nonce,
+        replaced_tx_hash: pending_tx.tx_hash,
+    })
+}
+
+/// Looks the transaction up among the tracked ones first and on the nodes then,
+/// to replace the transactions broadcast with `send_raw_transaction` or by another wallet too.
+async fn find_pending_tx(
+    coin: &EthCoin,
+    my_address: Address,
+    tx_hash: H256,
+) -> MmResult<PendingEthTx, EthPendingTxError> {
+    let pending_txs = coin
+        .load_pending_txs(my_address)
+        .await
+        .mm_err(|e| EthPendingTxError::Internal(e.to_string()))?;
+    if let Some(tx) = pending_txs.iter().find(|tx| tx.tx_hash == tx_hash) {
+        return Ok(tx.clone());
+    }
+
+    let tx = coin
+        .transaction(TransactionId::Hash(tx_hash))
+        .await
+        .map_to_mm(|e| EthPendingTxError::Transport(e.to_string()))?
+        .or_mm_err(|| EthPendingTxError::TxNotFound(tx_hash))?;
+    if tx.block_number.is_some() {
+        return MmError::err(EthPendingTxError::TxAlreadyMined(tx_hash));
+    }
+    if tx.from != Some(my_address) {
+        return MmError::err(EthPendingTxError::InvalidRequest(format!(
+            "Transaction {:?} is not sent from {}",
+            tx_hash,
+            my_address.display_address()
+        )));
+    }
+
+    // The transaction may have been replaced by us already, then the fee has to be bumped over the replacement's one.
+    let nonce = tx.nonce.low_u64();
+    match pending_txs.into_iter().find(|tracked| tracked.nonce == nonce) {
+        Some(tracked) => Ok(tracked),
+        None => Ok(PendingEthTx::from_web3_tx(&tx)),
+    }
+}
+
+fn bump_fee(fee: U256) -> U256 { fee + (fee * REPLACEMENT_FEE_BUMP_PERCENT + 99) / 100 }
+
+async fn replacement_fee(
+    coin: &EthCoin,
+    pending_tx: &PendingEthTx,
+    requested: Option<PayForGasParams>,
+) -> MmResult<PayForGasOption, EthPendingTxError> {
+    let original = pending_tx.pay_for_gas_option().or_mm_err(|| {
+        EthPendingTxError::Internal(format!("Unknown fee of the transaction {:?}", pending_tx.tx_hash))
+    })?;
+
+    match (original, requested) {
+        (PayForGasOption::Legacy(original), None) => {
+            let gas_price = coin
+                .get_gas_price()
+                .await
+                .mm_err(|e| EthPendingTxError::Transport(e.to_string()))?;
+            Ok(PayForGasOption::Legacy(LegacyGasPrice {
+                gas_price: gas_price.max(bump_fee(original.gas_price)),
+            }))
+        },
+        (PayForGasOption::Eip1559(original), None) => {
+            let estimated = coin
+                .get_eip1559_gas_fee(false)
+                .await
+                .mm_err(|e| EthPendingTxError::Transport(e.to_string()))?;
+            Ok(PayForGasOption::Eip1559(Eip1559FeePerGas {
+                max_fee_per_gas: estimated.high.max_fee_per_gas.max(bump_fee(original.max_fee_per_gas)),
+                max_priority_fee_per_gas: estimated
+                    .high
+                    .max_priority_fee_per_gas
+                    .max(bump_fee(original.max_priority_fee_per_gas)),
+            }))
+        },
+        (original, Some(requested)) => {
+            let requested =
+                PayForGasOption::try_from(requested).mm_err(|e| EthPendingTxError::InvalidRequest(e.to_string()))?;
+            check_replacement_fee(&original, &requested)?;
+            Ok(requested)
+        },
+    }
+}
+
+fn check_replacement_fee(original: &PayForGasOption, requested: &PayForGasOption) -> MmResult<(), EthPendingTxError> {
+    match (original, requested) {
+        (PayForGasOption::Legacy(original), PayForGasOption::Legacy(requested)) => {
+            let min_gas_price = bump_fee(original.gas_price);
+            if requested.gas_price < min_gas_price {
+                return MmError::err(EthPendingTxError::FeeTooLow(format!(
+                    "gas_price must be at least {} wei",
+                    min_gas_price
+                )));
+            }
+            Ok(())
+        },
+        (PayForGasOption::Eip1559(original), PayForGasOption::Eip1559(requested)) => {
+            let min_max_fee_per_gas = bump_fee(original.max_fee_per_gas);
+            let min_max_priority_fee_per_gas = bump_fee(original.max_priority_fee_per_gas);
+            if requested.max_fee_per_gas < min_max_fee_per_gas
+                || requested.max_priority_fee_per_gas < min_max_priority_fee_per_gas
+            {
+                return MmError::err(EthPendingTxError::FeeTooLow(format!(
+                    "max_fee_per_gas must be at least {} wei and max_priority_fee_per_gas at least {} wei",
+                    min_max_fee_per_gas, min_max_priority_fee_per_gas
+                )));
+            }
+            Ok(())
+        },
+        _ => MmError::err(EthPendingTxError::InvalidRequest(
+            "The replacement transaction must have the same tx_type as the original one".to_owned(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pending_tx_storage_tests::pending_tx;
+    use super::super::tests::eth_coin_with_db;
+    use super::*;
+    use crate::eth::{signed_eth_tx_from_bytes, Web3Transaction};
+    use common::block_on;
+    use mm2_number::BigDecimal;
+    use mocktopus::mocking::*;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+
+    /// The replacements broadcast by the tests.
+    static SENT_TXS: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+    fn legacy_pending_tx(nonce: u64, hash: u8) -> PendingEthTx {
+        PendingEthTx {
+            gas_price: Some(10_000_000_000u64.into()),
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            ..pending_tx(nonce, hash)
+        }
+    }
+
+    fn gas_price_gwei(gas_price: &str) -> Option<PayForGasParams> {
+        Some(PayForGasParams::Legacy(crate::LegacyGasPrice {
+            gas_price: BigDecimal::from_str(gas_price).unwrap(),
+        }))
+    }
+
+    #[test]
+    fn test_replace_transaction() {
+        EthCoin::transaction_count.mock_safe(|_, _, _| MockResult::Return(Box::pin(futures::future::ok(4.into()))));
+        EthCoin::transaction.mock_safe(|_, _| MockResult::Return(Box::pin(futures::future::ok(None))));
+        EthCoin::send_raw_transaction.mock_safe(|_, tx| {
+            SENT_TXS.lock().unwrap().push(tx.0.clone());
+            MockResult::Return(Box::pin(futures::future::ok(H256::zero())))
+        });
+        let (_ctx, coin) = eth_coin_with_db();
+        let my_address = block_on(my_address(&coin)).unwrap();
+        let original = legacy_pending_tx(5, 2);
+        assert!(block_on(coin.track_pending_tx(my_address, &legacy_pending_tx(4, 1))));
+        assert!(block_on(coin.track_pending_tx(my_address, &original)));
+
+        let speed_up = |pending_tx: &PendingEthTx, _| {
+            (
+                pending_tx.action(),
+                pending_tx.value,
+                pending_tx.data.0.clone(),
+                pending_tx.gas,
+            )
+        };
+
+        // The nodes require at least 10% higher gas price.
+        let error = block_on(replace_transaction(
+            &coin,
+            original.tx_hash,
+            gas_price_gwei("10.9"),
+            speed_up,
+        ))
+        .unwrap_err();
+        assert!(matches!(error.get_inner(), EthPendingTxError::FeeTooLow(_)));
+        let error = block_on(replace_transaction(
+            &coin,
+            H256::repeat_byte(9),
+            gas_price_gwei("11"),
+            speed_up,
+        ))
+        .unwrap_err();
+        assert!(matches!(error.get_inner(), EthPendingTxError::TxNotFound(_)));
+        assert!(SENT_TXS.lock().unwrap().is_empty());
+
+        let response = block_on(replace_transaction(
+            &coin,
+            original.tx_hash,
+            gas_price_gwei("11"),
+            speed_up,
+        ))
+        .unwrap();
+        assert_eq!(response.nonce, 5);
+        assert_eq!(response.replaced_tx_hash, original.tx_hash);
+        let sent = SENT_TXS.lock().unwrap().pop().unwrap();
+        assert_eq!(sent, response.tx_hex.0);
+        let signed = signed_eth_tx_from_bytes(&sent).unwrap();
+        assert_eq!(signed.tx_hash(), response.tx_hash);
+        assert_eq!(signed.sender(), my_address);
+        assert_eq!(signed.unsigned().nonce(), 5.into());
+        assert_eq!(signed.unsigned().action(), &original.action());
+        assert_eq!(signed.unsigned().value(), original.value);
+        assert_eq!(signed.unsigned().data(), &original.data.0);
+
+        // The replacement takes the place of the original transaction.
+        let replacement = PendingEthTx {
+            tx_hash: response.tx_hash,
+            gas_price: Some(11_000_000_000u64.into()),
+            broadcast_at: 0,
+            ..original.clone()
+        };
+        let pending_txs: Vec<_> = block_on(coin.load_pending_txs(my_address))
+            .unwrap()
+            .into_iter()
+            .map(|tx| PendingEthTx { broadcast_at: 0, ..tx })
+            .collect();
+        assert_eq!(pending_txs, vec![
+            PendingEthTx {
+                broadcast_at: 0,
+                ..legacy_pending_tx(4, 1)
+            },
+            replacement.clone()
+        ]);
+
+        // The replaced transaction is found by its nonce, and the fee is bumped over the replacement's one.
+        EthCoin::transaction.mock_safe(move |_, _| {
+            let tx = Web3Transaction {
+                hash: original.tx_hash,
+                nonce: 5.into(),
+                from: Some(my_address),
+                ..Web3Transaction::default()
+            };
+            MockResult::Return(Box::pin(futures::future::ok(Some(tx))))
+        });
+        let cancel =
+            |_: &PendingEthTx, my_address| (Action::Call(my_address), U256::zero(), Vec::new(), U256::from(21000));
+        let error = block_on(replace_transaction(
+            &coin,
+            replacement.tx_hash,
+            gas_price_gwei("12"),
+            cancel,
+        ))
+        .unwrap_err();
+        assert!(matches!(error.get_inner(), EthPendingTxError::FeeTooLow(_)));
+
+        let response = block_on(replace_transaction(
+            &coin,
+            H256::repeat_byte(2),
+            gas_price_gwei("12.1"),
+            cancel,
+        ))
+        .unwrap();
+        assert_eq!(response.nonce, 5);
+        assert_eq!(response.replaced_tx_hash, replacement.tx_hash);
+        let signed = signed_eth_tx_from_bytes(&SENT_TXS.lock().unwrap().pop().unwrap()).unwrap();
+        assert_eq!(signed.unsigned().nonce(), 5.into());
+        assert_eq!(signed.unsigned().action(), &Action::Call(my_address));
+        assert_eq!(signed.unsigned().value(), U256::zero());
+        assert!(signed.unsigned().data().is_empty());
+    }
+
+    #[test]
+    fn test_bump_fee_is_accepted_by_nodes() {
+        // Nodes require `new_fee >= old_fee * 110 / 100`, the rounding must never make the bump smaller.
+        for fee in [1u64, 9, 10, 1099, 10_000_000_099, 123_456_789_012] {
+            let fee = U256::from(fee);
+            assert!(bump_fee(fee) >= fee * 110 / 100, "fee {}", fee);
+            assert!(bump_fee(fee) > fee, "fee {}", fee);
+        }
+    }
+
+    #[test]
+    fn test_check_replacement_fee() {
+        let original = PayForGasOption::Eip1559(Eip1559FeePerGas {
+            max_fee_per_gas: 100.into(),
+            max_priority_fee_per_gas: 10.into(),
+        });
+        let enough = PayForGasOption::Eip1559(Eip1559FeePerGas {
+            max_fee_per_gas: 110.into(),
+            max_priority_fee_per_gas: 11.into(),
+        });
+        check_replacement_fee(&original, &enough).unwrap();
+
+        let low_priority_fee = PayForGasOption::Eip1559(Eip1559FeePerGas {
+            max_fee_per_gas: 200.into(),
+            max_priority_fee_per_gas: 10.into(),
+        });
+        let error = check_replacement_fee(&original, &low_priority_fee).unwrap_err();
+        assert!(matches!(error.get_inner(), EthPendingTxError::FeeTooLow(_)));
+
+        let legacy = PayForGasOption::Legacy(LegacyGasPrice { gas_price: 1000.into() });
+        let error = check_replacement_fee(&original, &legacy).unwrap_err();
+        assert!(matches!(error.get_inner(), EthPendingTxError::InvalidRequest(_)));
+    }
+}
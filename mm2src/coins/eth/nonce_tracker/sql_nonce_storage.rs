@@ -0,0 +1,116 @@
+use super::{PendingEthTx, PendingTxStorageError, PendingTxStorageOps, PendingTxStorageResult};
+use async_trait::async_trait;
+use common::async_blocking;
+use db_common::sqlite::rusqlite::{params, Connection, Error as SqlError};
+use ethereum_types::Address;
+use mm2_err_handle::prelude::*;
+use std::sync::{Arc, Mutex};
+
+const CREATE_PENDING_TXS_TABLE: &str = "CREATE TABLE IF NOT EXISTS eth_pending_txs (
+    coin VARCHAR(255) NOT NULL,
+    address VARCHAR(255) NOT NULL,
+    nonce INTEGER NOT NULL,
+    tx_hash VARCHAR(255) NOT NULL,
+    details_json TEXT NOT NULL,
+    UNIQUE (coin, address, nonce)
+);";
+
+const INSERT_OR_REPLACE_PENDING_TX: &str =
+    "INSERT OR REPLACE INTO eth_pending_txs (coin, address, nonce, tx_hash, details_json) VALUES (?1, ?2, ?3, ?4, ?5);";
+
+const SELECT_PENDING_TXS: &str =
+    "SELECT details_json FROM eth_pending_txs WHERE coin=?1 AND address=?2 ORDER BY nonce ASC;";
+
+const DELETE_PENDING_TXS_BELOW_NONCE: &str = "DELETE FROM eth_pending_txs WHERE coin=?1 AND address=?2 AND nonce<?3;";
+
+impl From<SqlError> for PendingTxStorageError {
+    fn from(e: SqlError) -> Self {
+        let error = e.to_string();
+        match e {
+            SqlError::FromSqlConversionFailure(_, _, _)
+            | SqlError::IntegralValueOutOfRange(_, _)
+            | SqlError::InvalidColumnIndex(_)
+            | SqlError::InvalidColumnType(_, _, _) => PendingTxStorageError::ErrorDeserializing(error),
+            _ => PendingTxStorageError::Internal(error),
+        }
+    }
+}
+
+fn address_to_string(address: Address) -> String { format!("{:#02x}", address) }
+
+#[derive(Clone)]
+pub(super) struct SqlitePendingTxStorage {
+    pub(super) ticker: String,
+    pub(super) conn: Arc<Mutex<Connection>>,
+}
+
+#[async_trait]
+impl PendingTxStorageOps for SqlitePendingTxStorage {
+    async fn init(&self) -> PendingTxStorageResult<()> {
+        let selfi = self.clone();
+        async_blocking(move || {
+            let conn = selfi.conn.lock().unwrap();
+            conn.execute(CREATE_PENDING_TXS_TABLE, [])
+                .map(|_| ())
+                .map_to_mm(|e| PendingTxStorageError::InitializationError(e.to_string()))
+        })
+        .await
+    }
+
+    async fn load_pending_txs(&self, address: Address) -> PendingTxStorageResult<Vec<PendingEthTx>> {
+        let selfi = self.clone();
+        async_blocking(move || {
+            let conn = selfi.conn.lock().unwrap();
+            let mut statement = conn.prepare(SELECT_PENDING_TXS)?;
+            let rows = statement
+                .query_map(params![selfi.ticker, address_to_string(address)], |row| {
+                    row.get::<_, String>(0)
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            rows.iter()
+                .map(|json| {
+                    serde_json::from_str(json).map_to_mm(|e| PendingTxStorageError::ErrorDeserializing(e.to_string()))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn save_pending_tx(&self, address: Address, tx: &PendingEthTx) -> PendingTxStorageResult<()> {
+        let selfi = self.clone();
+        let details_json =
+            serde_json::to_string(tx).map_to_mm(|e| PendingTxStorageError::ErrorSaving(e.to_string()))?;
+        let nonce = tx.nonce as i64;
+        let tx_hash = format!("{:02x}", tx.tx_hash);
+        async_blocking(move || {
+            let conn = selfi.conn.lock().unwrap();
+            conn.execute(INSERT_OR_REPLACE_PENDING_TX, params![
+                selfi.ticker,
+                address_to_string(address),
+                nonce,
+                tx_hash,
+                details_json
+            ])
+            .map(|_| ())
+            .map_to_mm(|e| PendingTxStorageError::ErrorSaving(e.to_string()))
+        })
+        .await
+    }
+
+    async fn remove_txs_below_nonce(&self, address: Address, nonce: u64) -> PendingTxStorageResult<()> {
+        let selfi = self.clone();
+        let nonce = nonce as i64;
+        async_blocking(move || {
+            let conn = selfi.conn.lock().unwrap();
+            conn.execute(DELETE_PENDING_TXS_BELOW_NONCE, params![
+                selfi.ticker,
+                address_to_string(address),
+                nonce
+            ])
+            .map(|_| ())
+            .map_to_mm(|e| PendingTxStorageError::ErrorSaving(e.to_string()))
+        })
+        .await
+    }
+}
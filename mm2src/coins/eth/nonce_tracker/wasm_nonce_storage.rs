@@ -0,0 +1,164 @@
+use super::{PendingEthTx, PendingTxStorageError, PendingTxStorageOps, PendingTxStorageResult};
+use async_trait::async_trait;
+use ethereum_types::Address;
+use mm2_core::mm_ctx::MmArc;
+use mm2_db::indexed_db::{ConstructibleDb, DbIdentifier, DbInstance, DbLocked, DbTransactionError, DbUpgrader,
+                         IndexedDb, IndexedDbBuilder, InitDbError, InitDbResult, MultiIndex, OnUpgradeResult,
+                         SharedDb, TableSignature};
+use mm2_err_handle::prelude::*;
+
+const DB_VERSION: u32 = 1;
+/// A non-unique index of the `PendingTxTable` table that consists of the following properties:
+/// * coin - platform coin ticker
+/// * address - the sender address
+const COIN_ADDRESS_INDEX: &str = "coin_address";
+/// A **unique** index of the `PendingTxTable` table that consists of the following properties:
+/// * coin - platform coin ticker
+/// * address - the sender address
+/// * nonce - the transaction nonce
+const COIN_ADDRESS_NONCE_INDEX: &str = "coin_address_nonce";
+
+type PendingTxDbLocked<'a> = DbLocked<'a, PendingTxDb>;
+
+impl From<DbTransactionError> for PendingTxStorageError {
+    fn from(e: DbTransactionError) -> Self {
+        let desc = e.to_string();
+        match e {
+            DbTransactionError::ErrorDeserializingItem(_) => PendingTxStorageError::ErrorDeserializing(desc),
+            DbTransactionError::ErrorGettingItems(_) | DbTransactionError::ErrorCountingItems(_) => {
+                PendingTxStorageError::ErrorLoading(desc)
+            },
+            DbTransactionError::ErrorUploadingItem(_) | DbTransactionError::ErrorDeletingItems(_) => {
+                PendingTxStorageError::ErrorSaving(desc)
+            },
+            _ => PendingTxStorageError::Internal(desc),
+        }
+    }
+}
+
+impl From<InitDbError> for PendingTxStorageError {
+    fn from(e: InitDbError) -> Self { PendingTxStorageError::InitializationError(e.to_string()) }
+}
+
+fn address_to_string(address: Address) -> String { format!("{:#02x}", address) }
+
+/// The table has the `coin_address` non-unique multi-index
+/// and the `coin_address_nonce` unique multi-index that consists of `coin`, `address`, `nonce`.
+#[derive(Deserialize, Serialize)]
+struct PendingTxTable {
+    coin: String,
+    address: String,
+    nonce: u64,
+    details: PendingEthTx,
+}
+
+impl TableSignature for PendingTxTable {
+    const TABLE_NAME: &'static str = "eth_pending_txs";
+
+    fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+        if let (0, 1) = (old_version, new_version) {
+            let table = upgrader.create_table(Self::TABLE_NAME)?;
+            table.create_multi_index(COIN_ADDRESS_INDEX, &["coin", "address"], false)?;
+            table.create_multi_index(COIN_ADDRESS_NONCE_INDEX, &["coin", "address", "nonce"], true)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) struct PendingTxDb {
+    inner: IndexedDb,
+}
+
+#[async_trait]
+impl DbInstance for PendingTxDb {
+    const DB_NAME: &'static str = "eth_nonce_tracker";
+
+    async fn init(db_id: DbIdentifier) -> InitDbResult<Self> {
+        let inner = IndexedDbBuilder::new(db_id)
+            .with_version(DB_VERSION)
+            .with_table::<PendingTxTable>()
+            .build()
+            .await?;
+        Ok(PendingTxDb { inner })
+    }
+}
+
+pub(super) struct IDBPendingTxStorage {
+    db: SharedDb<PendingTxDb>,
+    ticker: String,
+}
+
+impl IDBPendingTxStorage {
+    pub(super) fn new(ctx: &MmArc, ticker: String) -> Self {
+        IDBPendingTxStorage {
+            db: ConstructibleDb::new(ctx).into_shared(),
+            ticker,
+        }
+    }
+
+    async fn lock_db(&self) -> PendingTxStorageResult<PendingTxDbLocked<'_>> {
+        self.db.get_or_initialize().await.mm_err(PendingTxStorageError::from)
+    }
+
+    fn coin_address_index(&self, address: Address) -> PendingTxStorageResult<MultiIndex> {
+        Ok(MultiIndex::new(COIN_ADDRESS_INDEX)
+            .with_value(&self.ticker)?
+            .with_value(address_to_string(address))?)
+    }
+}
+
+#[async_trait]
+impl PendingTxStorageOps for IDBPendingTxStorage {
+    async fn init(&self) -> PendingTxStorageResult<()> { Ok(()) }
+
+    async fn load_pending_txs(&self, address: Address) -> PendingTxStorageResult<Vec<PendingEthTx>> {
+        let locked_db = self.lock_db().await?;
+        let transaction = locked_db.inner.transaction().await?;
+        let table = transaction.table::<PendingTxTable>().await?;
+
+        let mut txs: Vec<_> = table
+            .get_items_by_multi_index(self.coin_address_index(address)?)
+            .await?
+            .into_iter()
+            .map(|(_item_id, item)| item.details)
+            .collect();
+        txs.sort_by_key(|tx| tx.nonce);
+        Ok(txs)
+    }
+
+    async fn save_pending_tx(&self, address: Address, tx: &PendingEthTx) -> PendingTxStorageResult<()> {
+        let locked_db = self.lock_db().await?;
+        let transaction = locked_db.inner.transaction().await?;
+        let table = transaction.table::<PendingTxTable>().await?;
+
+        let item = PendingTxTable {
+            coin: self.ticker.clone(),
+            address: address_to_string(address),
+            nonce: tx.nonce,
+            details: tx.clone(),
+        };
+        let index_keys = MultiIndex::new(COIN_ADDRESS_NONCE_INDEX)
+            .with_value(&item.coin)?
+            .with_value(&item.address)?
+            .with_value(item.nonce)?;
+        table.replace_item_by_unique_multi_index(index_keys, &item).await?;
+        Ok(())
+    }
+
+    async fn remove_txs_below_nonce(&self, address: Address, nonce: u64) -> PendingTxStorageResult<()> {
+        let locked_db = self.lock_db().await?;
+        let transaction = locked_db.inner.transaction().await?;
+        let table = transaction.table::<PendingTxTable>().await?;
+
+        let items = table
+            .get_items_by_multi_index(self.coin_address_index(address)?)
+            .await?;
+        for (item_id, item) in items {
+            if item.nonce < nonce {
+                table.delete_item(item_id).await?;
+            }
+        }
+        Ok(())
+    }
+}
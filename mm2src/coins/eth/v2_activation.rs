@@ -196,6 +196,9 @@ pub struct EthActivationV2Request {
     #[serde(default)]
     pub path_to_address: HDPathAccountToAddressId,
     pub gap_limit: Option<u32>,
+    /// The minimum number of nodes that must agree on swap-critical reads. Overrides `rpc_quorum` of the coin config.
+    #[serde(default)]
+    pub rpc_quorum: Option<usize>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -462,6 +465,7 @@ impl EthCoin {
             decimals,
             ticker,
             web3_instances: AsyncMutex::new(self.web3_instances.lock().await.clone()),
+            rpc_quorum: self.rpc_quorum,
             history_sync_state: Mutex::new(self.history_sync_state.lock().unwrap().clone()),
             swap_txfee_policy: Mutex::new(SwapTxFeePolicy::Internal),
             max_eth_tx_type,
@@ -548,6 +552,7 @@ impl EthCoin {
             fallback_swap_contract: self.fallback_swap_contract,
             contract_supports_watchers: self.contract_supports_watchers,
             web3_instances: AsyncMutex::new(self.web3_instances.lock().await.clone()),
+            rpc_quorum: self.rpc_quorum,
             decimals: self.decimals,
             history_sync_state: Mutex::new(self.history_sync_state.lock().unwrap().clone()),
             swap_txfee_policy: Mutex::new(SwapTxFeePolicy::Internal),
@@ -640,6 +645,13 @@ pub async fn eth_coin_from_conf_and_request_v2(
         },
     };
 
+    // param from request should override the config
+    let rpc_quorum = req
+        .rpc_quorum
+        .or_else(|| conf["rpc_quorum"].as_u64().map(|quorum| quorum as usize));
+    let rpc_quorum =
+        validate_rpc_quorum(rpc_quorum, web3_instances.len()).map_to_mm(EthActivationV2Error::InvalidPayload)?;
+
     // param from request should override the config
     let required_confirmations = req
         .required_confirmations
@@ -683,6 +695,7 @@ pub async fn eth_coin_from_conf_and_request_v2(
         decimals: ETH_DECIMALS,
         ticker: ticker.to_string(),
         web3_instances: AsyncMutex::new(web3_instances),
+        rpc_quorum,
         history_sync_state: Mutex::new(HistorySyncState::NotEnabled),
         swap_txfee_policy: Mutex::new(SwapTxFeePolicy::Internal),
         max_eth_tx_type,
@@ -840,6 +853,7 @@ async fn build_web3_instances(
         web3_instances.push(Web3Instance {
             web3,
             is_parity: version.contains("Parity") || version.contains("parity"),
+            health: Default::default(),
         });
     }
 
@@ -936,7 +950,11 @@ async fn build_metamask_transport(
 
     // MetaMask doesn't use Parity nodes. So `MetamaskTransport` doesn't support `parity_nextNonce` RPC.
     // An example of the `web3_clientVersion` RPC - `MetaMask/v10.22.1`.
-    let web3_instances = vec![Web3Instance { web3, is_parity: false }];
+    let web3_instances = vec![Web3Instance {
+        web3,
+        is_parity: false,
+        health: Default::default(),
+    }];
 
     Ok(web3_instances)
 }
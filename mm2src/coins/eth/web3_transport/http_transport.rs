@@ -38,7 +38,7 @@ where
 pub struct HttpTransport {
     id: Arc<AtomicUsize>,
     pub(crate) last_request_failed: Arc<AtomicBool>,
-    node: HttpTransportNode,
+    pub(crate) node: HttpTransportNode,
     event_handlers: Vec<RpcTransportEventHandlerShared>,
     pub(crate) proxy_sign_keypair: Option<Keypair>,
 }
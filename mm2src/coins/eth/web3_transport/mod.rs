@@ -49,6 +49,16 @@ impl Web3Transport {
         }
     }
 
+    /// Returns the address of the node behind this transport, used to identify the node in health reports.
+    pub fn node_uri(&self) -> String {
+        match self {
+            Web3Transport::Http(http) => http.node.uri.to_string(),
+            Web3Transport::Websocket(websocket) => websocket.node.uri.to_string(),
+            #[cfg(target_arch = "wasm32")]
+            Web3Transport::Metamask(_) => "metamask".to_string(),
+        }
+    }
+
     fn set_last_request_failed(&self, val: bool) {
         match self {
             Web3Transport::Http(http) => http.last_request_failed.store(val, Ordering::SeqCst),
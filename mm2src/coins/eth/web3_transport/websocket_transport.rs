@@ -43,7 +43,7 @@ pub(crate) struct WebsocketTransportNode {
 pub struct WebsocketTransport {
     request_id: Arc<AtomicUsize>,
     pub(crate) last_request_failed: Arc<AtomicBool>,
-    node: WebsocketTransportNode,
+    pub(crate) node: WebsocketTransportNode,
     event_handlers: Vec<RpcTransportEventHandlerShared>,
     pub(crate) proxy_sign_keypair: Option<Keypair>,
     controller_channel: Arc<ControllerChannel>,
@@ -241,10 +241,13 @@ use qrc20::{qrc20_coin_with_policy, Qrc20ActivationParams, Qrc20Coin, Qrc20FeeDe
 
 pub mod rpc_command;
 use rpc_command::{get_new_address::{GetNewAddressTaskManager, GetNewAddressTaskManagerShared},
+                  get_token_allowances::{TokenAllowancesTaskManager, TokenAllowancesTaskManagerShared},
                   init_account_balance::{AccountBalanceTaskManager, AccountBalanceTaskManagerShared},
                   init_create_account::{CreateAccountTaskManager, CreateAccountTaskManagerShared},
                   init_scan_for_new_addresses::{ScanAddressesTaskManager, ScanAddressesTaskManagerShared},
-                  init_withdraw::{WithdrawTaskManager, WithdrawTaskManagerShared}};
+                  init_withdraw::{WithdrawTaskManager, WithdrawTaskManagerShared},
+                  multisig::{SignMultisigPsbtTaskManager, SignMultisigPsbtTaskManagerShared},
+                  sign_eth_message::{SignEthMessageTaskManager, SignEthMessageTaskManagerShared}};
 
 pub mod tendermint;
 use tendermint::htlc::CustomTendermintMsgType;
@@ -261,7 +264,8 @@ pub use test_coin::TestCoin;
 pub mod tx_history_storage;
 
 #[cfg(feature = "enable-sia")] pub mod siacoin;
-#[cfg(feature = "enable-sia")] use siacoin::SiaCoin;
+#[cfg(feature = "enable-sia")]
+use siacoin::{SiaCoin, SiaTransaction};
 
 pub mod utxo;
 use utxo::bch::{bch_coin_with_policy, BchActivationRequest, BchCoin};
@@ -270,6 +274,7 @@ use utxo::qtum::{self, qtum_coin_with_policy, Qrc20AddressError, QtumCoin, QtumD
 use utxo::rpc_clients::UtxoRpcError;
 use utxo::slp::SlpToken;
 use utxo::slp::{slp_addr_from_pubkey_str, SlpFeeDetails};
+use utxo::utxo_coin_control::UnspentOutPoint;
 use utxo::utxo_common::{big_decimal_from_sat_unsigned, payment_script, WaitForOutputSpendErr};
 use utxo::utxo_standard::{utxo_standard_coin_with_policy, UtxoStandardCoin};
 use utxo::{swap_proto_v2_scripts, BlockchainNetwork, GenerateTxError, UtxoActivationParams, UtxoFeeDetails, UtxoTx};
@@ -594,6 +599,8 @@ pub enum TransactionEnum {
     CosmosTransaction(CosmosTransaction),
     #[cfg(not(target_arch = "wasm32"))]
     LightningPayment(LightningPayment),
+    #[cfg(feature = "enable-sia")]
+    SiaTransaction(SiaTransaction),
 }
 
 ifrom!(TransactionEnum, UtxoTx);
@@ -601,6 +608,8 @@ ifrom!(TransactionEnum, SignedEthTx);
 ifrom!(TransactionEnum, ZTransaction);
 #[cfg(not(target_arch = "wasm32"))]
 ifrom!(TransactionEnum, LightningPayment);
+#[cfg(feature = "enable-sia")]
+ifrom!(TransactionEnum, SiaTransaction);
 
 impl TransactionEnum {
     #[cfg(not(target_arch = "wasm32"))]
@@ -621,6 +630,8 @@ impl Deref for TransactionEnum {
             TransactionEnum::CosmosTransaction(ref t) => t,
             #[cfg(not(target_arch = "wasm32"))]
             TransactionEnum::LightningPayment(ref p) => p,
+            #[cfg(feature = "enable-sia")]
+            TransactionEnum::SiaTransaction(ref t) => t,
         }
     }
 }
@@ -1355,6 +1366,82 @@ pub trait WatcherOps {
             "get_maker_watcher_reward is not implemented".to_string(),
         ))?
     }
+
+    /// Whether swap v2 HTLCs of this coin can be guarded by watchers.
+    fn is_supported_by_watchers_v2(&self) -> bool { false }
+
+    /// Signs the timelock refund of a swap v2 HTLC (taker funding, taker payment or maker payment).
+    /// A watcher broadcasts it on behalf of the offline owner once the timelock expires,
+    /// the part of the refunded amount exceeding the miner fee is left to the watcher as a reward.
+    async fn sign_refund_for_watcher_v2(&self, _args: RefundPaymentArgs<'_>) -> TransactionResult {
+        Err(TransactionErr::Plain(
+            "sign_refund_for_watcher_v2 is not implemented".to_string(),
+        ))
+    }
+
+    /// Signs the taker's spend of maker payment v2 without the maker secret.
+    /// A watcher inserts the secret once the maker reveals it by spending the taker payment.
+    async fn sign_maker_payment_spend_for_watcher_v2(
+        &self,
+        _args: MakerPaymentSpendForWatcherArgs<'_>,
+    ) -> TransactionResult {
+        Err(TransactionErr::Plain(
+            "sign_maker_payment_spend_for_watcher_v2 is not implemented".to_string(),
+        ))
+    }
+
+    /// Validates the swap v2 `preimage` received by a watcher before it starts guarding the payment:
+    /// `payment_tx` must be on chain and the preimage must spend it with a valid signature
+    /// on the timelock refund path (`is_refund`) or on the maker secret path.
+    async fn watcher_validate_preimage_v2(
+        &self,
+        _payment_tx: &[u8],
+        _preimage: &[u8],
+        _is_refund: bool,
+    ) -> ValidatePaymentResult<()> {
+        MmError::err(ValidatePaymentError::InternalError(
+            "watcher_validate_preimage_v2 is not implemented".to_string(),
+        ))
+    }
+
+    /// Completes the swap v2 `preimage` signed by [`WatcherOps::sign_refund_for_watcher_v2`] or
+    /// [`WatcherOps::sign_maker_payment_spend_for_watcher_v2`] and broadcasts it.
+    /// The `secret` is required to complete the maker payment spend.
+    async fn send_preimage_as_watcher_v2(
+        &self,
+        _payment_tx: &[u8],
+        _preimage: &[u8],
+        _secret: Option<&[u8]>,
+    ) -> TransactionResult {
+        Err(TransactionErr::Plain(
+            "send_preimage_as_watcher_v2 is not implemented".to_string(),
+        ))
+    }
+
+    /// Searches for a transaction spending the swap v2 HTLC `payment_tx`.
+    async fn watcher_search_for_payment_spend_v2(
+        &self,
+        _payment_tx: &[u8],
+        _search_from_block: u64,
+    ) -> Result<Option<TransactionEnum>, String> {
+        Err("watcher_search_for_payment_spend_v2 is not implemented".to_string())
+    }
+}
+
+/// Helper struct wrapping arguments for [WatcherOps::sign_maker_payment_spend_for_watcher_v2]
+pub struct MakerPaymentSpendForWatcherArgs<'a> {
+    /// Maker payment tx
+    pub maker_payment_tx: &'a [u8],
+    /// Maker will be able to refund the payment after this timestamp
+    pub time_lock: u64,
+    /// The hash of the secret generated by taker, this is used for immediate refund
+    pub taker_secret_hash: &'a [u8],
+    /// The hash of the secret generated by maker, taker needs it to spend the payment
+    pub maker_secret_hash: &'a [u8],
+    /// Maker's HTLC pubkey
+    pub maker_pub: &'a [u8],
+    /// Unique data of specific swap
+    pub swap_unique_data: &'a [u8],
 }
 
 /// Helper struct wrapping arguments for [TakerCoinSwapOpsV2::send_taker_funding]
@@ -2212,6 +2299,8 @@ pub trait GetWithdrawSenderAddress {
 pub struct WithdrawRequest {
     coin: String,
     from: Option<WithdrawFrom>,
+    /// Can be omitted if [`WithdrawRequest::outputs`] are set.
+    #[serde(default)]
     to: String,
     #[serde(default)]
     amount: BigDecimal,
@@ -2221,12 +2310,31 @@ pub struct WithdrawRequest {
     memo: Option<String>,
     /// Tendermint specific field used for manually providing the IBC channel IDs.
     ibc_source_channel: Option<String>,
+    /// UTXO specific field used to pay to multiple recipients within a single transaction.
+    /// `to`, `amount` and `max` must not be set along with this field.
+    #[serde(default)]
+    outputs: Vec<WithdrawOutput>,
+    /// UTXO specific field used to choose the unspent outputs that must be spent by the transaction.
+    /// Other spendable outputs are added only if these don't cover the amount and the fee,
+    /// or are not added at all if `max` is set.
+    #[serde(default)]
+    inputs: Vec<UnspentOutPoint>,
+    /// UTXO specific field used to choose the unspent outputs that must not be spent by the transaction.
+    #[serde(default)]
+    exclude_inputs: Vec<UnspentOutPoint>,
     /// Currently, this flag is used by ETH/ERC20 coins activated with MetaMask **only**.
     #[cfg(target_arch = "wasm32")]
     #[serde(default)]
     broadcast: bool,
 }
 
+/// A single recipient of the multi-output [`WithdrawRequest`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct WithdrawOutput {
+    pub to: String,
+    pub amount: BigDecimal,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
 pub enum StakingDetails {
@@ -2318,6 +2426,23 @@ impl WithdrawRequest {
             ..Default::default()
         }
     }
+
+    /// Rejects the UTXO specific fields, so they aren't silently ignored by the coins that don't support them.
+    pub fn ensure_no_utxo_specific_fields(&self, ticker: &str) -> MmResult<(), WithdrawError> {
+        if !self.outputs.is_empty() {
+            return MmError::err(WithdrawError::InvalidOutputs(format!(
+                "Multiple outputs are not supported by {}",
+                ticker
+            )));
+        }
+        if !self.inputs.is_empty() || !self.exclude_inputs.is_empty() {
+            return MmError::err(WithdrawError::InvalidInputs(format!(
+                "Coin control is not supported by {}",
+                ticker
+            )));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -3071,6 +3196,14 @@ pub enum WithdrawError {
     CoinDoesntSupportInitWithdraw {
         coin: String,
     },
+    #[display(fmt = "'{}' coin doesn't support PSBT", coin)]
+    CoinDoesntSupportPsbt {
+        coin: String,
+    },
+    #[display(fmt = "'{}' coin isn't activated as a multisig wallet", coin)]
+    CoinIsNotMultisig {
+        coin: String,
+    },
     #[display(
         fmt = "Not enough {} to withdraw: available {}, required at least {}",
         coin,
@@ -3106,6 +3239,10 @@ pub enum WithdrawError {
     InvalidFeePolicy(String),
     #[display(fmt = "Invalid memo field: {}", _0)]
     InvalidMemo(String),
+    #[display(fmt = "Invalid outputs: {}", _0)]
+    InvalidOutputs(String),
+    #[display(fmt = "Invalid inputs: {}", _0)]
+    InvalidInputs(String),
     #[display(fmt = "No such coin {}", coin)]
     NoSuchCoin {
         coin: String,
@@ -3196,6 +3333,8 @@ impl HttpStatusCode for WithdrawError {
             WithdrawError::NoSuchCoin { .. } => StatusCode::NOT_FOUND,
             WithdrawError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
             WithdrawError::CoinDoesntSupportInitWithdraw { .. }
+            | WithdrawError::CoinDoesntSupportPsbt { .. }
+            | WithdrawError::CoinIsNotMultisig { .. }
             | WithdrawError::NotSufficientBalance { .. }
             | WithdrawError::NotSufficientPlatformBalanceForFee { .. }
             | WithdrawError::ZeroBalanceToWithdrawMax
@@ -3203,6 +3342,8 @@ impl HttpStatusCode for WithdrawError {
             | WithdrawError::InvalidAddress(_)
             | WithdrawError::InvalidFeePolicy(_)
             | WithdrawError::InvalidMemo(_)
+            | WithdrawError::InvalidOutputs(_)
+            | WithdrawError::InvalidInputs(_)
             | WithdrawError::FromAddressNotFound
             | WithdrawError::UnexpectedFromAddress(_)
             | WithdrawError::UnknownAccount { .. }
@@ -3698,11 +3839,14 @@ impl MmCoinEnum {
     /// Otherwise, the function will default to `SecretHashAlgo::DHASH160`, which may not be correct for the new coin.
     pub fn secret_hash_algo_v2(&self) -> SecretHashAlgo {
         match self {
-            MmCoinEnum::Tendermint(_) | MmCoinEnum::TendermintToken(_) | MmCoinEnum::EthCoin(_) => {
-                SecretHashAlgo::SHA256
-            },
+            MmCoinEnum::Tendermint(_)
+            | MmCoinEnum::TendermintToken(_)
+            | MmCoinEnum::EthCoin(_)
+            | MmCoinEnum::Qrc20Coin(_) => SecretHashAlgo::SHA256,
             #[cfg(not(target_arch = "wasm32"))]
             MmCoinEnum::LightningCoin(_) => SecretHashAlgo::SHA256,
+            #[cfg(feature = "enable-sia")]
+            MmCoinEnum::SiaCoin(_) => SecretHashAlgo::SHA256,
             _ => SecretHashAlgo::DHASH160,
         }
     }
@@ -3973,6 +4117,9 @@ pub struct CoinsContext {
     get_new_address_manager: GetNewAddressTaskManagerShared,
     platform_coin_tokens: PaMutex<HashMap<String, HashSet<String>>>,
     scan_addresses_manager: ScanAddressesTaskManagerShared,
+    sign_eth_message_task_manager: SignEthMessageTaskManagerShared,
+    sign_multisig_psbt_task_manager: SignMultisigPsbtTaskManagerShared,
+    token_allowances_task_manager: TokenAllowancesTaskManagerShared,
     withdraw_task_manager: WithdrawTaskManagerShared,
     #[cfg(target_arch = "wasm32")]
     tx_history_db: SharedDb<TxHistoryDb>,
@@ -3997,6 +4144,11 @@ impl CoinsContext {
                 create_account_manager: CreateAccountTaskManager::new_shared(ctx.event_stream_manager.clone()),
                 get_new_address_manager: GetNewAddressTaskManager::new_shared(ctx.event_stream_manager.clone()),
                 scan_addresses_manager: ScanAddressesTaskManager::new_shared(ctx.event_stream_manager.clone()),
+                sign_eth_message_task_manager: SignEthMessageTaskManager::new_shared(ctx.event_stream_manager.clone()),
+                sign_multisig_psbt_task_manager: SignMultisigPsbtTaskManager::new_shared(
+                    ctx.event_stream_manager.clone(),
+                ),
+                token_allowances_task_manager: TokenAllowancesTaskManager::new_shared(ctx.event_stream_manager.clone()),
                 withdraw_task_manager: WithdrawTaskManager::new_shared(ctx.event_stream_manager.clone()),
                 #[cfg(target_arch = "wasm32")]
                 tx_history_db: ConstructibleDb::new(ctx).into_shared(),
@@ -6042,6 +6194,31 @@ mod tests {
         assert!(matches!(Some(coin), _found));
     }
 
+    #[test]
+    fn test_withdraw_request_utxo_specific_fields() {
+        let req: WithdrawRequest = json::from_value(json!({"coin": "ETH", "to": "0x0", "amount": 1})).unwrap();
+        req.ensure_no_utxo_specific_fields("ETH").unwrap();
+
+        let req: WithdrawRequest = json::from_value(json!({
+            "coin": "ETH",
+            "outputs": [{"to": "0x0", "amount": 1}],
+        }))
+        .unwrap();
+        let err = req.ensure_no_utxo_specific_fields("ETH").unwrap_err().into_inner();
+        assert!(matches!(err, WithdrawError::InvalidOutputs(_)), "{:?}", err);
+
+        let tx_hash = "0000000000000000000000000000000000000000000000000000000000000001";
+        let req: WithdrawRequest = json::from_value(json!({
+            "coin": "ETH",
+            "to": "0x0",
+            "amount": 1,
+            "exclude_inputs": [{"tx_hash": tx_hash, "index": 0}],
+        }))
+        .unwrap();
+        let err = req.ensure_no_utxo_specific_fields("ETH").unwrap_err().into_inner();
+        assert!(matches!(err, WithdrawError::InvalidInputs(_)), "{:?}", err);
+    }
+
     #[test]
     fn test_dex_fee_amount() {
         let base = "BTC";
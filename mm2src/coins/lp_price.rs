@@ -82,6 +82,9 @@ pub enum Provider {
     Forex,
     #[serde(rename = "livecoinwatch")]
     LiveCoinWatch,
+    /// The price is aggregated from the custom price sources configured by the user.
+    #[serde(rename = "custom")]
+    Custom,
     #[cfg(any(test, feature = "for-tests"))]
     #[serde(rename = "testcoin")]
     TestCoin,
@@ -76,6 +76,13 @@ pub trait TxHistoryStorage: Send + Sync + 'static {
     /// Gets the highest block_height from the selected wallet's history
     async fn get_highest_block_height(&self, wallet_id: &WalletId) -> Result<Option<u32>, MmError<Self::Error>>;
 
+    /// Gets the block height the selected wallet's history has been scanned up to,
+    /// for the coins that don't find the history by the addresses but scan the chain.
+    async fn get_scanned_block_height(&self, wallet_id: &WalletId) -> Result<Option<u64>, MmError<Self::Error>>;
+
+    /// Sets the block height the selected wallet's history has been scanned up to.
+    async fn set_scanned_block_height(&self, wallet_id: &WalletId, height: u64) -> Result<(), MmError<Self::Error>>;
+
     /// Returns whether the history contains unconfirmed transactions.
     async fn history_contains_unconfirmed_txes(
         &self,
@@ -376,6 +383,9 @@ pub async fn my_tx_history_v2_rpc(
         MmCoinEnum::QtumCoin(qtum) => my_tx_history_v2_impl(ctx, &qtum, request).await,
         MmCoinEnum::Tendermint(tendermint) => my_tx_history_v2_impl(ctx, &tendermint, request).await,
         MmCoinEnum::TendermintToken(tendermint_token) => my_tx_history_v2_impl(ctx, &tendermint_token, request).await,
+        MmCoinEnum::EthCoin(eth) => my_tx_history_v2_impl(ctx, &eth, request).await,
+        #[cfg(feature = "enable-sia")]
+        MmCoinEnum::SiaCoin(sia) => my_tx_history_v2_impl(ctx, &sia, request).await,
         other => MmError::err(MyTxHistoryErrorV2::NotSupportedFor(other.ticker().to_owned())),
     }
 }
@@ -17,15 +17,18 @@ use crate::utxo::{qtum, ActualTxFee, AdditionalTxData, AddrFromStrError, Broadca
                   UtxoFromLegacyReqErr, UtxoTx, UtxoTxBroadcastOps, UtxoTxGenerationOps, VerboseTransactionFrom,
                   UTXO_LOCK};
 use crate::{BalanceError, BalanceFut, CheckIfMyPaymentSentArgs, CoinBalance, ConfirmPaymentInput, DexFee, Eip1559Ops,
-            FeeApproxStage, FoundSwapTxSpend, HistorySyncState, IguanaPrivKey, MarketCoinOps, MmCoin,
+            FeeApproxStage, FoundSwapTxSpend, HistorySyncState, IguanaPrivKey, MarketCoinOps, MmCoin, MmCoinEnum,
             NegotiateSwapContractAddrErr, PrivKeyBuildPolicy, PrivKeyPolicyNotAllowed, RawTransactionFut,
-            RawTransactionRequest, RawTransactionResult, RefundPaymentArgs, SearchForSwapTxSpendInput,
-            SendPaymentArgs, SignRawTransactionRequest, SignatureResult, SpendPaymentArgs, SwapOps, SwapTxFeePolicy,
-            TradeFee, TradePreimageError, TradePreimageFut, TradePreimageResult, TradePreimageValue, TransactionData,
-            TransactionDetails, TransactionEnum, TransactionErr, TransactionResult, TransactionType, TxMarshalingErr,
+            RawTransactionRequest, RawTransactionResult, RefundPaymentArgs, RewardTarget, SearchForSwapTxSpendInput,
+            SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SignRawTransactionRequest, SignatureResult,
+            SpendPaymentArgs, SwapOps, SwapTxFeePolicy, TradeFee, TradePreimageError, TradePreimageFut,
+            TradePreimageResult, TradePreimageValue, TransactionData, TransactionDetails, TransactionEnum,
+            TransactionErr, TransactionFut, TransactionResult, TransactionType, TxMarshalingErr,
             UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr,
-            ValidatePaymentInput, VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WeakSpawner, WithdrawError,
-            WithdrawFee, WithdrawFut, WithdrawRequest, WithdrawResult};
+            ValidatePaymentFut, ValidatePaymentInput, ValidateWatcherSpendInput, VerificationResult,
+            WaitForHTLCTxSpendArgs, WatcherOps, WatcherReward, WatcherRewardError, WatcherSearchForSwapTxSpendInput,
+            WatcherValidatePaymentInput, WatcherValidateTakerFeeInput, WeakSpawner, WithdrawError, WithdrawFee,
+            WithdrawFut, WithdrawRequest, WithdrawResult};
 use async_trait::async_trait;
 use bitcrypto::{dhash160, sha256};
 use chain::TransactionOutput;
@@ -64,6 +67,7 @@ mod history;
 pub mod rpc_clients;
 pub mod script_pubkey;
 mod swap;
+mod swap_v2;
 
 /// Qtum amount is always 0 for the QRC20 UTXO outputs,
 /// because we should pay only a fee in Qtum to send the QRC20 transaction.
@@ -123,10 +127,20 @@ impl Qrc20GenTxError {
     }
 }
 
+/// Addresses of the EtomicSwap v2 contracts deployed on the Qtum network.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Qrc20SwapV2Contracts {
+    pub maker_swap_v2_contract: H160,
+    pub taker_swap_v2_contract: H160,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Qrc20ActivationParams {
     swap_contract_address: H160,
     fallback_swap_contract: Option<H160>,
+    /// Required to participate in the trading protocol v2 (TPU) swaps.
+    #[serde(default)]
+    swap_v2_contracts: Option<Qrc20SwapV2Contracts>,
     #[serde(flatten)]
     utxo_params: UtxoActivationParams,
 }
@@ -135,6 +149,7 @@ pub struct Qrc20ActivationParams {
 pub enum Qrc20FromLegacyReqErr {
     InvalidSwapContractAddr(json::Error),
     InvalidFallbackSwapContract(json::Error),
+    InvalidSwapV2Contracts(json::Error),
     InvalidUtxoParams(UtxoFromLegacyReqErr),
 }
 
@@ -148,10 +163,13 @@ impl Qrc20ActivationParams {
             .map_to_mm(Qrc20FromLegacyReqErr::InvalidSwapContractAddr)?;
         let fallback_swap_contract = json::from_value(req["fallback_swap_contract"].clone())
             .map_to_mm(Qrc20FromLegacyReqErr::InvalidFallbackSwapContract)?;
+        let swap_v2_contracts = json::from_value(req["swap_v2_contracts"].clone())
+            .map_to_mm(Qrc20FromLegacyReqErr::InvalidSwapV2Contracts)?;
         let utxo_params = UtxoActivationParams::from_legacy_req(req)?;
         Ok(Qrc20ActivationParams {
             swap_contract_address,
             fallback_swap_contract,
+            swap_v2_contracts,
             utxo_params,
         })
     }
@@ -297,6 +315,7 @@ impl<'a> UtxoCoinBuilder for Qrc20CoinBuilder<'a> {
             contract_address: self.token_contract_address,
             swap_contract_address: self.activation_params.swap_contract_address,
             fallback_swap_contract: self.activation_params.fallback_swap_contract,
+            swap_v2_contracts: self.activation_params.swap_v2_contracts,
         };
         Ok(Qrc20Coin(Arc::new(inner)))
     }
@@ -342,6 +361,7 @@ pub struct Qrc20CoinFields {
     pub contract_address: H160,
     pub swap_contract_address: H160,
     pub fallback_swap_contract: Option<H160>,
+    pub swap_v2_contracts: Option<Qrc20SwapV2Contracts>,
 }
 
 #[derive(Clone)]
@@ -376,12 +396,15 @@ impl From<ContractCallOutput> for TransactionOutput {
 }
 
 /// Functions of ERC20/EtomicSwap smart contracts that may change the blockchain state.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum MutContractCallType {
     Transfer,
     Erc20Payment,
     ReceiverSpend,
     SenderRefund,
+    Erc20PaymentReward,
+    ReceiverSpendReward,
+    SenderRefundReward,
 }
 
 impl MutContractCallType {
@@ -391,6 +414,9 @@ impl MutContractCallType {
             MutContractCallType::Erc20Payment => "erc20Payment",
             MutContractCallType::ReceiverSpend => "receiverSpend",
             MutContractCallType::SenderRefund => "senderRefund",
+            MutContractCallType::Erc20PaymentReward => "erc20PaymentReward",
+            MutContractCallType::ReceiverSpendReward => "receiverSpendReward",
+            MutContractCallType::SenderRefundReward => "senderRefundReward",
         }
     }
 
@@ -399,10 +425,36 @@ impl MutContractCallType {
             MutContractCallType::Transfer => eth::ERC20_CONTRACT.function(self.as_function_name()).unwrap(),
             MutContractCallType::Erc20Payment
             | MutContractCallType::ReceiverSpend
-            | MutContractCallType::SenderRefund => eth::SWAP_CONTRACT.function(self.as_function_name()).unwrap(),
+            | MutContractCallType::SenderRefund
+            | MutContractCallType::Erc20PaymentReward
+            | MutContractCallType::ReceiverSpendReward
+            | MutContractCallType::SenderRefundReward => eth::SWAP_CONTRACT.function(self.as_function_name()).unwrap(),
+        }
+    }
+
+    /// Returns the `*Reward` variant of the swap contract call if `watcher_reward` is set.
+    /// The `*Reward` functions let a watcher spend or refund the payment on behalf of the swap side.
+    fn with_watcher_reward(self, watcher_reward: bool) -> MutContractCallType {
+        match (self, watcher_reward) {
+            (MutContractCallType::Erc20Payment, true) => MutContractCallType::Erc20PaymentReward,
+            (MutContractCallType::ReceiverSpend, true) => MutContractCallType::ReceiverSpendReward,
+            (MutContractCallType::SenderRefund, true) => MutContractCallType::SenderRefundReward,
+            (call_type, _) => call_type,
         }
     }
 
+    /// Returns the swap contract call without the watcher reward args.
+    fn without_watcher_reward(self) -> MutContractCallType {
+        match self {
+            MutContractCallType::Erc20PaymentReward => MutContractCallType::Erc20Payment,
+            MutContractCallType::ReceiverSpendReward => MutContractCallType::ReceiverSpend,
+            MutContractCallType::SenderRefundReward => MutContractCallType::SenderRefund,
+            call_type => call_type,
+        }
+    }
+
+    fn is_watcher_reward(&self) -> bool { *self != self.without_watcher_reward() }
+
     pub fn from_script_pubkey(script: &[u8]) -> Result<Option<MutContractCallType>, String> {
         lazy_static! {
             static ref TRANSFER_SHORT_SIGN: [u8; 4] =
@@ -413,6 +465,18 @@ impl MutContractCallType {
                 eth::SWAP_CONTRACT.function("receiverSpend").unwrap().short_signature();
             static ref SENDER_REFUND_SHORT_SIGN: [u8; 4] =
                 eth::SWAP_CONTRACT.function("senderRefund").unwrap().short_signature();
+            static ref ERC20_PAYMENT_REWARD_SHORT_SIGN: [u8; 4] = eth::SWAP_CONTRACT
+                .function("erc20PaymentReward")
+                .unwrap()
+                .short_signature();
+            static ref RECEIVER_SPEND_REWARD_SHORT_SIGN: [u8; 4] = eth::SWAP_CONTRACT
+                .function("receiverSpendReward")
+                .unwrap()
+                .short_signature();
+            static ref SENDER_REFUND_REWARD_SHORT_SIGN: [u8; 4] = eth::SWAP_CONTRACT
+                .function("senderRefundReward")
+                .unwrap()
+                .short_signature();
         }
 
         if script.len() < 4 {
@@ -431,6 +495,15 @@ impl MutContractCallType {
         if script.starts_with(SENDER_REFUND_SHORT_SIGN.as_ref()) {
             return Ok(Some(MutContractCallType::SenderRefund));
         }
+        if script.starts_with(ERC20_PAYMENT_REWARD_SHORT_SIGN.as_ref()) {
+            return Ok(Some(MutContractCallType::Erc20PaymentReward));
+        }
+        if script.starts_with(RECEIVER_SPEND_REWARD_SHORT_SIGN.as_ref()) {
+            return Ok(Some(MutContractCallType::ReceiverSpendReward));
+        }
+        if script.starts_with(SENDER_REFUND_REWARD_SHORT_SIGN.as_ref()) {
+            return Ok(Some(MutContractCallType::SenderRefundReward));
+        }
         Ok(None)
     }
 
@@ -771,8 +844,16 @@ impl SwapOps for Qrc20Coin {
         let secret_hash = Vec::from(maker_payment_args.secret_hash);
         let swap_contract_address = try_tx_s!(maker_payment_args.swap_contract_address.try_to_address());
 
-        self.send_hash_time_locked_payment(id, value, time_lock, secret_hash, taker_addr, swap_contract_address)
-            .await
+        self.send_hash_time_locked_payment(
+            id,
+            value,
+            time_lock,
+            secret_hash,
+            taker_addr,
+            swap_contract_address,
+            maker_payment_args.watcher_reward.is_some(),
+        )
+        .await
     }
 
     #[inline]
@@ -783,8 +864,16 @@ impl SwapOps for Qrc20Coin {
         let value = try_tx_s!(wei_from_big_decimal(&taker_payment_args.amount, self.utxo.decimals));
         let secret_hash = Vec::from(taker_payment_args.secret_hash);
         let swap_contract_address = try_tx_s!(taker_payment_args.swap_contract_address.try_to_address());
-        self.send_hash_time_locked_payment(id, value, time_lock, secret_hash, maker_addr, swap_contract_address)
-            .await
+        self.send_hash_time_locked_payment(
+            id,
+            value,
+            time_lock,
+            secret_hash,
+            maker_addr,
+            swap_contract_address,
+            taker_payment_args.watcher_reward.is_some(),
+        )
+        .await
     }
 
     #[inline]
@@ -890,6 +979,7 @@ impl SwapOps for Qrc20Coin {
             input.secret_hash,
             input.amount,
             swap_contract_address,
+            input.watcher_reward.is_some(),
         )
         .await
     }
@@ -916,6 +1006,7 @@ impl SwapOps for Qrc20Coin {
             input.secret_hash,
             input.amount,
             swap_contract_address,
+            input.watcher_reward.is_some(),
         )
         .await
     }
@@ -1019,10 +1110,127 @@ impl SwapOps for Qrc20Coin {
     fn validate_other_pubkey(&self, raw_pubkey: &[u8]) -> MmResult<(), ValidateOtherPubKeyErr> {
         utxo_common::validate_other_pubkey(raw_pubkey)
     }
+
+    /// A watcher can spend or refund only the payments sent by `erc20PaymentReward`,
+    /// and they are sent only if the swaps use the watcher rewards, like it's done for ETH/ERC20.
+    fn is_supported_by_watchers(&self) -> bool { std::env::var("USE_WATCHER_REWARD").is_ok() }
 }
 
+/// Watchers spend and refund the `erc20PaymentReward` payments through `receiverSpendReward` and `senderRefundReward`,
+/// these functions can be called by anyone on behalf of the payment receiver or sender.
+/// The watcher pays the gas by itself, so the maker payment spend and the taker payment refund "preimages"
+/// are the payments themselves.
 #[async_trait]
-impl WatcherOps for Qrc20Coin {}
+impl WatcherOps for Qrc20Coin {
+    fn send_maker_payment_spend_preimage(&self, input: SendMakerPaymentSpendPreimageInput) -> TransactionFut {
+        let payment_tx: UtxoTx = try_tx_fus!(deserialize(input.preimage).map_err(|e| ERRL!("{:?}", e)));
+        let taker_addr = try_tx_fus!(self.contract_address_from_raw_pubkey(input.taker_pub));
+        let secret = input.secret.to_vec();
+        let coin = self.clone();
+        let fut = async move {
+            coin.watcher_spends_hash_time_locked_payment(payment_tx, secret, taker_addr)
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn send_taker_payment_refund_preimage(&self, watcher_refunds_payment_args: RefundPaymentArgs) -> TransactionFut {
+        let payment_tx: UtxoTx =
+            try_tx_fus!(deserialize(watcher_refunds_payment_args.payment_tx).map_err(|e| ERRL!("{:?}", e)));
+        let taker_addr = try_tx_fus!(self.contract_address_from_raw_pubkey(watcher_refunds_payment_args.other_pubkey));
+        let coin = self.clone();
+        let fut = async move {
+            coin.watcher_refunds_hash_time_locked_payment(payment_tx, taker_addr)
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn create_taker_payment_refund_preimage(
+        &self,
+        taker_payment_tx: &[u8],
+        _time_lock: u64,
+        _maker_pub: &[u8],
+        _secret_hash: &[u8],
+        _swap_contract_address: &Option<BytesJson>,
+        _swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        let payment_tx: UtxoTx = try_tx_fus!(deserialize(taker_payment_tx).map_err(|e| ERRL!("{:?}", e)));
+        Box::new(futures01::future::ok(payment_tx.into()))
+    }
+
+    fn create_maker_payment_spend_preimage(
+        &self,
+        maker_payment_tx: &[u8],
+        _time_lock: u64,
+        _maker_pub: &[u8],
+        _secret_hash: &[u8],
+        _swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        let payment_tx: UtxoTx = try_tx_fus!(deserialize(maker_payment_tx).map_err(|e| ERRL!("{:?}", e)));
+        Box::new(futures01::future::ok(payment_tx.into()))
+    }
+
+    fn watcher_validate_taker_fee(&self, input: WatcherValidateTakerFeeInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move { coin.watcher_validate_taker_fee_impl(input).await };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move { coin.watcher_validate_taker_payment_impl(input).await };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move { coin.validate_watcher_spend_impl(input).await };
+        Box::new(fut.boxed().compat())
+    }
+
+    async fn watcher_search_for_swap_tx_spend(
+        &self,
+        input: WatcherSearchForSwapTxSpendInput<'_>,
+    ) -> Result<Option<FoundSwapTxSpend>, String> {
+        let tx: UtxoTx = try_s!(deserialize(input.tx).map_err(|e| ERRL!("{:?}", e)));
+
+        self.search_for_swap_tx_spend(input.time_lock, input.secret_hash, tx, input.search_from_block)
+            .await
+    }
+
+    /// QRC20 payments don't reward the watchers, see [`swap::WatcherRewardArgs::no_reward`].
+    async fn get_taker_watcher_reward(
+        &self,
+        _other_coin: &MmCoinEnum,
+        _coin_amount: Option<BigDecimal>,
+        _other_coin_amount: Option<BigDecimal>,
+        _reward_amount: Option<BigDecimal>,
+        _wait_until: u64,
+    ) -> Result<WatcherReward, MmError<WatcherRewardError>> {
+        Ok(qrc20_watcher_reward())
+    }
+
+    /// QRC20 payments don't reward the watchers, see [`swap::WatcherRewardArgs::no_reward`].
+    async fn get_maker_watcher_reward(
+        &self,
+        _other_coin: &MmCoinEnum,
+        _reward_amount: Option<BigDecimal>,
+        _wait_until: u64,
+    ) -> Result<Option<WatcherReward>, MmError<WatcherRewardError>> {
+        Ok(Some(qrc20_watcher_reward()))
+    }
+}
+
+/// The watcher reward making the payment be sent by `erc20PaymentReward` without rewarding the watcher.
+fn qrc20_watcher_reward() -> WatcherReward {
+    WatcherReward {
+        amount: BigDecimal::from(0),
+        is_exact_amount: true,
+        reward_target: RewardTarget::None,
+        send_contract_reward_on_spend: false,
+    }
+}
 
 #[async_trait]
 impl MarketCoinOps for Qrc20Coin {
@@ -1228,6 +1436,7 @@ impl MmCoin for Qrc20Coin {
                     secret_hash.clone(),
                     receiver_addr,
                     self.swap_contract_address,
+                    None,
                 )
                 .await?;
             self.preimage_trade_fee_required_to_send_outputs(erc20_payment_outputs, &stage)
@@ -1352,6 +1561,18 @@ pub fn qrc20_swap_id(time_lock: u32, secret_hash: &[u8]) -> Vec<u8> {
     sha256(&input).to_vec()
 }
 
+/// Generates the swap id used by the EtomicSwap v2 contracts.
+/// Unlike [`qrc20_swap_id`], the `time_lock` is encoded as `u64`,
+/// the same as [`crate::eth::EthCoin::etomic_swap_id_v2`] does.
+pub fn qrc20_swap_id_v2(time_lock: u64, secret_hash: &[u8]) -> Vec<u8> {
+    let timelock_bytes = time_lock.to_le_bytes();
+    let mut input = Vec::with_capacity(timelock_bytes.len() + secret_hash.len());
+
+    input.extend_from_slice(&timelock_bytes);
+    input.extend_from_slice(secret_hash);
+    sha256(&input).to_vec()
+}
+
 pub fn contract_addr_into_rpc_format(address: &H160) -> H160Json { H160Json::from(address.0) }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
@@ -1369,6 +1590,7 @@ pub struct Qrc20FeeDetails {
 }
 
 async fn qrc20_withdraw(coin: Qrc20Coin, req: WithdrawRequest) -> WithdrawResult {
+    req.ensure_no_utxo_specific_fields(coin.ticker())?;
     let to_addr = UtxoAddress::from_legacyaddress(&req.to, &coin.as_ref().conf.address_prefixes)
         .map_to_mm(WithdrawError::InvalidAddress)?;
     let conf = &coin.utxo.conf;
@@ -782,12 +782,10 @@ fn is_transferred_from_contract(script_pubkey: &Script) -> bool {
             return false;
         },
     };
-    match call_type {
-        MutContractCallType::Transfer => false,
-        MutContractCallType::Erc20Payment => false,
-        MutContractCallType::ReceiverSpend => true,
-        MutContractCallType::SenderRefund => true,
-    }
+    matches!(
+        call_type.without_watcher_reward(),
+        MutContractCallType::ReceiverSpend | MutContractCallType::SenderRefund
+    )
 }
 
 fn is_transferred_to_contract(script_pubkey: &Script) -> bool {
@@ -806,12 +804,7 @@ fn is_transferred_to_contract(script_pubkey: &Script) -> bool {
             return false;
         },
     };
-    match call_type {
-        MutContractCallType::Transfer => false,
-        MutContractCallType::Erc20Payment => true,
-        MutContractCallType::ReceiverSpend => false,
-        MutContractCallType::SenderRefund => false,
-    }
+    call_type.without_watcher_reward() == MutContractCallType::Erc20Payment
 }
 
 fn is_transfer_event_log(log: &LogEntry) -> bool {
@@ -1150,3 +1150,156 @@ fn test_send_contract_calls_recoverable_tx() {
         discriminant(&TransactionErr::TxRecoverable(tx, String::new()))
     );
 }
+
+#[test]
+fn test_qrc20_activation_params_swap_v2_contracts() {
+    let req = json!({
+        "method": "electrum",
+        "servers": tqtum_electrums(),
+        "swap_contract_address": "0xba8b71f3544b93e2f681f996da519a98ace0107a",
+    });
+    let params = Qrc20ActivationParams::from_legacy_req(&req).unwrap();
+    assert!(params.swap_v2_contracts.is_none());
+
+    let req = json!({
+        "method": "electrum",
+        "servers": tqtum_electrums(),
+        "swap_contract_address": "0xba8b71f3544b93e2f681f996da519a98ace0107a",
+        "swap_v2_contracts": {
+            "maker_swap_v2_contract": "0x2a2f7e9a6ae9c9f5e5e5b0a8a3b1f8e2e4c6d8a0",
+            "taker_swap_v2_contract": "0x3b3f8e9a6ae9c9f5e5e5b0a8a3b1f8e2e4c6d8a1",
+        },
+    });
+    let params = Qrc20ActivationParams::from_legacy_req(&req).unwrap();
+    let contracts = params.swap_v2_contracts.unwrap();
+    assert_eq!(
+        contracts.maker_swap_v2_contract,
+        H160::from_str("0x2a2f7e9a6ae9c9f5e5e5b0a8a3b1f8e2e4c6d8a0").unwrap()
+    );
+    assert_eq!(
+        contracts.taker_swap_v2_contract,
+        H160::from_str("0x3b3f8e9a6ae9c9f5e5e5b0a8a3b1f8e2e4c6d8a1").unwrap()
+    );
+
+    let req = json!({
+        "method": "electrum",
+        "servers": tqtum_electrums(),
+        "swap_contract_address": "0xba8b71f3544b93e2f681f996da519a98ace0107a",
+        "swap_v2_contracts": "invalid",
+    });
+    let err = Qrc20ActivationParams::from_legacy_req(&req).unwrap_err().into_inner();
+    assert!(matches!(err, Qrc20FromLegacyReqErr::InvalidSwapV2Contracts(_)));
+}
+
+#[test]
+fn test_qrc20_swap_id_v2() {
+    let secret_hash = [1; 32];
+    let time_lock = 1_700_000_000u64;
+    let mut input = time_lock.to_le_bytes().to_vec();
+    input.extend_from_slice(&secret_hash);
+    assert_eq!(qrc20_swap_id_v2(time_lock, &secret_hash), sha256(&input).to_vec());
+    // the v2 id must differ from the legacy one, since the time lock is encoded as u64
+    assert_ne!(
+        qrc20_swap_id_v2(time_lock, &secret_hash),
+        qrc20_swap_id(time_lock as u32, &secret_hash)
+    );
+}
+
+#[test]
+fn test_swap_v2_contract_call_params() {
+    use super::swap_v2::{swap_v2_contract_call_output, swap_v2_contract_call_params};
+
+    let taker_swap_v2_contract = H160::from_str("0x3b3f8e9a6ae9c9f5e5e5b0a8a3b1f8e2e4c6d8a1").unwrap();
+    let other_contract = H160::from_str("0xd362e096e873eb7907e205fadc6175c6fec7bc44").unwrap();
+    let maker_secret = [2; 32];
+    let params = vec![
+        Token::FixedBytes(qrc20_swap_id_v2(1_700_000_000, &sha256(&maker_secret).to_vec())),
+        Token::Uint(100_000_000.into()),
+        Token::Uint(1_000_000.into()),
+        Token::Address(H160::from_str("0x783cf0be521101942da509846ea476e683aad832").unwrap()),
+        Token::FixedBytes(vec![1; 32]),
+        Token::FixedBytes(maker_secret.to_vec()),
+        Token::Address(other_contract),
+    ];
+    let spend_func = eth::TAKER_SWAP_V2.function("spendTakerPayment").unwrap();
+    let approve_func = eth::TAKER_SWAP_V2.function("takerPaymentApprove").unwrap();
+
+    let mut tx = UtxoTx::default();
+    // the same call to another contract must be skipped
+    tx.outputs.push(
+        swap_v2_contract_call_output(&other_contract, spend_func, &params)
+            .unwrap()
+            .into(),
+    );
+    tx.outputs.push(
+        swap_v2_contract_call_output(&taker_swap_v2_contract, spend_func, &params)
+            .unwrap()
+            .into(),
+    );
+
+    let decoded = swap_v2_contract_call_params(&tx, &taker_swap_v2_contract, spend_func).unwrap();
+    assert_eq!(decoded, params);
+
+    swap_v2_contract_call_params(&tx, &taker_swap_v2_contract, approve_func).unwrap_err();
+}
+
+#[test]
+fn test_receiver_spend_reward_call_details() {
+    use super::script_pubkey::extract_contract_call_from_script;
+    use super::swap::{find_swap_contract_call_with_swap_id, receiver_spend_call_details_from_script_pubkey,
+                      swap_contract_call_output, watcher_reward_args_from_tokens, WatcherRewardArgs};
+
+    let swap_contract_address = H160::from_str("0xba8b71f3544b93e2f681f996da519a98ace0107a").unwrap();
+    let token_address = H160::from_str("0xd362e096e873eb7907e205fadc6175c6fec7bc44").unwrap();
+    let sender = H160::from_str("0x783cf0be521101942da509846ea476e683aad832").unwrap();
+    let receiver = H160::from_str("0x3b3f8e9a6ae9c9f5e5e5b0a8a3b1f8e2e4c6d8a1").unwrap();
+    let secret = [1; 32];
+    let swap_id = qrc20_swap_id(1_700_000_000, dhash160(&secret).as_slice());
+
+    let mut tokens = vec![
+        Token::FixedBytes(swap_id.clone()),
+        Token::Uint(100_000_000.into()),
+        Token::FixedBytes(secret.to_vec()),
+        Token::Address(token_address),
+        Token::Address(sender),
+        Token::Address(receiver),
+    ];
+    tokens.extend(WatcherRewardArgs::no_reward().to_tokens());
+    let function = MutContractCallType::ReceiverSpendReward.as_function();
+    let params = function.encode_input(&tokens).unwrap();
+    let output = swap_contract_call_output(&params, &swap_contract_address).unwrap();
+
+    let script_pubkey: Script = output.script_pubkey.clone().into();
+    let contract_call_bytes = extract_contract_call_from_script(&script_pubkey).unwrap();
+    assert_eq!(
+        MutContractCallType::from_script_pubkey(&contract_call_bytes).unwrap(),
+        Some(MutContractCallType::ReceiverSpendReward)
+    );
+    let mut reward_tokens = eth::decode_contract_call(function, &contract_call_bytes)
+        .unwrap()
+        .into_iter()
+        .skip(6);
+    assert_eq!(
+        watcher_reward_args_from_tokens(&mut reward_tokens).unwrap(),
+        WatcherRewardArgs::no_reward()
+    );
+
+    // the secret must be extracted from the spend sent by a watcher as well
+    let details = receiver_spend_call_details_from_script_pubkey(&script_pubkey).unwrap();
+    assert_eq!(details.swap_id, swap_id);
+    assert_eq!(details.value, U256::from(100_000_000));
+    assert_eq!(details.secret, secret);
+    assert_eq!(details.token_address, token_address);
+    assert_eq!(details.sender, sender);
+
+    let mut tx = UtxoTx::default();
+    tx.outputs.push(output.into());
+    assert_eq!(
+        find_swap_contract_call_with_swap_id(MutContractCallType::ReceiverSpend, &tx, &swap_id),
+        Some(0)
+    );
+    assert_eq!(
+        find_swap_contract_call_with_swap_id(MutContractCallType::SenderRefund, &tx, &swap_id),
+        None
+    );
+}
@@ -162,6 +162,10 @@ pub enum ViewContractCallType {
     Decimals,
     /// EtomicSwap function.
     Payments,
+    /// EtomicSwapTakerV2 function.
+    TakerPayments,
+    /// EtomicSwapMakerV2 function.
+    MakerPayments,
 }
 
 impl ViewContractCallType {
@@ -171,6 +175,8 @@ impl ViewContractCallType {
             ViewContractCallType::Allowance => "allowance",
             ViewContractCallType::Decimals => "decimals",
             ViewContractCallType::Payments => "payments",
+            ViewContractCallType::TakerPayments => "takerPayments",
+            ViewContractCallType::MakerPayments => "makerPayments",
         }
     }
 
@@ -180,6 +186,8 @@ impl ViewContractCallType {
                 eth::ERC20_CONTRACT.function(self.as_function_name()).unwrap()
             },
             ViewContractCallType::Payments => eth::SWAP_CONTRACT.function(self.as_function_name()).unwrap(),
+            ViewContractCallType::TakerPayments => eth::TAKER_SWAP_V2.function(self.as_function_name()).unwrap(),
+            ViewContractCallType::MakerPayments => eth::MAKER_SWAP_V2.function(self.as_function_name()).unwrap(),
         }
     }
 }
@@ -1,6 +1,7 @@
 use super::history::TransferHistoryBuilder;
 use super::*;
 use crate::eth::{decode_contract_call, PaymentState};
+use crate::WatcherSpendType;
 use bitcrypto::ripemd160;
 use common::now_sec;
 use script_pubkey::{extract_contract_addr_from_script, extract_contract_call_from_script, is_contract_call};
@@ -19,6 +20,37 @@ pub struct Erc20PaymentDetails {
     pub timelock: U256,
     /// Contract call bytes extracted from [`TransactionOutput::script_pubkey`] using `extract_contract_call_from_script`.
     pub contract_call_bytes: Vec<u8>,
+    /// The watcher reward args if the payment was sent by `erc20PaymentReward`.
+    pub watcher_reward: Option<WatcherRewardArgs>,
+}
+
+/// Watcher reward args of the `erc20PaymentReward` call.
+/// The payment can be spent or refunded only by `receiverSpendReward` or `senderRefundReward` with the same args.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WatcherRewardArgs {
+    pub reward_target: U256,
+    pub sends_contract_reward_on_spend: bool,
+    pub reward_amount: U256,
+}
+
+impl WatcherRewardArgs {
+    /// QRC20 payments don't reward the watchers, a watcher pays the gas of the spend or refund by itself.
+    /// The reward variant of the payment is used only to let a watcher act on behalf of the swap side.
+    pub fn no_reward() -> WatcherRewardArgs {
+        WatcherRewardArgs {
+            reward_target: U256::from(RewardTarget::None as u8),
+            sends_contract_reward_on_spend: false,
+            reward_amount: U256::zero(),
+        }
+    }
+
+    pub(super) fn to_tokens(&self) -> [Token; 3] {
+        [
+            Token::Uint(self.reward_target),
+            Token::Bool(self.sends_contract_reward_on_spend),
+            Token::Uint(self.reward_amount),
+        ]
+    }
 }
 
 /// `receiverSpend` call details consist of values obtained from [`TransactionOutput::script_pubkey`].
@@ -40,6 +72,7 @@ impl Qrc20Coin {
         secret_hash: Vec<u8>,
         receiver_addr: H160,
         swap_contract_address: H160,
+        watcher_reward: bool,
     ) -> Result<TransactionEnum, TransactionErr> {
         let secret_hash = if secret_hash.len() == 32 {
             ripemd160(&secret_hash).to_vec()
@@ -64,6 +97,7 @@ impl Qrc20Coin {
                 secret_hash,
                 receiver_addr,
                 swap_contract_address,
+                watcher_reward.then(WatcherRewardArgs::no_reward),
             )
             .await
         );
@@ -78,7 +112,12 @@ impl Qrc20Coin {
         secret: Vec<u8>,
     ) -> Result<TransactionEnum, TransactionErr> {
         let Erc20PaymentDetails {
-            swap_id, value, sender, ..
+            swap_id,
+            value,
+            sender,
+            receiver,
+            watcher_reward,
+            ..
         } = try_tx_s!(self.erc20_payment_details_from_tx(&payment_tx).await);
 
         let status = try_tx_s!(self.payment_status(&swap_contract_address, swap_id.clone()).await);
@@ -86,8 +125,18 @@ impl Qrc20Coin {
             return TX_PLAIN_ERR!("Payment state is not PAYMENT_STATE_SENT, got {}", status);
         }
 
-        let spend_output =
-            try_tx_s!(self.receiver_spend_output(&swap_contract_address, swap_id, value, secret, sender));
+        let spend_output = match watcher_reward {
+            Some(reward) => try_tx_s!(self.receiver_spend_reward_output(
+                &swap_contract_address,
+                swap_id,
+                value,
+                secret,
+                sender,
+                receiver,
+                &reward
+            )),
+            None => try_tx_s!(self.receiver_spend_output(&swap_contract_address, swap_id, value, secret, sender)),
+        };
         self.send_contract_calls(vec![spend_output]).await
     }
 
@@ -99,8 +148,10 @@ impl Qrc20Coin {
         let Erc20PaymentDetails {
             swap_id,
             value,
+            sender,
             receiver,
             secret_hash,
+            watcher_reward,
             ..
         } = try_tx_s!(self.erc20_payment_details_from_tx(&payment_tx).await);
 
@@ -109,8 +160,18 @@ impl Qrc20Coin {
             return TX_PLAIN_ERR!("Payment state is not PAYMENT_STATE_SENT, got {}", status);
         }
 
-        let refund_output =
-            try_tx_s!(self.sender_refund_output(&swap_contract_address, swap_id, value, secret_hash, receiver));
+        let refund_output = match watcher_reward {
+            Some(reward) => try_tx_s!(self.sender_refund_reward_output(
+                &swap_contract_address,
+                swap_id,
+                value,
+                secret_hash,
+                sender,
+                receiver,
+                &reward
+            )),
+            None => try_tx_s!(self.sender_refund_output(&swap_contract_address, swap_id, value, secret_hash, receiver)),
+        };
         self.send_contract_calls(vec![refund_output]).await
     }
 
@@ -122,6 +183,7 @@ impl Qrc20Coin {
         secret_hash: Vec<u8>,
         amount: BigDecimal,
         expected_swap_contract_address: H160,
+        watcher_reward: bool,
     ) -> Result<(), MmError<ValidatePaymentError>> {
         let expected_swap_id = qrc20_swap_id(time_lock, &secret_hash);
         let status = self
@@ -145,6 +207,7 @@ impl Qrc20Coin {
                 time_lock,
                 &secret_hash,
                 expected_receiver,
+                watcher_reward.then(WatcherRewardArgs::no_reward).as_ref(),
             )?
         };
         let erc20_payment = self
@@ -153,7 +216,10 @@ impl Qrc20Coin {
             .map_to_mm(ValidatePaymentError::TxDeserializationError)?;
         if erc20_payment.contract_call_bytes != expected_call_bytes {
             return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
-                "Unexpected 'erc20Payment' contract call bytes: {:?}",
+                "Unexpected '{}' contract call bytes: {:?}",
+                MutContractCallType::Erc20Payment
+                    .with_watcher_reward(watcher_reward)
+                    .as_function_name(),
                 erc20_payment.contract_call_bytes
             )));
         }
@@ -175,6 +241,296 @@ impl Qrc20Coin {
         Ok(())
     }
 
+    /// Spends the `erc20PaymentReward` payment on behalf of its receiver revealing the `secret`.
+    /// The tokens are transferred to the receiver while the gas is paid by the watcher.
+    pub async fn watcher_spends_hash_time_locked_payment(
+        &self,
+        payment_tx: UtxoTx,
+        secret: Vec<u8>,
+        expected_receiver: H160,
+    ) -> Result<TransactionEnum, TransactionErr> {
+        let Erc20PaymentDetails {
+            swap_id,
+            value,
+            swap_contract_address,
+            sender,
+            receiver,
+            watcher_reward,
+            ..
+        } = try_tx_s!(self.erc20_payment_details_from_tx(&payment_tx).await);
+        let watcher_reward = match watcher_reward {
+            Some(reward) => reward,
+            None => return TX_PLAIN_ERR!("'erc20Payment' can be spent by its receiver only"),
+        };
+        if receiver != expected_receiver {
+            return TX_PLAIN_ERR!(
+                "Payment receiver {:?} is invalid, expected {:?}",
+                receiver,
+                expected_receiver
+            );
+        }
+
+        let status = try_tx_s!(self.payment_status(&swap_contract_address, swap_id.clone()).await);
+        if status != U256::from(PaymentState::Sent as u8) {
+            return TX_PLAIN_ERR!("Payment state is not PAYMENT_STATE_SENT, got {}", status);
+        }
+
+        let spend_output = try_tx_s!(self.receiver_spend_reward_output(
+            &swap_contract_address,
+            swap_id,
+            value,
+            secret,
+            sender,
+            receiver,
+            &watcher_reward
+        ));
+        self.send_contract_calls(vec![spend_output]).await
+    }
+
+    /// Refunds the `erc20PaymentReward` payment on behalf of its sender once the locktime expires.
+    /// The tokens are transferred back to the sender while the gas is paid by the watcher.
+    pub async fn watcher_refunds_hash_time_locked_payment(
+        &self,
+        payment_tx: UtxoTx,
+        expected_sender: H160,
+    ) -> Result<TransactionEnum, TransactionErr> {
+        let Erc20PaymentDetails {
+            swap_id,
+            value,
+            swap_contract_address,
+            sender,
+            receiver,
+            secret_hash,
+            watcher_reward,
+            ..
+        } = try_tx_s!(self.erc20_payment_details_from_tx(&payment_tx).await);
+        let watcher_reward = match watcher_reward {
+            Some(reward) => reward,
+            None => return TX_PLAIN_ERR!("'erc20Payment' can be refunded by its sender only"),
+        };
+        if sender != expected_sender {
+            return TX_PLAIN_ERR!("Payment sender {:?} is invalid, expected {:?}", sender, expected_sender);
+        }
+
+        let status = try_tx_s!(self.payment_status(&swap_contract_address, swap_id.clone()).await);
+        if status != U256::from(PaymentState::Sent as u8) {
+            return TX_PLAIN_ERR!("Payment state is not PAYMENT_STATE_SENT, got {}", status);
+        }
+
+        let refund_output = try_tx_s!(self.sender_refund_reward_output(
+            &swap_contract_address,
+            swap_id,
+            value,
+            secret_hash,
+            sender,
+            receiver,
+            &watcher_reward
+        ));
+        self.send_contract_calls(vec![refund_output]).await
+    }
+
+    /// Validates the dex fee sent by the taker before a watcher starts guarding the swap.
+    /// The watcher doesn't know the traded amount, so the dex fee value isn't checked.
+    pub async fn watcher_validate_taker_fee_impl(
+        &self,
+        input: WatcherValidateTakerFeeInput,
+    ) -> Result<(), MmError<ValidatePaymentError>> {
+        let fee_tx_hash: [u8; 32] = input.taker_fee_hash.as_slice().try_into().map_to_mm(|_| {
+            ValidatePaymentError::InternalError(format!(
+                "Invalid taker_fee_hash length: expected 32 bytes, got {} bytes",
+                input.taker_fee_hash.len()
+            ))
+        })?;
+        let fee_tx_hash = H256Json::from(fee_tx_hash);
+        let verbose_tx = self
+            .utxo
+            .rpc_client
+            .get_verbose_transaction(&fee_tx_hash)
+            .compat()
+            .await?;
+        let fee_tx: UtxoTx = deserialize(verbose_tx.hex.as_slice())?;
+
+        if !check_all_utxo_inputs_signed_by_pub(&fee_tx, &input.sender_pubkey)? {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                "Taker fee does not belong to the verified public key".to_string(),
+            ));
+        }
+
+        if now_sec_u32().saturating_sub(fee_tx.lock_time) > input.lock_duration as u32 {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Taker fee {:?} is too old",
+                fee_tx_hash
+            )));
+        }
+
+        let fee_addr = self
+            .contract_address_from_raw_pubkey(self.dex_pubkey())
+            .map_to_mm(ValidatePaymentError::WrongPaymentTx)?;
+        self.validate_fee_impl(fee_tx_hash, fee_addr, U256::zero(), input.min_block_number)
+            .await
+            .map_to_mm(ValidatePaymentError::WrongPaymentTx)
+    }
+
+    /// Validates the taker payment before a watcher starts guarding it.
+    /// The payment has to be sent by `erc20PaymentReward`, otherwise the watcher won't be able to refund it.
+    /// The refund "preimage" of a QRC20 payment is the payment itself,
+    /// see [`WatcherOps::create_taker_payment_refund_preimage`].
+    pub async fn watcher_validate_taker_payment_impl(
+        &self,
+        input: WatcherValidatePaymentInput,
+    ) -> Result<(), MmError<ValidatePaymentError>> {
+        if input.taker_payment_refund_preimage != input.payment_tx {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                "Taker payment refund preimage differs from the taker payment".to_string(),
+            ));
+        }
+
+        let payment_tx: UtxoTx = deserialize(input.payment_tx.as_slice())?;
+        let time_lock: u32 = input
+            .time_lock
+            .try_into()
+            .map_to_mm(ValidatePaymentError::TimelockOverflow)?;
+        let expected_sender = self
+            .contract_address_from_raw_pubkey(&input.taker_pub)
+            .map_to_mm(ValidatePaymentError::InvalidParameter)?;
+        let expected_receiver = self
+            .contract_address_from_raw_pubkey(&input.maker_pub)
+            .map_to_mm(ValidatePaymentError::InvalidParameter)?;
+        let expected_secret_hash = if input.secret_hash.len() == 32 {
+            ripemd160(&input.secret_hash).to_vec()
+        } else {
+            input.secret_hash.clone()
+        };
+
+        let payment = self
+            .erc20_payment_details_from_tx(&payment_tx)
+            .await
+            .map_to_mm(ValidatePaymentError::TxDeserializationError)?;
+        if payment.watcher_reward.is_none() {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                "Taker payment was sent by 'erc20Payment' and can't be refunded by a watcher".to_string(),
+            ));
+        }
+
+        if payment.swap_contract_address != self.swap_contract_address
+            && Some(payment.swap_contract_address) != self.fallback_swap_contract
+        {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Taker payment was sent to unknown swap contract {:?}",
+                payment.swap_contract_address
+            )));
+        }
+
+        let expected_swap_id = qrc20_swap_id(time_lock, &input.secret_hash);
+        if payment.swap_id != expected_swap_id {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Invalid 'swap_id' {}, expected {}",
+                hex::encode(&payment.swap_id),
+                hex::encode(&expected_swap_id)
+            )));
+        }
+
+        if payment.token_address != self.contract_address {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx token_address arg {:?} is invalid, expected {:?}",
+                payment.token_address, self.contract_address
+            )));
+        }
+
+        if payment.sender != expected_sender {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx {:?} was sent from wrong address, expected {:?}",
+                payment.sender, expected_sender
+            )));
+        }
+
+        if payment.receiver != expected_receiver {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx receiver arg {:?} is invalid, expected {:?}",
+                payment.receiver, expected_receiver
+            )));
+        }
+
+        if payment.secret_hash != expected_secret_hash {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx secret_hash arg {} is invalid, expected {}",
+                hex::encode(&payment.secret_hash),
+                hex::encode(&expected_secret_hash)
+            )));
+        }
+
+        if payment.timelock != U256::from(time_lock) {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx time_lock arg {} is invalid, expected {}",
+                payment.timelock, time_lock
+            )));
+        }
+
+        let status = self
+            .payment_status(&payment.swap_contract_address, expected_swap_id)
+            .await?;
+        if status != U256::from(PaymentState::Sent as u8) {
+            return MmError::err(ValidatePaymentError::UnexpectedPaymentState(format!(
+                "Payment state is not PAYMENT_STATE_SENT, got {}",
+                status
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Validates the maker payment spend or the taker payment refund sent by a watcher on behalf of the taker.
+    pub async fn validate_watcher_spend_impl(
+        &self,
+        input: ValidateWatcherSpendInput,
+    ) -> Result<(), MmError<ValidatePaymentError>> {
+        let tx: UtxoTx = deserialize(input.payment_tx.as_slice())?;
+        let expected_swap_contract_address = input
+            .swap_contract_address
+            .try_to_address()
+            .map_to_mm(ValidatePaymentError::InvalidParameter)?;
+        let time_lock = input
+            .time_lock
+            .try_into()
+            .map_to_mm(ValidatePaymentError::TimelockOverflow)?;
+        let swap_id = qrc20_swap_id(time_lock, &input.secret_hash);
+
+        let (call_type, expected_state) = match input.spend_type {
+            WatcherSpendType::MakerPaymentSpend => (MutContractCallType::ReceiverSpend, PaymentState::Spent as u8),
+            WatcherSpendType::TakerPaymentRefund => (MutContractCallType::SenderRefund, PaymentState::Refunded as u8),
+        };
+        let output_index = find_swap_contract_call_with_swap_id(call_type, &tx, &swap_id).ok_or_else(|| {
+            ValidatePaymentError::WrongPaymentTx(format!(
+                "Tx {:?} doesn't call '{}' with swap_id {}",
+                tx.hash().reversed(),
+                call_type.as_function_name(),
+                hex::encode(&swap_id)
+            ))
+        })?;
+
+        let script_pubkey: Script = tx.outputs[output_index].script_pubkey.clone().into();
+        let swap_contract_address = extract_contract_addr_from_script(&script_pubkey)
+            .map_to_mm(ValidatePaymentError::TxDeserializationError)?;
+        if swap_contract_address != expected_swap_contract_address {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Tx {:?} called wrong swap contract {:?}, expected {:?}",
+                tx.hash().reversed(),
+                swap_contract_address,
+                expected_swap_contract_address
+            )));
+        }
+
+        let status = self.payment_status(&swap_contract_address, swap_id).await?;
+        if status != U256::from(expected_state) {
+            return MmError::err(ValidatePaymentError::UnexpectedPaymentState(format!(
+                "Payment state is not {}, got {}",
+                expected_state, status
+            )));
+        }
+
+        Ok(())
+    }
+
     pub async fn validate_fee_impl(
         &self,
         fee_tx_hash: H256Json,
@@ -248,7 +604,12 @@ impl Qrc20Coin {
             return ERR!("'erc20Payment' was not confirmed yet. Please wait for at least one confirmation");
         }
 
-        let Erc20PaymentDetails { swap_id, receiver, .. } = try_s!(self.erc20_payment_details_from_tx(&tx).await);
+        let Erc20PaymentDetails {
+            swap_id,
+            sender,
+            receiver,
+            ..
+        } = try_s!(self.erc20_payment_details_from_tx(&tx).await);
         let expected_swap_id = qrc20_swap_id(time_lock, secret_hash);
         if expected_swap_id != swap_id {
             return ERR!("Unexpected swap_id {}", hex::encode(swap_id));
@@ -264,8 +625,6 @@ impl Qrc20Coin {
         }
 
         // Else try to find a 'senderRefund' contract call.
-        let my_address = try_s!(self.utxo.derivation_method.single_addr_or_err().await);
-        let sender = try_s!(qtum::contract_addr_from_utxo_addr(my_address));
         let refund_txs = try_s!(self.sender_refund_transactions(sender, search_from_block).await);
         let found = refund_txs.into_iter().find(|tx| {
             find_swap_contract_call_with_swap_id(MutContractCallType::SenderRefund, tx, &expected_swap_id).is_some()
@@ -421,7 +780,7 @@ impl Qrc20Coin {
             let contract_call_bytes = try_s!(extract_contract_call_from_script(&script_pubkey));
 
             let call_type = try_s!(MutContractCallType::from_script_pubkey(&contract_call_bytes));
-            match call_type {
+            match call_type.map(MutContractCallType::without_watcher_reward) {
                 Some(MutContractCallType::Erc20Payment)
                 | Some(MutContractCallType::ReceiverSpend)
                 | Some(MutContractCallType::SenderRefund) => (),
@@ -446,19 +805,9 @@ impl Qrc20Coin {
         secret_hash: Vec<u8>,
         receiver_addr: H160,
         swap_contract_address: H160,
+        watcher_reward: Option<WatcherRewardArgs>,
     ) -> UtxoRpcResult<Vec<ContractCallOutput>> {
-        let allowance = self.allowance(swap_contract_address).await?;
-
-        let mut outputs = Vec::with_capacity(3);
-        // check if we should reset the allowance to 0 and raise this to the max available value (our balance)
-        if allowance < value {
-            if allowance > U256::zero() {
-                // first reset the allowance to the 0
-                outputs.push(self.approve_output(swap_contract_address, 0.into())?);
-            }
-            // set the allowance from 0 to `my_balance` after the previous output is executed
-            outputs.push(self.approve_output(swap_contract_address, my_balance)?);
-        }
+        let mut outputs = self.allowance_outputs(swap_contract_address, value, my_balance).await?;
 
         // when this output is executed, the allowance will be sufficient already
         outputs.push(self.erc20_payment_output(
@@ -468,10 +817,34 @@ impl Qrc20Coin {
             &secret_hash,
             receiver_addr,
             &swap_contract_address,
+            watcher_reward.as_ref(),
         )?);
         Ok(outputs)
     }
 
+    /// Generate `approve` outputs required to let the `spender` transfer `value` from the wallet.
+    /// If the wallet allowance is not enough we should set it to the wallet balance.
+    pub async fn allowance_outputs(
+        &self,
+        spender: H160,
+        value: U256,
+        my_balance: U256,
+    ) -> UtxoRpcResult<Vec<ContractCallOutput>> {
+        let allowance = self.allowance(spender).await?;
+
+        let mut outputs = Vec::with_capacity(3);
+        // check if we should reset the allowance to 0 and raise this to the max available value (our balance)
+        if allowance < value {
+            if allowance > U256::zero() {
+                // first reset the allowance to the 0
+                outputs.push(self.approve_output(spender, 0.into())?);
+            }
+            // set the allowance from 0 to `my_balance` after the previous output is executed
+            outputs.push(self.approve_output(spender, my_balance)?);
+        }
+        Ok(outputs)
+    }
+
     pub async fn allowance(&self, spender: H160) -> UtxoRpcResult<U256> {
         let my_address = self
             .utxo
@@ -551,7 +924,9 @@ impl Qrc20Coin {
         })
     }
 
-    /// Generate a UTXO output with a script_pubkey that calls EtomicSwap `erc20Payment` function.
+    /// Generate a UTXO output with a script_pubkey that calls EtomicSwap `erc20Payment` function,
+    /// or `erc20PaymentReward` if the `watcher_reward` args are specified.
+    #[allow(clippy::too_many_arguments)]
     fn erc20_payment_output(
         &self,
         id: Vec<u8>,
@@ -560,8 +935,9 @@ impl Qrc20Coin {
         secret_hash: &[u8],
         receiver_addr: H160,
         swap_contract_address: &H160,
+        watcher_reward: Option<&WatcherRewardArgs>,
     ) -> Qrc20AbiResult<ContractCallOutput> {
-        let params = self.erc20_payment_call_bytes(id, value, time_lock, secret_hash, receiver_addr)?;
+        let params = self.erc20_payment_call_bytes(id, value, time_lock, secret_hash, receiver_addr, watcher_reward)?;
 
         let gas_limit = QRC20_PAYMENT_GAS_LIMIT;
         let gas_price = QRC20_GAS_PRICE_DEFAULT;
@@ -588,16 +964,23 @@ impl Qrc20Coin {
         time_lock: u32,
         secret_hash: &[u8],
         receiver_addr: H160,
+        watcher_reward: Option<&WatcherRewardArgs>,
     ) -> Qrc20AbiResult<Vec<u8>> {
-        let function = eth::SWAP_CONTRACT.function("erc20Payment")?;
-        Ok(function.encode_input(&[
+        let function = MutContractCallType::Erc20Payment
+            .with_watcher_reward(watcher_reward.is_some())
+            .as_function();
+        let mut tokens = vec![
             Token::FixedBytes(id),
             Token::Uint(value),
             Token::Address(self.contract_address),
             Token::Address(receiver_addr),
             Token::FixedBytes(secret_hash.to_vec()),
             Token::Uint(U256::from(time_lock)),
-        ])?)
+        ];
+        if let Some(reward) = watcher_reward {
+            tokens.extend(reward.to_tokens());
+        }
+        Ok(function.encode_input(&tokens)?)
     }
 
     /// Generate a UTXO output with a script_pubkey that calls EtomicSwap `receiverSpend` function.
@@ -672,7 +1055,61 @@ impl Qrc20Coin {
         })
     }
 
-    /// Get `erc20Payment` contract call details.
+    /// Generate a UTXO output with a script_pubkey that calls EtomicSwap `receiverSpendReward` function.
+    /// Unlike `receiverSpend`, it can be called by anyone knowing the secret, e.g. by a watcher.
+    #[allow(clippy::too_many_arguments)]
+    pub fn receiver_spend_reward_output(
+        &self,
+        swap_contract_address: &H160,
+        id: Vec<u8>,
+        value: U256,
+        secret: Vec<u8>,
+        sender: H160,
+        receiver: H160,
+        watcher_reward: &WatcherRewardArgs,
+    ) -> Qrc20AbiResult<ContractCallOutput> {
+        let function = MutContractCallType::ReceiverSpendReward.as_function();
+        let mut tokens = vec![
+            Token::FixedBytes(id),
+            Token::Uint(value),
+            Token::FixedBytes(secret),
+            Token::Address(self.contract_address),
+            Token::Address(sender),
+            Token::Address(receiver),
+        ];
+        tokens.extend(watcher_reward.to_tokens());
+        let params = function.encode_input(&tokens)?;
+        swap_contract_call_output(&params, swap_contract_address)
+    }
+
+    /// Generate a UTXO output with a script_pubkey that calls EtomicSwap `senderRefundReward` function.
+    /// Unlike `senderRefund`, it can be called by anyone after the payment locktime, e.g. by a watcher.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sender_refund_reward_output(
+        &self,
+        swap_contract_address: &H160,
+        id: Vec<u8>,
+        value: U256,
+        secret_hash: Vec<u8>,
+        sender: H160,
+        receiver: H160,
+        watcher_reward: &WatcherRewardArgs,
+    ) -> Qrc20AbiResult<ContractCallOutput> {
+        let function = MutContractCallType::SenderRefundReward.as_function();
+        let mut tokens = vec![
+            Token::FixedBytes(id),
+            Token::Uint(value),
+            Token::FixedBytes(secret_hash),
+            Token::Address(self.contract_address),
+            Token::Address(sender),
+            Token::Address(receiver),
+        ];
+        tokens.extend(watcher_reward.to_tokens());
+        let params = function.encode_input(&tokens)?;
+        swap_contract_call_output(&params, swap_contract_address)
+    }
+
+    /// Get `erc20Payment` or `erc20PaymentReward` contract call details.
     /// Note returns an error if the contract call was excepted.
     async fn erc20_payment_details_from_tx(&self, qtum_tx: &UtxoTx) -> Result<Erc20PaymentDetails, String> {
         let tx_hash: H256Json = qtum_tx.hash().reversed().into();
@@ -690,15 +1127,14 @@ impl Qrc20Coin {
 
             let contract_call_bytes = try_s!(extract_contract_call_from_script(&script_pubkey));
 
-            let call_type = try_s!(MutContractCallType::from_script_pubkey(&contract_call_bytes));
-            match call_type {
-                Some(MutContractCallType::Erc20Payment) => (),
+            let call_type = match try_s!(MutContractCallType::from_script_pubkey(&contract_call_bytes)) {
+                Some(call_type) if call_type.without_watcher_reward() == MutContractCallType::Erc20Payment => call_type,
                 _ => continue, // skip non-erc20Payment contract calls
-            }
+            };
 
             try_s!(check_if_contract_call_completed(&receipt));
 
-            let function = try_s!(eth::SWAP_CONTRACT.function("erc20Payment"));
+            let function = call_type.as_function();
             let decoded = try_s!(decode_contract_call(function, &contract_call_bytes));
 
             let mut decoded = decoded.into_iter();
@@ -739,6 +1175,12 @@ impl Qrc20Coin {
                 None => return ERR!("Couldn't find 'timelock' in erc20Payment call"),
             };
 
+            let watcher_reward = if call_type.is_watcher_reward() {
+                Some(try_s!(watcher_reward_args_from_tokens(&mut decoded)))
+            } else {
+                None
+            };
+
             // check if there is no arguments more
             if let Some(token) = decoded.next() {
                 return ERR!("Unexpected additional arg {:?}", token);
@@ -790,6 +1232,7 @@ impl Qrc20Coin {
                 secret_hash,
                 timelock,
                 contract_call_bytes,
+                watcher_reward,
             });
         }
         ERR!("Couldn't find erc20Payment contract call in {:?} tx", tx_hash)
@@ -816,7 +1259,7 @@ impl Qrc20Coin {
     /// Gets transactions emitted the specified events from etomic swap smart contract since `from_block`.
     /// `event_topic` is an event first and once topic in logs.
     /// `caller_address` is who called etomic swap smart contract functions that emitted the specified event.
-    async fn transactions_emitted_swap_event(
+    pub(super) async fn transactions_emitted_swap_event(
         &self,
         event_topic: &str,
         caller_address: H160,
@@ -851,6 +1294,25 @@ impl Qrc20Coin {
     }
 }
 
+/// Generate a UTXO output with a script_pubkey that calls the EtomicSwap contract with the given `params`.
+pub(super) fn swap_contract_call_output(
+    params: &[u8],
+    swap_contract_address: &H160,
+) -> Qrc20AbiResult<ContractCallOutput> {
+    let gas_limit = QRC20_GAS_LIMIT_DEFAULT;
+    let gas_price = QRC20_GAS_PRICE_DEFAULT;
+    let script_pubkey =
+        generate_contract_call_script_pubkey(params, gas_limit, gas_price, swap_contract_address.as_bytes())?
+            .to_bytes();
+
+    Ok(ContractCallOutput {
+        value: OUTPUT_QTUM_AMOUNT,
+        script_pubkey,
+        gas_limit,
+        gas_price,
+    })
+}
+
 /// Get `Transfer` events details from [`TxReceipt::logs`].
 fn transfer_events_from_receipt(receipt: &TxReceipt) -> Result<Vec<TransferEventDetails>, String> {
     receipt
@@ -906,20 +1368,20 @@ fn transfer_call_details_from_script_pubkey(script_pubkey: &Script) -> Result<(H
     Ok((receiver, value))
 }
 
-/// Get `receiverSpend` contract call details from script pubkey.
+/// Get `receiverSpend` or `receiverSpendReward` contract call details from script pubkey.
 pub fn receiver_spend_call_details_from_script_pubkey(script_pubkey: &Script) -> Result<ReceiverSpendDetails, String> {
     if !is_contract_call(script_pubkey) {
         return ERR!("Expected 'receiverSpend' contract call");
     }
 
     let contract_call_bytes = try_s!(extract_contract_call_from_script(script_pubkey));
-    let call_type = try_s!(MutContractCallType::from_script_pubkey(&contract_call_bytes));
-    match call_type {
-        Some(MutContractCallType::ReceiverSpend) => (),
+    let function = match try_s!(MutContractCallType::from_script_pubkey(&contract_call_bytes)) {
+        Some(call_type) if call_type.without_watcher_reward() == MutContractCallType::ReceiverSpend => {
+            call_type.as_function()
+        },
         _ => return ERR!("Expected 'receiverSpend' contract call"),
-    }
+    };
 
-    let function = try_s!(eth::SWAP_CONTRACT.function("receiverSpend"));
     let decoded = try_s!(decode_contract_call(function, &contract_call_bytes));
     let mut decoded = decoded.into_iter();
 
@@ -1003,7 +1465,7 @@ fn find_receiver_spend_with_swap_id_and_secret_hash(
     None
 }
 
-fn find_swap_contract_call_with_swap_id(
+pub(super) fn find_swap_contract_call_with_swap_id(
     expected_call_type: MutContractCallType,
     tx: &UtxoTx,
     expected_swap_id: &[u8],
@@ -1032,7 +1494,7 @@ fn find_swap_contract_call_with_swap_id(
                 continue;
             },
         };
-        if call_type != expected_call_type {
+        if call_type.without_watcher_reward() != expected_call_type {
             // skip the output
             continue;
         }
@@ -1067,6 +1529,35 @@ fn find_swap_contract_call_with_swap_id(
     None
 }
 
+/// Get the watcher reward args that follow the swap args of the `*Reward` contract calls.
+pub(super) fn watcher_reward_args_from_tokens(
+    decoded: &mut impl Iterator<Item = Token>,
+) -> Result<WatcherRewardArgs, String> {
+    let reward_target = match decoded.next() {
+        Some(Token::Uint(target)) => target,
+        Some(token) => return ERR!("'reward_target' arg is invalid, found {:?}", token),
+        None => return ERR!("Couldn't find 'reward_target' arg"),
+    };
+
+    let sends_contract_reward_on_spend = match decoded.next() {
+        Some(Token::Bool(sends)) => sends,
+        Some(token) => return ERR!("'sends_contract_reward_on_spend' arg is invalid, found {:?}", token),
+        None => return ERR!("Couldn't find 'sends_contract_reward_on_spend' arg"),
+    };
+
+    let reward_amount = match decoded.next() {
+        Some(Token::Uint(amount)) => amount,
+        Some(token) => return ERR!("'reward_amount' arg is invalid, found {:?}", token),
+        None => return ERR!("Couldn't find 'reward_amount' arg"),
+    };
+
+    Ok(WatcherRewardArgs {
+        reward_target,
+        sends_contract_reward_on_spend,
+        reward_amount,
+    })
+}
+
 fn check_if_contract_call_completed(receipt: &TxReceipt) -> Result<(), String> {
     match receipt.excepted {
         Some(ref ex) if ex != "None" && ex != "none" => {
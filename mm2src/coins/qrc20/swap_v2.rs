@@ -0,0 +1,751 @@
+//! Trading protocol v2 (TPU) implementation for QRC20 tokens.
+//!
+//! QRC20 tokens are locked in the same `EtomicSwapMakerV2` and `EtomicSwapTakerV2` contracts as ERC20 tokens are,
+//! so there are no transaction preimages to exchange: the taker approves the payment by calling `takerPaymentApprove`
+//! instead of signing the funding spend preimage, and the maker spends it calling `spendTakerPayment` directly.
+
+use super::*;
+use crate::eth::{decode_contract_call, MakerPaymentStateV2, TakerPaymentStateV2};
+use crate::{CommonSwapOpsV2, FindPaymentSpendError, FundingTxSpend, GenPreimageResult, GenTakerFundingSpendArgs,
+            GenTakerPaymentSpendArgs, MakerCoinSwapOpsV2, RefundFundingSecretArgs, RefundMakerPaymentSecretArgs,
+            RefundMakerPaymentTimelockArgs, RefundTakerPaymentArgs, SearchForFundingSpendErr, SendMakerPaymentArgs,
+            SendTakerFundingArgs, SpendMakerPaymentArgs, SwapTxTypeWithSecretHash, TakerCoinSwapOpsV2, TxGenError,
+            TxPreimageWithSig, ValidateMakerPaymentArgs, ValidateSwapV2TxError, ValidateSwapV2TxResult,
+            ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageResult, ValidateTakerPaymentSpendPreimageError,
+            ValidateTakerPaymentSpendPreimageResult};
+use keys::Signature;
+use script_pubkey::{extract_contract_addr_from_script, extract_contract_call_from_script, is_contract_call};
+
+const ERC20_TAKER_PAYMENT: &str = "erc20TakerPayment";
+const TAKER_PAYMENT_APPROVE: &str = "takerPaymentApprove";
+const SPEND_TAKER_PAYMENT: &str = "spendTakerPayment";
+const ERC20_MAKER_PAYMENT: &str = "erc20MakerPayment";
+/// `takerPayments` returns `(bytes20 paymentHash, uint32 preApproveLockTime, uint32 paymentLockTime, uint8 state)`.
+const TAKER_PAYMENT_STATE_INDEX: usize = 3;
+/// `makerPayments` returns `(bytes20 paymentHash, uint32 paymentLockTime, uint8 state)`.
+const MAKER_PAYMENT_STATE_INDEX: usize = 2;
+
+impl Qrc20Coin {
+    fn swap_v2_contracts_or_err(&self) -> Result<Qrc20SwapV2Contracts, String> {
+        self.swap_v2_contracts
+            .ok_or_else(|| ERRL!("Expected swap_v2_contracts to be Some, but found None"))
+    }
+
+    /// Sends the given contract calls and returns the resulting Qtum transaction.
+    async fn send_swap_v2_contract_calls(&self, outputs: Vec<ContractCallOutput>) -> Result<UtxoTx, TransactionErr> {
+        match self.send_contract_calls(outputs).await? {
+            TransactionEnum::UtxoTx(tx) => Ok(tx),
+            _ => TX_PLAIN_ERR!("Expected UtxoTx to be sent"),
+        }
+    }
+
+    /// Checks that the wallet balance is enough to lock `value` in the `swap_contract`
+    /// and generates the required `approve` outputs.
+    async fn swap_v2_allowance_outputs(
+        &self,
+        swap_contract: H160,
+        value: U256,
+    ) -> Result<Vec<ContractCallOutput>, String> {
+        let balance = try_s!(self.my_spendable_balance().compat().await);
+        let balance = try_s!(wei_from_big_decimal(&balance, self.utxo.decimals));
+
+        // Check the balance to avoid unnecessary burning of gas
+        if balance < value {
+            return ERR!("Balance {} is less than value {}", balance, value);
+        }
+        Ok(try_s!(self.allowance_outputs(swap_contract, value, balance).await))
+    }
+
+    /// Get the state of the `swap_id` payment from the `takerPayments` mapping of the taker swap v2 contract.
+    async fn taker_payment_state_v2(&self, taker_swap_v2_contract: &H160, swap_id: Token) -> UtxoRpcResult<U256> {
+        self.payment_state_v2(
+            ViewContractCallType::TakerPayments,
+            taker_swap_v2_contract,
+            swap_id,
+            TAKER_PAYMENT_STATE_INDEX,
+        )
+        .await
+    }
+
+    /// Get the state of the `swap_id` payment from the `makerPayments` mapping of the maker swap v2 contract.
+    async fn maker_payment_state_v2(&self, maker_swap_v2_contract: &H160, swap_id: Token) -> UtxoRpcResult<U256> {
+        self.payment_state_v2(
+            ViewContractCallType::MakerPayments,
+            maker_swap_v2_contract,
+            swap_id,
+            MAKER_PAYMENT_STATE_INDEX,
+        )
+        .await
+    }
+
+    async fn payment_state_v2(
+        &self,
+        payments: ViewContractCallType,
+        swap_v2_contract: &H160,
+        swap_id: Token,
+        state_index: usize,
+    ) -> UtxoRpcResult<U256> {
+        let decoded = self
+            .utxo
+            .rpc_client
+            .rpc_contract_call(payments, swap_v2_contract, &[swap_id])
+            .compat()
+            .await?;
+        match decoded.get(state_index) {
+            Some(Token::Uint(state)) => Ok(*state),
+            state => MmError::err(UtxoRpcError::InvalidResponse(format!(
+                "Payment status must be uint, got {:?}",
+                state
+            ))),
+        }
+    }
+
+    /// Checks that the transaction is known to the node and isn't modified.
+    async fn check_swap_v2_tx_bytes(&self, tx: &UtxoTx) -> ValidateSwapV2TxResult {
+        let tx_bytes_from_rpc = self
+            .utxo
+            .rpc_client
+            .get_transaction_bytes(&tx.hash().reversed().into())
+            .compat()
+            .await?;
+        let actual_tx_bytes = serialize(tx).take();
+        if tx_bytes_from_rpc.0 != actual_tx_bytes {
+            return MmError::err(ValidateSwapV2TxError::TxBytesMismatch {
+                from_rpc: tx_bytes_from_rpc,
+                actual: actual_tx_bytes.into(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Calls `refundTakerPaymentTimelock` of the taker swap v2 contract.
+    async fn refund_taker_payment_v2_timelock(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<UtxoTx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let (maker_secret_hash, taker_secret_hash) = match args.tx_type_with_secret_hash {
+            SwapTxTypeWithSecretHash::TakerPaymentV2 {
+                maker_secret_hash,
+                taker_secret_hash,
+            } => (maker_secret_hash, taker_secret_hash),
+            _ => return TX_PLAIN_ERR!("Unsupported swap tx type for timelock refund"),
+        };
+        let dex_fee = try_tx_s!(wei_from_big_decimal(
+            &args.dex_fee.fee_amount().to_decimal(),
+            self.utxo.decimals
+        ));
+        let amount = try_tx_s!(wei_from_big_decimal(
+            &(args.trading_amount + args.premium_amount),
+            self.utxo.decimals
+        ));
+        let maker_address = try_tx_s!(self.contract_address_from_raw_pubkey(args.maker_pub));
+
+        let function = try_tx_s!(eth::TAKER_SWAP_V2.function("refundTakerPaymentTimelock"));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.taker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.time_lock, maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Uint(dex_fee),
+                Token::Address(maker_address),
+                Token::FixedBytes(taker_secret_hash.to_vec()),
+                Token::FixedBytes(maker_secret_hash.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+}
+
+/// Generate a UTXO output with a script_pubkey that calls the `function` of the EtomicSwap v2 `swap_contract`.
+pub(super) fn swap_v2_contract_call_output(
+    swap_contract: &H160,
+    function: &Function,
+    params: &[Token],
+) -> Qrc20AbiResult<ContractCallOutput> {
+    let params = function.encode_input(params)?;
+
+    let gas_limit = QRC20_PAYMENT_GAS_LIMIT;
+    let gas_price = QRC20_GAS_PRICE_DEFAULT;
+    let script_pubkey =
+        generate_contract_call_script_pubkey(&params, gas_limit, gas_price, swap_contract.as_bytes())?.to_bytes();
+
+    Ok(ContractCallOutput {
+        value: OUTPUT_QTUM_AMOUNT,
+        script_pubkey,
+        gas_limit,
+        gas_price,
+    })
+}
+
+/// Finds an output of the `tx` that calls the `function` of the `swap_contract` and decodes the call params.
+pub(super) fn swap_v2_contract_call_params(
+    tx: &UtxoTx,
+    swap_contract: &H160,
+    function: &Function,
+) -> Result<Vec<Token>, String> {
+    let expected_signature = function.short_signature();
+    for output in tx.outputs.iter() {
+        let script_pubkey: Script = output.script_pubkey.clone().into();
+        if !is_contract_call(&script_pubkey) {
+            continue;
+        }
+        if try_s!(extract_contract_addr_from_script(&script_pubkey)) != *swap_contract {
+            continue;
+        }
+
+        let contract_call_bytes = try_s!(extract_contract_call_from_script(&script_pubkey));
+        if !contract_call_bytes.starts_with(&expected_signature) {
+            continue;
+        }
+        return Ok(try_s!(decode_contract_call(function, &contract_call_bytes)));
+    }
+
+    ERR!(
+        "Couldn't find '{}' contract call in tx {:?}",
+        function.name,
+        tx.hash().reversed()
+    )
+}
+
+fn swap_v2_call_param(decoded: &[Token], index: usize) -> Result<Token, String> {
+    decoded
+        .get(index)
+        .cloned()
+        .ok_or_else(|| ERRL!("Contract call has no param at index {}: {:?}", index, decoded))
+}
+
+#[async_trait]
+impl MakerCoinSwapOpsV2 for Qrc20Coin {
+    async fn send_maker_payment_v2(&self, args: SendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let amount = try_tx_s!(wei_from_big_decimal(&args.amount, self.utxo.decimals));
+        let taker_address = try_tx_s!(self.contract_address_from_raw_pubkey(&args.taker_pub.to_bytes()));
+
+        let mut outputs = try_tx_s!(
+            self.swap_v2_allowance_outputs(contracts.maker_swap_v2_contract, amount)
+                .await
+        );
+        let function = try_tx_s!(eth::MAKER_SWAP_V2.function(ERC20_MAKER_PAYMENT));
+        outputs.push(try_tx_s!(swap_v2_contract_call_output(
+            &contracts.maker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.time_lock, args.maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Address(self.contract_address),
+                Token::Address(taker_address),
+                Token::FixedBytes(args.taker_secret_hash.to_vec()),
+                Token::FixedBytes(args.maker_secret_hash.to_vec()),
+                Token::Uint(args.time_lock.into()),
+            ]
+        )));
+        self.send_swap_v2_contract_calls(outputs).await
+    }
+
+    async fn validate_maker_payment_v2(&self, args: ValidateMakerPaymentArgs<'_, Self>) -> ValidatePaymentResult<()> {
+        let contracts = self
+            .swap_v2_contracts_or_err()
+            .map_to_mm(ValidatePaymentError::InternalError)?;
+        let function = eth::MAKER_SWAP_V2
+            .function(ERC20_MAKER_PAYMENT)
+            .map_to_mm(|e| ValidatePaymentError::InternalError(e.to_string()))?;
+        let decoded = swap_v2_contract_call_params(args.maker_payment_tx, &contracts.maker_swap_v2_contract, function)
+            .map_to_mm(ValidatePaymentError::WrongPaymentTx)?;
+
+        let my_address = self
+            .my_addr_as_contract_addr()
+            .await
+            .mm_err(|e| ValidatePaymentError::InternalError(e.to_string()))?;
+        let expected = vec![
+            Token::FixedBytes(qrc20_swap_id_v2(args.time_lock, args.maker_secret_hash)),
+            Token::Uint(wei_from_big_decimal(&args.amount, self.utxo.decimals)?),
+            Token::Address(self.contract_address),
+            Token::Address(my_address),
+            Token::FixedBytes(args.taker_secret_hash.to_vec()),
+            Token::FixedBytes(args.maker_secret_hash.to_vec()),
+            Token::Uint(args.time_lock.into()),
+        ];
+        if decoded != expected {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Unexpected '{}' contract call params {:?}, expected {:?}",
+                ERC20_MAKER_PAYMENT, decoded, expected
+            )));
+        }
+
+        if !check_all_utxo_inputs_signed_by_pub(args.maker_payment_tx, &args.maker_pub.to_bytes())? {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                "Maker payment is not signed by the maker pubkey".to_string(),
+            ));
+        }
+
+        self.check_swap_v2_tx_bytes(args.maker_payment_tx)
+            .await
+            .mm_err(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?;
+
+        let state = self
+            .maker_payment_state_v2(&contracts.maker_swap_v2_contract, expected[0].clone())
+            .await
+            .mm_err(|e| ValidatePaymentError::Transport(e.to_string()))?;
+        if state != U256::from(MakerPaymentStateV2::PaymentSent as u8) {
+            return MmError::err(ValidatePaymentError::UnexpectedPaymentState(format!(
+                "Maker payment state is {}, expected {}",
+                state,
+                MakerPaymentStateV2::PaymentSent as u8
+            )));
+        }
+        Ok(())
+    }
+
+    async fn refund_maker_payment_v2_timelock(
+        &self,
+        args: RefundMakerPaymentTimelockArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let (maker_secret_hash, taker_secret_hash) = match args.tx_type_with_secret_hash {
+            SwapTxTypeWithSecretHash::MakerPaymentV2 {
+                maker_secret_hash,
+                taker_secret_hash,
+            } => (maker_secret_hash, taker_secret_hash),
+            _ => return TX_PLAIN_ERR!("Unsupported swap tx type for timelock refund"),
+        };
+        let amount = try_tx_s!(wei_from_big_decimal(&args.amount, self.utxo.decimals));
+        let taker_address = try_tx_s!(self.contract_address_from_raw_pubkey(args.taker_pub));
+
+        let function = try_tx_s!(eth::MAKER_SWAP_V2.function("refundMakerPaymentTimelock"));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.maker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.time_lock, maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Address(taker_address),
+                Token::FixedBytes(taker_secret_hash.to_vec()),
+                Token::FixedBytes(maker_secret_hash.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+
+    async fn refund_maker_payment_v2_secret(
+        &self,
+        args: RefundMakerPaymentSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let amount = try_tx_s!(wei_from_big_decimal(&args.amount, self.utxo.decimals));
+        let taker_address = try_tx_s!(self.contract_address_from_raw_pubkey(&args.taker_pub.to_bytes()));
+
+        let function = try_tx_s!(eth::MAKER_SWAP_V2.function("refundMakerPaymentSecret"));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.maker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.time_lock, args.maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Address(taker_address),
+                Token::FixedBytes(args.taker_secret.to_vec()),
+                Token::FixedBytes(args.maker_secret_hash.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+
+    async fn spend_maker_payment_v2(&self, args: SpendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let amount = try_tx_s!(wei_from_big_decimal(&args.amount, self.utxo.decimals));
+        let maker_address = try_tx_s!(self.contract_address_from_raw_pubkey(&args.maker_pub.to_bytes()));
+
+        let function = try_tx_s!(eth::MAKER_SWAP_V2.function("spendMakerPayment"));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.maker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.time_lock, args.maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Address(maker_address),
+                Token::FixedBytes(args.taker_secret_hash.to_vec()),
+                Token::FixedBytes(args.maker_secret.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+}
+
+#[async_trait]
+impl TakerCoinSwapOpsV2 for Qrc20Coin {
+    /// Calls `erc20TakerPayment` of the taker swap v2 contract, approving the token transfer first if required.
+    async fn send_taker_funding(&self, args: SendTakerFundingArgs<'_>) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let dex_fee = try_tx_s!(wei_from_big_decimal(
+            &args.dex_fee.fee_amount().into(),
+            self.utxo.decimals
+        ));
+        let amount = try_tx_s!(wei_from_big_decimal(
+            &(args.trading_amount + args.premium_amount),
+            self.utxo.decimals
+        ));
+        let total_amount = amount
+            .checked_add(dex_fee)
+            .ok_or_else(|| TransactionErr::Plain(ERRL!("Overflow occurred while calculating total payment")))?;
+        let maker_address = try_tx_s!(self.contract_address_from_raw_pubkey(args.maker_pub));
+
+        let mut outputs = try_tx_s!(
+            self.swap_v2_allowance_outputs(contracts.taker_swap_v2_contract, total_amount)
+                .await
+        );
+        let function = try_tx_s!(eth::TAKER_SWAP_V2.function(ERC20_TAKER_PAYMENT));
+        outputs.push(try_tx_s!(swap_v2_contract_call_output(
+            &contracts.taker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.payment_time_lock, args.maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Uint(dex_fee),
+                Token::Address(self.contract_address),
+                Token::Address(maker_address),
+                Token::FixedBytes(args.taker_secret_hash.to_vec()),
+                Token::FixedBytes(args.maker_secret_hash.to_vec()),
+                Token::Uint(args.funding_time_lock.into()),
+                Token::Uint(args.payment_time_lock.into()),
+            ]
+        )));
+        self.send_swap_v2_contract_calls(outputs).await
+    }
+
+    async fn validate_taker_funding(&self, args: ValidateTakerFundingArgs<'_, Self>) -> ValidateSwapV2TxResult {
+        let contracts = self
+            .swap_v2_contracts_or_err()
+            .map_to_mm(ValidateSwapV2TxError::Internal)?;
+        let function = eth::TAKER_SWAP_V2.function(ERC20_TAKER_PAYMENT)?;
+        let decoded = swap_v2_contract_call_params(args.funding_tx, &contracts.taker_swap_v2_contract, function)
+            .map_to_mm(ValidateSwapV2TxError::WrongPaymentTx)?;
+
+        let my_address = self
+            .my_addr_as_contract_addr()
+            .await
+            .mm_err(|e| ValidateSwapV2TxError::Internal(e.to_string()))?;
+        let dex_fee = wei_from_big_decimal(&args.dex_fee.fee_amount().into(), self.utxo.decimals)?;
+        let amount = wei_from_big_decimal(&(args.trading_amount + args.premium_amount), self.utxo.decimals)?;
+        let expected = vec![
+            Token::FixedBytes(qrc20_swap_id_v2(args.payment_time_lock, args.maker_secret_hash)),
+            Token::Uint(amount),
+            Token::Uint(dex_fee),
+            Token::Address(self.contract_address),
+            Token::Address(my_address),
+            Token::FixedBytes(args.taker_secret_hash.to_vec()),
+            Token::FixedBytes(args.maker_secret_hash.to_vec()),
+            Token::Uint(args.funding_time_lock.into()),
+            Token::Uint(args.payment_time_lock.into()),
+        ];
+        if decoded != expected {
+            return MmError::err(ValidateSwapV2TxError::InvalidDestinationOrAmount(format!(
+                "Unexpected '{}' contract call params {:?}, expected {:?}",
+                ERC20_TAKER_PAYMENT, decoded, expected
+            )));
+        }
+
+        let signed_by_taker = check_all_utxo_inputs_signed_by_pub(args.funding_tx, &args.taker_pub.to_bytes())
+            .mm_err(|e| ValidateSwapV2TxError::WrongPaymentTx(e.to_string()))?;
+        if !signed_by_taker {
+            return MmError::err(ValidateSwapV2TxError::WrongPaymentTx(
+                "Taker funding is not signed by the taker pubkey".to_string(),
+            ));
+        }
+
+        self.check_swap_v2_tx_bytes(args.funding_tx).await?;
+
+        let state = self
+            .taker_payment_state_v2(&contracts.taker_swap_v2_contract, expected[0].clone())
+            .await
+            .mm_err(|e| ValidateSwapV2TxError::Rpc(e.to_string()))?;
+        if state != U256::from(TakerPaymentStateV2::PaymentSent as u8) {
+            return MmError::err(ValidateSwapV2TxError::UnexpectedPaymentState(format!(
+                "Taker payment state is {}, expected {}",
+                state,
+                TakerPaymentStateV2::PaymentSent as u8
+            )));
+        }
+        Ok(())
+    }
+
+    async fn refund_taker_funding_timelock(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.refund_taker_payment_v2_timelock(args).await
+    }
+
+    async fn refund_taker_funding_secret(
+        &self,
+        args: RefundFundingSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let dex_fee = try_tx_s!(wei_from_big_decimal(
+            &args.dex_fee.fee_amount().to_decimal(),
+            self.utxo.decimals
+        ));
+        let amount = try_tx_s!(wei_from_big_decimal(
+            &(args.trading_amount + args.premium_amount),
+            self.utxo.decimals
+        ));
+        let maker_address = try_tx_s!(self.contract_address_from_raw_pubkey(&args.maker_pubkey.to_bytes()));
+
+        let function = try_tx_s!(eth::TAKER_SWAP_V2.function("refundTakerPaymentSecret"));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.taker_swap_v2_contract,
+            function,
+            &[
+                Token::FixedBytes(qrc20_swap_id_v2(args.payment_time_lock, args.maker_secret_hash)),
+                Token::Uint(amount),
+                Token::Uint(dex_fee),
+                Token::Address(maker_address),
+                Token::FixedBytes(args.taker_secret.to_vec()),
+                Token::FixedBytes(args.maker_secret_hash.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+
+    /// Checks that the taker payment state is `TakerApproved`. Called by maker.
+    async fn search_for_taker_funding_spend(
+        &self,
+        tx: &Self::Tx,
+        _from_block: u64,
+        _secret_hash: &[u8],
+    ) -> Result<Option<FundingTxSpend<Self>>, SearchForFundingSpendErr> {
+        let contracts = self
+            .swap_v2_contracts_or_err()
+            .map_err(SearchForFundingSpendErr::Internal)?;
+        let function = eth::TAKER_SWAP_V2.function(ERC20_TAKER_PAYMENT)?;
+        let decoded = swap_v2_contract_call_params(tx, &contracts.taker_swap_v2_contract, function)
+            .map_err(SearchForFundingSpendErr::InvalidInputTx)?;
+        let swap_id = swap_v2_call_param(&decoded, 0).map_err(SearchForFundingSpendErr::InvalidInputTx)?;
+
+        let state = self
+            .taker_payment_state_v2(&contracts.taker_swap_v2_contract, swap_id)
+            .await
+            .map_err(|e| SearchForFundingSpendErr::Rpc(e.to_string()))?;
+        if state == U256::from(TakerPaymentStateV2::TakerApproved as u8) {
+            return Ok(Some(FundingTxSpend::TransferredToTakerPayment(tx.clone())));
+        }
+        Ok(None)
+    }
+
+    /// QRC20 doesn't have preimages
+    async fn gen_taker_funding_spend_preimage(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        Ok(TxPreimageWithSig {
+            preimage: args.funding_tx.clone(),
+            signature: Signature::from(Vec::new()),
+        })
+    }
+
+    /// QRC20 doesn't have preimages
+    async fn validate_taker_funding_spend_preimage(
+        &self,
+        _gen_args: &GenTakerFundingSpendArgs<'_, Self>,
+        _preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerFundingSpendPreimageResult {
+        Ok(())
+    }
+
+    /// Calls `takerPaymentApprove` of the taker swap v2 contract.
+    /// Returns the approve transaction that is considered as the taker payment further.
+    async fn sign_and_send_taker_funding_spend(
+        &self,
+        _preimage: &TxPreimageWithSig<Self>,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let payment_func = try_tx_s!(eth::TAKER_SWAP_V2.function(ERC20_TAKER_PAYMENT));
+        let decoded = try_tx_s!(swap_v2_contract_call_params(
+            args.funding_tx,
+            &contracts.taker_swap_v2_contract,
+            payment_func
+        ));
+
+        let function = try_tx_s!(eth::TAKER_SWAP_V2.function(TAKER_PAYMENT_APPROVE));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.taker_swap_v2_contract,
+            function,
+            &[
+                try_tx_s!(swap_v2_call_param(&decoded, 0)), // id from erc20TakerPayment
+                try_tx_s!(swap_v2_call_param(&decoded, 1)), // amount from erc20TakerPayment
+                try_tx_s!(swap_v2_call_param(&decoded, 2)), // dexFee from erc20TakerPayment
+                try_tx_s!(swap_v2_call_param(&decoded, 4)), // receiver from erc20TakerPayment
+                Token::FixedBytes(args.taker_secret_hash.to_vec()),
+                Token::FixedBytes(args.maker_secret_hash.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+
+    async fn refund_combined_taker_payment(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.refund_taker_payment_v2_timelock(args).await
+    }
+
+    fn skip_taker_payment_spend_preimage(&self) -> bool { true }
+
+    /// QRC20 skips taker_payment_spend_preimage, as it doesn't need it
+    async fn gen_taker_payment_spend_preimage(
+        &self,
+        _args: &GenTakerPaymentSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        MmError::err(TxGenError::Other(
+            "QRC20 token doesn't have taker_payment_spend_preimage. Report the Bug!".to_string(),
+        ))
+    }
+
+    /// QRC20 skips taker_payment_spend_preimage, as it doesn't need it
+    async fn validate_taker_payment_spend_preimage(
+        &self,
+        _gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        _preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerPaymentSpendPreimageResult {
+        MmError::err(ValidateTakerPaymentSpendPreimageError::InvalidPreimage(
+            "QRC20 token skips taker_payment_spend_preimage validation. Report the Bug!".to_string(),
+        ))
+    }
+
+    /// Calls `spendTakerPayment` of the taker swap v2 contract. Called by maker.
+    async fn sign_and_broadcast_taker_payment_spend(
+        &self,
+        _preimage: Option<&TxPreimageWithSig<Self>>,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        secret: &[u8],
+        _swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        let contracts = try_tx_s!(self.swap_v2_contracts_or_err());
+        let payment_func = try_tx_s!(eth::TAKER_SWAP_V2.function(ERC20_TAKER_PAYMENT));
+        let decoded = try_tx_s!(swap_v2_contract_call_params(
+            gen_args.taker_tx,
+            &contracts.taker_swap_v2_contract,
+            payment_func
+        ));
+        let taker_address = try_tx_s!(self.contract_address_from_raw_pubkey(&gen_args.taker_pub.to_bytes()));
+
+        let function = try_tx_s!(eth::TAKER_SWAP_V2.function(SPEND_TAKER_PAYMENT));
+        let output = try_tx_s!(swap_v2_contract_call_output(
+            &contracts.taker_swap_v2_contract,
+            function,
+            &[
+                try_tx_s!(swap_v2_call_param(&decoded, 0)), // id from erc20TakerPayment
+                try_tx_s!(swap_v2_call_param(&decoded, 1)), // amount from erc20TakerPayment
+                try_tx_s!(swap_v2_call_param(&decoded, 2)), // dexFee from erc20TakerPayment
+                Token::Address(taker_address),
+                try_tx_s!(swap_v2_call_param(&decoded, 5)), // takerSecretHash from erc20TakerPayment
+                Token::FixedBytes(secret.to_vec()),
+                Token::Address(self.contract_address),
+            ]
+        ));
+        self.send_swap_v2_contract_calls(vec![output]).await
+    }
+
+    /// Looks for the `spendTakerPayment` call among transactions transferred the tokens to the maker.
+    /// `taker_payment` is the `takerPaymentApprove` transaction
+    /// returned by [`Self::sign_and_send_taker_funding_spend`].
+    async fn find_taker_payment_spend_tx(
+        &self,
+        taker_payment: &Self::Tx,
+        from_block: u64,
+        wait_until: u64,
+    ) -> MmResult<Self::Tx, FindPaymentSpendError> {
+        const CHECK_EVERY: f64 = 10.;
+
+        let contracts = self
+            .swap_v2_contracts_or_err()
+            .map_to_mm(FindPaymentSpendError::Internal)?;
+        let approve_func = eth::TAKER_SWAP_V2.function(TAKER_PAYMENT_APPROVE)?;
+        let decoded = swap_v2_contract_call_params(taker_payment, &contracts.taker_swap_v2_contract, approve_func)
+            .map_to_mm(FindPaymentSpendError::InvalidInputTx)?;
+        let swap_id = swap_v2_call_param(&decoded, 0).map_to_mm(FindPaymentSpendError::InvalidInputTx)?;
+        let maker_address = match swap_v2_call_param(&decoded, 3).map_to_mm(FindPaymentSpendError::InvalidInputTx)? {
+            Token::Address(maker) => maker,
+            token => {
+                return MmError::err(FindPaymentSpendError::InvalidData(format!(
+                    "Expected maker address, found {:?}",
+                    token
+                )))
+            },
+        };
+
+        let spend_func = eth::TAKER_SWAP_V2.function(SPEND_TAKER_PAYMENT)?;
+        let spent_topic = format!("{:x}", eth::TAKER_SWAP_V2.event("TakerPaymentSpent")?.signature());
+        loop {
+            let txs = self
+                .transactions_emitted_swap_event(&spent_topic, maker_address, from_block)
+                .await
+                .map_to_mm(FindPaymentSpendError::Transport)?;
+            let found = txs.into_iter().find(|tx| {
+                swap_v2_contract_call_params(tx, &contracts.taker_swap_v2_contract, spend_func)
+                    .map(|params| params.first() == Some(&swap_id))
+                    .unwrap_or_default()
+            });
+            if let Some(spend_tx) = found {
+                return Ok(spend_tx);
+            }
+
+            let now = now_sec();
+            if now > wait_until {
+                return MmError::err(FindPaymentSpendError::Timeout { wait_until, now });
+            }
+            Timer::sleep(CHECK_EVERY).await;
+        }
+    }
+
+    /// Extracts the maker's secret from the `spendTakerPayment` contract call.
+    async fn extract_secret_v2(&self, secret_hash: &[u8], spend_tx: &Self::Tx) -> Result<[u8; 32], String> {
+        let contracts = self.swap_v2_contracts_or_err()?;
+        let function = try_s!(eth::TAKER_SWAP_V2.function(SPEND_TAKER_PAYMENT));
+        let decoded = try_s!(swap_v2_contract_call_params(
+            spend_tx,
+            &contracts.taker_swap_v2_contract,
+            function
+        ));
+        let secret: [u8; 32] = match try_s!(swap_v2_call_param(&decoded, 5)) {
+            Token::FixedBytes(secret) => try_s!(secret.as_slice().try_into()),
+            token => return ERR!("Expected secret to be fixed bytes, found {:?}", token),
+        };
+
+        let actual_secret_hash = sha256(&secret);
+        if actual_secret_hash.as_slice() != secret_hash {
+            return ERR!(
+                "Invalid 'sha256(secret)' {:?}, expected {:?}",
+                actual_secret_hash,
+                secret_hash
+            );
+        }
+        Ok(secret)
+    }
+}
+
+impl CommonSwapOpsV2 for Qrc20Coin {
+    fn derive_htlc_pubkey_v2(&self, swap_unique_data: &[u8]) -> Self::Pubkey {
+        *self.derive_htlc_key_pair(swap_unique_data).public()
+    }
+
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2_bytes(&self, swap_unique_data: &[u8]) -> Vec<u8> {
+        self.derive_htlc_pubkey_v2(swap_unique_data).to_bytes()
+    }
+
+    #[inline(always)]
+    fn taker_pubkey_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.derive_htlc_pubkey_v2(&[]).to_bytes()) // unique_data not used for non-private coins
+    }
+}
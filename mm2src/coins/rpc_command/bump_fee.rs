@@ -0,0 +1,156 @@
+use common::{HttpStatusCode, StatusCode};
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_number::BigDecimal;
+use rpc::v1::types::H256 as H256Json;
+
+use crate::utxo::rpc_clients::UtxoRpcError;
+use crate::utxo::utxo_bump_fee;
+use crate::{lp_coinfind_or_err, CoinFindError, MmCoinEnum, NumConversError, PrivKeyPolicyNotAllowed,
+            RawTransactionError, TransactionDetails, UnexpectedDerivationMethod, WithdrawFee};
+
+pub type BumpFeeResult<T> = Result<T, MmError<BumpFeeError>>;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BumpFeeMethod {
+    /// Replace the transaction with a new one that pays a higher fee (BIP-125).
+    /// All the inputs of the original transaction must be spent from my address,
+    /// the fee difference is deducted from my change output.
+    Rbf,
+    /// Spend my output of the transaction by a child transaction
+    /// that pays the fee for both the parent and the child (Child-Pays-For-Parent).
+    Cpfp,
+}
+
+#[derive(Deserialize)]
+pub struct BumpFeeRequest {
+    pub coin: String,
+    /// The hash of the unconfirmed transaction to speed up.
+    pub tx_hash: H256Json,
+    /// If not set, `rbf` is used for the transactions that signal the replaceability and `cpfp` for the rest.
+    ///
+    /// Note that the RBF replacement changes the transaction hash which the swaps track their payments by,
+    /// so `cpfp` is always used while the swaps of the coin are running and `rbf` is rejected.
+    pub method: Option<BumpFeeMethod>,
+    /// The fee rate of the replacement transaction or the package (parent + child) in case of CPFP.
+    /// The current network fee rate is used if not set.
+    pub fee: Option<WithdrawFee>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BumpFeeResponse {
+    pub method: BumpFeeMethod,
+    /// The signed transaction that should be broadcasted by `send_raw_transaction`.
+    #[serde(flatten)]
+    pub tx_details: TransactionDetails,
+}
+
+#[derive(Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum BumpFeeError {
+    #[display(fmt = "No such coin {}", coin)]
+    NoSuchCoin { coin: String },
+    #[display(fmt = "Coin {} is not supported", _0)]
+    CoinIsNotSupported(String),
+    #[display(fmt = "Transaction {} is not found", _0)]
+    TxNotFound(String),
+    #[display(fmt = "Transaction {} is already confirmed", _0)]
+    TxAlreadyConfirmed(String),
+    #[display(fmt = "Transaction {} doesn't signal replace-by-fee", _0)]
+    TxNotReplaceable(String),
+    #[display(fmt = "Transaction {} spends outputs that don't belong to my address", _0)]
+    ForeignInputs(String),
+    #[display(fmt = "Transaction {} has no unspent output to my address", _0)]
+    NoOutputToMe(String),
+    #[display(
+        fmt = "Not enough funds in my output to pay the fee: available {}, required {}",
+        available,
+        required
+    )]
+    NotEnoughFunds {
+        available: BigDecimal,
+        required: BigDecimal,
+    },
+    #[display(fmt = "The new fee {} is too low, at least {} is required", fee, required)]
+    FeeTooLow { fee: BigDecimal, required: BigDecimal },
+    #[display(fmt = "Invalid fee policy: {}", _0)]
+    InvalidFeePolicy(String),
+    #[display(fmt = "Transaction can't be replaced: {}", _0)]
+    ReplacementNotAllowed(String),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    InternalError(String),
+}
+
+impl HttpStatusCode for BumpFeeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            BumpFeeError::NoSuchCoin { .. } | BumpFeeError::TxNotFound(_) => StatusCode::NOT_FOUND,
+            BumpFeeError::CoinIsNotSupported(_)
+            | BumpFeeError::TxAlreadyConfirmed(_)
+            | BumpFeeError::TxNotReplaceable(_)
+            | BumpFeeError::ForeignInputs(_)
+            | BumpFeeError::NoOutputToMe(_)
+            | BumpFeeError::NotEnoughFunds { .. }
+            | BumpFeeError::FeeTooLow { .. }
+            | BumpFeeError::InvalidFeePolicy(_)
+            | BumpFeeError::ReplacementNotAllowed(_) => StatusCode::BAD_REQUEST,
+            BumpFeeError::Transport(_) | BumpFeeError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<CoinFindError> for BumpFeeError {
+    fn from(e: CoinFindError) -> Self {
+        match e {
+            CoinFindError::NoSuchCoin { coin } => BumpFeeError::NoSuchCoin { coin },
+        }
+    }
+}
+
+impl From<UtxoRpcError> for BumpFeeError {
+    fn from(e: UtxoRpcError) -> Self {
+        match e {
+            UtxoRpcError::Transport(transport) | UtxoRpcError::ResponseParseError(transport) => {
+                BumpFeeError::Transport(transport.to_string())
+            },
+            UtxoRpcError::InvalidResponse(rpc) => BumpFeeError::Transport(rpc),
+            UtxoRpcError::Internal(internal) => BumpFeeError::InternalError(internal),
+        }
+    }
+}
+
+impl From<RawTransactionError> for BumpFeeError {
+    fn from(e: RawTransactionError) -> Self { BumpFeeError::InternalError(e.to_string()) }
+}
+
+impl From<NumConversError> for BumpFeeError {
+    fn from(e: NumConversError) -> Self { BumpFeeError::InvalidFeePolicy(e.to_string()) }
+}
+
+impl From<PrivKeyPolicyNotAllowed> for BumpFeeError {
+    fn from(e: PrivKeyPolicyNotAllowed) -> Self { BumpFeeError::InternalError(e.to_string()) }
+}
+
+impl From<UnexpectedDerivationMethod> for BumpFeeError {
+    fn from(e: UnexpectedDerivationMethod) -> Self { BumpFeeError::InternalError(e.to_string()) }
+}
+
+impl From<keys::Error> for BumpFeeError {
+    fn from(e: keys::Error) -> Self { BumpFeeError::InternalError(e.to_string()) }
+}
+
+/// Speeds up an unconfirmed transaction of my address either by the RBF replacement or by the CPFP child.
+/// Only the activated address of the wallet is considered as "my" address.
+///
+/// Note that this function doesn't know about the running swaps,
+/// the caller must force CPFP for the coins that are being swapped.
+pub async fn bump_fee_rpc(ctx: MmArc, req: BumpFeeRequest) -> BumpFeeResult<BumpFeeResponse> {
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_bump_fee::bump_fee(&utxo, req).await,
+        _ => MmError::err(BumpFeeError::CoinIsNotSupported(req.coin)),
+    }
+}
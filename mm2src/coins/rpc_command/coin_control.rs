@@ -0,0 +1,138 @@
+use common::{HttpStatusCode, StatusCode};
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_number::BigDecimal;
+
+use crate::hd_wallet::AddressDerivingError;
+use crate::utxo::rpc_clients::UtxoRpcError;
+use crate::utxo::utxo_coin_control::{self, UnspentOutPoint};
+use crate::utxo::utxo_lock_storage::UtxoLockStorageError;
+use crate::{lp_coinfind_or_err, CoinFindError, MmCoinEnum};
+
+pub type CoinControlResult<T> = Result<T, MmError<CoinControlError>>;
+
+#[derive(Deserialize)]
+pub struct LockUnspentRequest {
+    pub coin: String,
+    pub outpoints: Vec<UnspentOutPoint>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockUnspentResponse {
+    /// All the outpoints that are locked after the request is applied.
+    pub locked: Vec<UnspentOutPoint>,
+}
+
+#[derive(Deserialize)]
+pub struct ListUnspentRequest {
+    pub coin: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnspentOutputInfo {
+    #[serde(flatten)]
+    pub outpoint: UnspentOutPoint,
+    pub value: BigDecimal,
+    /// The block height the transaction is mined in, `None` if the transaction is not mined yet.
+    pub height: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressUnspents {
+    pub address: String,
+    pub unspents: Vec<UnspentOutputInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListUnspentResponse {
+    pub coin: String,
+    /// The spendable unspent outputs of every known address, the locked ones are not included.
+    pub addresses: Vec<AddressUnspents>,
+    /// The outpoints locked by `lock_unspent`.
+    pub locked: Vec<UnspentOutPoint>,
+}
+
+#[derive(Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum CoinControlError {
+    #[display(fmt = "No such coin {}", coin)]
+    NoSuchCoin { coin: String },
+    #[display(fmt = "Coin {} is not supported", _0)]
+    CoinIsNotSupported(String),
+    #[display(fmt = "Error deriving an address: {}", _0)]
+    ErrorDerivingAddress(String),
+    #[display(fmt = "Storage error: {}", _0)]
+    StorageError(String),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    InternalError(String),
+}
+
+impl HttpStatusCode for CoinControlError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            CoinControlError::NoSuchCoin { .. } => StatusCode::NOT_FOUND,
+            CoinControlError::CoinIsNotSupported(_) => StatusCode::BAD_REQUEST,
+            CoinControlError::ErrorDerivingAddress(_)
+            | CoinControlError::StorageError(_)
+            | CoinControlError::Transport(_)
+            | CoinControlError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<CoinFindError> for CoinControlError {
+    fn from(e: CoinFindError) -> Self {
+        match e {
+            CoinFindError::NoSuchCoin { coin } => CoinControlError::NoSuchCoin { coin },
+        }
+    }
+}
+
+impl From<UtxoRpcError> for CoinControlError {
+    fn from(e: UtxoRpcError) -> Self {
+        match e {
+            UtxoRpcError::Transport(transport) | UtxoRpcError::ResponseParseError(transport) => {
+                CoinControlError::Transport(transport.to_string())
+            },
+            UtxoRpcError::InvalidResponse(rpc) => CoinControlError::Transport(rpc),
+            UtxoRpcError::Internal(internal) => CoinControlError::InternalError(internal),
+        }
+    }
+}
+
+impl From<AddressDerivingError> for CoinControlError {
+    fn from(e: AddressDerivingError) -> Self { CoinControlError::ErrorDerivingAddress(e.to_string()) }
+}
+
+impl From<UtxoLockStorageError> for CoinControlError {
+    fn from(e: UtxoLockStorageError) -> Self { CoinControlError::StorageError(e.to_string()) }
+}
+
+/// Persistently locks the given unspent outputs so they're not spent by withdrawals, swaps and other transactions.
+pub async fn lock_unspent_rpc(ctx: MmArc, req: LockUnspentRequest) -> CoinControlResult<LockUnspentResponse> {
+    let locked = match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_coin_control::lock_unspents(&ctx, &utxo, req.outpoints).await?,
+        _ => return MmError::err(CoinControlError::CoinIsNotSupported(req.coin)),
+    };
+    Ok(LockUnspentResponse { locked })
+}
+
+/// Unlocks the unspent outputs previously locked by [`lock_unspent_rpc`].
+pub async fn unlock_unspent_rpc(ctx: MmArc, req: LockUnspentRequest) -> CoinControlResult<LockUnspentResponse> {
+    let locked = match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_coin_control::unlock_unspents(&ctx, &utxo, req.outpoints).await?,
+        _ => return MmError::err(CoinControlError::CoinIsNotSupported(req.coin)),
+    };
+    Ok(LockUnspentResponse { locked })
+}
+
+/// Returns the spendable unspent outputs per address, works for both Iguana and HD wallets.
+pub async fn list_unspent_rpc(ctx: MmArc, req: ListUnspentRequest) -> CoinControlResult<ListUnspentResponse> {
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_coin_control::list_unspent(&utxo).await,
+        _ => MmError::err(CoinControlError::CoinIsNotSupported(req.coin)),
+    }
+}
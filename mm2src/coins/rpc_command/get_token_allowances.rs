@@ -0,0 +1,504 @@
+//! Listing of the non-zero ERC20 allowances granted by my addresses.
+//!
+//! Scanning the `Approval` events of a long block range takes many `eth_getLogs` requests,
+//! so the listing is an RPC task reporting the number of the blocks left to scan.
+
+use crate::eth::block_range::AdaptiveBlockRange;
+use crate::eth::erc20::get_erc20_token_info;
+use crate::eth::erc20_allowances::Erc20Approval;
+use crate::eth::{u256_to_big_decimal, EthCoin, EthCoinType, Web3RpcError};
+use crate::hd_wallet::{AddressDerivingError, DisplayAddress};
+use crate::{lp_coinfind_or_err, CoinFindError, CoinWithDerivationMethod, CoinsContext, MarketCoinOps, MmCoin,
+            MmCoinEnum, NumConversError};
+use async_trait::async_trait;
+use common::log::debug;
+use common::{HttpStatusCode, SerdeInfallible, SuccessResponse};
+use derive_more::Display;
+use ethereum_types::{Address, U256};
+use futures::compat::Future01CompatExt;
+use http::StatusCode;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_number::BigDecimal;
+use rpc_task::rpc_common::{CancelRpcTaskError, CancelRpcTaskRequest, InitRpcTaskResponse, RpcTaskStatusError,
+                           RpcTaskStatusRequest};
+use rpc_task::{RpcTask, RpcTaskError, RpcTaskHandleShared, RpcTaskManager, RpcTaskManagerShared, RpcTaskStatus,
+               RpcTaskTypes};
+use std::collections::{HashMap, HashSet};
+
+pub type TokenAllowancesUserAction = SerdeInfallible;
+pub type TokenAllowancesAwaitingStatus = SerdeInfallible;
+pub type TokenAllowancesTaskManager = RpcTaskManager<GetTokenAllowancesTask>;
+pub type TokenAllowancesTaskManagerShared = RpcTaskManagerShared<GetTokenAllowancesTask>;
+pub type TokenAllowancesTaskHandleShared = RpcTaskHandleShared<GetTokenAllowancesTask>;
+pub type TokenAllowancesRpcTaskStatus = RpcTaskStatus<
+    GetTokenAllowancesResponse,
+    TokenAllowancesError,
+    TokenAllowancesInProgressStatus,
+    TokenAllowancesAwaitingStatus,
+>;
+
+#[derive(Clone, Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum TokenAllowancesError {
+    #[display(fmt = "No such coin {}", coin)]
+    NoSuchCoin { coin: String },
+    #[display(fmt = "Coin {} is not an EVM platform coin", coin)]
+    CoinNotSupported { coin: String },
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+impl HttpStatusCode for TokenAllowancesError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TokenAllowancesError::NoSuchCoin { .. } => StatusCode::NOT_FOUND,
+            TokenAllowancesError::CoinNotSupported { .. } => StatusCode::BAD_REQUEST,
+            TokenAllowancesError::Transport(_) | TokenAllowancesError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<CoinFindError> for TokenAllowancesError {
+    fn from(e: CoinFindError) -> Self {
+        match e {
+            CoinFindError::NoSuchCoin { coin } => TokenAllowancesError::NoSuchCoin { coin },
+        }
+    }
+}
+
+impl From<Web3RpcError> for TokenAllowancesError {
+    fn from(e: Web3RpcError) -> Self {
+        match e {
+            Web3RpcError::Transport(transport) => TokenAllowancesError::Transport(transport),
+            e => TokenAllowancesError::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<AddressDerivingError> for TokenAllowancesError {
+    fn from(e: AddressDerivingError) -> Self { TokenAllowancesError::Internal(e.to_string()) }
+}
+
+impl From<NumConversError> for TokenAllowancesError {
+    fn from(e: NumConversError) -> Self { TokenAllowancesError::Internal(e.to_string()) }
+}
+
+impl From<RpcTaskError> for TokenAllowancesError {
+    fn from(e: RpcTaskError) -> Self {
+        match e {
+            RpcTaskError::Cancelled => TokenAllowancesError::Internal("Cancelled".to_owned()),
+            RpcTaskError::Internal(internal) => TokenAllowancesError::Internal(internal),
+            e => TokenAllowancesError::Internal(e.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GetTokenAllowancesRequest {
+    /// The platform coin, e.g. ETH.
+    pub coin: String,
+    /// The block to scan the `Approval` events from.
+    /// The allowances granted before this block are found only for the enabled tokens and the known spenders.
+    from_block: u64,
+    /// The last block to scan, the current block by default.
+    to_block: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TokenAllowance {
+    token_contract: String,
+    /// The ticker of the enabled token or the symbol returned by the contract.
+    ticker: Option<String>,
+    /// If the decimals are unknown, `allowance` is in the smallest units of the token.
+    decimals: Option<u8>,
+    owner: String,
+    spender: String,
+    /// The name of the known spender, e.g. `swap_contract` or `1inch_v6_0_router`.
+    spender_name: Option<String>,
+    allowance: BigDecimal,
+    /// Whether the allowance is set to (nearly) the maximum value as many dApps do.
+    is_unlimited: bool,
+}
+
+/// An approval which allowance couldn't be requested, e.g. a spam contract emitting `Approval` events
+/// for any address and reverting `allowance()`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FailedAllowanceRequest {
+    token_contract: String,
+    owner: String,
+    spender: String,
+    error: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GetTokenAllowancesResponse {
+    allowances: Vec<TokenAllowance>,
+    failed: Vec<FailedAllowanceRequest>,
+    /// The last scanned block, can be used as `from_block` later to check the new approvals only.
+    to_block: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub enum TokenAllowancesInProgressStatus {
+    ScanningApprovals { blocks_left: u64 },
+    RequestingAllowances,
+}
+
+pub struct GetTokenAllowancesTask {
+    coin: EthCoin,
+    req: GetTokenAllowancesRequest,
+    /// The spenders that the node approves the tokens to besides the swap contracts, with their names.
+    other_spenders: Vec<(&'static str, Address)>,
+}
+
+impl RpcTaskTypes for GetTokenAllowancesTask {
+    type Item = GetTokenAllowancesResponse;
+    type Error = TokenAllowancesError;
+    type InProgressStatus = TokenAllowancesInProgressStatus;
+    type AwaitingStatus = TokenAllowancesAwaitingStatus;
+    type UserAction = TokenAllowancesUserAction;
+}
+
+#[async_trait]
+impl RpcTask for GetTokenAllowancesTask {
+    fn initial_status(&self) -> Self::InProgressStatus {
+        TokenAllowancesInProgressStatus::ScanningApprovals { blocks_left: 0 }
+    }
+
+    // Do nothing if the task has been cancelled.
+    async fn cancel(self) {}
+
+    async fn run(&mut self, task_handle: TokenAllowancesTaskHandleShared) -> Result<Self::Item, MmError<Self::Error>> {
+        let to_block = match self.req.to_block {
+            Some(to_block) => to_block,
+            None => self
+                .coin
+                .current_block()
+                .compat()
+                .await
+                .map_to_mm(TokenAllowancesError::Transport)?,
+        };
+        let owners = self.coin.all_addresses().await?;
+
+        let mut approvals = scan_approvals(&self.coin, &owners, self.req.from_block, to_block, |blocks_left| {
+            task_handle
+                .update_in_progress_status(TokenAllowancesInProgressStatus::ScanningApprovals { blocks_left })
+                .mm_err(TokenAllowancesError::from)
+        })
+        .await?;
+        task_handle.update_in_progress_status(TokenAllowancesInProgressStatus::RequestingAllowances)?;
+
+        let mut known_spenders = self.coin.swap_contract_spenders();
+        known_spenders.extend(self.other_spenders.iter().copied());
+        let enabled_tokens: HashMap<Address, (String, u8)> = self
+            .coin
+            .get_erc_tokens_infos()
+            .into_iter()
+            .map(|(ticker, info)| (info.token_address, (ticker, info.decimals)))
+            .collect();
+        for token_address in enabled_tokens.keys() {
+            for owner in owners.iter() {
+                for (_, spender) in known_spenders.iter() {
+                    approvals.insert(Erc20Approval {
+                        token_address: *token_address,
+                        owner: *owner,
+                        spender: *spender,
+                    });
+                }
+            }
+        }
+
+        let (allowances, failed) = request_allowances(&self.coin, approvals, enabled_tokens, &known_spenders).await?;
+        Ok(GetTokenAllowancesResponse {
+            allowances,
+            failed,
+            to_block,
+        })
+    }
+}
+
+/// Scans `[from_block, to_block]` for the approvals made by `owners`,
+/// `on_progress` is called with the number of the blocks left before every request.
+async fn scan_approvals<F>(
+    coin: &EthCoin,
+    owners: &HashSet<Address>,
+    from_block: u64,
+    to_block: u64,
+    on_progress: F,
+) -> MmResult<HashSet<Erc20Approval>, TokenAllowancesError>
+where
+    F: Fn(u64) -> MmResult<(), TokenAllowancesError>,
+{
+    let mut approvals = HashSet::new();
+    let mut block_range = AdaptiveBlockRange::default();
+    let mut next_block = from_block;
+    while next_block <= to_block {
+        on_progress(to_block - next_block + 1)?;
+        let range_end = block_range.range_end(next_block, to_block);
+        match coin.find_erc20_approvals(owners, next_block, range_end).await {
+            Ok(found) => approvals.extend(found),
+            Err(e) if block_range.shrink() => {
+                debug!(
+                    "Error {} on eth_getLogs for blocks {}..={}, retrying with a smaller range",
+                    e, next_block, range_end
+                );
+                continue;
+            },
+            Err(e) => return Err(e.into()),
+        }
+        next_block = range_end + 1;
+        block_range.grow();
+    }
+    Ok(approvals)
+}
+
+/// Requests the current allowances of the approvals, the zero ones are skipped.
+/// A failed request doesn't stop the others, it's returned in the second list instead.
+async fn request_allowances(
+    coin: &EthCoin,
+    approvals: HashSet<Erc20Approval>,
+    enabled_tokens: HashMap<Address, (String, u8)>,
+    known_spenders: &[(&'static str, Address)],
+) -> MmResult<(Vec<TokenAllowance>, Vec<FailedAllowanceRequest>), TokenAllowancesError> {
+    let mut token_infos: HashMap<Address, (Option<String>, Option<u8>)> = enabled_tokens
+        .into_iter()
+        .map(|(token_address, (ticker, decimals))| (token_address, (Some(ticker), Some(decimals))))
+        .collect();
+    let mut allowances = Vec::new();
+    let mut failed = Vec::new();
+    for approval in approvals {
+        let wei = match coin
+            .erc20_allowance(approval.token_address, approval.owner, approval.spender)
+            .await
+        {
+            Ok(wei) => wei,
+            Err(e) => {
+                failed.push(FailedAllowanceRequest {
+                    token_contract: approval.token_address.display_address(),
+                    owner: approval.owner.display_address(),
+                    spender: approval.spender.display_address(),
+                    error: e.to_string(),
+                });
+                continue;
+            },
+        };
+        if wei.is_zero() {
+            continue;
+        }
+
+        if !token_infos.contains_key(&approval.token_address) {
+            // Not an ERC20 contract or it doesn't implement the optional methods.
+            let info = get_erc20_token_info(coin, approval.token_address)
+                .await
+                .map(|info| (Some(info.symbol), Some(info.decimals)))
+                .unwrap_or((None, None));
+            token_infos.insert(approval.token_address, info);
+        }
+        let (ticker, decimals) = token_infos[&approval.token_address].clone();
+
+        let spender_name = known_spenders
+            .iter()
+            .find(|(_, spender)| *spender == approval.spender)
+            .map(|(name, _)| name.to_string());
+        allowances.push(TokenAllowance {
+            token_contract: approval.token_address.display_address(),
+            ticker,
+            decimals,
+            owner: approval.owner.display_address(),
+            spender: approval.spender.display_address(),
+            spender_name,
+            allowance: u256_to_big_decimal(wei, decimals.unwrap_or_default())?,
+            is_unlimited: wei > U256::MAX >> 1,
+        });
+    }
+
+    allowances
+        .sort_by(|a, b| (&a.token_contract, &a.owner, &a.spender).cmp(&(&b.token_contract, &b.owner, &b.spender)));
+    failed.sort_by(|a, b| (&a.token_contract, &a.owner, &a.spender).cmp(&(&b.token_contract, &b.owner, &b.spender)));
+    Ok((allowances, failed))
+}
+
+/// Returns the enabled platform coin, the allowances of its tokens are listed.
+pub async fn find_allowances_platform_coin(ctx: &MmArc, ticker: &str) -> MmResult<EthCoin, TokenAllowancesError> {
+    match lp_coinfind_or_err(ctx, ticker).await? {
+        MmCoinEnum::EthCoin(coin) if matches!(coin.coin_type, EthCoinType::Eth) => Ok(coin),
+        _ => MmError::err(TokenAllowancesError::CoinNotSupported {
+            coin: ticker.to_owned(),
+        }),
+    }
+}
+
+/// Spawns the task listing the allowances of the `coin` tokens.
+/// The enabled tokens are checked for the swap contracts and `other_spenders` even if they aren't found by the scan.
+pub fn spawn_get_token_allowances_task(
+    ctx: &MmArc,
+    client_id: u64,
+    coin: EthCoin,
+    req: GetTokenAllowancesRequest,
+    other_spenders: Vec<(&'static str, Address)>,
+) -> MmResult<InitRpcTaskResponse, TokenAllowancesError> {
+    let coins_ctx = CoinsContext::from_ctx(ctx).map_to_mm(TokenAllowancesError::Internal)?;
+    let spawner = coin.spawner();
+    let task = GetTokenAllowancesTask {
+        coin,
+        req,
+        other_spenders,
+    };
+    let task_id = TokenAllowancesTaskManager::spawn_rpc_task(
+        &coins_ctx.token_allowances_task_manager,
+        &spawner,
+        task,
+        client_id,
+    )?;
+    Ok(InitRpcTaskResponse { task_id })
+}
+
+pub async fn get_token_allowances_status(
+    ctx: MmArc,
+    req: RpcTaskStatusRequest,
+) -> MmResult<TokenAllowancesRpcTaskStatus, RpcTaskStatusError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(RpcTaskStatusError::Internal)?;
+    let mut task_manager = coins_ctx
+        .token_allowances_task_manager
+        .lock()
+        .map_to_mm(|e| RpcTaskStatusError::Internal(e.to_string()))?;
+    task_manager
+        .task_status(req.task_id, req.forget_if_finished)
+        .or_mm_err(|| RpcTaskStatusError::NoSuchTask(req.task_id))
+}
+
+pub async fn cancel_get_token_allowances(
+    ctx: MmArc,
+    req: CancelRpcTaskRequest,
+) -> MmResult<SuccessResponse, CancelRpcTaskError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(CancelRpcTaskError::Internal)?;
+    let mut task_manager = coins_ctx
+        .token_allowances_task_manager
+        .lock()
+        .map_to_mm(|e| CancelRpcTaskError::Internal(e.to_string()))?;
+    task_manager.cancel_task(req.task_id)?;
+    Ok(SuccessResponse::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eth::for_tests::eth_coin_for_test;
+    use common::block_on;
+    use mm2_test_helpers::for_tests::ETH_SEPOLIA_CHAIN_ID;
+    use mocktopus::mocking::*;
+    use std::sync::Mutex;
+
+    /// The block ranges requested by `test_scan_approvals`.
+    static REQUESTED_RANGES: Mutex<Vec<(u64, u64)>> = Mutex::new(Vec::new());
+
+    fn eth_coin() -> EthCoin {
+        eth_coin_for_test(EthCoinType::Eth, &["http://localhost:8545"], None, ETH_SEPOLIA_CHAIN_ID).1
+    }
+
+    fn approval(token: u8, spender: u8) -> Erc20Approval {
+        Erc20Approval {
+            token_address: Address::repeat_byte(token),
+            owner: Address::repeat_byte(0xaa),
+            spender: Address::repeat_byte(spender),
+        }
+    }
+
+    #[test]
+    fn test_scan_approvals() {
+        // The node rejects the ranges wider than 2500 blocks, an approval is found in every successful range.
+        EthCoin::find_erc20_approvals.mock_safe(|_, _, from_block, to_block| {
+            REQUESTED_RANGES.lock().unwrap().push((from_block, to_block));
+            let result = if to_block - from_block >= 2500 {
+                MmError::err(Web3RpcError::Transport("Block range is too wide".to_owned()))
+            } else {
+                Ok(HashSet::from([approval(1, from_block as u8)]))
+            };
+            MockResult::Return(Box::pin(futures::future::ready(result)))
+        });
+        let coin = eth_coin();
+        let owners = HashSet::from([Address::repeat_byte(0xaa)]);
+        let progress = Mutex::new(Vec::new());
+        let on_progress = |blocks_left: u64| -> MmResult<(), TokenAllowancesError> {
+            progress.lock().unwrap().push(blocks_left);
+            Ok(())
+        };
+
+        let approvals = block_on(scan_approvals(&coin, &owners, 1, 6000, on_progress)).unwrap();
+        assert_eq!(approvals.len(), 3);
+        assert_eq!(*REQUESTED_RANGES.lock().unwrap(), vec![
+            (1, 6000),
+            (1, 5000),
+            (1, 2500),
+            (2501, 6000),
+            (2501, 5000),
+            (5001, 6000),
+        ]);
+        assert_eq!(*progress.lock().unwrap(), vec![6000, 6000, 6000, 3500, 3500, 1000]);
+
+        // The error isn't caused by the range size if the minimum range fails too.
+        EthCoin::find_erc20_approvals.mock_safe(|_, _, _, _| {
+            let error = MmError::err(Web3RpcError::Transport("Connection refused".to_owned()));
+            MockResult::Return(Box::pin(futures::future::ready(error)))
+        });
+        let error = block_on(scan_approvals(&coin, &owners, 1, 6000, |_| Ok(()))).unwrap_err();
+        assert!(matches!(error.get_inner(), TokenAllowancesError::Transport(_)));
+    }
+
+    #[test]
+    fn test_request_allowances() {
+        EthCoin::erc20_allowance.mock_safe(|_, token_address, _, spender| {
+            let result = match (token_address.0[0], spender.0[0]) {
+                (1, _) => Ok(U256::zero()),
+                (2, 3) => Ok(U256::MAX),
+                (2, _) => Ok(U256::from(1_500_000)),
+                _ => MmError::err(Web3RpcError::InvalidResponse("execution reverted".to_owned())),
+            };
+            MockResult::Return(Box::pin(futures::future::ready(result)))
+        });
+        let coin = eth_coin();
+        let approvals = HashSet::from([approval(1, 3), approval(2, 3), approval(2, 4), approval(9, 3)]);
+        let enabled_tokens = HashMap::from([
+            (Address::repeat_byte(1), ("TKN1".to_owned(), 18)),
+            (Address::repeat_byte(2), ("TKN2".to_owned(), 6)),
+            (Address::repeat_byte(9), ("SPAM".to_owned(), 18)),
+        ]);
+        let known_spenders = [("swap_contract", Address::repeat_byte(3))];
+
+        let (allowances, failed) =
+            block_on(request_allowances(&coin, approvals, enabled_tokens, &known_spenders)).unwrap();
+        let token2 = Address::repeat_byte(2).display_address();
+        let owner = Address::repeat_byte(0xaa).display_address();
+        assert_eq!(allowances, vec![
+            TokenAllowance {
+                token_contract: token2.clone(),
+                ticker: Some("TKN2".to_owned()),
+                decimals: Some(6),
+                owner: owner.clone(),
+                spender: Address::repeat_byte(3).display_address(),
+                spender_name: Some("swap_contract".to_owned()),
+                allowance: u256_to_big_decimal(U256::MAX, 6).unwrap(),
+                is_unlimited: true,
+            },
+            TokenAllowance {
+                token_contract: token2,
+                ticker: Some("TKN2".to_owned()),
+                decimals: Some(6),
+                owner: owner.clone(),
+                spender: Address::repeat_byte(4).display_address(),
+                spender_name: None,
+                allowance: "1.5".parse().unwrap(),
+                is_unlimited: false,
+            },
+        ]);
+
+        // The reverting contract doesn't break the listing.
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].token_contract, Address::repeat_byte(9).display_address());
+        assert_eq!(failed[0].owner, owner);
+        assert!(failed[0].error.contains("execution reverted"));
+    }
+}
@@ -1,11 +1,17 @@
 pub mod account_balance;
+pub mod bump_fee;
+pub mod coin_control;
 pub mod get_current_mtp;
 pub mod get_enabled_coins;
 pub mod get_new_address;
+pub mod get_token_allowances;
 pub mod hd_account_balance_rpc_error;
 pub mod init_account_balance;
 pub mod init_create_account;
 pub mod init_scan_for_new_addresses;
 pub mod init_withdraw;
 #[cfg(not(target_arch = "wasm32"))] pub mod lightning;
+pub mod multisig;
+pub mod psbt;
+pub mod sign_eth_message;
 pub mod tendermint;
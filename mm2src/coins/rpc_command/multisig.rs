@@ -0,0 +1,367 @@
+use async_trait::async_trait;
+use common::{true_f, HttpStatusCode, SuccessResponse};
+use crypto::hw_rpc_task::{HwRpcTaskAwaitingStatus, HwRpcTaskUserAction, HwRpcTaskUserActionRequest};
+use crypto::trezor::TrezorProcessingError;
+use crypto::{from_hw_error, Bip44Chain, CryptoCtxError, HwError, HwProcessingError, HwRpcError, RpcDerivationPath,
+             WithHwRpcError};
+use derive_more::Display;
+use enum_derives::EnumFromTrait;
+use http::StatusCode;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_number::BigDecimal;
+use rpc::v1::types::Bytes as BytesJson;
+use rpc_task::rpc_common::{CancelRpcTaskError, CancelRpcTaskRequest, InitRpcTaskResponse, RpcTaskStatusError,
+                           RpcTaskStatusRequest, RpcTaskUserActionError};
+use rpc_task::{RpcInitReq, RpcTask, RpcTaskError, RpcTaskHandleShared, RpcTaskManager, RpcTaskManagerShared,
+               RpcTaskStatus, RpcTaskTypes};
+use std::time::Duration;
+use utxo_signer::psbt::PsbtError;
+use utxo_signer::UtxoSignTxError;
+
+use crate::utxo::rpc_clients::UtxoRpcError;
+use crate::utxo::utxo_multisig::{self, MultisigScriptType};
+use crate::{lp_coinfind_or_err, CoinFindError, CoinsContext, MmCoinEnum, UnexpectedDerivationMethod, WithdrawError,
+            WithdrawRequest, WithdrawResult};
+
+pub type MultisigRpcResult<T> = Result<T, MmError<MultisigRpcError>>;
+pub type SignMultisigPsbtUserAction = HwRpcTaskUserAction;
+pub type SignMultisigPsbtAwaitingStatus = HwRpcTaskAwaitingStatus;
+pub type SignMultisigPsbtTaskManager = RpcTaskManager<SignMultisigPsbtTask>;
+pub type SignMultisigPsbtTaskManagerShared = RpcTaskManagerShared<SignMultisigPsbtTask>;
+pub type SignMultisigPsbtTaskHandleShared = RpcTaskHandleShared<SignMultisigPsbtTask>;
+pub type SignMultisigPsbtRpcTaskStatus = RpcTaskStatus<
+    SignMultisigPsbtResponse,
+    MultisigRpcError,
+    SignMultisigPsbtInProgressStatus,
+    SignMultisigPsbtAwaitingStatus,
+>;
+
+/// The default number of addresses returned by `get_multisig_addresses`.
+const DEFAULT_ADDRESSES_COUNT: u32 = 20;
+
+#[derive(Clone, Debug, Display, EnumFromTrait, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum MultisigRpcError {
+    #[display(fmt = "No such coin {}", coin)]
+    NoSuchCoin { coin: String },
+    #[display(fmt = "Coin {} is not supported", _0)]
+    CoinIsNotSupported(String),
+    #[display(fmt = "'{}' coin isn't activated as a multisig wallet", coin)]
+    CoinIsNotMultisig { coin: String },
+    #[display(fmt = "Invalid request: {}", _0)]
+    InvalidRequest(String),
+    #[display(fmt = "Invalid PSBT: {}", _0)]
+    InvalidPsbt(String),
+    #[display(fmt = "Hardware Wallet context is not initialized")]
+    HwContextNotInitialized,
+    #[display(fmt = "RPC 'task' is awaiting '{}' user action", expected)]
+    UnexpectedUserAction { expected: String },
+    #[from_trait(WithTimeout::timeout)]
+    #[display(fmt = "RPC timed out {:?}", _0)]
+    Timeout(Duration),
+    #[from_trait(WithHwRpcError::hw_rpc_error)]
+    #[display(fmt = "{}", _0)]
+    HwError(HwRpcError),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[from_trait(WithInternal::internal)]
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+impl HttpStatusCode for MultisigRpcError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            MultisigRpcError::NoSuchCoin { .. } => StatusCode::NOT_FOUND,
+            MultisigRpcError::CoinIsNotSupported(_)
+            | MultisigRpcError::CoinIsNotMultisig { .. }
+            | MultisigRpcError::InvalidRequest(_)
+            | MultisigRpcError::InvalidPsbt(_)
+            | MultisigRpcError::HwContextNotInitialized
+            | MultisigRpcError::UnexpectedUserAction { .. } => StatusCode::BAD_REQUEST,
+            MultisigRpcError::HwError(_) => StatusCode::GONE,
+            MultisigRpcError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+            MultisigRpcError::Transport(_) | MultisigRpcError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<CoinFindError> for MultisigRpcError {
+    fn from(e: CoinFindError) -> Self {
+        match e {
+            CoinFindError::NoSuchCoin { coin } => MultisigRpcError::NoSuchCoin { coin },
+        }
+    }
+}
+
+impl From<UtxoRpcError> for MultisigRpcError {
+    fn from(e: UtxoRpcError) -> Self {
+        match e {
+            UtxoRpcError::Transport(transport) | UtxoRpcError::ResponseParseError(transport) => {
+                MultisigRpcError::Transport(transport.to_string())
+            },
+            UtxoRpcError::InvalidResponse(rpc) => MultisigRpcError::Transport(rpc),
+            UtxoRpcError::Internal(internal) => MultisigRpcError::Internal(internal),
+        }
+    }
+}
+
+impl From<PsbtError> for MultisigRpcError {
+    fn from(e: PsbtError) -> Self { MultisigRpcError::InvalidPsbt(e.to_string()) }
+}
+
+impl From<UtxoSignTxError> for MultisigRpcError {
+    fn from(e: UtxoSignTxError) -> Self {
+        match e {
+            UtxoSignTxError::TrezorError(trezor) => MultisigRpcError::from(HwError::from(trezor)),
+            UtxoSignTxError::PsbtError(psbt) => MultisigRpcError::from(psbt),
+            UtxoSignTxError::Transport(transport) => MultisigRpcError::Transport(transport),
+            sign_err => MultisigRpcError::Internal(sign_err.to_string()),
+        }
+    }
+}
+
+impl From<UnexpectedDerivationMethod> for MultisigRpcError {
+    fn from(e: UnexpectedDerivationMethod) -> Self { MultisigRpcError::Internal(e.to_string()) }
+}
+
+impl From<RpcTaskError> for MultisigRpcError {
+    fn from(e: RpcTaskError) -> Self {
+        let error = e.to_string();
+        match e {
+            RpcTaskError::Cancelled => MultisigRpcError::Internal("Cancelled".to_owned()),
+            RpcTaskError::Timeout(timeout) => MultisigRpcError::Timeout(timeout),
+            RpcTaskError::NoSuchTask(_) | RpcTaskError::UnexpectedTaskStatus { .. } => {
+                MultisigRpcError::Internal(error)
+            },
+            RpcTaskError::UnexpectedUserAction { expected } => MultisigRpcError::UnexpectedUserAction { expected },
+            RpcTaskError::Internal(internal) => MultisigRpcError::Internal(internal),
+        }
+    }
+}
+
+impl From<HwError> for MultisigRpcError {
+    fn from(e: HwError) -> Self { from_hw_error(e) }
+}
+
+impl From<HwProcessingError<RpcTaskError>> for MultisigRpcError {
+    fn from(e: HwProcessingError<RpcTaskError>) -> Self {
+        match e {
+            HwProcessingError::HwError(hw) => MultisigRpcError::from(hw),
+            HwProcessingError::ProcessorError(rpc_task) => MultisigRpcError::from(rpc_task),
+            HwProcessingError::InternalError(err) => MultisigRpcError::Internal(err),
+        }
+    }
+}
+
+impl From<TrezorProcessingError<RpcTaskError>> for MultisigRpcError {
+    fn from(e: TrezorProcessingError<RpcTaskError>) -> Self {
+        match e {
+            TrezorProcessingError::TrezorError(trezor) => MultisigRpcError::from(HwError::from(trezor)),
+            TrezorProcessingError::ProcessorError(rpc_task) => MultisigRpcError::from(rpc_task),
+        }
+    }
+}
+
+impl From<CryptoCtxError> for MultisigRpcError {
+    fn from(e: CryptoCtxError) -> Self { MultisigRpcError::Internal(e.to_string()) }
+}
+
+#[derive(Deserialize)]
+pub struct GetMultisigAddressesRequest {
+    pub coin: String,
+    #[serde(default = "external_chain")]
+    pub chain: Bip44Chain,
+    #[serde(default)]
+    pub from_id: u32,
+    pub count: Option<u32>,
+}
+
+fn external_chain() -> Bip44Chain { Bip44Chain::External }
+
+#[derive(Debug, Serialize)]
+pub struct MultisigAddressInfo {
+    pub address: String,
+    pub chain: Bip44Chain,
+    pub address_id: u32,
+    /// The derivation path of this wallet's key of the address.
+    pub derivation_path: RpcDerivationPath,
+    /// The redeem script of a P2SH address or the witness script of a P2WSH address.
+    pub script: BytesJson,
+    pub balance: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetMultisigAddressesResponse {
+    pub required: usize,
+    pub cosigners: usize,
+    pub script_type: MultisigScriptType,
+    pub addresses: Vec<MultisigAddressInfo>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MultisigInputSignature {
+    pub input_index: usize,
+    /// The DER encoded signature without the sighash type, e.g. returned by a hardware wallet.
+    pub signature: BytesJson,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SignMultisigPsbtRequest {
+    pub coin: String,
+    /// The base64 encoded PSBTs of the same transaction, e.g. signed by different cosigners.
+    /// They're combined into one before signing.
+    pub psbts: Vec<String>,
+    /// The signatures made by the other cosigners outside of a PSBT.
+    #[serde(default)]
+    pub signatures: Vec<MultisigInputSignature>,
+    /// Whether to sign the inputs with this wallet's keys.
+    #[serde(default = "true_f")]
+    pub sign: bool,
+    /// The outputs the transaction is expected to pay to, except the change returned to the multisig wallet.
+    /// This wallet's inputs are signed only if the transaction pays exactly these amounts to these addresses.
+    #[serde(default)]
+    pub outputs: Vec<MultisigPsbtOutput>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct MultisigPsbtOutput {
+    pub address: String,
+    pub amount: BigDecimal,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SignMultisigPsbtResponse {
+    /// The base64 encoded PSBT containing all the known signatures.
+    pub psbt: String,
+    /// The inputs that still don't have the required number of signatures.
+    /// If it's empty, the PSBT can be passed to `finalize_psbt`.
+    pub not_signed_inputs: Vec<usize>,
+}
+
+#[derive(Clone, Serialize)]
+pub enum SignMultisigPsbtInProgressStatus {
+    Preparing,
+    SigningTransaction,
+    Finishing,
+    /// The following statuses don't require the user to send `UserAction`,
+    /// but they tell the user that he should confirm/decline the operation on his device.
+    WaitingForTrezorToConnect,
+    FollowHwDeviceInstructions,
+    WaitingForUserToConfirmSigning,
+}
+
+pub struct SignMultisigPsbtTask {
+    ctx: MmArc,
+    coin: MmCoinEnum,
+    req: SignMultisigPsbtRequest,
+}
+
+impl RpcTaskTypes for SignMultisigPsbtTask {
+    type Item = SignMultisigPsbtResponse;
+    type Error = MultisigRpcError;
+    type InProgressStatus = SignMultisigPsbtInProgressStatus;
+    type AwaitingStatus = SignMultisigPsbtAwaitingStatus;
+    type UserAction = SignMultisigPsbtUserAction;
+}
+
+#[async_trait]
+impl RpcTask for SignMultisigPsbtTask {
+    fn initial_status(&self) -> Self::InProgressStatus { SignMultisigPsbtInProgressStatus::Preparing }
+
+    // Do nothing if the task has been cancelled.
+    async fn cancel(self) {}
+
+    async fn run(&mut self, task_handle: SignMultisigPsbtTaskHandleShared) -> Result<Self::Item, MmError<Self::Error>> {
+        match self.coin {
+            MmCoinEnum::UtxoCoin(ref utxo) => {
+                utxo_multisig::sign_multisig_psbt(&self.ctx, utxo, self.req.clone(), task_handle).await
+            },
+            _ => MmError::err(MultisigRpcError::CoinIsNotSupported(self.req.coin.clone())),
+        }
+    }
+}
+
+/// Returns the m-of-n multisig addresses of the given chain with their spendable balances.
+pub async fn get_multisig_addresses_rpc(
+    ctx: MmArc,
+    req: GetMultisigAddressesRequest,
+) -> MultisigRpcResult<GetMultisigAddressesResponse> {
+    let count = req.count.unwrap_or(DEFAULT_ADDRESSES_COUNT);
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_multisig::get_multisig_addresses(&utxo, req.chain, req.from_id, count).await,
+        _ => MmError::err(MultisigRpcError::CoinIsNotSupported(req.coin)),
+    }
+}
+
+/// Generates the withdrawal from the multisig addresses as an unsigned PSBT to be signed by the cosigners.
+/// The PSBT is returned as `tx.psbt` of the transaction details instead of the signed transaction.
+pub async fn withdraw_multisig_rpc(ctx: MmArc, req: WithdrawRequest) -> WithdrawResult {
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_multisig::withdraw_multisig(&ctx, &utxo, req).await,
+        _ => MmError::err(WithdrawError::CoinIsNotMultisig { coin: req.coin }),
+    }
+}
+
+pub async fn init_sign_multisig_psbt(
+    ctx: MmArc,
+    req: RpcInitReq<SignMultisigPsbtRequest>,
+) -> MultisigRpcResult<InitRpcTaskResponse> {
+    let (client_id, req) = (req.client_id, req.inner);
+    let coin = lp_coinfind_or_err(&ctx, &req.coin).await?;
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(MultisigRpcError::Internal)?;
+    let spawner = coin.spawner();
+    let task = SignMultisigPsbtTask {
+        ctx: ctx.clone(),
+        coin,
+        req,
+    };
+    let task_id = SignMultisigPsbtTaskManager::spawn_rpc_task(
+        &coins_ctx.sign_multisig_psbt_task_manager,
+        &spawner,
+        task,
+        client_id,
+    )?;
+    Ok(InitRpcTaskResponse { task_id })
+}
+
+pub async fn sign_multisig_psbt_status(
+    ctx: MmArc,
+    req: RpcTaskStatusRequest,
+) -> MmResult<SignMultisigPsbtRpcTaskStatus, RpcTaskStatusError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(RpcTaskStatusError::Internal)?;
+    let mut task_manager = coins_ctx
+        .sign_multisig_psbt_task_manager
+        .lock()
+        .map_to_mm(|e| RpcTaskStatusError::Internal(e.to_string()))?;
+    task_manager
+        .task_status(req.task_id, req.forget_if_finished)
+        .or_mm_err(|| RpcTaskStatusError::NoSuchTask(req.task_id))
+}
+
+pub async fn sign_multisig_psbt_user_action(
+    ctx: MmArc,
+    req: HwRpcTaskUserActionRequest,
+) -> MmResult<SuccessResponse, RpcTaskUserActionError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(RpcTaskUserActionError::Internal)?;
+    let mut task_manager = coins_ctx
+        .sign_multisig_psbt_task_manager
+        .lock()
+        .map_to_mm(|e| RpcTaskUserActionError::Internal(e.to_string()))?;
+    task_manager.on_user_action(req.task_id, req.user_action)?;
+    Ok(SuccessResponse::new())
+}
+
+pub async fn cancel_sign_multisig_psbt(
+    ctx: MmArc,
+    req: CancelRpcTaskRequest,
+) -> MmResult<SuccessResponse, CancelRpcTaskError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(CancelRpcTaskError::Internal)?;
+    let mut task_manager = coins_ctx
+        .sign_multisig_psbt_task_manager
+        .lock()
+        .map_to_mm(|e| CancelRpcTaskError::Internal(e.to_string()))?;
+    task_manager.cancel_task(req.task_id)?;
+    Ok(SuccessResponse::new())
+}
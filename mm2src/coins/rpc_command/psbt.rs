@@ -0,0 +1,108 @@
+use common::{HttpStatusCode, StatusCode};
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use rpc::v1::types::Bytes as BytesJson;
+use utxo_signer::psbt::PsbtError;
+
+use crate::utxo::rpc_clients::UtxoRpcError;
+use crate::utxo::utxo_psbt;
+use crate::utxo::utxo_withdraw::PsbtUtxoWithdraw;
+use crate::{lp_coinfind_or_err, CoinFindError, MmCoinEnum, WithdrawError, WithdrawRequest, WithdrawResult};
+
+pub type FinalizePsbtResult<T> = Result<T, MmError<FinalizePsbtError>>;
+
+#[derive(Deserialize)]
+pub struct FinalizePsbtRequest {
+    pub coin: String,
+    /// The base64 encoded PSBT containing the signatures of every input.
+    pub psbt: String,
+    /// Whether to broadcast the signed transaction, otherwise it can be broadcasted by `send_raw_transaction` later.
+    #[serde(default)]
+    pub broadcast: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinalizePsbtResponse {
+    pub tx_hex: BytesJson,
+    pub tx_hash: String,
+    pub broadcasted: bool,
+}
+
+#[derive(Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum FinalizePsbtError {
+    #[display(fmt = "No such coin {}", coin)]
+    NoSuchCoin { coin: String },
+    #[display(fmt = "Coin {} is not supported", _0)]
+    CoinIsNotSupported(String),
+    #[display(fmt = "Invalid PSBT: {}", _0)]
+    InvalidPsbt(String),
+    #[display(
+        fmt = "PSBT is not complete, the following inputs are not signed: {:?}",
+        not_signed_inputs
+    )]
+    PsbtIsNotComplete { not_signed_inputs: Vec<usize> },
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    InternalError(String),
+}
+
+impl HttpStatusCode for FinalizePsbtError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            FinalizePsbtError::NoSuchCoin { .. } => StatusCode::NOT_FOUND,
+            FinalizePsbtError::CoinIsNotSupported(_)
+            | FinalizePsbtError::InvalidPsbt(_)
+            | FinalizePsbtError::PsbtIsNotComplete { .. } => StatusCode::BAD_REQUEST,
+            FinalizePsbtError::Transport(_) | FinalizePsbtError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<CoinFindError> for FinalizePsbtError {
+    fn from(e: CoinFindError) -> Self {
+        match e {
+            CoinFindError::NoSuchCoin { coin } => FinalizePsbtError::NoSuchCoin { coin },
+        }
+    }
+}
+
+impl From<PsbtError> for FinalizePsbtError {
+    fn from(e: PsbtError) -> Self {
+        match e {
+            PsbtError::NotSigned(not_signed_inputs) => FinalizePsbtError::PsbtIsNotComplete { not_signed_inputs },
+            psbt => FinalizePsbtError::InvalidPsbt(psbt.to_string()),
+        }
+    }
+}
+
+impl From<UtxoRpcError> for FinalizePsbtError {
+    fn from(e: UtxoRpcError) -> Self {
+        match e {
+            UtxoRpcError::Transport(transport) | UtxoRpcError::ResponseParseError(transport) => {
+                FinalizePsbtError::Transport(transport.to_string())
+            },
+            UtxoRpcError::InvalidResponse(rpc) => FinalizePsbtError::Transport(rpc),
+            UtxoRpcError::Internal(internal) => FinalizePsbtError::InternalError(internal),
+        }
+    }
+}
+
+/// Generates the withdrawal as an unsigned PSBT containing the derivation paths of the keys required to sign it.
+/// The PSBT is returned as `tx.psbt` of the transaction details instead of the signed transaction.
+pub async fn withdraw_psbt_rpc(ctx: MmArc, req: WithdrawRequest) -> WithdrawResult {
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => PsbtUtxoWithdraw::new(ctx, utxo, req).await?.build().await,
+        _ => MmError::err(WithdrawError::CoinDoesntSupportPsbt { coin: req.coin }),
+    }
+}
+
+/// Finalizes the PSBT signed by an external signer and optionally broadcasts the signed transaction.
+pub async fn finalize_psbt_rpc(ctx: MmArc, req: FinalizePsbtRequest) -> FinalizePsbtResult<FinalizePsbtResponse> {
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::UtxoCoin(utxo) => utxo_psbt::finalize_psbt(&ctx, &utxo, req).await,
+        _ => MmError::err(FinalizePsbtError::CoinIsNotSupported(req.coin)),
+    }
+}
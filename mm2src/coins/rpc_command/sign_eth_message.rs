@@ -0,0 +1,299 @@
+use async_trait::async_trait;
+use common::{HttpStatusCode, SuccessResponse};
+use crypto::hw_rpc_task::{HwRpcTaskAwaitingStatus, HwRpcTaskUserAction, HwRpcTaskUserActionRequest};
+use crypto::trezor::{TrezorError, TrezorProcessingError};
+use crypto::{from_hw_error, CryptoCtxError, HwError, HwProcessingError, HwRpcError, WithHwRpcError};
+use derive_more::Display;
+use enum_derives::EnumFromTrait;
+use http::StatusCode;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use rpc_task::rpc_common::{CancelRpcTaskError, CancelRpcTaskRequest, InitRpcTaskResponse, RpcTaskStatusError,
+                           RpcTaskStatusRequest, RpcTaskUserActionError};
+use rpc_task::{RpcInitReq, RpcTask, RpcTaskError, RpcTaskHandleShared, RpcTaskManager, RpcTaskManagerShared,
+               RpcTaskStatus, RpcTaskTypes};
+use std::time::Duration;
+
+use crate::eth::eth_sign_message::{self, EthSignPayload};
+use crate::{lp_coinfind_or_err, CoinFindError, CoinsContext, MmCoinEnum, UnexpectedDerivationMethod};
+
+pub type EthSignMessageResult<T> = Result<T, MmError<EthSignMessageError>>;
+pub type SignEthMessageUserAction = HwRpcTaskUserAction;
+pub type SignEthMessageAwaitingStatus = HwRpcTaskAwaitingStatus;
+pub type SignEthMessageTaskManager = RpcTaskManager<SignEthMessageTask>;
+pub type SignEthMessageTaskManagerShared = RpcTaskManagerShared<SignEthMessageTask>;
+pub type SignEthMessageTaskHandleShared = RpcTaskHandleShared<SignEthMessageTask>;
+pub type SignEthMessageRpcTaskStatus = RpcTaskStatus<
+    SignEthMessageResponse,
+    EthSignMessageError,
+    SignEthMessageInProgressStatus,
+    SignEthMessageAwaitingStatus,
+>;
+
+#[derive(Clone, Debug, Display, EnumFromTrait, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum EthSignMessageError {
+    #[display(fmt = "No such coin {}", coin)]
+    NoSuchCoin { coin: String },
+    #[display(fmt = "Coin {} is not supported", _0)]
+    CoinIsNotSupported(String),
+    #[display(fmt = "Invalid request: {}", _0)]
+    InvalidRequest(String),
+    #[display(fmt = "Invalid signature: {}", _0)]
+    InvalidSignature(String),
+    #[display(
+        fmt = "Signing with the '{}' key policy is not supported by this method: {}",
+        policy,
+        reason
+    )]
+    KeyPolicyNotSupported { policy: String, reason: String },
+    #[display(fmt = "RPC 'task' is awaiting '{}' user action", expected)]
+    UnexpectedUserAction { expected: String },
+    #[from_trait(WithTimeout::timeout)]
+    #[display(fmt = "RPC timed out {:?}", _0)]
+    Timeout(Duration),
+    #[from_trait(WithHwRpcError::hw_rpc_error)]
+    #[display(fmt = "{}", _0)]
+    HwError(HwRpcError),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[from_trait(WithInternal::internal)]
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+impl HttpStatusCode for EthSignMessageError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            EthSignMessageError::NoSuchCoin { .. } => StatusCode::NOT_FOUND,
+            EthSignMessageError::CoinIsNotSupported(_)
+            | EthSignMessageError::InvalidRequest(_)
+            | EthSignMessageError::InvalidSignature(_)
+            | EthSignMessageError::KeyPolicyNotSupported { .. }
+            | EthSignMessageError::UnexpectedUserAction { .. } => StatusCode::BAD_REQUEST,
+            EthSignMessageError::HwError(_) => StatusCode::GONE,
+            EthSignMessageError::Timeout(_) => StatusCode::REQUEST_TIMEOUT,
+            EthSignMessageError::Transport(_) | EthSignMessageError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<CoinFindError> for EthSignMessageError {
+    fn from(e: CoinFindError) -> Self {
+        match e {
+            CoinFindError::NoSuchCoin { coin } => EthSignMessageError::NoSuchCoin { coin },
+        }
+    }
+}
+
+impl From<web3::Error> for EthSignMessageError {
+    fn from(e: web3::Error) -> Self { EthSignMessageError::Transport(e.to_string()) }
+}
+
+impl From<ethkey::Error> for EthSignMessageError {
+    fn from(e: ethkey::Error) -> Self { EthSignMessageError::InvalidSignature(e.to_string()) }
+}
+
+impl From<UnexpectedDerivationMethod> for EthSignMessageError {
+    fn from(e: UnexpectedDerivationMethod) -> Self { EthSignMessageError::Internal(e.to_string()) }
+}
+
+impl From<RpcTaskError> for EthSignMessageError {
+    fn from(e: RpcTaskError) -> Self {
+        let error = e.to_string();
+        match e {
+            RpcTaskError::Cancelled => EthSignMessageError::Internal("Cancelled".to_owned()),
+            RpcTaskError::Timeout(timeout) => EthSignMessageError::Timeout(timeout),
+            RpcTaskError::NoSuchTask(_) | RpcTaskError::UnexpectedTaskStatus { .. } => {
+                EthSignMessageError::Internal(error)
+            },
+            RpcTaskError::UnexpectedUserAction { expected } => EthSignMessageError::UnexpectedUserAction { expected },
+            RpcTaskError::Internal(internal) => EthSignMessageError::Internal(internal),
+        }
+    }
+}
+
+impl From<HwError> for EthSignMessageError {
+    fn from(e: HwError) -> Self { from_hw_error(e) }
+}
+
+impl From<TrezorError> for EthSignMessageError {
+    fn from(e: TrezorError) -> Self { EthSignMessageError::from(HwError::from(e)) }
+}
+
+impl From<HwProcessingError<RpcTaskError>> for EthSignMessageError {
+    fn from(e: HwProcessingError<RpcTaskError>) -> Self {
+        match e {
+            HwProcessingError::HwError(hw) => EthSignMessageError::from(hw),
+            HwProcessingError::ProcessorError(rpc_task) => EthSignMessageError::from(rpc_task),
+            HwProcessingError::InternalError(err) => EthSignMessageError::Internal(err),
+        }
+    }
+}
+
+impl From<TrezorProcessingError<RpcTaskError>> for EthSignMessageError {
+    fn from(e: TrezorProcessingError<RpcTaskError>) -> Self {
+        match e {
+            TrezorProcessingError::TrezorError(trezor) => EthSignMessageError::from(trezor),
+            TrezorProcessingError::ProcessorError(rpc_task) => EthSignMessageError::from(rpc_task),
+        }
+    }
+}
+
+impl From<CryptoCtxError> for EthSignMessageError {
+    fn from(e: CryptoCtxError) -> Self { EthSignMessageError::Internal(e.to_string()) }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SignEthMessageRequest {
+    pub coin: String,
+    #[serde(flatten)]
+    pub payload: EthSignPayload,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SignEthMessageResponse {
+    /// The 65-byte `r ‖ s ‖ v` signature with `v` being 27 or 28, as wallets return it.
+    pub signature: String,
+    /// The checksummed address of the signer.
+    pub signer: String,
+    /// The hash that has been signed.
+    pub hash: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEthMessageRequest {
+    #[serde(flatten)]
+    pub payload: EthSignPayload,
+    pub signature: String,
+    /// If set, the recovered signer is compared with this address.
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyEthMessageResponse {
+    /// The checksummed address recovered from the signature.
+    pub signer: String,
+    /// Whether the `signer` is the requested `address`. `None` if the address isn't specified.
+    pub is_valid: Option<bool>,
+}
+
+#[derive(Clone, Serialize)]
+pub enum SignEthMessageInProgressStatus {
+    Preparing,
+    Finishing,
+    /// The following statuses don't require the user to send `UserAction`,
+    /// but they tell the user to confirm/decline the operation on the device.
+    WaitingForTrezorToConnect,
+    FollowHwDeviceInstructions,
+    WaitingForUserToConfirmSigning,
+}
+
+pub struct SignEthMessageTask {
+    ctx: MmArc,
+    coin: MmCoinEnum,
+    req: SignEthMessageRequest,
+}
+
+impl RpcTaskTypes for SignEthMessageTask {
+    type Item = SignEthMessageResponse;
+    type Error = EthSignMessageError;
+    type InProgressStatus = SignEthMessageInProgressStatus;
+    type AwaitingStatus = SignEthMessageAwaitingStatus;
+    type UserAction = SignEthMessageUserAction;
+}
+
+#[async_trait]
+impl RpcTask for SignEthMessageTask {
+    fn initial_status(&self) -> Self::InProgressStatus { SignEthMessageInProgressStatus::Preparing }
+
+    // Do nothing if the task has been cancelled.
+    async fn cancel(self) {}
+
+    async fn run(&mut self, task_handle: SignEthMessageTaskHandleShared) -> Result<Self::Item, MmError<Self::Error>> {
+        match self.coin {
+            MmCoinEnum::EthCoin(ref eth) => {
+                eth_sign_message::sign_eth_message_with_task(&self.ctx, eth, &self.req.payload, task_handle).await
+            },
+            _ => MmError::err(EthSignMessageError::CoinIsNotSupported(self.req.coin.clone())),
+        }
+    }
+}
+
+/// Signs an EIP-191 message or EIP-712 typed data with the Iguana, HD or MetaMask key policy.
+/// Trezor requires user interaction, so `task::sign_eth_message::init` should be used instead.
+pub async fn sign_eth_message_rpc(
+    ctx: MmArc,
+    req: SignEthMessageRequest,
+) -> EthSignMessageResult<SignEthMessageResponse> {
+    match lp_coinfind_or_err(&ctx, &req.coin).await? {
+        MmCoinEnum::EthCoin(eth) => eth_sign_message::sign_eth_message(&eth, &req.payload).await,
+        _ => MmError::err(EthSignMessageError::CoinIsNotSupported(req.coin)),
+    }
+}
+
+/// Recovers the signer of an EIP-191 message or EIP-712 typed data signature.
+pub async fn verify_eth_message_rpc(
+    _ctx: MmArc,
+    req: VerifyEthMessageRequest,
+) -> EthSignMessageResult<VerifyEthMessageResponse> {
+    eth_sign_message::verify_eth_message(req)
+}
+
+pub async fn init_sign_eth_message(
+    ctx: MmArc,
+    req: RpcInitReq<SignEthMessageRequest>,
+) -> EthSignMessageResult<InitRpcTaskResponse> {
+    let (client_id, req) = (req.client_id, req.inner);
+    let coin = lp_coinfind_or_err(&ctx, &req.coin).await?;
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(EthSignMessageError::Internal)?;
+    let spawner = coin.spawner();
+    let task = SignEthMessageTask {
+        ctx: ctx.clone(),
+        coin,
+        req,
+    };
+    let task_id =
+        SignEthMessageTaskManager::spawn_rpc_task(&coins_ctx.sign_eth_message_task_manager, &spawner, task, client_id)?;
+    Ok(InitRpcTaskResponse { task_id })
+}
+
+pub async fn sign_eth_message_status(
+    ctx: MmArc,
+    req: RpcTaskStatusRequest,
+) -> MmResult<SignEthMessageRpcTaskStatus, RpcTaskStatusError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(RpcTaskStatusError::Internal)?;
+    let mut task_manager = coins_ctx
+        .sign_eth_message_task_manager
+        .lock()
+        .map_to_mm(|e| RpcTaskStatusError::Internal(e.to_string()))?;
+    task_manager
+        .task_status(req.task_id, req.forget_if_finished)
+        .or_mm_err(|| RpcTaskStatusError::NoSuchTask(req.task_id))
+}
+
+pub async fn sign_eth_message_user_action(
+    ctx: MmArc,
+    req: HwRpcTaskUserActionRequest,
+) -> MmResult<SuccessResponse, RpcTaskUserActionError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(RpcTaskUserActionError::Internal)?;
+    let mut task_manager = coins_ctx
+        .sign_eth_message_task_manager
+        .lock()
+        .map_to_mm(|e| RpcTaskUserActionError::Internal(e.to_string()))?;
+    task_manager.on_user_action(req.task_id, req.user_action)?;
+    Ok(SuccessResponse::new())
+}
+
+pub async fn cancel_sign_eth_message(
+    ctx: MmArc,
+    req: CancelRpcTaskRequest,
+) -> MmResult<SuccessResponse, CancelRpcTaskError> {
+    let coins_ctx = CoinsContext::from_ctx(&ctx).map_to_mm(CancelRpcTaskError::Internal)?;
+    let mut task_manager = coins_ctx
+        .sign_eth_message_task_manager
+        .lock()
+        .map_to_mm(|e| CancelRpcTaskError::Internal(e.to_string()))?;
+    task_manager.cancel_task(req.task_id)?;
+    Ok(SuccessResponse::new())
+}
@@ -1,30 +1,56 @@
 use super::{BalanceError, CoinBalance, HistorySyncState, MarketCoinOps, MmCoin, RawTransactionFut,
             RawTransactionRequest, SwapOps, TradeFee, TransactionEnum};
-use crate::{coin_errors::MyAddressError, BalanceFut, CanRefundHtlc, CheckIfMyPaymentSentArgs, ConfirmPaymentInput,
-            DexFee, FeeApproxStage, FoundSwapTxSpend, NegotiateSwapContractAddrErr, PrivKeyBuildPolicy, PrivKeyPolicy,
-            RawTransactionResult, RefundPaymentArgs, SearchForSwapTxSpendInput, SendPaymentArgs,
-            SignRawTransactionRequest, SignatureResult, SpendPaymentArgs, TradePreimageFut, TradePreimageResult,
-            TradePreimageValue, TransactionResult, TxMarshalingErr, UnexpectedDerivationMethod, ValidateAddressResult,
-            ValidateFeeArgs, ValidateOtherPubKeyErr, ValidatePaymentInput, ValidatePaymentResult, VerificationResult,
-            WaitForHTLCTxSpendArgs, WatcherOps, WeakSpawner, WithdrawFut, WithdrawRequest};
+use crate::utxo::UtxoFeeDetails;
+use crate::{coin_errors::MyAddressError, BalanceFut, CheckIfMyPaymentSentArgs, ConfirmPaymentInput, DexFee,
+            FeeApproxStage, FoundSwapTxSpend, NegotiateSwapContractAddrErr, PrivKeyBuildPolicy, PrivKeyPolicy,
+            PrivKeyPolicyNotAllowed, RawTransactionError, RawTransactionRes, RawTransactionResult, RefundPaymentArgs,
+            SearchForSwapTxSpendInput, SendPaymentArgs, SignRawTransactionRequest, SignatureError, SignatureResult,
+            SpendPaymentArgs, TradePreimageFut, TradePreimageResult, TradePreimageValue, Transaction, TransactionData,
+            TransactionDetails, TransactionResult, TransactionType, TxFeeDetails, TxMarshalingErr,
+            UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr,
+            ValidatePaymentInput, ValidatePaymentResult, VerificationError, VerificationResult,
+            WaitForHTLCTxSpendArgs, WatcherOps, WeakSpawner, WithdrawError, WithdrawFut, WithdrawRequest};
 use async_trait::async_trait;
-use common::executor::AbortedError;
+use common::executor::{abortable_queue::AbortableQueue, AbortableSystem, AbortedError, Timer};
+use common::log::warn;
+use common::now_sec;
 pub use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature};
+use ed25519_dalek::{Signer, Verifier};
 use futures::{FutureExt, TryFutureExt};
 use futures01::Future;
 use keys::KeyPair;
 use mm2_core::mm_ctx::MmArc;
 use mm2_err_handle::prelude::*;
 use mm2_number::{BigDecimal, BigInt, MmNumber};
+use num_traits::ToPrimitive;
 use rpc::v1::types::Bytes as BytesJson;
 use serde_json::Value as Json;
+use std::convert::TryFrom;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
 
 use sia_rust::http_client::{SiaApiClient, SiaApiClientError, SiaHttpConf};
+use sia_rust::http_endpoints::{AddressUtxosRequest, AddressesEventsRequest, EventsTxidRequest, TxpoolBroadcastRequest};
 use sia_rust::spend_policy::SpendPolicy;
+use sia_rust::transaction::{SiacoinElement, SiacoinOutput, V2Transaction, V2TransactionBuilder};
+use sia_rust::types::{Address, Currency, Event, EventDataWrapper, H256};
 
 pub mod sia_hd_wallet;
+mod sia_swap;
+pub mod sia_tx_history;
+mod sia_withdraw;
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod sia_mock_server;
+
+/// Sia amounts are expressed in hastings, 1 SC = 10^24 hastings.
+pub const SIA_DECIMALS: u8 = 24;
+/// The miner fee paid by every transaction unless `txfee` is set in the coin config, in hastings.
+pub const SIA_DEFAULT_TX_FEE: u128 = 10_000_000_000_000_000_000;
+/// The smallest amount that can be sent in a Sia transaction, in hastings.
+const SIA_MIN_TX_AMOUNT: u128 = 1;
 
 #[derive(Clone)]
 pub struct SiaCoin(SiaArc);
@@ -33,9 +59,10 @@ pub struct SiaArc(Arc<SiaCoinFields>);
 
 #[derive(Debug, Display)]
 pub enum SiaConfError {
-    #[display(fmt = "'foo' field is not found in config")]
-    Foo,
-    Bar(String),
+    #[display(fmt = "Invalid 'txfee' field: {}", _0)]
+    InvalidTxFee(String),
+    #[display(fmt = "Invalid 'dex_fee_address' field: {}", _0)]
+    InvalidDexFeeAddress(String),
 }
 
 pub type SiaConfResult<T> = Result<T, MmError<SiaConfError>>;
@@ -43,11 +70,13 @@ pub type SiaConfResult<T> = Result<T, MmError<SiaConfError>>;
 #[derive(Debug)]
 pub struct SiaCoinConf {
     ticker: String,
-    pub foo: u32,
+    /// The miner fee paid by every transaction, in hastings.
+    pub tx_fee: u128,
+    /// The address taker fees are sent to. Swaps can't be started as a taker if it's not set.
+    pub dex_fee_address: Option<Address>,
+    pub sign_message_prefix: Option<String>,
 }
 
-// TODO see https://github.com/KomodoPlatform/komodo-defi-framework/pull/2086#discussion_r1521660384
-// for additional fields needed
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SiaCoinActivationParams {
     #[serde(default)]
@@ -58,7 +87,6 @@ pub struct SiaCoinActivationParams {
 }
 
 pub struct SiaConfBuilder<'a> {
-    #[allow(dead_code)]
     conf: &'a Json,
     ticker: &'a str,
 }
@@ -69,19 +97,52 @@ impl<'a> SiaConfBuilder<'a> {
     pub fn build(&self) -> SiaConfResult<SiaCoinConf> {
         Ok(SiaCoinConf {
             ticker: self.ticker.to_owned(),
-            foo: 0,
+            tx_fee: self.tx_fee()?,
+            dex_fee_address: self.dex_fee_address()?,
+            sign_message_prefix: self.conf["sign_message_prefix"]
+                .as_str()
+                .map(|prefix| prefix.to_owned()),
         })
     }
+
+    /// `txfee` can be set either as a number or as a string, as hastings may not fit into `u64`.
+    fn tx_fee(&self) -> SiaConfResult<u128> {
+        match &self.conf["txfee"] {
+            Json::Null => Ok(SIA_DEFAULT_TX_FEE),
+            Json::Number(fee) => fee
+                .as_u64()
+                .map(u128::from)
+                .or_mm_err(|| SiaConfError::InvalidTxFee(fee.to_string())),
+            Json::String(fee) => u128::from_str(fee).map_to_mm(|e| SiaConfError::InvalidTxFee(e.to_string())),
+            other => MmError::err(SiaConfError::InvalidTxFee(other.to_string())),
+        }
+    }
+
+    fn dex_fee_address(&self) -> SiaConfResult<Option<Address>> {
+        match self.conf["dex_fee_address"].as_str() {
+            Some(address) => Address::from_str(address)
+                .map(Some)
+                .map_to_mm(|e| SiaConfError::InvalidDexFeeAddress(e.to_string())),
+            None => Ok(None),
+        }
+    }
 }
 
-// TODO see https://github.com/KomodoPlatform/komodo-defi-framework/pull/2086#discussion_r1521668313
-// for additional fields needed
 pub struct SiaCoinFields {
     /// SIA coin config
     pub conf: SiaCoinConf,
     pub priv_key_policy: PrivKeyPolicy<Keypair>,
+    /// The secp256k1 key pair signing the swap messages, see [`sia_swap::secp_key_pair_from_sia_secret`].
+    htlc_key_pair: KeyPair,
+    /// The ed25519 public key passed in the HTLC pubkey fields of the swap messages.
+    htlc_pubkey: [u8; 33],
     /// HTTP(s) client
     pub http_client: SiaApiClient,
+    required_confirmations: AtomicU64,
+    history_sync_state: Mutex<HistorySyncState>,
+    /// This abortable system is used to spawn coin's related futures that should be aborted on coin deactivation
+    /// and on [`MmArc::stop`].
+    abortable_system: AbortableQueue,
 }
 
 pub async fn sia_coin_from_conf_and_params(
@@ -142,6 +203,17 @@ fn siacoin_from_hastings(hastings: u128) -> BigDecimal {
     BigDecimal::from(hastings) / BigDecimal::from(decimals)
 }
 
+/// Convert siacoin amount to hastings amount.
+/// Fails if the amount is negative or doesn't fit into `u128`.
+fn siacoin_to_hastings(siacoin: &BigDecimal) -> Result<u128, String> {
+    let decimals = BigDecimal::from(BigInt::from(10u128.pow(SIA_DECIMALS as u32)));
+    (siacoin * decimals)
+        .to_u128()
+        .ok_or_else(|| format!("Could not get hastings from amount {}", siacoin))
+}
+
+fn currency_from_hastings(hastings: u128) -> Currency { Currency::new(hastings as u64, (hastings >> 64) as u64) }
+
 impl From<SiaConfError> for SiaCoinBuildError {
     fn from(e: SiaConfError) -> Self { SiaCoinBuildError::ConfError(e) }
 }
@@ -152,25 +224,47 @@ pub enum SiaCoinBuildError {
     UnsupportedPrivKeyPolicy,
     ClientError(SiaApiClientError),
     EllipticCurveError(ed25519_dalek::ed25519::Error),
+    Internal(String),
 }
 
 impl<'a> SiaCoinBuilder<'a> {
-    #[allow(dead_code)]
     fn ctx(&self) -> &MmArc { self.ctx }
 
-    #[allow(dead_code)]
     fn conf(&self) -> &Json { self.conf }
 
     fn ticker(&self) -> &str { self.ticker }
 
     async fn build(self) -> MmResult<SiaCoin, SiaCoinBuildError> {
-        let conf = SiaConfBuilder::new(self.conf, self.ticker()).build()?;
+        let conf = SiaConfBuilder::new(self.conf(), self.ticker()).build()?;
+        let required_confirmations = self
+            .params
+            .required_confirmations
+            .unwrap_or_else(|| self.conf()["required_confirmations"].as_u64().unwrap_or(1));
+        let history_sync_state = if self.params.tx_history {
+            HistorySyncState::NotStarted
+        } else {
+            HistorySyncState::NotEnabled
+        };
+        // Create an abortable system linked to the `MmCtx` so if the context is stopped via `MmArc::stop`,
+        // all spawned futures related to `SiaCoin` will be aborted as well.
+        let abortable_system = self
+            .ctx()
+            .abortable_system
+            .create_subsystem()
+            .map_to_mm(|e| SiaCoinBuildError::Internal(e.to_string()))?;
+        let htlc_key_pair = sia_swap::secp_key_pair_from_sia_secret(&self.key_pair.secret)
+            .map_to_mm(|e| SiaCoinBuildError::Internal(e.to_string()))?;
         let sia_fields = SiaCoinFields {
             conf,
+            htlc_key_pair,
+            htlc_pubkey: sia_swap::htlc_pubkey_from_sia_pubkey(&self.key_pair.public),
             http_client: SiaApiClient::new(self.params.http_conf.clone())
                 .map_err(SiaCoinBuildError::ClientError)
                 .await?,
             priv_key_policy: PrivKeyPolicy::Iguana(self.key_pair),
+            required_confirmations: AtomicU64::new(required_confirmations),
+            history_sync_state: Mutex::new(history_sync_state),
+            abortable_system,
         };
         let sia_arc = SiaArc::new(sia_fields);
 
@@ -204,63 +298,368 @@ impl SiaArc {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SiaCoinProtocolInfo;
 
+/// Sia v2 transaction. It's serialized to JSON when it's passed as raw bytes, e.g. between swap sides.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct SiaTransaction(pub V2Transaction);
+
+impl SiaTransaction {
+    pub fn txid(&self) -> H256 { self.0.txid() }
+}
+
+impl Transaction for SiaTransaction {
+    fn tx_hex(&self) -> Vec<u8> { serde_json::to_vec(&self.0).expect("V2Transaction serialization can't fail") }
+
+    fn tx_hash_as_bytes(&self) -> BytesJson { BytesJson::from(self.txid().0.to_vec()) }
+}
+
+impl TryFrom<&[u8]> for SiaTransaction {
+    type Error = serde_json::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> { serde_json::from_slice(bytes) }
+}
+
+fn txid_from_slice(bytes: &[u8]) -> Result<H256, String> {
+    let txid =
+        <[u8; 32]>::try_from(bytes).map_err(|_| format!("Invalid hash length: expected 32, got {}", bytes.len()))?;
+    Ok(H256(txid))
+}
+
+fn txid_from_str(hash: &str) -> Result<H256, String> {
+    let bytes = hex::decode(hash).map_err(|e| e.to_string())?;
+    txid_from_slice(&bytes)
+}
+
+impl SiaCoin {
+    fn my_keypair(&self) -> Result<&Keypair, MmError<PrivKeyPolicyNotAllowed>> {
+        self.0.priv_key_policy.activated_key_or_err()
+    }
+
+    fn my_sia_address(&self) -> Result<Address, MmError<PrivKeyPolicyNotAllowed>> {
+        let key_pair = self.my_keypair()?;
+        Ok(SpendPolicy::PublicKey(key_pair.public).address())
+    }
+
+    fn set_history_sync_state(&self, new_state: HistorySyncState) {
+        *self.0.history_sync_state.lock().unwrap() = new_state;
+    }
+
+    /// The fixed miner fee paid by every transaction.
+    fn tx_fee(&self) -> BigDecimal { siacoin_from_hastings(self.0.conf.tx_fee) }
+
+    /// Returns the confirmed outputs of `address` that can be spent at the current height.
+    async fn spendable_outputs(&self, address: &Address) -> Result<Vec<SiacoinElement>, SiaApiClientError> {
+        let current_height = self.0.http_client.current_height().await?;
+        let outputs = self
+            .0
+            .http_client
+            .dispatcher(AddressUtxosRequest {
+                address: address.clone(),
+            })
+            .await?;
+        Ok(outputs
+            .into_iter()
+            .filter(|output| output.maturity_height <= current_height)
+            .collect())
+    }
+
+    /// Builds a transaction sending `outputs` from my address and signs it.
+    /// The change, if any, is sent back to my address.
+    async fn build_signed_tx(
+        &self,
+        outputs: Vec<SiacoinOutput>,
+        tx_fee: u128,
+    ) -> MmResult<SiaTransaction, WithdrawError> {
+        let key_pair = self.my_keypair()?;
+        let my_address = self.my_sia_address()?;
+
+        let mut required = tx_fee;
+        for output in outputs.iter() {
+            required = required
+                .checked_add(output.value.to_u128())
+                .or_mm_err(|| WithdrawError::InternalError("Outputs total overflows u128".to_owned()))?;
+        }
+
+        let unspents = self
+            .spendable_outputs(&my_address)
+            .await
+            .map_to_mm(|e| WithdrawError::Transport(e.to_string()))?;
+
+        let mut builder = V2TransactionBuilder::new(currency_from_hastings(tx_fee));
+        let mut selected = 0u128;
+        for unspent in unspents {
+            if selected >= required {
+                break;
+            }
+            selected += unspent.siacoin_output.value.to_u128();
+            builder.add_siacoin_input(unspent, SpendPolicy::PublicKey(key_pair.public));
+        }
+        if selected < required {
+            return MmError::err(WithdrawError::NotSufficientBalance {
+                coin: self.ticker().to_owned(),
+                available: siacoin_from_hastings(selected),
+                required: siacoin_from_hastings(required),
+            });
+        }
+
+        for output in outputs {
+            builder.add_siacoin_output(output);
+        }
+        let change = selected - required;
+        if change > 0 {
+            builder.add_siacoin_output(SiacoinOutput {
+                value: currency_from_hastings(change),
+                address: my_address,
+            });
+        }
+
+        builder
+            .sign_simple(vec![key_pair])
+            .map_to_mm(|e| WithdrawError::SigningError(e.to_string()))?;
+        Ok(SiaTransaction(builder.build()))
+    }
+
+    async fn broadcast_tx(&self, tx: &SiaTransaction) -> Result<(), SiaApiClientError> {
+        let request = TxpoolBroadcastRequest {
+            transactions: vec![],
+            v2transactions: vec![tx.0.clone()],
+        };
+        self.0.http_client.dispatcher(request).await?;
+        Ok(())
+    }
+
+    /// Builds the transaction details from my address point of view.
+    fn tx_details(&self, tx: &SiaTransaction, block_height: u64, timestamp: u64) -> Result<TransactionDetails, String> {
+        let my_address = self.my_sia_address().map_err(|e| e.to_string())?;
+
+        let mut from = Vec::new();
+        let mut spent_by_me = 0u128;
+        let mut total_amount = 0u128;
+        for input in tx.0.siacoin_inputs.iter() {
+            let output = &input.parent.siacoin_output;
+            let value = output.value.to_u128();
+            if output.address == my_address {
+                spent_by_me += value;
+            }
+            total_amount += value;
+            from.push(output.address.to_string());
+        }
+
+        let mut to = Vec::new();
+        let mut received_by_me = 0u128;
+        for output in tx.0.siacoin_outputs.iter() {
+            if output.address == my_address {
+                received_by_me += output.value.to_u128();
+            }
+            to.push(output.address.to_string());
+        }
+        from.sort();
+        from.dedup();
+        to.sort();
+        to.dedup();
+
+        let spent_by_me = siacoin_from_hastings(spent_by_me);
+        let received_by_me = siacoin_from_hastings(received_by_me);
+        let tx_hash = tx.tx_hash_as_bytes();
+        Ok(TransactionDetails {
+            tx: TransactionData::new_signed(tx.tx_hex().into(), hex::encode(&tx_hash.0)),
+            from,
+            to,
+            total_amount: siacoin_from_hastings(total_amount),
+            my_balance_change: &received_by_me - &spent_by_me,
+            spent_by_me,
+            received_by_me,
+            block_height,
+            timestamp,
+            fee_details: Some(TxFeeDetails::Utxo(UtxoFeeDetails {
+                coin: Some(self.ticker().to_owned()),
+                amount: siacoin_from_hastings(tx.0.miner_fee.to_u128()),
+            })),
+            coin: self.ticker().to_owned(),
+            internal_id: tx_hash,
+            kmd_rewards: None,
+            transaction_type: TransactionType::StandardTransfer,
+            memo: None,
+        })
+    }
+
+    /// Sends `outputs` from my address and returns the broadcasted transaction.
+    async fn send_outputs(&self, outputs: Vec<SiacoinOutput>) -> Result<SiaTransaction, String> {
+        let tx = self
+            .build_signed_tx(outputs, self.0.conf.tx_fee)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.broadcast_tx(&tx).await.map_err(|e| e.to_string())?;
+        Ok(tx)
+    }
+
+    /// Returns the confirmed events related to `address`.
+    async fn address_events(&self, address: &Address) -> Result<Vec<Event>, SiaApiClientError> {
+        self.0
+            .http_client
+            .dispatcher(AddressesEventsRequest {
+                address: address.clone(),
+            })
+            .await
+    }
+
+    /// Returns the v2 transactions related to `address` along with the height they were mined at.
+    async fn address_transactions(&self, address: &Address) -> Result<Vec<(SiaTransaction, u64)>, String> {
+        let events = self.address_events(address).await.map_err(|e| e.to_string())?;
+        Ok(events
+            .into_iter()
+            .filter_map(|event| match event.data {
+                EventDataWrapper::V2Transaction(tx) => Some((SiaTransaction(tx), event.index.height)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Returns the transaction event by `txid` or `None` if the transaction isn't mined yet.
+    async fn tx_event(&self, txid: H256) -> Result<Option<Event>, SiaApiClientError> {
+        match self.0.http_client.dispatcher(EventsTxidRequest { txid }).await {
+            Ok(event) => Ok(Some(event)),
+            Err(SiaApiClientError::UnexpectedHttpStatus(404)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn raw_transaction(&self, txid: H256) -> MmResult<RawTransactionRes, RawTransactionError> {
+        let event = self
+            .tx_event(txid)
+            .await
+            .map_to_mm(|e| RawTransactionError::Transport(e.to_string()))?
+            .or_mm_err(|| RawTransactionError::HashNotExist(hex::encode(txid.0)))?;
+        match event.data {
+            EventDataWrapper::V2Transaction(tx) => Ok(RawTransactionRes {
+                tx_hex: SiaTransaction(tx).tx_hex().into(),
+            }),
+            _ => MmError::err(RawTransactionError::NotImplemented {
+                coin: self.ticker().to_owned(),
+            }),
+        }
+    }
+}
+
 #[async_trait]
 impl MmCoin for SiaCoin {
     fn is_asset_chain(&self) -> bool { false }
 
-    fn spawner(&self) -> WeakSpawner { unimplemented!() }
+    fn spawner(&self) -> WeakSpawner { self.0.abortable_system.weak_spawner() }
 
-    fn get_raw_transaction(&self, _req: RawTransactionRequest) -> RawTransactionFut { unimplemented!() }
+    fn get_raw_transaction(&self, req: RawTransactionRequest) -> RawTransactionFut {
+        let coin = self.clone();
+        let fut = async move {
+            let txid = txid_from_str(&req.tx_hash).map_to_mm(RawTransactionError::InvalidHashError)?;
+            coin.raw_transaction(txid).await
+        };
+        Box::new(fut.boxed().compat())
+    }
 
-    fn get_tx_hex_by_hash(&self, _tx_hash: Vec<u8>) -> RawTransactionFut { unimplemented!() }
+    fn get_tx_hex_by_hash(&self, tx_hash: Vec<u8>) -> RawTransactionFut {
+        let coin = self.clone();
+        let fut = async move {
+            let txid = txid_from_slice(&tx_hash).map_to_mm(RawTransactionError::InvalidHashError)?;
+            coin.raw_transaction(txid).await
+        };
+        Box::new(fut.boxed().compat())
+    }
 
-    fn withdraw(&self, _req: WithdrawRequest) -> WithdrawFut { unimplemented!() }
+    fn withdraw(&self, req: WithdrawRequest) -> WithdrawFut {
+        let coin = self.clone();
+        let fut = async move { coin.withdraw_impl(req).await };
+        Box::new(fut.boxed().compat())
+    }
 
-    fn decimals(&self) -> u8 { unimplemented!() }
+    fn decimals(&self) -> u8 { SIA_DECIMALS }
 
-    fn convert_to_address(&self, _from: &str, _to_address_format: Json) -> Result<String, String> { unimplemented!() }
+    fn convert_to_address(&self, _from: &str, _to_address_format: Json) -> Result<String, String> {
+        Err(format!("{} doesn't support address conversion", self.ticker()))
+    }
 
-    fn validate_address(&self, _address: &str) -> ValidateAddressResult { unimplemented!() }
+    fn validate_address(&self, address: &str) -> ValidateAddressResult {
+        match Address::from_str(address) {
+            Ok(_) => ValidateAddressResult {
+                is_valid: true,
+                reason: None,
+            },
+            Err(e) => ValidateAddressResult {
+                is_valid: false,
+                reason: Some(e.to_string()),
+            },
+        }
+    }
 
-    fn process_history_loop(&self, _ctx: MmArc) -> Box<dyn Future<Item = (), Error = ()> + Send> { unimplemented!() }
+    /// Transaction history is fetched by [`sia_tx_history::sia_history_loop`] started on coin activation.
+    fn process_history_loop(&self, _ctx: MmArc) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+        Box::new(futures01::future::err(()))
+    }
 
-    fn history_sync_status(&self) -> HistorySyncState { unimplemented!() }
+    fn history_sync_status(&self) -> HistorySyncState { self.0.history_sync_state.lock().unwrap().clone() }
 
     /// Get fee to be paid per 1 swap transaction
-    fn get_trade_fee(&self) -> Box<dyn Future<Item = TradeFee, Error = String> + Send> { unimplemented!() }
+    fn get_trade_fee(&self) -> Box<dyn Future<Item = TradeFee, Error = String> + Send> {
+        Box::new(futures01::future::ok(TradeFee {
+            coin: self.ticker().to_owned(),
+            amount: self.tx_fee().into(),
+            paid_from_trading_vol: false,
+        }))
+    }
 
     async fn get_sender_trade_fee(
         &self,
         _value: TradePreimageValue,
         _stage: FeeApproxStage,
-        _include_refund_fee: bool,
+        include_refund_fee: bool,
     ) -> TradePreimageResult<TradeFee> {
-        unimplemented!()
+        let mut amount = self.tx_fee();
+        if include_refund_fee {
+            amount += self.tx_fee();
+        }
+        Ok(TradeFee {
+            coin: self.ticker().to_owned(),
+            amount: amount.into(),
+            paid_from_trading_vol: false,
+        })
     }
 
-    fn get_receiver_trade_fee(&self, _stage: FeeApproxStage) -> TradePreimageFut<TradeFee> { unimplemented!() }
+    fn get_receiver_trade_fee(&self, _stage: FeeApproxStage) -> TradePreimageFut<TradeFee> {
+        Box::new(futures01::future::ok(TradeFee {
+            coin: self.ticker().to_owned(),
+            amount: self.tx_fee().into(),
+            paid_from_trading_vol: true,
+        }))
+    }
 
     async fn get_fee_to_send_taker_fee(
         &self,
         _dex_fee_amount: DexFee,
         _stage: FeeApproxStage,
     ) -> TradePreimageResult<TradeFee> {
-        unimplemented!()
+        Ok(TradeFee {
+            coin: self.ticker().to_owned(),
+            amount: self.tx_fee().into(),
+            paid_from_trading_vol: false,
+        })
     }
 
-    fn required_confirmations(&self) -> u64 { unimplemented!() }
+    fn required_confirmations(&self) -> u64 { self.0.required_confirmations.load(AtomicOrdering::Relaxed) }
 
     fn requires_notarization(&self) -> bool { false }
 
-    fn set_required_confirmations(&self, _confirmations: u64) { unimplemented!() }
+    fn set_required_confirmations(&self, confirmations: u64) {
+        self.0
+            .required_confirmations
+            .store(confirmations, AtomicOrdering::Relaxed);
+    }
 
-    fn set_requires_notarization(&self, _requires_nota: bool) { unimplemented!() }
+    fn set_requires_notarization(&self, _requires_nota: bool) {}
 
-    fn swap_contract_address(&self) -> Option<BytesJson> { unimplemented!() }
+    fn swap_contract_address(&self) -> Option<BytesJson> { None }
 
-    fn fallback_swap_contract(&self) -> Option<BytesJson> { unimplemented!() }
+    fn fallback_swap_contract(&self) -> Option<BytesJson> { None }
 
-    fn mature_confirmations(&self) -> Option<u32> { unimplemented!() }
+    fn mature_confirmations(&self) -> Option<u32> { None }
 
     fn coin_protocol_info(&self, _amount_to_receive: Option<MmNumber>) -> Vec<u8> { Vec::new() }
 
@@ -274,17 +673,15 @@ impl MmCoin for SiaCoin {
         true
     }
 
-    fn on_disabled(&self) -> Result<(), AbortedError> { Ok(()) }
+    fn on_disabled(&self) -> Result<(), AbortedError> { AbortableSystem::abort_all(&self.0.abortable_system) }
 
     fn on_token_deactivated(&self, _ticker: &str) {}
 }
 
-// TODO Alright - Dummy values for these functions allow minimal functionality to produce signatures
 #[async_trait]
 impl MarketCoinOps for SiaCoin {
     fn ticker(&self) -> &str { &self.0.conf.ticker }
 
-    // needs test coverage FIXME COME BACK
     fn my_address(&self) -> MmResult<String, MyAddressError> {
         let key_pair = match &self.0.priv_key_policy {
             PrivKeyPolicy::Iguana(key_pair) => key_pair,
@@ -312,14 +709,63 @@ impl MarketCoinOps for SiaCoin {
         Ok(address.to_string())
     }
 
-    async fn get_public_key(&self) -> Result<String, MmError<UnexpectedDerivationMethod>> { unimplemented!() }
+    async fn get_public_key(&self) -> Result<String, MmError<UnexpectedDerivationMethod>> {
+        let key_pair = self.my_keypair()?;
+        Ok(hex::encode(key_pair.public.as_bytes()))
+    }
 
-    fn sign_message_hash(&self, _message: &str) -> Option<[u8; 32]> { unimplemented!() }
+    fn sign_message_hash(&self, message: &str) -> Option<[u8; 32]> {
+        let prefix = self.0.conf.sign_message_prefix.as_ref()?;
+        let hash = blake2b_simd::Params::new()
+            .hash_length(32)
+            .to_state()
+            .update(prefix.as_bytes())
+            .update(message.as_bytes())
+            .finalize();
+        let mut res = [0u8; 32];
+        res.copy_from_slice(hash.as_bytes());
+        Some(res)
+    }
 
-    fn sign_message(&self, _message: &str) -> SignatureResult<String> { unimplemented!() }
+    /// The signature is the hex encoded public key followed by the ed25519 signature,
+    /// as the address can't be recovered from the signature alone.
+    fn sign_message(&self, message: &str) -> SignatureResult<String> {
+        let message_hash = self
+            .sign_message_hash(message)
+            .or_mm_err(|| SignatureError::PrefixNotFound)?;
+        let key_pair = self.my_keypair()?;
+        let signature = key_pair.sign(&message_hash);
+
+        let mut signature_bytes = key_pair.public.as_bytes().to_vec();
+        signature_bytes.extend_from_slice(&signature.to_bytes());
+        Ok(hex::encode(signature_bytes))
+    }
 
-    fn verify_message(&self, _signature: &str, _message: &str, _address: &str) -> VerificationResult<bool> {
-        unimplemented!()
+    fn verify_message(&self, signature: &str, message: &str, address: &str) -> VerificationResult<bool> {
+        let message_hash = self
+            .sign_message_hash(message)
+            .or_mm_err(|| VerificationError::PrefixNotFound)?;
+        let address =
+            Address::from_str(address).map_to_mm(|e| VerificationError::AddressDecodingError(e.to_string()))?;
+
+        let signature_bytes =
+            hex::decode(signature).map_to_mm(|e| VerificationError::SignatureDecodingError(e.to_string()))?;
+        if signature_bytes.len() != ed25519_dalek::PUBLIC_KEY_LENGTH + ed25519_dalek::SIGNATURE_LENGTH {
+            return MmError::err(VerificationError::SignatureDecodingError(format!(
+                "Invalid signature length: {}",
+                signature_bytes.len()
+            )));
+        }
+        let (pubkey_bytes, signature_bytes) = signature_bytes.split_at(ed25519_dalek::PUBLIC_KEY_LENGTH);
+        let pubkey = PublicKey::from_bytes(pubkey_bytes)
+            .map_to_mm(|e| VerificationError::SignatureDecodingError(e.to_string()))?;
+        let signature = Signature::try_from(signature_bytes)
+            .map_to_mm(|e| VerificationError::SignatureDecodingError(e.to_string()))?;
+
+        if SpendPolicy::PublicKey(pubkey).address() != address {
+            return Ok(false);
+        }
+        Ok(pubkey.verify(&message_hash, &signature).is_ok())
     }
 
     fn my_balance(&self) -> BalanceFut<CoinBalance> {
@@ -347,30 +793,76 @@ impl MarketCoinOps for SiaCoin {
         Box::new(fut.boxed().compat())
     }
 
-    fn base_coin_balance(&self) -> BalanceFut<BigDecimal> { unimplemented!() }
+    fn base_coin_balance(&self) -> BalanceFut<BigDecimal> {
+        Box::new(self.my_balance().map(|balance| balance.spendable))
+    }
 
-    fn platform_ticker(&self) -> &str { "FOO" } // TODO Alright
+    fn platform_ticker(&self) -> &str { self.ticker() }
 
     /// Receives raw transaction bytes in hexadecimal format as input and returns tx hash in hexadecimal format
-    fn send_raw_tx(&self, _tx: &str) -> Box<dyn Future<Item = String, Error = String> + Send> { unimplemented!() }
+    fn send_raw_tx(&self, tx: &str) -> Box<dyn Future<Item = String, Error = String> + Send> {
+        let tx_bytes = try_fus!(hex::decode(tx));
+        self.send_raw_tx_bytes(&tx_bytes)
+    }
 
-    fn send_raw_tx_bytes(&self, _tx: &[u8]) -> Box<dyn Future<Item = String, Error = String> + Send> {
-        unimplemented!()
+    fn send_raw_tx_bytes(&self, tx: &[u8]) -> Box<dyn Future<Item = String, Error = String> + Send> {
+        let tx = try_fus!(SiaTransaction::try_from(tx));
+        let coin = self.clone();
+        let fut = async move {
+            coin.broadcast_tx(&tx).await.map_err(|e| e.to_string())?;
+            Ok(hex::encode(tx.txid().0))
+        };
+        Box::new(fut.boxed().compat())
     }
 
     #[inline(always)]
-    async fn sign_raw_tx(&self, _args: &SignRawTransactionRequest) -> RawTransactionResult { unimplemented!() }
+    async fn sign_raw_tx(&self, _args: &SignRawTransactionRequest) -> RawTransactionResult {
+        MmError::err(RawTransactionError::NotImplemented {
+            coin: self.ticker().to_owned(),
+        })
+    }
 
-    fn wait_for_confirmations(&self, _input: ConfirmPaymentInput) -> Box<dyn Future<Item = (), Error = String> + Send> {
-        unimplemented!()
+    fn wait_for_confirmations(&self, input: ConfirmPaymentInput) -> Box<dyn Future<Item = (), Error = String> + Send> {
+        let tx = try_fus!(SiaTransaction::try_from(input.payment_tx.as_slice()));
+        let coin = self.clone();
+        let fut = async move {
+            let txid = tx.txid();
+            loop {
+                if now_sec() > input.wait_until {
+                    return ERR!(
+                        "Waited too long until {} for transaction {} to be confirmed {} times",
+                        input.wait_until,
+                        hex::encode(txid.0),
+                        input.confirmations
+                    );
+                }
+
+                match coin.tx_event(txid).await {
+                    Ok(Some(event)) => {
+                        let current_height = coin.0.http_client.current_height().await.map_err(|e| e.to_string())?;
+                        let confirmations = current_height.saturating_sub(event.index.height) + 1;
+                        if confirmations >= input.confirmations {
+                            return Ok(());
+                        }
+                    },
+                    Ok(None) => (),
+                    Err(e) => warn!("Error getting Sia transaction {}: {}", hex::encode(txid.0), e),
+                }
+
+                Timer::sleep(input.check_every as f64).await;
+            }
+        };
+        Box::new(fut.boxed().compat())
     }
 
-    async fn wait_for_htlc_tx_spend(&self, _args: WaitForHTLCTxSpendArgs<'_>) -> TransactionResult { unimplemented!() }
+    async fn wait_for_htlc_tx_spend(&self, args: WaitForHTLCTxSpendArgs<'_>) -> TransactionResult {
+        self.wait_for_htlc_spend(args).await
+    }
 
-    fn tx_enum_from_bytes(&self, _bytes: &[u8]) -> Result<TransactionEnum, MmError<TxMarshalingErr>> {
-        MmError::err(TxMarshalingErr::NotSupported(
-            "tx_enum_from_bytes is not supported for Sia coin yet.".to_string(),
-        ))
+    fn tx_enum_from_bytes(&self, bytes: &[u8]) -> Result<TransactionEnum, MmError<TxMarshalingErr>> {
+        SiaTransaction::try_from(bytes)
+            .map(TransactionEnum::from)
+            .map_to_mm(|e| TxMarshalingErr::InvalidInput(e.to_string()))
     }
 
     fn current_block(&self) -> Box<dyn Future<Item = u64, Error = String> + Send> {
@@ -383,11 +875,14 @@ impl MarketCoinOps for SiaCoin {
         Box::new(height_fut)
     }
 
-    fn display_priv_key(&self) -> Result<String, String> { unimplemented!() }
+    fn display_priv_key(&self) -> Result<String, String> {
+        let key_pair = self.my_keypair().map_err(|e| e.to_string())?;
+        Ok(hex::encode(key_pair.secret.as_bytes()))
+    }
 
-    fn min_tx_amount(&self) -> BigDecimal { unimplemented!() }
+    fn min_tx_amount(&self) -> BigDecimal { siacoin_from_hastings(SIA_MIN_TX_AMOUNT) }
 
-    fn min_trading_vol(&self) -> MmNumber { unimplemented!() }
+    fn min_trading_vol(&self) -> MmNumber { self.min_tx_amount().into() }
 
     fn should_burn_dex_fee(&self) -> bool { false }
 
@@ -396,86 +891,94 @@ impl MarketCoinOps for SiaCoin {
 
 #[async_trait]
 impl SwapOps for SiaCoin {
-    async fn send_taker_fee(&self, _dex_fee: DexFee, _uuid: &[u8], _expire_at: u64) -> TransactionResult {
-        unimplemented!()
+    async fn send_taker_fee(&self, dex_fee: DexFee, _uuid: &[u8], _expire_at: u64) -> TransactionResult {
+        let dex_fee_address = try_tx_s!(self
+            .0
+            .conf
+            .dex_fee_address
+            .clone()
+            .ok_or("'dex_fee_address' is not set in the coin config"));
+        let amount = try_tx_s!(siacoin_to_hastings(&dex_fee.fee_amount().into()));
+        let output = SiacoinOutput {
+            value: currency_from_hastings(amount),
+            address: dex_fee_address,
+        };
+        let tx = try_tx_s!(self.send_outputs(vec![output]).await);
+        Ok(tx.into())
     }
 
-    async fn send_maker_payment(&self, _maker_payment_args: SendPaymentArgs<'_>) -> TransactionResult {
-        unimplemented!()
+    async fn send_maker_payment(&self, maker_payment_args: SendPaymentArgs<'_>) -> TransactionResult {
+        self.send_htlc_payment(&maker_payment_args).await
     }
 
-    async fn send_taker_payment(&self, _taker_payment_args: SendPaymentArgs<'_>) -> TransactionResult {
-        unimplemented!()
+    async fn send_taker_payment(&self, taker_payment_args: SendPaymentArgs<'_>) -> TransactionResult {
+        self.send_htlc_payment(&taker_payment_args).await
     }
 
     async fn send_maker_spends_taker_payment(
         &self,
-        _maker_spends_payment_args: SpendPaymentArgs<'_>,
+        maker_spends_payment_args: SpendPaymentArgs<'_>,
     ) -> TransactionResult {
-        unimplemented!()
+        self.spend_htlc_payment(&maker_spends_payment_args).await
     }
 
     async fn send_taker_spends_maker_payment(
         &self,
-        _taker_spends_payment_args: SpendPaymentArgs<'_>,
+        taker_spends_payment_args: SpendPaymentArgs<'_>,
     ) -> TransactionResult {
-        unimplemented!()
+        self.spend_htlc_payment(&taker_spends_payment_args).await
     }
 
-    async fn send_taker_refunds_payment(
-        &self,
-        _taker_refunds_payment_args: RefundPaymentArgs<'_>,
-    ) -> TransactionResult {
-        unimplemented!()
+    async fn send_taker_refunds_payment(&self, taker_refunds_payment_args: RefundPaymentArgs<'_>) -> TransactionResult {
+        self.refund_htlc_payment(&taker_refunds_payment_args).await
     }
 
-    async fn send_maker_refunds_payment(
-        &self,
-        _maker_refunds_payment_args: RefundPaymentArgs<'_>,
-    ) -> TransactionResult {
-        unimplemented!()
+    async fn send_maker_refunds_payment(&self, maker_refunds_payment_args: RefundPaymentArgs<'_>) -> TransactionResult {
+        self.refund_htlc_payment(&maker_refunds_payment_args).await
     }
 
-    async fn validate_fee(&self, _validate_fee_args: ValidateFeeArgs<'_>) -> ValidatePaymentResult<()> {
-        unimplemented!()
+    async fn validate_fee(&self, validate_fee_args: ValidateFeeArgs<'_>) -> ValidatePaymentResult<()> {
+        self.validate_dex_fee(validate_fee_args).await
     }
 
-    async fn validate_maker_payment(&self, _input: ValidatePaymentInput) -> ValidatePaymentResult<()> {
-        unimplemented!()
+    async fn validate_maker_payment(&self, input: ValidatePaymentInput) -> ValidatePaymentResult<()> {
+        self.validate_htlc_payment(input).await
     }
 
-    async fn validate_taker_payment(&self, _input: ValidatePaymentInput) -> ValidatePaymentResult<()> {
-        unimplemented!()
+    async fn validate_taker_payment(&self, input: ValidatePaymentInput) -> ValidatePaymentResult<()> {
+        self.validate_htlc_payment(input).await
     }
 
     async fn check_if_my_payment_sent(
         &self,
-        _if_my_payment_sent_args: CheckIfMyPaymentSentArgs<'_>,
+        if_my_payment_sent_args: CheckIfMyPaymentSentArgs<'_>,
     ) -> Result<Option<TransactionEnum>, String> {
-        unimplemented!()
+        self.find_my_htlc_payment(&if_my_payment_sent_args).await
     }
 
     async fn search_for_swap_tx_spend_my(
         &self,
-        _: SearchForSwapTxSpendInput<'_>,
+        input: SearchForSwapTxSpendInput<'_>,
     ) -> Result<Option<FoundSwapTxSpend>, String> {
-        unimplemented!()
+        self.search_for_htlc_spend(&input, true).await
     }
 
     async fn search_for_swap_tx_spend_other(
         &self,
-        _: SearchForSwapTxSpendInput<'_>,
+        input: SearchForSwapTxSpendInput<'_>,
     ) -> Result<Option<FoundSwapTxSpend>, String> {
-        unimplemented!()
+        self.search_for_htlc_spend(&input, false).await
     }
 
     async fn extract_secret(
         &self,
-        _secret_hash: &[u8],
-        _spend_tx: &[u8],
+        secret_hash: &[u8],
+        spend_tx: &[u8],
         _watcher_reward: bool,
     ) -> Result<[u8; 32], String> {
-        unimplemented!()
+        let spend_tx = try_s!(SiaTransaction::try_from(spend_tx));
+        sia_swap::extract_secret(&spend_tx, secret_hash)
+            .ok_or_else(|| format!("Secret is not found in transaction {}", hex::encode(spend_tx.txid().0)))
     }
 
     fn negotiate_swap_contract_addr(
@@ -485,15 +988,20 @@ impl SwapOps for SiaCoin {
         Ok(None)
     }
 
-    fn derive_htlc_key_pair(&self, _swap_unique_data: &[u8]) -> KeyPair { unimplemented!() }
+    /// Sia HTLCs are signed with the ed25519 key, this secp256k1 key pair is derived from the same secret
+    /// at the activation and is used to sign swap messages only.
+    fn derive_htlc_key_pair(&self, _swap_unique_data: &[u8]) -> KeyPair { self.0.htlc_key_pair }
 
-    fn derive_htlc_pubkey(&self, _swap_unique_data: &[u8]) -> [u8; 33] { unimplemented!() }
+    fn derive_htlc_pubkey(&self, _swap_unique_data: &[u8]) -> [u8; 33] { self.0.htlc_pubkey }
 
-    async fn can_refund_htlc(&self, _locktime: u64) -> Result<CanRefundHtlc, String> { unimplemented!() }
-
-    fn validate_other_pubkey(&self, _raw_pubkey: &[u8]) -> MmResult<(), ValidateOtherPubKeyErr> { unimplemented!() }
+    fn validate_other_pubkey(&self, raw_pubkey: &[u8]) -> MmResult<(), ValidateOtherPubKeyErr> {
+        sia_swap::sia_pubkey_from_htlc_pubkey(raw_pubkey).map_to_mm(ValidateOtherPubKeyErr::InvalidPubKey)?;
+        Ok(())
+    }
 }
 
+/// Not supported: v2 transaction inputs carry merkle proofs of the spent outputs that go stale with every block,
+/// so spend and refund preimages presigned by the taker can't be broadcast by a watcher later.
 #[async_trait]
 impl WatcherOps for SiaCoin {}
 
@@ -503,6 +1011,243 @@ mod tests {
     use mm2_number::BigDecimal;
     use std::str::FromStr;
 
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::my_tx_history_v2::{CoinWithTxHistoryV2, TxHistoryStorage};
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::tx_history_storage::TxHistoryStorageBuilder;
+    #[cfg(not(target_arch = "wasm32"))] use common::block_on;
+    #[cfg(not(target_arch = "wasm32"))]
+    use mm2_test_helpers::for_tests::mm_ctx_with_custom_db;
+    #[cfg(not(target_arch = "wasm32"))]
+    use sia_mock_server::{address_for_test, empty_wallet_routes, events_route, outputs_route,
+                          sia_coin_with_mock_walletd, siacoin_element, tx_event};
+
+    #[test]
+    fn test_siacoin_to_hastings() {
+        let siacoin = BigDecimal::from_str("1.000000000000000000000001").unwrap();
+        assert_eq!(siacoin_to_hastings(&siacoin).unwrap(), 10u128.pow(24) + 1);
+        assert_eq!(siacoin_from_hastings(siacoin_to_hastings(&siacoin).unwrap()), siacoin);
+
+        siacoin_to_hastings(&BigDecimal::from(-1)).unwrap_err();
+    }
+
+    #[test]
+    fn test_sia_conf_builder() {
+        let conf = SiaConfBuilder::new(&json!({}), "TSIA").build().unwrap();
+        assert_eq!(conf.tx_fee, SIA_DEFAULT_TX_FEE);
+        assert!(conf.dex_fee_address.is_none());
+
+        let conf = SiaConfBuilder::new(&json!({"txfee": "100000000000000000000000"}), "TSIA")
+            .build()
+            .unwrap();
+        assert_eq!(conf.tx_fee, 10u128.pow(23));
+
+        let conf = SiaConfBuilder::new(&json!({"txfee": 1000}), "TSIA").build().unwrap();
+        assert_eq!(conf.tx_fee, 1000);
+
+        let err = SiaConfBuilder::new(&json!({"txfee": -1}), "TSIA").build().unwrap_err();
+        assert!(matches!(err.into_inner(), SiaConfError::InvalidTxFee(_)));
+
+        let err = SiaConfBuilder::new(&json!({"dex_fee_address": "invalid"}), "TSIA")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err.into_inner(), SiaConfError::InvalidDexFeeAddress(_)));
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_mock_walletd_balance_and_height() {
+        let mut routes = empty_wallet_routes(150);
+        routes[1] = (
+            "/balance".to_owned(),
+            json!({ "siacoins": "1500000000000000000000000", "immatureSiacoins": "1000000000000000000000000" }),
+        );
+        let coin = sia_coin_with_mock_walletd([1; 32], json!({}), routes);
+
+        assert_eq!(coin.current_block().wait().unwrap(), 150);
+        let balance = coin.my_balance().wait().unwrap();
+        assert_eq!(balance.spendable, BigDecimal::from_str("1.5").unwrap());
+        assert_eq!(balance.unspendable, BigDecimal::from(1));
+        assert_eq!(
+            coin.base_coin_balance().wait().unwrap(),
+            BigDecimal::from_str("1.5").unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_trade_fees() {
+        let conf = json!({"txfee": "100000000000000000000000"});
+        let coin = sia_coin_with_mock_walletd([1; 32], conf, empty_wallet_routes(1));
+        let tx_fee = MmNumber::from("0.1");
+
+        let trade_fee = coin.get_trade_fee().wait().unwrap();
+        assert_eq!(trade_fee.amount, tx_fee);
+        assert_eq!(trade_fee.coin, "TSIA");
+
+        let sender_fee = block_on(coin.get_sender_trade_fee(
+            TradePreimageValue::Exact(BigDecimal::from(1)),
+            FeeApproxStage::WithoutApprox,
+            true,
+        ))
+        .unwrap();
+        assert_eq!(sender_fee.amount, MmNumber::from("0.2"));
+
+        let receiver_fee = coin
+            .get_receiver_trade_fee(FeeApproxStage::WithoutApprox)
+            .wait()
+            .unwrap();
+        assert_eq!(receiver_fee.amount, tx_fee);
+        assert!(receiver_fee.paid_from_trading_vol);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_withdraw_empty_wallet() {
+        let coin = sia_coin_with_mock_walletd([1; 32], json!({}), empty_wallet_routes(1));
+        let to = coin.my_address().unwrap();
+
+        let req = WithdrawRequest {
+            coin: coin.ticker().to_owned(),
+            to: to.clone(),
+            amount: BigDecimal::from(1),
+            ..Default::default()
+        };
+        let err = coin.withdraw(req).wait().unwrap_err().into_inner();
+        assert!(matches!(err, WithdrawError::NotSufficientBalance { .. }), "{:?}", err);
+
+        let req = WithdrawRequest::new_max(coin.ticker().to_owned(), to.clone());
+        let err = coin.withdraw(req).wait().unwrap_err().into_inner();
+        assert!(matches!(err, WithdrawError::ZeroBalanceToWithdrawMax), "{:?}", err);
+
+        let req = WithdrawRequest::new_max(coin.ticker().to_owned(), "invalid".to_owned());
+        let err = coin.withdraw(req).wait().unwrap_err().into_inner();
+        assert!(matches!(err, WithdrawError::InvalidAddress(_)), "{:?}", err);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_withdraw_with_mock_walletd() {
+        let one_sc = 10u128.pow(24);
+        let my_address = address_for_test([1; 32]);
+        let to = address_for_test([2; 32]);
+        let mut routes = empty_wallet_routes(10);
+        // The second output matures at 20, so it can't be spent yet.
+        routes.push(outputs_route(&my_address, vec![
+            siacoin_element(1, &my_address, 2 * one_sc, 0),
+            siacoin_element(2, &my_address, 5 * one_sc, 20),
+        ]));
+        let coin = sia_coin_with_mock_walletd([1; 32], json!({}), routes);
+        let tx_fee = siacoin_from_hastings(SIA_DEFAULT_TX_FEE);
+
+        let req = WithdrawRequest {
+            coin: coin.ticker().to_owned(),
+            to: to.to_string(),
+            amount: BigDecimal::from(1),
+            memo: Some("memo".to_owned()),
+            ..Default::default()
+        };
+        let details = coin.withdraw(req).wait().unwrap();
+        assert_eq!(details.spent_by_me, BigDecimal::from(2));
+        assert_eq!(details.received_by_me, BigDecimal::from(1) - &tx_fee);
+        assert_eq!(details.my_balance_change, -(BigDecimal::from(1) + &tx_fee));
+        assert_eq!(details.from, vec![my_address.to_string()]);
+        let mut expected_to = vec![my_address.to_string(), to.to_string()];
+        expected_to.sort();
+        assert_eq!(details.to, expected_to);
+        assert_eq!(details.memo, Some("memo".to_owned()));
+        match details.fee_details {
+            Some(TxFeeDetails::Utxo(fee)) => assert_eq!(fee.amount, tx_fee),
+            fee => panic!("Unexpected fee details: {:?}", fee),
+        }
+
+        let tx = SiaTransaction::try_from(details.tx.tx_hex().unwrap().as_slice()).unwrap();
+        assert_eq!(tx.0.siacoin_inputs.len(), 1);
+        assert_eq!(tx.0.siacoin_outputs.len(), 2);
+        assert_eq!(details.internal_id, tx.tx_hash_as_bytes());
+
+        let req = WithdrawRequest::new_max(coin.ticker().to_owned(), to.to_string());
+        let details = coin.withdraw(req).wait().unwrap();
+        assert_eq!(details.spent_by_me, BigDecimal::from(2));
+        assert_eq!(details.received_by_me, BigDecimal::from(0));
+
+        let req = WithdrawRequest {
+            coin: coin.ticker().to_owned(),
+            to: to.to_string(),
+            amount: BigDecimal::from(3),
+            ..Default::default()
+        };
+        match coin.withdraw(req).wait().unwrap_err().into_inner() {
+            WithdrawError::NotSufficientBalance { available, .. } => assert_eq!(available, BigDecimal::from(2)),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_tx_history_with_mock_walletd() {
+        let one_sc = 10u128.pow(24);
+        let sender_address = address_for_test([1; 32]);
+        let my_address = address_for_test([2; 32]);
+
+        let mut routes = empty_wallet_routes(10);
+        routes.push(outputs_route(&sender_address, vec![siacoin_element(
+            1,
+            &sender_address,
+            2 * one_sc,
+            0,
+        )]));
+        let sender = sia_coin_with_mock_walletd([1; 32], json!({}), routes);
+        let output = SiacoinOutput {
+            value: currency_from_hastings(one_sc),
+            address: my_address.clone(),
+        };
+        let tx = block_on(sender.build_signed_tx(vec![output], SIA_DEFAULT_TX_FEE)).unwrap();
+
+        let mut routes = empty_wallet_routes(10);
+        routes.push(events_route(&my_address, vec![tx_event(&tx, 5)]));
+        let coin = sia_coin_with_mock_walletd([2; 32], json!({}), routes);
+
+        let ctx = mm_ctx_with_custom_db();
+        let storage = TxHistoryStorageBuilder::new(&ctx).build().unwrap();
+        let wallet_id = coin.history_wallet_id();
+        block_on(storage.init(&wallet_id)).unwrap();
+
+        let new_transactions = block_on(sia_tx_history::fetch_new_transactions(&coin, &storage, &wallet_id)).unwrap();
+        assert_eq!(new_transactions.len(), 1);
+        let details = &new_transactions[0];
+        assert_eq!(details.internal_id, tx.tx_hash_as_bytes());
+        assert_eq!(details.block_height, 5);
+        assert_eq!(details.timestamp, 1721329456);
+        assert_eq!(details.spent_by_me, BigDecimal::from(0));
+        assert_eq!(details.received_by_me, BigDecimal::from(1));
+        assert_eq!(details.from, vec![sender_address.to_string()]);
+
+        // The stored transactions aren't fetched again.
+        block_on(storage.add_transactions_to_history(&wallet_id, new_transactions)).unwrap();
+        let new_transactions = block_on(sia_tx_history::fetch_new_transactions(&coin, &storage, &wallet_id)).unwrap();
+        assert!(new_transactions.is_empty());
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_sign_verify_message() {
+        let conf = json!({"sign_message_prefix": "Sia Signed Message:\n"});
+        let coin = sia_coin_with_mock_walletd([1; 32], conf, empty_wallet_routes(1));
+        let address = coin.my_address().unwrap();
+        assert!(coin.validate_address(&address).is_valid);
+        assert!(!coin.validate_address("invalid").is_valid);
+
+        let signature = coin.sign_message("test").unwrap();
+        assert!(coin.verify_message(&signature, "test", &address).unwrap());
+        assert!(!coin.verify_message(&signature, "other", &address).unwrap());
+
+        let other_address = SpendPolicy::PublicKey(PublicKey::from(&SecretKey::from_bytes(&[2; 32]).unwrap()))
+            .address()
+            .to_string();
+        assert!(!coin.verify_message(&signature, "test", &other_address).unwrap());
+    }
+
     #[test]
     fn test_siacoin_from_hastings() {
         let hastings = u128::MAX;
@@ -0,0 +1,159 @@
+//! A minimal mock of the walletd HTTP API used to test `SiaCoin` without a running node.
+
+use super::{currency_from_hastings, sia_coin_from_conf_and_params, SiaCoin, SiaCoinActivationParams, SiaTransaction};
+use crate::{IguanaPrivKey, PrivKeyBuildPolicy};
+use common::block_on;
+use common::log::error;
+use ed25519_dalek::{PublicKey, SecretKey};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use mm2_core::mm_ctx::MmCtxBuilder;
+use serde_json::Value as Json;
+use sia_rust::http_client::SiaHttpConf;
+use sia_rust::spend_policy::SpendPolicy;
+use sia_rust::transaction::SiacoinOutput;
+use sia_rust::types::Address;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use url::Url;
+
+/// A path suffix and the JSON body it's answered with.
+pub(super) type MockRoute = (String, Json);
+
+/// Responds with the JSON body of the longest route suffix matching the request path,
+/// or with `404 Not Found` otherwise.
+/// So the address specific routes take precedence over the generic ones.
+fn respond(routes: &[MockRoute], path: &str) -> Response<Body> {
+    let route = routes
+        .iter()
+        .filter(|(suffix, _)| path.ends_with(suffix.as_str()))
+        .max_by_key(|(suffix, _)| suffix.len());
+    match route {
+        Some((_, body)) => Response::new(Body::from(body.to_string())),
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .expect("valid response"),
+    }
+}
+
+/// Spawns the mock server on a random local port and returns its URL.
+/// The server lives until the end of the test process.
+pub(super) async fn spawn_mock_walletd(routes: Vec<MockRoute>) -> Url {
+    let routes = Arc::new(routes);
+    let make_svc = make_service_fn(move |_conn| {
+        let routes = routes.clone();
+        futures::future::ready(Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+            futures::future::ready(Ok::<_, Infallible>(respond(&routes, req.uri().path())))
+        })))
+    });
+
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let url = Url::parse(&format!("http://{}/", server.local_addr())).expect("valid URL");
+    common::executor::spawn(async move {
+        if let Err(e) = server.await {
+            error!("Sia mock server stopped: {}", e);
+        }
+    });
+    url
+}
+
+/// Activates `TSIA` with the `priv_key` against a mock walletd serving the `routes`.
+pub(super) fn sia_coin_with_mock_walletd(priv_key: [u8; 32], conf: Json, routes: Vec<MockRoute>) -> SiaCoin {
+    block_on(async move {
+        let url = spawn_mock_walletd(routes).await;
+        let ctx = MmCtxBuilder::new().into_mm_arc();
+        let params = SiaCoinActivationParams {
+            tx_history: false,
+            required_confirmations: None,
+            gap_limit: None,
+            http_conf: SiaHttpConf {
+                url,
+                password: "password".to_owned(),
+            },
+        };
+        let priv_key_policy = PrivKeyBuildPolicy::IguanaPrivKey(IguanaPrivKey::from(priv_key));
+        sia_coin_from_conf_and_params(&ctx, "TSIA", &conf, &params, priv_key_policy)
+            .await
+            .unwrap()
+    })
+}
+
+/// The pubkey of the coin activated with the `priv_key`.
+pub(super) fn pubkey_for_test(priv_key: [u8; 32]) -> PublicKey {
+    PublicKey::from(&SecretKey::from_bytes(&priv_key).unwrap())
+}
+
+/// The address of the coin activated with the `priv_key`.
+pub(super) fn address_for_test(priv_key: [u8; 32]) -> Address {
+    SpendPolicy::PublicKey(pubkey_for_test(priv_key)).address()
+}
+
+/// A `SiacoinElement` of `value` hastings locked at `address`, as returned by `/addresses/:addr/outputs/siacoin`.
+/// The `id` byte makes the element unique.
+pub(super) fn siacoin_element(id: u8, address: &Address, value: u128, maturity_height: u64) -> Json {
+    json!({
+        "id": format!("h:{}", hex::encode([id; 32])),
+        "leafIndex": id,
+        "merkleProof": [],
+        "siacoinOutput": SiacoinOutput {
+            value: currency_from_hastings(value),
+            address: address.clone(),
+        },
+        "maturityHeight": maturity_height,
+    })
+}
+
+/// A `v2Transaction` event of `tx` mined at `height`, as returned by `/events/:id` and `/addresses/:addr/events`.
+pub(super) fn tx_event(tx: &SiaTransaction, height: u64) -> Json {
+    json!({
+        "id": format!("h:{}", hex::encode(tx.txid().0)),
+        "index": {
+            "height": height,
+            "id": "bid:0000000000000000000000000000000000000000000000000000000000000000",
+        },
+        "timestamp": "2024-07-18T19:04:16Z",
+        "maturityHeight": height,
+        "type": "v2Transaction",
+        "data": tx.0,
+        "relevant": [],
+    })
+}
+
+/// Route of the `address` spendable `outputs`.
+pub(super) fn outputs_route(address: &Address, outputs: Vec<Json>) -> MockRoute {
+    (format!("{}/outputs/siacoin", address), Json::Array(outputs))
+}
+
+/// Route of the `address` confirmed `events`.
+pub(super) fn events_route(address: &Address, events: Vec<Json>) -> MockRoute {
+    (format!("{}/events", address), Json::Array(events))
+}
+
+/// Route of the `tx` confirmed at `height`.
+/// Unconfirmed transactions aren't found by walletd, so they have no route.
+pub(super) fn tx_event_route(tx: &SiaTransaction, height: u64) -> MockRoute {
+    (hex::encode(tx.txid().0), tx_event(tx, height))
+}
+
+/// Routes of a walletd node at `height` with an empty wallet.
+/// Every broadcasted transaction is accepted.
+pub(super) fn empty_wallet_routes(height: u64) -> Vec<MockRoute> {
+    vec![
+        (
+            "/consensus/tip".to_owned(),
+            json!({
+                "height": height,
+                "id": "bid:0000000000000000000000000000000000000000000000000000000000000000",
+            }),
+        ),
+        (
+            "/balance".to_owned(),
+            json!({ "siacoins": "0", "immatureSiacoins": "0" }),
+        ),
+        ("/outputs/siacoin".to_owned(), json!([])),
+        ("/events".to_owned(), json!([])),
+        ("/txpool/broadcast".to_owned(), Json::Null),
+    ]
+}
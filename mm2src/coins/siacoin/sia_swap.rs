@@ -0,0 +1,713 @@
+//! Sia HTLC implementation of the legacy swap protocol.
+//!
+//! A swap payment is sent to the address of the following spend policy:
+//! `(success_pubkey AND sha256(secret) == secret_hash) OR (refund_pubkey AND now > time_lock)`.
+//! The branch that isn't used by the spending transaction is kept opaque, so the secret preimage
+//! is the only data revealed by the success path.
+
+use super::{currency_from_hastings, siacoin_to_hastings, SiaCoin, SiaTransaction};
+use crate::{CheckIfMyPaymentSentArgs, FoundSwapTxSpend, MarketCoinOps, RefundPaymentArgs, SearchForSwapTxSpendInput,
+            SendPaymentArgs, SpendPaymentArgs, SwapTxTypeWithSecretHash, TransactionEnum, TransactionResult,
+            ValidateFeeArgs, ValidatePaymentError, ValidatePaymentInput, ValidatePaymentResult, WaitForHTLCTxSpendArgs};
+use bitcrypto::sha256;
+use common::executor::Timer;
+use common::log::warn;
+use common::now_sec;
+use crypto::privkey::{key_pair_from_secret, secp_privkey_from_hash, PrivKeyResult};
+use ed25519_dalek::{SecretKey, Signer};
+use keys::KeyPair;
+use mm2_err_handle::prelude::*;
+use sia_rust::spend_policy::SpendPolicy;
+use sia_rust::transaction::{SiacoinOutput, V2TransactionBuilder};
+use sia_rust::types::{Address, EventDataWrapper, H256};
+use sia_rust::PublicKey;
+use std::convert::TryFrom;
+
+/// Sia public keys are passed in the 33 bytes HTLC pubkey fields prefixed with this byte.
+const SIA_HTLC_PUBKEY_PREFIX: u8 = 0;
+/// The domain separation tag of the secp256k1 key derived from the ed25519 secret.
+const SIA_SECP_KEY_DOMAIN: &[u8] = b"KDF/Sia/secp256k1-swap-key";
+
+/// Derives the secp256k1 key pair signing the swap messages from the ed25519 `secret`.
+/// The secret is hashed with a domain separation tag, so the key isn't the same scalar as the ed25519 one.
+pub(super) fn secp_key_pair_from_sia_secret(secret: &SecretKey) -> PrivKeyResult<KeyPair> {
+    let hash = sha256(&[SIA_SECP_KEY_DOMAIN, secret.as_bytes()].concat());
+    key_pair_from_secret(&secp_privkey_from_hash(hash).take())
+}
+
+pub(super) fn htlc_pubkey_from_sia_pubkey(pubkey: &PublicKey) -> [u8; 33] {
+    let mut res = [SIA_HTLC_PUBKEY_PREFIX; 33];
+    res[1..].copy_from_slice(pubkey.as_bytes());
+    res
+}
+
+pub(super) fn sia_pubkey_from_htlc_pubkey(raw_pubkey: &[u8]) -> Result<PublicKey, String> {
+    match raw_pubkey {
+        [SIA_HTLC_PUBKEY_PREFIX, pubkey @ ..] if pubkey.len() == ed25519_dalek::PUBLIC_KEY_LENGTH => {
+            PublicKey::from_bytes(pubkey).map_err(|e| e.to_string())
+        },
+        _ => Err(format!("Invalid Sia HTLC pubkey: {}", hex::encode(raw_pubkey))),
+    }
+}
+
+fn secret_hash_from_slice(secret_hash: &[u8]) -> Result<H256, String> {
+    let hash = <[u8; 32]>::try_from(secret_hash)
+        .map_err(|_| format!("Invalid secret hash length: expected 32, got {}", secret_hash.len()))?;
+    Ok(H256(hash))
+}
+
+fn success_branch(success_pubkey: PublicKey, secret_hash: H256) -> SpendPolicy {
+    SpendPolicy::Threshold {
+        n: 2,
+        of: vec![SpendPolicy::PublicKey(success_pubkey), SpendPolicy::Hash(secret_hash)],
+    }
+}
+
+fn refund_branch(refund_pubkey: PublicKey, time_lock: u64) -> SpendPolicy {
+    SpendPolicy::Threshold {
+        n: 2,
+        of: vec![SpendPolicy::PublicKey(refund_pubkey), SpendPolicy::After(time_lock)],
+    }
+}
+
+/// Parameters of the HTLC spend policy.
+pub(super) struct SiaHtlc {
+    pub success_pubkey: PublicKey,
+    pub refund_pubkey: PublicKey,
+    pub time_lock: u64,
+    pub secret_hash: H256,
+}
+
+impl SiaHtlc {
+    pub fn policy(&self) -> SpendPolicy {
+        SpendPolicy::Threshold {
+            n: 1,
+            of: vec![
+                success_branch(self.success_pubkey, self.secret_hash),
+                refund_branch(self.refund_pubkey, self.time_lock),
+            ],
+        }
+    }
+
+    /// The policy revealed by the transaction spending the payment with the secret.
+    pub fn success_policy(&self) -> SpendPolicy {
+        let refund_address = refund_branch(self.refund_pubkey, self.time_lock).address();
+        SpendPolicy::Threshold {
+            n: 1,
+            of: vec![
+                success_branch(self.success_pubkey, self.secret_hash),
+                SpendPolicy::Opaque(refund_address),
+            ],
+        }
+    }
+
+    /// The policy revealed by the transaction refunding the payment after the time lock.
+    pub fn refund_policy(&self) -> SpendPolicy {
+        let success_address = success_branch(self.success_pubkey, self.secret_hash).address();
+        SpendPolicy::Threshold {
+            n: 1,
+            of: vec![
+                SpendPolicy::Opaque(success_address),
+                refund_branch(self.refund_pubkey, self.time_lock),
+            ],
+        }
+    }
+
+    pub fn address(&self) -> Address { self.policy().address() }
+}
+
+/// Returns the secret if it's revealed by one of `spend_tx` inputs.
+pub(super) fn extract_secret(spend_tx: &SiaTransaction, secret_hash: &[u8]) -> Option<[u8; 32]> {
+    spend_tx
+        .0
+        .siacoin_inputs
+        .iter()
+        .flat_map(|input| input.satisfied_policy.preimages.iter())
+        .find(|preimage| sha256(preimage).take()[..] == *secret_hash)
+        .and_then(|preimage| <[u8; 32]>::try_from(preimage.as_slice()).ok())
+}
+
+/// Whether `tx` spends an output locked at `address`.
+fn spends_from(tx: &SiaTransaction, address: &Address) -> bool {
+    tx.0.siacoin_inputs
+        .iter()
+        .any(|input| &input.parent.siacoin_output.address == address)
+}
+
+/// Returns the amount `tx` sends to `address`, in hastings.
+fn sent_to(tx: &SiaTransaction, address: &Address) -> u128 {
+    tx.0.siacoin_outputs
+        .iter()
+        .filter(|output| &output.address == address)
+        .map(|output| output.value.to_u128())
+        .sum()
+}
+
+impl SiaCoin {
+    fn my_pubkey(&self) -> Result<PublicKey, String> {
+        self.my_keypair()
+            .map(|key_pair| key_pair.public)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Builds the HTLC of the payment sent by me.
+    fn my_payment_htlc(&self, time_lock: u64, other_pubkey: &[u8], secret_hash: &[u8]) -> Result<SiaHtlc, String> {
+        Ok(SiaHtlc {
+            success_pubkey: sia_pubkey_from_htlc_pubkey(other_pubkey)?,
+            refund_pubkey: self.my_pubkey()?,
+            time_lock,
+            secret_hash: secret_hash_from_slice(secret_hash)?,
+        })
+    }
+
+    /// Builds the HTLC of the payment sent by the other side.
+    fn other_payment_htlc(&self, time_lock: u64, other_pubkey: &[u8], secret_hash: &[u8]) -> Result<SiaHtlc, String> {
+        Ok(SiaHtlc {
+            success_pubkey: self.my_pubkey()?,
+            refund_pubkey: sia_pubkey_from_htlc_pubkey(other_pubkey)?,
+            time_lock,
+            secret_hash: secret_hash_from_slice(secret_hash)?,
+        })
+    }
+
+    pub(super) async fn send_htlc_payment(&self, args: &SendPaymentArgs<'_>) -> TransactionResult {
+        let htlc = try_tx_s!(self.my_payment_htlc(args.time_lock, args.other_pubkey, args.secret_hash));
+        let amount = try_tx_s!(siacoin_to_hastings(&args.amount));
+        let output = SiacoinOutput {
+            value: currency_from_hastings(amount),
+            address: htlc.address(),
+        };
+        let tx = try_tx_s!(self.send_outputs(vec![output]).await);
+        Ok(tx.into())
+    }
+
+    /// Spends all outputs locked at the `htlc` address to my address revealing `revealed_policy`.
+    /// The `secret` is required by the success path only.
+    async fn spend_htlc(
+        &self,
+        htlc: &SiaHtlc,
+        revealed_policy: SpendPolicy,
+        secret: Option<&[u8]>,
+    ) -> TransactionResult {
+        let key_pair = try_tx_s!(self.my_keypair());
+        let my_address = try_tx_s!(self.my_sia_address());
+        let tx_fee = self.0.conf.tx_fee;
+
+        let htlc_outputs = try_tx_s!(self.spendable_outputs(&htlc.address()).await);
+        if htlc_outputs.is_empty() {
+            return Err(crate::TransactionErr::Plain(format!(
+                "HTLC {} has no outputs to spend",
+                htlc.address()
+            )));
+        }
+        let total: u128 = htlc_outputs
+            .iter()
+            .map(|output| output.siacoin_output.value.to_u128())
+            .sum();
+        if total <= tx_fee {
+            return Err(crate::TransactionErr::Plain(format!(
+                "HTLC amount {} doesn't cover the fee {}",
+                total, tx_fee
+            )));
+        }
+
+        let mut builder = V2TransactionBuilder::new(currency_from_hastings(tx_fee));
+        for output in htlc_outputs {
+            builder.add_siacoin_input(output, revealed_policy.clone());
+        }
+        builder.add_siacoin_output(SiacoinOutput {
+            value: currency_from_hastings(total - tx_fee),
+            address: my_address,
+        });
+
+        let signature = key_pair.sign(&builder.input_sig_hash().0);
+        let mut tx = builder.build();
+        for input in tx.siacoin_inputs.iter_mut() {
+            input.satisfied_policy.signatures.push(signature);
+            if let Some(secret) = secret {
+                input.satisfied_policy.preimages.push(secret.to_vec());
+            }
+        }
+
+        let tx = SiaTransaction(tx);
+        try_tx_s!(self.broadcast_tx(&tx).await);
+        Ok(tx.into())
+    }
+
+    pub(super) async fn spend_htlc_payment(&self, args: &SpendPaymentArgs<'_>) -> TransactionResult {
+        let htlc = try_tx_s!(self.other_payment_htlc(args.time_lock, args.other_pubkey, args.secret_hash));
+        self.spend_htlc(&htlc, htlc.success_policy(), Some(args.secret)).await
+    }
+
+    pub(super) async fn refund_htlc_payment(&self, args: &RefundPaymentArgs<'_>) -> TransactionResult {
+        let secret_hash = match args.tx_type_with_secret_hash {
+            SwapTxTypeWithSecretHash::TakerOrMakerPayment { maker_secret_hash } => maker_secret_hash,
+            _ => {
+                return Err(crate::TransactionErr::ProtocolNotSupported(format!(
+                    "{} supports refunds of legacy swap payments only",
+                    self.ticker()
+                )))
+            },
+        };
+        let htlc = try_tx_s!(self.my_payment_htlc(args.time_lock, args.other_pubkey, secret_hash));
+        self.spend_htlc(&htlc, htlc.refund_policy(), None).await
+    }
+
+    pub(super) async fn validate_dex_fee(&self, args: ValidateFeeArgs<'_>) -> ValidatePaymentResult<()> {
+        let txid = match args.fee_tx {
+            TransactionEnum::SiaTransaction(tx) => tx.txid(),
+            fee_tx => {
+                return MmError::err(ValidatePaymentError::InternalError(format!(
+                    "Invalid fee tx type. fee tx: {:?}",
+                    fee_tx
+                )))
+            },
+        };
+        let dex_fee_address = self.0.conf.dex_fee_address.clone().or_mm_err(|| {
+            ValidatePaymentError::InternalError("'dex_fee_address' is not set in the coin config".to_owned())
+        })?;
+        let expected_sender =
+            sia_pubkey_from_htlc_pubkey(args.expected_sender).map_to_mm(ValidatePaymentError::InvalidParameter)?;
+        let expected_amount =
+            siacoin_to_hastings(&args.dex_fee.fee_amount().into()).map_to_mm(ValidatePaymentError::InternalError)?;
+
+        // Validate the transaction known to walletd rather than the one sent by the taker.
+        let event = self
+            .tx_event(txid)
+            .await
+            .map_to_mm(|e| ValidatePaymentError::Transport(e.to_string()))?
+            .or_mm_err(|| {
+                ValidatePaymentError::TxDoesNotExist(format!("Fee tx {} is not confirmed", hex::encode(txid.0)))
+            })?;
+        if event.index.height < args.min_block_number {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Fee tx {} was mined at {} before min_block_number {}",
+                hex::encode(txid.0),
+                event.index.height,
+                args.min_block_number
+            )));
+        }
+        let fee_tx = match event.data {
+            EventDataWrapper::V2Transaction(tx) => SiaTransaction(tx),
+            _ => {
+                return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                    "Fee tx {} is not a v2 transaction",
+                    hex::encode(txid.0)
+                )))
+            },
+        };
+
+        if !spends_from(&fee_tx, &SpendPolicy::PublicKey(expected_sender).address()) {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Fee tx {} is not sent by the expected sender",
+                hex::encode(txid.0)
+            )));
+        }
+        let actual_amount = sent_to(&fee_tx, &dex_fee_address);
+        if actual_amount < expected_amount {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Fee tx {} sends {} hastings to the dex fee address, expected at least {}",
+                hex::encode(txid.0),
+                actual_amount,
+                expected_amount
+            )));
+        }
+        Ok(())
+    }
+
+    pub(super) async fn validate_htlc_payment(&self, input: ValidatePaymentInput) -> ValidatePaymentResult<()> {
+        let payment_tx = SiaTransaction::try_from(input.payment_tx.as_slice())
+            .map_to_mm(|e| ValidatePaymentError::TxDeserializationError(e.to_string()))?;
+        let htlc = self
+            .other_payment_htlc(input.time_lock, &input.other_pub, &input.secret_hash)
+            .map_to_mm(ValidatePaymentError::InvalidParameter)?;
+        let htlc_address = htlc.address();
+        let expected_amount = siacoin_to_hastings(&input.amount).map_to_mm(ValidatePaymentError::InternalError)?;
+
+        let actual_amount = sent_to(&payment_tx, &htlc_address);
+        if actual_amount != expected_amount {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx {} sends {} hastings to the HTLC address {}, expected {}",
+                hex::encode(payment_tx.txid().0),
+                actual_amount,
+                htlc_address,
+                expected_amount
+            )));
+        }
+
+        let locked = self
+            .spendable_outputs(&htlc_address)
+            .await
+            .map_to_mm(|e| ValidatePaymentError::Transport(e.to_string()))?
+            .iter()
+            .map(|output| output.siacoin_output.value.to_u128())
+            .sum::<u128>();
+        if locked < expected_amount {
+            return MmError::err(ValidatePaymentError::TxDoesNotExist(format!(
+                "HTLC address {} holds {} hastings, expected {}",
+                htlc_address, locked, expected_amount
+            )));
+        }
+        Ok(())
+    }
+
+    pub(super) async fn find_my_htlc_payment(
+        &self,
+        args: &CheckIfMyPaymentSentArgs<'_>,
+    ) -> Result<Option<TransactionEnum>, String> {
+        let htlc = self.my_payment_htlc(args.time_lock, args.other_pub, args.secret_hash)?;
+        let htlc_address = htlc.address();
+        let payment = self
+            .address_transactions(&htlc_address)
+            .await?
+            .into_iter()
+            .find(|(tx, _)| sent_to(tx, &htlc_address) > 0)
+            .map(|(tx, _)| tx.into());
+        Ok(payment)
+    }
+
+    pub(super) async fn search_for_htlc_spend(
+        &self,
+        input: &SearchForSwapTxSpendInput<'_>,
+        is_my_payment: bool,
+    ) -> Result<Option<FoundSwapTxSpend>, String> {
+        let htlc = if is_my_payment {
+            self.my_payment_htlc(input.time_lock, input.other_pub, input.secret_hash)?
+        } else {
+            self.other_payment_htlc(input.time_lock, input.other_pub, input.secret_hash)?
+        };
+        let htlc_address = htlc.address();
+
+        let spend = self
+            .address_transactions(&htlc_address)
+            .await?
+            .into_iter()
+            .map(|(tx, _)| tx)
+            .find(|tx| spends_from(tx, &htlc_address));
+        Ok(spend.map(|tx| match extract_secret(&tx, input.secret_hash) {
+            Some(_) => FoundSwapTxSpend::Spent(tx.into()),
+            None => FoundSwapTxSpend::Refunded(tx.into()),
+        }))
+    }
+
+    /// Waits for any transaction spending the HTLC outputs of `args.tx_bytes` payment.
+    pub(super) async fn wait_for_htlc_spend(&self, args: WaitForHTLCTxSpendArgs<'_>) -> TransactionResult {
+        let payment_tx = try_tx_s!(SiaTransaction::try_from(args.tx_bytes));
+        let my_address = try_tx_s!(self.my_sia_address());
+        // The payment sends the change back to the sender, so the HTLC is the only foreign output address.
+        let htlc_addresses: Vec<_> = payment_tx
+            .0
+            .siacoin_outputs
+            .iter()
+            .map(|output| output.address.clone())
+            .filter(|address| address != &my_address)
+            .collect();
+
+        loop {
+            for htlc_address in htlc_addresses.iter() {
+                match self.address_transactions(htlc_address).await {
+                    Ok(txs) => {
+                        if let Some((tx, _)) = txs.into_iter().find(|(tx, _)| spends_from(tx, htlc_address)) {
+                            return Ok(tx.into());
+                        }
+                    },
+                    Err(e) => warn!("Error getting {} transactions: {}", htlc_address, e),
+                }
+            }
+
+            if now_sec() > args.wait_until {
+                return Err(crate::TransactionErr::Plain(format!(
+                    "Waited too long until {} for payment {} to be spent",
+                    args.wait_until,
+                    hex::encode(payment_tx.txid().0)
+                )));
+            }
+            Timer::sleep(args.check_every).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    use super::super::sia_mock_server::{address_for_test, empty_wallet_routes, outputs_route, pubkey_for_test,
+                                        sia_coin_with_mock_walletd, siacoin_element, tx_event_route};
+    #[cfg(not(target_arch = "wasm32"))]
+    use super::super::SIA_DEFAULT_TX_FEE;
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::{DexFee, SwapOps, Transaction};
+    #[cfg(not(target_arch = "wasm32"))] use common::block_on;
+    #[cfg(not(target_arch = "wasm32"))]
+    use mm2_number::{BigDecimal, MmNumber};
+
+    #[cfg(not(target_arch = "wasm32"))]
+    const ONE_SC: u128 = 1_000_000_000_000_000_000_000_000;
+
+    fn pubkey_from_secret(secret: [u8; 32]) -> PublicKey {
+        let secret = SecretKey::from_bytes(&secret).unwrap();
+        PublicKey::from(&secret)
+    }
+
+    fn test_htlc() -> SiaHtlc {
+        SiaHtlc {
+            success_pubkey: pubkey_from_secret([1; 32]),
+            refund_pubkey: pubkey_from_secret([2; 32]),
+            time_lock: 1_700_000_000,
+            secret_hash: H256(sha256(&[3; 32]).take()),
+        }
+    }
+
+    #[test]
+    fn test_htlc_pubkey_round_trip() {
+        let pubkey = pubkey_from_secret([1; 32]);
+        let htlc_pubkey = htlc_pubkey_from_sia_pubkey(&pubkey);
+        assert_eq!(htlc_pubkey[0], SIA_HTLC_PUBKEY_PREFIX);
+        assert_eq!(sia_pubkey_from_htlc_pubkey(&htlc_pubkey).unwrap(), pubkey);
+
+        // secp256k1 pubkeys can't be used as Sia HTLC pubkeys.
+        let mut secp_pubkey = htlc_pubkey;
+        secp_pubkey[0] = 2;
+        sia_pubkey_from_htlc_pubkey(&secp_pubkey).unwrap_err();
+        sia_pubkey_from_htlc_pubkey(pubkey.as_bytes()).unwrap_err();
+    }
+
+    #[test]
+    fn test_secp_key_pair_from_sia_secret() {
+        let secret = SecretKey::from_bytes(&[1; 32]).unwrap();
+        let key_pair = secp_key_pair_from_sia_secret(&secret).unwrap();
+        assert_eq!(key_pair, secp_key_pair_from_sia_secret(&secret).unwrap());
+        // The ed25519 secret isn't reused as the secp256k1 secret.
+        assert_ne!(key_pair.private().secret.take(), [1; 32]);
+
+        let other_secret = SecretKey::from_bytes(&[2; 32]).unwrap();
+        assert_ne!(key_pair, secp_key_pair_from_sia_secret(&other_secret).unwrap());
+    }
+
+    #[test]
+    fn test_htlc_revealed_policies_keep_address() {
+        let htlc = test_htlc();
+        assert_eq!(htlc.success_policy().address(), htlc.address());
+        assert_eq!(htlc.refund_policy().address(), htlc.address());
+
+        let other_time_lock = SiaHtlc {
+            time_lock: htlc.time_lock + 1,
+            ..test_htlc()
+        };
+        assert_ne!(other_time_lock.address(), htlc.address());
+    }
+
+    #[test]
+    fn test_secret_hash_from_slice() {
+        secret_hash_from_slice(&[0; 20]).unwrap_err();
+        assert_eq!(secret_hash_from_slice(&[1; 32]).unwrap(), H256([1; 32]));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sia_tx(tx: TransactionEnum) -> SiaTransaction {
+        match tx {
+            TransactionEnum::SiaTransaction(tx) => tx,
+            tx => panic!("Unexpected tx: {:?}", tx),
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_htlc_send_validate_spend_refund() {
+        let maker_address = address_for_test([1; 32]);
+        let taker_address = address_for_test([2; 32]);
+        let maker_pubkey = htlc_pubkey_from_sia_pubkey(&pubkey_for_test([1; 32]));
+        let taker_pubkey = htlc_pubkey_from_sia_pubkey(&pubkey_for_test([2; 32]));
+        let secret = [3; 32];
+        let secret_hash = sha256(&secret).take();
+        let time_lock = 1_700_000_000;
+
+        let mut routes = empty_wallet_routes(10);
+        routes.push(outputs_route(&maker_address, vec![siacoin_element(
+            1,
+            &maker_address,
+            2 * ONE_SC,
+            0,
+        )]));
+        let maker = sia_coin_with_mock_walletd([1; 32], json!({}), routes);
+        let send_args = SendPaymentArgs {
+            time_lock_duration: 0,
+            time_lock,
+            other_pubkey: &taker_pubkey,
+            secret_hash: &secret_hash,
+            amount: BigDecimal::from(1),
+            swap_contract_address: &None,
+            swap_unique_data: &[],
+            payment_instructions: &None,
+            watcher_reward: None,
+            wait_for_confirmation_until: 0,
+        };
+        let payment = sia_tx(block_on(maker.send_htlc_payment(&send_args)).unwrap());
+        let htlc = maker.my_payment_htlc(time_lock, &taker_pubkey, &secret_hash).unwrap();
+        let htlc_address = htlc.address();
+        assert_eq!(sent_to(&payment, &htlc_address), ONE_SC);
+        assert!(spends_from(&payment, &maker_address));
+
+        // The taker sees the payment output locked at the HTLC address.
+        let mut routes = empty_wallet_routes(10);
+        routes.push(outputs_route(&htlc_address, vec![siacoin_element(
+            2,
+            &htlc_address,
+            ONE_SC,
+            0,
+        )]));
+        let taker = sia_coin_with_mock_walletd([2; 32], json!({}), routes.clone());
+        assert_eq!(
+            taker
+                .other_payment_htlc(time_lock, &maker_pubkey, &secret_hash)
+                .unwrap()
+                .address(),
+            htlc_address
+        );
+
+        let validate_input = ValidatePaymentInput {
+            payment_tx: payment.tx_hex(),
+            time_lock_duration: 0,
+            time_lock,
+            other_pub: maker_pubkey.to_vec(),
+            secret_hash: secret_hash.to_vec(),
+            amount: BigDecimal::from(1),
+            swap_contract_address: None,
+            try_spv_proof_until: 0,
+            confirmations: 1,
+            unique_swap_data: Vec::new(),
+            watcher_reward: None,
+        };
+        block_on(taker.validate_htlc_payment(validate_input.clone())).unwrap();
+
+        let wrong_amount = ValidatePaymentInput {
+            amount: BigDecimal::from(2),
+            ..validate_input.clone()
+        };
+        let err = block_on(taker.validate_htlc_payment(wrong_amount))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        let wrong_time_lock = ValidatePaymentInput {
+            time_lock: time_lock + 1,
+            ..validate_input.clone()
+        };
+        let err = block_on(taker.validate_htlc_payment(wrong_time_lock))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        // The payment isn't mined yet, so there is nothing locked at the HTLC address.
+        let unconfirmed_taker = sia_coin_with_mock_walletd([2; 32], json!({}), empty_wallet_routes(10));
+        let err = block_on(unconfirmed_taker.validate_htlc_payment(validate_input))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::TxDoesNotExist(_)), "{:?}", err);
+
+        let payment_bytes = payment.tx_hex();
+        let spend_args = SpendPaymentArgs {
+            other_payment_tx: &payment_bytes,
+            time_lock,
+            other_pubkey: &maker_pubkey,
+            secret: &secret,
+            secret_hash: &secret_hash,
+            swap_contract_address: &None,
+            swap_unique_data: &[],
+            watcher_reward: false,
+        };
+        let spend = sia_tx(block_on(taker.spend_htlc_payment(&spend_args)).unwrap());
+        assert!(spends_from(&spend, &htlc_address));
+        assert_eq!(extract_secret(&spend, &secret_hash), Some(secret));
+        assert_eq!(sent_to(&spend, &taker_address), ONE_SC - SIA_DEFAULT_TX_FEE);
+
+        // The maker refunds the same HTLC output without revealing the secret.
+        let maker = sia_coin_with_mock_walletd([1; 32], json!({}), routes);
+        let refund_args = RefundPaymentArgs {
+            payment_tx: &payment_bytes,
+            time_lock,
+            other_pubkey: &taker_pubkey,
+            tx_type_with_secret_hash: SwapTxTypeWithSecretHash::TakerOrMakerPayment {
+                maker_secret_hash: &secret_hash,
+            },
+            swap_contract_address: &None,
+            swap_unique_data: &[],
+            watcher_reward: false,
+        };
+        let refund = sia_tx(block_on(maker.refund_htlc_payment(&refund_args)).unwrap());
+        assert!(spends_from(&refund, &htlc_address));
+        assert_eq!(extract_secret(&refund, &secret_hash), None);
+        assert!(refund
+            .0
+            .siacoin_inputs
+            .iter()
+            .all(|input| input.satisfied_policy.preimages.is_empty()));
+        assert_eq!(sent_to(&refund, &maker_address), ONE_SC - SIA_DEFAULT_TX_FEE);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn validate_args<'a>(
+        fee_tx: &'a TransactionEnum,
+        dex_fee: &'a DexFee,
+        expected_sender: &'a [u8],
+        min_block_number: u64,
+    ) -> ValidateFeeArgs<'a> {
+        ValidateFeeArgs {
+            fee_tx,
+            expected_sender,
+            dex_fee,
+            min_block_number,
+            uuid: &[],
+        }
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_sia_validate_dex_fee() {
+        let taker_address = address_for_test([2; 32]);
+        let taker_pubkey = htlc_pubkey_from_sia_pubkey(&pubkey_for_test([2; 32]));
+        let maker_pubkey = htlc_pubkey_from_sia_pubkey(&pubkey_for_test([1; 32]));
+        let conf = json!({ "dex_fee_address": address_for_test([4; 32]).to_string() });
+        let dex_fee = DexFee::Standard(MmNumber::from("0.1"));
+
+        let mut routes = empty_wallet_routes(10);
+        routes.push(outputs_route(&taker_address, vec![siacoin_element(
+            1,
+            &taker_address,
+            ONE_SC,
+            0,
+        )]));
+        let taker = sia_coin_with_mock_walletd([2; 32], conf.clone(), routes);
+        let fee_tx = block_on(taker.send_taker_fee(dex_fee.clone(), &[], 0)).unwrap();
+        let fee_sia_tx = sia_tx(fee_tx.clone());
+
+        let mut routes = empty_wallet_routes(10);
+        routes.push(tx_event_route(&fee_sia_tx, 5));
+        let maker = sia_coin_with_mock_walletd([1; 32], conf.clone(), routes);
+        block_on(maker.validate_dex_fee(validate_args(&fee_tx, &dex_fee, &taker_pubkey, 5))).unwrap();
+
+        let err = block_on(maker.validate_dex_fee(validate_args(&fee_tx, &dex_fee, &taker_pubkey, 6)))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        let err = block_on(maker.validate_dex_fee(validate_args(&fee_tx, &dex_fee, &maker_pubkey, 5)))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        let higher_fee = DexFee::Standard(MmNumber::from("0.2"));
+        let err = block_on(maker.validate_dex_fee(validate_args(&fee_tx, &higher_fee, &taker_pubkey, 5)))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        // walletd doesn't know the fee tx until it's mined.
+        let maker = sia_coin_with_mock_walletd([1; 32], conf, empty_wallet_routes(10));
+        let err = block_on(maker.validate_dex_fee(validate_args(&fee_tx, &dex_fee, &taker_pubkey, 5)))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, ValidatePaymentError::TxDoesNotExist(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,100 @@
+use super::{SiaCoin, SiaTransaction};
+use crate::my_tx_history_v2::{CoinWithTxHistoryV2, MyTxHistoryErrorV2, MyTxHistoryTarget, TxHistoryStorage};
+use crate::tx_history_storage::{GetTxHistoryFilters, WalletId};
+use crate::utxo::tx_history_events::TxHistoryEventStreamer;
+use crate::{HistorySyncState, MarketCoinOps, Transaction, TransactionDetails};
+use async_trait::async_trait;
+use common::executor::Timer;
+use common::log;
+use mm2_err_handle::prelude::*;
+use mm2_event_stream::StreamingManager;
+use sia_rust::types::EventDataWrapper;
+
+/// The interval between the wallet events requests, in seconds.
+const SIA_HISTORY_POLL_INTERVAL: f64 = 30.;
+
+#[async_trait]
+impl CoinWithTxHistoryV2 for SiaCoin {
+    fn history_wallet_id(&self) -> WalletId { WalletId::new(self.ticker().into()) }
+
+    async fn get_tx_history_filters(
+        &self,
+        target: MyTxHistoryTarget,
+    ) -> MmResult<GetTxHistoryFilters, MyTxHistoryErrorV2> {
+        match target {
+            MyTxHistoryTarget::Iguana => Ok(GetTxHistoryFilters::for_address(self.my_address()?)),
+            target => MmError::err(MyTxHistoryErrorV2::InvalidTarget(format!(
+                "{:?} is not supported by {}",
+                target,
+                self.ticker()
+            ))),
+        }
+    }
+}
+
+/// Fetches the confirmed v2 transactions of my address that aren't stored in the history yet.
+/// Miner payouts and v1 transactions are skipped.
+pub(super) async fn fetch_new_transactions(
+    coin: &SiaCoin,
+    storage: &impl TxHistoryStorage,
+    wallet_id: &WalletId,
+) -> Result<Vec<TransactionDetails>, String> {
+    let my_address = coin.my_sia_address().map_err(|e| e.to_string())?;
+    let events = coin.address_events(&my_address).await.map_err(|e| e.to_string())?;
+
+    let mut new_transactions = Vec::new();
+    for event in events {
+        let tx = match event.data {
+            EventDataWrapper::V2Transaction(tx) => SiaTransaction(tx),
+            _ => continue,
+        };
+        let internal_id = tx.tx_hash_as_bytes();
+        let stored = storage
+            .get_tx_from_history(wallet_id, &internal_id)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        if stored.is_some() {
+            continue;
+        }
+        let timestamp = event.timestamp.timestamp() as u64;
+        new_transactions.push(coin.tx_details(&tx, event.index.height, timestamp)?);
+    }
+    Ok(new_transactions)
+}
+
+/// Polls the wallet events of my address and stores the new transactions to the history.
+pub async fn sia_history_loop(coin: SiaCoin, storage: impl TxHistoryStorage, streaming_manager: StreamingManager) {
+    let wallet_id = coin.history_wallet_id();
+    if let Err(e) = storage.init(&wallet_id).await {
+        log::error!("Error initializing {} tx history storage: {:?}", coin.ticker(), e);
+        coin.set_history_sync_state(HistorySyncState::Error(json!({
+            "message": format!("{:?}", e),
+        })));
+        return;
+    }
+
+    loop {
+        match fetch_new_transactions(&coin, &storage, &wallet_id).await {
+            Ok(new_transactions) => {
+                if !new_transactions.is_empty() {
+                    streaming_manager
+                        .send_fn(&TxHistoryEventStreamer::derive_streamer_id(coin.ticker()), || {
+                            new_transactions.clone()
+                        })
+                        .ok();
+                    if let Err(e) = storage.add_transactions_to_history(&wallet_id, new_transactions).await {
+                        log::error!("Error adding {} transactions to history: {:?}", coin.ticker(), e);
+                        coin.set_history_sync_state(HistorySyncState::Error(json!({
+                            "message": format!("{:?}", e),
+                        })));
+                        return;
+                    }
+                }
+                coin.set_history_sync_state(HistorySyncState::Finished);
+            },
+            Err(e) => log::warn!("Error fetching {} transactions: {}", coin.ticker(), e),
+        }
+
+        Timer::sleep(SIA_HISTORY_POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,77 @@
+use super::{currency_from_hastings, siacoin_from_hastings, siacoin_to_hastings, SiaCoin};
+use crate::{MarketCoinOps, TransactionDetails, WithdrawError, WithdrawFee, WithdrawRequest};
+use common::now_sec;
+use mm2_err_handle::prelude::*;
+use sia_rust::transaction::SiacoinOutput;
+use sia_rust::types::Address;
+use std::str::FromStr;
+
+impl SiaCoin {
+    /// Sia withdrawals pay a fixed miner fee, so only [`WithdrawFee::UtxoFixed`] can override the configured one.
+    fn withdraw_fee(&self, fee: &Option<WithdrawFee>) -> MmResult<u128, WithdrawError> {
+        match fee {
+            None => Ok(self.0.conf.tx_fee),
+            Some(WithdrawFee::UtxoFixed { amount }) => {
+                siacoin_to_hastings(amount).map_to_mm(WithdrawError::InvalidFeePolicy)
+            },
+            Some(fee_policy) => MmError::err(WithdrawError::InvalidFeePolicy(format!(
+                "Expected 'UtxoFixed' fee policy, found {:?}",
+                fee_policy
+            ))),
+        }
+    }
+
+    pub(super) async fn withdraw_impl(&self, req: WithdrawRequest) -> MmResult<TransactionDetails, WithdrawError> {
+        if req.from.is_some() {
+            return MmError::err(WithdrawError::UnsupportedError(
+                "'from' is not supported, Sia is activated with a single address".to_owned(),
+            ));
+        }
+        req.ensure_no_utxo_specific_fields(self.ticker())?;
+
+        let to = Address::from_str(&req.to).map_to_mm(|e| WithdrawError::InvalidAddress(e.to_string()))?;
+        let tx_fee = self.withdraw_fee(&req.fee)?;
+
+        let amount = if req.max {
+            let my_address = self.my_sia_address()?;
+            let balance: u128 = self
+                .spendable_outputs(&my_address)
+                .await
+                .map_to_mm(|e| WithdrawError::Transport(e.to_string()))?
+                .iter()
+                .map(|output| output.siacoin_output.value.to_u128())
+                .sum();
+            if balance == 0 {
+                return MmError::err(WithdrawError::ZeroBalanceToWithdrawMax);
+            }
+            balance.checked_sub(tx_fee).filter(|amount| *amount > 0).or_mm_err(|| {
+                WithdrawError::NotSufficientBalance {
+                    coin: self.ticker().to_owned(),
+                    available: siacoin_from_hastings(balance),
+                    required: siacoin_from_hastings(tx_fee),
+                }
+            })?
+        } else {
+            siacoin_to_hastings(&req.amount).map_to_mm(WithdrawError::InternalError)?
+        };
+
+        let min_tx_amount = self.min_tx_amount();
+        if siacoin_from_hastings(amount) < min_tx_amount {
+            return MmError::err(WithdrawError::AmountTooLow {
+                amount: siacoin_from_hastings(amount),
+                threshold: min_tx_amount,
+            });
+        }
+
+        let output = SiacoinOutput {
+            value: currency_from_hastings(amount),
+            address: to,
+        };
+        let tx = self.build_signed_tx(vec![output], tx_fee).await?;
+        let mut details = self
+            .tx_details(&tx, 0, now_sec())
+            .map_to_mm(WithdrawError::InternalError)?;
+        details.memo = req.memo;
+        Ok(details)
+    }
+}
@@ -3,11 +3,14 @@ mod nucleus;
 
 use std::{convert::TryFrom, str::FromStr};
 
+use cosmrs::proto::traits::Name;
 use cosmrs::{tx::Msg, AccountId, Any, Coin, ErrorReport};
 use iris::htlc::{IrisClaimHtlcMsg, IrisCreateHtlcMsg};
 use nucleus::htlc::{NucleusClaimHtlcMsg, NucleusCreateHtlcMsg};
 
+#[cfg(test)] use iris::htlc_proto::IrisHtlcProto;
 use iris::htlc_proto::{IrisClaimHtlcProto, IrisCreateHtlcProto, IrisQueryHtlcResponseProto};
+#[cfg(test)] use nucleus::htlc_proto::NucleusHtlcProto;
 use nucleus::htlc_proto::{NucleusClaimHtlcProto, NucleusCreateHtlcProto, NucleusQueryHtlcResponseProto};
 use prost::{DecodeError, Message};
 use std::io;
@@ -54,6 +57,22 @@ impl HtlcType {
             Self::Iris => IRIS_PATH.to_owned(),
         }
     }
+
+    /// Returns the type URL of the create HTLC message.
+    pub(crate) fn create_htlc_type_url(&self) -> String {
+        match self {
+            Self::Nucleus => NucleusCreateHtlcProto::type_url(),
+            Self::Iris => IrisCreateHtlcProto::type_url(),
+        }
+    }
+
+    /// Returns the type URL of the claim HTLC message.
+    pub(crate) fn claim_htlc_type_url(&self) -> String {
+        match self {
+            Self::Nucleus => NucleusClaimHtlcProto::type_url(),
+            Self::Iris => IrisClaimHtlcProto::type_url(),
+        }
+    }
 }
 
 /// Custom Tendermint message types specific to certain Cosmos chains and may not be available on all chains.
@@ -170,6 +189,22 @@ impl CreateHtlcMsg {
         }
     }
 
+    /// Returns the inner field `hash_lock`.
+    pub(crate) fn hash_lock(&self) -> &str {
+        match self {
+            Self::Iris(inner) => &inner.hash_lock,
+            Self::Nucleus(inner) => &inner.hash_lock,
+        }
+    }
+
+    /// Returns the inner field `time_lock`.
+    pub(crate) fn time_lock(&self) -> u64 {
+        match self {
+            Self::Iris(inner) => inner.time_lock,
+            Self::Nucleus(inner) => inner.time_lock,
+        }
+    }
+
     /// Generates `Any` from the inner CreateHTLC message.
     pub(crate) fn to_any(&self) -> Result<Any, ErrorReport> {
         match self {
@@ -193,6 +228,14 @@ impl ClaimHtlcMsg {
         }
     }
 
+    /// Returns the inner field `id`.
+    pub(crate) fn id(&self) -> &str {
+        match self {
+            Self::Iris(inner) => &inner.id,
+            Self::Nucleus(inner) => &inner.id,
+        }
+    }
+
     /// Returns the inner field `secret`.
     pub(crate) fn secret(&self) -> &str {
         match self {
@@ -285,6 +328,25 @@ impl QueryHtlcResponse {
         }
     }
 
+    /// Builds the response of an HTLC in the given `state`.
+    #[cfg(test)]
+    pub(crate) fn with_state(htlc_type: HtlcType, state: i32) -> Self {
+        match htlc_type {
+            HtlcType::Nucleus => Self::Nucleus(NucleusQueryHtlcResponseProto {
+                htlc: Some(NucleusHtlcProto {
+                    state,
+                    ..Default::default()
+                }),
+            }),
+            HtlcType::Iris => Self::Iris(IrisQueryHtlcResponseProto {
+                htlc: Some(IrisHtlcProto {
+                    state,
+                    ..Default::default()
+                }),
+            }),
+        }
+    }
+
     /// Returns the inner field `htlc_state`.
     pub(crate) fn htlc_state(&self) -> Option<i32> {
         match self {
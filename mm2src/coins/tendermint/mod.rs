@@ -8,12 +8,14 @@ mod ibc;
 mod rpc;
 pub mod tendermint_balance_events;
 mod tendermint_coin;
+mod tendermint_swap_v2;
 mod tendermint_token;
 pub mod tendermint_tx_history_v2;
 
 pub use cosmrs::tendermint::PublicKey as TendermintPublicKey;
 pub use cosmrs::AccountId;
 pub use tendermint_coin::*;
+pub use tendermint_swap_v2::TendermintAssocTypesError;
 pub use tendermint_token::*;
 
 pub(crate) const BCH_COIN_PROTOCOL_TYPE: &str = "BCH";
@@ -20,13 +20,17 @@ use crate::{big_decimal_from_sat_unsigned, BalanceError, BalanceFut, BigDecimal,
             CoinBalance, ConfirmPaymentInput, DelegationError, DexFee, FeeApproxStage, FoundSwapTxSpend,
             HistorySyncState, MarketCoinOps, MmCoin, NegotiateSwapContractAddrErr, PrivKeyBuildPolicy, PrivKeyPolicy,
             PrivKeyPolicyNotAllowed, RawTransactionError, RawTransactionFut, RawTransactionRequest, RawTransactionRes,
-            RawTransactionResult, RefundPaymentArgs, RpcCommonOps, SearchForSwapTxSpendInput, SendPaymentArgs,
-            SignRawTransactionRequest, SignatureError, SignatureResult, SpendPaymentArgs, SwapOps, ToBytes, TradeFee,
-            TradePreimageError, TradePreimageFut, TradePreimageResult, TradePreimageValue, TransactionData,
-            TransactionDetails, TransactionEnum, TransactionErr, TransactionFut, TransactionResult, TransactionType,
-            TxFeeDetails, TxMarshalingErr, UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs,
-            ValidateOtherPubKeyErr, ValidatePaymentFut, ValidatePaymentInput, VerificationError, VerificationResult,
-            WaitForHTLCTxSpendArgs, WatcherOps, WeakSpawner, WithdrawError, WithdrawFee, WithdrawFut, WithdrawRequest};
+            RawTransactionResult, RefundPaymentArgs, RpcCommonOps, SearchForSwapTxSpendInput,
+            SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SignRawTransactionRequest, SignatureError,
+            SignatureResult, SpendPaymentArgs, SwapOps, ToBytes, TradeFee, TradePreimageError, TradePreimageFut,
+            TradePreimageResult, TradePreimageValue, TransactionData, TransactionDetails, TransactionEnum,
+            TransactionErr, TransactionFut, TransactionResult, TransactionType, TxFeeDetails, TxMarshalingErr,
+            UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr,
+            ValidatePaymentFut, ValidatePaymentInput, ValidateWatcherSpendInput, VerificationError,
+            VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WatcherSearchForSwapTxSpendInput,
+            WatcherSpendType, WatcherValidatePaymentInput, WatcherValidateTakerFeeInput, WeakSpawner, WithdrawError,
+            WithdrawFee, WithdrawFut, WithdrawRequest, EARLY_CONFIRMATION_ERR_LOG, INVALID_PAYMENT_STATE_ERR_LOG,
+            INVALID_RECEIVER_ERR_LOG, INVALID_REFUND_TX_ERR_LOG, INVALID_SENDER_ERR_LOG, OLD_TRANSACTION_ERR_LOG};
 use async_std::prelude::FutureExt as AsyncStdFutureExt;
 use async_trait::async_trait;
 use bip32::DerivationPath;
@@ -126,6 +130,9 @@ const MIN_TIME_LOCK: i64 = 50;
 
 const ACCOUNT_SEQUENCE_ERR: &str = "account sequence mismatch";
 
+/// Time given to a watcher to broadcast the transaction claiming an HTLC.
+const WATCHER_CLAIM_HTLC_TIMEOUT_SECS: u64 = 120;
+
 lazy_static! {
     static ref SEQUENCE_PARSER_REGEX: Regex = Regex::new(r"expected (\d+)").unwrap();
 }
@@ -143,7 +150,7 @@ pub struct TendermintKeyPair {
 }
 
 impl TendermintKeyPair {
-    fn new(private_key_secret: Secp256k1Secret, public_key: Public) -> Self {
+    pub(super) fn new(private_key_secret: Secp256k1Secret, public_key: Public) -> Self {
         Self {
             private_key_secret,
             public_key,
@@ -815,7 +822,7 @@ impl TendermintCoin {
         &self,
         account_info: &BaseAccount,
         priv_key: &Secp256k1Secret,
-        tx_payload: Any,
+        tx_payload: Vec<Any>,
         timeout_height: u64,
         memo: &str,
     ) -> cosmrs::Result<Vec<u8>> {
@@ -827,7 +834,7 @@ impl TendermintCoin {
         let fee = Fee::from_amount_and_gas(fee_amount, GAS_LIMIT_DEFAULT);
 
         let signkey = SigningKey::from_slice(priv_key.as_slice())?;
-        let tx_body = tx::Body::new(vec![tx_payload], memo, timeout_height as u32);
+        let tx_body = tx::Body::new(tx_payload, memo, timeout_height as u32);
         let auth_info = SignerInfo::single_direct(Some(signkey.public_key()), account_info.sequence).auth_info(fee);
         let sign_doc = SignDoc::new(&tx_body, &auth_info, &self.chain_id, account_info.account_number)?;
         sign_doc.sign(&signkey)?.to_bytes()
@@ -837,7 +844,7 @@ impl TendermintCoin {
     /// Refs:
     ///  - Main algorithm: https://github.com/irisnet/irismod/blob/main/modules/htlc/types/htlc.go#L157
     ///  - Coins string building https://github.com/cosmos/cosmos-sdk/blob/main/types/coin.go#L210-L225
-    fn calculate_htlc_id(
+    pub(super) fn calculate_htlc_id(
         &self,
         from_address: &AccountId,
         to_address: &AccountId,
@@ -869,6 +876,20 @@ impl TendermintCoin {
         timeout_height: u64,
         memo: &str,
         timeout: Duration,
+    ) -> Result<(String, Raw), TransactionErr> {
+        self.common_send_raw_msgs_bytes(vec![tx_payload], fee, timeout_height, memo, timeout)
+            .await
+    }
+
+    /// Same as [`TendermintCoin::common_send_raw_tx_bytes`], but puts all the given messages into a single transaction,
+    /// so they are executed atomically.
+    pub(super) async fn common_send_raw_msgs_bytes(
+        &self,
+        tx_payload: Vec<Any>,
+        fee: Fee,
+        timeout_height: u64,
+        memo: &str,
+        timeout: Duration,
     ) -> Result<(String, Raw), TransactionErr> {
         // As there wouldn't be enough time to process the data, to mitigate potential edge problems (such as attempting to send transaction
         // bytes half a second before expiration, which may take longer to send and result in the transaction amount being wasted due to a timeout),
@@ -895,7 +916,7 @@ impl TendermintCoin {
 
     async fn seq_safe_send_raw_tx_bytes(
         &self,
-        tx_payload: Any,
+        tx_payload: Vec<Any>,
         fee: Fee,
         timeout_height: u64,
         memo: &str,
@@ -930,7 +951,7 @@ impl TendermintCoin {
 
     async fn send_unsigned_tx_externally(
         &self,
-        tx_payload: Any,
+        tx_payload: Vec<Any>,
         fee: Fee,
         timeout_height: u64,
         memo: &str,
@@ -945,6 +966,10 @@ impl TendermintCoin {
 
         let account_info = try_tx_s!(self.account_info(&self.account_id).await);
         let SerializedUnsignedTx { tx_json, body_bytes } = if self.is_keplr_from_ledger {
+            let tx_payload = try_tx_s!(tx_payload
+                .into_iter()
+                .exactly_one()
+                .map_err(|_| ERRL!("Ledger signing supports transactions with a single message only")));
             try_tx_s!(self.any_to_legacy_amino_json(&account_info, tx_payload, fee, timeout_height, memo))
         } else {
             try_tx_s!(self.any_to_serialized_sign_doc(&account_info, tx_payload, fee, timeout_height, memo))
@@ -972,7 +997,6 @@ impl TendermintCoin {
         Ok((data.hash, Raw::from(tx_raw_inner)))
     }
 
-    #[allow(deprecated)]
     pub(super) async fn calculate_fee(
         &self,
         msg: Any,
@@ -980,11 +1004,26 @@ impl TendermintCoin {
         memo: &str,
         withdraw_fee: Option<WithdrawFee>,
     ) -> MmResult<Fee, TendermintCoinRpcError> {
+        self.calculate_fee_for_msgs(vec![msg], timeout_height, memo, withdraw_fee)
+            .await
+    }
+
+    /// Calculates the fee of a transaction carrying all the given messages.
+    /// The default gas limit is scaled by the number of messages.
+    #[allow(deprecated)]
+    pub(super) async fn calculate_fee_for_msgs(
+        &self,
+        msgs: Vec<Any>,
+        timeout_height: u64,
+        memo: &str,
+        withdraw_fee: Option<WithdrawFee>,
+    ) -> MmResult<Fee, TendermintCoinRpcError> {
+        let fallback_gas_limit = GAS_LIMIT_DEFAULT * msgs.len() as u64;
         let activated_priv_key = if let Ok(activated_priv_key) = self.activation_policy.activated_key_or_err() {
             activated_priv_key
         } else {
-            let (gas_price, gas_limit) = self.gas_info_for_withdraw(&withdraw_fee, GAS_LIMIT_DEFAULT);
-            let amount = ((GAS_WANTED_BASE_VALUE * 1.5) * gas_price).ceil();
+            let (gas_price, gas_limit) = self.gas_info_for_withdraw(&withdraw_fee, fallback_gas_limit);
+            let amount = ((GAS_WANTED_BASE_VALUE * 1.5 * msgs.len() as f64) * gas_price).ceil();
 
             let fee_amount = Coin {
                 denom: self.platform_denom().clone(),
@@ -997,7 +1036,7 @@ impl TendermintCoin {
         let mut account_info = self.account_info(&self.account_id).await?;
         let (response, raw_response) = loop {
             let tx_bytes = self
-                .gen_simulated_tx(&account_info, activated_priv_key, msg.clone(), timeout_height, memo)
+                .gen_simulated_tx(&account_info, activated_priv_key, msgs.clone(), timeout_height, memo)
                 .map_to_mm(|e| TendermintCoinRpcError::InternalError(format!("{}", e)))?;
 
             let request = AbciRequest::new(
@@ -1039,7 +1078,7 @@ impl TendermintCoin {
             ))
         })?;
 
-        let (gas_price, gas_limit) = self.gas_info_for_withdraw(&withdraw_fee, GAS_LIMIT_DEFAULT);
+        let (gas_price, gas_limit) = self.gas_info_for_withdraw(&withdraw_fee, fallback_gas_limit);
 
         let amount = ((gas.gas_used as f64 * 1.5) * gas_price).ceil();
 
@@ -1071,7 +1110,7 @@ impl TendermintCoin {
         let mut account_info = self.account_info(account_id).await?;
         let (response, raw_response) = loop {
             let tx_bytes = self
-                .gen_simulated_tx(&account_info, &priv_key, msg.clone(), timeout_height, memo)
+                .gen_simulated_tx(&account_info, &priv_key, vec![msg.clone()], timeout_height, memo)
                 .map_to_mm(|e| TendermintCoinRpcError::InternalError(format!("{}", e)))?;
 
             let request = AbciRequest::new(
@@ -1230,7 +1269,8 @@ impl TendermintCoin {
         memo: &str,
     ) -> Result<TransactionData, ErrorReport> {
         if let Some(priv_key) = maybe_priv_key {
-            let tx_raw = self.any_to_signed_raw_tx(&priv_key, account_info, message, fee, timeout_height, memo)?;
+            let tx_raw =
+                self.any_to_signed_raw_tx(&priv_key, account_info, vec![message], fee, timeout_height, memo)?;
             let tx_bytes = tx_raw.to_bytes()?;
             let hash = sha256(&tx_bytes);
 
@@ -1242,14 +1282,14 @@ impl TendermintCoin {
             let SerializedUnsignedTx { tx_json, .. } = if self.is_keplr_from_ledger {
                 self.any_to_legacy_amino_json(account_info, message, fee, timeout_height, memo)
             } else {
-                self.any_to_serialized_sign_doc(account_info, message, fee, timeout_height, memo)
+                self.any_to_serialized_sign_doc(account_info, vec![message], fee, timeout_height, memo)
             }?;
 
             Ok(TransactionData::Unsigned(tx_json))
         }
     }
 
-    fn gen_create_htlc_tx(
+    pub(super) fn gen_create_htlc_tx(
         &self,
         denom: Denom,
         to: &AccountId,
@@ -1287,7 +1327,11 @@ impl TendermintCoin {
         })
     }
 
-    fn gen_claim_htlc_tx(&self, htlc_id: String, secret: &[u8]) -> MmResult<TendermintHtlc, TxMarshalingErr> {
+    pub(super) fn gen_claim_htlc_tx(
+        &self,
+        htlc_id: String,
+        secret: &[u8],
+    ) -> MmResult<TendermintHtlc, TxMarshalingErr> {
         let htlc_type = HtlcType::from_str(&self.account_prefix).map_err(|_| {
             TxMarshalingErr::NotSupported(format!(
                 "Account type '{}' is not supported for HTLCs",
@@ -1309,13 +1353,13 @@ impl TendermintCoin {
         &self,
         priv_key: &Secp256k1Secret,
         account_info: &BaseAccount,
-        tx_payload: Any,
+        tx_payload: Vec<Any>,
         fee: Fee,
         timeout_height: u64,
         memo: &str,
     ) -> cosmrs::Result<Raw> {
         let signkey = SigningKey::from_slice(priv_key.as_slice())?;
-        let tx_body = tx::Body::new(vec![tx_payload], memo, timeout_height as u32);
+        let tx_body = tx::Body::new(tx_payload, memo, timeout_height as u32);
         let auth_info = SignerInfo::single_direct(Some(signkey.public_key()), account_info.sequence).auth_info(fee);
         let sign_doc = SignDoc::new(&tx_body, &auth_info, &self.chain_id, account_info.account_number)?;
         sign_doc.sign(&signkey)
@@ -1324,12 +1368,12 @@ impl TendermintCoin {
     pub(super) fn any_to_serialized_sign_doc(
         &self,
         account_info: &BaseAccount,
-        tx_payload: Any,
+        tx_payload: Vec<Any>,
         fee: Fee,
         timeout_height: u64,
         memo: &str,
     ) -> cosmrs::Result<SerializedUnsignedTx> {
-        let tx_body = tx::Body::new(vec![tx_payload], memo, timeout_height as u32);
+        let tx_body = tx::Body::new(tx_payload, memo, timeout_height as u32);
         let pubkey = self.activation_policy.public_key()?.into();
         let auth_info = SignerInfo::single_direct(Some(pubkey), account_info.sequence).auth_info(fee);
         let sign_doc = SignDoc::new(&tx_body, &auth_info, &self.chain_id, account_info.account_number)?;
@@ -1440,7 +1484,7 @@ impl TendermintCoin {
             .insert(denom.to_string(), ActivatedTokenInfo { decimals, ticker });
     }
 
-    fn estimate_blocks_from_duration(&self, duration: u64) -> i64 {
+    pub(super) fn estimate_blocks_from_duration(&self, duration: u64) -> i64 {
         let estimated_time_lock = (duration / self.avg_blocktime as u64) as i64;
 
         estimated_time_lock.clamp(MIN_TIME_LOCK, MAX_TIME_LOCK)
@@ -2093,7 +2137,7 @@ impl TendermintCoin {
         Ok((denom_ubalance, denom_balance_dec))
     }
 
-    async fn request_tx(&self, hash: String) -> MmResult<Tx, TendermintCoinRpcError> {
+    pub(super) async fn request_tx(&self, hash: String) -> MmResult<Tx, TendermintCoinRpcError> {
         let request = GetTxRequest { hash };
         let response = self
             .rpc_client()
@@ -2112,6 +2156,30 @@ impl TendermintCoin {
             .or_mm_err(|| TendermintCoinRpcError::InvalidResponse(format!("Tx {} does not exist", request.hash)))
     }
 
+    /// Returns the transaction with the given hash along with the height of the block it's included in.
+    async fn request_tx_with_height(&self, hash: String) -> MmResult<(Tx, i64), TendermintCoinRpcError> {
+        let request = GetTxRequest { hash };
+        let response = self
+            .rpc_client()
+            .await?
+            .abci_query(
+                Some(ABCI_GET_TX_PATH.to_string()),
+                request.encode_to_vec(),
+                ABCI_REQUEST_HEIGHT,
+                ABCI_REQUEST_PROVE,
+            )
+            .await?;
+
+        let response = GetTxResponse::decode(response.value.as_slice())?;
+        match (response.tx, response.tx_response) {
+            (Some(tx), Some(tx_response)) => Ok((tx, tx_response.height)),
+            _ => MmError::err(TendermintCoinRpcError::InvalidResponse(format!(
+                "Tx {} does not exist",
+                request.hash
+            ))),
+        }
+    }
+
     /// Returns status code of transaction.
     /// If tx doesn't exists on chain, then returns `None`.
     async fn get_tx_status_code_or_none(
@@ -2243,6 +2311,279 @@ impl TendermintCoin {
         }
     }
 
+    /// Decodes the `CreateHtlc` message of the given HTLC payment.
+    fn create_htlc_msg_from_payment(&self, payment_tx: &[u8]) -> MmResult<CreateHtlcMsg, ValidatePaymentError> {
+        let tx = cosmrs::Tx::from_bytes(payment_tx)
+            .map_to_mm(|e| ValidatePaymentError::TxDeserializationError(e.to_string()))?;
+        let msg = match tx.body.messages.as_slice() {
+            [msg] => msg,
+            _ => {
+                return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                    "Payment tx must have exactly one message".into(),
+                ))
+            },
+        };
+        let htlc_type = HtlcType::from_str(&self.account_prefix)
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+        let htlc_proto = CreateHtlcProto::decode(htlc_type, msg.value.as_slice())
+            .map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?;
+        CreateHtlcMsg::try_from(htlc_proto).map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))
+    }
+
+    /// HTLCs can be claimed by anyone who knows the secret and are refunded automatically once expired,
+    /// so watchers get the payment itself instead of a presigned spend or refund transaction.
+    pub(super) fn htlc_payment_as_watcher_preimage(&self, payment_tx: &[u8]) -> TransactionFut {
+        let tx = try_tx_fus!(self.tx_enum_from_bytes(payment_tx));
+        Box::new(futures01::future::ok(tx))
+    }
+
+    /// Claims the HTLC created by `payment_tx` on behalf of its recipient.
+    /// The claimed amount is sent to the HTLC recipient regardless of who broadcasts the claim.
+    pub(super) async fn claim_htlc_as_watcher(
+        &self,
+        payment_tx: &[u8],
+        secret_hash: &[u8],
+        secret: &[u8],
+    ) -> TransactionResult {
+        let htlc = try_tx_s!(self.create_htlc_msg_from_payment(payment_tx));
+        let htlc_id = self.calculate_htlc_id(htlc.sender(), htlc.to(), htlc.amount(), secret_hash);
+        let claim_htlc_tx = try_tx_s!(self.gen_claim_htlc_tx(htlc_id, secret));
+
+        let current_block = try_tx_s!(self.current_block().compat().await);
+        let timeout_height = current_block + TIMEOUT_HEIGHT_DELTA;
+
+        let fee = try_tx_s!(
+            self.calculate_fee(claim_htlc_tx.msg_payload.clone(), timeout_height, TX_DEFAULT_MEMO, None)
+                .await
+        );
+
+        let (_tx_id, tx_raw) = self
+            .common_send_raw_tx_bytes(
+                claim_htlc_tx.msg_payload,
+                fee,
+                timeout_height,
+                TX_DEFAULT_MEMO,
+                Duration::from_secs(WATCHER_CLAIM_HTLC_TIMEOUT_SECS),
+            )
+            .await?;
+
+        Ok(TransactionEnum::CosmosTransaction(CosmosTransaction {
+            data: tx_raw.into(),
+        }))
+    }
+
+    pub(super) async fn watcher_validate_taker_fee_for_denom(
+        &self,
+        input: WatcherValidateTakerFeeInput,
+        denom: Denom,
+    ) -> ValidatePaymentResult<()> {
+        let hash = hex::encode_upper(&input.taker_fee_hash);
+        let (fee_tx, height) = self.request_tx_with_height(hash.clone()).await?;
+
+        if (height as u64) < input.min_block_number {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Fee tx {} confirmed before min_block {}",
+                EARLY_CONFIRMATION_ERR_LOG, hash, input.min_block_number
+            )));
+        }
+
+        let confirmed_at = self
+            .get_block_timestamp(height)
+            .await?
+            .or_mm_err(|| ValidatePaymentError::InvalidRpcResponse(format!("No timestamp of block {}", height)))?;
+        if now_sec().saturating_sub(confirmed_at) > input.lock_duration {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Taker fee {} is too old",
+                OLD_TRANSACTION_ERR_LOG, hash
+            )));
+        }
+
+        let expected_sender = AccountId::new(&self.account_prefix, dhash160(&input.sender_pubkey).as_slice())
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+        let dex_address = AccountId::new(&self.account_prefix, dhash160(self.dex_pubkey()).as_slice())
+            .map_to_mm(|e| ValidatePaymentError::InternalError(e.to_string()))?;
+
+        let tx_body = fee_tx
+            .body
+            .or_mm_err(|| ValidatePaymentError::TxDeserializationError(format!("Fee tx {} has no body", hash)))?;
+        let msg = match tx_body.messages.as_slice() {
+            [msg] => msg,
+            _ => {
+                return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                    "Tx body must have exactly one message".to_string(),
+                ))
+            },
+        };
+
+        // The standard dex fee is sent with `MsgSend`, the dex fee with burn is sent with `MsgMultiSend`
+        // having the dex fee as the first output.
+        let (sender, receiver, coins) = if let Ok(msg) = MsgSend::from_any(msg) {
+            (msg.from_address, msg.to_address, msg.amount)
+        } else if let Ok(mut msg) = MsgMultiSend::from_any(msg) {
+            if msg.inputs.len() != 1 || msg.outputs.is_empty() {
+                return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                    "Msg must have exactly one input and at least one output".to_string(),
+                ));
+            }
+            let dex_output = msg.outputs.remove(0);
+            (msg.inputs.remove(0).address, dex_output.address, dex_output.coins)
+        } else {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Unexpected fee tx message type {}",
+                msg.type_url
+            )));
+        };
+
+        if sender != expected_sender {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Invalid sender: {}, expected {}",
+                INVALID_SENDER_ERR_LOG, sender, expected_sender
+            )));
+        }
+        if receiver != dex_address {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Dex fee is sent to wrong address: {}, expected {}",
+                INVALID_RECEIVER_ERR_LOG, receiver, dex_address
+            )));
+        }
+        if !coins.iter().any(|coin| coin.denom == denom) {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Dex fee {:?} is not paid in {}",
+                coins, denom
+            )));
+        }
+        Ok(())
+    }
+
+    pub(super) async fn watcher_validate_taker_payment_for_denom(
+        &self,
+        input: WatcherValidatePaymentInput,
+        denom: Denom,
+    ) -> ValidatePaymentResult<()> {
+        if input.taker_payment_refund_preimage != input.payment_tx {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Refund preimage must be the taker payment itself",
+                INVALID_REFUND_TX_ERR_LOG
+            )));
+        }
+
+        let htlc = self.create_htlc_msg_from_payment(&input.payment_tx)?;
+
+        let taker_address = AccountId::new(&self.account_prefix, dhash160(&input.taker_pub).as_slice())
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+        let maker_address = AccountId::new(&self.account_prefix, dhash160(&input.maker_pub).as_slice())
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+
+        if htlc.sender() != &taker_address {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Payment tx is sent by {}, expected {}",
+                INVALID_SENDER_ERR_LOG,
+                htlc.sender(),
+                taker_address
+            )));
+        }
+        if htlc.to() != &maker_address {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Payment tx is sent to {}, expected {}",
+                INVALID_RECEIVER_ERR_LOG,
+                htlc.to(),
+                maker_address
+            )));
+        }
+        let expected_hash_lock = hex::encode(&input.secret_hash);
+        if htlc.hash_lock() != expected_hash_lock {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment tx hash lock {} doesn't match the expected {}",
+                htlc.hash_lock(),
+                expected_hash_lock
+            )));
+        }
+        if !htlc.amount().iter().any(|coin| coin.denom == denom) {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Payment {:?} is not sent in {}",
+                htlc.amount(),
+                denom
+            )));
+        }
+
+        let hash = hex::encode_upper(sha256(&input.payment_tx).as_slice());
+        let tx_from_rpc = self.request_tx(hash).await?;
+        if input.payment_tx != tx_from_rpc.encode_to_vec() {
+            return MmError::err(ValidatePaymentError::InvalidRpcResponse(
+                "Tx from RPC doesn't match the input".into(),
+            ));
+        }
+
+        let htlc_id = self.calculate_htlc_id(htlc.sender(), htlc.to(), htlc.amount(), &input.secret_hash);
+        let htlc_response = self.query_htlc(htlc_id.clone()).await?;
+        let htlc_state = htlc_response
+            .htlc_state()
+            .or_mm_err(|| ValidatePaymentError::InvalidRpcResponse(format!("No HTLC data for {}", htlc_id)))?;
+
+        match htlc_state {
+            HTLC_STATE_OPEN => Ok(()),
+            unexpected_state => MmError::err(ValidatePaymentError::UnexpectedPaymentState(format!(
+                "{}: {}",
+                INVALID_PAYMENT_STATE_ERR_LOG, unexpected_state
+            ))),
+        }
+    }
+
+    pub(super) async fn taker_validates_payment_spend_or_refund_for_denom(
+        &self,
+        input: ValidateWatcherSpendInput,
+        denom: Denom,
+        decimals: u8,
+    ) -> ValidatePaymentResult<()> {
+        match input.spend_type {
+            WatcherSpendType::MakerPaymentSpend => {},
+            // HTLC is refunded automatically without transaction, and the refund is reported
+            // by `search_for_swap_tx_spend` only once the HTLC state is queried as refunded.
+            WatcherSpendType::TakerPaymentRefund => return Ok(()),
+        }
+
+        let tx = cosmrs::Tx::from_bytes(&input.payment_tx)
+            .map_to_mm(|e| ValidatePaymentError::TxDeserializationError(e.to_string()))?;
+        let msg = match tx.body.messages.as_slice() {
+            [msg] => msg,
+            _ => {
+                return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                    "Claim tx must have exactly one message".into(),
+                ))
+            },
+        };
+        let htlc_type = HtlcType::from_str(&self.account_prefix)
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+        let claim_proto = ClaimHtlcProto::decode(htlc_type, msg.value.as_slice())
+            .map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?;
+        let claim =
+            ClaimHtlcMsg::try_from(claim_proto).map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?;
+
+        let maker_address = AccountId::new(&self.account_prefix, dhash160(&input.maker_pub).as_slice())
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+        let amount = sat_from_big_decimal(&input.amount, decimals)?;
+        let amount = vec![Coin {
+            denom,
+            amount: amount.into(),
+        }];
+        let expected_id = self.calculate_htlc_id(&maker_address, &self.account_id, &amount, &input.secret_hash);
+        if claim.id() != expected_id {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Claim tx spends HTLC {}, expected {}",
+                claim.id(),
+                expected_id
+            )));
+        }
+
+        let secret = hex::decode(claim.secret()).map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?;
+        if sha256(&secret).as_slice() != input.secret_hash.as_slice() {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(
+                "Claim tx secret doesn't match the secret hash".into(),
+            ));
+        }
+        Ok(())
+    }
+
     pub(crate) fn gas_info_for_withdraw(
         &self,
         withdraw_fee: &Option<WithdrawFee>,
@@ -3016,6 +3357,7 @@ impl MmCoin for TendermintCoin {
     fn withdraw(&self, req: WithdrawRequest) -> WithdrawFut {
         let coin = self.clone();
         let fut = async move {
+            req.ensure_no_utxo_specific_fields(coin.ticker())?;
             let to_address =
                 AccountId::from_str(&req.to).map_to_mm(|e| WithdrawError::InvalidAddress(e.to_string()))?;
 
@@ -3560,10 +3902,9 @@ impl SwapOps for TendermintCoin {
         .await
     }
 
-    // TODO: release this function once watchers are supported
-    // fn is_supported_by_watchers(&self) -> bool {
-    //     !matches!(self.activation_policy, TendermintActivationPolicy::PublicKey(_))
-    // }
+    fn is_supported_by_watchers(&self) -> bool {
+        !matches!(self.activation_policy, TendermintActivationPolicy::PublicKey(_))
+    }
 
     async fn send_maker_spends_taker_payment(
         &self,
@@ -3793,7 +4134,90 @@ impl SwapOps for TendermintCoin {
 }
 
 #[async_trait]
-impl WatcherOps for TendermintCoin {}
+impl WatcherOps for TendermintCoin {
+    fn send_maker_payment_spend_preimage(&self, input: SendMakerPaymentSpendPreimageInput) -> TransactionFut {
+        let coin = self.clone();
+        let payment_tx = input.preimage.to_vec();
+        let secret_hash = input.secret_hash.to_vec();
+        let secret = input.secret.to_vec();
+        let fut = async move { coin.claim_htlc_as_watcher(&payment_tx, &secret_hash, &secret).await };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn send_taker_payment_refund_preimage(&self, _watcher_refunds_payment_args: RefundPaymentArgs) -> TransactionFut {
+        Box::new(futures01::future::err(TransactionErr::Plain(
+            "Doesn't need transaction broadcast to refund IRIS HTLC".into(),
+        )))
+    }
+
+    fn create_taker_payment_refund_preimage(
+        &self,
+        taker_payment_tx: &[u8],
+        _time_lock: u64,
+        _maker_pub: &[u8],
+        _secret_hash: &[u8],
+        _swap_contract_address: &Option<BytesJson>,
+        _swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        self.htlc_payment_as_watcher_preimage(taker_payment_tx)
+    }
+
+    fn create_maker_payment_spend_preimage(
+        &self,
+        maker_payment_tx: &[u8],
+        _time_lock: u64,
+        _maker_pub: &[u8],
+        _secret_hash: &[u8],
+        _swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        self.htlc_payment_as_watcher_preimage(maker_payment_tx)
+    }
+
+    fn watcher_validate_taker_fee(&self, input: WatcherValidateTakerFeeInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move {
+            coin.watcher_validate_taker_fee_for_denom(input, coin.denom.clone())
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move {
+            coin.watcher_validate_taker_payment_for_denom(input, coin.denom.clone())
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move {
+            coin.taker_validates_payment_spend_or_refund_for_denom(input, coin.denom.clone(), coin.decimals)
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    async fn watcher_search_for_swap_tx_spend(
+        &self,
+        input: WatcherSearchForSwapTxSpendInput<'_>,
+    ) -> Result<Option<FoundSwapTxSpend>, String> {
+        self.search_for_swap_tx_spend(SearchForSwapTxSpendInput {
+            time_lock: input.time_lock as u64,
+            other_pub: input.maker_pub,
+            secret_hash: input.secret_hash,
+            tx: input.tx,
+            search_from_block: input.search_from_block,
+            swap_contract_address: &None,
+            swap_unique_data: &[],
+            watcher_reward: input.watcher_reward,
+        })
+        .await
+        .map_err(|e| e.to_string())
+    }
+}
 
 /// Processes the given `priv_key_build_policy` and returns corresponding `TendermintPrivKeyPolicy`.
 /// This function expects either [`PrivKeyBuildPolicy::IguanaPrivKey`]
@@ -5133,4 +5557,172 @@ pub mod tendermint_coin_tests {
 
         assert_eq!(expected_list, actual_list);
     }
+
+    /// Builds an offline IRIS coin for the tests that mock the RPC requests.
+    fn iris_coin_for_mocked_rpc(ctx: &MmArc) -> TendermintCoin {
+        let conf = TendermintConf {
+            avg_blocktime: AVG_BLOCKTIME,
+            derivation_path: None,
+        };
+
+        let key_pair = key_pair_from_seed(IRIS_TESTNET_HTLC_PAIR1_SEED).unwrap();
+        let tendermint_pair = TendermintKeyPair::new(key_pair.private().secret, *key_pair.public());
+        let activation_policy =
+            TendermintActivationPolicy::with_private_key_policy(TendermintPrivKeyPolicy::Iguana(tendermint_pair));
+
+        block_on(TendermintCoin::init(
+            ctx,
+            "IRIS".to_string(),
+            conf,
+            get_iris_protocol(),
+            vec![RpcNode::for_test(IRIS_TESTNET_RPC_URL)],
+            false,
+            activation_policy,
+            false,
+        ))
+        .unwrap()
+    }
+
+    fn sign_msg_for_test(coin: &TendermintCoin, msg: Any) -> Vec<u8> {
+        let priv_key = coin.activation_policy.activated_key_or_err().unwrap();
+        coin.gen_simulated_tx(&BaseAccount::default(), priv_key, vec![msg], 0, TX_DEFAULT_MEMO)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_watcher_validate_taker_payment() {
+        let ctx = mm2_core::mm_ctx::MmCtxBuilder::default().into_mm_arc();
+        let coin = iris_coin_for_mocked_rpc(&ctx);
+
+        let taker_pub = key_pair_from_seed(IRIS_TESTNET_HTLC_PAIR1_SEED)
+            .unwrap()
+            .public()
+            .to_vec();
+        let maker_address: AccountId = IRIS_TESTNET_HTLC_PAIR2_ADDRESS.parse().unwrap();
+        let secret_hash = sha256(&[1; 32]).to_vec();
+        let create_htlc_tx = coin
+            .gen_create_htlc_tx(coin.denom.clone(), &maker_address, 1000, &secret_hash, 1000)
+            .unwrap();
+        let payment_tx = sign_msg_for_test(&coin, create_htlc_tx.msg_payload);
+
+        let rpc_tx = payment_tx.clone();
+        TendermintCoin::request_tx.mock_safe(move |_, _| {
+            let tx = Tx::decode(rpc_tx.as_slice()).unwrap();
+            MockResult::Return(Box::pin(async move { Ok(tx) }))
+        });
+        let htlc_state = Arc::new(Mutex::new(HTLC_STATE_OPEN));
+        let queried_state = htlc_state.clone();
+        TendermintCoin::query_htlc.mock_safe(move |_, _| {
+            let response = QueryHtlcResponse::with_state(HtlcType::Iris, *queried_state.lock().unwrap());
+            MockResult::Return(Box::pin(async move { Ok(response) }))
+        });
+
+        let input = WatcherValidatePaymentInput {
+            payment_tx: payment_tx.clone(),
+            taker_payment_refund_preimage: payment_tx.clone(),
+            time_lock: 1000,
+            taker_pub: taker_pub.clone(),
+            maker_pub: IRIS_TESTNET_HTLC_PAIR2_PUB_KEY.to_vec(),
+            secret_hash: secret_hash.clone(),
+            wait_until: 0,
+            confirmations: 1,
+            maker_coin: coin.clone().into(),
+        };
+        let validate = |input: WatcherValidatePaymentInput| {
+            block_on(coin.watcher_validate_taker_payment_for_denom(input, coin.denom.clone()))
+        };
+        validate(input.clone()).unwrap();
+
+        let wrong_refund = WatcherValidatePaymentInput {
+            taker_payment_refund_preimage: vec![0; 32],
+            ..input.clone()
+        };
+        let err = validate(wrong_refund).unwrap_err().into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        // The payment is sent by the maker instead of the taker.
+        let wrong_taker = WatcherValidatePaymentInput {
+            taker_pub: IRIS_TESTNET_HTLC_PAIR2_PUB_KEY.to_vec(),
+            maker_pub: taker_pub,
+            ..input.clone()
+        };
+        let err = validate(wrong_taker).unwrap_err().into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        let wrong_secret_hash = WatcherValidatePaymentInput {
+            secret_hash: sha256(&[2; 32]).to_vec(),
+            ..input.clone()
+        };
+        let err = validate(wrong_secret_hash).unwrap_err().into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        *htlc_state.lock().unwrap() = HTLC_STATE_COMPLETED;
+        let err = validate(input).unwrap_err().into_inner();
+        assert!(
+            matches!(err, ValidatePaymentError::UnexpectedPaymentState(_)),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_taker_validates_watcher_spend() {
+        let ctx = mm2_core::mm_ctx::MmCtxBuilder::default().into_mm_arc();
+        let coin = iris_coin_for_mocked_rpc(&ctx);
+
+        let maker_address: AccountId = IRIS_TESTNET_HTLC_PAIR2_ADDRESS.parse().unwrap();
+        let secret = [1; 32];
+        let secret_hash = sha256(&secret).to_vec();
+        let amount = vec![Coin {
+            denom: coin.denom.clone(),
+            amount: 1000u64.into(),
+        }];
+        let htlc_id = coin.calculate_htlc_id(&maker_address, &coin.account_id, &amount, &secret_hash);
+        let claim_htlc_tx = coin.gen_claim_htlc_tx(htlc_id, &secret).unwrap();
+
+        let input = ValidateWatcherSpendInput {
+            payment_tx: sign_msg_for_test(&coin, claim_htlc_tx.msg_payload),
+            maker_pub: IRIS_TESTNET_HTLC_PAIR2_PUB_KEY.to_vec(),
+            swap_contract_address: None,
+            time_lock: 1000,
+            secret_hash: secret_hash.clone(),
+            amount: big_decimal_from_sat_unsigned(1000, coin.decimals),
+            watcher_reward: None,
+            spend_type: WatcherSpendType::MakerPaymentSpend,
+        };
+        let validate = |input: ValidateWatcherSpendInput| {
+            block_on(coin.taker_validates_payment_spend_or_refund_for_denom(input, coin.denom.clone(), coin.decimals))
+        };
+        validate(input.clone()).unwrap();
+
+        // The claimed HTLC locks another amount.
+        let wrong_amount = ValidateWatcherSpendInput {
+            amount: big_decimal_from_sat_unsigned(999, coin.decimals),
+            ..input.clone()
+        };
+        let err = validate(wrong_amount).unwrap_err().into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        // The revealed secret doesn't match the secret hash of the claimed HTLC.
+        let other_secret_hash = sha256(&[2; 32]).to_vec();
+        let other_htlc_id = coin.calculate_htlc_id(&maker_address, &coin.account_id, &amount, &other_secret_hash);
+        let wrong_secret = ValidateWatcherSpendInput {
+            payment_tx: sign_msg_for_test(
+                &coin,
+                coin.gen_claim_htlc_tx(other_htlc_id, &secret).unwrap().msg_payload,
+            ),
+            secret_hash: other_secret_hash,
+            ..input.clone()
+        };
+        let err = validate(wrong_secret).unwrap_err().into_inner();
+        assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)), "{:?}", err);
+
+        // HTLCs are refunded without a transaction, so there is nothing to validate.
+        let refund = ValidateWatcherSpendInput {
+            payment_tx: vec![],
+            spend_type: WatcherSpendType::TakerPaymentRefund,
+            ..input
+        };
+        validate(refund).unwrap();
+    }
 }
@@ -0,0 +1,1461 @@
+//! Trading Protocol Upgrade (swap v2) for Tendermint coins and tokens, built on top of the IRIS and Nucleus HTLCs.
+//!
+//! These HTLCs are locked by a single hash, can be claimed by anyone who knows the secret (the funds always go to the
+//! recipient) and are refunded to the sender by the chain once expired. Swap v2 transactions map onto them as follows:
+//! * Taker funding creates HTLCs from the taker to itself, one per part of the taker's volume (trading amount with
+//!   premium, dex fee and burn amount). Each of them is locked by a funding secret derived from the taker's private
+//!   key, so only the taker can move them.
+//! * Taker payment is a single transaction claiming the funding HTLCs and creating the HTLCs paying these parts to the
+//!   maker, the dex fee address and the burn address, all locked by the maker's secret hash. The maker claims all of
+//!   them at once revealing its secret, otherwise the chain refunds them to the taker.
+//! * Immediate refund of the taker funding claims the funding HTLCs back and reveals the taker's secret in the memo.
+//! * Maker payment is an HTLC from the maker to the taker locked by the maker's secret hash.
+//! * Time-locked refunds don't need a transaction: like in the legacy swaps, we wait until the chain refunds the
+//!   expired HTLCs and return an empty transaction.
+
+use super::htlc::{ClaimHtlcMsg, ClaimHtlcProto, CreateHtlcMsg, CreateHtlcProto, HtlcType, HTLC_STATE_COMPLETED,
+                  HTLC_STATE_OPEN, HTLC_STATE_REFUNDED};
+use super::rpc::*;
+use super::{CosmosTransaction, TendermintCoin, TendermintCoinRpcError, TendermintToken, TIMEOUT_HEIGHT_DELTA,
+            TX_DEFAULT_MEMO};
+use crate::coin_errors::{ValidatePaymentError, ValidatePaymentResult};
+use crate::hd_wallet::AddrToString;
+use crate::utxo::sat_from_big_decimal;
+use crate::{CommonSwapOpsV2, DexFee, FindPaymentSpendError, FundingTxSpend, GenPreimageResult,
+            GenTakerFundingSpendArgs, GenTakerPaymentSpendArgs, MakerCoinSwapOpsV2, MarketCoinOps, NumConversResult,
+            ParseCoinAssocTypes, PrivKeyPolicyNotAllowed, RefundFundingSecretArgs, RefundMakerPaymentSecretArgs,
+            RefundMakerPaymentTimelockArgs, RefundTakerPaymentArgs, SearchForFundingSpendErr, SendMakerPaymentArgs,
+            SendTakerFundingArgs, SpendMakerPaymentArgs, SwapOps, TakerCoinSwapOpsV2, ToBytes, Transaction,
+            TransactionErr, TxGenError, TxPreimageWithSig, ValidateMakerPaymentArgs, ValidateSwapV2TxError,
+            ValidateSwapV2TxResult, ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageError,
+            ValidateTakerFundingSpendPreimageResult, ValidateTakerPaymentSpendPreimageError,
+            ValidateTakerPaymentSpendPreimageResult};
+use async_trait::async_trait;
+use bitcrypto::{dhash160, sha256};
+use common::executor::Timer;
+use common::log::{debug, warn};
+use common::{now_sec, Future01CompatExt};
+use compatible_time::Duration;
+use cosmrs::proto::cosmos::tx::v1beta1::TxRaw;
+use cosmrs::proto::prost::Message;
+use cosmrs::tendermint::PublicKey;
+use cosmrs::{AccountId, Any, Coin, Denom, ErrorReport};
+use derive_more::Display;
+use hex::FromHexError;
+use keys::{Public, Signature};
+use mm2_err_handle::prelude::*;
+use mm2_number::BigDecimal;
+#[cfg(test)] use mocktopus::macros::*;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+/// Domain separator of the funding secrets derivation.
+const FUNDING_SECRET_PREFIX: &[u8] = b"tendermint swap v2 funding";
+/// Swap v2 transactions are broadcasted with at least this timeout (in seconds).
+const MIN_BROADCAST_TIMEOUT: u64 = 60;
+/// Interval (in seconds) of checking whether an expired HTLC has been refunded or claimed.
+const HTLC_CHECK_INTERVAL: f64 = 10.;
+
+#[derive(Debug, Display)]
+pub enum TendermintAssocTypesError {
+    InvalidAddress(String),
+    InvalidPubkey(String),
+    TxParseError(String),
+}
+
+/// Errors of the validation of swap v2 HTLC transactions, shared by the maker payment and the taker funding.
+#[derive(Debug, Display)]
+enum HtlcTxValidationError {
+    WrongHtlcs(String),
+    InvalidAmount(String),
+    Rpc(String),
+    #[display(fmt = "Tx from RPC doesn't match the input")]
+    TxMismatch {
+        from_rpc: Vec<u8>,
+        actual: Vec<u8>,
+    },
+    UnexpectedHtlcState(String),
+}
+
+impl From<HtlcTxValidationError> for ValidateSwapV2TxError {
+    fn from(err: HtlcTxValidationError) -> Self {
+        match err {
+            HtlcTxValidationError::WrongHtlcs(e) | HtlcTxValidationError::UnexpectedHtlcState(e) => {
+                ValidateSwapV2TxError::WrongPaymentTx(e)
+            },
+            HtlcTxValidationError::InvalidAmount(e) => ValidateSwapV2TxError::InvalidDestinationOrAmount(e),
+            HtlcTxValidationError::Rpc(e) => ValidateSwapV2TxError::Rpc(e),
+            HtlcTxValidationError::TxMismatch { from_rpc, actual } => ValidateSwapV2TxError::TxBytesMismatch {
+                from_rpc: from_rpc.into(),
+                actual: actual.into(),
+            },
+        }
+    }
+}
+
+impl From<HtlcTxValidationError> for ValidatePaymentError {
+    fn from(err: HtlcTxValidationError) -> Self {
+        match err {
+            HtlcTxValidationError::WrongHtlcs(e) | HtlcTxValidationError::InvalidAmount(e) => {
+                ValidatePaymentError::WrongPaymentTx(e)
+            },
+            HtlcTxValidationError::Rpc(e) => ValidatePaymentError::Transport(e),
+            HtlcTxValidationError::TxMismatch { .. } => ValidatePaymentError::InvalidRpcResponse(err.to_string()),
+            HtlcTxValidationError::UnexpectedHtlcState(e) => ValidatePaymentError::UnexpectedPaymentState(e),
+        }
+    }
+}
+
+/// HTLC that a swap v2 transaction is expected to create.
+struct ExpectedHtlc {
+    sender: AccountId,
+    to: AccountId,
+    amount: Vec<Coin>,
+    /// `None` if the hash lock is unknown to the validating side (e.g. funding secret hashes).
+    hash_lock: Option<Vec<u8>>,
+    min_time_lock: u64,
+}
+
+/// HTLC messages and memo of a swap v2 transaction.
+struct SwapV2TxMsgs {
+    created: Vec<CreateHtlcMsg>,
+    claim_secrets: Vec<String>,
+    memo: String,
+}
+
+/// Returns the parts of the taker's volume in micro-units, in the order of the funding and taker payment HTLCs:
+/// trading amount with premium, then dex fee and burn amount if they are not zero.
+fn taker_volume_parts(
+    dex_fee: &DexFee,
+    premium_amount: &BigDecimal,
+    trading_amount: &BigDecimal,
+    decimals: u8,
+) -> NumConversResult<Vec<u64>> {
+    let mut parts = vec![sat_from_big_decimal(&(premium_amount + trading_amount), decimals)?];
+    let fee_amount = dex_fee.fee_amount_as_u64(decimals)?;
+    if fee_amount > 0 {
+        parts.push(fee_amount);
+    }
+    match dex_fee.burn_amount_as_u64(decimals)? {
+        Some(burn_amount) if burn_amount > 0 => parts.push(burn_amount),
+        _ => (),
+    }
+    Ok(parts)
+}
+
+fn single_coin(denom: &Denom, amount: u64) -> Vec<Coin> {
+    vec![Coin {
+        denom: denom.clone(),
+        amount: amount.into(),
+    }]
+}
+
+impl TendermintCoin {
+    fn account_id_from_pubkey(&self, pubkey: &[u8]) -> Result<AccountId, ErrorReport> {
+        AccountId::new(&self.account_prefix, dhash160(pubkey).as_slice())
+    }
+
+    /// Converts an absolute swap v2 time lock into the HTLC time lock, which is a number of blocks.
+    fn htlc_time_lock(&self, time_lock: u64) -> u64 {
+        self.estimate_blocks_from_duration(time_lock.saturating_sub(now_sec())) as u64
+    }
+
+    /// Derives the secret locking the funding HTLC at `index`.
+    /// Requires the private key, so the funding can't be done with the external signing activation policy.
+    fn funding_secret(&self, swap_unique_data: &[u8], index: usize) -> MmResult<[u8; 32], PrivKeyPolicyNotAllowed> {
+        let priv_key = self.activation_policy.activated_key_or_err()?;
+        let preimage = [FUNDING_SECRET_PREFIX, priv_key.as_slice(), swap_unique_data, &[
+            index as u8
+        ]]
+        .concat();
+        Ok(sha256(&preimage).take())
+    }
+
+    /// Returns the recipients of the taker payment parts: maker, dex fee address and burn address.
+    fn taker_payment_recipients(&self, maker: AccountId) -> Result<Vec<AccountId>, ErrorReport> {
+        Ok(vec![
+            maker,
+            self.account_id_from_pubkey(self.dex_pubkey())?,
+            self.account_id_from_pubkey(self.burn_pubkey())?,
+        ])
+    }
+
+    fn decode_swap_v2_msgs(&self, tx: &CosmosTransaction) -> Result<SwapV2TxMsgs, String> {
+        let htlc_type = HtlcType::from_str(&self.account_prefix).map_err(|e| e.to_string())?;
+        let tx = cosmrs::Tx::from_bytes(&tx.tx_hex()).map_err(|e| e.to_string())?;
+
+        let mut msgs = SwapV2TxMsgs {
+            created: Vec::new(),
+            claim_secrets: Vec::new(),
+            memo: tx.body.memo,
+        };
+        for msg in tx.body.messages {
+            if msg.type_url == htlc_type.create_htlc_type_url() {
+                let proto = CreateHtlcProto::decode(htlc_type, msg.value.as_slice()).map_err(|e| e.to_string())?;
+                msgs.created
+                    .push(CreateHtlcMsg::try_from(proto).map_err(|e| e.to_string())?);
+            } else if msg.type_url == htlc_type.claim_htlc_type_url() {
+                let proto = ClaimHtlcProto::decode(htlc_type, msg.value.as_slice()).map_err(|e| e.to_string())?;
+                let claim = ClaimHtlcMsg::try_from(proto).map_err(|e| e.to_string())?;
+                msgs.claim_secrets.push(claim.secret().to_owned());
+            }
+        }
+        Ok(msgs)
+    }
+
+    fn htlc_id(&self, msg: &CreateHtlcMsg) -> Result<String, FromHexError> {
+        let hash_lock = hex::decode(msg.hash_lock())?;
+        Ok(self.calculate_htlc_id(msg.sender(), msg.to(), msg.amount(), &hash_lock))
+    }
+}
+
+#[cfg_attr(test, mockable)]
+impl TendermintCoin {
+    /// Returns the transaction that claimed the HTLC, if any.
+    async fn find_htlc_claim_tx(&self, htlc_id: &str) -> MmResult<Option<CosmosTransaction>, TendermintCoinRpcError> {
+        let request = TxSearchRequest {
+            query: format!("claim_htlc.id='{}'", htlc_id),
+            order_by: TendermintResultOrder::Ascending.into(),
+            page: 1,
+            per_page: 1,
+            prove: false,
+        };
+        let response = self
+            .rpc_client()
+            .await?
+            .perform(request)
+            .await
+            .map_to_mm(TendermintCoinRpcError::from)?;
+
+        match response.txs.first() {
+            Some(raw_tx) => {
+                let tx = cosmrs::Tx::from_bytes(&raw_tx.tx)?;
+                Ok(Some(CosmosTransaction {
+                    data: TxRaw {
+                        body_bytes: tx.body.into_bytes()?,
+                        auth_info_bytes: tx.auth_info.into_bytes()?,
+                        signatures: tx.signatures,
+                    },
+                }))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Signs and broadcasts the given messages as a single transaction.
+    async fn broadcast_swap_v2_msgs(
+        &self,
+        msgs: Vec<Any>,
+        memo: &str,
+        wait_until: u64,
+    ) -> Result<CosmosTransaction, TransactionErr> {
+        let current_block = try_tx_s!(self.current_block().compat().await);
+        let timeout_height = current_block + TIMEOUT_HEIGHT_DELTA;
+        let fee = try_tx_s!(
+            self.calculate_fee_for_msgs(msgs.clone(), timeout_height, memo, None)
+                .await
+        );
+        let timeout = wait_until.saturating_sub(now_sec()).max(MIN_BROADCAST_TIMEOUT);
+
+        let (_tx_id, tx_raw) = self
+            .common_send_raw_msgs_bytes(msgs, fee, timeout_height, memo, Duration::from_secs(timeout))
+            .await?;
+
+        Ok(CosmosTransaction { data: tx_raw.into() })
+    }
+}
+
+impl TendermintCoin {
+    /// Claims all the HTLCs created by `htlc_tx` with the given secret.
+    async fn claim_htlcs(
+        &self,
+        htlc_tx: &CosmosTransaction,
+        secret: &[u8],
+        wait_until: u64,
+    ) -> Result<CosmosTransaction, TransactionErr> {
+        let created = try_tx_s!(self.decode_swap_v2_msgs(htlc_tx)).created;
+        if created.is_empty() {
+            return Err(TransactionErr::Plain(ERRL!("Tx doesn't create any HTLC")));
+        }
+
+        let mut msgs = Vec::with_capacity(created.len());
+        for msg in created.iter() {
+            let htlc_id = try_tx_s!(self.htlc_id(msg));
+            msgs.push(try_tx_s!(self.gen_claim_htlc_tx(htlc_id, secret)).msg_payload);
+        }
+        self.broadcast_swap_v2_msgs(msgs, TX_DEFAULT_MEMO, wait_until).await
+    }
+
+    /// Waits until the chain refunds all the HTLCs created by the given transaction.
+    /// Expired HTLCs are refunded without a transaction, so an empty one is returned, like in the legacy swaps.
+    async fn wait_for_htlcs_refund(&self, htlc_tx: &[u8]) -> Result<CosmosTransaction, TransactionErr> {
+        let htlc_tx = CosmosTransaction {
+            data: try_tx_s!(TxRaw::decode(htlc_tx)),
+        };
+        let created = try_tx_s!(self.decode_swap_v2_msgs(&htlc_tx)).created;
+        if created.is_empty() {
+            return Err(TransactionErr::Plain(ERRL!("Tx doesn't create any HTLC")));
+        }
+
+        for msg in created.iter() {
+            let htlc_id = try_tx_s!(self.htlc_id(msg));
+            loop {
+                match self.query_htlc(htlc_id.clone()).await.map(|htlc| htlc.htlc_state()) {
+                    Ok(Some(HTLC_STATE_REFUNDED)) => break,
+                    Ok(Some(HTLC_STATE_COMPLETED)) => {
+                        return Err(TransactionErr::Plain(ERRL!("HTLC {} has been claimed", htlc_id)));
+                    },
+                    Ok(state) => debug!("HTLC {} is not refunded yet, state: {:?}", htlc_id, state),
+                    Err(e) => warn!("Error querying HTLC {}: {}", htlc_id, e),
+                }
+                Timer::sleep(HTLC_CHECK_INTERVAL).await;
+            }
+        }
+
+        Ok(CosmosTransaction { data: TxRaw::default() })
+    }
+
+    /// Checks that the transaction creates exactly the expected HTLCs and returns them.
+    fn validate_created_htlcs(
+        &self,
+        tx: &CosmosTransaction,
+        expected: &[ExpectedHtlc],
+    ) -> MmResult<Vec<CreateHtlcMsg>, HtlcTxValidationError> {
+        let created = self
+            .decode_swap_v2_msgs(tx)
+            .map_to_mm(HtlcTxValidationError::WrongHtlcs)?
+            .created;
+        if created.len() != expected.len() {
+            return MmError::err(HtlcTxValidationError::WrongHtlcs(format!(
+                "Tx creates {} HTLCs, expected {}",
+                created.len(),
+                expected.len()
+            )));
+        }
+
+        for (msg, expected) in created.iter().zip(expected) {
+            if msg.sender() != &expected.sender || msg.to() != &expected.to {
+                return MmError::err(HtlcTxValidationError::WrongHtlcs(format!(
+                    "HTLC is sent from {} to {}, expected from {} to {}",
+                    msg.sender(),
+                    msg.to(),
+                    expected.sender,
+                    expected.to
+                )));
+            }
+            if msg.amount() != expected.amount.as_slice() {
+                return MmError::err(HtlcTxValidationError::InvalidAmount(format!(
+                    "HTLC amount {:?}, expected {:?}",
+                    msg.amount(),
+                    expected.amount
+                )));
+            }
+            if let Some(hash_lock) = &expected.hash_lock {
+                if !msg.hash_lock().eq_ignore_ascii_case(&hex::encode(hash_lock)) {
+                    return MmError::err(HtlcTxValidationError::WrongHtlcs(format!(
+                        "HTLC hash lock {}, expected {}",
+                        msg.hash_lock(),
+                        hex::encode(hash_lock)
+                    )));
+                }
+            }
+            if msg.time_lock() < expected.min_time_lock {
+                return MmError::err(HtlcTxValidationError::WrongHtlcs(format!(
+                    "HTLC time lock {} is lower than {}",
+                    msg.time_lock(),
+                    expected.min_time_lock
+                )));
+            }
+        }
+
+        Ok(created)
+    }
+
+    /// Checks that the transaction is on-chain and the HTLCs it creates are still open.
+    async fn validate_htlcs_on_chain(
+        &self,
+        tx: &CosmosTransaction,
+        created: &[CreateHtlcMsg],
+    ) -> MmResult<(), HtlcTxValidationError> {
+        let tx_bytes = tx.tx_hex();
+        let hash = hex::encode_upper(sha256(&tx_bytes).as_slice());
+        let tx_from_rpc = self
+            .request_tx(hash)
+            .await
+            .map_to_mm(|e| HtlcTxValidationError::Rpc(e.to_string()))?
+            .encode_to_vec();
+        if tx_bytes != tx_from_rpc {
+            return MmError::err(HtlcTxValidationError::TxMismatch {
+                from_rpc: tx_from_rpc,
+                actual: tx_bytes,
+            });
+        }
+
+        for msg in created {
+            let htlc_id = self
+                .htlc_id(msg)
+                .map_to_mm(|e| HtlcTxValidationError::WrongHtlcs(e.to_string()))?;
+            let htlc_state = self
+                .query_htlc(htlc_id.clone())
+                .await
+                .map_to_mm(|e| HtlcTxValidationError::Rpc(e.to_string()))?
+                .htlc_state();
+            if htlc_state != Some(HTLC_STATE_OPEN) {
+                return MmError::err(HtlcTxValidationError::UnexpectedHtlcState(format!(
+                    "HTLC {} state is {:?}",
+                    htlc_id, htlc_state
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) async fn send_taker_funding_for_denom(
+        &self,
+        args: SendTakerFundingArgs<'_>,
+        denom: Denom,
+        decimals: u8,
+    ) -> Result<CosmosTransaction, TransactionErr> {
+        let parts = try_tx_s!(taker_volume_parts(
+            args.dex_fee,
+            &args.premium_amount,
+            &args.trading_amount,
+            decimals
+        ));
+        let time_lock = self.htlc_time_lock(args.funding_time_lock);
+
+        let mut msgs = Vec::with_capacity(parts.len());
+        for (index, amount) in parts.into_iter().enumerate() {
+            let secret = try_tx_s!(self.funding_secret(args.swap_unique_data, index));
+            let htlc = try_tx_s!(self.gen_create_htlc_tx(
+                denom.clone(),
+                &self.account_id,
+                amount.into(),
+                sha256(&secret).as_slice(),
+                time_lock
+            ));
+            msgs.push(htlc.msg_payload);
+        }
+        self.broadcast_swap_v2_msgs(msgs, TX_DEFAULT_MEMO, args.funding_time_lock)
+            .await
+    }
+
+    pub(super) async fn validate_taker_funding_for_denom<Coin>(
+        &self,
+        args: ValidateTakerFundingArgs<'_, Coin>,
+        denom: Denom,
+        decimals: u8,
+    ) -> ValidateSwapV2TxResult
+    where
+        Coin: ParseCoinAssocTypes<Tx = CosmosTransaction, Pubkey = Public> + ?Sized,
+    {
+        let taker = self
+            .account_id_from_pubkey(args.taker_pub)
+            .map_to_mm(|e| ValidateSwapV2TxError::InvalidData(e.to_string()))?;
+        let min_time_lock = self.htlc_time_lock(args.funding_time_lock);
+        let expected: Vec<_> = taker_volume_parts(args.dex_fee, &args.premium_amount, &args.trading_amount, decimals)?
+            .into_iter()
+            .map(|amount| ExpectedHtlc {
+                sender: taker.clone(),
+                to: taker.clone(),
+                amount: single_coin(&denom, amount),
+                hash_lock: None,
+                min_time_lock,
+            })
+            .collect();
+
+        let created = self.validate_created_htlcs(args.funding_tx, &expected)?;
+        Ok(self.validate_htlcs_on_chain(args.funding_tx, &created).await?)
+    }
+
+    pub(super) async fn refund_taker_funding_secret_v2(
+        &self,
+        funding_tx: &CosmosTransaction,
+        taker_secret: &[u8; 32],
+        funding_time_lock: u64,
+        swap_unique_data: &[u8],
+    ) -> Result<CosmosTransaction, TransactionErr> {
+        let created = try_tx_s!(self.decode_swap_v2_msgs(funding_tx)).created;
+
+        let mut msgs = Vec::with_capacity(created.len());
+        for (index, msg) in created.iter().enumerate() {
+            let secret = try_tx_s!(self.funding_secret(swap_unique_data, index));
+            let htlc_id = try_tx_s!(self.htlc_id(msg));
+            msgs.push(try_tx_s!(self.gen_claim_htlc_tx(htlc_id, &secret)).msg_payload);
+        }
+        self.broadcast_swap_v2_msgs(msgs, &hex::encode(taker_secret), funding_time_lock)
+            .await
+    }
+
+    pub(super) async fn search_for_taker_funding_spend_v2<Coin>(
+        &self,
+        funding_tx: &CosmosTransaction,
+        taker_secret_hash: &[u8],
+    ) -> Result<Option<FundingTxSpend<Coin>>, SearchForFundingSpendErr>
+    where
+        Coin: ParseCoinAssocTypes<Tx = CosmosTransaction> + ?Sized,
+    {
+        let created = self
+            .decode_swap_v2_msgs(funding_tx)
+            .map_err(SearchForFundingSpendErr::InvalidInputTx)?
+            .created;
+        let first_htlc = created
+            .first()
+            .ok_or_else(|| SearchForFundingSpendErr::InvalidInputTx("Funding tx doesn't create any HTLC".into()))?;
+        let htlc_id = self
+            .htlc_id(first_htlc)
+            .map_err(|e| SearchForFundingSpendErr::InvalidInputTx(e.to_string()))?;
+
+        let htlc_state = self
+            .query_htlc(htlc_id.clone())
+            .await
+            .map_err(|e| SearchForFundingSpendErr::Rpc(e.to_string()))?
+            .htlc_state();
+        match htlc_state {
+            None | Some(HTLC_STATE_OPEN) => Ok(None),
+            Some(HTLC_STATE_REFUNDED) => Ok(Some(FundingTxSpend::RefundedTimelock(CosmosTransaction {
+                data: TxRaw::default(),
+            }))),
+            Some(HTLC_STATE_COMPLETED) => {
+                // The claim tx might not be indexed yet, so return an RPC error to retry.
+                let spend_tx = self
+                    .find_htlc_claim_tx(&htlc_id)
+                    .await
+                    .map_err(|e| SearchForFundingSpendErr::Rpc(e.to_string()))?
+                    .ok_or_else(|| SearchForFundingSpendErr::Rpc(format!("Claim tx of HTLC {} not found", htlc_id)))?;
+                let msgs = self
+                    .decode_swap_v2_msgs(&spend_tx)
+                    .map_err(SearchForFundingSpendErr::FailedToProcessSpendTx)?;
+                if !msgs.created.is_empty() {
+                    return Ok(Some(FundingTxSpend::TransferredToTakerPayment(spend_tx)));
+                }
+
+                let secret = hex::decode(&msgs.memo)
+                    .ok()
+                    .and_then(|secret| <[u8; 32]>::try_from(secret.as_slice()).ok())
+                    .filter(|secret| sha256(secret).as_slice() == taker_secret_hash)
+                    .ok_or_else(|| {
+                        SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
+                            "Funding spend {} neither creates the taker payment nor reveals the taker secret",
+                            hex::encode(spend_tx.tx_hash_as_bytes().0)
+                        ))
+                    })?;
+                Ok(Some(FundingTxSpend::RefundedSecret { tx: spend_tx, secret }))
+            },
+            Some(unexpected_state) => Err(SearchForFundingSpendErr::Internal(format!(
+                "Unexpected HTLC {} state {}",
+                htlc_id, unexpected_state
+            ))),
+        }
+    }
+
+    /// Claims the funding HTLCs and creates the taker payment HTLCs paying the same amounts to the maker,
+    /// the dex fee address and the burn address.
+    pub(super) async fn send_combined_taker_payment<Coin>(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Coin>,
+        swap_unique_data: &[u8],
+    ) -> Result<CosmosTransaction, TransactionErr>
+    where
+        Coin: ParseCoinAssocTypes<Tx = CosmosTransaction, Pubkey = Public> + ?Sized,
+    {
+        let created = try_tx_s!(self.decode_swap_v2_msgs(args.funding_tx)).created;
+        let maker = try_tx_s!(self.account_id_from_pubkey(args.maker_pub));
+        let recipients = try_tx_s!(self.taker_payment_recipients(maker));
+        if created.is_empty() || created.len() > recipients.len() {
+            return Err(TransactionErr::Plain(ERRL!(
+                "Funding tx creates {} HTLCs, expected 1 to {}",
+                created.len(),
+                recipients.len()
+            )));
+        }
+        let time_lock = self.htlc_time_lock(args.taker_payment_time_lock);
+
+        let mut claims = Vec::with_capacity(created.len());
+        let mut payments = Vec::with_capacity(created.len());
+        for (index, (funding, to)) in created.iter().zip(recipients).enumerate() {
+            let secret = try_tx_s!(self.funding_secret(swap_unique_data, index));
+            let funding_id = try_tx_s!(self.htlc_id(funding));
+            claims.push(try_tx_s!(self.gen_claim_htlc_tx(funding_id, &secret)).msg_payload);
+
+            let coin = try_tx_s!(funding.amount().first().ok_or("Funding HTLC has no amount"));
+            let payment = try_tx_s!(self.gen_create_htlc_tx(
+                coin.denom.clone(),
+                &to,
+                coin.amount,
+                args.maker_secret_hash,
+                time_lock
+            ));
+            payments.push(payment.msg_payload);
+        }
+
+        claims.extend(payments);
+        self.broadcast_swap_v2_msgs(claims, TX_DEFAULT_MEMO, args.taker_payment_time_lock)
+            .await
+    }
+
+    /// Validates the taker payment and claims all of its HTLCs revealing the maker's secret.
+    pub(super) async fn spend_combined_taker_payment<Coin>(
+        &self,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Coin>,
+        secret: &[u8],
+        denom: Denom,
+        decimals: u8,
+    ) -> Result<CosmosTransaction, TransactionErr>
+    where
+        Coin: ParseCoinAssocTypes<Tx = CosmosTransaction, Pubkey = Public> + ?Sized,
+    {
+        let taker = try_tx_s!(self.account_id_from_pubkey(gen_args.taker_pub));
+        let recipients = try_tx_s!(self.taker_payment_recipients(self.account_id.clone()));
+        let parts = try_tx_s!(taker_volume_parts(
+            gen_args.dex_fee,
+            &gen_args.premium_amount,
+            &gen_args.trading_amount,
+            decimals
+        ));
+        let expected: Vec<_> = parts
+            .into_iter()
+            .zip(recipients)
+            .map(|(amount, to)| ExpectedHtlc {
+                sender: taker.clone(),
+                to,
+                amount: single_coin(&denom, amount),
+                hash_lock: Some(gen_args.maker_secret_hash.to_vec()),
+                // The maker must have enough blocks left to get its claim mined before the taker's refund.
+                min_time_lock: self.htlc_time_lock(gen_args.time_lock),
+            })
+            .collect();
+
+        let created = try_tx_s!(self.validate_created_htlcs(gen_args.taker_tx, &expected));
+        try_tx_s!(self.validate_htlcs_on_chain(gen_args.taker_tx, &created).await);
+        self.claim_htlcs(gen_args.taker_tx, secret, gen_args.time_lock).await
+    }
+
+    pub(super) async fn find_taker_payment_spend_v2(
+        &self,
+        taker_payment: &CosmosTransaction,
+        wait_until: u64,
+    ) -> MmResult<CosmosTransaction, FindPaymentSpendError> {
+        let created = self
+            .decode_swap_v2_msgs(taker_payment)
+            .map_to_mm(FindPaymentSpendError::InvalidInputTx)?
+            .created;
+        let first_htlc = created
+            .first()
+            .or_mm_err(|| FindPaymentSpendError::InvalidInputTx("Taker payment doesn't create any HTLC".into()))?;
+        let htlc_id = self
+            .htlc_id(first_htlc)
+            .map_to_mm(|e| FindPaymentSpendError::InvalidInputTx(e.to_string()))?;
+
+        loop {
+            match self.find_htlc_claim_tx(&htlc_id).await {
+                Ok(Some(spend_tx)) => return Ok(spend_tx),
+                Ok(None) => debug!("Taker payment HTLC {} is not claimed yet", htlc_id),
+                Err(e) => warn!("Error searching for claim of HTLC {}: {}", htlc_id, e),
+            }
+
+            let now = now_sec();
+            if now > wait_until {
+                return MmError::err(FindPaymentSpendError::Timeout { wait_until, now });
+            }
+            Timer::sleep(HTLC_CHECK_INTERVAL).await;
+        }
+    }
+
+    pub(super) fn extract_secret_v2_from_claim(
+        &self,
+        secret_hash: &[u8],
+        spend_tx: &CosmosTransaction,
+    ) -> Result<[u8; 32], String> {
+        let msgs = try_s!(self.decode_swap_v2_msgs(spend_tx));
+        msgs.claim_secrets
+            .iter()
+            .filter_map(|secret| hex::decode(secret).ok())
+            .filter_map(|secret| <[u8; 32]>::try_from(secret.as_slice()).ok())
+            .find(|secret| sha256(secret).as_slice() == secret_hash)
+            .ok_or_else(|| ERRL!("No secret matching {} found in tx", hex::encode(secret_hash)))
+    }
+
+    pub(super) async fn send_maker_payment_v2_for_denom<Coin>(
+        &self,
+        args: SendMakerPaymentArgs<'_, Coin>,
+        denom: Denom,
+        decimals: u8,
+    ) -> Result<CosmosTransaction, TransactionErr>
+    where
+        Coin: ParseCoinAssocTypes<Pubkey = Public> + ?Sized,
+    {
+        let to = try_tx_s!(self.account_id_from_pubkey(args.taker_pub));
+        let amount = try_tx_s!(sat_from_big_decimal(&args.amount, decimals));
+        let htlc = try_tx_s!(self.gen_create_htlc_tx(
+            denom,
+            &to,
+            amount.into(),
+            args.maker_secret_hash,
+            self.htlc_time_lock(args.time_lock)
+        ));
+        self.broadcast_swap_v2_msgs(vec![htlc.msg_payload], TX_DEFAULT_MEMO, args.time_lock)
+            .await
+    }
+
+    pub(super) async fn validate_maker_payment_v2_for_denom<Coin>(
+        &self,
+        args: ValidateMakerPaymentArgs<'_, Coin>,
+        denom: Denom,
+        decimals: u8,
+    ) -> ValidatePaymentResult<()>
+    where
+        Coin: ParseCoinAssocTypes<Tx = CosmosTransaction, Pubkey = Public> + ?Sized,
+    {
+        let maker = self
+            .account_id_from_pubkey(args.maker_pub)
+            .map_to_mm(|e| ValidatePaymentError::InvalidParameter(e.to_string()))?;
+        let expected = ExpectedHtlc {
+            sender: maker,
+            to: self.account_id.clone(),
+            amount: single_coin(&denom, sat_from_big_decimal(&args.amount, decimals)?),
+            hash_lock: Some(args.maker_secret_hash.to_vec()),
+            min_time_lock: self.htlc_time_lock(args.time_lock),
+        };
+
+        let created = self.validate_created_htlcs(args.maker_payment_tx, &[expected])?;
+        Ok(self.validate_htlcs_on_chain(args.maker_payment_tx, &created).await?)
+    }
+}
+
+impl ToBytes for CosmosTransaction {
+    fn to_bytes(&self) -> Vec<u8> { self.tx_hex() }
+}
+
+impl AddrToString for AccountId {
+    fn addr_to_string(&self) -> String { self.to_string() }
+}
+
+#[async_trait]
+impl ParseCoinAssocTypes for TendermintCoin {
+    type Address = AccountId;
+    type AddressParseError = MmError<TendermintAssocTypesError>;
+    type Pubkey = Public;
+    type PubkeyParseError = MmError<TendermintAssocTypesError>;
+    type Tx = CosmosTransaction;
+    type TxParseError = MmError<TendermintAssocTypesError>;
+    type Preimage = CosmosTransaction;
+    type PreimageParseError = MmError<TendermintAssocTypesError>;
+    type Sig = Signature;
+    type SigParseError = MmError<TendermintAssocTypesError>;
+
+    async fn my_addr(&self) -> Self::Address { self.account_id.clone() }
+
+    fn parse_address(&self, address: &str) -> Result<Self::Address, Self::AddressParseError> {
+        let account_id =
+            AccountId::from_str(address).map_to_mm(|e| TendermintAssocTypesError::InvalidAddress(e.to_string()))?;
+        if account_id.prefix() != self.account_prefix {
+            return MmError::err(TendermintAssocTypesError::InvalidAddress(format!(
+                "Expected {} address prefix, got {}",
+                self.account_prefix,
+                account_id.prefix()
+            )));
+        }
+        Ok(account_id)
+    }
+
+    fn parse_pubkey(&self, pubkey: &[u8]) -> Result<Self::Pubkey, Self::PubkeyParseError> {
+        PublicKey::from_raw_secp256k1(pubkey)
+            .or_mm_err(|| TendermintAssocTypesError::InvalidPubkey(hex::encode(pubkey)))?;
+        Public::from_slice(pubkey).map_to_mm(|e| TendermintAssocTypesError::InvalidPubkey(e.to_string()))
+    }
+
+    fn parse_tx(&self, tx: &[u8]) -> Result<Self::Tx, Self::TxParseError> {
+        let data = TxRaw::decode(tx).map_to_mm(|e| TendermintAssocTypesError::TxParseError(e.to_string()))?;
+        Ok(CosmosTransaction { data })
+    }
+
+    fn parse_preimage(&self, tx: &[u8]) -> Result<Self::Preimage, Self::PreimageParseError> { self.parse_tx(tx) }
+
+    fn parse_signature(&self, sig: &[u8]) -> Result<Self::Sig, Self::SigParseError> { Ok(sig.to_vec().into()) }
+}
+
+#[async_trait]
+impl ParseCoinAssocTypes for TendermintToken {
+    type Address = AccountId;
+    type AddressParseError = MmError<TendermintAssocTypesError>;
+    type Pubkey = Public;
+    type PubkeyParseError = MmError<TendermintAssocTypesError>;
+    type Tx = CosmosTransaction;
+    type TxParseError = MmError<TendermintAssocTypesError>;
+    type Preimage = CosmosTransaction;
+    type PreimageParseError = MmError<TendermintAssocTypesError>;
+    type Sig = Signature;
+    type SigParseError = MmError<TendermintAssocTypesError>;
+
+    async fn my_addr(&self) -> Self::Address { self.platform_coin.my_addr().await }
+
+    fn parse_address(&self, address: &str) -> Result<Self::Address, Self::AddressParseError> {
+        self.platform_coin.parse_address(address)
+    }
+
+    fn parse_pubkey(&self, pubkey: &[u8]) -> Result<Self::Pubkey, Self::PubkeyParseError> {
+        self.platform_coin.parse_pubkey(pubkey)
+    }
+
+    fn parse_tx(&self, tx: &[u8]) -> Result<Self::Tx, Self::TxParseError> { self.platform_coin.parse_tx(tx) }
+
+    fn parse_preimage(&self, tx: &[u8]) -> Result<Self::Preimage, Self::PreimageParseError> {
+        self.platform_coin.parse_preimage(tx)
+    }
+
+    fn parse_signature(&self, sig: &[u8]) -> Result<Self::Sig, Self::SigParseError> {
+        self.platform_coin.parse_signature(sig)
+    }
+}
+
+#[async_trait]
+impl CommonSwapOpsV2 for TendermintCoin {
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2(&self, swap_unique_data: &[u8]) -> Self::Pubkey {
+        Public::from_slice(&self.derive_htlc_pubkey(swap_unique_data)).expect("valid pubkey")
+    }
+
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2_bytes(&self, swap_unique_data: &[u8]) -> Vec<u8> {
+        self.derive_htlc_pubkey(swap_unique_data).to_vec()
+    }
+
+    #[inline(always)]
+    fn taker_pubkey_bytes(&self) -> Option<Vec<u8>> { Some(self.derive_htlc_pubkey_v2_bytes(&[])) }
+}
+
+#[async_trait]
+impl CommonSwapOpsV2 for TendermintToken {
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2(&self, swap_unique_data: &[u8]) -> Self::Pubkey {
+        self.platform_coin.derive_htlc_pubkey_v2(swap_unique_data)
+    }
+
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2_bytes(&self, swap_unique_data: &[u8]) -> Vec<u8> {
+        self.platform_coin.derive_htlc_pubkey_v2_bytes(swap_unique_data)
+    }
+
+    #[inline(always)]
+    fn taker_pubkey_bytes(&self) -> Option<Vec<u8>> { self.platform_coin.taker_pubkey_bytes() }
+}
+
+#[async_trait]
+impl MakerCoinSwapOpsV2 for TendermintCoin {
+    async fn send_maker_payment_v2(&self, args: SendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        self.send_maker_payment_v2_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn validate_maker_payment_v2(&self, args: ValidateMakerPaymentArgs<'_, Self>) -> ValidatePaymentResult<()> {
+        self.validate_maker_payment_v2_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn refund_maker_payment_v2_timelock(
+        &self,
+        args: RefundMakerPaymentTimelockArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.wait_for_htlcs_refund(args.payment_tx).await
+    }
+
+    /// HTLCs can't be refunded before expiration, so this waits for the automatic refund as well.
+    async fn refund_maker_payment_v2_secret(
+        &self,
+        args: RefundMakerPaymentSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.wait_for_htlcs_refund(&args.maker_payment_tx.tx_hex()).await
+    }
+
+    async fn spend_maker_payment_v2(&self, args: SpendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        self.claim_htlcs(args.maker_payment_tx, &args.maker_secret, args.time_lock)
+            .await
+    }
+}
+
+#[async_trait]
+impl TakerCoinSwapOpsV2 for TendermintCoin {
+    async fn send_taker_funding(&self, args: SendTakerFundingArgs<'_>) -> Result<Self::Tx, TransactionErr> {
+        self.send_taker_funding_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn validate_taker_funding(&self, args: ValidateTakerFundingArgs<'_, Self>) -> ValidateSwapV2TxResult {
+        self.validate_taker_funding_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn refund_taker_funding_timelock(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.wait_for_htlcs_refund(args.payment_tx).await
+    }
+
+    async fn refund_taker_funding_secret(
+        &self,
+        args: RefundFundingSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.refund_taker_funding_secret_v2(
+            args.funding_tx,
+            args.taker_secret,
+            args.funding_time_lock,
+            args.swap_unique_data,
+        )
+        .await
+    }
+
+    async fn search_for_taker_funding_spend(
+        &self,
+        tx: &Self::Tx,
+        _from_block: u64,
+        secret_hash: &[u8],
+    ) -> Result<Option<FundingTxSpend<Self>>, SearchForFundingSpendErr> {
+        self.search_for_taker_funding_spend_v2(tx, secret_hash).await
+    }
+
+    /// The taker spends its funding HTLCs itself, so the preimage is the funding tx and no signature is needed.
+    async fn gen_taker_funding_spend_preimage(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        Ok(TxPreimageWithSig {
+            preimage: args.funding_tx.clone(),
+            signature: Vec::new().into(),
+        })
+    }
+
+    async fn validate_taker_funding_spend_preimage(
+        &self,
+        gen_args: &GenTakerFundingSpendArgs<'_, Self>,
+        preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerFundingSpendPreimageResult {
+        if &preimage.preimage != gen_args.funding_tx {
+            return MmError::err(ValidateTakerFundingSpendPreimageError::InvalidPreimage(
+                "Preimage doesn't match the funding tx".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn sign_and_send_taker_funding_spend(
+        &self,
+        _preimage: &TxPreimageWithSig<Self>,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.send_combined_taker_payment(args, swap_unique_data).await
+    }
+
+    async fn refund_combined_taker_payment(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.wait_for_htlcs_refund(args.payment_tx).await
+    }
+
+    fn skip_taker_payment_spend_preimage(&self) -> bool { true }
+
+    /// Tendermint skips taker_payment_spend_preimage, as the maker claims the HTLCs with its secret only
+    async fn gen_taker_payment_spend_preimage(
+        &self,
+        _args: &GenTakerPaymentSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        MmError::err(TxGenError::Other(
+            "Tendermint coin doesn't have taker_payment_spend_preimage. Report the Bug!".to_string(),
+        ))
+    }
+
+    /// Tendermint skips taker_payment_spend_preimage, as the maker claims the HTLCs with its secret only
+    async fn validate_taker_payment_spend_preimage(
+        &self,
+        _gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        _preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerPaymentSpendPreimageResult {
+        MmError::err(ValidateTakerPaymentSpendPreimageError::InvalidPreimage(
+            "Tendermint coin skips taker_payment_spend_preimage validation. Report the Bug!".to_string(),
+        ))
+    }
+
+    async fn sign_and_broadcast_taker_payment_spend(
+        &self,
+        _preimage: Option<&TxPreimageWithSig<Self>>,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        secret: &[u8],
+        _swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.spend_combined_taker_payment(gen_args, secret, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn find_taker_payment_spend_tx(
+        &self,
+        taker_payment: &Self::Tx,
+        _from_block: u64,
+        wait_until: u64,
+    ) -> MmResult<Self::Tx, FindPaymentSpendError> {
+        self.find_taker_payment_spend_v2(taker_payment, wait_until).await
+    }
+
+    async fn extract_secret_v2(&self, secret_hash: &[u8], spend_tx: &Self::Tx) -> Result<[u8; 32], String> {
+        self.extract_secret_v2_from_claim(secret_hash, spend_tx)
+    }
+}
+
+#[async_trait]
+impl MakerCoinSwapOpsV2 for TendermintToken {
+    async fn send_maker_payment_v2(&self, args: SendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .send_maker_payment_v2_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn validate_maker_payment_v2(&self, args: ValidateMakerPaymentArgs<'_, Self>) -> ValidatePaymentResult<()> {
+        self.platform_coin
+            .validate_maker_payment_v2_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn refund_maker_payment_v2_timelock(
+        &self,
+        args: RefundMakerPaymentTimelockArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin.wait_for_htlcs_refund(args.payment_tx).await
+    }
+
+    async fn refund_maker_payment_v2_secret(
+        &self,
+        args: RefundMakerPaymentSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .wait_for_htlcs_refund(&args.maker_payment_tx.tx_hex())
+            .await
+    }
+
+    async fn spend_maker_payment_v2(&self, args: SpendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .claim_htlcs(args.maker_payment_tx, &args.maker_secret, args.time_lock)
+            .await
+    }
+}
+
+#[async_trait]
+impl TakerCoinSwapOpsV2 for TendermintToken {
+    async fn send_taker_funding(&self, args: SendTakerFundingArgs<'_>) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .send_taker_funding_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn validate_taker_funding(&self, args: ValidateTakerFundingArgs<'_, Self>) -> ValidateSwapV2TxResult {
+        self.platform_coin
+            .validate_taker_funding_for_denom(args, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn refund_taker_funding_timelock(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin.wait_for_htlcs_refund(args.payment_tx).await
+    }
+
+    async fn refund_taker_funding_secret(
+        &self,
+        args: RefundFundingSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .refund_taker_funding_secret_v2(
+                args.funding_tx,
+                args.taker_secret,
+                args.funding_time_lock,
+                args.swap_unique_data,
+            )
+            .await
+    }
+
+    async fn search_for_taker_funding_spend(
+        &self,
+        tx: &Self::Tx,
+        _from_block: u64,
+        secret_hash: &[u8],
+    ) -> Result<Option<FundingTxSpend<Self>>, SearchForFundingSpendErr> {
+        self.platform_coin
+            .search_for_taker_funding_spend_v2(tx, secret_hash)
+            .await
+    }
+
+    async fn gen_taker_funding_spend_preimage(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        Ok(TxPreimageWithSig {
+            preimage: args.funding_tx.clone(),
+            signature: Vec::new().into(),
+        })
+    }
+
+    async fn validate_taker_funding_spend_preimage(
+        &self,
+        gen_args: &GenTakerFundingSpendArgs<'_, Self>,
+        preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerFundingSpendPreimageResult {
+        if &preimage.preimage != gen_args.funding_tx {
+            return MmError::err(ValidateTakerFundingSpendPreimageError::InvalidPreimage(
+                "Preimage doesn't match the funding tx".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn sign_and_send_taker_funding_spend(
+        &self,
+        _preimage: &TxPreimageWithSig<Self>,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .send_combined_taker_payment(args, swap_unique_data)
+            .await
+    }
+
+    async fn refund_combined_taker_payment(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin.wait_for_htlcs_refund(args.payment_tx).await
+    }
+
+    fn skip_taker_payment_spend_preimage(&self) -> bool { true }
+
+    async fn gen_taker_payment_spend_preimage(
+        &self,
+        _args: &GenTakerPaymentSpendArgs<'_, Self>,
+        _swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        MmError::err(TxGenError::Other(
+            "Tendermint token doesn't have taker_payment_spend_preimage. Report the Bug!".to_string(),
+        ))
+    }
+
+    async fn validate_taker_payment_spend_preimage(
+        &self,
+        _gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        _preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerPaymentSpendPreimageResult {
+        MmError::err(ValidateTakerPaymentSpendPreimageError::InvalidPreimage(
+            "Tendermint token skips taker_payment_spend_preimage validation. Report the Bug!".to_string(),
+        ))
+    }
+
+    async fn sign_and_broadcast_taker_payment_spend(
+        &self,
+        _preimage: Option<&TxPreimageWithSig<Self>>,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        secret: &[u8],
+        _swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.platform_coin
+            .spend_combined_taker_payment(gen_args, secret, self.denom.clone(), self.decimals)
+            .await
+    }
+
+    async fn find_taker_payment_spend_tx(
+        &self,
+        taker_payment: &Self::Tx,
+        _from_block: u64,
+        wait_until: u64,
+    ) -> MmResult<Self::Tx, FindPaymentSpendError> {
+        self.platform_coin
+            .find_taker_payment_spend_v2(taker_payment, wait_until)
+            .await
+    }
+
+    async fn extract_secret_v2(&self, secret_hash: &[u8], spend_tx: &Self::Tx) -> Result<[u8; 32], String> {
+        self.platform_coin.extract_secret_v2_from_claim(secret_hash, spend_tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tendermint::htlc::QueryHtlcResponse;
+    use crate::tendermint::{TendermintActivationPolicy, TendermintConf, TendermintKeyPair, TendermintProtocolInfo};
+    use crate::{DexFeeBurnDestination, PrivKeyPolicy};
+    use common::block_on;
+    use cosmrs::proto::cosmos::auth::v1beta1::BaseAccount;
+    use cosmrs::proto::cosmos::tx::v1beta1::Tx;
+    use crypto::privkey::key_pair_from_seed;
+    use mm2_core::mm_ctx::{MmArc, MmCtxBuilder};
+    use mm2_number::MmNumber;
+    use mocktopus::mocking::{MockResult, Mockable};
+    use serde_json as json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Broadcasted txs and the created HTLCs by id, with their hash locks and states.
+    #[derive(Default)]
+    struct MockChain {
+        txs: Vec<CosmosTransaction>,
+        htlcs: HashMap<String, (String, i32)>,
+    }
+
+    impl MockChain {
+        fn hash_lock(secret: &str) -> String { hex::encode(sha256(&hex::decode(secret).unwrap()).as_slice()) }
+    }
+
+    fn iris_coin_for_test(ctx: &MmArc, seed: &str) -> TendermintCoin {
+        let conf = TendermintConf::try_from_json("IRIS", &json!({ "avg_blocktime": 5 })).unwrap();
+        let protocol_info: TendermintProtocolInfo = json::from_value(json!({
+            "decimals": 6,
+            "denom": "unyan",
+            "account_prefix": "iaa",
+            "chain_id": "nyancat-9",
+        }))
+        .unwrap();
+        let nodes = json::from_value(json!([{ "url": "http://127.0.0.1:26657" }])).unwrap();
+
+        let key_pair = key_pair_from_seed(seed).unwrap();
+        let tendermint_pair = TendermintKeyPair::new(key_pair.private().secret, *key_pair.public());
+        let activation_policy =
+            TendermintActivationPolicy::with_private_key_policy(PrivKeyPolicy::Iguana(tendermint_pair));
+
+        block_on(TendermintCoin::init(
+            ctx,
+            "IRIS".to_string(),
+            conf,
+            protocol_info,
+            nodes,
+            false,
+            activation_policy,
+            false,
+        ))
+        .unwrap()
+    }
+
+    /// Mocks the chain: broadcasted txs create open HTLCs and complete the open HTLCs locked by the revealed secrets.
+    fn mock_chain() {
+        let chain = Arc::new(Mutex::new(MockChain::default()));
+
+        let broadcast_chain = chain.clone();
+        TendermintCoin::broadcast_swap_v2_msgs.mock_safe(move |coin, msgs, memo, _| {
+            let priv_key = coin.activation_policy.activated_key_or_err().unwrap();
+            let tx_bytes = coin
+                .gen_simulated_tx(&BaseAccount::default(), priv_key, msgs, 0, memo)
+                .unwrap();
+            let tx = CosmosTransaction {
+                data: TxRaw::decode(tx_bytes.as_slice()).unwrap(),
+            };
+
+            let msgs = coin.decode_swap_v2_msgs(&tx).unwrap();
+            let mut chain = broadcast_chain.lock().unwrap();
+            for secret in msgs.claim_secrets.iter() {
+                let hash_lock = MockChain::hash_lock(secret);
+                for (htlc_hash_lock, state) in chain.htlcs.values_mut() {
+                    if *htlc_hash_lock == hash_lock && *state == HTLC_STATE_OPEN {
+                        *state = HTLC_STATE_COMPLETED;
+                    }
+                }
+            }
+            for msg in msgs.created.iter() {
+                let htlc = (msg.hash_lock().to_lowercase(), HTLC_STATE_OPEN);
+                chain.htlcs.insert(coin.htlc_id(msg).unwrap(), htlc);
+            }
+            chain.txs.push(tx.clone());
+            MockResult::Return(Box::pin(async move { Ok(tx) }))
+        });
+
+        let request_chain = chain.clone();
+        TendermintCoin::request_tx.mock_safe(move |_, hash| {
+            let tx = request_chain
+                .lock()
+                .unwrap()
+                .txs
+                .iter()
+                .find(|tx| hex::encode_upper(sha256(&tx.tx_hex()).as_slice()) == hash)
+                .map(|tx| Tx::decode(tx.tx_hex().as_slice()).unwrap());
+            MockResult::Return(Box::pin(async move {
+                tx.or_mm_err(|| TendermintCoinRpcError::InvalidResponse(format!("Tx {} not found", hash)))
+            }))
+        });
+
+        let query_chain = chain.clone();
+        TendermintCoin::query_htlc.mock_safe(move |_, id| {
+            let response = match query_chain.lock().unwrap().htlcs.get(&id) {
+                Some((_, state)) => QueryHtlcResponse::with_state(HtlcType::Iris, *state),
+                None => QueryHtlcResponse::decode(HtlcType::Iris, &[]).unwrap(),
+            };
+            MockResult::Return(Box::pin(async move { Ok(response) }))
+        });
+
+        TendermintCoin::find_htlc_claim_tx.mock_safe(move |coin, htlc_id| {
+            let chain = chain.lock().unwrap();
+            let claim_tx = chain
+                .htlcs
+                .get(htlc_id)
+                .filter(|(_, state)| *state == HTLC_STATE_COMPLETED)
+                .and_then(|(hash_lock, _)| {
+                    chain.txs.iter().find(|tx| {
+                        let claim_secrets = coin.decode_swap_v2_msgs(tx).unwrap().claim_secrets;
+                        claim_secrets
+                            .iter()
+                            .any(|secret| MockChain::hash_lock(secret) == *hash_lock)
+                    })
+                })
+                .cloned();
+            MockResult::Return(Box::pin(async move { Ok(claim_tx) }))
+        });
+    }
+
+    #[test]
+    fn test_taker_volume_parts() {
+        let premium = BigDecimal::from(0);
+        let trading = "1.5".parse::<BigDecimal>().unwrap();
+
+        let parts = taker_volume_parts(&DexFee::NoFee, &premium, &trading, 6).unwrap();
+        assert_eq!(parts, vec![1_500_000]);
+
+        let dex_fee = DexFee::Standard(MmNumber::from("0.01"));
+        let parts = taker_volume_parts(&dex_fee, &premium, &trading, 6).unwrap();
+        assert_eq!(parts, vec![1_500_000, 10_000]);
+
+        let dex_fee = DexFee::WithBurn {
+            fee_amount: MmNumber::from("0.0075"),
+            burn_amount: MmNumber::from("0.0025"),
+            burn_destination: DexFeeBurnDestination::PreBurnAccount,
+        };
+        let parts = taker_volume_parts(&dex_fee, &premium, &trading, 6).unwrap();
+        assert_eq!(parts, vec![1_500_000, 7_500, 2_500]);
+    }
+
+    #[test]
+    fn test_htlc_type_urls() {
+        assert_eq!(HtlcType::Iris.create_htlc_type_url(), "/irismod.htlc.MsgCreateHTLC");
+        assert_eq!(HtlcType::Iris.claim_htlc_type_url(), "/irismod.htlc.MsgClaimHTLC");
+        assert_eq!(HtlcType::Nucleus.create_htlc_type_url(), "/nucleus.htlc.MsgCreateHTLC");
+        assert_eq!(HtlcType::Nucleus.claim_htlc_type_url(), "/nucleus.htlc.MsgClaimHTLC");
+    }
+
+    #[test]
+    fn test_swap_v2_funding_payment_spend_and_refund() {
+        let ctx = MmCtxBuilder::default().into_mm_arc();
+        let maker = iris_coin_for_test(&ctx, "iris test seed");
+        let taker = iris_coin_for_test(&ctx, "iris test2 seed");
+        mock_chain();
+
+        let maker_pub = maker.derive_htlc_pubkey_v2(&[]);
+        let maker_pub_bytes = maker.derive_htlc_pubkey_v2_bytes(&[]);
+        let taker_pub = taker.derive_htlc_pubkey_v2(&[]);
+        let maker_address = block_on(maker.my_addr());
+        let taker_secret = [1; 32];
+        let taker_secret_hash = sha256(&taker_secret).take();
+        let maker_secret = [2; 32];
+        let maker_secret_hash = sha256(&maker_secret).take();
+        let dex_fee = DexFee::Standard(MmNumber::from("0.01"));
+        let trading_amount = BigDecimal::from(1);
+        let funding_time_lock = now_sec() + 3000;
+        let payment_time_lock = now_sec() + 6000;
+
+        let funding_args = |swap_unique_data: &'static [u8]| SendTakerFundingArgs {
+            funding_time_lock,
+            payment_time_lock,
+            taker_secret_hash: &taker_secret_hash,
+            maker_secret_hash: &maker_secret_hash,
+            maker_pub: &maker_pub_bytes,
+            dex_fee: &dex_fee,
+            premium_amount: 0.into(),
+            trading_amount: trading_amount.clone(),
+            swap_unique_data,
+        };
+
+        // Funding -> taker payment -> spend by the maker.
+        let swap_unique_data = b"spent swap";
+        let funding_tx = block_on(taker.send_taker_funding(funding_args(swap_unique_data))).unwrap();
+        let validate_args = ValidateTakerFundingArgs {
+            funding_tx: &funding_tx,
+            funding_time_lock,
+            payment_time_lock,
+            taker_secret_hash: &taker_secret_hash,
+            maker_secret_hash: &maker_secret_hash,
+            taker_pub: &taker_pub,
+            dex_fee: &dex_fee,
+            premium_amount: 0.into(),
+            trading_amount: trading_amount.clone(),
+            swap_unique_data,
+        };
+        block_on(maker.validate_taker_funding(validate_args)).unwrap();
+
+        let gen_args = GenTakerFundingSpendArgs {
+            funding_tx: &funding_tx,
+            maker_pub: &maker_pub,
+            taker_pub: &taker_pub,
+            funding_time_lock,
+            taker_secret_hash: &taker_secret_hash,
+            taker_payment_time_lock: payment_time_lock,
+            maker_secret_hash: &maker_secret_hash,
+        };
+        let preimage = block_on(taker.gen_taker_funding_spend_preimage(&gen_args, swap_unique_data)).unwrap();
+        block_on(maker.validate_taker_funding_spend_preimage(&gen_args, &preimage)).unwrap();
+        let taker_payment =
+            block_on(taker.sign_and_send_taker_funding_spend(&preimage, &gen_args, swap_unique_data)).unwrap();
+        match block_on(maker.search_for_taker_funding_spend(&funding_tx, 0, &taker_secret_hash)).unwrap() {
+            Some(FundingTxSpend::TransferredToTakerPayment(tx)) => assert_eq!(tx, taker_payment),
+            _ => panic!("Funding should be transferred to the taker payment"),
+        }
+
+        let mut spend_args = GenTakerPaymentSpendArgs {
+            taker_tx: &taker_payment,
+            time_lock: payment_time_lock + 3000,
+            maker_secret_hash: &maker_secret_hash,
+            maker_pub: &maker_pub,
+            maker_address: &maker_address,
+            taker_pub: &taker_pub,
+            dex_fee: &dex_fee,
+            premium_amount: 0.into(),
+            trading_amount: trading_amount.clone(),
+        };
+        // The taker payment HTLCs expire before the time lock the maker expects.
+        block_on(maker.sign_and_broadcast_taker_payment_spend(None, &spend_args, &maker_secret, &[])).unwrap_err();
+
+        spend_args.time_lock = payment_time_lock;
+        let spend_tx =
+            block_on(maker.sign_and_broadcast_taker_payment_spend(None, &spend_args, &maker_secret, &[])).unwrap();
+        let found_spend = block_on(taker.find_taker_payment_spend_tx(&taker_payment, 0, now_sec() + 10)).unwrap();
+        assert_eq!(found_spend, spend_tx);
+        let secret = block_on(taker.extract_secret_v2(&maker_secret_hash, &found_spend)).unwrap();
+        assert_eq!(secret, maker_secret);
+        // The HTLCs are not open anymore.
+        block_on(maker.sign_and_broadcast_taker_payment_spend(None, &spend_args, &maker_secret, &[])).unwrap_err();
+
+        // Funding -> refund revealing the taker secret.
+        let swap_unique_data = b"refunded swap";
+        let funding_tx = block_on(taker.send_taker_funding(funding_args(swap_unique_data))).unwrap();
+        let refund_args = RefundFundingSecretArgs {
+            funding_tx: &funding_tx,
+            funding_time_lock,
+            payment_time_lock,
+            maker_pubkey: &maker_pub,
+            taker_secret: &taker_secret,
+            taker_secret_hash: &taker_secret_hash,
+            maker_secret_hash: &maker_secret_hash,
+            dex_fee: &dex_fee,
+            premium_amount: 0.into(),
+            trading_amount: trading_amount.clone(),
+            swap_unique_data,
+            watcher_reward: false,
+        };
+        let refund_tx = block_on(taker.refund_taker_funding_secret(refund_args)).unwrap();
+        match block_on(maker.search_for_taker_funding_spend(&funding_tx, 0, &taker_secret_hash)).unwrap() {
+            Some(FundingTxSpend::RefundedSecret { tx, secret }) => {
+                assert_eq!(tx, refund_tx);
+                assert_eq!(secret, taker_secret);
+            },
+            _ => panic!("Funding should be refunded with the taker secret"),
+        }
+    }
+}
@@ -9,12 +9,14 @@ use crate::{big_decimal_from_sat_unsigned, utxo::sat_from_big_decimal, BalanceFu
             CheckIfMyPaymentSentArgs, CoinBalance, ConfirmPaymentInput, DexFee, FeeApproxStage, FoundSwapTxSpend,
             HistorySyncState, MarketCoinOps, MmCoin, MyAddressError, NegotiateSwapContractAddrErr,
             RawTransactionError, RawTransactionFut, RawTransactionRequest, RawTransactionResult, RefundPaymentArgs,
-            SearchForSwapTxSpendInput, SendPaymentArgs, SignRawTransactionRequest, SignatureResult, SpendPaymentArgs,
-            SwapOps, TradeFee, TradePreimageFut, TradePreimageResult, TradePreimageValue, TransactionDetails,
-            TransactionEnum, TransactionErr, TransactionResult, TransactionType, TxFeeDetails, TxMarshalingErr,
-            UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr,
-            ValidatePaymentInput, VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WeakSpawner, WithdrawError,
-            WithdrawFut, WithdrawRequest};
+            SearchForSwapTxSpendInput, SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SignRawTransactionRequest,
+            SignatureResult, SpendPaymentArgs, SwapOps, TradeFee, TradePreimageFut, TradePreimageResult,
+            TradePreimageValue, TransactionDetails, TransactionEnum, TransactionErr, TransactionFut,
+            TransactionResult, TransactionType, TxFeeDetails, TxMarshalingErr, UnexpectedDerivationMethod,
+            ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr, ValidatePaymentFut, ValidatePaymentInput,
+            ValidateWatcherSpendInput, VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps,
+            WatcherSearchForSwapTxSpendInput, WatcherValidatePaymentInput, WatcherValidateTakerFeeInput, WeakSpawner,
+            WithdrawError, WithdrawFut, WithdrawRequest};
 use async_trait::async_trait;
 use bitcrypto::sha256;
 use common::executor::abortable_queue::AbortableQueue;
@@ -258,10 +260,84 @@ impl SwapOps for TendermintToken {
     fn validate_other_pubkey(&self, raw_pubkey: &[u8]) -> MmResult<(), ValidateOtherPubKeyErr> {
         self.platform_coin.validate_other_pubkey(raw_pubkey)
     }
+
+    fn is_supported_by_watchers(&self) -> bool { self.platform_coin.is_supported_by_watchers() }
 }
 
 #[async_trait]
-impl WatcherOps for TendermintToken {}
+impl WatcherOps for TendermintToken {
+    fn send_maker_payment_spend_preimage(&self, input: SendMakerPaymentSpendPreimageInput) -> TransactionFut {
+        self.platform_coin.send_maker_payment_spend_preimage(input)
+    }
+
+    fn send_taker_payment_refund_preimage(&self, watcher_refunds_payment_args: RefundPaymentArgs) -> TransactionFut {
+        self.platform_coin
+            .send_taker_payment_refund_preimage(watcher_refunds_payment_args)
+    }
+
+    fn create_taker_payment_refund_preimage(
+        &self,
+        taker_payment_tx: &[u8],
+        _time_lock: u64,
+        _maker_pub: &[u8],
+        _secret_hash: &[u8],
+        _swap_contract_address: &Option<BytesJson>,
+        _swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        self.platform_coin.htlc_payment_as_watcher_preimage(taker_payment_tx)
+    }
+
+    fn create_maker_payment_spend_preimage(
+        &self,
+        maker_payment_tx: &[u8],
+        _time_lock: u64,
+        _maker_pub: &[u8],
+        _secret_hash: &[u8],
+        _swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        self.platform_coin.htlc_payment_as_watcher_preimage(maker_payment_tx)
+    }
+
+    fn watcher_validate_taker_fee(&self, input: WatcherValidateTakerFeeInput) -> ValidatePaymentFut<()> {
+        let token = self.clone();
+        let fut = async move {
+            token
+                .platform_coin
+                .watcher_validate_taker_fee_for_denom(input, token.denom.clone())
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
+        let token = self.clone();
+        let fut = async move {
+            token
+                .platform_coin
+                .watcher_validate_taker_payment_for_denom(input, token.denom.clone())
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
+        let token = self.clone();
+        let fut = async move {
+            token
+                .platform_coin
+                .taker_validates_payment_spend_or_refund_for_denom(input, token.denom.clone(), token.decimals)
+                .await
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    async fn watcher_search_for_swap_tx_spend(
+        &self,
+        input: WatcherSearchForSwapTxSpendInput<'_>,
+    ) -> Result<Option<FoundSwapTxSpend>, String> {
+        self.platform_coin.watcher_search_for_swap_tx_spend(input).await
+    }
+}
 
 #[async_trait]
 impl MarketCoinOps for TendermintToken {
@@ -375,6 +451,7 @@ impl MmCoin for TendermintToken {
         let platform = self.platform_coin.clone();
         let token = self.clone();
         let fut = async move {
+            req.ensure_no_utxo_specific_fields(token.ticker())?;
             let to_address =
                 AccountId::from_str(&req.to).map_to_mm(|e| WithdrawError::InvalidAddress(e.to_string()))?;
 
@@ -535,7 +612,9 @@ impl MmCoin for TendermintToken {
         self.platform_coin.get_raw_transaction(req)
     }
 
-    fn get_tx_hex_by_hash(&self, tx_hash: Vec<u8>) -> RawTransactionFut { unimplemented!() }
+    fn get_tx_hex_by_hash(&self, tx_hash: Vec<u8>) -> RawTransactionFut {
+        self.platform_coin.get_tx_hex_by_hash(tx_hash)
+    }
 
     fn decimals(&self) -> u8 { self.decimals }
 
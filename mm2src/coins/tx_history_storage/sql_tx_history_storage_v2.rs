@@ -22,6 +22,9 @@ fn tx_address_table(wallet_id: &WalletId) -> String { wallet_id.to_sql_table_nam
 /// Please note TX cache table name doesn't depend on [`WalletId::hd_wallet_rmd160`].
 fn tx_cache_table(wallet_id: &WalletId) -> String { format!("{}_tx_cache", wallet_id.ticker) }
 
+/// The only row of this table keeps the block height the history has been scanned up to.
+fn scanned_block_table(wallet_id: &WalletId) -> String { wallet_id.to_sql_table_name() + "_scanned_block" }
+
 fn create_tx_history_table_sql(wallet_id: &WalletId) -> Result<String, MmError<SqlError>> {
     let table_name = tx_history_table(wallet_id);
     validate_table_name(&table_name)?;
@@ -73,6 +76,21 @@ fn create_tx_cache_table_sql(wallet_id: &WalletId) -> Result<String, MmError<Sql
     Ok(sql)
 }
 
+fn create_scanned_block_table_sql(wallet_id: &WalletId) -> Result<String, MmError<SqlError>> {
+    let table_name = scanned_block_table(wallet_id);
+    validate_table_name(&table_name)?;
+
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS {} (
+            id INTEGER NOT NULL PRIMARY KEY CHECK (id = 0),
+            block_height INTEGER NOT NULL
+        );",
+        table_name
+    );
+
+    Ok(sql)
+}
+
 fn create_internal_id_index_sql<F>(wallet_id: &WalletId, table_name_creator: F) -> Result<String, MmError<SqlError>>
 where
     F: FnOnce(&WalletId) -> String,
@@ -167,6 +185,27 @@ fn select_highest_block_height_sql(wallet_id: &WalletId) -> Result<String, MmErr
     Ok(sql)
 }
 
+fn select_scanned_block_height_sql(wallet_id: &WalletId) -> Result<String, MmError<SqlError>> {
+    let table_name = scanned_block_table(wallet_id);
+    validate_table_name(&table_name)?;
+
+    let sql = format!("SELECT block_height FROM {} WHERE id = 0;", table_name);
+
+    Ok(sql)
+}
+
+fn upsert_scanned_block_height_sql(wallet_id: &WalletId) -> Result<String, MmError<SqlError>> {
+    let table_name = scanned_block_table(wallet_id);
+    validate_table_name(&table_name)?;
+
+    let sql = format!(
+        "INSERT OR REPLACE INTO {} (id, block_height) VALUES (0, ?1);",
+        table_name
+    );
+
+    Ok(sql)
+}
+
 fn update_tx_in_table_by_internal_id_sql(wallet_id: &WalletId) -> Result<String, MmError<SqlError>> {
     let table_name = tx_history_table(wallet_id);
     validate_table_name(&table_name)?;
@@ -354,6 +393,13 @@ fn tx_details_from_row(row: &Row<'_>) -> Result<TransactionDetails, SqlError> {
 
 fn block_height_from_row(row: &Row<'_>) -> Result<u32, SqlError> { row.get(0) }
 
+fn scanned_block_height_from_row(row: &Row<'_>) -> Result<u64, SqlError> {
+    let height: i64 = row.get(0)?;
+    height
+        .try_into()
+        .map_err(|e| SqlError::FromSqlConversionFailure(0, Type::Integer, Box::new(e)))
+}
+
 impl TxHistoryStorageError for SqlError {}
 
 impl ConfirmationStatus {
@@ -396,6 +442,7 @@ impl TxHistoryStorage for SqliteTxHistoryStorage {
         let sql_history = create_tx_history_table_sql(wallet_id)?;
         let sql_cache = create_tx_cache_table_sql(wallet_id)?;
         let sql_addr = create_tx_address_table_sql(wallet_id)?;
+        let sql_scanned_block = create_scanned_block_table_sql(wallet_id)?;
 
         let sql_history_index = create_internal_id_index_sql(wallet_id, tx_history_table)?;
         let sql_addr_index = create_internal_id_index_sql(wallet_id, tx_address_table)?;
@@ -406,6 +453,7 @@ impl TxHistoryStorage for SqliteTxHistoryStorage {
             conn.execute(&sql_history, []).map(|_| ())?;
             conn.execute(&sql_addr, []).map(|_| ())?;
             conn.execute(&sql_cache, []).map(|_| ())?;
+            conn.execute(&sql_scanned_block, []).map(|_| ())?;
 
             conn.execute(&sql_history_index, []).map(|_| ())?;
             conn.execute(&sql_addr_index, []).map(|_| ())?;
@@ -542,6 +590,30 @@ impl TxHistoryStorage for SqliteTxHistoryStorage {
         .await
     }
 
+    async fn get_scanned_block_height(&self, wallet_id: &WalletId) -> Result<Option<u64>, MmError<Self::Error>> {
+        let sql = select_scanned_block_height_sql(wallet_id)?;
+        let selfi = self.clone();
+
+        async_blocking(move || {
+            let conn = selfi.0.lock().unwrap();
+            query_single_row(&conn, &sql, [], scanned_block_height_from_row).map_to_mm(SqlError::from)
+        })
+        .await
+    }
+
+    async fn set_scanned_block_height(&self, wallet_id: &WalletId, height: u64) -> Result<(), MmError<Self::Error>> {
+        let sql = upsert_scanned_block_height_sql(wallet_id)?;
+        let params = [height.to_string()];
+        let selfi = self.clone();
+
+        async_blocking(move || {
+            let conn = selfi.0.lock().unwrap();
+            conn.execute(&sql, params).map(|_| ())?;
+            Ok(())
+        })
+        .await
+    }
+
     async fn history_contains_unconfirmed_txes(
         &self,
         wallet_id: &WalletId,
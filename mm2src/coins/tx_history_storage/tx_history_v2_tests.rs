@@ -303,6 +303,28 @@ async fn test_has_transactions_with_hash_impl() {
     assert!(has_tx_hash);
 }
 
+async fn test_scanned_block_height_impl() {
+    let wallet_id = wallet_id_for_test("TEST_SCANNED_BLOCK_HEIGHT");
+    let other_wallet_id = wallet_id_for_test("TEST_SCANNED_BLOCK_HEIGHT_OTHER");
+
+    let ctx = mm_ctx_with_custom_db();
+    let storage = TxHistoryStorageBuilder::new(&ctx).build().unwrap();
+
+    storage.init(&wallet_id).await.unwrap();
+    storage.init(&other_wallet_id).await.unwrap();
+
+    let height = storage.get_scanned_block_height(&wallet_id).await.unwrap();
+    assert_eq!(height, None);
+
+    storage.set_scanned_block_height(&wallet_id, 1000).await.unwrap();
+    storage.set_scanned_block_height(&wallet_id, 2000).await.unwrap();
+    let height = storage.get_scanned_block_height(&wallet_id).await.unwrap();
+    assert_eq!(height, Some(2000));
+
+    let height = storage.get_scanned_block_height(&other_wallet_id).await.unwrap();
+    assert_eq!(height, None);
+}
+
 async fn test_unique_tx_hashes_num_impl() {
     let wallet_id = wallet_id_for_test("TEST_UNIQUE_TX_HASHES_NUM");
 
@@ -624,6 +646,9 @@ mod native_tests {
     #[test]
     fn test_has_transactions_with_hash() { block_on(super::test_has_transactions_with_hash_impl()); }
 
+    #[test]
+    fn test_scanned_block_height() { block_on(super::test_scanned_block_height_impl()); }
+
     #[test]
     fn test_unique_tx_hashes_num() { block_on(super::test_unique_tx_hashes_num_impl()); }
 
@@ -694,6 +719,9 @@ mod wasm_tests {
     #[wasm_bindgen_test]
     async fn test_has_transactions_with_hash() { super::test_has_transactions_with_hash_impl().await; }
 
+    #[wasm_bindgen_test]
+    async fn test_scanned_block_height() { super::test_scanned_block_height_impl().await; }
+
     #[wasm_bindgen_test]
     async fn test_unique_tx_hashes_num() { super::test_unique_tx_hashes_num_impl().await; }
 
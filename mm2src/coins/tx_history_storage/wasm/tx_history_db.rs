@@ -1,9 +1,10 @@
 use crate::tx_history_storage::wasm::tx_history_storage_v1::TxHistoryTableV1;
-use crate::tx_history_storage::wasm::tx_history_storage_v2::{TxCacheTableV2, TxHistoryTableV2};
+use crate::tx_history_storage::wasm::tx_history_storage_v2::{TxCacheTableV2, TxHistoryScannedBlockTableV2,
+                                                             TxHistoryTableV2};
 use async_trait::async_trait;
 use mm2_db::indexed_db::{DbIdentifier, DbInstance, DbLocked, IndexedDb, IndexedDbBuilder, InitDbResult};
 
-const DB_VERSION: u32 = 1;
+const DB_VERSION: u32 = 2;
 
 pub type TxHistoryDbLocked<'a> = DbLocked<'a, TxHistoryDb>;
 
@@ -21,6 +22,7 @@ impl DbInstance for TxHistoryDb {
             .with_table::<TxHistoryTableV1>()
             .with_table::<TxHistoryTableV2>()
             .with_table::<TxCacheTableV2>()
+            .with_table::<TxHistoryScannedBlockTableV2>()
             .build()
             .await?;
         Ok(TxHistoryDb { inner })
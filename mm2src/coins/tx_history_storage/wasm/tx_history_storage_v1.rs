@@ -1,7 +1,8 @@
 use crate::tx_history_storage::wasm::tx_history_db::TxHistoryDb;
 use crate::tx_history_storage::wasm::WasmTxHistoryResult;
 use crate::TransactionDetails;
-use mm2_db::indexed_db::{DbIdentifier, DbInstance, DbUpgrader, OnUpgradeResult, TableSignature};
+use mm2_db::indexed_db::{DbIdentifier, DbInstance, DbUpgrader, OnUpgradeError, OnUpgradeResult, TableSignature};
+use mm2_err_handle::prelude::*;
 
 pub async fn load_tx_history(
     db: &TxHistoryDb,
@@ -75,12 +76,27 @@ pub(crate) struct TxHistoryTableV1 {
 impl TableSignature for TxHistoryTableV1 {
     const TABLE_NAME: &'static str = "tx_history";
 
-    fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
-        if let (0, 1) = (old_version, new_version) {
-            let table = upgrader.create_table(Self::TABLE_NAME)?;
-            table.create_index("history_id", true)?;
+    fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+        while old_version < new_version {
+            match old_version {
+                0 => {
+                    let table = upgrader.create_table(Self::TABLE_NAME)?;
+                    table.create_index("history_id", true)?;
+                },
+                1 => {
+                    // nothing to change
+                },
+                unsupported_version => {
+                    return MmError::err(OnUpgradeError::UnsupportedVersion {
+                        unsupported_version,
+                        old_version,
+                        new_version,
+                    })
+                },
+            }
+
+            old_version += 1;
         }
-
         Ok(())
     }
 }
@@ -8,7 +8,7 @@ use async_trait::async_trait;
 use common::PagingOptionsEnum;
 use itertools::Itertools;
 use mm2_core::mm_ctx::MmArc;
-use mm2_db::indexed_db::{BeBigUint, DbUpgrader, MultiIndex, OnUpgradeResult, SharedDb, TableSignature};
+use mm2_db::indexed_db::{BeBigUint, DbUpgrader, MultiIndex, OnUpgradeError, OnUpgradeResult, SharedDb, TableSignature};
 use mm2_err_handle::prelude::*;
 use rpc::v1::types::Bytes as BytesJson;
 use serde_json::{self as json, Value as Json};
@@ -125,6 +125,37 @@ impl TxHistoryStorage for IndexedDbTxHistoryStorage {
         Ok(None)
     }
 
+    async fn get_scanned_block_height(&self, wallet_id: &WalletId) -> Result<Option<u64>, MmError<Self::Error>> {
+        let locked_db = self.lock_db().await?;
+        let db_transaction = locked_db.get_inner().transaction().await?;
+        let table = db_transaction.table::<TxHistoryScannedBlockTableV2>().await?;
+
+        let index_keys = MultiIndex::new(TxHistoryScannedBlockTableV2::WALLET_ID_INDEX)
+            .with_value(&wallet_id.ticker)?
+            .with_value(wallet_id.hd_wallet_rmd160_or_exclude())?;
+        Ok(table
+            .get_item_by_unique_multi_index(index_keys)
+            .await?
+            .map(|(_item_id, item)| item.block_height))
+    }
+
+    async fn set_scanned_block_height(&self, wallet_id: &WalletId, height: u64) -> Result<(), MmError<Self::Error>> {
+        let locked_db = self.lock_db().await?;
+        let db_transaction = locked_db.get_inner().transaction().await?;
+        let table = db_transaction.table::<TxHistoryScannedBlockTableV2>().await?;
+
+        let index_keys = MultiIndex::new(TxHistoryScannedBlockTableV2::WALLET_ID_INDEX)
+            .with_value(&wallet_id.ticker)?
+            .with_value(wallet_id.hd_wallet_rmd160_or_exclude())?;
+        let item = TxHistoryScannedBlockTableV2 {
+            coin: wallet_id.ticker.clone(),
+            hd_wallet_rmd160: wallet_id.hd_wallet_rmd160_or_exclude(),
+            block_height: height,
+        };
+        table.replace_item_by_unique_multi_index(index_keys, &item).await?;
+        Ok(())
+    }
+
     /// Since we need to filter the transactions by the given `for_addresses`,
     /// we can't use [`DbTable::count_by_multi_index`].
     /// TODO consider one of the solutions described at [`IndexedDbTxHistoryStorage::get_history`].
@@ -423,30 +454,50 @@ impl TxHistoryTableV2 {
 impl TableSignature for TxHistoryTableV2 {
     const TABLE_NAME: &'static str = "tx_history_v2";
 
-    fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
-        if let (0, 1) = (old_version, new_version) {
-            let table = upgrader.create_table(Self::TABLE_NAME)?;
-            table.create_multi_index(TxHistoryTableV2::WALLET_ID_INDEX, &["coin", "hd_wallet_rmd160"], false)?;
-            table.create_multi_index(
-                TxHistoryTableV2::WALLET_ID_INTERNAL_ID_INDEX,
-                &["coin", "hd_wallet_rmd160", "internal_id"],
-                true,
-            )?;
-            table.create_multi_index(
-                TxHistoryTableV2::WALLET_ID_TX_HASH_INDEX,
-                &["coin", "hd_wallet_rmd160", "tx_hash"],
-                false,
-            )?;
-            table.create_multi_index(
-                TxHistoryTableV2::WALLET_ID_CONFIRMATION_STATUS_INDEX,
-                &["coin", "hd_wallet_rmd160", "confirmation_status"],
-                false,
-            )?;
-            table.create_multi_index(
-                TxHistoryTableV2::WALLET_ID_TOKEN_ID_INDEX,
-                &["coin", "hd_wallet_rmd160", "token_id"],
-                false,
-            )?;
+    fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+        while old_version < new_version {
+            match old_version {
+                0 => {
+                    let table = upgrader.create_table(Self::TABLE_NAME)?;
+                    table.create_multi_index(
+                        TxHistoryTableV2::WALLET_ID_INDEX,
+                        &["coin", "hd_wallet_rmd160"],
+                        false,
+                    )?;
+                    table.create_multi_index(
+                        TxHistoryTableV2::WALLET_ID_INTERNAL_ID_INDEX,
+                        &["coin", "hd_wallet_rmd160", "internal_id"],
+                        true,
+                    )?;
+                    table.create_multi_index(
+                        TxHistoryTableV2::WALLET_ID_TX_HASH_INDEX,
+                        &["coin", "hd_wallet_rmd160", "tx_hash"],
+                        false,
+                    )?;
+                    table.create_multi_index(
+                        TxHistoryTableV2::WALLET_ID_CONFIRMATION_STATUS_INDEX,
+                        &["coin", "hd_wallet_rmd160", "confirmation_status"],
+                        false,
+                    )?;
+                    table.create_multi_index(
+                        TxHistoryTableV2::WALLET_ID_TOKEN_ID_INDEX,
+                        &["coin", "hd_wallet_rmd160", "token_id"],
+                        false,
+                    )?;
+                },
+                1 => {
+                    // nothing to change
+                },
+                unsupported_version => {
+                    return MmError::err(OnUpgradeError::UnsupportedVersion {
+                        unsupported_version,
+                        old_version,
+                        new_version,
+                    })
+                },
+            }
+
+            old_version += 1;
         }
         Ok(())
     }
@@ -483,10 +534,70 @@ impl TxCacheTableV2 {
 impl TableSignature for TxCacheTableV2 {
     const TABLE_NAME: &'static str = "tx_cache_v2";
 
-    fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
-        if let (0, 1) = (old_version, new_version) {
-            let table = upgrader.create_table(Self::TABLE_NAME)?;
-            table.create_multi_index(TxCacheTableV2::COIN_TX_HASH_INDEX, &["coin", "tx_hash"], true)?;
+    fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+        while old_version < new_version {
+            match old_version {
+                0 => {
+                    let table = upgrader.create_table(Self::TABLE_NAME)?;
+                    table.create_multi_index(TxCacheTableV2::COIN_TX_HASH_INDEX, &["coin", "tx_hash"], true)?;
+                },
+                1 => {
+                    // nothing to change
+                },
+                unsupported_version => {
+                    return MmError::err(OnUpgradeError::UnsupportedVersion {
+                        unsupported_version,
+                        old_version,
+                        new_version,
+                    })
+                },
+            }
+
+            old_version += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The block height the history of a wallet has been scanned up to.
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct TxHistoryScannedBlockTableV2 {
+    coin: String,
+    hd_wallet_rmd160: String,
+    block_height: u64,
+}
+
+impl TxHistoryScannedBlockTableV2 {
+    /// A **unique** index that consists of the following properties:
+    /// * coin - coin ticker
+    /// * hd_wallet_rmd160 - HD wallet ID (can be an empty string)
+    const WALLET_ID_INDEX: &'static str = "wallet_id";
+}
+
+impl TableSignature for TxHistoryScannedBlockTableV2 {
+    const TABLE_NAME: &'static str = "tx_history_scanned_block_v2";
+
+    fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+        while old_version < new_version {
+            match old_version {
+                0 => {
+                    // do nothing explicitly because the table should be created on upgrade
+                    // from version 1 to 2 in order to avoid breaking existing databases
+                },
+                1 => {
+                    let table = upgrader.create_table(Self::TABLE_NAME)?;
+                    table.create_multi_index(Self::WALLET_ID_INDEX, &["coin", "hd_wallet_rmd160"], true)?;
+                },
+                unsupported_version => {
+                    return MmError::err(OnUpgradeError::UnsupportedVersion {
+                        unsupported_version,
+                        old_version,
+                        new_version,
+                    })
+                },
+            }
+
+            old_version += 1;
         }
         Ok(())
     }
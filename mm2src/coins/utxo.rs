@@ -36,8 +36,13 @@ pub mod tx_history_events;
 pub mod utxo_balance_events;
 pub mod utxo_block_header_storage;
 pub mod utxo_builder;
+pub mod utxo_bump_fee;
+pub mod utxo_coin_control;
 pub mod utxo_common;
 pub mod utxo_hd_wallet;
+pub mod utxo_lock_storage;
+pub mod utxo_multisig;
+pub mod utxo_psbt;
 pub mod utxo_standard;
 pub mod utxo_tx_history_v2;
 pub mod utxo_withdraw;
@@ -46,6 +51,7 @@ use async_trait::async_trait;
 #[cfg(not(target_arch = "wasm32"))]
 use bitcoin::network::constants::Network as BitcoinNetwork;
 pub use bitcrypto::{dhash160, sha256, ChecksumType};
+use chain::constants::SEQUENCE_RBF_ENABLED;
 pub use chain::Transaction as UtxoTx;
 use chain::{OutPoint, TransactionOutput, TxHashAlgo};
 use common::executor::abortable_queue::AbortableQueue;
@@ -97,6 +103,7 @@ use std::sync::{Arc, Mutex, Weak};
 use utxo_builder::UtxoConfBuilder;
 use utxo_common::{big_decimal_from_sat, UtxoTxBuilder};
 use utxo_hd_wallet::UtxoHDWallet;
+use utxo_multisig::{UtxoMultisigParams, UtxoMultisigWallet};
 use utxo_signer::with_key_pair::sign_tx;
 use utxo_signer::{TxProvider, TxProviderError, UtxoSignTxError, UtxoSignTxResult};
 
@@ -126,6 +133,7 @@ const KILO_BYTE: u64 = 1000;
 /// https://bitcoin.stackexchange.com/a/77192
 const MAX_DER_SIGNATURE_LEN: usize = 72;
 const COMPRESSED_PUBKEY_LEN: usize = 33;
+const SCHNORR_SIGNATURE_LEN: usize = 64;
 const P2PKH_OUTPUT_LEN: u64 = 34;
 const MATURE_CONFIRMATIONS_DEFAULT: u32 = 100;
 const UTXO_DUST_AMOUNT: u64 = 1000;
@@ -547,6 +555,9 @@ pub struct UtxoCoinConf {
     /// relay fee amount instead of calculated
     /// https://github.com/KomodoPlatform/atomicDEX-API/issues/617
     pub force_min_relay_fee: bool,
+    /// If set to true, the swap payments and the withdrawals opt in to the replace-by-fee (BIP-125),
+    /// so they can be replaced by a transaction paying a higher fee using the `bump_fee` RPC.
+    pub signal_rbf: bool,
     /// Block count for median time past calculation
     pub mtp_block_count: NonZeroU64,
     pub estimate_fee_mode: Option<EstimateFeeMode>,
@@ -593,6 +604,10 @@ pub struct UtxoCoinFields {
     /// The daemon needs some time to update the listunspent list for address which makes it return already spent UTXOs
     /// This cache helps to prevent UTXO reuse in such cases
     pub recently_spent_outpoints: AsyncMutex<RecentlySpentOutPoints>,
+    /// The unspent outputs locked by the user or by pending PSBTs that must not be spent unless they're unlocked
+    /// or their locks expire.
+    /// The set is loaded from [`utxo_lock_storage::UtxoLockStorage`] on the coin activation and kept in sync with it.
+    pub locked_unspents: Mutex<utxo_lock_storage::LockedOutPoints>,
     pub tx_hash_algo: TxHashAlgo,
     /// The flag determines whether to use mature unspent outputs *only* to generate transactions.
     /// https://github.com/KomodoPlatform/atomicDEX-API/issues/1181
@@ -610,6 +625,8 @@ pub struct UtxoCoinFields {
     /// This abortable system is used to spawn coin's related futures that should be aborted on coin deactivation
     /// and on [`MmArc::stop`].
     pub abortable_system: AbortableQueue,
+    /// The m-of-n multisig wallet shared with other cosigners, initialized only if `multisig` activation param is set.
+    pub multisig: Option<UtxoMultisigWallet>,
 }
 
 #[derive(Debug, Display)]
@@ -1088,6 +1105,7 @@ pub trait GetUtxoListOps {
     /// + `RecentlySpentOutPoints` MutexGuard for further interaction (e.g. to add new transaction to it).
     /// The function uses either [`GetUtxoListOps::get_all_unspent_ordered_list`] or [`GetUtxoListOps::get_mature_unspent_ordered_list`]
     /// depending on the coin configuration.
+    /// The unspents locked by the user (see [`UtxoCoinFields::locked_unspents`]) are excluded.
     async fn get_unspent_ordered_list(
         &self,
         address: &Address,
@@ -1125,6 +1143,7 @@ pub trait GetUtxoMapOps {
     /// + `RecentlySpentOutPoints` MutexGuard for further interaction (e.g. to add new transaction to it).
     /// The function uses either [`GetUtxoMapOps::get_all_unspent_ordered_map`] or [`GetUtxoMapOps::get_mature_unspent_ordered_map`]
     /// depending on the coin configuration.
+    /// The unspents locked by the user (see [`UtxoCoinFields::locked_unspents`]) are excluded.
     async fn get_unspent_ordered_map(
         &self,
         addresses: Vec<Address>,
@@ -1413,6 +1432,10 @@ pub struct UtxoActivationParams {
     /// If not specified, the first non-change address for the first account is used.
     #[serde(default)]
     pub path_to_address: HDPathAccountToAddressId,
+    /// If specified, the coin is activated as an m-of-n multisig wallet shared with the given cosigners.
+    /// The multisig addresses are spent using `withdraw_multisig` and `task::sign_multisig_psbt::*` RPCs.
+    #[serde(default)]
+    pub multisig: Option<UtxoMultisigParams>,
 }
 
 #[derive(Debug, Display)]
@@ -1430,6 +1453,7 @@ pub enum UtxoFromLegacyReqErr {
     InvalidPrivKeyPolicy(json::Error),
     InvalidAccount(json::Error),
     InvalidAddressIndex(json::Error),
+    InvalidMultisig(json::Error),
 }
 
 impl UtxoActivationParams {
@@ -1476,6 +1500,7 @@ impl UtxoActivationParams {
         let path_to_address = json::from_value::<Option<HDPathAccountToAddressId>>(req["path_to_address"].clone())
             .map_to_mm(UtxoFromLegacyReqErr::InvalidAddressIndex)?
             .unwrap_or_default();
+        let multisig = json::from_value(req["multisig"].clone()).map_to_mm(UtxoFromLegacyReqErr::InvalidMultisig)?;
 
         Ok(UtxoActivationParams {
             mode,
@@ -1489,6 +1514,7 @@ impl UtxoActivationParams {
             priv_key_policy,
             check_utxo_maturity,
             path_to_address,
+            multisig,
         })
     }
 }
@@ -1753,6 +1779,9 @@ where
         .add_available_inputs(unspents)
         .add_outputs(outputs)
         .with_fee_policy(fee_policy);
+    if coin.as_ref().conf.signal_rbf {
+        builder = builder.with_input_sequence(SEQUENCE_RBF_ENABLED);
+    }
     if let Some(required) = required_inputs {
         builder = builder.add_required_inputs(required);
     }
@@ -1795,6 +1824,7 @@ pub fn output_script(address: &Address) -> Result<Script, keys::Error> {
         AddressScriptType::P2SH => Ok(Builder::build_p2sh(address.hash())),
         AddressScriptType::P2WPKH => Builder::build_p2wpkh(address.hash()),
         AddressScriptType::P2WSH => Builder::build_p2wsh(address.hash()),
+        AddressScriptType::P2TR => Builder::build_p2tr(address.hash()),
     }
 }
 
@@ -1821,6 +1851,7 @@ pub fn address_by_conf_and_pubkey_str(
         check_utxo_maturity: None,
         // This will not be used since the pubkey from orderbook/etc.. will be used to generate the address
         path_to_address: HDPathAccountToAddressId::default(),
+        multisig: None,
     };
     let conf_builder = UtxoConfBuilder::new(conf, &params, coin);
     let utxo_conf = try_s!(conf_builder.build());
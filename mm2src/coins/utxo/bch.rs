@@ -14,16 +14,22 @@ use crate::utxo::utxo_hd_wallet::{UtxoHDAccount, UtxoHDAddress};
 use crate::utxo::utxo_tx_history_v2::{UtxoMyAddressesHistoryError, UtxoTxDetailsError, UtxoTxDetailsParams,
                                       UtxoTxHistoryOps};
 use crate::{coin_balance, BlockHeightAndTime, CanRefundHtlc, CheckIfMyPaymentSentArgs, CoinBalance, CoinBalanceMap,
-            CoinProtocol, CoinWithDerivationMethod, CoinWithPrivKeyPolicy, ConfirmPaymentInput, DexFee,
-            GetWithdrawSenderAddress, IguanaBalanceOps, IguanaPrivKey, MmCoinEnum, NegotiateSwapContractAddrErr,
-            PrivKeyBuildPolicy, RawTransactionFut, RawTransactionRequest, RawTransactionResult, RefundPaymentArgs,
-            SearchForSwapTxSpendInput, SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SignRawTransactionRequest,
-            SignatureResult, SpendPaymentArgs, SwapOps, TradePreimageValue, TransactionFut, TransactionResult,
-            TransactionType, TxFeeDetails, TxMarshalingErr, UnexpectedDerivationMethod, ValidateAddressResult,
-            ValidateFeeArgs, ValidateOtherPubKeyErr, ValidatePaymentError, ValidatePaymentFut, ValidatePaymentInput,
-            ValidateWatcherSpendInput, VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WatcherReward,
-            WatcherRewardError, WatcherSearchForSwapTxSpendInput, WatcherValidatePaymentInput,
-            WatcherValidateTakerFeeInput, WithdrawFut};
+            CoinProtocol, CoinWithDerivationMethod, CoinWithPrivKeyPolicy, CommonSwapOpsV2, ConfirmPaymentInput,
+            DexFee, FindPaymentSpendError, FundingTxSpend, GenPreimageResult, GenTakerFundingSpendArgs,
+            GenTakerPaymentSpendArgs, GetWithdrawSenderAddress, IguanaBalanceOps, IguanaPrivKey, MakerCoinSwapOpsV2,
+            MmCoinEnum, NegotiateSwapContractAddrErr, PrivKeyBuildPolicy, RawTransactionFut, RawTransactionRequest,
+            RawTransactionResult, RefundFundingSecretArgs, RefundMakerPaymentSecretArgs,
+            RefundMakerPaymentTimelockArgs, RefundPaymentArgs, RefundTakerPaymentArgs, SearchForFundingSpendErr,
+            SearchForSwapTxSpendInput, SendMakerPaymentArgs, SendMakerPaymentSpendPreimageInput, SendPaymentArgs,
+            SendTakerFundingArgs, SignRawTransactionRequest, SignatureResult, SpendMakerPaymentArgs, SpendPaymentArgs,
+            SwapOps, TakerCoinSwapOpsV2, ToBytes, TradePreimageValue, TransactionFut, TransactionResult,
+            TransactionType, TxFeeDetails, TxMarshalingErr, TxPreimageWithSig, UnexpectedDerivationMethod,
+            ValidateAddressResult, ValidateFeeArgs, ValidateMakerPaymentArgs, ValidateOtherPubKeyErr,
+            ValidatePaymentError, ValidatePaymentFut, ValidatePaymentInput, ValidateSwapV2TxResult,
+            ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageResult,
+            ValidateTakerPaymentSpendPreimageResult, ValidateWatcherSpendInput, VerificationResult,
+            WaitForHTLCTxSpendArgs, WatcherOps, WatcherReward, WatcherRewardError, WatcherSearchForSwapTxSpendInput,
+            WatcherValidatePaymentInput, WatcherValidateTakerFeeInput, WithdrawFut};
 use common::executor::{AbortableSystem, AbortedError};
 use common::log::warn;
 use derive_more::Display;
@@ -1088,12 +1094,12 @@ impl WatcherOps for BchCoin {
 
     #[inline]
     fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
-        utxo_common::watcher_validate_taker_payment(self, input)
+        utxo_common::watcher_validate_taker_payment(self, input, utxo_common::DEFAULT_SWAP_VOUT)
     }
 
     #[inline]
     fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
-        utxo_common::validate_payment_spend_or_refund(self, input)
+        utxo_common::validate_payment_spend_or_refund(self, input, utxo_common::DEFAULT_SWAP_VOUT)
     }
 
     async fn watcher_search_for_swap_tx_spend(
@@ -1130,6 +1136,209 @@ impl WatcherOps for BchCoin {
     ) -> Result<Option<WatcherReward>, MmError<WatcherRewardError>> {
         Ok(None)
     }
+
+    fn is_supported_by_watchers_v2(&self) -> bool { true }
+
+    async fn sign_refund_for_watcher_v2(&self, args: RefundPaymentArgs<'_>) -> TransactionResult {
+        utxo_common::sign_refund_for_watcher_v2(self, args)
+            .await
+            .map(|tx| tx.into())
+    }
+
+    async fn sign_maker_payment_spend_for_watcher_v2(
+        &self,
+        args: MakerPaymentSpendForWatcherArgs<'_>,
+    ) -> TransactionResult {
+        utxo_common::sign_maker_payment_spend_for_watcher_v2(self, args)
+            .await
+            .map(|tx| tx.into())
+    }
+
+    async fn watcher_validate_preimage_v2(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        is_refund: bool,
+    ) -> ValidatePaymentResult<()> {
+        utxo_common::watcher_validate_preimage_v2(self, payment_tx, preimage, is_refund).await
+    }
+
+    async fn send_preimage_as_watcher_v2(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        secret: Option<&[u8]>,
+    ) -> TransactionResult {
+        utxo_common::send_preimage_as_watcher_v2(self, payment_tx, preimage, secret)
+            .await
+            .map(|tx| tx.into())
+    }
+
+    async fn watcher_search_for_payment_spend_v2(
+        &self,
+        payment_tx: &[u8],
+        search_from_block: u64,
+    ) -> Result<Option<TransactionEnum>, String> {
+        utxo_common::watcher_search_for_payment_spend_v2(self, payment_tx, search_from_block)
+            .await
+            .map(|spend| spend.map(|tx| tx.into()))
+    }
+}
+
+#[async_trait]
+impl MakerCoinSwapOpsV2 for BchCoin {
+    async fn send_maker_payment_v2(&self, args: SendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::send_maker_payment_v2(self.clone(), args).await
+    }
+
+    async fn validate_maker_payment_v2(&self, args: ValidateMakerPaymentArgs<'_, Self>) -> ValidatePaymentResult<()> {
+        utxo_common::validate_maker_payment_v2(self, args).await
+    }
+
+    async fn refund_maker_payment_v2_timelock(
+        &self,
+        args: RefundMakerPaymentTimelockArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::refund_maker_payment_v2_timelock(self.clone(), args).await
+    }
+
+    async fn refund_maker_payment_v2_secret(
+        &self,
+        args: RefundMakerPaymentSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::refund_maker_payment_v2_secret(self.clone(), args).await
+    }
+
+    async fn spend_maker_payment_v2(&self, args: SpendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::spend_maker_payment_v2(self, args).await
+    }
+}
+
+#[async_trait]
+impl TakerCoinSwapOpsV2 for BchCoin {
+    async fn send_taker_funding(&self, args: SendTakerFundingArgs<'_>) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::send_taker_funding(self.clone(), args).await
+    }
+
+    async fn validate_taker_funding(&self, args: ValidateTakerFundingArgs<'_, Self>) -> ValidateSwapV2TxResult {
+        utxo_common::validate_taker_funding(self, args).await
+    }
+
+    async fn refund_taker_funding_timelock(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::refund_taker_tx_v2_timelock(self.clone(), args).await
+    }
+
+    async fn refund_taker_funding_secret(
+        &self,
+        args: RefundFundingSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::refund_taker_funding_secret(self.clone(), args).await
+    }
+
+    async fn search_for_taker_funding_spend(
+        &self,
+        tx: &Self::Tx,
+        from_block: u64,
+        _secret_hash: &[u8],
+    ) -> Result<Option<FundingTxSpend<Self>>, SearchForFundingSpendErr> {
+        utxo_common::search_for_taker_funding_spend(self.as_ref(), tx, utxo_common::DEFAULT_SWAP_VOUT, from_block).await
+    }
+
+    async fn gen_taker_funding_spend_preimage(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        utxo_common::gen_and_sign_taker_funding_spend_preimage(self, args, &htlc_keypair).await
+    }
+
+    async fn validate_taker_funding_spend_preimage(
+        &self,
+        gen_args: &GenTakerFundingSpendArgs<'_, Self>,
+        preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerFundingSpendPreimageResult {
+        utxo_common::validate_taker_funding_spend_preimage(self, gen_args, preimage).await
+    }
+
+    async fn sign_and_send_taker_funding_spend(
+        &self,
+        preimage: &TxPreimageWithSig<Self>,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        utxo_common::sign_and_send_taker_funding_spend(self, preimage, args, &htlc_keypair).await
+    }
+
+    async fn refund_combined_taker_payment(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        utxo_common::refund_taker_tx_v2_timelock(self.clone(), args).await
+    }
+
+    async fn gen_taker_payment_spend_preimage(
+        &self,
+        args: &GenTakerPaymentSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        let key_pair = self.derive_htlc_key_pair(swap_unique_data);
+        utxo_common::gen_and_sign_taker_payment_spend_preimage(self, args, &key_pair).await
+    }
+
+    async fn validate_taker_payment_spend_preimage(
+        &self,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerPaymentSpendPreimageResult {
+        utxo_common::validate_taker_payment_spend_preimage(self, gen_args, preimage).await
+    }
+
+    async fn sign_and_broadcast_taker_payment_spend(
+        &self,
+        preimage: Option<&TxPreimageWithSig<Self>>,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        secret: &[u8],
+        swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        let preimage = preimage
+            .ok_or_else(|| TransactionErr::Plain(ERRL!("taker_payment_spend_preimage must be Some for UTXO coin")))?;
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        utxo_common::sign_and_broadcast_taker_payment_spend(self, preimage, gen_args, secret, &htlc_keypair).await
+    }
+
+    async fn find_taker_payment_spend_tx(
+        &self,
+        taker_payment: &Self::Tx,
+        from_block: u64,
+        wait_until: u64,
+    ) -> MmResult<Self::Tx, FindPaymentSpendError> {
+        utxo_common::find_taker_payment_spend_tx(self.as_ref(), taker_payment, from_block, wait_until).await
+    }
+
+    async fn extract_secret_v2(&self, secret_hash: &[u8], spend_tx: &Self::Tx) -> Result<[u8; 32], String> {
+        utxo_common::extract_secret_v2(secret_hash, spend_tx)
+    }
+}
+
+impl CommonSwapOpsV2 for BchCoin {
+    fn derive_htlc_pubkey_v2(&self, swap_unique_data: &[u8]) -> Self::Pubkey {
+        *self.derive_htlc_key_pair(swap_unique_data).public()
+    }
+
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2_bytes(&self, swap_unique_data: &[u8]) -> Vec<u8> {
+        self.derive_htlc_pubkey_v2(swap_unique_data).to_bytes()
+    }
+
+    #[inline(always)]
+    fn taker_pubkey_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.derive_htlc_pubkey_v2(&[]).to_bytes()) // unique_data not used for non-private coins
+    }
 }
 
 #[async_trait]
@@ -10,19 +10,21 @@ use crate::utxo::bch::BchCoin;
 use crate::utxo::bchd_grpc::{check_slp_transaction, validate_slp_utxos, ValidateSlpUtxosErr};
 use crate::utxo::rpc_clients::{UnspentInfo, UtxoRpcClientEnum, UtxoRpcError, UtxoRpcResult};
 use crate::utxo::utxo_common::{self, big_decimal_from_sat_unsigned, payment_script, UtxoTxBuilder};
-use crate::utxo::{generate_and_send_tx, sat_from_big_decimal, ActualTxFee, AdditionalTxData, BroadcastTxErr,
-                  FeePolicy, GenerateTxError, RecentlySpentOutPointsGuard, UtxoCoinConf, UtxoCoinFields,
-                  UtxoCommonOps, UtxoTx, UtxoTxBroadcastOps, UtxoTxGenerationOps};
+use crate::utxo::{generate_and_send_tx, sat_from_big_decimal, ActualTxFee, AdditionalTxData, AddrFromStrError,
+                  BroadcastTxErr, FeePolicy, GenerateTxError, RecentlySpentOutPointsGuard, UtxoCoinConf,
+                  UtxoCoinFields, UtxoCommonOps, UtxoTx, UtxoTxBroadcastOps, UtxoTxGenerationOps};
 use crate::{BalanceFut, CheckIfMyPaymentSentArgs, CoinBalance, ConfirmPaymentInput, DerivationMethod, DexFee,
             FeeApproxStage, FoundSwapTxSpend, HistorySyncState, MarketCoinOps, MmCoin, NegotiateSwapContractAddrErr,
-            NumConversError, PrivKeyPolicyNotAllowed, RawTransactionFut, RawTransactionRequest, RawTransactionResult,
-            RefundPaymentArgs, SearchForSwapTxSpendInput, SendPaymentArgs, SignRawTransactionRequest, SignatureResult,
-            SpendPaymentArgs, SwapOps, SwapTxTypeWithSecretHash, TradeFee, TradePreimageError, TradePreimageFut,
-            TradePreimageResult, TradePreimageValue, TransactionData, TransactionDetails, TransactionEnum,
-            TransactionErr, TransactionResult, TxFeeDetails, TxMarshalingErr, UnexpectedDerivationMethod,
-            ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr, ValidatePaymentInput, VerificationError,
-            VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WeakSpawner, WithdrawError, WithdrawFee,
-            WithdrawFut, WithdrawRequest};
+            NumConversError, ParseCoinAssocTypes, PrivKeyPolicyNotAllowed, RawTransactionFut, RawTransactionRequest,
+            RawTransactionResult, RefundPaymentArgs, SearchForSwapTxSpendInput, SendMakerPaymentSpendPreimageInput,
+            SendPaymentArgs, SignRawTransactionRequest, SignatureResult, SpendPaymentArgs, SwapOps,
+            SwapTxTypeWithSecretHash, TradeFee, TradePreimageError, TradePreimageFut, TradePreimageResult,
+            TradePreimageValue, TransactionData, TransactionDetails, TransactionEnum, TransactionErr, TransactionFut,
+            TransactionResult, TxFeeDetails, TxMarshalingErr, UnexpectedDerivationMethod, ValidateAddressResult,
+            ValidateFeeArgs, ValidateOtherPubKeyErr, ValidatePaymentFut, ValidatePaymentInput,
+            ValidateWatcherSpendInput, VerificationError, VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps,
+            WatcherSearchForSwapTxSpendInput, WatcherValidatePaymentInput, WatcherValidateTakerFeeInput, WeakSpawner,
+            WithdrawError, WithdrawFee, WithdrawFut, WithdrawRequest};
 use async_trait::async_trait;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
@@ -38,8 +40,8 @@ use futures::{FutureExt, TryFutureExt};
 use futures01::Future;
 use hex::FromHexError;
 use keys::hash::H160;
-use keys::{AddressHashEnum, CashAddrType, CashAddress, CompactSignature, KeyPair, NetworkPrefix as CashAddrPrefix,
-           Public};
+use keys::{Address, AddressHashEnum, CashAddrType, CashAddress, CompactSignature, KeyPair,
+           NetworkPrefix as CashAddrPrefix, Public, Signature};
 use mm2_core::mm_ctx::MmArc;
 use mm2_err_handle::prelude::*;
 use mm2_number::{BigDecimal, MmNumber};
@@ -56,8 +58,14 @@ use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::sync::Arc;
 use utxo_signer::with_key_pair::{p2pkh_spend, p2sh_spend, sign_tx, UtxoSignWithKeyPairError};
 
+#[cfg(test)] use mocktopus::macros::*;
+
+mod swap_v2;
+
 const SLP_SWAP_VOUT: usize = 1;
 const SLP_FEE_VOUT: usize = 1;
+/// Index of the output receiving SLP tokens in HTLC spend and refund transactions.
+const SLP_HTLC_SPEND_VOUT: usize = 1;
 const SLP_HTLC_SPEND_SIZE: u64 = 555;
 const SLP_LOKAD_ID: &str = "SLP\x00";
 const SLP_FUNGIBLE: u8 = 1;
@@ -296,6 +304,16 @@ pub struct SlpProtocolConf {
     pub required_confirmations: Option<u64>,
 }
 
+#[cfg_attr(test, mockable)]
+impl SlpToken {
+    /// Returns unspents of the SLP token plus plain BCH UTXOs plus RecentlySpentOutPoints mutex guard
+    async fn slp_unspents_for_spend(
+        &self,
+    ) -> UtxoRpcResult<(Vec<SlpUnspent>, Vec<UnspentInfo>, RecentlySpentOutPointsGuard<'_>)> {
+        self.platform_coin.get_token_utxos_for_spend(&self.conf.token_id).await
+    }
+}
+
 impl SlpToken {
     pub fn new(
         decimals: u8,
@@ -325,13 +343,6 @@ impl SlpToken {
 
     fn rpc(&self) -> &UtxoRpcClientEnum { &self.platform_coin.as_ref().rpc_client }
 
-    /// Returns unspents of the SLP token plus plain BCH UTXOs plus RecentlySpentOutPoints mutex guard
-    async fn slp_unspents_for_spend(
-        &self,
-    ) -> UtxoRpcResult<(Vec<SlpUnspent>, Vec<UnspentInfo>, RecentlySpentOutPointsGuard<'_>)> {
-        self.platform_coin.get_token_utxos_for_spend(&self.conf.token_id).await
-    }
-
     async fn slp_unspents_for_display(&self) -> UtxoRpcResult<(Vec<SlpUnspent>, Vec<UnspentInfo>)> {
         self.platform_coin
             .get_token_utxos_for_display(&self.conf.token_id)
@@ -519,25 +530,14 @@ impl SlpToken {
         .await
     }
 
-    pub async fn refund_htlc(
-        &self,
-        htlc_tx: &[u8],
-        other_pub: &Public,
-        time_lock: u32,
-        secret_hash: &[u8],
-        htlc_keypair: &KeyPair,
-    ) -> Result<UtxoTx, MmError<SpendHtlcError>> {
+    /// Returns the SLP UTXO locked at the `SLP_SWAP_VOUT` output of the `htlc_tx`.
+    fn htlc_slp_unspent(&self, htlc_tx: &[u8]) -> Result<SlpUnspent, MmError<SpendHtlcError>> {
         let tx: UtxoTx = deserialize(htlc_tx)?;
-        if tx.outputs.is_empty() {
+        if tx.outputs.len() <= SLP_SWAP_VOUT {
             return MmError::err(SpendHtlcError::TxLackOfOutputs);
         }
 
         let slp_tx: SlpTxDetails = parse_slp_script(tx.outputs[0].script_pubkey.as_slice())?;
-
-        let other_pub = Public::from_slice(other_pub)?;
-        let my_public_key = self.platform_coin.my_public_key()?;
-        let redeem_script = payment_script(time_lock, secret_hash, my_public_key, &other_pub);
-
         let slp_amount = match slp_tx.transaction {
             SlpTransaction::Send { token_id, amounts } => {
                 if token_id != self.token_id() {
@@ -547,7 +547,7 @@ impl SlpToken {
             },
             _ => return MmError::err(SpendHtlcError::InvalidSlpDetails),
         };
-        let slp_utxo = SlpUnspent {
+        Ok(SlpUnspent {
             bch_unspent: UnspentInfo {
                 outpoint: OutPoint {
                     hash: tx.hash(),
@@ -558,7 +558,22 @@ impl SlpToken {
                 script: tx.outputs[SLP_SWAP_VOUT].script_pubkey.clone().into(),
             },
             slp_amount,
-        };
+        })
+    }
+
+    pub async fn refund_htlc(
+        &self,
+        htlc_tx: &[u8],
+        other_pub: &Public,
+        time_lock: u32,
+        secret_hash: &[u8],
+        htlc_keypair: &KeyPair,
+    ) -> Result<UtxoTx, MmError<SpendHtlcError>> {
+        let slp_utxo = self.htlc_slp_unspent(htlc_tx)?;
+
+        let other_pub = Public::from_slice(other_pub)?;
+        let my_public_key = self.platform_coin.my_public_key()?;
+        let redeem_script = payment_script(time_lock, secret_hash, my_public_key, &other_pub);
 
         let tx_locktime = self.platform_coin.p2sh_tx_locktime(time_lock).await?;
         let script_data = ScriptBuilder::default().push_opcode(Opcode::OP_1).into_script();
@@ -584,34 +599,11 @@ impl SlpToken {
         secret_hash: &[u8],
         keypair: &KeyPair,
     ) -> Result<UtxoTx, MmError<SpendHtlcError>> {
-        let tx: UtxoTx = deserialize(htlc_tx)?;
-        let slp_tx: SlpTxDetails = deserialize(tx.outputs[0].script_pubkey.as_slice())?;
+        let slp_utxo = self.htlc_slp_unspent(htlc_tx)?;
 
         let other_pub = Public::from_slice(other_pub)?;
         let redeem = payment_script(time_lock, secret_hash, &other_pub, keypair.public());
 
-        let slp_amount = match slp_tx.transaction {
-            SlpTransaction::Send { token_id, amounts } => {
-                if token_id != self.token_id() {
-                    return MmError::err(SpendHtlcError::InvalidSlpDetails);
-                }
-                *amounts.first().ok_or(SpendHtlcError::InvalidSlpDetails)?
-            },
-            _ => return MmError::err(SpendHtlcError::InvalidSlpDetails),
-        };
-        let slp_utxo = SlpUnspent {
-            bch_unspent: UnspentInfo {
-                outpoint: OutPoint {
-                    hash: tx.hash(),
-                    index: SLP_SWAP_VOUT as u32,
-                },
-                value: tx.outputs[SLP_SWAP_VOUT].value,
-                height: None,
-                script: tx.outputs[SLP_SWAP_VOUT].script_pubkey.clone().into(),
-            },
-            slp_amount,
-        };
-
         let tx_locktime = self.platform_coin.p2sh_tx_locktime(time_lock).await?;
         let script_data = ScriptBuilder::default()
             .push_data(secret)
@@ -623,6 +615,61 @@ impl SlpToken {
         Ok(tx)
     }
 
+    /// Generates the maker payment spend signed by taker without the secret.
+    /// Watcher inserts the secret into the script sig once it's revealed,
+    /// see [`utxo_common::maker_payment_spend_preimage_with_secret`].
+    ///
+    /// The maker payment BCH value is dust, so the preimage also spends the BCH UTXOs of my address to pay the fee.
+    /// It becomes invalid if any of them is spent before the watcher broadcasts it.
+    pub async fn maker_payment_spend_preimage(
+        &self,
+        htlc_tx: &[u8],
+        maker_pub: &Public,
+        time_lock: u32,
+        secret_hash: &[u8],
+        htlc_keypair: &KeyPair,
+    ) -> Result<UtxoTx, MmError<SpendHtlcError>> {
+        let slp_utxo = self.htlc_slp_unspent(htlc_tx)?;
+        let redeem_script = payment_script(time_lock, secret_hash, maker_pub, htlc_keypair.public());
+        let tx = self
+            .sign_p2sh_spend(
+                slp_utxo,
+                time_lock,
+                SEQUENCE_FINAL,
+                ScriptBuilder::default().into_script(),
+                redeem_script,
+                htlc_keypair,
+            )
+            .await?;
+        Ok(tx)
+    }
+
+    /// Generates the taker payment refund that can be broadcast by watcher after the `time_lock`.
+    /// Like [`SlpToken::maker_payment_spend_preimage`], it also spends the BCH UTXOs of my address to pay the fee.
+    pub async fn taker_payment_refund_preimage(
+        &self,
+        htlc_tx: &[u8],
+        maker_pub: &Public,
+        time_lock: u32,
+        secret_hash: &[u8],
+        htlc_keypair: &KeyPair,
+    ) -> Result<UtxoTx, MmError<SpendHtlcError>> {
+        let slp_utxo = self.htlc_slp_unspent(htlc_tx)?;
+        let redeem_script = payment_script(time_lock, secret_hash, htlc_keypair.public(), maker_pub);
+        let script_data = ScriptBuilder::default().push_opcode(Opcode::OP_1).into_script();
+        let tx = self
+            .sign_p2sh_spend(
+                slp_utxo,
+                time_lock,
+                SEQUENCE_FINAL - 1,
+                script_data,
+                redeem_script,
+                htlc_keypair,
+            )
+            .await?;
+        Ok(tx)
+    }
+
     pub async fn spend_p2sh(
         &self,
         p2sh_utxo: SlpUnspent,
@@ -631,6 +678,36 @@ impl SlpToken {
         script_data: Script,
         redeem_script: Script,
         htlc_keypair: &KeyPair,
+    ) -> Result<UtxoTx, MmError<SpendP2SHError>> {
+        let signed = self
+            .sign_p2sh_spend(
+                p2sh_utxo,
+                tx_locktime,
+                input_sequence,
+                script_data,
+                redeem_script,
+                htlc_keypair,
+            )
+            .await?;
+
+        let _broadcast = self
+            .rpc()
+            .send_raw_transaction(serialize(&signed).into())
+            .compat()
+            .await?;
+        Ok(signed)
+    }
+
+    /// Generates and signs the transaction spending the SLP `p2sh_utxo` to my address.
+    /// BCH inputs of my address are added to cover the miner fee.
+    async fn sign_p2sh_spend(
+        &self,
+        p2sh_utxo: SlpUnspent,
+        tx_locktime: u32,
+        input_sequence: u32,
+        script_data: Script,
+        redeem_script: Script,
+        htlc_keypair: &KeyPair,
     ) -> Result<UtxoTx, MmError<SpendP2SHError>> {
         let op_return_out_mm = self.send_op_return_output(&[p2sh_utxo.slp_amount]);
         let mut outputs = Vec::with_capacity(3);
@@ -709,12 +786,6 @@ impl SlpToken {
             tx_hash_algo: self.platform_coin.as_ref().tx_hash_algo,
             v_extra_payload: None,
         };
-
-        let _broadcast = self
-            .rpc()
-            .send_raw_transaction(serialize(&signed).into())
-            .compat()
-            .await?;
         Ok(signed)
     }
 
@@ -1426,10 +1497,159 @@ impl SwapOps for SlpToken {
     fn validate_other_pubkey(&self, raw_pubkey: &[u8]) -> MmResult<(), ValidateOtherPubKeyErr> {
         utxo_common::validate_other_pubkey(raw_pubkey)
     }
+
+    fn is_supported_by_watchers(&self) -> bool { true }
+}
+
+#[async_trait]
+impl WatcherOps for SlpToken {
+    fn create_maker_payment_spend_preimage(
+        &self,
+        maker_payment_tx: &[u8],
+        time_lock: u64,
+        maker_pub: &[u8],
+        secret_hash: &[u8],
+        swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        let tx = maker_payment_tx.to_owned();
+        let maker_pub = try_tx_fus!(Public::from_slice(maker_pub));
+        let time_lock = try_tx_fus!(time_lock.try_into());
+        let secret_hash = secret_hash.to_owned();
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let coin = self.clone();
+        let fut = async move {
+            let tx = try_tx_s!(
+                coin.maker_payment_spend_preimage(&tx, &maker_pub, time_lock, &secret_hash, &htlc_keypair)
+                    .await
+            );
+            Ok(tx.into())
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    fn create_taker_payment_refund_preimage(
+        &self,
+        taker_payment_tx: &[u8],
+        time_lock: u64,
+        maker_pub: &[u8],
+        secret_hash: &[u8],
+        _swap_contract_address: &Option<BytesJson>,
+        swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        let tx = taker_payment_tx.to_owned();
+        let maker_pub = try_tx_fus!(Public::from_slice(maker_pub));
+        let time_lock = try_tx_fus!(time_lock.try_into());
+        let secret_hash = secret_hash.to_owned();
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let coin = self.clone();
+        let fut = async move {
+            let tx = try_tx_s!(
+                coin.taker_payment_refund_preimage(&tx, &maker_pub, time_lock, &secret_hash, &htlc_keypair)
+                    .await
+            );
+            Ok(tx.into())
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    #[inline]
+    fn send_maker_payment_spend_preimage(&self, input: SendMakerPaymentSpendPreimageInput) -> TransactionFut {
+        utxo_common::send_maker_payment_spend_preimage(&self.platform_coin, input)
+    }
+
+    #[inline]
+    fn send_taker_payment_refund_preimage(&self, watcher_refunds_payment_args: RefundPaymentArgs) -> TransactionFut {
+        utxo_common::send_taker_payment_refund_preimage(&self.platform_coin, watcher_refunds_payment_args)
+    }
+
+    #[inline]
+    fn watcher_validate_taker_fee(&self, input: WatcherValidateTakerFeeInput) -> ValidatePaymentFut<()> {
+        utxo_common::watcher_validate_taker_fee(&self.platform_coin, input, SLP_FEE_VOUT)
+    }
+
+    #[inline]
+    fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
+        utxo_common::watcher_validate_taker_payment(&self.platform_coin, input, SLP_SWAP_VOUT)
+    }
+
+    #[inline]
+    fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let validate_spend_fut =
+            utxo_common::validate_payment_spend_or_refund(&self.platform_coin, input.clone(), SLP_HTLC_SPEND_VOUT);
+        let fut = async move {
+            validate_spend_fut.compat().await?;
+
+            let tx: UtxoTx = deserialize(input.payment_tx.as_slice())?;
+            let op_return = tx
+                .outputs
+                .first()
+                .or_mm_err(|| ValidatePaymentError::WrongPaymentTx("Payment spend tx has no outputs".to_string()))?;
+            let slp_tx: SlpTxDetails = parse_slp_script(&op_return.script_pubkey)
+                .map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?;
+            let expected_amount = sat_from_big_decimal(&input.amount, coin.decimals())?;
+            match slp_tx.transaction {
+                SlpTransaction::Send { token_id, amounts }
+                    if token_id == coin.token_id() && amounts.first() == Some(&expected_amount) =>
+                {
+                    Ok(())
+                },
+                transaction => MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                    "Invalid SLP details {:?}, expected {} of token {}",
+                    transaction,
+                    expected_amount,
+                    coin.token_id()
+                ))),
+            }
+        };
+        Box::new(fut.boxed().compat())
+    }
+
+    #[inline]
+    async fn watcher_search_for_swap_tx_spend(
+        &self,
+        input: WatcherSearchForSwapTxSpendInput<'_>,
+    ) -> Result<Option<FoundSwapTxSpend>, String> {
+        utxo_common::watcher_search_for_swap_tx_spend(&self.platform_coin, input, SLP_SWAP_VOUT).await
+    }
 }
 
 #[async_trait]
-impl WatcherOps for SlpToken {}
+impl ParseCoinAssocTypes for SlpToken {
+    type Address = Address;
+    type AddressParseError = MmError<AddrFromStrError>;
+    type Pubkey = Public;
+    type PubkeyParseError = MmError<keys::Error>;
+    type Tx = UtxoTx;
+    type TxParseError = MmError<SerError>;
+    type Preimage = UtxoTx;
+    type PreimageParseError = MmError<SerError>;
+    type Sig = Signature;
+    type SigParseError = MmError<secp256k1::Error>;
+
+    async fn my_addr(&self) -> Self::Address { self.platform_coin.my_addr().await }
+
+    /// SLP addresses are CashAddresses with the SLP prefix, so the network prefix is not checked here.
+    fn parse_address(&self, address: &str) -> Result<Self::Address, Self::AddressParseError> {
+        utxo_common::address_from_str_unchecked(self.as_ref(), address)
+    }
+
+    #[inline]
+    fn parse_pubkey(&self, pubkey: &[u8]) -> Result<Self::Pubkey, Self::PubkeyParseError> {
+        self.platform_coin.parse_pubkey(pubkey)
+    }
+
+    #[inline]
+    fn parse_tx(&self, tx: &[u8]) -> Result<Self::Tx, Self::TxParseError> { self.platform_coin.parse_tx(tx) }
+
+    #[inline]
+    fn parse_preimage(&self, tx: &[u8]) -> Result<Self::Preimage, Self::PreimageParseError> { self.parse_tx(tx) }
+
+    #[inline]
+    fn parse_signature(&self, sig: &[u8]) -> Result<Self::Sig, Self::SigParseError> {
+        self.platform_coin.parse_signature(sig)
+    }
+}
 
 impl From<GenSlpSpendErr> for TradePreimageError {
     fn from(slp: GenSlpSpendErr) -> TradePreimageError {
@@ -1492,6 +1712,7 @@ impl MmCoin for SlpToken {
                     "Withdraw from a specific address is not supported for slp yet".to_owned(),
                 ));
             }
+            req.ensure_no_utxo_specific_fields(coin.ticker())?;
 
             let key_pair = coin.platform_coin.as_ref().priv_key_policy.activated_key_or_err()?;
 
@@ -2189,4 +2410,237 @@ mod slp_tests {
             .unwrap();
         assert!(is_valid);
     }
+
+    fn slp_swap_v2_test_tx(token_id: &H256, slp_amount: u64, value: u64, redeem_script: &Script) -> UtxoTx {
+        UtxoTx {
+            outputs: vec![slp_send_output(token_id, &[slp_amount]), TransactionOutput {
+                value,
+                script_pubkey: ScriptBuilder::build_p2sh(&dhash160(redeem_script).into()).to_bytes(),
+            }],
+            ..UtxoTx::default()
+        }
+    }
+
+    #[test]
+    fn test_gen_taker_funding_spend_preimage_v2() {
+        use crate::utxo::swap_proto_v2_scripts;
+        use crate::GenTakerFundingSpendArgs;
+        use crypto::privkey::key_pair_from_seed;
+
+        let (_ctx, bch) = tbch_coin_for_test();
+        let token_id = H256::from("bb309e48930671582bea508f9a1d9b491e49b69be3d6f372dc08da2ac6e90eb7");
+        let fusd = SlpToken::new(4, "FUSD".into(), token_id, bch, 0).unwrap();
+        let taker_pub = *fusd.derive_htlc_key_pair(&[]).public();
+        let maker_pub = *key_pair_from_seed("SLP swap v2 maker").unwrap().public();
+
+        let funding_script = swap_proto_v2_scripts::taker_funding_script(1000, &[1; 20], &taker_pub, &maker_pub);
+        let funding_tx = slp_swap_v2_test_tx(&token_id, 10000, 5000, &funding_script);
+        let args = GenTakerFundingSpendArgs {
+            funding_tx: &funding_tx,
+            maker_pub: &maker_pub,
+            taker_pub: &taker_pub,
+            funding_time_lock: 1000,
+            taker_secret_hash: &[1; 20],
+            taker_payment_time_lock: 2000,
+            maker_secret_hash: &[2; 20],
+        };
+        let preimage = fusd.gen_taker_funding_spend_preimage_with_fee(&args, 1000).unwrap();
+
+        assert_eq!(preimage.inputs.len(), 1);
+        assert_eq!(preimage.inputs[0].previous_output.hash, funding_tx.hash());
+        assert_eq!(preimage.inputs[0].previous_output.index, SLP_SWAP_VOUT as u32);
+        assert_eq!(preimage.outputs.len(), 2);
+
+        let slp_data = parse_slp_script(&preimage.outputs[0].script_pubkey).unwrap();
+        assert_eq!(slp_data.transaction, SlpTransaction::Send {
+            token_id,
+            amounts: vec![10000],
+        });
+
+        let payment_script = swap_proto_v2_scripts::taker_payment_script(2000, &[2; 20], &taker_pub, &maker_pub);
+        let expected_script_pubkey = ScriptBuilder::build_p2sh(&dhash160(&payment_script).into()).to_bytes();
+        assert_eq!(preimage.outputs[1].script_pubkey, expected_script_pubkey);
+        assert_eq!(preimage.outputs[1].value, 4000);
+
+        // the fee must leave at least dust in the taker payment output
+        let err = fusd
+            .gen_taker_funding_spend_preimage_with_fee(&args, 5000)
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, TxGenError::TxFeeTooHigh(_)));
+    }
+
+    #[test]
+    fn test_gen_taker_payment_spend_preimage_v2() {
+        use crate::utxo::{output_script, swap_proto_v2_scripts};
+        use crate::{DexFeeBurnDestination, GenTakerPaymentSpendArgs, ParseCoinAssocTypes};
+        use crypto::privkey::key_pair_from_seed;
+
+        let (_ctx, bch) = tbch_coin_for_test();
+        let token_id = H256::from("bb309e48930671582bea508f9a1d9b491e49b69be3d6f372dc08da2ac6e90eb7");
+        let fusd = SlpToken::new(4, "FUSD".into(), token_id, bch, 0).unwrap();
+        let taker_pub = *fusd.derive_htlc_key_pair(&[]).public();
+        let maker_pub = *key_pair_from_seed("SLP swap v2 maker").unwrap().public();
+        let maker_address = block_on(fusd.my_addr());
+
+        let payment_script = swap_proto_v2_scripts::taker_payment_script(2000, &[2; 20], &taker_pub, &maker_pub);
+        let payment_tx = slp_swap_v2_test_tx(&token_id, 10000, 5000, &payment_script);
+        let dex_fee = DexFee::Standard("0.01".into());
+        let mut args = GenTakerPaymentSpendArgs {
+            taker_tx: &payment_tx,
+            time_lock: 2000,
+            maker_secret_hash: &[2; 20],
+            maker_pub: &maker_pub,
+            maker_address: &maker_address,
+            taker_pub: &taker_pub,
+            dex_fee: &dex_fee,
+            premium_amount: 0.into(),
+            trading_amount: 0.into(),
+        };
+        let preimage = fusd.gen_taker_payment_spend_preimage_with_fee(&args, 1000).unwrap();
+
+        assert_eq!(preimage.inputs[0].previous_output.index, SLP_SWAP_VOUT as u32);
+        assert_eq!(preimage.outputs.len(), 3);
+        let slp_data = parse_slp_script(&preimage.outputs[0].script_pubkey).unwrap();
+        assert_eq!(slp_data.transaction, SlpTransaction::Send {
+            token_id,
+            amounts: vec![100, 9900],
+        });
+        let dex_pub = Public::from_slice(fusd.dex_pubkey()).unwrap();
+        let expected_dex_script = ScriptBuilder::build_p2pkh(&dex_pub.address_hash().into()).to_bytes();
+        assert_eq!(preimage.outputs[1].script_pubkey, expected_dex_script);
+        assert_eq!(preimage.outputs[1].value, fusd.platform_dust());
+        let expected_maker_script = output_script(&maker_address).unwrap().to_bytes();
+        assert_eq!(preimage.outputs[2].script_pubkey, expected_maker_script);
+        assert_eq!(preimage.outputs[2].value, 5000 - 1000 - fusd.platform_dust());
+
+        let no_fee = DexFee::NoFee;
+        args.dex_fee = &no_fee;
+        let preimage = fusd.gen_taker_payment_spend_preimage_with_fee(&args, 1000).unwrap();
+        assert_eq!(preimage.outputs.len(), 2);
+        let slp_data = parse_slp_script(&preimage.outputs[0].script_pubkey).unwrap();
+        assert_eq!(slp_data.transaction, SlpTransaction::Send {
+            token_id,
+            amounts: vec![10000],
+        });
+        assert_eq!(preimage.outputs[1].value, 4000);
+
+        let with_burn = DexFee::WithBurn {
+            fee_amount: "0.01".into(),
+            burn_amount: "0.01".into(),
+            burn_destination: DexFeeBurnDestination::PreBurnAccount,
+        };
+        args.dex_fee = &with_burn;
+        fusd.gen_taker_payment_spend_preimage_with_fee(&args, 1000).unwrap_err();
+    }
+
+    #[test]
+    fn test_watcher_preimages() {
+        use crypto::privkey::key_pair_from_seed;
+
+        let (_ctx, bch) = tbch_coin_for_test();
+        let token_id = H256::from("bb309e48930671582bea508f9a1d9b491e49b69be3d6f372dc08da2ac6e90eb7");
+        let fusd = SlpToken::new(4, "FUSD".into(), token_id, bch, 0).unwrap();
+        let htlc_keypair = fusd.derive_htlc_key_pair(&[]);
+        let maker_pub = *key_pair_from_seed("SLP swap v2 maker").unwrap().public();
+        let my_pub = *fusd.platform_coin.my_public_key().unwrap();
+        let my_script_pubkey = ScriptBuilder::build_p2pkh(&my_pub.address_hash().into());
+
+        // the preimages spend a BCH UTXO of my address to pay the fee
+        let fee_utxo = UnspentInfo {
+            outpoint: OutPoint {
+                hash: 1.into(),
+                index: 0,
+            },
+            value: 100000,
+            height: None,
+            script: my_script_pubkey.clone(),
+        };
+        SlpToken::slp_unspents_for_spend.mock_safe(move |token| {
+            let fee_utxo = fee_utxo.clone();
+            MockResult::Return(Box::pin(async move {
+                let recently_spent = token.platform_coin.as_ref().recently_spent_outpoints.lock().await;
+                Ok((Vec::new(), vec![fee_utxo], recently_spent))
+            }))
+        });
+        BchCoin::get_tx_fee
+            .mock_safe(|_| MockResult::Return(Box::pin(futures::future::ok(ActualTxFee::FixedPerKb(1000)))));
+
+        let time_lock = 1000;
+        let secret = [1; 32];
+        let secret_hash = dhash160(&secret);
+
+        let maker_payment_script = payment_script(time_lock, secret_hash.as_slice(), &maker_pub, htlc_keypair.public());
+        let maker_payment = slp_swap_v2_test_tx(&token_id, 10000, 5000, &maker_payment_script);
+        let preimage = block_on(fusd.maker_payment_spend_preimage(
+            &serialize(&maker_payment),
+            &maker_pub,
+            time_lock,
+            secret_hash.as_slice(),
+            &htlc_keypair,
+        ))
+        .unwrap();
+
+        assert_eq!(preimage.lock_time, time_lock);
+        assert_eq!(preimage.inputs.len(), 2);
+        assert_eq!(preimage.inputs[0].previous_output, OutPoint {
+            hash: maker_payment.hash(),
+            index: SLP_SWAP_VOUT as u32,
+        });
+        assert_eq!(preimage.inputs[0].sequence, SEQUENCE_FINAL);
+        assert_eq!(preimage.inputs[1].previous_output.hash, 1.into());
+        let slp_data = parse_slp_script(&preimage.outputs[0].script_pubkey).unwrap();
+        assert_eq!(slp_data.transaction, SlpTransaction::Send {
+            token_id,
+            amounts: vec![10000],
+        });
+        assert_eq!(
+            preimage.outputs[SLP_HTLC_SPEND_VOUT].script_pubkey,
+            my_script_pubkey.to_bytes()
+        );
+        assert_eq!(preimage.outputs[SLP_HTLC_SPEND_VOUT].value, fusd.platform_dust());
+
+        // the watcher inserts the secret once it's revealed
+        let spend = utxo_common::maker_payment_spend_preimage_with_secret(&serialize(&preimage), &secret).unwrap();
+        let script_sig = Script::from(spend.inputs[0].script_sig.clone());
+        let instructions: Vec<_> = script_sig.iter().map(|instruction| instruction.unwrap()).collect();
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[1].data, Some(&secret[..]));
+        assert_eq!(instructions[2].opcode, Opcode::OP_0);
+        assert_eq!(instructions[3].data, Some(&maker_payment_script[..]));
+
+        let taker_payment_script = payment_script(time_lock, secret_hash.as_slice(), htlc_keypair.public(), &maker_pub);
+        let taker_payment = slp_swap_v2_test_tx(&token_id, 10000, 5000, &taker_payment_script);
+        let refund = block_on(fusd.taker_payment_refund_preimage(
+            &serialize(&taker_payment),
+            &maker_pub,
+            time_lock,
+            secret_hash.as_slice(),
+            &htlc_keypair,
+        ))
+        .unwrap();
+
+        assert_eq!(refund.lock_time, time_lock);
+        assert_eq!(refund.inputs[0].previous_output.hash, taker_payment.hash());
+        // the sequence must be non-final for the lock time to be enforced
+        assert_eq!(refund.inputs[0].sequence, SEQUENCE_FINAL - 1);
+        let script_sig = Script::from(refund.inputs[0].script_sig.clone());
+        let instructions: Vec<_> = script_sig.iter().map(|instruction| instruction.unwrap()).collect();
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[1].opcode, Opcode::OP_1);
+        assert_eq!(instructions[2].data, Some(&taker_payment_script[..]));
+
+        // the payment must carry the SLP token of the coin
+        let other_token_payment = slp_swap_v2_test_tx(&H256::from([2; 32]), 10000, 5000, &taker_payment_script);
+        let err = block_on(fusd.taker_payment_refund_preimage(
+            &serialize(&other_token_payment),
+            &maker_pub,
+            time_lock,
+            secret_hash.as_slice(),
+            &htlc_keypair,
+        ))
+        .unwrap_err()
+        .into_inner();
+        assert!(matches!(err, SpendHtlcError::InvalidSlpDetails), "{:?}", err);
+    }
 }
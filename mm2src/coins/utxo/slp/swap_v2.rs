@@ -0,0 +1,945 @@
+//! Trading protocol upgrade (swap v2) implementation for SLP tokens.
+//! The SLP amount is always kept at `SLP_SWAP_VOUT` output of every swap transaction,
+//! while the OP_RETURN at the first output describes the token amounts of the following outputs.
+//! BCH value of the taker funding covers miner fees of both the funding and the taker payment spends,
+//! so these transactions are built from a single P2SH input without adding any other inputs.
+
+use super::{parse_slp_script, slp_send_output, SlpOutput, SlpToken, SlpTransaction, SlpTxDetails, SlpUnspent,
+            SLP_HTLC_SPEND_SIZE, SLP_SWAP_VOUT};
+use crate::coin_errors::{ValidatePaymentError, ValidatePaymentResult};
+use crate::utxo::bchd_grpc::validate_slp_utxos;
+use crate::utxo::rpc_clients::{UnspentInfo, UtxoRpcClientEnum};
+use crate::utxo::swap_proto_v2_scripts;
+use crate::utxo::utxo_common::{self, DEFAULT_SWAP_VOUT};
+use crate::utxo::{generate_and_send_tx, output_script, sat_from_big_decimal, FeePolicy, UtxoCommonOps, UtxoTx,
+                  UtxoTxBroadcastOps};
+use crate::{CommonSwapOpsV2, DexFee, FeeApproxStage, FindPaymentSpendError, FundingTxSpend, GenPreimageResult,
+            GenTakerFundingSpendArgs, GenTakerPaymentSpendArgs, MakerCoinSwapOpsV2, ParseCoinAssocTypes,
+            RefundFundingSecretArgs, RefundMakerPaymentSecretArgs, RefundMakerPaymentTimelockArgs,
+            RefundTakerPaymentArgs, SearchForFundingSpendErr, SendMakerPaymentArgs, SendTakerFundingArgs,
+            SpendMakerPaymentArgs, SwapOps, SwapTxTypeWithSecretHash, TakerCoinSwapOpsV2, ToBytes, TransactionErr,
+            TxGenError, TxPreimageWithSig, ValidateMakerPaymentArgs, ValidateSwapV2TxError, ValidateSwapV2TxResult,
+            ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageError, ValidateTakerFundingSpendPreimageResult,
+            ValidateTakerPaymentSpendPreimageError, ValidateTakerPaymentSpendPreimageResult};
+use async_trait::async_trait;
+use bitcrypto::dhash160;
+use chain::constants::SEQUENCE_FINAL;
+use chain::{OutPoint, TransactionOutput};
+use futures::compat::Future01CompatExt;
+use keys::{AddressBuilder, AddressFormat, KeyPair, Public};
+use mm2_err_handle::prelude::*;
+use script::{Builder as ScriptBuilder, Opcode, Script, TransactionInputSigner, UnsignedTransactionInput};
+use serialization::serialize;
+use std::convert::TryInto;
+use std::num::TryFromIntError;
+use utxo_signer::with_key_pair::{calc_and_sign_sighash, signature_hash_to_sign, SIGHASH_ALL};
+
+impl SlpToken {
+    /// Returns the SLP amount locked at `SLP_SWAP_VOUT` output of the swap `tx`.
+    fn swap_tx_slp_amount(&self, tx: &UtxoTx) -> Result<u64, String> {
+        let op_return = tx.outputs.first().ok_or("Tx has no outputs")?;
+        let slp_tx: SlpTxDetails = parse_slp_script(&op_return.script_pubkey).map_err(|e| e.to_string())?;
+        match slp_tx.transaction {
+            SlpTransaction::Send { token_id, amounts } => {
+                if token_id != *self.token_id() {
+                    return Err(format!(
+                        "Unexpected token_id {}, expected {}",
+                        token_id,
+                        self.token_id()
+                    ));
+                }
+                amounts
+                    .first()
+                    .copied()
+                    .ok_or_else(|| "SLP amounts can't be empty".to_string())
+            },
+            _ => Err("Expected SLP Send transaction".into()),
+        }
+    }
+
+    /// Returns the SLP UTXO locked at `SLP_SWAP_VOUT` output of the swap `tx`.
+    fn swap_tx_slp_unspent(&self, tx: &UtxoTx) -> Result<SlpUnspent, String> {
+        let slp_amount = self.swap_tx_slp_amount(tx)?;
+        let output = tx
+            .outputs
+            .get(SLP_SWAP_VOUT)
+            .ok_or_else(|| format!("Tx has no output with index {}", SLP_SWAP_VOUT))?;
+        Ok(SlpUnspent {
+            bch_unspent: UnspentInfo {
+                outpoint: OutPoint {
+                    hash: tx.hash(),
+                    index: SLP_SWAP_VOUT as u32,
+                },
+                value: output.value,
+                height: None,
+                script: output.script_pubkey.clone().into(),
+            },
+            slp_amount,
+        })
+    }
+
+    /// BCH value locked at the taker funding output.
+    /// It covers the dust outputs of the taker payment spend and miner fees of both funding and payment spends.
+    async fn taker_funding_bch_value(&self) -> Result<u64, String> {
+        let htlc_spend_fee = self
+            .platform_coin
+            .get_htlc_spend_fee(SLP_HTLC_SPEND_SIZE, &FeeApproxStage::WithoutApprox)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(2 * self.platform_dust() + 2 * htlc_spend_fee)
+    }
+
+    /// Sends SLP `amount` to the P2SH address of the `redeem_script` with the given BCH `value`.
+    async fn send_slp_to_p2sh(
+        &self,
+        redeem_script: &Script,
+        amount: u64,
+        value: u64,
+    ) -> Result<UtxoTx, TransactionErr> {
+        let script_pubkey = ScriptBuilder::build_p2sh(&dhash160(redeem_script).into()).to_bytes();
+        let slp_out = SlpOutput { amount, script_pubkey };
+        let (mut preimage, recently_spent) = try_tx_s!(self.generate_slp_tx_preimage(vec![slp_out]).await);
+        preimage.outputs[SLP_SWAP_VOUT].value = value;
+
+        try_tx_s!(self.import_p2sh_address_if_native(redeem_script).await);
+        generate_and_send_tx(
+            self,
+            preimage.available_bch_inputs,
+            Some(preimage.slp_inputs.into_iter().map(|slp| slp.bch_unspent).collect()),
+            FeePolicy::SendExact,
+            recently_spent,
+            preimage.outputs,
+        )
+        .await
+    }
+
+    /// Imports the P2SH address of the `redeem_script` in native mode to track the output spend.
+    async fn import_p2sh_address_if_native(&self, redeem_script: &Script) -> Result<(), String> {
+        if let UtxoRpcClientEnum::Native(client) = self.rpc() {
+            let conf = self.platform_conf();
+            let address = AddressBuilder::new(
+                AddressFormat::Standard,
+                conf.checksum_type,
+                conf.address_prefixes.clone(),
+                conf.bech32_hrp.clone(),
+            )
+            .as_sh(dhash160(redeem_script).into())
+            .build()?;
+            let addr_string = address.to_string();
+            client
+                .import_address(&addr_string, &addr_string, false)
+                .compat()
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Spends the SLP locked at the swap `tx` P2SH output back to my address, e.g. refunds or maker payment spend.
+    async fn spend_swap_p2sh(
+        &self,
+        tx: &UtxoTx,
+        time_lock: u64,
+        input_sequence: u32,
+        script_data: Script,
+        redeem_script: Script,
+        htlc_keypair: &KeyPair,
+    ) -> Result<UtxoTx, TransactionErr> {
+        let p2sh_utxo = try_tx_s!(self.swap_tx_slp_unspent(tx));
+        let tx_locktime = try_tx_s!(
+            self.platform_coin
+                .p2sh_tx_locktime(try_tx_s!(time_lock.try_into()))
+                .await
+        );
+        let tx = try_tx_s!(
+            self.spend_p2sh(
+                p2sh_utxo,
+                tx_locktime,
+                input_sequence,
+                script_data,
+                redeem_script,
+                htlc_keypair
+            )
+            .await
+        );
+        Ok(tx)
+    }
+
+    /// Refunds the swap `payment_tx` using the timelock path.
+    async fn refund_swap_tx_v2_timelock(
+        &self,
+        payment_tx: &[u8],
+        time_lock: u64,
+        other_pub: &[u8],
+        tx_type_with_secret_hash: SwapTxTypeWithSecretHash<'_>,
+        swap_unique_data: &[u8],
+    ) -> Result<UtxoTx, TransactionErr> {
+        let tx = try_tx_s!(self.parse_tx(payment_tx));
+        let other_pub = try_tx_s!(Public::from_slice(other_pub));
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let redeem_script =
+            tx_type_with_secret_hash.redeem_script(try_tx_s!(time_lock.try_into()), htlc_keypair.public(), &other_pub);
+        let script_data = ScriptBuilder::default().push_opcode(Opcode::OP_1).into_script();
+        self.spend_swap_p2sh(
+            &tx,
+            time_lock,
+            SEQUENCE_FINAL - 1,
+            script_data,
+            redeem_script,
+            &htlc_keypair,
+        )
+        .await
+    }
+
+    /// Builds an unsigned tx spending the `prev_tx` SLP P2SH output to the given `outputs`.
+    fn swap_p2sh_spend_preimage(
+        &self,
+        prev_tx: &UtxoTx,
+        outputs: Vec<TransactionOutput>,
+    ) -> Result<TransactionInputSigner, MmError<TxGenError>> {
+        let amount = prev_tx
+            .outputs
+            .get(SLP_SWAP_VOUT)
+            .or_mm_err(|| TxGenError::PrevTxIsNotValid(format!("Tx has no output with index {}", SLP_SWAP_VOUT)))?
+            .value;
+        let conf = self.platform_conf();
+        Ok(TransactionInputSigner {
+            lock_time: 0,
+            version: conf.tx_version,
+            n_time: None,
+            overwintered: conf.overwintered,
+            inputs: vec![UnsignedTransactionInput {
+                sequence: SEQUENCE_FINAL,
+                previous_output: OutPoint {
+                    hash: prev_tx.hash(),
+                    index: SLP_SWAP_VOUT as u32,
+                },
+                prev_script: Vec::new().into(),
+                amount,
+            }],
+            outputs,
+            expiry_height: 0,
+            join_splits: vec![],
+            shielded_spends: vec![],
+            shielded_outputs: vec![],
+            value_balance: 0,
+            version_group_id: conf.version_group_id,
+            consensus_branch_id: conf.consensus_branch_id,
+            zcash: conf.zcash,
+            posv: conf.is_posv,
+            str_d_zeel: None,
+            hash_algo: self.as_ref().tx_hash_algo.into(),
+            v_extra_payload: None,
+        })
+    }
+
+    /// Generates taker funding spend preimage transferring the funding SLP and BCH to the taker payment P2SH
+    /// with the given miner `fee` subtracted from BCH value.
+    pub(super) fn gen_taker_funding_spend_preimage_with_fee(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        fee: u64,
+    ) -> Result<TransactionInputSigner, MmError<TxGenError>> {
+        let payment_time_lock = args
+            .taker_payment_time_lock
+            .try_into()
+            .map_to_mm(|e: TryFromIntError| TxGenError::LocktimeOverflow(e.to_string()))?;
+        let payment_redeem_script = swap_proto_v2_scripts::taker_payment_script(
+            payment_time_lock,
+            args.maker_secret_hash,
+            args.taker_pub,
+            args.maker_pub,
+        );
+
+        let funding_utxo = self
+            .swap_tx_slp_unspent(args.funding_tx)
+            .map_to_mm(TxGenError::PrevTxIsNotValid)?;
+        let funding_value = funding_utxo.bch_unspent.value;
+        let fee_plus_dust = fee + self.platform_dust();
+        if funding_value < fee_plus_dust {
+            return MmError::err(TxGenError::TxFeeTooHigh(format!(
+                "Fee + dust {} is larger than funding value {}",
+                fee_plus_dust, funding_value
+            )));
+        }
+
+        let outputs = vec![
+            slp_send_output(self.token_id(), &[funding_utxo.slp_amount]),
+            TransactionOutput {
+                value: funding_value - fee,
+                script_pubkey: ScriptBuilder::build_p2sh(&dhash160(&payment_redeem_script).into()).to_bytes(),
+            },
+        ];
+        self.swap_p2sh_spend_preimage(args.funding_tx, outputs)
+    }
+
+    /// Generates taker payment spend preimage sending the dex fee to the dex address and the rest of SLP to maker.
+    /// Unlike UTXO coins, all outputs are generated by taker and signed with `SIGHASH_ALL`,
+    /// because the OP_RETURN must describe the SLP amounts of all outputs.
+    pub(super) fn gen_taker_payment_spend_preimage_with_fee(
+        &self,
+        args: &GenTakerPaymentSpendArgs<'_, Self>,
+        fee: u64,
+    ) -> Result<TransactionInputSigner, MmError<TxGenError>> {
+        if args.dex_fee.burn_amount().is_some() {
+            return MmError::err(TxGenError::Other("SLP doesn't support dex fee burning".into()));
+        }
+        let payment_utxo = self
+            .swap_tx_slp_unspent(args.taker_tx)
+            .map_to_mm(TxGenError::PrevTxIsNotValid)?;
+        let maker_script_pubkey = output_script(args.maker_address)
+            .map_to_mm(|e| TxGenError::Other(format!("Couldn't generate maker output script: {}", e)))?
+            .to_bytes();
+
+        let (slp_amounts, mut bch_outputs) = match args.dex_fee {
+            DexFee::NoFee => (vec![payment_utxo.slp_amount], Vec::with_capacity(1)),
+            dex_fee => {
+                let dex_fee_sat = dex_fee.fee_amount_as_u64(self.decimals())?;
+                let maker_amount = payment_utxo.slp_amount.checked_sub(dex_fee_sat).or_mm_err(|| {
+                    TxGenError::PrevOutputTooLow(format!(
+                        "SLP amount {} is lower than dex fee {}",
+                        payment_utxo.slp_amount, dex_fee_sat
+                    ))
+                })?;
+                let dex_pub = Public::from_slice(self.dex_pubkey()).map_to_mm(|e| TxGenError::Other(e.to_string()))?;
+                let dex_fee_output = TransactionOutput {
+                    value: self.platform_dust(),
+                    script_pubkey: ScriptBuilder::build_p2pkh(&dex_pub.address_hash().into()).to_bytes(),
+                };
+                (vec![dex_fee_sat, maker_amount], vec![dex_fee_output])
+            },
+        };
+
+        let spent_value = bch_outputs.iter().fold(fee, |total, output| total + output.value);
+        let maker_value = payment_utxo
+            .bch_unspent
+            .value
+            .checked_sub(spent_value)
+            .filter(|value| *value >= self.platform_dust())
+            .or_mm_err(|| {
+                TxGenError::PrevOutputTooLow(format!(
+                    "Taker payment value {} is too low to cover fee and dust outputs",
+                    payment_utxo.bch_unspent.value
+                ))
+            })?;
+        bch_outputs.push(TransactionOutput {
+            value: maker_value,
+            script_pubkey: maker_script_pubkey,
+        });
+
+        let mut outputs = vec![slp_send_output(self.token_id(), &slp_amounts)];
+        outputs.extend(bch_outputs);
+        self.swap_p2sh_spend_preimage(args.taker_tx, outputs)
+    }
+
+    async fn htlc_spend_fee(&self) -> Result<u64, MmError<TxGenError>> {
+        let fee = self
+            .platform_coin
+            .get_htlc_spend_fee(SLP_HTLC_SPEND_SIZE, &FeeApproxStage::WithoutApprox)
+            .await?;
+        Ok(fee)
+    }
+
+    /// Checks that the `actual_fee` of the counterparty preimage is close to the expected one.
+    async fn check_preimage_fee(&self, actual_fee: u64) -> Result<(), String> {
+        let expected_fee = self.htlc_spend_fee().await.map_err(|e| e.to_string())?;
+        let fee_div = expected_fee as f64 / actual_fee as f64;
+        if !(0.9..=1.1).contains(&fee_div) {
+            return Err(format!(
+                "Too large difference between expected {} and actual {} fees",
+                expected_fee, actual_fee
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the miner fee paid by the `preimage` spending the SLP P2SH output of the `prev_tx`.
+fn swap_p2sh_spend_fee(prev_tx: &UtxoTx, preimage: &UtxoTx) -> Result<u64, String> {
+    let input_value = prev_tx
+        .outputs
+        .get(SLP_SWAP_VOUT)
+        .ok_or_else(|| format!("Tx has no output with index {}", SLP_SWAP_VOUT))?
+        .value;
+    let output_value = preimage.outputs.iter().fold(0, |total, output| total + output.value);
+    input_value
+        .checked_sub(output_value)
+        .ok_or_else(|| format!("Preimage outputs {} larger than input {}", output_value, input_value))
+}
+
+fn signature_with_sighash(signature: &[u8], fork_id: u32) -> Vec<u8> {
+    let mut signature = signature.to_vec();
+    signature.push((SIGHASH_ALL | fork_id) as u8);
+    signature
+}
+
+#[async_trait]
+impl MakerCoinSwapOpsV2 for SlpToken {
+    async fn send_maker_payment_v2(&self, args: SendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        let maker_htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::maker_payment_script(
+            try_tx_s!(args.time_lock.try_into()),
+            args.maker_secret_hash,
+            args.taker_secret_hash,
+            maker_htlc_keypair.public(),
+            args.taker_pub,
+        );
+        let amount = try_tx_s!(sat_from_big_decimal(&args.amount, self.decimals()));
+        self.send_slp_to_p2sh(&redeem_script, amount, self.platform_dust())
+            .await
+    }
+
+    async fn validate_maker_payment_v2(&self, args: ValidateMakerPaymentArgs<'_, Self>) -> ValidatePaymentResult<()> {
+        let slp_satoshis = sat_from_big_decimal(&args.amount, self.decimals())?;
+        let slp_unspent = self
+            .swap_tx_slp_unspent(args.maker_payment_tx)
+            .map_to_mm(ValidatePaymentError::WrongPaymentTx)?;
+        if slp_unspent.slp_amount != slp_satoshis {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "Invalid SLP amount. Expected: {}, found: {}",
+                slp_satoshis, slp_unspent.slp_amount
+            )));
+        }
+        validate_slp_utxos(self.platform_coin.bchd_urls(), &[slp_unspent], self.token_id()).await?;
+
+        let taker_htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let time_lock = args
+            .time_lock
+            .try_into()
+            .map_to_mm(ValidatePaymentError::TimelockOverflow)?;
+        utxo_common::validate_payment(
+            self.platform_coin.clone(),
+            args.maker_payment_tx,
+            SLP_SWAP_VOUT,
+            args.maker_pub,
+            taker_htlc_keypair.public(),
+            SwapTxTypeWithSecretHash::MakerPaymentV2 {
+                maker_secret_hash: args.maker_secret_hash,
+                taker_secret_hash: args.taker_secret_hash,
+            },
+            self.platform_dust_dec(),
+            None,
+            time_lock,
+            0,
+            0,
+        )
+        .await
+    }
+
+    async fn refund_maker_payment_v2_timelock(
+        &self,
+        args: RefundMakerPaymentTimelockArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.refund_swap_tx_v2_timelock(
+            args.payment_tx,
+            args.time_lock,
+            args.taker_pub,
+            args.tx_type_with_secret_hash,
+            args.swap_unique_data,
+        )
+        .await
+    }
+
+    async fn refund_maker_payment_v2_secret(
+        &self,
+        args: RefundMakerPaymentSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        let htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::maker_payment_script(
+            try_tx_s!(args.time_lock.try_into()),
+            args.maker_secret_hash,
+            args.taker_secret_hash,
+            htlc_keypair.public(),
+            args.taker_pub,
+        );
+        let script_data = ScriptBuilder::default()
+            .push_data(args.taker_secret)
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_0)
+            .into_script();
+        self.spend_swap_p2sh(
+            args.maker_payment_tx,
+            args.time_lock,
+            SEQUENCE_FINAL,
+            script_data,
+            redeem_script,
+            &htlc_keypair,
+        )
+        .await
+    }
+
+    async fn spend_maker_payment_v2(&self, args: SpendMakerPaymentArgs<'_, Self>) -> Result<Self::Tx, TransactionErr> {
+        let htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::maker_payment_script(
+            try_tx_s!(args.time_lock.try_into()),
+            args.maker_secret_hash,
+            args.taker_secret_hash,
+            args.maker_pub,
+            htlc_keypair.public(),
+        );
+        let script_data = ScriptBuilder::default()
+            .push_data(&args.maker_secret)
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_0)
+            .into_script();
+        self.spend_swap_p2sh(
+            args.maker_payment_tx,
+            args.time_lock,
+            SEQUENCE_FINAL,
+            script_data,
+            redeem_script,
+            &htlc_keypair,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl TakerCoinSwapOpsV2 for SlpToken {
+    async fn send_taker_funding(&self, args: SendTakerFundingArgs<'_>) -> Result<Self::Tx, TransactionErr> {
+        let maker_pub = try_tx_s!(Public::from_slice(args.maker_pub));
+        let taker_htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::taker_funding_script(
+            try_tx_s!(args.funding_time_lock.try_into()),
+            args.taker_secret_hash,
+            taker_htlc_keypair.public(),
+            &maker_pub,
+        );
+        let total_amount =
+            &args.dex_fee.total_spend_amount().to_decimal() + &args.premium_amount + &args.trading_amount;
+        let amount = try_tx_s!(sat_from_big_decimal(&total_amount, self.decimals()));
+        let value = try_tx_s!(self.taker_funding_bch_value().await);
+        self.send_slp_to_p2sh(&redeem_script, amount, value).await
+    }
+
+    async fn validate_taker_funding(&self, args: ValidateTakerFundingArgs<'_, Self>) -> ValidateSwapV2TxResult {
+        let total_expected_amount =
+            &args.dex_fee.total_spend_amount().to_decimal() + &args.premium_amount + &args.trading_amount;
+        let expected_amount_sat = sat_from_big_decimal(&total_expected_amount, self.decimals())?;
+
+        let slp_unspent = self
+            .swap_tx_slp_unspent(args.funding_tx)
+            .map_to_mm(ValidateSwapV2TxError::WrongPaymentTx)?;
+        if slp_unspent.slp_amount != expected_amount_sat {
+            return MmError::err(ValidateSwapV2TxError::InvalidDestinationOrAmount(format!(
+                "Expected SLP amount {}, got {}",
+                expected_amount_sat, slp_unspent.slp_amount
+            )));
+        }
+
+        let time_lock = args
+            .funding_time_lock
+            .try_into()
+            .map_to_mm(|e: TryFromIntError| ValidateSwapV2TxError::Overflow(e.to_string()))?;
+        let maker_htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::taker_funding_script(
+            time_lock,
+            args.taker_secret_hash,
+            args.taker_pub,
+            maker_htlc_keypair.public(),
+        );
+        let expected_script_pubkey: Script = ScriptBuilder::build_p2sh(&dhash160(&redeem_script).into());
+        if slp_unspent.bch_unspent.script != expected_script_pubkey {
+            return MmError::err(ValidateSwapV2TxError::InvalidDestinationOrAmount(format!(
+                "Expected script_pubkey {}, got {}",
+                expected_script_pubkey, slp_unspent.bch_unspent.script
+            )));
+        }
+        let min_value = 2 * self.platform_dust();
+        if slp_unspent.bch_unspent.value < min_value {
+            return MmError::err(ValidateSwapV2TxError::InvalidDestinationOrAmount(format!(
+                "Funding value {} is lower than {}",
+                slp_unspent.bch_unspent.value, min_value
+            )));
+        }
+
+        let tx_bytes_from_rpc = self
+            .rpc()
+            .get_transaction_bytes(&args.funding_tx.hash().reversed().into())
+            .compat()
+            .await?;
+        let actual_tx_bytes = serialize(args.funding_tx).take();
+        if tx_bytes_from_rpc.0 != actual_tx_bytes {
+            return MmError::err(ValidateSwapV2TxError::TxBytesMismatch {
+                from_rpc: tx_bytes_from_rpc,
+                actual: actual_tx_bytes.into(),
+            });
+        }
+
+        validate_slp_utxos(self.platform_coin.bchd_urls(), &[slp_unspent], self.token_id())
+            .await
+            .mm_err(|e| ValidateSwapV2TxError::WrongPaymentTx(e.to_string()))?;
+
+        self.import_p2sh_address_if_native(&redeem_script)
+            .await
+            .map_to_mm(ValidateSwapV2TxError::Rpc)
+    }
+
+    async fn refund_taker_funding_timelock(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.refund_swap_tx_v2_timelock(
+            args.payment_tx,
+            args.time_lock,
+            args.maker_pub,
+            args.tx_type_with_secret_hash,
+            args.swap_unique_data,
+        )
+        .await
+    }
+
+    async fn refund_taker_funding_secret(
+        &self,
+        args: RefundFundingSecretArgs<'_, Self>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        let htlc_keypair = self.derive_htlc_key_pair(args.swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::taker_funding_script(
+            try_tx_s!(args.funding_time_lock.try_into()),
+            args.taker_secret_hash,
+            htlc_keypair.public(),
+            args.maker_pubkey,
+        );
+        let script_data = ScriptBuilder::default()
+            .push_data(args.taker_secret)
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_0)
+            .into_script();
+        self.spend_swap_p2sh(
+            args.funding_tx,
+            args.funding_time_lock,
+            SEQUENCE_FINAL,
+            script_data,
+            redeem_script,
+            &htlc_keypair,
+        )
+        .await
+    }
+
+    async fn search_for_taker_funding_spend(
+        &self,
+        tx: &Self::Tx,
+        from_block: u64,
+        _secret_hash: &[u8],
+    ) -> Result<Option<FundingTxSpend<Self>>, SearchForFundingSpendErr> {
+        utxo_common::search_for_taker_funding_spend(self.as_ref(), tx, SLP_SWAP_VOUT, from_block).await
+    }
+
+    async fn gen_taker_funding_spend_preimage(
+        &self,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        let funding_time_lock = args
+            .funding_time_lock
+            .try_into()
+            .map_to_mm(|e: TryFromIntError| TxGenError::LocktimeOverflow(e.to_string()))?;
+        let fee = self.htlc_spend_fee().await?;
+        let preimage = self.gen_taker_funding_spend_preimage_with_fee(args, fee)?;
+
+        let redeem_script = swap_proto_v2_scripts::taker_funding_script(
+            funding_time_lock,
+            args.taker_secret_hash,
+            args.taker_pub,
+            args.maker_pub,
+        );
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let signature = calc_and_sign_sighash(
+            &preimage,
+            DEFAULT_SWAP_VOUT,
+            &redeem_script,
+            &htlc_keypair,
+            self.platform_conf().signature_version,
+            SIGHASH_ALL,
+            self.platform_conf().fork_id,
+        )?;
+        Ok(TxPreimageWithSig {
+            preimage: preimage.into(),
+            signature: signature.take().into(),
+        })
+    }
+
+    async fn validate_taker_funding_spend_preimage(
+        &self,
+        gen_args: &GenTakerFundingSpendArgs<'_, Self>,
+        preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerFundingSpendPreimageResult {
+        let actual_fee = swap_p2sh_spend_fee(gen_args.funding_tx, &preimage.preimage)
+            .map_to_mm(ValidateTakerFundingSpendPreimageError::InvalidPreimage)?;
+        self.check_preimage_fee(actual_fee)
+            .await
+            .map_to_mm(ValidateTakerFundingSpendPreimageError::UnexpectedPreimageFee)?;
+        let expected_preimage = self.gen_taker_funding_spend_preimage_with_fee(gen_args, actual_fee)?;
+
+        let funding_time_lock = gen_args
+            .funding_time_lock
+            .try_into()
+            .map_to_mm(|e: TryFromIntError| ValidateTakerFundingSpendPreimageError::LocktimeOverflow(e.to_string()))?;
+        let redeem_script = swap_proto_v2_scripts::taker_funding_script(
+            funding_time_lock,
+            gen_args.taker_secret_hash,
+            gen_args.taker_pub,
+            gen_args.maker_pub,
+        );
+        let sig_hash = signature_hash_to_sign(
+            &expected_preimage,
+            DEFAULT_SWAP_VOUT,
+            &redeem_script,
+            self.platform_conf().signature_version,
+            SIGHASH_ALL,
+            self.platform_conf().fork_id,
+        )?;
+        if !gen_args
+            .maker_pub
+            .verify(&sig_hash, &preimage.signature)
+            .map_to_mm(|e| ValidateTakerFundingSpendPreimageError::SignatureVerificationFailure(e.to_string()))?
+        {
+            return MmError::err(ValidateTakerFundingSpendPreimageError::InvalidMakerSignature);
+        }
+
+        let expected_preimage_tx: UtxoTx = expected_preimage.into();
+        if expected_preimage_tx != preimage.preimage {
+            return MmError::err(ValidateTakerFundingSpendPreimageError::InvalidPreimage(
+                "Preimage is not equal to expected".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn sign_and_send_taker_funding_spend(
+        &self,
+        preimage: &TxPreimageWithSig<Self>,
+        args: &GenTakerFundingSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        let redeem_script = swap_proto_v2_scripts::taker_funding_script(
+            try_tx_s!(args.funding_time_lock.try_into()),
+            args.taker_secret_hash,
+            args.taker_pub,
+            args.maker_pub,
+        );
+        let funding_utxo = try_tx_s!(self.swap_tx_slp_unspent(args.funding_tx));
+
+        let mut signer: TransactionInputSigner = preimage.preimage.clone().into();
+        let funding_input = try_tx_s!(signer.inputs.first_mut().ok_or("Preimage doesn't have inputs"));
+        funding_input.amount = funding_utxo.bch_unspent.value;
+        signer.consensus_branch_id = self.platform_conf().consensus_branch_id;
+        drop_mutability!(signer);
+
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let fork_id = self.platform_conf().fork_id;
+        let taker_signature = try_tx_s!(calc_and_sign_sighash(
+            &signer,
+            DEFAULT_SWAP_VOUT,
+            &redeem_script,
+            &htlc_keypair,
+            self.platform_conf().signature_version,
+            SIGHASH_ALL,
+            fork_id
+        ));
+        let script_sig = ScriptBuilder::default()
+            .push_data(&signature_with_sighash(&preimage.signature, fork_id))
+            .push_data(&signature_with_sighash(&taker_signature, fork_id))
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_0)
+            .push_data(&redeem_script)
+            .into_bytes();
+        let mut final_tx: UtxoTx = signer.into();
+        let final_tx_input = try_tx_s!(final_tx.inputs.first_mut().ok_or("Final tx doesn't have inputs"));
+        final_tx_input.script_sig = script_sig;
+        drop_mutability!(final_tx);
+
+        let payment_redeem_script = swap_proto_v2_scripts::taker_payment_script(
+            try_tx_s!(args.taker_payment_time_lock.try_into()),
+            args.maker_secret_hash,
+            args.taker_pub,
+            args.maker_pub,
+        );
+        try_tx_s!(self.import_p2sh_address_if_native(&payment_redeem_script).await);
+        try_tx_s!(self.broadcast_tx(&final_tx).await, final_tx);
+        Ok(final_tx)
+    }
+
+    async fn refund_combined_taker_payment(
+        &self,
+        args: RefundTakerPaymentArgs<'_>,
+    ) -> Result<Self::Tx, TransactionErr> {
+        self.refund_swap_tx_v2_timelock(
+            args.payment_tx,
+            args.time_lock,
+            args.maker_pub,
+            args.tx_type_with_secret_hash,
+            args.swap_unique_data,
+        )
+        .await
+    }
+
+    async fn gen_taker_payment_spend_preimage(
+        &self,
+        args: &GenTakerPaymentSpendArgs<'_, Self>,
+        swap_unique_data: &[u8],
+    ) -> GenPreimageResult<Self> {
+        let time_lock = args
+            .time_lock
+            .try_into()
+            .map_to_mm(|e: TryFromIntError| TxGenError::LocktimeOverflow(e.to_string()))?;
+        let fee = self.htlc_spend_fee().await?;
+        let preimage = self.gen_taker_payment_spend_preimage_with_fee(args, fee)?;
+
+        let redeem_script = swap_proto_v2_scripts::taker_payment_script(
+            time_lock,
+            args.maker_secret_hash,
+            args.taker_pub,
+            args.maker_pub,
+        );
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let signature = calc_and_sign_sighash(
+            &preimage,
+            DEFAULT_SWAP_VOUT,
+            &redeem_script,
+            &htlc_keypair,
+            self.platform_conf().signature_version,
+            SIGHASH_ALL,
+            self.platform_conf().fork_id,
+        )?;
+        Ok(TxPreimageWithSig {
+            preimage: preimage.into(),
+            signature: signature.take().into(),
+        })
+    }
+
+    async fn validate_taker_payment_spend_preimage(
+        &self,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        preimage: &TxPreimageWithSig<Self>,
+    ) -> ValidateTakerPaymentSpendPreimageResult {
+        let actual_fee = swap_p2sh_spend_fee(gen_args.taker_tx, &preimage.preimage)
+            .map_to_mm(ValidateTakerPaymentSpendPreimageError::InvalidPreimage)?;
+        self.check_preimage_fee(actual_fee)
+            .await
+            .map_to_mm(ValidateTakerPaymentSpendPreimageError::InvalidPreimage)?;
+        let expected_preimage = self.gen_taker_payment_spend_preimage_with_fee(gen_args, actual_fee)?;
+
+        let time_lock = gen_args
+            .time_lock
+            .try_into()
+            .map_to_mm(|e: TryFromIntError| ValidateTakerPaymentSpendPreimageError::LocktimeOverflow(e.to_string()))?;
+        let redeem_script = swap_proto_v2_scripts::taker_payment_script(
+            time_lock,
+            gen_args.maker_secret_hash,
+            gen_args.taker_pub,
+            gen_args.maker_pub,
+        );
+        let sig_hash = signature_hash_to_sign(
+            &expected_preimage,
+            DEFAULT_SWAP_VOUT,
+            &redeem_script,
+            self.platform_conf().signature_version,
+            SIGHASH_ALL,
+            self.platform_conf().fork_id,
+        )?;
+        if !gen_args
+            .taker_pub
+            .verify(&sig_hash, &preimage.signature)
+            .map_to_mm(|e| ValidateTakerPaymentSpendPreimageError::SignatureVerificationFailure(e.to_string()))?
+        {
+            return MmError::err(ValidateTakerPaymentSpendPreimageError::InvalidTakerSignature);
+        }
+
+        let expected_preimage_tx: UtxoTx = expected_preimage.into();
+        if expected_preimage_tx != preimage.preimage {
+            return MmError::err(ValidateTakerPaymentSpendPreimageError::InvalidPreimage(
+                "Preimage is not equal to expected".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn sign_and_broadcast_taker_payment_spend(
+        &self,
+        preimage: Option<&TxPreimageWithSig<Self>>,
+        gen_args: &GenTakerPaymentSpendArgs<'_, Self>,
+        secret: &[u8],
+        swap_unique_data: &[u8],
+    ) -> Result<Self::Tx, TransactionErr> {
+        let preimage = preimage
+            .ok_or_else(|| TransactionErr::Plain(ERRL!("taker_payment_spend_preimage must be Some for SLP token")))?;
+        let htlc_keypair = self.derive_htlc_key_pair(swap_unique_data);
+        let redeem_script = swap_proto_v2_scripts::taker_payment_script(
+            try_tx_s!(gen_args.time_lock.try_into()),
+            gen_args.maker_secret_hash,
+            gen_args.taker_pub,
+            htlc_keypair.public(),
+        );
+        let payment_utxo = try_tx_s!(self.swap_tx_slp_unspent(gen_args.taker_tx));
+
+        let mut signer: TransactionInputSigner = preimage.preimage.clone().into();
+        let payment_input = try_tx_s!(signer.inputs.first_mut().ok_or("Preimage doesn't have inputs"));
+        payment_input.amount = payment_utxo.bch_unspent.value;
+        signer.consensus_branch_id = self.platform_conf().consensus_branch_id;
+        drop_mutability!(signer);
+
+        let fork_id = self.platform_conf().fork_id;
+        let maker_signature = try_tx_s!(calc_and_sign_sighash(
+            &signer,
+            DEFAULT_SWAP_VOUT,
+            &redeem_script,
+            &htlc_keypair,
+            self.platform_conf().signature_version,
+            SIGHASH_ALL,
+            fork_id
+        ));
+        let script_sig = ScriptBuilder::default()
+            .push_data(&signature_with_sighash(&maker_signature, fork_id))
+            .push_data(&signature_with_sighash(&preimage.signature, fork_id))
+            .push_data(secret)
+            .push_opcode(Opcode::OP_0)
+            .push_data(&redeem_script)
+            .into_bytes();
+        let mut final_tx: UtxoTx = signer.into();
+        let final_tx_input = try_tx_s!(final_tx.inputs.first_mut().ok_or("Final tx doesn't have inputs"));
+        final_tx_input.script_sig = script_sig;
+        drop_mutability!(final_tx);
+
+        try_tx_s!(self.broadcast_tx(&final_tx).await, final_tx);
+        Ok(final_tx)
+    }
+
+    async fn find_taker_payment_spend_tx(
+        &self,
+        taker_payment: &Self::Tx,
+        from_block: u64,
+        wait_until: u64,
+    ) -> MmResult<Self::Tx, FindPaymentSpendError> {
+        let res = utxo_common::wait_for_output_spend_impl(
+            self.as_ref(),
+            taker_payment,
+            SLP_SWAP_VOUT,
+            from_block,
+            wait_until,
+            10.,
+        )
+        .await?;
+        Ok(res)
+    }
+
+    async fn extract_secret_v2(&self, secret_hash: &[u8], spend_tx: &Self::Tx) -> Result<[u8; 32], String> {
+        utxo_common::extract_secret_v2(secret_hash, spend_tx)
+    }
+}
+
+impl CommonSwapOpsV2 for SlpToken {
+    fn derive_htlc_pubkey_v2(&self, swap_unique_data: &[u8]) -> Self::Pubkey {
+        *self.derive_htlc_key_pair(swap_unique_data).public()
+    }
+
+    #[inline(always)]
+    fn derive_htlc_pubkey_v2_bytes(&self, swap_unique_data: &[u8]) -> Vec<u8> {
+        self.derive_htlc_pubkey_v2(swap_unique_data).to_bytes()
+    }
+
+    #[inline(always)]
+    fn taker_pubkey_bytes(&self) -> Option<Vec<u8>> {
+        Some(self.derive_htlc_pubkey_v2(&[]).to_bytes()) // unique_data not used for non-private coins
+    }
+}
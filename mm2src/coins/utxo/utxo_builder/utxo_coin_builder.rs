@@ -5,6 +5,8 @@ use crate::utxo::rpc_clients::{ElectrumClient, ElectrumClientSettings, ElectrumC
 use crate::utxo::tx_cache::{UtxoVerboseCacheOps, UtxoVerboseCacheShared};
 use crate::utxo::utxo_block_header_storage::BlockHeaderStorage;
 use crate::utxo::utxo_builder::utxo_conf_builder::{UtxoConfBuilder, UtxoConfError};
+use crate::utxo::utxo_lock_storage::{load_locked_unspents, UtxoLockStorageError};
+use crate::utxo::utxo_multisig::UtxoMultisigWallet;
 use crate::utxo::{output_script, ElectrumBuilderArgs, RecentlySpentOutPoints, TxFee, UtxoCoinConf, UtxoCoinFields,
                   UtxoHDWallet, UtxoRpcMode, UtxoSyncStatus, UtxoSyncStatusLoopHandle, UTXO_DUST_AMOUNT};
 use crate::{BlockchainNetwork, CoinTransportMetrics, DerivationMethod, HistorySyncState, IguanaPrivKey,
@@ -14,7 +16,8 @@ use async_trait::async_trait;
 use chain::TxHashAlgo;
 use common::executor::{abortable_queue::AbortableQueue, AbortableSystem, AbortedError};
 use common::now_sec;
-use crypto::{Bip32DerPathError, CryptoCtx, CryptoCtxError, GlobalHDAccountArc, HwWalletType, StandardHDPathError};
+use crypto::{Bip32DerPathError, Bip43Purpose, CryptoCtx, CryptoCtxError, GlobalHDAccountArc, HwWalletType,
+             StandardHDPathError};
 use derive_more::Display;
 use futures::channel::mpsc::{channel, Receiver as AsyncReceiver};
 use futures::compat::Future01CompatExt;
@@ -67,6 +70,7 @@ pub enum UtxoCoinBuildError {
     )]
     CoinDoesntSupportTrezor,
     BlockHeaderStorageError(BlockHeaderStorageError),
+    UtxoLockStorageError(UtxoLockStorageError),
     #[display(fmt = "Internal error: {}", _0)]
     Internal(String),
     #[display(fmt = "SPV params verificaiton failed. Error: {_0}")]
@@ -79,6 +83,8 @@ pub enum UtxoCoinBuildError {
         mode: String,
     },
     InvalidPathToAddress(String),
+    #[display(fmt = "Invalid multisig params: {}", _0)]
+    InvalidMultisigParams(String),
 }
 
 impl From<UtxoConfError> for UtxoCoinBuildError {
@@ -102,6 +108,10 @@ impl From<BlockHeaderStorageError> for UtxoCoinBuildError {
     fn from(e: BlockHeaderStorageError) -> Self { UtxoCoinBuildError::BlockHeaderStorageError(e) }
 }
 
+impl From<UtxoLockStorageError> for UtxoCoinBuildError {
+    fn from(e: UtxoLockStorageError) -> Self { UtxoCoinBuildError::UtxoLockStorageError(e) }
+}
+
 impl From<AbortedError> for UtxoCoinBuildError {
     fn from(e: AbortedError) -> Self { UtxoCoinBuildError::Internal(e.to_string()) }
 }
@@ -201,6 +211,14 @@ pub trait UtxoFieldsWithGlobalHDBuilder: UtxoCoinBuilderCommonOps {
         };
 
         let address_format = self.address_format()?;
+        // BIP-86 accounts are used for the Taproot addresses only and vice versa.
+        if address_format.is_taproot() != (path_to_coin.purpose() == Bip43Purpose::Bip86) {
+            let error = format!(
+                "{:?} address format can't be used with the '{}' derivation path",
+                address_format, path_to_coin
+            );
+            return MmError::err(UtxoCoinBuildError::from(UtxoConfError::InvalidAddressFormat(error)));
+        }
         let hd_wallet_rmd160 = *self.ctx().rmd160();
         let hd_wallet_storage =
             HDWalletCoinStorage::init_with_rmd160(self.ctx(), self.ticker().to_owned(), hd_wallet_rmd160).await?;
@@ -264,6 +282,8 @@ where
     let tx_cache = builder.tx_cache();
     let (block_headers_status_notifier, block_headers_status_watcher) =
         builder.block_header_status_channel(&conf.spv_conf);
+    let locked_unspents = load_locked_unspents(builder.ctx(), &conf.ticker).await?;
+    let multisig = builder.multisig_wallet(&priv_key_policy)?;
 
     let coin = UtxoCoinFields {
         conf,
@@ -275,6 +295,7 @@ where
         history_sync_state: Mutex::new(initial_history_state),
         tx_cache,
         recently_spent_outpoints: AsyncMutex::new(RecentlySpentOutPoints::new(my_script_pubkey)),
+        locked_unspents: Mutex::new(locked_unspents),
         tx_fee,
         tx_hash_algo,
         check_utxo_maturity,
@@ -282,6 +303,7 @@ where
         block_headers_status_watcher,
         ctx: builder.ctx().clone().weak(),
         abortable_system,
+        multisig,
     };
 
     Ok(coin)
@@ -342,6 +364,8 @@ pub trait UtxoFieldsWithHardwareWalletBuilder: UtxoCoinBuilderCommonOps {
         let tx_cache = self.tx_cache();
         let (block_headers_status_notifier, block_headers_status_watcher) =
             self.block_header_status_channel(&conf.spv_conf);
+        let locked_unspents = load_locked_unspents(self.ctx(), &conf.ticker).await?;
+        let multisig = self.multisig_wallet(&PrivKeyPolicy::Trezor)?;
 
         let coin = UtxoCoinFields {
             conf,
@@ -353,6 +377,7 @@ pub trait UtxoFieldsWithHardwareWalletBuilder: UtxoCoinBuilderCommonOps {
             history_sync_state: Mutex::new(initial_history_state),
             tx_cache,
             recently_spent_outpoints,
+            locked_unspents: Mutex::new(locked_unspents),
             tx_fee,
             tx_hash_algo,
             check_utxo_maturity,
@@ -360,6 +385,7 @@ pub trait UtxoFieldsWithHardwareWalletBuilder: UtxoCoinBuilderCommonOps {
             block_headers_status_watcher,
             ctx: self.ctx().clone().weak(),
             abortable_system,
+            multisig,
         };
         Ok(coin)
     }
@@ -407,9 +433,9 @@ pub trait UtxoCoinBuilderCommonOps {
 
         let mut address_format = match format_from_req {
             Some(from_req) => {
-                if from_req.is_segwit() != format_from_conf.is_segwit() {
+                if from_req.is_witness() != format_from_conf.is_witness() {
                     let error = format!(
-                        "Both conf {:?} and request {:?} must be either Segwit or Standard/CashAddress",
+                        "Both conf {:?} and request {:?} must be either Segwit/Taproot or Standard/CashAddress",
                         format_from_conf, from_req
                     );
                     return MmError::err(UtxoCoinBuildError::from(UtxoConfError::InvalidAddressFormat(error)));
@@ -431,9 +457,11 @@ pub trait UtxoCoinBuilderCommonOps {
         }
 
         let is_segwit_in_conf = self.conf()["segwit"].as_bool().unwrap_or(false);
-        if address_format.is_segwit() && (!is_segwit_in_conf || self.conf()["bech32_hrp"].is_null()) {
-            let error =
-                "Cannot use Segwit address format for coin without segwit support or bech32_hrp in config".to_owned();
+        if address_format.is_witness() && (!is_segwit_in_conf || self.conf()["bech32_hrp"].is_null()) {
+            let error = format!(
+                "Cannot use {:?} address format for coin without segwit support or bech32_hrp in config",
+                address_format
+            );
             return MmError::err(UtxoCoinBuildError::from(UtxoConfError::InvalidAddressFormat(error)));
         }
         Ok(address_format)
@@ -668,6 +696,33 @@ pub trait UtxoCoinBuilderCommonOps {
         self.conf()["check_utxo_maturity"].as_bool().unwrap_or_default()
     }
 
+    /// Initializes the multisig wallet if the `multisig` activation param is set.
+    /// The wallet's own xpub must be one of the cosigners' xpubs unless it's Trezor,
+    /// which keys are checked by the device on signing.
+    fn multisig_wallet(
+        &self,
+        priv_key_policy: &PrivKeyPolicy<KeyPair>,
+    ) -> UtxoCoinBuildResult<Option<UtxoMultisigWallet>> {
+        let params = match self.activation_params().multisig {
+            Some(ref params) => params,
+            None => return Ok(None),
+        };
+        let wallet = UtxoMultisigWallet::from_params(params).map_to_mm(UtxoCoinBuildError::InvalidMultisigParams)?;
+        match priv_key_policy {
+            PrivKeyPolicy::HDWallet {
+                bip39_secp_priv_key, ..
+            } => wallet
+                .check_own_xpub(bip39_secp_priv_key)
+                .map_to_mm(UtxoCoinBuildError::InvalidMultisigParams)?,
+            PrivKeyPolicy::Trezor => (),
+            _ => {
+                let error = "Multisig wallet can be activated with HD wallet or Trezor only".to_owned();
+                return MmError::err(UtxoCoinBuildError::InvalidMultisigParams(error));
+            },
+        }
+        Ok(Some(wallet))
+    }
+
     #[cfg(target_arch = "wasm32")]
     fn tx_cache(&self) -> UtxoVerboseCacheShared {
         #[allow(clippy::default_constructed_unit_structs)] // This is a false-possitive bug from clippy
@@ -106,6 +106,7 @@ impl<'a> UtxoConfBuilder<'a> {
         let is_posv = self.is_posv();
         let segwit = self.segwit();
         let force_min_relay_fee = self.conf["force_min_relay_fee"].as_bool().unwrap_or(false);
+        let signal_rbf = self.conf["signal_rbf"].as_bool().unwrap_or(false);
         let mtp_block_count = self.mtp_block_count();
         let estimate_fee_mode = self.estimate_fee_mode();
         let estimate_fee_blocks = self.estimate_fee_blocks();
@@ -137,6 +138,7 @@ impl<'a> UtxoConfBuilder<'a> {
             fork_id,
             required_confirmations: required_confirmations.into(),
             force_min_relay_fee,
+            signal_rbf,
             mtp_block_count,
             estimate_fee_mode,
             mature_confirmations,
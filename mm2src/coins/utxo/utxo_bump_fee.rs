@@ -0,0 +1,456 @@
+//! Fee bumping of the unconfirmed transactions of my address:
+//! * [BIP-125](https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki) replace-by-fee,
+//! * Child-Pays-For-Parent.
+
+use super::KILO_BYTE;
+use crate::rpc_command::bump_fee::{BumpFeeError, BumpFeeMethod, BumpFeeRequest, BumpFeeResponse, BumpFeeResult};
+use crate::utxo::utxo_common::{big_decimal_from_sat, get_unspents_for_inputs, tx_size_in_v_bytes};
+use crate::utxo::{output_script, sat_from_big_decimal, ActualTxFee, GetUtxoListOps, UtxoAddressFormat, UtxoCommonOps,
+                  UtxoFeeDetails, UtxoTx, UTXO_LOCK};
+use crate::{TransactionData, TransactionDetails, WithdrawFee};
+use chain::constants::{SEQUENCE_FINAL, SEQUENCE_RBF_ENABLED};
+use chain::TransactionOutput;
+use common::now_sec;
+use futures::compat::Future01CompatExt;
+use keys::Address;
+use mm2_err_handle::prelude::*;
+use rpc::v1::types::ToTxHash;
+use script::{Script, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+use serialization::{deserialize, serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use utxo_signer::with_key_pair::sign_tx;
+
+/// The minimum fee rate by which a replacement or a child transaction must increase the fee (1 sat/vbyte).
+const INCREMENTAL_RELAY_FEE_PER_KB: u64 = 1000;
+
+pub async fn bump_fee<T>(coin: &T, req: BumpFeeRequest) -> BumpFeeResult<BumpFeeResponse>
+where
+    T: UtxoCommonOps + GetUtxoListOps,
+{
+    let tx_hash = req.tx_hash.to_string();
+    let verbose_tx = coin
+        .as_ref()
+        .rpc_client
+        .get_verbose_transaction(&req.tx_hash)
+        .compat()
+        .await
+        .mm_err(|e| BumpFeeError::TxNotFound(format!("{}: {}", tx_hash, e)))?;
+    if verbose_tx.confirmations > 0 {
+        return MmError::err(BumpFeeError::TxAlreadyConfirmed(tx_hash));
+    }
+    let mut tx: UtxoTx =
+        deserialize(verbose_tx.hex.as_slice()).map_to_mm(|e| BumpFeeError::InternalError(e.to_string()))?;
+    tx.tx_hash_algo = coin.as_ref().tx_hash_algo;
+
+    let fee = match req.fee {
+        Some(ref fee) => Some(actual_fee_from_withdraw_fee(fee, coin.as_ref().decimals)?),
+        None => None,
+    };
+    let method = req.method.unwrap_or(if tx.signals_rbf() {
+        BumpFeeMethod::Rbf
+    } else {
+        BumpFeeMethod::Cpfp
+    });
+
+    let my_address = coin.as_ref().derivation_method.single_addr_or_err().await?;
+    let (unsigned, spent_by_me, fee_amount) = match method {
+        BumpFeeMethod::Rbf => replace_by_fee(coin, &my_address, tx, fee).await?,
+        BumpFeeMethod::Cpfp => child_pays_for_parent(coin, &my_address, tx, fee).await?,
+    };
+
+    let key_pair = coin.as_ref().priv_key_policy.activated_key_or_err()?;
+    let signature_version = match my_address.addr_format() {
+        UtxoAddressFormat::Segwit => SignatureVersion::WitnessV0,
+        UtxoAddressFormat::Taproot => SignatureVersion::Taproot,
+        UtxoAddressFormat::Standard | UtxoAddressFormat::CashAddress { .. } => coin.as_ref().conf.signature_version,
+    };
+    let signed = sign_tx(unsigned, key_pair, signature_version, coin.as_ref().conf.fork_id)
+        .map_to_mm(|e| BumpFeeError::InternalError(e.to_string()))?;
+
+    let tx_details = tx_details_from_signed(coin, &my_address, &signed, spent_by_me, fee_amount)?;
+    Ok(BumpFeeResponse { method, tx_details })
+}
+
+/// Builds the replacement of the `tx` that pays at least the `fee` rate
+/// deducting the fee difference from the output to `my_address`.
+/// Returns the unsigned replacement, the amount spent by me and the new fee.
+async fn replace_by_fee<T>(
+    coin: &T,
+    my_address: &Address,
+    tx: UtxoTx,
+    fee: Option<ActualTxFee>,
+) -> BumpFeeResult<(TransactionInputSigner, u64, u64)>
+where
+    T: UtxoCommonOps,
+{
+    let tx_hash = tx.hash().reversed().to_string();
+    if !tx.signals_rbf() {
+        return MmError::err(BumpFeeError::TxNotReplaceable(tx_hash));
+    }
+
+    let my_script_pubkey = output_script(my_address)?.to_bytes();
+    let prev_outputs = get_unspents_for_inputs(coin.as_ref(), &tx.inputs).await?;
+    if prev_outputs
+        .iter()
+        .any(|prev| prev.script.to_bytes() != my_script_pubkey)
+    {
+        return MmError::err(BumpFeeError::ForeignInputs(tx_hash));
+    }
+    let spent_by_me: u64 = prev_outputs.iter().map(|prev| prev.value).sum();
+    let old_fee = spent_by_me.saturating_sub(tx.total_spends());
+
+    let mut unsigned = TransactionInputSigner::from(tx);
+    unsigned.consensus_branch_id = coin.as_ref().conf.consensus_branch_id;
+    for (input, prev) in unsigned.inputs.iter_mut().zip(prev_outputs) {
+        input.amount = prev.value;
+        input.prev_script = prev.script;
+    }
+
+    let v_size = tx_size_in_v_bytes(my_address.addr_format(), &UtxoTx::from(unsigned.clone())) as u64;
+    let decimals = coin.as_ref().decimals;
+    let new_fee = match fee {
+        Some(fee) => replacement_fee(old_fee, v_size, &fee, true, decimals)?,
+        None => replacement_fee(old_fee, v_size, &coin.get_tx_fee().await?, false, decimals)?,
+    };
+    deduct_fee_from_my_output(
+        &mut unsigned.outputs,
+        &my_script_pubkey,
+        new_fee - old_fee,
+        coin.as_ref().dust_amount,
+        decimals,
+        &tx_hash,
+    )?;
+
+    Ok((unsigned, spent_by_me, new_fee))
+}
+
+/// Builds a child transaction that spends the largest unspent output of the `tx` to `my_address`
+/// so the package (parent + child) pays at least the `fee` rate.
+/// Returns the unsigned child transaction, the amount spent by me and the child fee.
+async fn child_pays_for_parent<T>(
+    coin: &T,
+    my_address: &Address,
+    tx: UtxoTx,
+    fee: Option<ActualTxFee>,
+) -> BumpFeeResult<(TransactionInputSigner, u64, u64)>
+where
+    T: UtxoCommonOps + GetUtxoListOps,
+{
+    let parent_hash = tx.hash();
+    let tx_hash = parent_hash.reversed().to_string();
+
+    let prev_outputs = get_unspents_for_inputs(coin.as_ref(), &tx.inputs).await?;
+    let parent_inputs: u64 = prev_outputs.iter().map(|prev| prev.value).sum();
+    let parent_fee = parent_inputs.saturating_sub(tx.total_spends());
+    let parent_v_size = tx_v_size(&tx);
+
+    let _utxo_lock = UTXO_LOCK.lock().await;
+    let (unspents, _) = coin.get_unspent_ordered_list(my_address).await?;
+    let parent_output = unspents
+        .into_iter()
+        .filter(|unspent| unspent.outpoint.hash == parent_hash)
+        .max_by_key(|unspent| unspent.value)
+        .or_mm_err(|| BumpFeeError::NoOutputToMe(tx_hash))?;
+
+    let sequence = if coin.as_ref().conf.signal_rbf {
+        SEQUENCE_RBF_ENABLED
+    } else {
+        SEQUENCE_FINAL
+    };
+    let mut unsigned = coin.as_ref().transaction_preimage();
+    unsigned.inputs.push(UnsignedTransactionInput {
+        previous_output: parent_output.outpoint,
+        prev_script: parent_output.script,
+        sequence,
+        amount: parent_output.value,
+    });
+    unsigned.outputs.push(TransactionOutput {
+        value: parent_output.value,
+        script_pubkey: output_script(my_address)?.to_bytes(),
+    });
+
+    let child_v_size = tx_size_in_v_bytes(my_address.addr_format(), &UtxoTx::from(unsigned.clone())) as u64;
+    let decimals = coin.as_ref().decimals;
+    let fee = match fee {
+        Some(fee) => fee,
+        None => coin.get_tx_fee().await?,
+    };
+    let child_fee = package_child_fee(parent_fee, parent_v_size, child_v_size, &fee, decimals)?;
+    let my_script_pubkey = unsigned.outputs[0].script_pubkey.clone();
+    deduct_fee_from_my_output(
+        &mut unsigned.outputs,
+        &my_script_pubkey,
+        child_fee,
+        coin.as_ref().dust_amount,
+        decimals,
+        &tx_hash,
+    )?;
+
+    Ok((unsigned, parent_output.value, child_fee))
+}
+
+/// Calculates the fee of the replacement of `v_size` vbytes for the transaction that paid the `old_fee`.
+/// The `requested` fee rate must satisfy the BIP-125 rules, the network fee rate is raised to the required minimum.
+fn replacement_fee(old_fee: u64, v_size: u64, fee: &ActualTxFee, requested: bool, decimals: u8) -> BumpFeeResult<u64> {
+    // BIP-125 rule #4: the replacement must pay for its own bandwidth at the incremental relay fee rate.
+    let min_fee = old_fee + fee_for_v_size(&ActualTxFee::Dynamic(INCREMENTAL_RELAY_FEE_PER_KB), v_size);
+    let new_fee = fee_for_v_size(fee, v_size);
+    if new_fee >= min_fee {
+        return Ok(new_fee);
+    }
+    if !requested {
+        return Ok(min_fee);
+    }
+    MmError::err(BumpFeeError::FeeTooLow {
+        fee: big_decimal_from_sat(new_fee as i64, decimals),
+        required: big_decimal_from_sat(min_fee as i64, decimals),
+    })
+}
+
+/// Calculates the fee of the child transaction of `child_v_size` vbytes
+/// so the package (parent + child) pays the `fee` rate.
+fn package_child_fee(
+    parent_fee: u64,
+    parent_v_size: u64,
+    child_v_size: u64,
+    fee: &ActualTxFee,
+    decimals: u8,
+) -> BumpFeeResult<u64> {
+    // The child must pay at least for its own bandwidth to be relayed.
+    let min_fee = fee_for_v_size(&ActualTxFee::Dynamic(INCREMENTAL_RELAY_FEE_PER_KB), child_v_size);
+    let package_fee = fee_for_v_size(fee, parent_v_size + child_v_size);
+    let child_fee = package_fee.saturating_sub(parent_fee);
+    if child_fee < min_fee {
+        return MmError::err(BumpFeeError::FeeTooLow {
+            fee: big_decimal_from_sat(package_fee as i64, decimals),
+            required: big_decimal_from_sat((parent_fee + min_fee) as i64, decimals),
+        });
+    }
+    Ok(child_fee)
+}
+
+/// Deducts the `fee` from the largest output to `my_script_pubkey` that must stay above the `dust`.
+fn deduct_fee_from_my_output(
+    outputs: &mut [TransactionOutput],
+    my_script_pubkey: &[u8],
+    fee: u64,
+    dust: u64,
+    decimals: u8,
+    tx_hash: &str,
+) -> BumpFeeResult<()> {
+    let my_output = outputs
+        .iter_mut()
+        .filter(|output| output.script_pubkey.as_slice() == my_script_pubkey)
+        .max_by_key(|output| output.value)
+        .or_mm_err(|| BumpFeeError::NoOutputToMe(tx_hash.to_owned()))?;
+    if my_output.value < fee + dust {
+        return MmError::err(BumpFeeError::NotEnoughFunds {
+            available: big_decimal_from_sat(my_output.value as i64, decimals),
+            required: big_decimal_from_sat((fee + dust) as i64, decimals),
+        });
+    }
+    my_output.value -= fee;
+    Ok(())
+}
+
+fn actual_fee_from_withdraw_fee(fee: &WithdrawFee, decimals: u8) -> BumpFeeResult<ActualTxFee> {
+    match fee {
+        WithdrawFee::UtxoFixed { amount } => Ok(ActualTxFee::FixedPerKb(sat_from_big_decimal(amount, decimals)?)),
+        WithdrawFee::UtxoPerKbyte { amount } => Ok(ActualTxFee::Dynamic(sat_from_big_decimal(amount, decimals)?)),
+        fee_policy => {
+            let error = format!(
+                "Expected 'UtxoFixed' or 'UtxoPerKbyte' fee types, found {:?}",
+                fee_policy
+            );
+            MmError::err(BumpFeeError::InvalidFeePolicy(error))
+        },
+    }
+}
+
+/// Calculates the fee the same way as [`crate::utxo::utxo_common::UtxoTxBuilder`] does.
+fn fee_for_v_size(fee: &ActualTxFee, v_size: u64) -> u64 {
+    match fee {
+        ActualTxFee::Dynamic(fee_per_kb) => fee_per_kb * v_size / KILO_BYTE,
+        ActualTxFee::FixedPerKb(fee_per_kb) => fee_per_kb * ((v_size + KILO_BYTE - 1) / KILO_BYTE),
+    }
+}
+
+/// Virtual size of the signed transaction as it's defined in BIP-141.
+fn tx_v_size(tx: &UtxoTx) -> u64 {
+    let base_size = serialize(tx).len() as u64;
+    let total_size = serialize_with_flags(tx, SERIALIZE_TRANSACTION_WITNESS).len() as u64;
+    (base_size * 3 + total_size + 3) / 4
+}
+
+fn tx_details_from_signed<T: UtxoCommonOps>(
+    coin: &T,
+    my_address: &Address,
+    signed: &UtxoTx,
+    spent_by_me: u64,
+    fee_amount: u64,
+) -> BumpFeeResult<TransactionDetails> {
+    let ticker = coin.as_ref().conf.ticker.clone();
+    let decimals = coin.as_ref().decimals;
+    let my_script_pubkey = output_script(my_address)?.to_bytes();
+
+    let received_by_me: u64 = signed
+        .outputs
+        .iter()
+        .filter(|output| output.script_pubkey == my_script_pubkey)
+        .map(|output| output.value)
+        .sum();
+    let mut to = Vec::new();
+    for output in signed.outputs.iter() {
+        let script = Script::from(output.script_pubkey.clone());
+        for address in coin
+            .addresses_from_script(&script)
+            .map_to_mm(BumpFeeError::InternalError)?
+        {
+            let address = address.display_address().map_to_mm(BumpFeeError::InternalError)?;
+            if !to.contains(&address) {
+                to.push(address);
+            }
+        }
+    }
+    let from = my_address.display_address().map_to_mm(BumpFeeError::InternalError)?;
+
+    let fee_details = UtxoFeeDetails {
+        coin: Some(ticker.clone()),
+        amount: big_decimal_from_sat(fee_amount as i64, decimals),
+    };
+    let tx_hex = match my_address.addr_format() {
+        UtxoAddressFormat::Segwit | UtxoAddressFormat::Taproot => {
+            serialize_with_flags(signed, SERIALIZE_TRANSACTION_WITNESS).into()
+        },
+        _ => serialize(signed).into(),
+    };
+    Ok(TransactionDetails {
+        from: vec![from],
+        to,
+        total_amount: big_decimal_from_sat(spent_by_me as i64, decimals),
+        spent_by_me: big_decimal_from_sat(spent_by_me as i64, decimals),
+        received_by_me: big_decimal_from_sat(received_by_me as i64, decimals),
+        my_balance_change: big_decimal_from_sat(received_by_me as i64 - spent_by_me as i64, decimals),
+        tx: TransactionData::new_signed(tx_hex, signed.hash().reversed().to_vec().to_tx_hash()),
+        fee_details: Some(fee_details.into()),
+        block_height: 0,
+        coin: ticker,
+        internal_id: vec![].into(),
+        timestamp: now_sec(),
+        kmd_rewards: None,
+        transaction_type: Default::default(),
+        memo: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_for_v_size() {
+        assert_eq!(fee_for_v_size(&ActualTxFee::Dynamic(1000), 226), 226);
+        assert_eq!(fee_for_v_size(&ActualTxFee::Dynamic(20_000), 141), 2820);
+        // DOGE-like fee is paid for every started kilobyte.
+        assert_eq!(fee_for_v_size(&ActualTxFee::FixedPerKb(100_000), 226), 100_000);
+        assert_eq!(fee_for_v_size(&ActualTxFee::FixedPerKb(100_000), 1001), 200_000);
+    }
+
+    #[test]
+    fn test_tx_v_size() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
+        let segwit_tx: UtxoTx = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000".into();
+        assert_eq!(tx_v_size(&segwit_tx), 261);
+
+        let legacy_tx: UtxoTx = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+        assert_eq!(tx_v_size(&legacy_tx), serialize(&legacy_tx).len() as u64);
+    }
+
+    #[test]
+    fn test_replacement_fee() {
+        // The replacement must pay the old fee + 1 sat/vbyte at least.
+        assert_eq!(
+            replacement_fee(1000, 200, &ActualTxFee::Dynamic(10_000), true, 8).unwrap(),
+            2000
+        );
+        assert_eq!(
+            replacement_fee(1000, 200, &ActualTxFee::Dynamic(6000), true, 8).unwrap(),
+            1200
+        );
+        let err = replacement_fee(1000, 200, &ActualTxFee::Dynamic(5000), true, 8).unwrap_err();
+        match err.into_inner() {
+            BumpFeeError::FeeTooLow { fee, required } => {
+                assert_eq!(fee, "0.00001".parse().unwrap());
+                assert_eq!(required, "0.000012".parse().unwrap());
+            },
+            e => panic!("Unexpected error: {}", e),
+        }
+        // The network fee rate is raised to the minimum.
+        assert_eq!(
+            replacement_fee(1000, 200, &ActualTxFee::Dynamic(1000), false, 8).unwrap(),
+            1200
+        );
+    }
+
+    #[test]
+    fn test_package_child_fee() {
+        // The package of 200 + 110 vbytes pays 10 sat/vbyte.
+        assert_eq!(
+            package_child_fee(200, 200, 110, &ActualTxFee::Dynamic(10_000), 8).unwrap(),
+            2900
+        );
+        // The child pays for its own bandwidth exactly.
+        assert_eq!(
+            package_child_fee(200, 200, 110, &ActualTxFee::Dynamic(1000), 8).unwrap(),
+            110
+        );
+        // The parent already pays more than the requested rate.
+        let err = package_child_fee(400, 200, 110, &ActualTxFee::Dynamic(1500), 8).unwrap_err();
+        match err.into_inner() {
+            BumpFeeError::FeeTooLow { fee, required } => {
+                assert_eq!(fee, "0.00000465".parse().unwrap());
+                assert_eq!(required, "0.0000051".parse().unwrap());
+            },
+            e => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_deduct_fee_from_my_output() {
+        let my_script: Vec<u8> = vec![1; 25];
+        let other_script: Vec<u8> = vec![2; 25];
+        let mut outputs = vec![
+            TransactionOutput {
+                value: 50_000,
+                script_pubkey: other_script.clone().into(),
+            },
+            TransactionOutput {
+                value: 3000,
+                script_pubkey: my_script.clone().into(),
+            },
+            TransactionOutput {
+                value: 10_000,
+                script_pubkey: my_script.clone().into(),
+            },
+        ];
+
+        // The fee is deducted from the largest output to me.
+        deduct_fee_from_my_output(&mut outputs, &my_script, 1000, 1000, 8, "tx").unwrap();
+        let values: Vec<_> = outputs.iter().map(|output| output.value).collect();
+        assert_eq!(values, vec![50_000, 3000, 9000]);
+
+        // The output must stay above the dust.
+        let err = deduct_fee_from_my_output(&mut outputs, &my_script, 8500, 1000, 8, "tx").unwrap_err();
+        match err.into_inner() {
+            BumpFeeError::NotEnoughFunds { available, required } => {
+                assert_eq!(available, "0.00009".parse().unwrap());
+                assert_eq!(required, "0.000095".parse().unwrap());
+            },
+            e => panic!("Unexpected error: {}", e),
+        }
+        assert_eq!(outputs[2].value, 9000);
+
+        let err = deduct_fee_from_my_output(&mut outputs[..1], &my_script, 1000, 1000, 8, "tx").unwrap_err();
+        assert!(matches!(err.into_inner(), BumpFeeError::NoOutputToMe(_)));
+        assert_eq!(outputs[0].value, 50_000);
+    }
+}
@@ -0,0 +1,206 @@
+//! Coin control of UTXO coins: locking/unlocking of the unspent outputs and listing of the spendable ones.
+
+use crate::hd_wallet::HDWalletCoinOps;
+use crate::rpc_command::coin_control::{AddressUnspents, CoinControlError, CoinControlResult, ListUnspentResponse,
+                                       UnspentOutputInfo};
+use crate::utxo::rpc_clients::UnspentInfo;
+use crate::utxo::utxo_common::big_decimal_from_sat;
+use crate::utxo::utxo_hd_wallet::UtxoHDWallet;
+use crate::utxo::utxo_lock_storage::{UtxoLockStorage, UtxoLockStorageOps, UtxoLockStorageResult};
+use crate::utxo::{GetUtxoMapOps, UtxoCoinFields, UtxoCommonOps};
+use crate::{big_decimal_from_sat_unsigned, CoinBalance, CoinWithDerivationMethod};
+use chain::OutPoint;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use rpc::v1::types::H256 as H256Json;
+
+/// The reference to a transaction output as it's displayed and accepted by the RPC.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct UnspentOutPoint {
+    /// The transaction hash in the displayed (reversed) byte order.
+    pub tx_hash: H256Json,
+    pub index: u32,
+}
+
+impl From<OutPoint> for UnspentOutPoint {
+    fn from(outpoint: OutPoint) -> Self {
+        UnspentOutPoint {
+            tx_hash: outpoint.hash.reversed().into(),
+            index: outpoint.index,
+        }
+    }
+}
+
+impl From<UnspentOutPoint> for OutPoint {
+    fn from(outpoint: UnspentOutPoint) -> Self {
+        OutPoint {
+            hash: outpoint.tx_hash.reversed().into(),
+            index: outpoint.index,
+        }
+    }
+}
+
+/// Removes the unspents locked by the user from the given `unspents` list.
+pub fn exclude_locked_unspents<T>(coin: &T, unspents: &mut Vec<UnspentInfo>)
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let locked = coin.as_ref().locked_unspents.lock().unwrap();
+    if !locked.is_empty() {
+        unspents.retain(|unspent| !locked.contains(&unspent.outpoint));
+    }
+}
+
+/// Whether any outpoint of the coin is locked by the user.
+pub fn has_locked_unspents<T>(coin: &T) -> bool
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    !coin.as_ref().locked_unspents.lock().unwrap().is_empty()
+}
+
+/// Moves the value of the `unspents` locked by the user from the spendable part of the `balance`
+/// to the unspendable one, so the locked coins are counted neither by withdrawals nor by trading volumes.
+pub fn move_locked_to_unspendable<T>(coin: &T, unspents: &[UnspentInfo], balance: &mut CoinBalance)
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let locked_sat: u64 = {
+        let locked = coin.as_ref().locked_unspents.lock().unwrap();
+        unspents
+            .iter()
+            .filter(|unspent| locked.contains(&unspent.outpoint))
+            .map(|unspent| unspent.value)
+            .sum()
+    };
+    if locked_sat == 0 {
+        return;
+    }
+    // The balance might be requested from a different source than the unspents.
+    let locked_amount =
+        big_decimal_from_sat_unsigned(locked_sat, coin.as_ref().decimals).min(balance.spendable.clone());
+    balance.spendable = &balance.spendable - &locked_amount;
+    balance.unspendable = &balance.unspendable + &locked_amount;
+}
+
+/// Returns the outpoints locked by the user sorted by the transaction hash and the output index.
+pub fn locked_unspents<T>(coin: &T) -> Vec<UnspentOutPoint>
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let locked = coin.as_ref().locked_unspents.lock().unwrap();
+    let mut result: Vec<UnspentOutPoint> = locked.active().into_iter().map(UnspentOutPoint::from).collect();
+    result.sort();
+    result
+}
+
+/// Persistently locks the given `outpoints` so they won't be spent until they're unlocked.
+/// The outpoints don't have to be unspent at the moment,
+/// e.g. it's allowed to lock an output of a not yet received transaction.
+pub async fn lock_unspents<T>(
+    ctx: &MmArc,
+    coin: &T,
+    outpoints: Vec<UnspentOutPoint>,
+) -> CoinControlResult<Vec<UnspentOutPoint>>
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let outpoints: Vec<OutPoint> = outpoints.into_iter().map(OutPoint::from).collect();
+    let storage = UtxoLockStorage::new_from_ctx(ctx, coin.as_ref().conf.ticker.clone())?;
+    storage.init().await?;
+    storage.lock_outpoints(outpoints.clone(), None).await?;
+
+    coin.as_ref().locked_unspents.lock().unwrap().lock(outpoints, None);
+    Ok(locked_unspents(coin))
+}
+
+/// Unlocks the given `outpoints` so they can be spent again.
+pub async fn unlock_unspents<T>(
+    ctx: &MmArc,
+    coin: &T,
+    outpoints: Vec<UnspentOutPoint>,
+) -> CoinControlResult<Vec<UnspentOutPoint>>
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let outpoints: Vec<OutPoint> = outpoints.into_iter().map(OutPoint::from).collect();
+    let storage = UtxoLockStorage::new_from_ctx(ctx, coin.as_ref().conf.ticker.clone())?;
+    storage.init().await?;
+    storage.unlock_outpoints(outpoints.clone()).await?;
+
+    coin.as_ref().locked_unspents.lock().unwrap().unlock(&outpoints);
+    Ok(locked_unspents(coin))
+}
+
+/// Persistently locks the given `outpoints` until `expires_at` (a timestamp in seconds)
+/// unless they're unlocked earlier, e.g. the inputs of a PSBT that is being signed by external signers.
+pub async fn lock_unspents_until<T>(
+    ctx: &MmArc,
+    coin: &T,
+    outpoints: Vec<OutPoint>,
+    expires_at: u64,
+) -> UtxoLockStorageResult<()>
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let storage = UtxoLockStorage::new_from_ctx(ctx, coin.as_ref().conf.ticker.clone())?;
+    storage.init().await?;
+    storage.lock_outpoints(outpoints.clone(), Some(expires_at)).await?;
+
+    coin.as_ref()
+        .locked_unspents
+        .lock()
+        .unwrap()
+        .lock(outpoints, Some(expires_at));
+    Ok(())
+}
+
+/// Unlocks those of the given `outpoints` that were locked by [`lock_unspents_until`],
+/// the outpoints locked by the user are kept locked.
+pub async fn unlock_expiring_unspents<T>(ctx: &MmArc, coin: &T, outpoints: &[OutPoint]) -> UtxoLockStorageResult<()>
+where
+    T: AsRef<UtxoCoinFields>,
+{
+    let unlocked = coin.as_ref().locked_unspents.lock().unwrap().unlock_expiring(outpoints);
+    if unlocked.is_empty() {
+        return Ok(());
+    }
+
+    let storage = UtxoLockStorage::new_from_ctx(ctx, coin.as_ref().conf.ticker.clone())?;
+    storage.init().await?;
+    storage.unlock_outpoints(unlocked).await
+}
+
+/// Returns the spendable unspent outputs of every known address:
+/// the activated address in case of an Iguana wallet, or all the known addresses of every HD account.
+pub async fn list_unspent<T>(coin: &T) -> CoinControlResult<ListUnspentResponse>
+where
+    T: UtxoCommonOps + GetUtxoMapOps + CoinWithDerivationMethod + HDWalletCoinOps<HDWallet = UtxoHDWallet>,
+{
+    let decimals = coin.as_ref().decimals;
+    let addresses = coin.all_addresses().await?.into_iter().collect();
+    let (unspents_map, _recently_spent) = coin.get_unspent_ordered_map(addresses).await?;
+
+    let mut addresses = Vec::with_capacity(unspents_map.len());
+    for (address, unspents) in unspents_map {
+        let unspents = unspents
+            .into_iter()
+            .map(|unspent| UnspentOutputInfo {
+                outpoint: unspent.outpoint.into(),
+                value: big_decimal_from_sat(unspent.value as i64, decimals),
+                height: unspent.height,
+            })
+            .collect();
+        addresses.push(AddressUnspents {
+            address: address.display_address().map_to_mm(CoinControlError::InternalError)?,
+            unspents,
+        });
+    }
+    addresses.sort_by(|a, b| a.address.cmp(&b.address));
+
+    Ok(ListUnspentResponse {
+        coin: coin.as_ref().conf.ticker.clone(),
+        addresses,
+        locked: locked_unspents(coin),
+    })
+}
@@ -9,26 +9,29 @@ use crate::utxo::rpc_clients::{electrum_script_hash, BlockHashOrHeight, UnspentI
                                UtxoRpcClientOps, UtxoRpcResult};
 use crate::utxo::spv::SimplePaymentVerification;
 use crate::utxo::tx_cache::TxCacheResult;
+use crate::utxo::utxo_coin_control::{exclude_locked_unspents, has_locked_unspents, move_locked_to_unspendable};
 use crate::utxo::utxo_hd_wallet::UtxoHDAddress;
 use crate::utxo::utxo_withdraw::{InitUtxoWithdraw, StandardUtxoWithdraw, UtxoWithdraw};
 use crate::watcher_common::validate_watcher_reward;
 use crate::{scan_for_new_addresses_impl, CanRefundHtlc, CoinBalance, CoinWithDerivationMethod, ConfirmPaymentInput,
-            DexFee, DexFeeBurnDestination, GenPreimageResult, GenTakerFundingSpendArgs, GenTakerPaymentSpendArgs,
-            GetWithdrawSenderAddress, RawTransactionError, RawTransactionRequest, RawTransactionRes,
-            RawTransactionResult, RefundFundingSecretArgs, RefundMakerPaymentSecretArgs, RefundPaymentArgs,
-            RewardTarget, SearchForSwapTxSpendInput, SendMakerPaymentArgs, SendMakerPaymentSpendPreimageInput,
-            SendPaymentArgs, SendTakerFundingArgs, SignRawTransactionEnum, SignRawTransactionRequest,
-            SignUtxoTransactionParams, SignatureError, SignatureResult, SpendMakerPaymentArgs, SpendPaymentArgs,
-            SwapOps, SwapTxTypeWithSecretHash, TradePreimageValue, TransactionData, TransactionFut, TransactionResult,
-            TxFeeDetails, TxGenError, TxMarshalingErr, TxPreimageWithSig, ValidateAddressResult,
-            ValidateOtherPubKeyErr, ValidatePaymentFut, ValidatePaymentInput, ValidateSwapV2TxError,
-            ValidateSwapV2TxResult, ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageError,
-            ValidateTakerFundingSpendPreimageResult, ValidateTakerPaymentSpendPreimageError,
-            ValidateTakerPaymentSpendPreimageResult, ValidateWatcherSpendInput, VerificationError, VerificationResult,
-            WatcherSearchForSwapTxSpendInput, WatcherValidatePaymentInput, WatcherValidateTakerFeeInput,
-            WithdrawResult, WithdrawSenderAddress, EARLY_CONFIRMATION_ERR_LOG, INVALID_RECEIVER_ERR_LOG,
-            INVALID_REFUND_TX_ERR_LOG, INVALID_SCRIPT_ERR_LOG, INVALID_SENDER_ERR_LOG, OLD_TRANSACTION_ERR_LOG};
-use crate::{MmCoinEnum, WatcherReward, WatcherRewardError};
+            DexFee, DexFeeBurnDestination, FindPaymentSpendError, FundingTxSpend, GenPreimageResult,
+            GenTakerFundingSpendArgs, GenTakerPaymentSpendArgs, GetWithdrawSenderAddress, RawTransactionError,
+            RawTransactionRequest, RawTransactionRes, RawTransactionResult, RefundFundingSecretArgs,
+            RefundMakerPaymentSecretArgs, RefundMakerPaymentTimelockArgs, RefundPaymentArgs, RefundTakerPaymentArgs,
+            RewardTarget, SearchForFundingSpendErr, SearchForSwapTxSpendInput, SendMakerPaymentArgs,
+            SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SendTakerFundingArgs, SignRawTransactionEnum,
+            SignRawTransactionRequest, SignUtxoTransactionParams, SignatureError, SignatureResult,
+            SpendMakerPaymentArgs, SpendPaymentArgs, SwapOps, SwapTxTypeWithSecretHash, TradePreimageValue,
+            TransactionData, TransactionFut, TransactionResult, TxFeeDetails, TxGenError, TxMarshalingErr,
+            TxPreimageWithSig, ValidateAddressResult, ValidateMakerPaymentArgs, ValidateOtherPubKeyErr,
+            ValidatePaymentFut, ValidatePaymentInput, ValidateSwapV2TxError, ValidateSwapV2TxResult,
+            ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageError, ValidateTakerFundingSpendPreimageResult,
+            ValidateTakerPaymentSpendPreimageError, ValidateTakerPaymentSpendPreimageResult,
+            ValidateWatcherSpendInput, VerificationError, VerificationResult, WatcherSearchForSwapTxSpendInput,
+            WatcherValidatePaymentInput, WatcherValidateTakerFeeInput, WithdrawResult, WithdrawSenderAddress,
+            EARLY_CONFIRMATION_ERR_LOG, INVALID_RECEIVER_ERR_LOG, INVALID_REFUND_TX_ERR_LOG, INVALID_SCRIPT_ERR_LOG,
+            INVALID_SENDER_ERR_LOG, OLD_TRANSACTION_ERR_LOG};
+use crate::{MakerPaymentSpendForWatcherArgs, MmCoinEnum, WatcherReward, WatcherRewardError};
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 pub use bitcrypto::{dhash160, sha256, ChecksumType};
@@ -67,7 +70,7 @@ use std::sync::atomic::Ordering as AtomicOrdering;
 #[cfg(test)]
 use utxo_common_tests::{utxo_coin_fields_for_test, utxo_coin_from_fields};
 use utxo_signer::with_key_pair::{calc_and_sign_sighash, p2sh_spend, signature_hash_to_sign, SIGHASH_ALL,
-                                 SIGHASH_SINGLE};
+                                 SIGHASH_ANYONECANPAY, SIGHASH_SINGLE};
 use utxo_signer::UtxoSignerOps;
 
 pub mod utxo_tx_history_v2_common;
@@ -214,22 +217,34 @@ pub async fn address_balance<T>(coin: &T, address: &Address) -> BalanceResult<Co
 where
     T: UtxoCommonOps + GetUtxoListOps + MarketCoinOps,
 {
+    let decimals = coin.as_ref().decimals;
     if coin.as_ref().check_utxo_maturity {
         let (unspents, _) = coin.get_mature_unspent_ordered_list(address).await?;
-        return Ok(unspents.to_coin_balance(coin.as_ref().decimals));
+        let mut balance = unspents.to_coin_balance(decimals);
+        move_locked_to_unspendable(coin, &unspents.mature, &mut balance);
+        return Ok(balance);
     }
 
-    let balance = coin
+    let spendable = coin
         .as_ref()
         .rpc_client
-        .display_balance(address.clone(), coin.as_ref().decimals)
+        .display_balance(address.clone(), decimals)
         .compat()
         .await?;
-
-    Ok(CoinBalance {
-        spendable: balance,
+    let mut balance = CoinBalance {
+        spendable,
         unspendable: BigDecimal::from(0),
-    })
+    };
+    if has_locked_unspents(coin) {
+        let unspents = coin
+            .as_ref()
+            .rpc_client
+            .list_unspent(address, decimals)
+            .compat()
+            .await?;
+        move_locked_to_unspendable(coin, &unspents, &mut balance);
+    }
+    Ok(balance)
 }
 
 /// Requests balances of the given `addresses`.
@@ -238,6 +253,7 @@ pub async fn addresses_balances<T>(coin: &T, addresses: Vec<Address>) -> Balance
 where
     T: UtxoCommonOps + GetUtxoMapOps + MarketCoinOps,
 {
+    let decimals = coin.as_ref().decimals;
     if coin.as_ref().check_utxo_maturity {
         let (unspents_map, _) = coin.get_mature_unspent_ordered_map(addresses.clone()).await?;
         addresses
@@ -247,21 +263,35 @@ where
                     let error = format!("'get_mature_unspent_ordered_map' should have returned '{}'", address);
                     BalanceError::Internal(error)
                 })?;
-                let balance = unspents.to_coin_balance(coin.as_ref().decimals);
+                let mut balance = unspents.to_coin_balance(decimals);
+                move_locked_to_unspendable(coin, &unspents.mature, &mut balance);
                 Ok((address, balance))
             })
             .collect()
     } else {
-        Ok(coin
+        let balances = coin
             .as_ref()
             .rpc_client
-            .display_balances(addresses.clone(), coin.as_ref().decimals)
+            .display_balances(addresses.clone(), decimals)
             .compat()
-            .await?
+            .await?;
+        let unspents_map = if has_locked_unspents(coin) {
+            coin.as_ref()
+                .rpc_client
+                .list_unspent_group(addresses, decimals)
+                .compat()
+                .await?
+        } else {
+            UnspentMap::new()
+        };
+        Ok(balances
             .into_iter()
             .map(|(address, spendable)| {
                 let unspendable = BigDecimal::from(0);
-                let balance = CoinBalance { spendable, unspendable };
+                let mut balance = CoinBalance { spendable, unspendable };
+                if let Some(unspents) = unspents_map.get(&address) {
+                    move_locked_to_unspendable(coin, unspents, &mut balance);
+                }
                 (address, balance)
             })
             .collect())
@@ -322,6 +352,7 @@ pub fn addresses_from_script<T: UtxoCommonOps>(coin: &T, script: &Script) -> Res
                 ),
                 AddressScriptType::P2WPKH => (UtxoAddressFormat::Segwit, AddressBuilderOption::PubkeyHash(dst.hash)),
                 AddressScriptType::P2WSH => (UtxoAddressFormat::Segwit, AddressBuilderOption::ScriptHash(dst.hash)),
+                AddressScriptType::P2TR => (UtxoAddressFormat::Taproot, AddressBuilderOption::PubkeyHash(dst.hash)),
             };
 
             AddressBuilder::new(
@@ -423,6 +454,35 @@ pub fn tx_size_in_v_bytes(from_addr_format: &UtxoAddressFormat, tx: &UtxoTx) ->
             let total_size = transaction_bytes.len() + 4 + tx.inputs().len() * (additional_len + 1);
             ((0.75 * base_size as f64) + (0.25 * total_size as f64)) as usize
         },
+        UtxoAddressFormat::Taproot => {
+            let base_size = transaction_bytes.len();
+            // The witness of a key path spending consists of a single Schnorr signature only,
+            // 1 byte for the number of the witness items and 1 byte for the signature length
+            let total_size = transaction_bytes.len() + 4 + tx.inputs().len() * (2 + SCHNORR_SIGNATURE_LEN);
+            ((0.75 * base_size as f64) + (0.25 * total_size as f64)) as usize
+        },
+        _ => transaction_bytes.len() + tx.inputs().len() * additional_len,
+    }
+}
+
+/// Estimates the virtual size of the transaction spending m-of-n multisig P2SH or P2WSH outputs only.
+/// `script_len` is the length of the redeem script or the witness script correspondingly.
+pub fn multisig_tx_size_in_v_bytes(
+    from_addr_format: &UtxoAddressFormat,
+    tx: &UtxoTx,
+    required: usize,
+    script_len: usize,
+) -> usize {
+    let transaction_bytes = serialize(tx);
+    // `OP_0`, `required` signatures with their lengths and the script with up to 3 bytes of its length
+    let additional_len = 1 + required * (1 + MAX_DER_SIGNATURE_LEN) + 3 + script_len;
+    match from_addr_format {
+        UtxoAddressFormat::Segwit => {
+            let base_size = transaction_bytes.len();
+            // 4 additional bytes for the marker and the flag and 1 additional byte for the number of the witness items
+            let total_size = transaction_bytes.len() + 4 + tx.inputs().len() * (additional_len + 1);
+            ((0.75 * base_size as f64) + (0.25 * total_size as f64)) as usize
+        },
         _ => transaction_bytes.len() + tx.inputs().len() * additional_len,
     }
 }
@@ -435,21 +495,23 @@ pub fn output_script_checked(coin: &UtxoCoinFields, addr: &Address) -> MmResult<
                 return MmError::err(UnsupportedAddr::PrefixError(coin.conf.ticker.clone()));
             }
         },
-        UtxoAddressFormat::Segwit => match (coin.conf.bech32_hrp.as_ref(), addr.hrp().as_ref()) {
-            (Some(conf_hrp), Some(addr_hrp)) => {
-                if conf_hrp != addr_hrp {
+        UtxoAddressFormat::Segwit | UtxoAddressFormat::Taproot => {
+            match (coin.conf.bech32_hrp.as_ref(), addr.hrp().as_ref()) {
+                (Some(conf_hrp), Some(addr_hrp)) => {
+                    if conf_hrp != addr_hrp {
+                        return MmError::err(UnsupportedAddr::HrpError {
+                            ticker: coin.conf.ticker.clone(),
+                            hrp: addr_hrp.to_string(),
+                        });
+                    }
+                },
+                (_, _) => {
                     return MmError::err(UnsupportedAddr::HrpError {
                         ticker: coin.conf.ticker.clone(),
-                        hrp: addr_hrp.to_string(),
+                        hrp: addr.hrp().clone().unwrap_or_else(|| "".to_owned()),
                     });
-                }
-            },
-            (_, _) => {
-                return MmError::err(UnsupportedAddr::HrpError {
-                    ticker: coin.conf.ticker.clone(),
-                    hrp: addr.hrp().clone().unwrap_or_else(|| "".to_owned()),
-                });
-            },
+                },
+            }
         },
         UtxoAddressFormat::CashAddress {
             network: _,
@@ -481,6 +543,10 @@ pub struct UtxoTxBuilder<'a, T: AsRef<UtxoCoinFields> + UtxoTxGenerationOps> {
     tx_fee: u64,
     min_relay_fee: Option<u64>,
     dust: Option<u64>,
+    /// `nSequence` of the inputs added by the builder.
+    input_sequence: u32,
+    /// The number of the required signatures and the script length if the inputs spend multisig outputs.
+    multisig_inputs: Option<(usize, usize)>,
 }
 
 impl<'a, T: AsRef<UtxoCoinFields> + UtxoTxGenerationOps> UtxoTxBuilder<'a, T> {
@@ -499,6 +565,8 @@ impl<'a, T: AsRef<UtxoCoinFields> + UtxoTxGenerationOps> UtxoTxBuilder<'a, T> {
             tx_fee: 0,
             min_relay_fee: None,
             dust: None,
+            input_sequence: SEQUENCE_FINAL,
+            multisig_inputs: None,
         }
     }
 
@@ -512,18 +580,42 @@ impl<'a, T: AsRef<UtxoCoinFields> + UtxoTxGenerationOps> UtxoTxBuilder<'a, T> {
         self
     }
 
+    /// Sets `nSequence` of the inputs, e.g. `SEQUENCE_RBF_ENABLED` to signal the replace-by-fee.
+    /// Applies to the already added inputs as well.
+    pub fn with_input_sequence(mut self, sequence: u32) -> Self {
+        self.input_sequence = sequence;
+        self.tx.inputs.iter_mut().for_each(|input| input.sequence = sequence);
+        self
+    }
+
+    /// Estimates the fee considering the inputs spend m-of-n multisig outputs
+    /// with `required` signatures and the redeem or witness script of `script_len` bytes.
+    pub fn with_multisig_inputs(mut self, required: usize, script_len: usize) -> Self {
+        self.multisig_inputs = Some((required, script_len));
+        self
+    }
+
     pub fn add_required_inputs(mut self, inputs: impl IntoIterator<Item = UnspentInfo>) -> Self {
+        let sequence = self.input_sequence;
         self.tx
             .inputs
             .extend(inputs.into_iter().map(|input| UnsignedTransactionInput {
                 previous_output: input.outpoint,
                 prev_script: input.script,
-                sequence: SEQUENCE_FINAL,
+                sequence,
                 amount: input.value,
             }));
         self
     }
 
+    /// Adds the inputs chosen by the user (coin control) that must be spent by the transaction.
+    /// Unlike [`UtxoTxBuilder::add_required_inputs`], the value of these inputs is counted as spent by me,
+    /// so the available inputs are added only if the selected ones don't cover the outputs and the fee.
+    pub fn add_selected_inputs(mut self, inputs: Vec<UnspentInfo>) -> Self {
+        self.sum_inputs += inputs.iter().map(|input| input.value).sum::<u64>();
+        self.add_required_inputs(inputs)
+    }
+
     /// This function expects that utxos are sorted by amounts in ascending order
     /// Consider sorting before calling this function
     pub fn add_available_inputs(mut self, inputs: impl IntoIterator<Item = UnspentInfo>) -> Self {
@@ -560,15 +652,17 @@ impl<'a, T: AsRef<UtxoCoinFields> + UtxoTxGenerationOps> UtxoTxBuilder<'a, T> {
         from_addr_format: &UtxoAddressFormat,
         actual_tx_fee: &ActualTxFee,
     ) -> bool {
-        self.tx_fee = match &actual_tx_fee {
-            ActualTxFee::Dynamic(f) => {
-                let transaction = UtxoTx::from(self.tx.clone());
-                let v_size = tx_size_in_v_bytes(from_addr_format, &transaction);
-                (f * v_size as u64) / KILO_BYTE
+        let transaction = UtxoTx::from(self.tx.clone());
+        let v_size = match self.multisig_inputs {
+            Some((required, script_len)) => {
+                multisig_tx_size_in_v_bytes(from_addr_format, &transaction, required, script_len)
             },
+            None => tx_size_in_v_bytes(from_addr_format, &transaction),
+        };
+        self.tx_fee = match &actual_tx_fee {
+            ActualTxFee::Dynamic(f) => (f * v_size as u64) / KILO_BYTE,
             ActualTxFee::FixedPerKb(f) => {
-                let transaction = UtxoTx::from(self.tx.clone());
-                let v_size = tx_size_in_v_bytes(from_addr_format, &transaction) as u64;
+                let v_size = v_size as u64;
                 let v_size_kb = if v_size % KILO_BYTE == 0 {
                     v_size / KILO_BYTE
                 } else {
@@ -692,7 +786,7 @@ impl<'a, T: AsRef<UtxoCoinFields> + UtxoTxGenerationOps> UtxoTxBuilder<'a, T> {
                 self.tx.inputs.push(UnsignedTransactionInput {
                     previous_output: utxo.outpoint,
                     prev_script: utxo.script,
-                    sequence: SEQUENCE_FINAL,
+                    sequence: self.input_sequence,
                     amount: utxo.value,
                 });
                 self.sum_inputs += utxo.value;
@@ -1712,24 +1806,40 @@ pub fn send_maker_payment_spend_preimage<T: UtxoCommonOps + SwapOps>(
     coin: &T,
     input: SendMakerPaymentSpendPreimageInput,
 ) -> TransactionFut {
-    let mut transaction: UtxoTx = try_tx_fus!(deserialize(input.preimage).map_err(|e| ERRL!("{:?}", e)));
+    let transaction = try_tx_fus!(maker_payment_spend_preimage_with_secret(input.preimage, input.secret));
+
+    let coin = coin.clone();
+    let fut = async move {
+        let tx_fut = coin.as_ref().rpc_client.send_transaction(&transaction).compat();
+        try_tx_s!(tx_fut.await, transaction);
+
+        Ok(transaction.into())
+    };
+
+    Box::new(fut.boxed().compat())
+}
+
+/// Inserts the `secret` into the script sig of the maker payment spend `preimage`
+/// that was signed by taker without the secret, see [`create_maker_payment_spend_preimage`].
+pub fn maker_payment_spend_preimage_with_secret(preimage: &[u8], secret: &[u8]) -> Result<UtxoTx, String> {
+    let mut transaction: UtxoTx = deserialize(preimage).map_err(|e| ERRL!("{:?}", e))?;
     if transaction.inputs.is_empty() {
-        return try_tx_fus!(TX_PLAIN_ERR!("Transaction doesn't have any input"));
+        return ERR!("Transaction doesn't have any input");
     }
     let script = Script::from(transaction.inputs[DEFAULT_SWAP_VIN].script_sig.clone());
     let mut instructions = script.iter();
 
-    let instruction_1 = try_tx_fus!(try_tx_fus!(instructions.next().ok_or("Instruction not found")));
-    let instruction_2 = try_tx_fus!(try_tx_fus!(instructions.next().ok_or("Instruction not found")));
+    let instruction_1 = try_s!(instructions.next().ok_or("Instruction not found"));
+    let instruction_2 = try_s!(instructions.next().ok_or("Instruction not found"));
 
-    let script_sig = try_tx_fus!(instruction_1
+    let script_sig = try_s!(try_s!(instruction_1)
         .data
         .ok_or("No script signature in the taker spends maker payment preimage"));
-    let redeem_script = try_tx_fus!(instruction_2
+    let redeem_script = try_s!(try_s!(instruction_2)
         .data
         .ok_or("No redeem script in the taker spends maker payment preimage"));
     let script_data = Builder::default()
-        .push_data(input.secret)
+        .push_data(secret)
         .push_opcode(Opcode::OP_0)
         .into_script();
 
@@ -1739,16 +1849,7 @@ pub fn send_maker_payment_spend_preimage<T: UtxoCommonOps + SwapOps>(
     resulting_script.extend_from_slice(&redeem_part);
 
     transaction.inputs[DEFAULT_SWAP_VIN].script_sig = resulting_script;
-
-    let coin = coin.clone();
-    let fut = async move {
-        let tx_fut = coin.as_ref().rpc_client.send_transaction(&transaction).compat();
-        try_tx_s!(tx_fut.await, transaction);
-
-        Ok(transaction.into())
-    };
-
-    Box::new(fut.boxed().compat())
+    Ok(transaction)
 }
 
 pub fn create_maker_payment_spend_preimage<T: UtxoCommonOps + SwapOps>(
@@ -2377,6 +2478,7 @@ pub async fn validate_maker_payment<T: UtxoCommonOps + SwapOps>(
 pub fn watcher_validate_taker_payment<T: UtxoCommonOps + SwapOps>(
     coin: &T,
     input: WatcherValidatePaymentInput,
+    output_index: usize,
 ) -> ValidatePaymentFut<()> {
     let taker_payment_tx: UtxoTx = try_f!(deserialize(input.payment_tx.as_slice()));
     let taker_payment_refund_preimage: UtxoTx = try_f!(deserialize(input.taker_payment_refund_preimage.as_slice()));
@@ -2401,7 +2503,7 @@ pub fn watcher_validate_taker_payment<T: UtxoCommonOps + SwapOps>(
             )));
         }
 
-        let taker_payment_locking_script = match taker_payment_tx.outputs.get(DEFAULT_SWAP_VOUT) {
+        let taker_payment_locking_script = match taker_payment_tx.outputs.get(output_index) {
             Some(output) => output.script_pubkey.clone(),
             None => {
                 return MmError::err(ValidatePaymentError::WrongPaymentTx(
@@ -2486,6 +2588,7 @@ pub async fn validate_taker_payment<T: UtxoCommonOps + SwapOps>(
 pub fn validate_payment_spend_or_refund<T: UtxoCommonOps + SwapOps>(
     coin: &T,
     input: ValidateWatcherSpendInput,
+    output_index: usize,
 ) -> ValidatePaymentFut<()> {
     let mut payment_spend_tx: UtxoTx = try_f!(deserialize(input.payment_tx.as_slice()));
     payment_spend_tx.tx_hash_algo = coin.as_ref().tx_hash_algo;
@@ -2496,7 +2599,7 @@ pub fn validate_payment_spend_or_refund<T: UtxoCommonOps + SwapOps>(
         let expected_script_pubkey = output_script(&my_address).map(|script| script.to_bytes())?;
         let output = payment_spend_tx
             .outputs
-            .get(DEFAULT_SWAP_VOUT)
+            .get(output_index)
             .ok_or_else(|| ValidatePaymentError::WrongPaymentTx("Payment tx has no outputs".to_string()))?;
 
         if expected_script_pubkey != output.script_pubkey {
@@ -2817,7 +2920,7 @@ pub fn send_raw_tx_bytes(
 }
 
 /// Helper to load unspent outputs from cache or rpc
-async fn get_unspents_for_inputs(
+pub async fn get_unspents_for_inputs(
     coin: &UtxoCoinFields,
     inputs: &Vec<TransactionInput>,
 ) -> Result<Vec<UnspentInfo>, RawTransactionError> {
@@ -3220,6 +3323,17 @@ pub fn convert_to_address<T: UtxoCommonOps>(coin: &T, from: &str, to_address_for
                 None => ERR!("Cannot convert to a segwit address for a coin with no bech32_hrp in config"),
             }
         },
+        UtxoAddressFormat::Taproot => {
+            // The output key can't be derived from an address hash.
+            if !from_address.hash().is_taproot_output_key() {
+                return ERR!("Only a taproot address can be converted to the taproot format");
+            }
+            let bech32_hrp = &coin.as_ref().conf.bech32_hrp;
+            match bech32_hrp {
+                Some(hrp) => Ok(SegwitAddress::new(from_address.hash(), hrp.clone()).to_string()),
+                None => ERR!("Cannot convert to a taproot address for a coin with no bech32_hrp in config"),
+            }
+        },
         UtxoAddressFormat::CashAddress { network, .. } => Ok(try_s!(from_address
             .to_cashaddress(&network, &coin.as_ref().conf.address_prefixes)
             .and_then(|cashaddress| cashaddress.encode()))),
@@ -4661,14 +4775,16 @@ pub async fn get_unspent_ordered_list<'a, T>(
 where
     T: UtxoCommonOps + GetUtxoListOps,
 {
-    if coin.as_ref().check_utxo_maturity {
+    let (mut unspents, recently_spent) = if coin.as_ref().check_utxo_maturity {
         coin.get_mature_unspent_ordered_list(address)
             .await
             // Convert `MatureUnspentList` into `Vec<UnspentInfo>` by discarding immature unspents.
-            .map(|(mature_unspents, recently_spent)| (mature_unspents.only_mature(), recently_spent))
+            .map(|(mature_unspents, recently_spent)| (mature_unspents.only_mature(), recently_spent))?
     } else {
-        coin.get_all_unspent_ordered_list(address).await
-    }
+        coin.get_all_unspent_ordered_list(address).await?
+    };
+    exclude_locked_unspents(coin, &mut unspents);
+    Ok((unspents, recently_spent))
 }
 
 /// [`GetUtxoMapOps::get_unspent_ordered_map`] implementation.
@@ -4681,7 +4797,7 @@ pub async fn get_unspent_ordered_map<T>(
 where
     T: UtxoCommonOps + GetUtxoMapOps,
 {
-    if coin.as_ref().check_utxo_maturity {
+    let (mut unspents_map, recently_spent) = if coin.as_ref().check_utxo_maturity {
         coin.get_mature_unspent_ordered_map(addresses)
             .await
             // Convert `MatureUnspentMap` into `UnspentMap` by discarding immature unspents.
@@ -4691,10 +4807,14 @@ where
                     .map(|(address, unspents)| (address, unspents.only_mature()))
                     .collect();
                 (unspents_map, recently_spent)
-            })
+            })?
     } else {
-        coin.get_all_unspent_ordered_map(addresses).await
+        coin.get_all_unspent_ordered_map(addresses).await?
+    };
+    for unspents in unspents_map.values_mut() {
+        exclude_locked_unspents(coin, unspents);
     }
+    Ok((unspents_map, recently_spent))
 }
 
 /// [`GetUtxoListOps::get_all_unspent_ordered_list`] implementation.
@@ -4811,7 +4931,7 @@ pub fn addr_format(coin: &dyn AsRef<UtxoCoinFields>) -> &UtxoAddressFormat {
 
 pub fn addr_format_for_standard_scripts(coin: &dyn AsRef<UtxoCoinFields>) -> UtxoAddressFormat {
     match &coin.as_ref().conf.default_address_format {
-        UtxoAddressFormat::Segwit => UtxoAddressFormat::Standard,
+        UtxoAddressFormat::Segwit | UtxoAddressFormat::Taproot => UtxoAddressFormat::Standard,
         format @ (UtxoAddressFormat::Standard | UtxoAddressFormat::CashAddress { .. }) => format.clone(),
     }
 }
@@ -4834,7 +4954,7 @@ where
                 Ok(())
             }
         },
-        UtxoAddressFormat::Segwit => {
+        UtxoAddressFormat::Segwit | UtxoAddressFormat::Taproot => {
             if !conf.segwit {
                 return MmError::err(UnsupportedAddr::SegwitNotActivated(conf.ticker.clone()));
             }
@@ -5264,6 +5384,521 @@ where
     Ok(transaction)
 }
 
+/// Common implementation of maker payment v2 validation for UTXO coins.
+pub async fn validate_maker_payment_v2<T>(coin: &T, args: ValidateMakerPaymentArgs<'_, T>) -> ValidatePaymentResult<()>
+where
+    T: UtxoCommonOps + SwapOps,
+{
+    let taker_key_pair = coin.derive_htlc_key_pair(args.swap_unique_data);
+    let time_lock = args
+        .time_lock
+        .try_into()
+        .map_to_mm(ValidatePaymentError::TimelockOverflow)?;
+    validate_payment(
+        coin.clone(),
+        args.maker_payment_tx,
+        DEFAULT_SWAP_VOUT,
+        args.maker_pub,
+        taker_key_pair.public(),
+        SwapTxTypeWithSecretHash::MakerPaymentV2 {
+            maker_secret_hash: args.maker_secret_hash,
+            taker_secret_hash: args.taker_secret_hash,
+        },
+        args.amount,
+        None,
+        time_lock,
+        0,
+        0,
+    )
+    .await
+}
+
+/// Common implementation of maker payment v2 reclaim for UTXO coins using timelock path.
+pub async fn refund_maker_payment_v2_timelock<T>(
+    coin: T,
+    args: RefundMakerPaymentTimelockArgs<'_>,
+) -> Result<UtxoTx, TransactionErr>
+where
+    T: UtxoCommonOps + SwapOps,
+{
+    let args = RefundPaymentArgs {
+        payment_tx: args.payment_tx,
+        time_lock: args.time_lock,
+        other_pubkey: args.taker_pub,
+        tx_type_with_secret_hash: args.tx_type_with_secret_hash,
+        swap_contract_address: &None,
+        swap_unique_data: args.swap_unique_data,
+        watcher_reward: args.watcher_reward,
+    };
+    refund_htlc_payment(coin, args).await
+}
+
+/// Common implementation of taker funding or combined taker payment reclaim for UTXO coins using timelock path.
+/// Both transactions are refunded the same way, only the redeem script (defined by `tx_type_with_secret_hash`) differs.
+pub async fn refund_taker_tx_v2_timelock<T>(coin: T, args: RefundTakerPaymentArgs<'_>) -> Result<UtxoTx, TransactionErr>
+where
+    T: UtxoCommonOps + SwapOps,
+{
+    let args = RefundPaymentArgs {
+        payment_tx: args.payment_tx,
+        time_lock: args.time_lock,
+        other_pubkey: args.maker_pub,
+        tx_type_with_secret_hash: args.tx_type_with_secret_hash,
+        swap_contract_address: &None,
+        swap_unique_data: args.swap_unique_data,
+        watcher_reward: args.watcher_reward,
+    };
+    refund_htlc_payment(coin, args).await
+}
+
+/// Builds the output of a swap v2 HTLC spend signed for a watcher.
+/// The output leaves the miner fee and the watcher reward (equal to the fee) out of the HTLC value.
+async fn watcher_v2_owner_output<T: UtxoCommonOps>(
+    coin: &T,
+    payment_tx: &UtxoTx,
+) -> Result<TransactionOutput, TransactionErr> {
+    let my_address = try_tx_s!(coin.as_ref().derivation_method.single_addr_or_err().await);
+    let payment_value = try_tx_s!(payment_tx.first_output()).value;
+    let fee = try_tx_s!(
+        coin.get_htlc_spend_fee(DEFAULT_SWAP_TX_SPEND_SIZE, &FeeApproxStage::WithoutApprox)
+            .await
+    );
+    let reward = fee;
+    if fee + reward + coin.as_ref().dust_amount > payment_value {
+        return TX_PLAIN_ERR!(
+            "HTLC spend fee {} and watcher reward {} are too large for transaction output {}",
+            fee,
+            reward,
+            payment_value
+        );
+    }
+    Ok(TransactionOutput {
+        value: payment_value - fee - reward,
+        script_pubkey: try_tx_s!(output_script(&my_address)).to_bytes(),
+    })
+}
+
+/// Signs the only input of the swap v2 HTLC spend `preimage` for a watcher.
+/// `SIGHASH_SINGLE | SIGHASH_ANYONECANPAY` commits to the owner's output only,
+/// so the watcher can add its reward output.
+fn sign_for_watcher_v2<T: UtxoCommonOps>(
+    coin: &T,
+    preimage: &TransactionInputSigner,
+    redeem_script: &Script,
+    key_pair: &KeyPair,
+) -> Result<Vec<u8>, TransactionErr> {
+    let sighash_type = SIGHASH_SINGLE | SIGHASH_ANYONECANPAY;
+    let signature = try_tx_s!(calc_and_sign_sighash(
+        preimage,
+        DEFAULT_SWAP_VIN,
+        redeem_script,
+        key_pair,
+        coin.as_ref().conf.signature_version,
+        sighash_type,
+        coin.as_ref().conf.fork_id
+    ));
+    let mut signature_with_sighash = signature.take();
+    signature_with_sighash.push((sighash_type | coin.as_ref().conf.fork_id) as u8);
+    Ok(signature_with_sighash)
+}
+
+/// Common implementation of swap v2 HTLC timelock refund signing for a watcher.
+pub async fn sign_refund_for_watcher_v2<T>(coin: &T, args: RefundPaymentArgs<'_>) -> Result<UtxoTx, TransactionErr>
+where
+    T: UtxoCommonOps + SwapOps,
+{
+    let mut prev_transaction: UtxoTx =
+        try_tx_s!(deserialize(args.payment_tx).map_err(|e| TransactionErr::Plain(format!("{:?}", e))));
+    prev_transaction.tx_hash_algo = coin.as_ref().tx_hash_algo;
+    drop_mutability!(prev_transaction);
+    let other_public = try_tx_s!(Public::from_slice(args.other_pubkey));
+    let key_pair = coin.derive_htlc_key_pair(args.swap_unique_data);
+    let time_lock = try_tx_s!(args.time_lock.try_into());
+    let redeem_script = args
+        .tx_type_with_secret_hash
+        .redeem_script(time_lock, key_pair.public(), &other_public);
+
+    let output = watcher_v2_owner_output(coin, &prev_transaction).await?;
+    let preimage = try_tx_s!(
+        p2sh_spending_tx_preimage(
+            coin,
+            &prev_transaction,
+            LocktimeSetting::CalcByHtlcLocktime(time_lock),
+            NTimeSetting::UseNow,
+            SEQUENCE_FINAL - 1,
+            vec![output],
+        )
+        .await
+    );
+    let signature = sign_for_watcher_v2(coin, &preimage, &redeem_script, &key_pair)?;
+    let script_sig = Builder::default()
+        .push_data(&signature)
+        .push_opcode(Opcode::OP_1)
+        .push_data(&redeem_script)
+        .into_bytes();
+
+    let mut transaction: UtxoTx = preimage.into();
+    transaction.inputs[DEFAULT_SWAP_VIN].script_sig = script_sig;
+    Ok(transaction)
+}
+
+/// Common implementation of maker payment v2 spend signing for a watcher.
+/// The script sig of the result lacks the maker secret, see [`send_preimage_as_watcher_v2`].
+pub async fn sign_maker_payment_spend_for_watcher_v2<T>(
+    coin: &T,
+    args: MakerPaymentSpendForWatcherArgs<'_>,
+) -> Result<UtxoTx, TransactionErr>
+where
+    T: UtxoCommonOps + SwapOps,
+{
+    let mut prev_transaction: UtxoTx =
+        try_tx_s!(deserialize(args.maker_payment_tx).map_err(|e| TransactionErr::Plain(format!("{:?}", e))));
+    prev_transaction.tx_hash_algo = coin.as_ref().tx_hash_algo;
+    drop_mutability!(prev_transaction);
+    let maker_public = try_tx_s!(Public::from_slice(args.maker_pub));
+    let key_pair = coin.derive_htlc_key_pair(args.swap_unique_data);
+    let time_lock = try_tx_s!(args.time_lock.try_into());
+    let redeem_script = swap_proto_v2_scripts::maker_payment_script(
+        time_lock,
+        args.maker_secret_hash,
+        args.taker_secret_hash,
+        &maker_public,
+        key_pair.public(),
+    );
+
+    let output = watcher_v2_owner_output(coin, &prev_transaction).await?;
+    let preimage = try_tx_s!(
+        p2sh_spending_tx_preimage(
+            coin,
+            &prev_transaction,
+            LocktimeSetting::CalcByHtlcLocktime(time_lock),
+            NTimeSetting::UseNow,
+            SEQUENCE_FINAL,
+            vec![output],
+        )
+        .await
+    );
+    let signature = sign_for_watcher_v2(coin, &preimage, &redeem_script, &key_pair)?;
+    let script_sig = Builder::default()
+        .push_data(&signature)
+        .push_data(&redeem_script)
+        .into_bytes();
+
+    let mut transaction: UtxoTx = preimage.into();
+    transaction.inputs[DEFAULT_SWAP_VIN].script_sig = script_sig;
+    Ok(transaction)
+}
+
+/// Swap v2 HTLC spend or refund signed for a watcher, see [`sign_for_watcher_v2`].
+struct WatcherV2Preimage {
+    transaction: UtxoTx,
+    /// The signature followed by the sighash type.
+    signature: Vec<u8>,
+    redeem_script: Script,
+    /// Whether the script sig selects the timelock refund path of the redeem script.
+    is_refund: bool,
+}
+
+impl WatcherV2Preimage {
+    /// Parses the `preimage` and checks that it has the only input spending the `payment` by its redeem script,
+    /// and the only output.
+    fn parse(coin: &UtxoCoinFields, payment: &UtxoTx, preimage: &[u8]) -> Result<WatcherV2Preimage, String> {
+        let mut transaction: UtxoTx = try_s!(deserialize(preimage).map_err(|e| ERRL!("{:?}", e)));
+        transaction.tx_hash_algo = coin.tx_hash_algo;
+        drop_mutability!(transaction);
+
+        let expected_outpoint = OutPoint {
+            hash: payment.hash(),
+            index: DEFAULT_SWAP_VOUT as u32,
+        };
+        if transaction.inputs.len() != 1 || transaction.inputs[DEFAULT_SWAP_VIN].previous_output != expected_outpoint {
+            return ERR!(
+                "Preimage must have the only input spending the payment {:?}",
+                expected_outpoint
+            );
+        }
+        if transaction.outputs.len() != 1 {
+            return ERR!("Preimage must have the only output, got {}", transaction.outputs.len());
+        }
+
+        let script_sig = Script::from(transaction.inputs[DEFAULT_SWAP_VIN].script_sig.clone());
+        let instructions = try_s!(script_sig.iter().collect::<Result<Vec<_>, _>>());
+        let (signature, redeem_script, is_refund) = match instructions.as_slice() {
+            [signature, redeem_script] => (signature.data, redeem_script.data, false),
+            [signature, path, redeem_script] if path.opcode == Opcode::OP_1 => {
+                (signature.data, redeem_script.data, true)
+            },
+            _ => return ERR!("Unexpected preimage script sig {}", script_sig),
+        };
+        let signature = try_s!(signature.ok_or("No signature in the preimage")).to_vec();
+        let redeem_script: Script = try_s!(redeem_script.ok_or("No redeem script in the preimage"))
+            .to_vec()
+            .into();
+
+        let expected_script_pubkey = Builder::build_p2sh(&dhash160(&redeem_script).into()).to_bytes();
+        if try_s!(payment.first_output()).script_pubkey != expected_script_pubkey {
+            return ERR!("Preimage redeem script doesn't match the payment output");
+        }
+
+        Ok(WatcherV2Preimage {
+            transaction,
+            signature,
+            redeem_script,
+            is_refund,
+        })
+    }
+
+    /// Verifies the signature by the key of the spending path.
+    /// The first key of the swap v2 redeem scripts is the payment owner's one on the timelock refund path,
+    /// and the second key of the maker payment script is the taker's one on the maker secret path.
+    fn verify_signature(&self, coin: &UtxoCoinFields, input_amount: u64) -> Result<(), String> {
+        let keys: Vec<Public> = self
+            .redeem_script
+            .iter()
+            .filter_map(|instruction| instruction.ok()?.data)
+            .filter_map(|data| Public::from_slice(data).ok())
+            .collect();
+        let key_index = if self.is_refund { 0 } else { 1 };
+        let public = try_s!(keys.get(key_index).ok_or("Not enough keys in the redeem script"));
+
+        let (sighash_type, signature) = try_s!(self.signature.split_last().ok_or("Empty preimage signature"));
+        let expected_sighash_type = SIGHASH_SINGLE | SIGHASH_ANYONECANPAY;
+        if *sighash_type as u32 != expected_sighash_type | coin.conf.fork_id {
+            return ERR!("Unexpected preimage sighash type {}", sighash_type);
+        }
+
+        let mut signer = TransactionInputSigner::from(self.transaction.clone());
+        signer.inputs[DEFAULT_SWAP_VIN].amount = input_amount;
+        signer.consensus_branch_id = coin.conf.consensus_branch_id;
+        let sighash = try_s!(signature_hash_to_sign(
+            &signer,
+            DEFAULT_SWAP_VIN,
+            &self.redeem_script,
+            coin.conf.signature_version,
+            expected_sighash_type,
+            coin.conf.fork_id
+        ));
+        if !try_s!(public.verify(&sighash, &signature.to_vec().into())) {
+            return ERR!("Invalid preimage signature");
+        }
+        Ok(())
+    }
+}
+
+/// Common implementation of swap v2 preimage validation by a watcher before it starts guarding the payment.
+/// The `payment_tx` must be on chain, and the `preimage` must be validly signed for the refund path (`is_refund`)
+/// or the maker secret path of the payment.
+pub async fn watcher_validate_preimage_v2<T: UtxoCommonOps>(
+    coin: &T,
+    payment_tx: &[u8],
+    preimage: &[u8],
+    is_refund: bool,
+) -> ValidatePaymentResult<()> {
+    let mut payment: UtxoTx =
+        deserialize(payment_tx).map_to_mm(|e| ValidatePaymentError::TxDeserializationError(e.to_string()))?;
+    payment.tx_hash_algo = coin.as_ref().tx_hash_algo;
+    drop_mutability!(payment);
+
+    let tx_from_rpc = coin
+        .as_ref()
+        .rpc_client
+        .get_transaction_bytes(&payment.hash().reversed().into())
+        .compat()
+        .await?;
+    if serialize(&payment).take() != tx_from_rpc.0
+        && serialize_with_flags(&payment, SERIALIZE_TRANSACTION_WITNESS).take() != tx_from_rpc.0
+    {
+        return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+            "Payment {:?} doesn't match tx data from rpc",
+            payment
+        )));
+    }
+
+    let preimage =
+        WatcherV2Preimage::parse(coin.as_ref(), &payment, preimage).map_to_mm(ValidatePaymentError::WrongPaymentTx)?;
+    if preimage.is_refund != is_refund {
+        return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+            "Preimage spends the payment by the wrong path, expected refund: {}",
+            is_refund
+        )));
+    }
+    let payment_value = payment
+        .first_output()
+        .map_to_mm(|e| ValidatePaymentError::WrongPaymentTx(e.to_string()))?
+        .value;
+    preimage
+        .verify_signature(coin.as_ref(), payment_value)
+        .map_to_mm(ValidatePaymentError::WrongPaymentTx)
+}
+
+/// Common implementation of swap v2 preimage completion and broadcasting by a watcher.
+/// Inserts the maker `secret` into the maker payment spend and adds the watcher reward output
+/// if the amount left by the owner above the current miner fee isn't dust.
+pub async fn send_preimage_as_watcher_v2<T: UtxoCommonOps>(
+    coin: &T,
+    payment_tx: &[u8],
+    preimage: &[u8],
+    secret: Option<&[u8]>,
+) -> Result<UtxoTx, TransactionErr> {
+    let mut payment: UtxoTx = try_tx_s!(deserialize(payment_tx).map_err(|e| ERRL!("{:?}", e)));
+    payment.tx_hash_algo = coin.as_ref().tx_hash_algo;
+    drop_mutability!(payment);
+    let preimage = try_tx_s!(WatcherV2Preimage::parse(coin.as_ref(), &payment, preimage));
+    let mut transaction = preimage.transaction;
+
+    match secret {
+        Some(secret) if !preimage.is_refund => {
+            transaction.inputs[DEFAULT_SWAP_VIN].script_sig = Builder::default()
+                .push_data(&preimage.signature)
+                .push_data(secret)
+                .push_opcode(Opcode::OP_1)
+                .push_opcode(Opcode::OP_0)
+                .push_data(&preimage.redeem_script)
+                .into_bytes();
+        },
+        None if preimage.is_refund => (),
+        _ => return TX_PLAIN_ERR!("The secret is required to complete the maker payment spend only"),
+    }
+
+    let payment_value = try_tx_s!(payment.first_output()).value;
+    let fee = try_tx_s!(
+        coin.get_htlc_spend_fee(DEFAULT_SWAP_TX_SPEND_SIZE, &FeeApproxStage::WithoutApprox)
+            .await
+    );
+    let reward = payment_value
+        .saturating_sub(transaction.outputs[0].value)
+        .saturating_sub(fee);
+    if reward >= coin.as_ref().dust_amount {
+        let my_address = try_tx_s!(coin.as_ref().derivation_method.single_addr_or_err().await);
+        transaction.outputs.push(TransactionOutput {
+            value: reward,
+            script_pubkey: try_tx_s!(output_script(&my_address)).to_bytes(),
+        });
+    }
+    drop_mutability!(transaction);
+
+    try_tx_s!(coin.broadcast_tx(&transaction).await, transaction);
+    Ok(transaction)
+}
+
+/// Common implementation of swap v2 HTLC spend search for watchers.
+pub async fn watcher_search_for_payment_spend_v2<T: UtxoCommonOps>(
+    coin: &T,
+    payment_tx: &[u8],
+    search_from_block: u64,
+) -> Result<Option<UtxoTx>, String> {
+    let mut payment: UtxoTx = try_s!(deserialize(payment_tx).map_err(|e| ERRL!("{:?}", e)));
+    payment.tx_hash_algo = coin.as_ref().tx_hash_algo;
+    drop_mutability!(payment);
+    let script_pubkey = &try_s!(payment.first_output()).script_pubkey;
+
+    let spend = try_s!(
+        coin.as_ref()
+            .rpc_client
+            .find_output_spend(
+                payment.hash(),
+                script_pubkey,
+                DEFAULT_SWAP_VOUT,
+                BlockHashOrHeight::Height(search_from_block as i64),
+                coin.as_ref().tx_hash_algo,
+            )
+            .compat()
+            .await
+    );
+    Ok(spend.map(|found| found.spending_tx))
+}
+
+/// Common implementation of taker funding spend search for UTXO coins.
+/// Detects the spending path by the first instruction of the spending input script_sig.
+/// `funding_vout` is the index of the funding P2SH output, which is not the first one e.g. for SLP tokens.
+pub async fn search_for_taker_funding_spend<T>(
+    coin: &UtxoCoinFields,
+    tx: &UtxoTx,
+    funding_vout: usize,
+    from_block: u64,
+) -> Result<Option<FundingTxSpend<T>>, SearchForFundingSpendErr>
+where
+    T: ParseCoinAssocTypes<Tx = UtxoTx> + ?Sized,
+{
+    let script_pubkey = &tx
+        .outputs
+        .get(funding_vout)
+        .ok_or_else(|| {
+            SearchForFundingSpendErr::InvalidInputTx(format!("Funding tx has no output with index {}", funding_vout))
+        })?
+        .script_pubkey;
+
+    let from_block = from_block
+        .try_into()
+        .map_err(SearchForFundingSpendErr::FromBlockConversionErr)?;
+
+    let output_spend = coin
+        .rpc_client
+        .find_output_spend(
+            tx.hash(),
+            script_pubkey,
+            funding_vout,
+            BlockHashOrHeight::Height(from_block),
+            coin.tx_hash_algo,
+        )
+        .compat()
+        .await
+        .map_err(SearchForFundingSpendErr::Rpc)?;
+    match output_spend {
+        Some(found) => {
+            let script_sig: Script = found.input.script_sig.into();
+            let maybe_first_op_if = script_sig
+                .get_instruction(1)
+                .ok_or_else(|| SearchForFundingSpendErr::FailedToProcessSpendTx("No instruction at index 1".into()))?
+                .map_err(|e| {
+                    SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
+                        "Couldn't get instruction at index 1: {}",
+                        e
+                    ))
+                })?;
+            match maybe_first_op_if.opcode {
+                Opcode::OP_1 => Ok(Some(FundingTxSpend::RefundedTimelock(found.spending_tx))),
+                Opcode::OP_PUSHBYTES_32 => Ok(Some(FundingTxSpend::RefundedSecret {
+                    tx: found.spending_tx,
+                    secret: maybe_first_op_if
+                        .data
+                        .ok_or_else(|| {
+                            SearchForFundingSpendErr::FailedToProcessSpendTx(
+                                "No data at instruction with index 1".into(),
+                            )
+                        })?
+                        .try_into()
+                        .map_err(|e| {
+                            SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
+                                "Failed to parse data at instruction with index 1 as [u8; 32]: {}",
+                                e
+                            ))
+                        })?,
+                })),
+                Opcode::OP_PUSHBYTES_70 | Opcode::OP_PUSHBYTES_71 | Opcode::OP_PUSHBYTES_72 => {
+                    Ok(Some(FundingTxSpend::TransferredToTakerPayment(found.spending_tx)))
+                },
+                unexpected => Err(SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
+                    "Got unexpected opcode {:?} at instruction with index 1",
+                    unexpected
+                ))),
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+/// Common implementation of waiting for the taker payment spend for UTXO coins.
+pub async fn find_taker_payment_spend_tx(
+    coin: &UtxoCoinFields,
+    taker_payment: &UtxoTx,
+    from_block: u64,
+    wait_until: u64,
+) -> MmResult<UtxoTx, FindPaymentSpendError> {
+    let res = wait_for_output_spend_impl(coin, taker_payment, DEFAULT_SWAP_VOUT, from_block, wait_until, 10.).await?;
+    Ok(res)
+}
+
 #[test]
 fn test_increase_by_percent() {
     assert_eq!(increase_by_percent(4300, 1.), 4343);
@@ -5326,6 +5961,20 @@ fn test_tx_v_size() {
     assert_eq!(v_size, 209)
 }
 
+#[test]
+fn test_multisig_tx_v_size() {
+    // Only the size of the transaction without the scriptSigs and the witnesses matters,
+    // so a single input transaction is used as if it spent a 2-of-3 multisig output with a 105 bytes long script.
+    // https://live.blockcypher.com/btc-testnet/tx/f8c1fed6f307eb131040965bd11018787567413e6437c907b1fd15de6517ad16/
+    let tx: UtxoTx = "010000000001017996e77b2b1f4e66da606cfc2f16e3f52e1eac4a294168985bd4dbd54442e61f0100000000ffffffff01ab36010000000000220020693090c0e291752d448826a9dc72c9045b34ed4f7bd77e6e8e62645c23d69ac502483045022100d0800719239d646e69171ede7f02af916ac778ffe384fa0a5928645b23826c9f022044072622de2b47cfc81ac5172b646160b0c48d69d881a0ce77be06dbd6f6e5ac0121031ac6d25833a5961e2a8822b2e8b0ac1fd55d90cbbbb18a780552cbd66fc02bb3735a9e61".into();
+    let legacy_size = multisig_tx_size_in_v_bytes(&UtxoAddressFormat::Standard, &tx, 2, 105);
+    // The base transaction is 94 bytes, the scriptSig is 1 + 2 * 73 + 3 + 105 = 255 bytes.
+    assert_eq!(legacy_size, 94 + 255);
+    let segwit_size = multisig_tx_size_in_v_bytes(&UtxoAddressFormat::Segwit, &tx, 2, 105);
+    // The witness data is discounted.
+    assert_eq!(segwit_size, (0.75 * 94.0 + 0.25 * (94.0 + 4.0 + 256.0)) as usize);
+}
+
 #[test]
 fn test_generate_taker_fee_tx_outputs_with_standard_dex_fee() {
     let client = UtxoRpcClientEnum::Native(NativeClient(Arc::new(NativeClientImpl::default())));
@@ -125,6 +125,7 @@ pub(super) fn utxo_coin_fields_for_test(
             signature_version: SignatureVersion::Base,
             required_confirmations: 1.into(),
             force_min_relay_fee: false,
+            signal_rbf: false,
             mtp_block_count: NonZeroU64::new(11).unwrap(),
             estimate_fee_mode: None,
             mature_confirmations: MATURE_CONFIRMATIONS_DEFAULT,
@@ -143,12 +144,14 @@ pub(super) fn utxo_coin_fields_for_test(
         history_sync_state: Mutex::new(HistorySyncState::NotEnabled),
         tx_cache: DummyVerboseCache.into_shared(),
         recently_spent_outpoints: AsyncMutex::new(RecentlySpentOutPoints::new(my_script_pubkey)),
+        locked_unspents: Mutex::new(utxo_lock_storage::LockedOutPoints::default()),
         tx_hash_algo: TxHashAlgo::DSHA256,
         check_utxo_maturity: false,
         block_headers_status_notifier: None,
         block_headers_status_watcher: None,
         ctx: MmWeak::default(),
         abortable_system: AbortableQueue::default(),
+        multisig: None,
     }
 }
 
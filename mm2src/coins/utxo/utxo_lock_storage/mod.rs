@@ -0,0 +1,311 @@
+//! The persistent storage of the unspent outputs locked by the user or by pending PSBTs.
+//! Locked outputs are not spent by withdrawals, swaps and other transactions until they're unlocked
+//! or their lock expires.
+
+#[cfg(not(target_arch = "wasm32"))] mod sql_utxo_lock_storage;
+#[cfg(target_arch = "wasm32")] mod wasm_utxo_lock_storage;
+
+use async_trait::async_trait;
+use chain::OutPoint;
+use common::now_sec;
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use std::collections::HashMap;
+
+cfg_native! {
+    use sql_utxo_lock_storage::SqliteUtxoLockStorage;
+}
+
+cfg_wasm32! {
+    use wasm_utxo_lock_storage::IDBUtxoLockStorage;
+}
+
+pub type UtxoLockStorageResult<T> = MmResult<T, UtxoLockStorageError>;
+
+#[derive(Debug, Display)]
+pub enum UtxoLockStorageError {
+    #[display(fmt = "Error initializing UTXO lock storage: {}", _0)]
+    InitializationError(String),
+    #[display(fmt = "Error saving changes in UTXO lock storage: {}", _0)]
+    ErrorSaving(String),
+    #[display(fmt = "Error loading from UTXO lock storage: {}", _0)]
+    ErrorLoading(String),
+    #[display(fmt = "Error deserializing a locked outpoint: {}", _0)]
+    ErrorDeserializing(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+/// The outpoints locked either by the user until they're unlocked
+/// or by a pending PSBT until it's finalized or the lock expires.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LockedOutPoints {
+    /// The expiration timestamps (in seconds) of the locks, `None` if the outpoint is locked until it's unlocked.
+    locks: HashMap<OutPoint, Option<u64>>,
+}
+
+impl LockedOutPoints {
+    /// Whether the `outpoint` is locked at the moment.
+    pub fn contains(&self, outpoint: &OutPoint) -> bool {
+        match self.locks.get(outpoint) {
+            Some(expires_at) => is_active_lock(*expires_at, now_sec()),
+            None => false,
+        }
+    }
+
+    /// Whether no outpoint is locked at the moment.
+    pub fn is_empty(&self) -> bool {
+        let now = now_sec();
+        !self.locks.values().any(|expires_at| is_active_lock(*expires_at, now))
+    }
+
+    /// Returns the outpoints locked at the moment.
+    pub fn active(&self) -> Vec<OutPoint> {
+        let now = now_sec();
+        self.locks
+            .iter()
+            .filter(|(_outpoint, expires_at)| is_active_lock(**expires_at, now))
+            .map(|(outpoint, _expires_at)| *outpoint)
+            .collect()
+    }
+
+    /// Locks the `outpoints` until `expires_at`, or until they're unlocked if it's `None`.
+    /// The lock of an already locked outpoint is never shortened.
+    pub fn lock(&mut self, outpoints: impl IntoIterator<Item = OutPoint>, expires_at: Option<u64>) {
+        for outpoint in outpoints {
+            let lock = self.locks.entry(outpoint).or_insert(expires_at);
+            *lock = longest_lock(*lock, expires_at);
+        }
+    }
+
+    pub fn unlock(&mut self, outpoints: &[OutPoint]) {
+        for outpoint in outpoints {
+            self.locks.remove(outpoint);
+        }
+    }
+
+    /// Unlocks those of the `outpoints` that are locked until a certain time, e.g. by a PSBT,
+    /// the outpoints locked by the user are kept locked. Returns the unlocked outpoints.
+    pub fn unlock_expiring(&mut self, outpoints: &[OutPoint]) -> Vec<OutPoint> {
+        let mut unlocked = Vec::new();
+        for outpoint in outpoints {
+            if let Some(Some(_expires_at)) = self.locks.get(outpoint) {
+                self.locks.remove(outpoint);
+                unlocked.push(*outpoint);
+            }
+        }
+        unlocked
+    }
+}
+
+fn is_active_lock(expires_at: Option<u64>, now: u64) -> bool { expires_at.map_or(true, |expires_at| expires_at > now) }
+
+fn longest_lock(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        _ => None,
+    }
+}
+
+#[async_trait]
+pub trait UtxoLockStorageOps {
+    async fn init(&self) -> UtxoLockStorageResult<()>;
+
+    /// Loads the outpoints locked at the moment, the expired locks are removed from the storage.
+    async fn load_locked_outpoints(&self) -> UtxoLockStorageResult<LockedOutPoints>;
+
+    /// Adds the given `outpoints` to the storage locking them until `expires_at`,
+    /// or until they're unlocked if it's `None`. The lock of an already locked outpoint is never shortened.
+    async fn lock_outpoints(&self, outpoints: Vec<OutPoint>, expires_at: Option<u64>) -> UtxoLockStorageResult<()>;
+
+    /// Removes the given `outpoints` from the storage. Not locked outpoints are ignored.
+    async fn unlock_outpoints(&self, outpoints: Vec<OutPoint>) -> UtxoLockStorageResult<()>;
+}
+
+/// The storage of the locked unspent outputs of a single coin.
+pub struct UtxoLockStorage {
+    inner: Box<dyn UtxoLockStorageOps + Send + Sync>,
+}
+
+impl UtxoLockStorage {
+    #[cfg(all(not(test), not(target_arch = "wasm32")))]
+    pub fn new_from_ctx(ctx: &MmArc, ticker: String) -> UtxoLockStorageResult<Self> {
+        #[cfg(not(feature = "new-db-arch"))]
+        let maybe_sqlite_connection = ctx.sqlite_connection.get();
+        #[cfg(feature = "new-db-arch")]
+        let maybe_sqlite_connection = ctx.global_db_conn.get();
+        let conn = maybe_sqlite_connection.or_mm_err(|| {
+            UtxoLockStorageError::InitializationError("UtxoLockStorage's SQL DB is not initialized".to_owned())
+        })?;
+        Ok(UtxoLockStorage {
+            inner: Box::new(SqliteUtxoLockStorage {
+                ticker,
+                conn: conn.clone(),
+            }),
+        })
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn new_from_ctx(ctx: &MmArc, ticker: String) -> UtxoLockStorageResult<Self> {
+        Ok(UtxoLockStorage {
+            inner: Box::new(IDBUtxoLockStorage::new(ctx, ticker)),
+        })
+    }
+
+    #[cfg(all(test, not(target_arch = "wasm32")))]
+    pub fn new_from_ctx(ctx: &MmArc, ticker: String) -> UtxoLockStorageResult<Self> {
+        use db_common::sqlite::rusqlite::Connection;
+        use std::sync::{Arc, Mutex};
+
+        let conn = ctx
+            .sqlite_connection
+            .get()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(Mutex::new(Connection::open_in_memory().unwrap())));
+
+        Ok(UtxoLockStorage {
+            inner: Box::new(SqliteUtxoLockStorage { ticker, conn }),
+        })
+    }
+}
+
+#[async_trait]
+impl UtxoLockStorageOps for UtxoLockStorage {
+    async fn init(&self) -> UtxoLockStorageResult<()> { self.inner.init().await }
+
+    async fn load_locked_outpoints(&self) -> UtxoLockStorageResult<LockedOutPoints> {
+        self.inner.load_locked_outpoints().await
+    }
+
+    async fn lock_outpoints(&self, outpoints: Vec<OutPoint>, expires_at: Option<u64>) -> UtxoLockStorageResult<()> {
+        self.inner.lock_outpoints(outpoints, expires_at).await
+    }
+
+    async fn unlock_outpoints(&self, outpoints: Vec<OutPoint>) -> UtxoLockStorageResult<()> {
+        self.inner.unlock_outpoints(outpoints).await
+    }
+}
+
+/// Initializes the storage and loads the locked outpoints of the `ticker` coin.
+pub async fn load_locked_unspents(ctx: &MmArc, ticker: &str) -> UtxoLockStorageResult<LockedOutPoints> {
+    let storage = UtxoLockStorage::new_from_ctx(ctx, ticker.to_owned())?;
+    storage.init().await?;
+    storage.load_locked_outpoints().await
+}
+
+#[cfg(any(test, target_arch = "wasm32"))]
+mod utxo_lock_storage_tests {
+    use super::*;
+    use mm2_test_helpers::for_tests::mm_ctx_with_custom_db;
+    use std::collections::HashSet;
+
+    cfg_wasm32! {
+        use wasm_bindgen_test::*;
+
+        wasm_bindgen_test_configure!(run_in_browser);
+    }
+
+    fn outpoint(hash: u8, index: u32) -> OutPoint {
+        OutPoint {
+            hash: [hash; 32].into(),
+            index,
+        }
+    }
+
+    async fn load_active(storage: &UtxoLockStorage) -> HashSet<OutPoint> {
+        storage
+            .load_locked_outpoints()
+            .await
+            .unwrap()
+            .active()
+            .into_iter()
+            .collect()
+    }
+
+    async fn test_lock_unlock_outpoints_impl() {
+        let ctx = mm_ctx_with_custom_db();
+        let storage = UtxoLockStorage::new_from_ctx(&ctx, "RICK".to_owned()).unwrap();
+        storage.init().await.unwrap();
+        assert!(storage.load_locked_outpoints().await.unwrap().is_empty());
+
+        storage
+            .lock_outpoints(vec![outpoint(1, 0), outpoint(1, 1), outpoint(2, 0)], None)
+            .await
+            .unwrap();
+        // Locking the same outpoint twice shouldn't fail.
+        storage.lock_outpoints(vec![outpoint(1, 0)], None).await.unwrap();
+
+        let other_storage = UtxoLockStorage::new_from_ctx(&ctx, "MORTY".to_owned()).unwrap();
+        other_storage.init().await.unwrap();
+        other_storage.lock_outpoints(vec![outpoint(3, 0)], None).await.unwrap();
+
+        let expected: HashSet<_> = vec![outpoint(1, 0), outpoint(1, 1), outpoint(2, 0)]
+            .into_iter()
+            .collect();
+        assert_eq!(load_active(&storage).await, expected);
+
+        storage
+            .unlock_outpoints(vec![outpoint(1, 1), outpoint(4, 0)])
+            .await
+            .unwrap();
+        let expected: HashSet<_> = vec![outpoint(1, 0), outpoint(2, 0)].into_iter().collect();
+        assert_eq!(load_active(&storage).await, expected);
+
+        let expected: HashSet<_> = std::iter::once(outpoint(3, 0)).collect();
+        assert_eq!(load_active(&other_storage).await, expected);
+    }
+
+    async fn test_lock_outpoints_until_impl() {
+        let ctx = mm_ctx_with_custom_db();
+        let storage = UtxoLockStorage::new_from_ctx(&ctx, "RICK".to_owned()).unwrap();
+        storage.init().await.unwrap();
+
+        let now = now_sec();
+        storage
+            .lock_outpoints(vec![outpoint(1, 0), outpoint(2, 0)], Some(now + 1000))
+            .await
+            .unwrap();
+        storage
+            .lock_outpoints(vec![outpoint(3, 0)], Some(now - 1))
+            .await
+            .unwrap();
+        // The user lock isn't shortened by the expiring one and vice versa.
+        storage.lock_outpoints(vec![outpoint(4, 0)], None).await.unwrap();
+        storage
+            .lock_outpoints(vec![outpoint(4, 0)], Some(now + 1000))
+            .await
+            .unwrap();
+        storage.lock_outpoints(vec![outpoint(2, 0)], None).await.unwrap();
+
+        let mut locked = storage.load_locked_outpoints().await.unwrap();
+        let expected: HashSet<_> = vec![outpoint(1, 0), outpoint(2, 0), outpoint(4, 0)]
+            .into_iter()
+            .collect();
+        assert_eq!(locked.active().into_iter().collect::<HashSet<_>>(), expected);
+        assert!(!locked.contains(&outpoint(3, 0)));
+
+        // Only the expiring lock is removed.
+        let unlocked = locked.unlock_expiring(&[outpoint(1, 0), outpoint(2, 0), outpoint(4, 0)]);
+        assert_eq!(unlocked, vec![outpoint(1, 0)]);
+        assert!(!locked.contains(&outpoint(1, 0)));
+        assert!(locked.contains(&outpoint(2, 0)));
+        assert!(locked.contains(&outpoint(4, 0)));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_lock_unlock_outpoints() { common::block_on(test_lock_unlock_outpoints_impl()) }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn test_lock_unlock_outpoints() { test_lock_unlock_outpoints_impl().await }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_lock_outpoints_until() { common::block_on(test_lock_outpoints_until_impl()) }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test]
+    async fn test_lock_outpoints_until() { test_lock_outpoints_until_impl().await }
+}
@@ -0,0 +1,139 @@
+use super::{LockedOutPoints, UtxoLockStorageError, UtxoLockStorageOps, UtxoLockStorageResult};
+use async_trait::async_trait;
+use chain::OutPoint;
+use common::{async_blocking, now_sec};
+use db_common::sqlite::rusqlite::{params, Connection, Error as SqlError, Row};
+use mm2_err_handle::prelude::*;
+use primitives::hash::H256;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+const CREATE_LOCKED_UNSPENTS_TABLE: &str = "CREATE TABLE IF NOT EXISTS utxo_locked_unspents (
+    coin VARCHAR(255) NOT NULL,
+    tx_hash VARCHAR(255) NOT NULL,
+    output_index INTEGER NOT NULL,
+    expires_at INTEGER,
+    UNIQUE (coin, tx_hash, output_index)
+);";
+
+/// The lock of an already locked outpoint is never shortened, `NULL` means it's locked until it's unlocked.
+const INSERT_LOCKED_UNSPENT: &str =
+    "INSERT INTO utxo_locked_unspents (coin, tx_hash, output_index, expires_at) VALUES (?1, ?2, ?3, ?4)
+    ON CONFLICT (coin, tx_hash, output_index) DO UPDATE SET expires_at = CASE
+        WHEN utxo_locked_unspents.expires_at IS NULL OR excluded.expires_at IS NULL THEN NULL
+        ELSE MAX(utxo_locked_unspents.expires_at, excluded.expires_at)
+    END;";
+
+const DELETE_LOCKED_UNSPENT: &str =
+    "DELETE FROM utxo_locked_unspents WHERE coin=?1 AND tx_hash=?2 AND output_index=?3;";
+
+const DELETE_EXPIRED_UNSPENTS: &str = "DELETE FROM utxo_locked_unspents WHERE coin=?1 AND expires_at <= ?2;";
+
+const SELECT_LOCKED_UNSPENTS: &str =
+    "SELECT tx_hash, output_index, expires_at FROM utxo_locked_unspents WHERE coin=?1;";
+
+impl From<SqlError> for UtxoLockStorageError {
+    fn from(e: SqlError) -> Self {
+        let error = e.to_string();
+        match e {
+            SqlError::FromSqlConversionFailure(_, _, _)
+            | SqlError::IntegralValueOutOfRange(_, _)
+            | SqlError::InvalidColumnIndex(_)
+            | SqlError::InvalidColumnType(_, _, _) => UtxoLockStorageError::ErrorDeserializing(error),
+            _ => UtxoLockStorageError::Internal(error),
+        }
+    }
+}
+
+/// The transaction hash is stored in the displayed (reversed) byte order.
+fn outpoint_from_row(row: &Row<'_>) -> Result<(String, u32, Option<u64>), SqlError> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+}
+
+#[derive(Clone)]
+pub(super) struct SqliteUtxoLockStorage {
+    pub(super) ticker: String,
+    pub(super) conn: Arc<Mutex<Connection>>,
+}
+
+#[async_trait]
+impl UtxoLockStorageOps for SqliteUtxoLockStorage {
+    async fn init(&self) -> UtxoLockStorageResult<()> {
+        let selfi = self.clone();
+        async_blocking(move || {
+            let conn = selfi.conn.lock().unwrap();
+            conn.execute(CREATE_LOCKED_UNSPENTS_TABLE, [])
+                .map(|_| ())
+                .map_to_mm(|e| UtxoLockStorageError::InitializationError(e.to_string()))
+        })
+        .await
+    }
+
+    async fn load_locked_outpoints(&self) -> UtxoLockStorageResult<LockedOutPoints> {
+        let selfi = self.clone();
+        async_blocking(move || {
+            let conn = selfi.conn.lock().unwrap();
+            conn.execute(DELETE_EXPIRED_UNSPENTS, params![selfi.ticker, now_sec()])
+                .map_to_mm(|e| UtxoLockStorageError::ErrorSaving(e.to_string()))?;
+
+            let mut statement = conn.prepare(SELECT_LOCKED_UNSPENTS)?;
+            let rows = statement
+                .query_map([&selfi.ticker], outpoint_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut locked = LockedOutPoints::default();
+            for (tx_hash, index, expires_at) in rows {
+                let hash =
+                    H256::from_str(&tx_hash).map_to_mm(|e| UtxoLockStorageError::ErrorDeserializing(e.to_string()))?;
+                let outpoint = OutPoint {
+                    hash: hash.reversed(),
+                    index,
+                };
+                locked.lock(std::iter::once(outpoint), expires_at);
+            }
+            Ok(locked)
+        })
+        .await
+    }
+
+    async fn lock_outpoints(&self, outpoints: Vec<OutPoint>, expires_at: Option<u64>) -> UtxoLockStorageResult<()> {
+        let selfi = self.clone();
+        async_blocking(move || {
+            let mut conn = selfi.conn.lock().unwrap();
+            let sql_transaction = conn.transaction()?;
+            for outpoint in outpoints {
+                let tx_hash = outpoint.hash.reversed().to_string();
+                sql_transaction
+                    .execute(INSERT_LOCKED_UNSPENT, params![
+                        selfi.ticker,
+                        tx_hash,
+                        outpoint.index,
+                        expires_at
+                    ])
+                    .map_to_mm(|e| UtxoLockStorageError::ErrorSaving(e.to_string()))?;
+            }
+            sql_transaction
+                .commit()
+                .map_to_mm(|e| UtxoLockStorageError::ErrorSaving(e.to_string()))
+        })
+        .await
+    }
+
+    async fn unlock_outpoints(&self, outpoints: Vec<OutPoint>) -> UtxoLockStorageResult<()> {
+        let selfi = self.clone();
+        async_blocking(move || {
+            let mut conn = selfi.conn.lock().unwrap();
+            let sql_transaction = conn.transaction()?;
+            for outpoint in outpoints {
+                let tx_hash = outpoint.hash.reversed().to_string();
+                sql_transaction
+                    .execute(DELETE_LOCKED_UNSPENT, params![selfi.ticker, tx_hash, outpoint.index])
+                    .map_to_mm(|e| UtxoLockStorageError::ErrorSaving(e.to_string()))?;
+            }
+            sql_transaction
+                .commit()
+                .map_to_mm(|e| UtxoLockStorageError::ErrorSaving(e.to_string()))
+        })
+        .await
+    }
+}
@@ -0,0 +1,180 @@
+use super::{longest_lock, LockedOutPoints, UtxoLockStorageError, UtxoLockStorageOps, UtxoLockStorageResult};
+use async_trait::async_trait;
+use chain::OutPoint;
+use common::now_sec;
+use mm2_core::mm_ctx::MmArc;
+use mm2_db::indexed_db::{ConstructibleDb, DbIdentifier, DbInstance, DbLocked, DbTransactionError, DbTransactionResult,
+                         DbUpgrader, IndexedDb, IndexedDbBuilder, InitDbError, InitDbResult, MultiIndex,
+                         OnUpgradeResult, SharedDb, TableSignature};
+use mm2_err_handle::prelude::*;
+use primitives::hash::H256;
+use std::str::FromStr;
+
+const DB_VERSION: u32 = 1;
+/// A **unique** index of the `LockedUnspentTable` table that consists of the following properties:
+/// * coin - coin ticker
+/// * tx_hash - the transaction hash in the displayed (reversed) byte order
+/// * output_index - the index of the output within the transaction
+const COIN_OUTPOINT_INDEX: &str = "coin_outpoint";
+
+type UtxoLockDbLocked<'a> = DbLocked<'a, UtxoLockDb>;
+
+impl From<DbTransactionError> for UtxoLockStorageError {
+    fn from(e: DbTransactionError) -> Self {
+        let desc = e.to_string();
+        match e {
+            DbTransactionError::ErrorDeserializingItem(_) => UtxoLockStorageError::ErrorDeserializing(desc),
+            DbTransactionError::ErrorGettingItems(_) | DbTransactionError::ErrorCountingItems(_) => {
+                UtxoLockStorageError::ErrorLoading(desc)
+            },
+            DbTransactionError::ErrorUploadingItem(_) | DbTransactionError::ErrorDeletingItems(_) => {
+                UtxoLockStorageError::ErrorSaving(desc)
+            },
+            _ => UtxoLockStorageError::Internal(desc),
+        }
+    }
+}
+
+impl From<InitDbError> for UtxoLockStorageError {
+    fn from(e: InitDbError) -> Self { UtxoLockStorageError::InitializationError(e.to_string()) }
+}
+
+/// The table has the `coin` non-unique index
+/// and one unique multi-index `coin_outpoint` that consists of `coin`, `tx_hash`, `output_index`.
+#[derive(Deserialize, Serialize)]
+struct LockedUnspentTable {
+    coin: String,
+    tx_hash: String,
+    output_index: u32,
+    /// The outpoint is locked until it's unlocked if it's `None`.
+    #[serde(default)]
+    expires_at: Option<u64>,
+}
+
+impl LockedUnspentTable {
+    const COIN_INDEX: &'static str = "coin";
+
+    fn new(coin: String, outpoint: &OutPoint, expires_at: Option<u64>) -> LockedUnspentTable {
+        LockedUnspentTable {
+            coin,
+            tx_hash: outpoint.hash.reversed().to_string(),
+            output_index: outpoint.index,
+            expires_at,
+        }
+    }
+
+    fn outpoint_index(&self) -> DbTransactionResult<MultiIndex> {
+        MultiIndex::new(COIN_OUTPOINT_INDEX)
+            .with_value(&self.coin)?
+            .with_value(&self.tx_hash)?
+            .with_value(self.output_index)
+    }
+}
+
+impl TableSignature for LockedUnspentTable {
+    const TABLE_NAME: &'static str = "utxo_locked_unspents";
+
+    fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+        if let (0, 1) = (old_version, new_version) {
+            let table = upgrader.create_table(Self::TABLE_NAME)?;
+            table.create_index(Self::COIN_INDEX, false)?;
+            table.create_multi_index(COIN_OUTPOINT_INDEX, &["coin", "tx_hash", "output_index"], true)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) struct UtxoLockDb {
+    inner: IndexedDb,
+}
+
+#[async_trait]
+impl DbInstance for UtxoLockDb {
+    const DB_NAME: &'static str = "utxo_lock";
+
+    async fn init(db_id: DbIdentifier) -> InitDbResult<Self> {
+        let inner = IndexedDbBuilder::new(db_id)
+            .with_version(DB_VERSION)
+            .with_table::<LockedUnspentTable>()
+            .build()
+            .await?;
+        Ok(UtxoLockDb { inner })
+    }
+}
+
+pub(super) struct IDBUtxoLockStorage {
+    db: SharedDb<UtxoLockDb>,
+    ticker: String,
+}
+
+impl IDBUtxoLockStorage {
+    pub(super) fn new(ctx: &MmArc, ticker: String) -> Self {
+        IDBUtxoLockStorage {
+            db: ConstructibleDb::new(ctx).into_shared(),
+            ticker,
+        }
+    }
+
+    async fn lock_db(&self) -> UtxoLockStorageResult<UtxoLockDbLocked<'_>> {
+        self.db.get_or_initialize().await.mm_err(UtxoLockStorageError::from)
+    }
+}
+
+#[async_trait]
+impl UtxoLockStorageOps for IDBUtxoLockStorage {
+    async fn init(&self) -> UtxoLockStorageResult<()> { Ok(()) }
+
+    async fn load_locked_outpoints(&self) -> UtxoLockStorageResult<LockedOutPoints> {
+        let locked_db = self.lock_db().await?;
+        let transaction = locked_db.inner.transaction().await?;
+        let table = transaction.table::<LockedUnspentTable>().await?;
+
+        let now = now_sec();
+        let mut locked = LockedOutPoints::default();
+        for (item_id, item) in table.get_items(LockedUnspentTable::COIN_INDEX, &self.ticker).await? {
+            if matches!(item.expires_at, Some(expires_at) if expires_at <= now) {
+                table.delete_item(item_id).await?;
+                continue;
+            }
+            let hash =
+                H256::from_str(&item.tx_hash).map_to_mm(|e| UtxoLockStorageError::ErrorDeserializing(e.to_string()))?;
+            let outpoint = OutPoint {
+                hash: hash.reversed(),
+                index: item.output_index,
+            };
+            locked.lock(std::iter::once(outpoint), item.expires_at);
+        }
+        Ok(locked)
+    }
+
+    async fn lock_outpoints(&self, outpoints: Vec<OutPoint>, expires_at: Option<u64>) -> UtxoLockStorageResult<()> {
+        let locked_db = self.lock_db().await?;
+        let transaction = locked_db.inner.transaction().await?;
+        let table = transaction.table::<LockedUnspentTable>().await?;
+
+        for outpoint in outpoints {
+            let mut item = LockedUnspentTable::new(self.ticker.clone(), &outpoint, expires_at);
+            // Never shorten the lock of an already locked outpoint.
+            if let Some((_item_id, existing)) = table.get_item_by_unique_multi_index(item.outpoint_index()?).await? {
+                item.expires_at = longest_lock(existing.expires_at, expires_at);
+            }
+            table
+                .replace_item_by_unique_multi_index(item.outpoint_index()?, &item)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn unlock_outpoints(&self, outpoints: Vec<OutPoint>) -> UtxoLockStorageResult<()> {
+        let locked_db = self.lock_db().await?;
+        let transaction = locked_db.inner.transaction().await?;
+        let table = transaction.table::<LockedUnspentTable>().await?;
+
+        for outpoint in outpoints {
+            let item = LockedUnspentTable::new(self.ticker.clone(), &outpoint, None);
+            table.delete_item_by_unique_multi_index(item.outpoint_index()?).await?;
+        }
+        Ok(())
+    }
+}
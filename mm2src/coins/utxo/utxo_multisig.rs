@@ -0,0 +1,793 @@
+//! m-of-n multisig wallets shared by several cosigners. Every cosigner provides an account extended public key,
+//! and the address keys are derived from them by the same non-hardened `chain/address_id` path.
+//! The keys are sorted by [BIP-67](https://github.com/bitcoin/bips/blob/master/bip-0067.mediawiki),
+//! so the cosigners get the same P2SH or P2WSH addresses regardless of the order of the xpubs.
+//!
+//! The withdrawals are generated as unsigned PSBTs which are passed between the cosigners
+//! to be signed by [`sign_multisig_psbt`] and finalized by `finalize_psbt` once enough signatures are collected.
+
+use crate::coin_balance::HDAddressBalanceScanner;
+use crate::rpc_command::multisig::{GetMultisigAddressesResponse, MultisigAddressInfo, MultisigPsbtOutput,
+                                   MultisigRpcError, MultisigRpcResult, SignMultisigPsbtInProgressStatus,
+                                   SignMultisigPsbtRequest, SignMultisigPsbtResponse, SignMultisigPsbtTaskHandleShared};
+use crate::utxo::rpc_clients::UnspentInfo;
+use crate::utxo::utxo_coin_control::{lock_unspents_until, unlock_expiring_unspents};
+use crate::utxo::utxo_common::{big_decimal_from_sat_unsigned, UtxoTxBuilder};
+use crate::utxo::utxo_withdraw::{build_withdraw_tx, derive_hd_key_pair, withdraw_recipients, withdraw_tx_details,
+                                 PSBT_INPUTS_LOCK_DURATION};
+use crate::utxo::{dhash160, output_script, sat_from_big_decimal, sha256, utxo_common, GetUtxoMapOps, PrivKeyPolicy,
+                  UtxoAddressScanner, UtxoCoinConf, UtxoCommonOps, UTXO_LOCK};
+use crate::{TransactionData, WithdrawError, WithdrawRequest, WithdrawResult};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bip32::ExtendedPrivateKey;
+use chain::{OutPoint, TransactionOutput};
+use common::log::LogOnError;
+use common::now_sec;
+use crypto::hw_rpc_task::HwRpcTaskAwaitingStatus;
+use crypto::trezor::trezor_rpc_task::{TrezorRequestStatuses, TrezorRpcTaskProcessor};
+use crypto::{Bip44Chain, ChildNumber, CryptoCtx, DerivationPath, HwRpcError, RpcDerivationPath,
+             Secp256k1ExtendedPublicKey, XPubConverter};
+use futures::compat::Future01CompatExt;
+use keys::bytes::Bytes;
+use keys::{Address, AddressBuilder, AddressFormat, AddressHashEnum, Public};
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use primitives::hash::H264;
+use script::{Builder, Script, SignatureVersion, TransactionInputSigner};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use utxo_signer::psbt::{Psbt, PsbtError};
+use utxo_signer::sign_params::{MultisigCosigner, MultisigInputInfo, OutputDestination, SendingOutputInfo,
+                               SpendingInputInfo, UtxoSignTxParamsBuilder};
+use utxo_signer::with_trezor::TrezorTxSigner;
+use utxo_signer::UtxoSignerOps;
+
+/// The max number of public keys in a standard multisig script.
+const MAX_MULTISIG_COSIGNERS: usize = 15;
+/// The default number of addresses of each chain the multisig unspents are looked up at.
+const DEFAULT_MULTISIG_ADDRESS_COUNT: u32 = 20;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum MultisigScriptType {
+    #[default]
+    P2SH,
+    P2WSH,
+}
+
+/// The `multisig` activation param.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UtxoMultisigParams {
+    /// The number of signatures required to spend an output.
+    pub required: usize,
+    /// The account extended public keys of all the cosigners including this wallet.
+    pub xpubs: Vec<String>,
+    /// The derivation path of this wallet's account, e.g. `m/48'/0'/0'/2'`.
+    pub account_derivation_path: String,
+    #[serde(default)]
+    pub script_type: MultisigScriptType,
+    /// The number of addresses of each chain the unspents are looked up at.
+    pub address_count: Option<u32>,
+}
+
+/// A multisig address derived from the cosigners' xpubs.
+#[derive(Clone)]
+pub struct MultisigAddress {
+    pub address: Address,
+    /// The redeem script of a P2SH address or the witness script of a P2WSH address.
+    pub script: Script,
+    /// The cosigners sorted by their public keys.
+    pub cosigners: Vec<MultisigCosigner>,
+    pub chain: Bip44Chain,
+    pub address_id: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct UtxoMultisigWallet {
+    pub required: usize,
+    pub xpubs: Vec<Secp256k1ExtendedPublicKey>,
+    pub account_derivation_path: DerivationPath,
+    pub script_type: MultisigScriptType,
+    pub address_count: u32,
+}
+
+impl UtxoMultisigWallet {
+    pub fn from_params(params: &UtxoMultisigParams) -> Result<UtxoMultisigWallet, String> {
+        let cosigners = params.xpubs.len();
+        if cosigners == 0 || cosigners > MAX_MULTISIG_COSIGNERS {
+            return Err(format!(
+                "Expected from 1 to {} xpubs, found {}",
+                MAX_MULTISIG_COSIGNERS, cosigners
+            ));
+        }
+        if params.required == 0 || params.required > cosigners {
+            return Err(format!(
+                "'required' must be from 1 to {}, found {}",
+                cosigners, params.required
+            ));
+        }
+
+        let mut xpubs: Vec<Secp256k1ExtendedPublicKey> = Vec::with_capacity(cosigners);
+        for xpub in params.xpubs.iter() {
+            let standard = XPubConverter::replace_magic_prefix(xpub.clone()).map_err(|e| e.to_string())?;
+            let xpub = Secp256k1ExtendedPublicKey::from_str(&standard).map_err(|e| e.to_string())?;
+            if xpubs.iter().any(|known| known.public_key() == xpub.public_key()) {
+                return Err(format!("Duplicate xpub '{}'", standard));
+            }
+            xpubs.push(xpub);
+        }
+
+        let account_derivation_path =
+            DerivationPath::from_str(&params.account_derivation_path).map_err(|e| e.to_string())?;
+        Ok(UtxoMultisigWallet {
+            required: params.required,
+            xpubs,
+            account_derivation_path,
+            script_type: params.script_type,
+            address_count: params.address_count.unwrap_or(DEFAULT_MULTISIG_ADDRESS_COUNT),
+        })
+    }
+
+    /// Checks that the account xpub derived from the HD wallet seed is one of the cosigners' xpubs.
+    pub fn check_own_xpub(&self, bip39_secp_priv_key: &ExtendedPrivateKey<secp256k1::SecretKey>) -> Result<(), String> {
+        let mut priv_key = bip39_secp_priv_key.clone();
+        for child in self.account_derivation_path.iter() {
+            priv_key = priv_key.derive_child(child).map_err(|e| e.to_string())?;
+        }
+        let own_pubkey = *priv_key.public_key().public_key();
+        if !self.xpubs.iter().any(|xpub| *xpub.public_key() == own_pubkey) {
+            return Err(format!(
+                "The xpub of '{}' account isn't one of the cosigners' xpubs",
+                self.account_derivation_path
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the derivation path of this wallet's key of the given address.
+    pub fn derivation_path(&self, chain: Bip44Chain, address_id: u32) -> DerivationPath {
+        let mut derivation_path = self.account_derivation_path.clone();
+        derivation_path.push(chain.to_child_number());
+        derivation_path.push(ChildNumber::from(address_id));
+        derivation_path
+    }
+
+    pub fn derive_address(
+        &self,
+        conf: &UtxoCoinConf,
+        coin_format: &AddressFormat,
+        chain: Bip44Chain,
+        address_id: u32,
+    ) -> Result<MultisigAddress, String> {
+        let path = [chain.to_child_number(), ChildNumber::from(address_id)];
+        let mut cosigners = Vec::with_capacity(self.xpubs.len());
+        for xpub in self.xpubs.iter() {
+            let mut derived = xpub.clone();
+            for child in path.iter() {
+                derived = derived.derive_child(*child).map_err(|e| e.to_string())?;
+            }
+            cosigners.push(MultisigCosigner {
+                xpub: xpub.clone(),
+                pubkey: Public::Compressed(H264::from(derived.public_key().serialize())),
+            });
+        }
+        cosigners.sort_by(|a, b| a.pubkey[..].cmp(&b.pubkey[..]));
+
+        let pubkeys: Vec<Public> = cosigners.iter().map(|cosigner| cosigner.pubkey).collect();
+        let script = Builder::build_multisig(self.required, &pubkeys).map_err(|e| e.to_string())?;
+        let (addr_format, hash) = match self.script_type {
+            MultisigScriptType::P2SH => {
+                let addr_format = match coin_format {
+                    AddressFormat::CashAddress { .. } => coin_format.clone(),
+                    _ => AddressFormat::Standard,
+                };
+                (addr_format, AddressHashEnum::AddressHash(dhash160(&script)))
+            },
+            MultisigScriptType::P2WSH => (
+                AddressFormat::Segwit,
+                AddressHashEnum::WitnessScriptHash(sha256(&script)),
+            ),
+        };
+        let address = AddressBuilder::new(
+            addr_format,
+            conf.checksum_type,
+            conf.address_prefixes.clone(),
+            conf.bech32_hrp.clone(),
+        )
+        .as_sh(hash)
+        .build()?;
+
+        Ok(MultisigAddress {
+            address,
+            script,
+            cosigners,
+            chain,
+            address_id,
+        })
+    }
+
+    /// Derives `address_count` addresses of both external and internal chains.
+    pub fn all_addresses(
+        &self,
+        conf: &UtxoCoinConf,
+        coin_format: &AddressFormat,
+    ) -> Result<Vec<MultisigAddress>, String> {
+        let mut addresses = Vec::with_capacity(self.address_count as usize * 2);
+        for chain in [Bip44Chain::External, Bip44Chain::Internal] {
+            for address_id in 0..self.address_count {
+                addresses.push(self.derive_address(conf, coin_format, chain, address_id)?);
+            }
+        }
+        Ok(addresses)
+    }
+
+    fn input_info(&self, address: &MultisigAddress) -> SpendingInputInfo {
+        let multisig = MultisigInputInfo {
+            required: self.required,
+            cosigners: address.cosigners.clone(),
+            address_path: vec![address.chain as u32, address.address_id],
+            script: address.script.clone(),
+        };
+        let address_derivation_path = self.derivation_path(address.chain, address.address_id);
+        match self.script_type {
+            MultisigScriptType::P2SH => SpendingInputInfo::P2SH {
+                address_derivation_path,
+                multisig,
+            },
+            MultisigScriptType::P2WSH => SpendingInputInfo::P2WSH {
+                address_derivation_path,
+                multisig,
+            },
+        }
+    }
+}
+
+/// Returns the multisig addresses keyed by their script pubkeys.
+fn addresses_by_script(addresses: Vec<MultisigAddress>) -> Result<HashMap<Bytes, MultisigAddress>, String> {
+    addresses
+        .into_iter()
+        .map(|address| {
+            let script_pubkey = output_script(&address.address).map_err(|e| e.to_string())?;
+            Ok((script_pubkey.to_bytes(), address))
+        })
+        .collect()
+}
+
+pub async fn get_multisig_addresses<Coin>(
+    coin: &Coin,
+    chain: Bip44Chain,
+    from_id: u32,
+    count: u32,
+) -> MultisigRpcResult<GetMultisigAddressesResponse>
+where
+    Coin: UtxoCommonOps,
+{
+    let ticker = &coin.as_ref().conf.ticker;
+    let wallet = coin
+        .as_ref()
+        .multisig
+        .as_ref()
+        .or_mm_err(|| MultisigRpcError::CoinIsNotMultisig { coin: ticker.clone() })?;
+    let conf = &coin.as_ref().conf;
+    let coin_format = coin.addr_format();
+
+    let to_id = from_id
+        .checked_add(count)
+        .or_mm_err(|| MultisigRpcError::InvalidRequest("Too big 'from_id' or 'count'".to_owned()))?;
+    let addresses = (from_id..to_id)
+        .map(|address_id| wallet.derive_address(conf, coin_format, chain, address_id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_to_mm(MultisigRpcError::Internal)?;
+
+    let balances = coin
+        .as_ref()
+        .rpc_client
+        .display_balances(
+            addresses.iter().map(|address| address.address.clone()).collect(),
+            coin.as_ref().decimals,
+        )
+        .compat()
+        .await?;
+
+    let addresses = addresses
+        .into_iter()
+        .zip(balances)
+        .map(|(address, (_, balance))| {
+            Ok(MultisigAddressInfo {
+                address: address
+                    .address
+                    .display_address()
+                    .map_to_mm(MultisigRpcError::Internal)?,
+                chain: address.chain,
+                address_id: address.address_id,
+                derivation_path: RpcDerivationPath(wallet.derivation_path(address.chain, address.address_id)),
+                script: address.script.to_bytes().into(),
+                balance,
+            })
+        })
+        .collect::<MultisigRpcResult<Vec<_>>>()?;
+    Ok(GetMultisigAddressesResponse {
+        required: wallet.required,
+        cosigners: wallet.xpubs.len(),
+        script_type: wallet.script_type,
+        addresses,
+    })
+}
+
+/// Returns the first address of the internal chain that has never been used,
+/// so the change of every withdrawal is sent to a new address.
+/// The change address must be within `address_count` for its unspents to be looked up.
+async fn fresh_change_address<Coin>(
+    coin: &Coin,
+    wallet: &UtxoMultisigWallet,
+) -> MmResult<MultisigAddress, WithdrawError>
+where
+    Coin: UtxoCommonOps,
+{
+    let conf = &coin.as_ref().conf;
+    let coin_format = coin.addr_format();
+    let address_scanner = UtxoAddressScanner::init(coin.as_ref().rpc_client.clone()).await?;
+    for address_id in 0..wallet.address_count {
+        let address = wallet
+            .derive_address(conf, coin_format, Bip44Chain::Internal, address_id)
+            .map_to_mm(WithdrawError::InternalError)?;
+        if !address_scanner.is_address_used(&address.address).await? {
+            return Ok(address);
+        }
+    }
+    MmError::err(WithdrawError::InternalError(format!(
+        "All {} multisig change addresses are used, 'address_count' should be increased",
+        wallet.address_count
+    )))
+}
+
+/// Generates the withdrawal spending the outputs of all the multisig addresses as an unsigned PSBT.
+/// The change is sent to the first unused address of the internal chain.
+/// The inputs of the PSBT are locked until it's finalized or for [`PSBT_INPUTS_LOCK_DURATION`],
+/// so they aren't spent by other withdrawals while the cosigners are signing it.
+pub async fn withdraw_multisig<Coin>(ctx: &MmArc, coin: &Coin, req: WithdrawRequest) -> WithdrawResult
+where
+    Coin: UtxoCommonOps + GetUtxoMapOps + UtxoSignerOps,
+{
+    let ticker = coin.as_ref().conf.ticker.clone();
+    let wallet = coin
+        .as_ref()
+        .multisig
+        .as_ref()
+        .or_mm_err(|| WithdrawError::CoinIsNotMultisig { coin: ticker })?;
+    let conf = &coin.as_ref().conf;
+    let coin_format = coin.addr_format();
+
+    let change = fresh_change_address(coin, wallet).await?;
+    let from = change
+        .address
+        .display_address()
+        .map_to_mm(WithdrawError::InternalError)?;
+    let addresses = wallet
+        .all_addresses(conf, coin_format)
+        .and_then(addresses_by_script)
+        .map_to_mm(WithdrawError::InternalError)?;
+
+    let (unsigned, data) = {
+        let _utxo_lock = UTXO_LOCK.lock().await;
+        let (unspent_map, _) = coin
+            .get_unspent_ordered_map(addresses.values().map(|address| address.address.clone()).collect())
+            .await?;
+        let mut unspents: Vec<UnspentInfo> = unspent_map.into_values().flatten().collect();
+        unspents.sort_by(|a, b| a.value.cmp(&b.value));
+
+        let tx_builder = UtxoTxBuilder::new(coin)
+            .await
+            .with_from_address(change.address.clone())
+            .with_multisig_inputs(wallet.required, change.script.len());
+        let (unsigned, data) = build_withdraw_tx(coin, tx_builder, unspents, &req).await?;
+        let inputs = unsigned.inputs.iter().map(|input| input.previous_output).collect();
+        lock_unspents_until(ctx, coin, inputs, now_sec() + PSBT_INPUTS_LOCK_DURATION)
+            .await
+            .mm_err(|e| WithdrawError::InternalError(e.to_string()))?;
+        (unsigned, data)
+    };
+    let inputs: Vec<OutPoint> = unsigned.inputs.iter().map(|input| input.previous_output).collect();
+    match multisig_psbt(coin, wallet, &addresses, &req, &from, unsigned).await {
+        Ok(psbt) => {
+            let tx = TransactionData::new_unsigned(json!({ "psbt": STANDARD.encode(psbt) }));
+            Ok(withdraw_tx_details(coin, &req, from, data, tx))
+        },
+        Err(e) => {
+            unlock_expiring_unspents(ctx, coin, &inputs).await.error_log();
+            Err(e)
+        },
+    }
+}
+
+/// Returns the serialized PSBT of the `unsigned` multisig withdrawal.
+async fn multisig_psbt<Coin>(
+    coin: &Coin,
+    wallet: &UtxoMultisigWallet,
+    addresses: &HashMap<Bytes, MultisigAddress>,
+    req: &WithdrawRequest,
+    change: &str,
+    unsigned: TransactionInputSigner,
+) -> Result<Vec<u8>, MmError<WithdrawError>>
+where
+    Coin: UtxoCommonOps + UtxoSignerOps,
+{
+    let mut sign_params = UtxoSignTxParamsBuilder::new();
+    for input in unsigned.inputs.iter() {
+        let address = addresses
+            .get(&input.prev_script.to_bytes())
+            .or_mm_err(|| WithdrawError::InternalError(format!("Unknown input script '{}'", input.prev_script)))?;
+        sign_params.add_inputs_infos(std::iter::once(wallet.input_info(address)));
+    }
+    let recipients = withdraw_recipients(req);
+    sign_params.add_outputs_infos(recipients.iter().map(|(to, _amount)| SendingOutputInfo {
+        destination_address: OutputDestination::plain(to.to_string()),
+    }));
+    if unsigned.outputs.len() > recipients.len() {
+        sign_params.add_outputs_infos(std::iter::once(SendingOutputInfo {
+            destination_address: OutputDestination::plain(change.to_owned()),
+        }));
+    }
+    let signature_version = match wallet.script_type {
+        MultisigScriptType::P2SH => coin.as_ref().conf.signature_version,
+        MultisigScriptType::P2WSH => SignatureVersion::WitnessV0,
+    };
+    sign_params
+        .with_signature_version(signature_version)
+        .with_unsigned_tx(unsigned);
+
+    let psbt = coin.unsigned_psbt(sign_params.build()?, None).await?;
+    psbt.serialize().mm_err(|e| WithdrawError::InternalError(e.to_string()))
+}
+
+/// Combines the given PSBTs, adds the cosigners' signatures and signs the multisig inputs with this wallet's keys.
+pub async fn sign_multisig_psbt<Coin>(
+    ctx: &MmArc,
+    coin: &Coin,
+    req: SignMultisigPsbtRequest,
+    task_handle: SignMultisigPsbtTaskHandleShared,
+) -> MultisigRpcResult<SignMultisigPsbtResponse>
+where
+    Coin: UtxoCommonOps + UtxoSignerOps,
+{
+    let ticker = coin.as_ref().conf.ticker.clone();
+    let wallet = coin
+        .as_ref()
+        .multisig
+        .as_ref()
+        .or_mm_err(|| MultisigRpcError::CoinIsNotMultisig { coin: ticker })?;
+
+    let mut psbts = req.psbts.iter().map(|psbt| -> MultisigRpcResult<Psbt> {
+        let psbt = STANDARD
+            .decode(psbt.trim())
+            .map_to_mm(|e| MultisigRpcError::InvalidPsbt(e.to_string()))?;
+        Ok(Psbt::deserialize(&psbt)?)
+    });
+    let mut psbt = psbts
+        .next()
+        .or_mm_err(|| MultisigRpcError::InvalidRequest("At least one PSBT is expected".to_owned()))??;
+    for other in psbts {
+        psbt.combine(other?)?;
+    }
+    for signature in req.signatures.iter() {
+        psbt.insert_signature(signature.input_index, &signature.signature)?;
+    }
+
+    if req.sign {
+        task_handle.update_in_progress_status(SignMultisigPsbtInProgressStatus::SigningTransaction)?;
+        sign_own_inputs(ctx, coin, wallet, &req.outputs, &mut psbt, &task_handle).await?;
+    }
+
+    task_handle.update_in_progress_status(SignMultisigPsbtInProgressStatus::Finishing)?;
+    let not_signed_inputs = match psbt.clone().finalize() {
+        Ok(_) => Vec::new(),
+        Err(e) => match e.into_inner() {
+            PsbtError::NotSigned(not_signed) => not_signed,
+            other => return MmError::err(MultisigRpcError::from(other)),
+        },
+    };
+    Ok(SignMultisigPsbtResponse {
+        psbt: STANDARD.encode(psbt.serialize()?),
+        not_signed_inputs,
+    })
+}
+
+/// Signs the inputs spending the outputs of this wallet's multisig addresses
+/// if the transaction pays the `expected_outputs` only.
+async fn sign_own_inputs<Coin>(
+    ctx: &MmArc,
+    coin: &Coin,
+    wallet: &UtxoMultisigWallet,
+    expected_outputs: &[MultisigPsbtOutput],
+    psbt: &mut Psbt,
+    task_handle: &SignMultisigPsbtTaskHandleShared,
+) -> MultisigRpcResult<()>
+where
+    Coin: UtxoCommonOps + UtxoSignerOps,
+{
+    let addresses = wallet
+        .all_addresses(&coin.as_ref().conf, coin.addr_format())
+        .and_then(addresses_by_script)
+        .map_to_mm(MultisigRpcError::Internal)?;
+    let unsigned = psbt.input_signer()?;
+    validate_psbt_outputs(coin, &addresses, &unsigned.outputs, expected_outputs)?;
+    let own_inputs: Vec<Option<&MultisigAddress>> = unsigned
+        .inputs
+        .iter()
+        .map(|input| addresses.get(&input.prev_script.to_bytes()))
+        .collect();
+
+    match coin.as_ref().priv_key_policy {
+        PrivKeyPolicy::HDWallet { .. } => {
+            for (input_index, address) in own_inputs.into_iter().enumerate() {
+                if let Some(address) = address {
+                    let derivation_path = wallet.derivation_path(address.chain, address.address_id);
+                    let key_pair = derive_hd_key_pair(coin, &derivation_path)?;
+                    psbt.sign_input(input_index, &key_pair)?;
+                }
+            }
+            Ok(())
+        },
+        PrivKeyPolicy::Trezor => {
+            // Trezor signs the whole transaction, so every input must be known.
+            let mut inputs_infos = Vec::with_capacity(own_inputs.len());
+            for (input_index, address) in own_inputs.into_iter().enumerate() {
+                let address = address.or_mm_err(|| {
+                    let error = format!("Input {} doesn't spend an output of the multisig wallet", input_index);
+                    MultisigRpcError::InvalidPsbt(error)
+                })?;
+                inputs_infos.push(wallet.input_info(address));
+            }
+            let mut outputs_infos = Vec::with_capacity(unsigned.outputs.len());
+            for output in unsigned.outputs.iter() {
+                let script = Script::from(output.script_pubkey.clone());
+                let address = utxo_common::addresses_from_script(coin, &script)
+                    .map_to_mm(MultisigRpcError::InvalidPsbt)?
+                    .into_iter()
+                    .next()
+                    .or_mm_err(|| MultisigRpcError::InvalidPsbt(format!("Unsupported output script '{}'", script)))?;
+                let address = address.display_address().map_to_mm(MultisigRpcError::Internal)?;
+                outputs_infos.push(SendingOutputInfo {
+                    destination_address: OutputDestination::plain(address),
+                });
+            }
+            let signature_version = match wallet.script_type {
+                MultisigScriptType::P2SH => coin.as_ref().conf.signature_version,
+                MultisigScriptType::P2WSH => SignatureVersion::WitnessV0,
+            };
+            let mut sign_params = UtxoSignTxParamsBuilder::new();
+            sign_params
+                .add_inputs_infos(inputs_infos.into_iter())
+                .add_outputs_infos(outputs_infos.into_iter())
+                .with_signature_version(signature_version)
+                .with_unsigned_tx(unsigned);
+
+            let trezor_statuses = TrezorRequestStatuses {
+                on_button_request: SignMultisigPsbtInProgressStatus::FollowHwDeviceInstructions,
+                on_pin_request: HwRpcTaskAwaitingStatus::EnterTrezorPin,
+                on_passphrase_request: HwRpcTaskAwaitingStatus::EnterTrezorPassphrase,
+                on_ready: SignMultisigPsbtInProgressStatus::FollowHwDeviceInstructions,
+            };
+            let sign_processor = Arc::new(TrezorRpcTaskProcessor::new(task_handle.clone(), trezor_statuses));
+            let crypto_ctx = CryptoCtx::from_ctx(ctx)?;
+            let hw_ctx = crypto_ctx
+                .hw_ctx()
+                .or_mm_err(|| MultisigRpcError::HwError(HwRpcError::NoTrezorDeviceAvailable))?;
+            let trezor = hw_ctx.trezor(sign_processor).await?;
+            task_handle.update_in_progress_status(SignMultisigPsbtInProgressStatus::WaitingForUserToConfirmSigning)?;
+
+            let signer = TrezorTxSigner {
+                trezor,
+                tx_provider: coin.tx_provider(),
+                trezor_coin: coin.trezor_coin()?,
+                params: sign_params.build()?,
+                fork_id: coin.fork_id(),
+                branch_id: coin.branch_id(),
+            };
+            for (input_index, signature) in signer.signatures().await?.into_iter().enumerate() {
+                psbt.insert_signature(input_index, &signature)?;
+            }
+            Ok(())
+        },
+        PrivKeyPolicy::Iguana(_) => MmError::err(MultisigRpcError::Internal(
+            "Multisig wallet can't be activated with an Iguana key".to_owned(),
+        )),
+        #[cfg(target_arch = "wasm32")]
+        PrivKeyPolicy::Metamask(_) => MmError::err(MultisigRpcError::Internal(
+            "`PrivKeyPolicy::Metamask` is not supported for UTXO coins!".to_owned(),
+        )),
+    }
+}
+
+/// Checks that the transaction pays exactly the `expected` amounts to the `expected` addresses,
+/// the rest of the outputs must return the change to the multisig wallet `addresses`.
+fn validate_psbt_outputs<Coin>(
+    coin: &Coin,
+    addresses: &HashMap<Bytes, MultisigAddress>,
+    outputs: &[TransactionOutput],
+    expected: &[MultisigPsbtOutput],
+) -> MultisigRpcResult<()>
+where
+    Coin: UtxoCommonOps,
+{
+    let decimals = coin.as_ref().decimals;
+    let mut expected_outputs = Vec::with_capacity(expected.len());
+    for output in expected {
+        let address = coin
+            .address_from_str(&output.address)
+            .map_to_mm(|e| MultisigRpcError::InvalidRequest(e.to_string()))?;
+        let script = output_script(&address).map_to_mm(|e| MultisigRpcError::InvalidRequest(e.to_string()))?;
+        let value = sat_from_big_decimal(&output.amount, decimals)
+            .map_to_mm(|e| MultisigRpcError::InvalidRequest(e.to_string()))?;
+        expected_outputs.push((output, script.to_bytes(), value));
+    }
+
+    for output in outputs {
+        let expected_position = expected_outputs
+            .iter()
+            .position(|(_, script, value)| *script == output.script_pubkey && *value == output.value);
+        match expected_position {
+            Some(position) => {
+                expected_outputs.swap_remove(position);
+            },
+            None if addresses.contains_key(&output.script_pubkey) => (),
+            None => {
+                let error = format!(
+                    "Unexpected output paying {} to '{}'",
+                    big_decimal_from_sat_unsigned(output.value, decimals),
+                    Script::from(output.script_pubkey.clone())
+                );
+                return MmError::err(MultisigRpcError::InvalidPsbt(error));
+            },
+        }
+    }
+    if let Some((missing, _, _)) = expected_outputs.first() {
+        let error = format!("No output paying {} to '{}'", missing.amount, missing.address);
+        return MmError::err(MultisigRpcError::InvalidPsbt(error));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::utxo::rpc_clients::{NativeClient, NativeClientImpl, UtxoRpcClientEnum};
+    use crate::utxo::utxo_common_tests::{utxo_coin_fields_for_test, utxo_coin_from_fields};
+    use mm2_number::BigDecimal;
+
+    /// The master xpubs of the `[1; 32]`, `[2; 32]` and `[3; 32]` seeds.
+    const XPUBS: [&str; 3] = [
+        "xpub661MyMwAqRbcEtUEgdXRTY6dJQG9fRgs7C5QomqETKMYBJVtSGpRqyHSmhWy8snovPd5oWZgQ14zUquxbxu7Z1umuXbN5VDpUL1QobD5xUY",
+        "xpub661MyMwAqRbcGFeMhhkrJL6Yj3YKQFNZQSM2BAvoMmhdjNKBh43n5v3c4YT5dFtjkirfhqQHMd22br7cHAQXAV8cZdicedZJkNweja4WWBK",
+        "xpub661MyMwAqRbcFNWTmtbxHaYsjddAazfBQ51wm61veSzW3z2N7s1U7M9epMGsedj3unJKhXBY2sxWpQutwrZnpFuWe2vVVvLig8nFT4nmG67",
+    ];
+
+    fn multisig_wallet(xpubs: &[&str], script_type: MultisigScriptType) -> UtxoMultisigWallet {
+        let params = UtxoMultisigParams {
+            required: 2,
+            xpubs: xpubs.iter().map(|xpub| xpub.to_string()).collect(),
+            account_derivation_path: "m/48'/141'/0'/2'".to_owned(),
+            script_type,
+            address_count: None,
+        };
+        UtxoMultisigWallet::from_params(&params).unwrap()
+    }
+
+    fn coin_conf(is_segwit_coin: bool) -> UtxoCoinConf {
+        let client = NativeClient(Arc::new(NativeClientImpl::default()));
+        utxo_coin_fields_for_test(UtxoRpcClientEnum::Native(client), None, is_segwit_coin).conf
+    }
+
+    #[test]
+    fn test_derive_multisig_address_bip67() {
+        let conf = coin_conf(false);
+
+        // The derived keys `1/5` are `[034d58.., 03a8b0.., 02667f..]`, so the script is ordered as `[2, 0, 1]`.
+        let expected_script = concat!(
+            "522102667f4f6d5efc57027bf787052b8c5ec6bfa7d1b5c29ff924f3c7078ea581a63d21034d585b646c7d53194e2c63",
+            "ed6a200e62dd87db183909446ff9b9ae6d3f9c828b2103a8b0424fb76e883c6abe2808dd30c22834313fe4f8c3f91bd54e",
+            "25f9e7c2616553ae"
+        );
+        let wallet = multisig_wallet(&XPUBS, MultisigScriptType::P2SH);
+        let address = wallet
+            .derive_address(&conf, &AddressFormat::Standard, Bip44Chain::Internal, 5)
+            .unwrap();
+        assert_eq!(hex::encode(&*address.script), expected_script);
+        assert_eq!(address.address.to_string(), "bD4v8V171jS7BBdVKVziswFNrM4E5ZKWBB");
+        let cosigners_order: Vec<_> = address
+            .cosigners
+            .iter()
+            .map(|cosigner| {
+                wallet
+                    .xpubs
+                    .iter()
+                    .position(|xpub| xpub.public_key() == cosigner.xpub.public_key())
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(cosigners_order, vec![2, 0, 1]);
+
+        // The cosigners get the same address regardless of the order of the xpubs.
+        let reordered = multisig_wallet(&[XPUBS[1], XPUBS[2], XPUBS[0]], MultisigScriptType::P2SH);
+        let reordered_address = reordered
+            .derive_address(&conf, &AddressFormat::Standard, Bip44Chain::Internal, 5)
+            .unwrap();
+        assert_eq!(reordered_address.address, address.address);
+
+        let expected_script = concat!(
+            "5221020cbfd8fec17e1e6033bedcb124333805f70ea4179129ccbfea7f1a3ebd50ed8621025ffa13f12a5b259d19f89d",
+            "a22d6109f3dc2bf63cc5d4e2873dde0e3593a19620210325e6e4bcc31905f66a4006205f2b7caff683785206bbfae0ee",
+            "373e52b8168b4f53ae"
+        );
+        let address = wallet
+            .derive_address(&conf, &AddressFormat::Standard, Bip44Chain::External, 0)
+            .unwrap();
+        assert_eq!(hex::encode(&*address.script), expected_script);
+        assert_eq!(address.address.to_string(), "bHq9JKJGwu9AMtABkzhAuppS9okFaLStZ9");
+
+        let wallet = multisig_wallet(&XPUBS, MultisigScriptType::P2WSH);
+        let address = wallet
+            .derive_address(&coin_conf(true), &AddressFormat::Segwit, Bip44Chain::External, 0)
+            .unwrap();
+        let expected_script_pubkey = "002013087930ce78c60ffff53378863974a831d74f1240eb9c00adc15dae885ac3e6";
+        let script_pubkey = output_script(&address.address).unwrap();
+        assert_eq!(hex::encode(&*script_pubkey), expected_script_pubkey);
+    }
+
+    #[test]
+    fn test_validate_psbt_outputs() {
+        let client = NativeClient(Arc::new(NativeClientImpl::default()));
+        let coin = utxo_coin_from_fields(utxo_coin_fields_for_test(
+            UtxoRpcClientEnum::Native(client),
+            None,
+            false,
+        ));
+        let conf = &coin.as_ref().conf;
+        let wallet = multisig_wallet(&XPUBS, MultisigScriptType::P2SH);
+        let addresses = addresses_by_script(wallet.all_addresses(conf, &AddressFormat::Standard).unwrap()).unwrap();
+        let change = wallet
+            .derive_address(conf, &AddressFormat::Standard, Bip44Chain::Internal, 0)
+            .unwrap();
+        let recipient = multisig_wallet(&XPUBS[..2], MultisigScriptType::P2SH)
+            .derive_address(conf, &AddressFormat::Standard, Bip44Chain::External, 0)
+            .unwrap();
+        let outputs = vec![
+            TransactionOutput {
+                value: 100_000_000,
+                script_pubkey: output_script(&recipient.address).unwrap().to_bytes(),
+            },
+            TransactionOutput {
+                value: 50_000,
+                script_pubkey: output_script(&change.address).unwrap().to_bytes(),
+            },
+        ];
+        let expected = |amount: &str| {
+            vec![MultisigPsbtOutput {
+                address: recipient.address.to_string(),
+                amount: BigDecimal::from_str(amount).unwrap(),
+            }]
+        };
+
+        // The change is returned to the multisig wallet.
+        validate_psbt_outputs(&coin, &addresses, &outputs, &expected("1")).unwrap();
+
+        // The recipient gets another amount.
+        let error = validate_psbt_outputs(&coin, &addresses, &outputs, &expected("0.9"))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(error, MultisigRpcError::InvalidPsbt(_)), "{:?}", error);
+
+        // The output isn't expected at all.
+        let error = validate_psbt_outputs(&coin, &addresses, &outputs, &[])
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(error, MultisigRpcError::InvalidPsbt(_)), "{:?}", error);
+
+        // The expected output is missing.
+        let error = validate_psbt_outputs(&coin, &addresses, &outputs[1..], &expected("1"))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(error, MultisigRpcError::InvalidPsbt(_)), "{:?}", error);
+    }
+}
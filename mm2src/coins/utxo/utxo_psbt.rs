@@ -0,0 +1,56 @@
+//! Finalization and broadcasting of the [BIP-174](https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki)
+//! PSBTs signed by external signers. The unsigned PSBTs are generated by [`super::utxo_withdraw::PsbtUtxoWithdraw`].
+
+use crate::rpc_command::psbt::{FinalizePsbtError, FinalizePsbtRequest, FinalizePsbtResponse, FinalizePsbtResult};
+use crate::utxo::utxo_coin_control::unlock_expiring_unspents;
+use crate::utxo::UtxoCommonOps;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::compat::Future01CompatExt;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use rpc::v1::types::ToTxHash;
+use serialization::{serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use utxo_signer::psbt::Psbt;
+
+/// Finalizes the inputs of the signed PSBT, extracts the signed transaction and broadcasts it if it's requested.
+/// The inputs locked by [`super::utxo_withdraw::PsbtUtxoWithdraw`] or [`super::utxo_multisig::withdraw_multisig`]
+/// are unlocked once the transaction is broadcasted,
+/// otherwise they're kept locked until the lock expires as the transaction can be broadcasted later.
+pub async fn finalize_psbt<T>(
+    ctx: &MmArc,
+    coin: &T,
+    req: FinalizePsbtRequest,
+) -> FinalizePsbtResult<FinalizePsbtResponse>
+where
+    T: UtxoCommonOps,
+{
+    let psbt = STANDARD
+        .decode(req.psbt.trim())
+        .map_to_mm(|e| FinalizePsbtError::InvalidPsbt(e.to_string()))?;
+    let psbt = Psbt::deserialize(&psbt)?;
+
+    let mut signed = psbt.finalize()?;
+    signed.tx_hash_algo = coin.as_ref().tx_hash_algo;
+    let tx_hex = serialize_with_flags(&signed, SERIALIZE_TRANSACTION_WITNESS);
+    let tx_hash = signed.hash().reversed().to_vec().to_tx_hash();
+
+    if req.broadcast {
+        coin.as_ref()
+            .rpc_client
+            .send_raw_transaction(tx_hex.clone().into())
+            .compat()
+            .await?;
+
+        let inputs: Vec<_> = signed.inputs.iter().map(|input| input.previous_output).collect();
+        unlock_expiring_unspents(ctx, coin, &inputs)
+            .await
+            .mm_err(|e| FinalizePsbtError::InternalError(e.to_string()))?;
+    }
+
+    Ok(FinalizePsbtResponse {
+        tx_hex: tx_hex.into(),
+        tx_hash,
+        broadcasted: req.broadcast,
+    })
+}
@@ -17,7 +17,6 @@ use crate::rpc_command::init_scan_for_new_addresses::{self, InitScanAddressesRpc
                                                       ScanAddressesResponse};
 use crate::rpc_command::init_withdraw::{InitWithdrawCoin, WithdrawTaskHandleShared};
 use crate::tx_history_storage::{GetTxHistoryFilters, WalletId};
-use crate::utxo::rpc_clients::BlockHashOrHeight;
 use crate::utxo::utxo_builder::{UtxoArcBuilder, UtxoCoinBuilder};
 use crate::utxo::utxo_hd_wallet::{UtxoHDAccount, UtxoHDAddress};
 use crate::utxo::utxo_tx_history_v2::{UtxoMyAddressesHistoryError, UtxoTxDetailsError, UtxoTxDetailsParams,
@@ -25,14 +24,14 @@ use crate::utxo::utxo_tx_history_v2::{UtxoMyAddressesHistoryError, UtxoTxDetails
 use crate::{CanRefundHtlc, CheckIfMyPaymentSentArgs, CoinBalance, CoinBalanceMap, CoinWithDerivationMethod,
             CoinWithPrivKeyPolicy, CommonSwapOpsV2, ConfirmPaymentInput, DexFee, FindPaymentSpendError,
             FundingTxSpend, GenPreimageResult, GenTakerFundingSpendArgs, GenTakerPaymentSpendArgs,
-            GetWithdrawSenderAddress, IguanaBalanceOps, IguanaPrivKey, MakerCoinSwapOpsV2, MmCoinEnum,
-            NegotiateSwapContractAddrErr, PrivKeyBuildPolicy, RawTransactionRequest, RawTransactionResult,
-            RefundFundingSecretArgs, RefundMakerPaymentSecretArgs, RefundMakerPaymentTimelockArgs, RefundPaymentArgs,
-            RefundTakerPaymentArgs, SearchForFundingSpendErr, SearchForSwapTxSpendInput, SendMakerPaymentArgs,
-            SendMakerPaymentSpendPreimageInput, SendPaymentArgs, SendTakerFundingArgs, SignRawTransactionRequest,
-            SignatureResult, SpendMakerPaymentArgs, SpendPaymentArgs, SwapOps, SwapTxTypeWithSecretHash,
-            TakerCoinSwapOpsV2, ToBytes, TradePreimageValue, TransactionFut, TransactionResult, TxMarshalingErr,
-            TxPreimageWithSig, ValidateAddressResult, ValidateFeeArgs, ValidateMakerPaymentArgs,
+            GetWithdrawSenderAddress, IguanaBalanceOps, IguanaPrivKey, MakerCoinSwapOpsV2,
+            MakerPaymentSpendForWatcherArgs, MmCoinEnum, NegotiateSwapContractAddrErr, PrivKeyBuildPolicy,
+            RawTransactionRequest, RawTransactionResult, RefundFundingSecretArgs, RefundMakerPaymentSecretArgs,
+            RefundMakerPaymentTimelockArgs, RefundPaymentArgs, RefundTakerPaymentArgs, SearchForFundingSpendErr,
+            SearchForSwapTxSpendInput, SendMakerPaymentArgs, SendMakerPaymentSpendPreimageInput, SendPaymentArgs,
+            SendTakerFundingArgs, SignRawTransactionRequest, SignatureResult, SpendMakerPaymentArgs, SpendPaymentArgs,
+            SwapOps, TakerCoinSwapOpsV2, ToBytes, TradePreimageValue, TransactionFut, TransactionResult,
+            TxMarshalingErr, TxPreimageWithSig, ValidateAddressResult, ValidateFeeArgs, ValidateMakerPaymentArgs,
             ValidateOtherPubKeyErr, ValidatePaymentError, ValidatePaymentFut, ValidatePaymentInput,
             ValidateSwapV2TxResult, ValidateTakerFundingArgs, ValidateTakerFundingSpendPreimageResult,
             ValidateTakerPaymentSpendPreimageResult, ValidateWatcherSpendInput, VerificationResult,
@@ -43,7 +42,6 @@ use futures::{FutureExt, TryFutureExt};
 use mm2_metrics::MetricsArc;
 use mm2_number::MmNumber;
 #[cfg(test)] use mocktopus::macros::*;
-use script::Opcode;
 use utxo_signer::UtxoSignerOps;
 
 #[derive(Clone)]
@@ -514,12 +512,12 @@ impl WatcherOps for UtxoStandardCoin {
 
     #[inline]
     fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
-        utxo_common::watcher_validate_taker_payment(self, input)
+        utxo_common::watcher_validate_taker_payment(self, input, utxo_common::DEFAULT_SWAP_VOUT)
     }
 
     #[inline]
     fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
-        utxo_common::validate_payment_spend_or_refund(self, input)
+        utxo_common::validate_payment_spend_or_refund(self, input, utxo_common::DEFAULT_SWAP_VOUT)
     }
 
     #[inline]
@@ -557,6 +555,53 @@ impl WatcherOps for UtxoStandardCoin {
     ) -> Result<Option<WatcherReward>, MmError<WatcherRewardError>> {
         Ok(None)
     }
+
+    fn is_supported_by_watchers_v2(&self) -> bool { true }
+
+    async fn sign_refund_for_watcher_v2(&self, args: RefundPaymentArgs<'_>) -> TransactionResult {
+        utxo_common::sign_refund_for_watcher_v2(self, args)
+            .await
+            .map(|tx| tx.into())
+    }
+
+    async fn sign_maker_payment_spend_for_watcher_v2(
+        &self,
+        args: MakerPaymentSpendForWatcherArgs<'_>,
+    ) -> TransactionResult {
+        utxo_common::sign_maker_payment_spend_for_watcher_v2(self, args)
+            .await
+            .map(|tx| tx.into())
+    }
+
+    async fn watcher_validate_preimage_v2(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        is_refund: bool,
+    ) -> ValidatePaymentResult<()> {
+        utxo_common::watcher_validate_preimage_v2(self, payment_tx, preimage, is_refund).await
+    }
+
+    async fn send_preimage_as_watcher_v2(
+        &self,
+        payment_tx: &[u8],
+        preimage: &[u8],
+        secret: Option<&[u8]>,
+    ) -> TransactionResult {
+        utxo_common::send_preimage_as_watcher_v2(self, payment_tx, preimage, secret)
+            .await
+            .map(|tx| tx.into())
+    }
+
+    async fn watcher_search_for_payment_spend_v2(
+        &self,
+        payment_tx: &[u8],
+        search_from_block: u64,
+    ) -> Result<Option<TransactionEnum>, String> {
+        utxo_common::watcher_search_for_payment_spend_v2(self, payment_tx, search_from_block)
+            .await
+            .map(|spend| spend.map(|tx| tx.into()))
+    }
 }
 
 impl ToBytes for Public {
@@ -570,44 +615,14 @@ impl MakerCoinSwapOpsV2 for UtxoStandardCoin {
     }
 
     async fn validate_maker_payment_v2(&self, args: ValidateMakerPaymentArgs<'_, Self>) -> ValidatePaymentResult<()> {
-        let taker_pub = self.derive_htlc_pubkey_v2(args.swap_unique_data);
-        let time_lock = args
-            .time_lock
-            .try_into()
-            .map_to_mm(ValidatePaymentError::TimelockOverflow)?;
-        utxo_common::validate_payment(
-            self.clone(),
-            args.maker_payment_tx,
-            utxo_common::DEFAULT_SWAP_VOUT,
-            args.maker_pub,
-            &taker_pub,
-            SwapTxTypeWithSecretHash::MakerPaymentV2 {
-                maker_secret_hash: args.maker_secret_hash,
-                taker_secret_hash: args.taker_secret_hash,
-            },
-            args.amount,
-            None,
-            time_lock,
-            0,
-            0,
-        )
-        .await
+        utxo_common::validate_maker_payment_v2(self, args).await
     }
 
     async fn refund_maker_payment_v2_timelock(
         &self,
         args: RefundMakerPaymentTimelockArgs<'_>,
     ) -> Result<Self::Tx, TransactionErr> {
-        let args = RefundPaymentArgs {
-            payment_tx: args.payment_tx,
-            time_lock: args.time_lock,
-            other_pubkey: args.taker_pub,
-            tx_type_with_secret_hash: args.tx_type_with_secret_hash,
-            swap_contract_address: &None,
-            swap_unique_data: args.swap_unique_data,
-            watcher_reward: args.watcher_reward,
-        };
-        utxo_common::refund_htlc_payment(self.clone(), args).await
+        utxo_common::refund_maker_payment_v2_timelock(self.clone(), args).await
     }
 
     async fn refund_maker_payment_v2_secret(
@@ -636,16 +651,7 @@ impl TakerCoinSwapOpsV2 for UtxoStandardCoin {
         &self,
         args: RefundTakerPaymentArgs<'_>,
     ) -> Result<Self::Tx, TransactionErr> {
-        let args = RefundPaymentArgs {
-            payment_tx: args.payment_tx,
-            time_lock: args.time_lock,
-            other_pubkey: args.maker_pub,
-            tx_type_with_secret_hash: args.tx_type_with_secret_hash,
-            swap_contract_address: &None,
-            swap_unique_data: args.swap_unique_data,
-            watcher_reward: args.watcher_reward,
-        };
-        utxo_common::refund_htlc_payment(self.clone(), args).await
+        utxo_common::refund_taker_tx_v2_timelock(self.clone(), args).await
     }
 
     async fn refund_taker_funding_secret(
@@ -661,72 +667,7 @@ impl TakerCoinSwapOpsV2 for UtxoStandardCoin {
         from_block: u64,
         _secret_hash: &[u8],
     ) -> Result<Option<FundingTxSpend<Self>>, SearchForFundingSpendErr> {
-        let script_pubkey = &tx
-            .first_output()
-            .map_err(|e| SearchForFundingSpendErr::InvalidInputTx(e.to_string()))?
-            .script_pubkey;
-
-        let from_block = from_block
-            .try_into()
-            .map_err(SearchForFundingSpendErr::FromBlockConversionErr)?;
-
-        let output_spend = self
-            .as_ref()
-            .rpc_client
-            .find_output_spend(
-                tx.hash(),
-                script_pubkey,
-                utxo_common::DEFAULT_SWAP_VOUT,
-                BlockHashOrHeight::Height(from_block),
-                self.as_ref().tx_hash_algo,
-            )
-            .compat()
-            .await
-            .map_err(SearchForFundingSpendErr::Rpc)?;
-        match output_spend {
-            Some(found) => {
-                let script_sig: Script = found.input.script_sig.into();
-                let maybe_first_op_if = script_sig
-                    .get_instruction(1)
-                    .ok_or_else(|| {
-                        SearchForFundingSpendErr::FailedToProcessSpendTx("No instruction at index 1".into())
-                    })?
-                    .map_err(|e| {
-                        SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
-                            "Couldn't get instruction at index 1: {}",
-                            e
-                        ))
-                    })?;
-                match maybe_first_op_if.opcode {
-                    Opcode::OP_1 => Ok(Some(FundingTxSpend::RefundedTimelock(found.spending_tx))),
-                    Opcode::OP_PUSHBYTES_32 => Ok(Some(FundingTxSpend::RefundedSecret {
-                        tx: found.spending_tx,
-                        secret: maybe_first_op_if
-                            .data
-                            .ok_or_else(|| {
-                                SearchForFundingSpendErr::FailedToProcessSpendTx(
-                                    "No data at instruction with index 1".into(),
-                                )
-                            })?
-                            .try_into()
-                            .map_err(|e| {
-                                SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
-                                    "Failed to parse data at instruction with index 1 as [u8; 32]: {}",
-                                    e
-                                ))
-                            })?,
-                    })),
-                    Opcode::OP_PUSHBYTES_70 | Opcode::OP_PUSHBYTES_71 | Opcode::OP_PUSHBYTES_72 => {
-                        Ok(Some(FundingTxSpend::TransferredToTakerPayment(found.spending_tx)))
-                    },
-                    unexpected => Err(SearchForFundingSpendErr::FailedToProcessSpendTx(format!(
-                        "Got unexpected opcode {:?} at instruction with index 1",
-                        unexpected
-                    ))),
-                }
-            },
-            None => Ok(None),
-        }
+        utxo_common::search_for_taker_funding_spend(self.as_ref(), tx, utxo_common::DEFAULT_SWAP_VOUT, from_block).await
     }
 
     async fn gen_taker_funding_spend_preimage(
@@ -760,16 +701,7 @@ impl TakerCoinSwapOpsV2 for UtxoStandardCoin {
         &self,
         args: RefundTakerPaymentArgs<'_>,
     ) -> Result<Self::Tx, TransactionErr> {
-        let args = RefundPaymentArgs {
-            payment_tx: args.payment_tx,
-            time_lock: args.time_lock,
-            other_pubkey: args.maker_pub,
-            tx_type_with_secret_hash: args.tx_type_with_secret_hash,
-            swap_contract_address: &None,
-            swap_unique_data: args.swap_unique_data,
-            watcher_reward: args.watcher_reward,
-        };
-        utxo_common::refund_htlc_payment(self.clone(), args).await
+        utxo_common::refund_taker_tx_v2_timelock(self.clone(), args).await
     }
 
     async fn gen_taker_payment_spend_preimage(
@@ -808,16 +740,7 @@ impl TakerCoinSwapOpsV2 for UtxoStandardCoin {
         from_block: u64,
         wait_until: u64,
     ) -> MmResult<Self::Tx, FindPaymentSpendError> {
-        let res = utxo_common::wait_for_output_spend_impl(
-            self.as_ref(),
-            taker_payment,
-            utxo_common::DEFAULT_SWAP_VOUT,
-            from_block,
-            wait_until,
-            10.,
-        )
-        .await?;
-        Ok(res)
+        utxo_common::find_taker_payment_spend_tx(self.as_ref(), taker_payment, from_block, wait_until).await
     }
 
     async fn extract_secret_v2(&self, secret_hash: &[u8], spend_tx: &Self::Tx) -> Result<[u8; 32], String> {
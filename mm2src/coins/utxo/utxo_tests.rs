@@ -31,7 +31,7 @@ use crate::{BlockHeightAndTime, CoinBalance, CoinBalanceMap, ConfirmPaymentInput
             PrivKeyBuildPolicy, SearchForSwapTxSpendInput, SpendPaymentArgs, StakingInfosDetails, SwapOps,
             TradePreimageValue, TxFeeDetails, TxMarshalingErr, ValidateFeeArgs, INVALID_SENDER_ERR_LOG};
 #[cfg(not(target_arch = "wasm32"))]
-use crate::{WaitForHTLCTxSpendArgs, WithdrawFee};
+use crate::{WaitForHTLCTxSpendArgs, WithdrawFee, WithdrawOutput};
 use chain::{BlockHeader, BlockHeaderBits, OutPoint};
 use common::executor::Timer;
 use common::{block_on, block_on_f01, wait_until_sec, OrdRange, PagingOptionsEnum, DEX_FEE_ADDR_RAW_PUBKEY};
@@ -280,6 +280,48 @@ fn test_generate_transaction() {
     block_on(builder.build()).unwrap_err();
 }
 
+#[test]
+fn test_generate_transaction_with_input_sequence() {
+    let client = electrum_client_for_test(DOC_ELECTRUM_ADDRS);
+    let coin = utxo_coin_for_test(client.into(), None, false);
+    let required = vec![UnspentInfo {
+        value: 50000,
+        outpoint: OutPoint {
+            hash: 1.into(),
+            index: 0,
+        },
+        height: Default::default(),
+        script: Vec::new().into(),
+    }];
+    let available = vec![UnspentInfo {
+        value: 100000,
+        outpoint: OutPoint {
+            hash: 2.into(),
+            index: 0,
+        },
+        height: Default::default(),
+        script: Vec::new().into(),
+    }];
+
+    let outputs = vec![TransactionOutput {
+        script_pubkey: vec![].into(),
+        value: 90000,
+    }];
+
+    let builder = block_on(UtxoTxBuilder::new(&coin))
+        .add_required_inputs(required)
+        .with_input_sequence(SEQUENCE_RBF_ENABLED)
+        .add_available_inputs(available)
+        .add_outputs(outputs);
+    let (unsigned, _) = block_on(builder.build()).unwrap();
+    assert_eq!(unsigned.inputs.len(), 2);
+    assert!(unsigned
+        .inputs
+        .iter()
+        .all(|input| input.sequence == SEQUENCE_RBF_ENABLED));
+    assert!(UtxoTx::from(unsigned).signals_rbf());
+}
+
 #[test]
 fn test_addresses_from_script() {
     let client = electrum_client_for_test(DOC_ELECTRUM_ADDRS);
@@ -625,6 +667,159 @@ fn test_withdraw_impl_set_fixed_fee() {
     assert_eq!(expected, tx_details.fee_details);
 }
 
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_withdraw_multiple_outputs_with_coin_control() {
+    UtxoStandardCoin::get_unspent_ordered_list.mock_safe(|coin, _| {
+        let fut = async move {
+            let cache = coin.as_ref().recently_spent_outpoints.lock().await;
+            let script = coin
+                .script_for_address(&coin.as_ref().derivation_method.unwrap_single_addr().await)
+                .unwrap();
+            let unspents = vec![
+                UnspentInfo {
+                    outpoint: OutPoint {
+                        hash: 1.into(),
+                        index: 0,
+                    },
+                    value: 100000000,
+                    height: Default::default(),
+                    script: script.clone(),
+                },
+                UnspentInfo {
+                    outpoint: OutPoint {
+                        hash: 2.into(),
+                        index: 0,
+                    },
+                    value: 500000000,
+                    height: Default::default(),
+                    script: script.clone(),
+                },
+                UnspentInfo {
+                    outpoint: OutPoint {
+                        hash: 3.into(),
+                        index: 1,
+                    },
+                    value: 1000000000,
+                    height: Default::default(),
+                    script,
+                },
+            ];
+            Ok((unspents, cache))
+        };
+        MockResult::Return(fut.boxed())
+    });
+
+    let client = NativeClient(Arc::new(NativeClientImpl::default()));
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(client), None, false);
+
+    let outputs = vec![
+        WithdrawOutput {
+            to: "RQq6fWoy8aGGMLjvRfMY5mBNVm2RQxJyLa".to_owned(),
+            amount: 1.into(),
+        },
+        WithdrawOutput {
+            to: "R9o9xTocqr6CeEDGDH6mEYpwLoMz6jNjMW".to_owned(),
+            amount: 2.into(),
+        },
+    ];
+    let withdraw_req = WithdrawRequest {
+        coin: TEST_COIN_NAME.into(),
+        outputs,
+        inputs: vec![OutPoint {
+            hash: 2.into(),
+            index: 0,
+        }
+        .into()],
+        exclude_inputs: vec![OutPoint {
+            hash: 1.into(),
+            index: 0,
+        }
+        .into()],
+        fee: Some(WithdrawFee::UtxoFixed {
+            amount: "0.1".parse().unwrap(),
+        }),
+        ..Default::default()
+    };
+    let tx_details = block_on_f01(coin.withdraw(withdraw_req.clone())).unwrap();
+    assert_eq!(tx_details.to, vec![
+        "RQq6fWoy8aGGMLjvRfMY5mBNVm2RQxJyLa".to_owned(),
+        "R9o9xTocqr6CeEDGDH6mEYpwLoMz6jNjMW".to_owned()
+    ]);
+
+    let transaction: UtxoTx = deserialize(tx_details.tx.tx_hex().unwrap().as_slice()).unwrap();
+    // The selected input covers the outputs, so no other input is spent.
+    assert_eq!(transaction.inputs.len(), 1);
+    assert_eq!(transaction.inputs[0].previous_output.hash, 2.into());
+    let output_values: Vec<_> = transaction.outputs.iter().map(|output| output.value).collect();
+    assert_eq!(output_values, vec![100000000, 200000000, 190000000]);
+
+    // The same output can't be both included and excluded.
+    let withdraw_req = WithdrawRequest {
+        exclude_inputs: withdraw_req.inputs.clone(),
+        ..withdraw_req
+    };
+    let error = block_on_f01(coin.withdraw(withdraw_req)).unwrap_err().into_inner();
+    assert!(matches!(error, WithdrawError::InvalidInputs(_)), "{:?}", error);
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_my_balance_excludes_locked_unspents() {
+    NativeClient::display_balance
+        .mock_safe(|_, _, _| MockResult::Return(Box::new(futures01::future::ok(BigDecimal::from(6)))));
+    NativeClient::list_unspent.mock_safe(|_, address, _| {
+        let script = output_script(address).unwrap();
+        let unspents = vec![
+            UnspentInfo {
+                outpoint: OutPoint {
+                    hash: 1.into(),
+                    index: 0,
+                },
+                value: 100000000,
+                height: Default::default(),
+                script: script.clone(),
+            },
+            UnspentInfo {
+                outpoint: OutPoint {
+                    hash: 2.into(),
+                    index: 1,
+                },
+                value: 500000000,
+                height: Default::default(),
+                script,
+            },
+        ];
+        MockResult::Return(Box::new(futures01::future::ok(unspents)))
+    });
+
+    let client = NativeClient(Arc::new(NativeClientImpl::default()));
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(client), None, false);
+
+    let expected = CoinBalance {
+        spendable: 6.into(),
+        unspendable: 0.into(),
+    };
+    assert_eq!(block_on_f01(coin.my_balance()).unwrap(), expected);
+
+    // The locked coins can be neither withdrawn nor traded.
+    let locked = OutPoint {
+        hash: 2.into(),
+        index: 1,
+    };
+    coin.as_ref()
+        .locked_unspents
+        .lock()
+        .unwrap()
+        .lock(iter::once(locked), None);
+    let expected = CoinBalance {
+        spendable: 1.into(),
+        unspendable: 5.into(),
+    };
+    assert_eq!(block_on_f01(coin.my_balance()).unwrap(), expected);
+    assert_eq!(block_on_f01(coin.my_spendable_balance()).unwrap(), 1.into());
+}
+
 #[test]
 #[cfg(not(target_arch = "wasm32"))]
 fn test_withdraw_impl_sat_per_kb_fee() {
@@ -5012,3 +5207,829 @@ fn test_electrum_v14_block_hash() {
     // Verify V14 header produces the same hash as our verified BlockHeader implementation
     assert_eq!(hash, headers[0].hash().into());
 }
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_withdraw_psbt_locks_inputs_until_finalized() {
+    use crate::rpc_command::psbt::FinalizePsbtRequest;
+    use crate::utxo::utxo_psbt::finalize_psbt;
+    use crate::utxo::utxo_withdraw::PsbtUtxoWithdraw;
+    use crate::TransactionData;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use chain::constants::SEQUENCE_FINAL;
+    use script::UnsignedTransactionInput;
+    use utxo_signer::psbt::Psbt;
+
+    let client = NativeClient(Arc::new(NativeClientImpl::default()));
+    let mut fields = utxo_coin_fields_for_test(UtxoRpcClientEnum::Native(client), None, true);
+    // PSBTs of Zcash transactions aren't supported.
+    fields.conf.overwintered = false;
+    fields.conf.zcash = false;
+    fields.conf.tx_version = 2;
+    let coin = utxo_coin_from_fields(fields);
+    let ctx = mm_ctx_with_custom_db();
+
+    let my_address = block_on(coin.as_ref().derivation_method.unwrap_single_addr());
+    let my_script = coin.script_for_address(&my_address).unwrap();
+    let mut prev = coin.as_ref().transaction_preimage();
+    prev.inputs.push(UnsignedTransactionInput {
+        previous_output: OutPoint {
+            hash: 1.into(),
+            index: 0,
+        },
+        prev_script: Builder::default().into_script(),
+        sequence: SEQUENCE_FINAL,
+        amount: 0,
+    });
+    prev.outputs.push(TransactionOutput {
+        value: 1000000000,
+        script_pubkey: my_script.to_bytes(),
+    });
+    let prev = UtxoTx::from(prev);
+    let outpoint = OutPoint {
+        hash: prev.hash(),
+        index: 0,
+    };
+
+    // The previous transaction is added to the PSBT input.
+    let prev_hex: BytesJson = serialize(&prev).take().into();
+    NativeClient::get_verbose_transaction.mock_safe(move |_, txid| {
+        assert_eq!(*txid, H256Json::from(outpoint.hash.reversed()));
+        let verbose = RpcTransaction {
+            hex: prev_hex.clone(),
+            txid: *txid,
+            hash: None,
+            size: None,
+            vsize: None,
+            version: 2,
+            locktime: 0,
+            vin: vec![],
+            vout: vec![],
+            blockhash: Default::default(),
+            confirmations: 1,
+            rawconfirmations: None,
+            time: 0,
+            blocktime: 0,
+            height: None,
+        };
+        MockResult::Return(Box::new(futures01::future::ok(verbose)))
+    });
+    UtxoStandardCoin::get_unspent_ordered_list.mock_safe(move |coin, _| {
+        let fut = async move {
+            let cache = coin.as_ref().recently_spent_outpoints.lock().await;
+            let unspents = vec![UnspentInfo {
+                outpoint,
+                value: 1000000000,
+                height: Default::default(),
+                script: coin
+                    .script_for_address(&coin.as_ref().derivation_method.unwrap_single_addr().await)
+                    .unwrap(),
+            }];
+            Ok((unspents, cache))
+        };
+        MockResult::Return(fut.boxed())
+    });
+    NativeClient::send_raw_transaction
+        .mock_safe(|_, _| MockResult::Return(Box::new(futures01::future::ok(H256Json::default()))));
+
+    let withdraw_req = WithdrawRequest {
+        amount: 1.into(),
+        to: "RQq6fWoy8aGGMLjvRfMY5mBNVm2RQxJyLa".to_string(),
+        coin: TEST_COIN_NAME.into(),
+        ..Default::default()
+    };
+    let withdraw = block_on(PsbtUtxoWithdraw::new(ctx.clone(), coin.clone(), withdraw_req)).unwrap();
+    let tx_details = block_on(withdraw.build()).unwrap();
+    let psbt = match tx_details.tx {
+        TransactionData::Unsigned(ref unsigned) => unsigned["psbt"].as_str().unwrap().to_owned(),
+        TransactionData::Signed { .. } => panic!("Expected an unsigned PSBT"),
+    };
+
+    // The inputs are locked while the PSBT is being signed.
+    assert!(coin.as_ref().locked_unspents.lock().unwrap().contains(&outpoint));
+    let mut psbt = Psbt::deserialize(&STANDARD.decode(psbt).unwrap()).unwrap();
+    assert_eq!(psbt.unsigned_tx.inputs[0].previous_output, outpoint);
+    assert_eq!(psbt.inputs[0].non_witness_utxo, Some(prev));
+
+    let key_pair = coin.as_ref().priv_key_policy.activated_key_or_err().unwrap();
+    psbt.sign_input(0, key_pair).unwrap();
+    let finalize_req = FinalizePsbtRequest {
+        coin: TEST_COIN_NAME.into(),
+        psbt: STANDARD.encode(psbt.serialize().unwrap()),
+        broadcast: true,
+    };
+    let finalized = block_on(finalize_psbt(&ctx, &coin, finalize_req)).unwrap();
+    assert!(finalized.broadcasted);
+
+    // The inputs are unlocked once the transaction is broadcasted.
+    assert!(!coin.as_ref().locked_unspents.lock().unwrap().contains(&outpoint));
+    assert!(block_on(utxo_lock_storage::load_locked_unspents(&ctx, TEST_COIN_NAME))
+        .unwrap()
+        .is_empty());
+}
+
+/// Mocks the RPC so the returned unconfirmed transaction spends 1 coin received by my address
+/// sending 0.5 to another address and 0.4999 back to me, so it pays 0.0001 as the fee.
+#[cfg(not(target_arch = "wasm32"))]
+fn mock_unconfirmed_tx_for_bump_fee(coin: &UtxoStandardCoin, sequence: u32) -> UtxoTx {
+    use chain::constants::SEQUENCE_FINAL;
+    use rpc::v1::types::{ScriptType, SignedTransactionOutput, TransactionOutputScript};
+    use script::UnsignedTransactionInput;
+
+    let my_address = block_on(coin.as_ref().derivation_method.unwrap_single_addr());
+    let my_script = coin.script_for_address(&my_address).unwrap();
+
+    let mut prev = coin.as_ref().transaction_preimage();
+    prev.inputs.push(UnsignedTransactionInput {
+        previous_output: OutPoint {
+            hash: 1.into(),
+            index: 0,
+        },
+        prev_script: Builder::default().into_script(),
+        sequence: SEQUENCE_FINAL,
+        amount: 0,
+    });
+    prev.outputs.push(TransactionOutput {
+        value: 100_000_000,
+        script_pubkey: my_script.to_bytes(),
+    });
+    let prev = UtxoTx::from(prev);
+    let prev_verbose = RpcTransaction {
+        hex: serialize(&prev).take().into(),
+        txid: prev.hash().reversed().into(),
+        hash: None,
+        size: None,
+        vsize: None,
+        version: prev.version,
+        locktime: 0,
+        vin: vec![],
+        vout: vec![SignedTransactionOutput {
+            value: Some(1.),
+            n: 0,
+            script: TransactionOutputScript {
+                asm: "".into(),
+                hex: my_script.to_bytes().take().into(),
+                req_sigs: 1,
+                script_type: ScriptType::PubKeyHash,
+                addresses: vec![],
+            },
+        }],
+        blockhash: Default::default(),
+        confirmations: 1,
+        rawconfirmations: None,
+        time: 0,
+        blocktime: 0,
+        height: None,
+    };
+
+    let mut tx = coin.as_ref().transaction_preimage();
+    tx.inputs.push(UnsignedTransactionInput {
+        previous_output: OutPoint {
+            hash: prev.hash(),
+            index: 0,
+        },
+        prev_script: my_script.clone(),
+        sequence,
+        amount: 100_000_000,
+    });
+    tx.outputs.push(TransactionOutput {
+        value: 50_000_000,
+        script_pubkey: "76a91483762a373935ca241d557dfce89171d582b486de88ac".into(),
+    });
+    tx.outputs.push(TransactionOutput {
+        value: 49_990_000,
+        script_pubkey: my_script.to_bytes(),
+    });
+    let tx = UtxoTx::from(tx);
+    let mut tx_verbose = prev_verbose.clone();
+    tx_verbose.hex = serialize(&tx).take().into();
+    tx_verbose.txid = tx.hash().reversed().into();
+    tx_verbose.confirmations = 0;
+
+    NativeClient::get_verbose_transaction.mock_safe(move |_, txid| {
+        assert_eq!(*txid, tx_verbose.txid);
+        MockResult::Return(Box::new(futures01::future::ok(tx_verbose.clone())))
+    });
+    NativeClient::get_verbose_transactions.mock_safe(move |_, txids| {
+        assert_eq!(txids, [prev_verbose.txid]);
+        MockResult::Return(Box::new(futures01::future::ok(vec![prev_verbose.clone()])))
+    });
+    tx
+}
+
+/// Returns the virtual size of the signed `tx` as it's estimated before signing.
+#[cfg(not(target_arch = "wasm32"))]
+fn estimated_v_size(tx: &UtxoTx) -> u64 {
+    let mut unsigned = tx.clone();
+    for input in unsigned.inputs.iter_mut() {
+        input.script_sig = Default::default();
+    }
+    utxo_common::tx_size_in_v_bytes(&UtxoAddressFormat::Standard, &unsigned) as u64
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_bump_fee_rbf() {
+    use crate::rpc_command::bump_fee::{BumpFeeMethod, BumpFeeRequest};
+
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    let tx = mock_unconfirmed_tx_for_bump_fee(&coin, SEQUENCE_RBF_ENABLED);
+
+    let req = BumpFeeRequest {
+        coin: TEST_COIN_NAME.into(),
+        tx_hash: tx.hash().reversed().into(),
+        method: None,
+        fee: Some(WithdrawFee::UtxoPerKbyte {
+            amount: "0.001".parse().unwrap(),
+        }),
+    };
+    let res = block_on(utxo_bump_fee::bump_fee(&coin, req)).unwrap();
+    assert_eq!(res.method, BumpFeeMethod::Rbf);
+
+    let replacement: UtxoTx = deserialize(res.tx_details.tx.tx_hex().unwrap().as_slice()).unwrap();
+    let new_fee = 100 * estimated_v_size(&replacement);
+    assert_eq!(replacement.inputs.len(), 1);
+    assert_eq!(replacement.inputs[0].previous_output, tx.inputs[0].previous_output);
+    assert_eq!(replacement.inputs[0].sequence, SEQUENCE_RBF_ENABLED);
+    // The payment is kept, the fee difference is deducted from my change.
+    assert_eq!(replacement.outputs[0], tx.outputs[0]);
+    assert_eq!(replacement.outputs[1].script_pubkey, tx.outputs[1].script_pubkey);
+    assert_eq!(replacement.outputs[1].value, 49_990_000 - (new_fee - 10_000));
+
+    let expected_fee = TxFeeDetails::Utxo(UtxoFeeDetails {
+        coin: Some(TEST_COIN_NAME.into()),
+        amount: big_decimal_from_sat(new_fee as i64, TEST_COIN_DECIMALS),
+    });
+    assert_eq!(res.tx_details.fee_details, Some(expected_fee));
+    assert_eq!(res.tx_details.spent_by_me, 1.into());
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_bump_fee_rbf_errors() {
+    use crate::rpc_command::bump_fee::{BumpFeeError, BumpFeeMethod, BumpFeeRequest};
+    use chain::constants::SEQUENCE_FINAL;
+
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    let tx = mock_unconfirmed_tx_for_bump_fee(&coin, SEQUENCE_RBF_ENABLED);
+
+    // 4 sat/vbyte doesn't cover the old fee + the incremental relay fee.
+    let req = BumpFeeRequest {
+        coin: TEST_COIN_NAME.into(),
+        tx_hash: tx.hash().reversed().into(),
+        method: Some(BumpFeeMethod::Rbf),
+        fee: Some(WithdrawFee::UtxoPerKbyte {
+            amount: "0.00004".parse().unwrap(),
+        }),
+    };
+    let err = block_on(utxo_bump_fee::bump_fee(&coin, req)).unwrap_err();
+    assert!(matches!(err.into_inner(), BumpFeeError::FeeTooLow { .. }));
+
+    // The whole change can't be spent on the fee.
+    let req = BumpFeeRequest {
+        coin: TEST_COIN_NAME.into(),
+        tx_hash: tx.hash().reversed().into(),
+        method: Some(BumpFeeMethod::Rbf),
+        fee: Some(WithdrawFee::UtxoFixed {
+            amount: "0.5".parse().unwrap(),
+        }),
+    };
+    let err = block_on(utxo_bump_fee::bump_fee(&coin, req)).unwrap_err();
+    assert!(matches!(err.into_inner(), BumpFeeError::NotEnoughFunds { .. }));
+
+    // The transaction that doesn't signal RBF can't be replaced.
+    let tx = mock_unconfirmed_tx_for_bump_fee(&coin, SEQUENCE_FINAL);
+    let req = BumpFeeRequest {
+        coin: TEST_COIN_NAME.into(),
+        tx_hash: tx.hash().reversed().into(),
+        method: Some(BumpFeeMethod::Rbf),
+        fee: None,
+    };
+    let err = block_on(utxo_bump_fee::bump_fee(&coin, req)).unwrap_err();
+    assert!(matches!(err.into_inner(), BumpFeeError::TxNotReplaceable(_)));
+}
+
+#[test]
+#[cfg(not(target_arch = "wasm32"))]
+fn test_bump_fee_cpfp() {
+    use crate::rpc_command::bump_fee::{BumpFeeMethod, BumpFeeRequest};
+    use chain::constants::SEQUENCE_FINAL;
+
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    let tx = mock_unconfirmed_tx_for_bump_fee(&coin, SEQUENCE_FINAL);
+    let change_outpoint = OutPoint {
+        hash: tx.hash(),
+        index: 1,
+    };
+    UtxoStandardCoin::get_unspent_ordered_list.mock_safe(move |coin, _| {
+        let fut = async move {
+            let cache = coin.as_ref().recently_spent_outpoints.lock().await;
+            let unspents = vec![UnspentInfo {
+                outpoint: change_outpoint,
+                value: 49_990_000,
+                height: None,
+                script: coin
+                    .script_for_address(&coin.as_ref().derivation_method.unwrap_single_addr().await)
+                    .unwrap(),
+            }];
+            Ok((unspents, cache))
+        };
+        MockResult::Return(fut.boxed())
+    });
+
+    let req = BumpFeeRequest {
+        coin: TEST_COIN_NAME.into(),
+        tx_hash: tx.hash().reversed().into(),
+        method: None,
+        fee: Some(WithdrawFee::UtxoPerKbyte {
+            amount: "0.001".parse().unwrap(),
+        }),
+    };
+    let res = block_on(utxo_bump_fee::bump_fee(&coin, req)).unwrap();
+    assert_eq!(res.method, BumpFeeMethod::Cpfp);
+
+    let child: UtxoTx = deserialize(res.tx_details.tx.tx_hex().unwrap().as_slice()).unwrap();
+    // The package pays 100 sat/vbyte, the parent has already paid 10000 sat.
+    let parent_v_size = serialize(&tx).len() as u64;
+    let child_fee = 100 * (parent_v_size + estimated_v_size(&child)) - 10_000;
+    assert_eq!(child.inputs.len(), 1);
+    assert_eq!(child.inputs[0].previous_output, change_outpoint);
+    assert_eq!(child.outputs.len(), 1);
+    assert_eq!(child.outputs[0].script_pubkey, tx.outputs[1].script_pubkey);
+    assert_eq!(child.outputs[0].value, 49_990_000 - child_fee);
+
+    let expected_fee = TxFeeDetails::Utxo(UtxoFeeDetails {
+        coin: Some(TEST_COIN_NAME.into()),
+        amount: big_decimal_from_sat(child_fee as i64, TEST_COIN_DECIMALS),
+    });
+    assert_eq!(res.tx_details.fee_details, Some(expected_fee));
+}
+
+/// Generates and validates the swap v2 spend preimages of `coin`.
+/// The taker funding spend is signed by the maker and the taker payment spend by the taker,
+/// so `coin` plays the signing side of each one against a counterparty key.
+fn check_swap_v2_spend_preimages<Coin>(coin: Coin)
+where
+    Coin: TakerCoinSwapOpsV2 + SwapOps + ParseCoinAssocTypes<Tx = UtxoTx, Pubkey = Public>,
+{
+    use crate::utxo::swap_proto_v2_scripts;
+    use crate::{GenTakerFundingSpendArgs, GenTakerPaymentSpendArgs, TakerCoinSwapOpsV2,
+                ValidateTakerFundingSpendPreimageError, ValidateTakerPaymentSpendPreimageError};
+
+    let my_pub = *coin.derive_htlc_key_pair(&[]).public();
+    let other_pub = *key_pair_from_seed("swap v2 counterparty").unwrap().public();
+    let p2sh_tx = |redeem_script: &Script, value: u64| UtxoTx {
+        outputs: vec![TransactionOutput {
+            value,
+            script_pubkey: Builder::build_p2sh(&dhash160(redeem_script).into()).to_bytes(),
+        }],
+        ..UtxoTx::default()
+    };
+
+    let funding_script = swap_proto_v2_scripts::taker_funding_script(1000, &[1; 20], &other_pub, &my_pub);
+    let funding_tx = p2sh_tx(&funding_script, 100_000);
+    let funding_args = GenTakerFundingSpendArgs {
+        funding_tx: &funding_tx,
+        maker_pub: &my_pub,
+        taker_pub: &other_pub,
+        funding_time_lock: 1000,
+        taker_secret_hash: &[1; 20],
+        taker_payment_time_lock: 2000,
+        maker_secret_hash: &[2; 20],
+    };
+    let preimage = block_on(coin.gen_taker_funding_spend_preimage(&funding_args, &[])).unwrap();
+    block_on(coin.validate_taker_funding_spend_preimage(&funding_args, &preimage)).unwrap();
+
+    let swapped_args = GenTakerFundingSpendArgs {
+        maker_pub: &other_pub,
+        taker_pub: &my_pub,
+        ..funding_args
+    };
+    let err = block_on(coin.validate_taker_funding_spend_preimage(&swapped_args, &preimage))
+        .unwrap_err()
+        .into_inner();
+    assert!(matches!(
+        err,
+        ValidateTakerFundingSpendPreimageError::InvalidMakerSignature
+    ));
+
+    let payment_script = swap_proto_v2_scripts::taker_payment_script(2000, &[2; 20], &my_pub, &other_pub);
+    let payment_tx = p2sh_tx(&payment_script, 1_000_000);
+    let maker_address = block_on(coin.my_addr());
+    let dex_fee = DexFee::Standard("0.001".into());
+    let payment_args = GenTakerPaymentSpendArgs {
+        taker_tx: &payment_tx,
+        time_lock: 2000,
+        maker_secret_hash: &[2; 20],
+        maker_pub: &other_pub,
+        maker_address: &maker_address,
+        taker_pub: &my_pub,
+        dex_fee: &dex_fee,
+        premium_amount: 0.into(),
+        trading_amount: 0.into(),
+    };
+    let preimage = block_on(coin.gen_taker_payment_spend_preimage(&payment_args, &[])).unwrap();
+    block_on(coin.validate_taker_payment_spend_preimage(&payment_args, &preimage)).unwrap();
+
+    let swapped_args = GenTakerPaymentSpendArgs {
+        maker_pub: &my_pub,
+        taker_pub: &other_pub,
+        ..payment_args
+    };
+    let err = block_on(coin.validate_taker_payment_spend_preimage(&swapped_args, &preimage))
+        .unwrap_err()
+        .into_inner();
+    assert!(matches!(
+        err,
+        ValidateTakerPaymentSpendPreimageError::InvalidTakerSignature
+    ));
+}
+
+#[test]
+fn test_bch_swap_v2_spend_preimages() {
+    use crate::utxo::bch::{BchCoin, CashAddrPrefix};
+
+    let mut fields = utxo_coin_fields_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    fields.conf.overwintered = false;
+    fields.conf.zcash = false;
+    fields.conf.tx_version = 1;
+    fields.conf.fork_id = 0x40;
+    fields.conf.signature_version = SignatureVersion::ForkId;
+    let coin = BchCoin::new(fields.into(), CashAddrPrefix::SlpTest, Vec::new());
+
+    check_swap_v2_spend_preimages(coin);
+}
+
+#[test]
+fn test_qtum_swap_v2_spend_preimages() {
+    let mut fields = utxo_coin_fields_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    fields.conf.overwintered = false;
+    fields.conf.zcash = false;
+    fields.conf.tx_version = 1;
+    let coin = QtumCoin::from(UtxoArc::from(fields));
+
+    check_swap_v2_spend_preimages(coin);
+}
+
+/// Checks that the only input of the watcher v2 `tx` is signed by `public` with
+/// `SIGHASH_SINGLE | SIGHASH_ANYONECANPAY`, i.e. the signature stays valid whatever
+/// outputs and inputs are appended by the watcher.
+fn check_watcher_v2_signature(
+    coin: &UtxoStandardCoin,
+    tx: &UtxoTx,
+    redeem_script: &Script,
+    public: &Public,
+    input_amount: u64,
+) {
+    use utxo_signer::with_key_pair::{signature_hash_to_sign, SIGHASH_ANYONECANPAY, SIGHASH_SINGLE};
+
+    let script_sig = Script::from(tx.inputs[0].script_sig.clone());
+    let signature = script_sig.iter().next().unwrap().unwrap().data.unwrap().to_vec();
+    let (sighash_type, signature) = signature.split_last().unwrap();
+    assert_eq!(*sighash_type as u32, SIGHASH_SINGLE | SIGHASH_ANYONECANPAY);
+
+    let mut signer = TransactionInputSigner::from(tx.clone());
+    signer.inputs[0].amount = input_amount;
+    signer.consensus_branch_id = coin.as_ref().conf.consensus_branch_id;
+    let sighash = signature_hash_to_sign(
+        &signer,
+        0,
+        redeem_script,
+        coin.as_ref().conf.signature_version,
+        SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+        coin.as_ref().conf.fork_id,
+    )
+    .unwrap();
+    assert!(public.verify(&sighash, &signature.to_vec().into()).unwrap());
+}
+
+#[test]
+fn test_swap_v2_watcher_refund_preimage() {
+    use crate::utxo::swap_proto_v2_scripts;
+    use crate::{RefundPaymentArgs, SwapTxTypeWithSecretHash};
+    use chain::constants::SEQUENCE_FINAL;
+    use chain::TransactionInput;
+
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    let taker_pub = *coin.derive_htlc_key_pair(&[]).public();
+    let maker_pub = *key_pair_from_seed("swap v2 counterparty").unwrap().public();
+    let funding_script = swap_proto_v2_scripts::taker_funding_script(1000, &[1; 20], &taker_pub, &maker_pub);
+    let funding_tx = UtxoTx {
+        outputs: vec![TransactionOutput {
+            value: 100_000,
+            script_pubkey: Builder::build_p2sh(&dhash160(&funding_script).into()).to_bytes(),
+        }],
+        ..UtxoTx::default()
+    };
+    let funding_bytes = serialize(&funding_tx).take();
+
+    UtxoStandardCoin::get_current_mtp.mock_safe(|_| MockResult::Return(Box::pin(futures::future::ok(2000))));
+    let rpc_bytes = funding_bytes.clone();
+    NativeClient::get_transaction_bytes
+        .mock_safe(move |_, _| MockResult::Return(Box::new(futures01::future::ok(rpc_bytes.clone().into()))));
+    UtxoStandardCoin::broadcast_tx
+        .mock_safe(|_, tx| MockResult::Return(Box::pin(futures::future::ok(tx.hash().reversed().into()))));
+
+    let refund = block_on(utxo_common::sign_refund_for_watcher_v2(&coin, RefundPaymentArgs {
+        payment_tx: &funding_bytes,
+        time_lock: 1000,
+        other_pubkey: &maker_pub,
+        tx_type_with_secret_hash: SwapTxTypeWithSecretHash::TakerFunding {
+            taker_secret_hash: &[1; 20],
+        },
+        swap_contract_address: &None,
+        swap_unique_data: &[],
+        watcher_reward: false,
+    }))
+    .unwrap();
+    // The owner leaves the miner fee and the same amount as the watcher reward.
+    assert_eq!(refund.outputs.len(), 1);
+    assert_eq!(refund.outputs[0].value, 98_000);
+    assert_eq!(refund.lock_time, 1999);
+    check_watcher_v2_signature(&coin, &refund, &funding_script, &taker_pub, 100_000);
+
+    let refund_bytes = serialize(&refund).take();
+    block_on(utxo_common::watcher_validate_preimage_v2(
+        &coin,
+        &funding_bytes,
+        &refund_bytes,
+        true,
+    ))
+    .unwrap();
+    let err = block_on(utxo_common::watcher_validate_preimage_v2(
+        &coin,
+        &funding_bytes,
+        &refund_bytes,
+        false,
+    ))
+    .unwrap_err()
+    .into_inner();
+    assert!(matches!(err, ValidatePaymentError::WrongPaymentTx(_)));
+
+    let mut tampered = refund.clone();
+    tampered.outputs[0].value = 99_000;
+    let err = block_on(utxo_common::watcher_validate_preimage_v2(
+        &coin,
+        &funding_bytes,
+        &serialize(&tampered).take(),
+        true,
+    ))
+    .unwrap_err()
+    .into_inner();
+    match err {
+        ValidatePaymentError::WrongPaymentTx(e) => assert!(e.contains("Invalid preimage signature")),
+        e => panic!("Unexpected error {:?}", e),
+    }
+
+    let sent = block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &funding_bytes,
+        &refund_bytes,
+        None,
+    ))
+    .unwrap();
+    let my_script = output_script(&block_on(coin.as_ref().derivation_method.single_addr_or_err()).unwrap()).unwrap();
+    assert_eq!(sent.outputs.len(), 2);
+    assert_eq!(sent.outputs[0], refund.outputs[0]);
+    assert_eq!(sent.outputs[1].value, 1000);
+    assert_eq!(sent.outputs[1].script_pubkey, my_script.to_bytes());
+    assert_eq!(sent.inputs[0].script_sig, refund.inputs[0].script_sig);
+    check_watcher_v2_signature(&coin, &sent, &funding_script, &taker_pub, 100_000);
+
+    // The signature also stays valid if the watcher adds an input to pay a higher fee.
+    let mut with_input = sent.clone();
+    with_input.inputs.push(TransactionInput {
+        previous_output: OutPoint {
+            hash: 1.into(),
+            index: 0,
+        },
+        script_sig: Bytes::default(),
+        sequence: SEQUENCE_FINAL,
+        script_witness: vec![],
+    });
+    check_watcher_v2_signature(&coin, &with_input, &funding_script, &taker_pub, 100_000);
+
+    block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &funding_bytes,
+        &refund_bytes,
+        Some(&[2; 32]),
+    ))
+    .unwrap_err();
+}
+
+#[test]
+fn test_swap_v2_watcher_maker_payment_spend_preimage() {
+    use crate::utxo::swap_proto_v2_scripts;
+    use crate::MakerPaymentSpendForWatcherArgs;
+    use script::Opcode;
+
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    let taker_pub = *coin.derive_htlc_key_pair(&[]).public();
+    let maker_pub = *key_pair_from_seed("swap v2 counterparty").unwrap().public();
+    let payment_script = swap_proto_v2_scripts::maker_payment_script(1000, &[2; 20], &[1; 20], &maker_pub, &taker_pub);
+    let payment_tx = UtxoTx {
+        outputs: vec![TransactionOutput {
+            value: 100_000,
+            script_pubkey: Builder::build_p2sh(&dhash160(&payment_script).into()).to_bytes(),
+        }],
+        ..UtxoTx::default()
+    };
+    let payment_bytes = serialize(&payment_tx).take();
+
+    UtxoStandardCoin::get_current_mtp.mock_safe(|_| MockResult::Return(Box::pin(futures::future::ok(2000))));
+    let rpc_bytes = payment_bytes.clone();
+    NativeClient::get_transaction_bytes
+        .mock_safe(move |_, _| MockResult::Return(Box::new(futures01::future::ok(rpc_bytes.clone().into()))));
+    UtxoStandardCoin::broadcast_tx
+        .mock_safe(|_, tx| MockResult::Return(Box::pin(futures::future::ok(tx.hash().reversed().into()))));
+
+    let spend = block_on(utxo_common::sign_maker_payment_spend_for_watcher_v2(
+        &coin,
+        MakerPaymentSpendForWatcherArgs {
+            maker_payment_tx: &payment_bytes,
+            time_lock: 1000,
+            taker_secret_hash: &[1; 20],
+            maker_secret_hash: &[2; 20],
+            maker_pub: &maker_pub,
+            swap_unique_data: &[],
+        },
+    ))
+    .unwrap();
+    assert_eq!(spend.outputs.len(), 1);
+    assert_eq!(spend.outputs[0].value, 98_000);
+    check_watcher_v2_signature(&coin, &spend, &payment_script, &taker_pub, 100_000);
+
+    let spend_bytes = serialize(&spend).take();
+    block_on(utxo_common::watcher_validate_preimage_v2(
+        &coin,
+        &payment_bytes,
+        &spend_bytes,
+        false,
+    ))
+    .unwrap();
+    block_on(utxo_common::watcher_validate_preimage_v2(
+        &coin,
+        &payment_bytes,
+        &spend_bytes,
+        true,
+    ))
+    .unwrap_err();
+
+    // A preimage of another payment must not be accepted.
+    let other_payment = UtxoTx {
+        outputs: vec![TransactionOutput {
+            value: 100_001,
+            ..payment_tx.outputs[0].clone()
+        }],
+        ..UtxoTx::default()
+    };
+    let other_bytes = serialize(&other_payment).take();
+    NativeClient::get_transaction_bytes
+        .mock_safe(move |_, _| MockResult::Return(Box::new(futures01::future::ok(other_bytes.clone().into()))));
+    block_on(utxo_common::watcher_validate_preimage_v2(
+        &coin,
+        &serialize(&other_payment).take(),
+        &spend_bytes,
+        false,
+    ))
+    .unwrap_err();
+
+    // The spend can't be broadcast without the maker secret.
+    block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &payment_bytes,
+        &spend_bytes,
+        None,
+    ))
+    .unwrap_err();
+
+    let secret = [3; 32];
+    let sent = block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &payment_bytes,
+        &spend_bytes,
+        Some(&secret),
+    ))
+    .unwrap();
+    assert_eq!(sent.outputs.len(), 2);
+    assert_eq!(sent.outputs[1].value, 1000);
+    let script_sig = Script::from(sent.inputs[0].script_sig.clone());
+    let instructions: Vec<_> = script_sig.iter().map(|i| i.unwrap()).collect();
+    assert_eq!(instructions.len(), 5);
+    assert_eq!(instructions[1].data, Some(&secret[..]));
+    assert_eq!(instructions[2].opcode, Opcode::OP_1);
+    assert_eq!(instructions[3].opcode, Opcode::OP_0);
+    assert_eq!(instructions[4].data, Some(&payment_script[..]));
+    check_watcher_v2_signature(&coin, &sent, &payment_script, &taker_pub, 100_000);
+
+    // The reward output is skipped when the current fee leaves only dust for it.
+    UtxoStandardCoin::get_htlc_spend_fee.mock_safe(|_, _, _| MockResult::Return(Box::pin(futures::future::ok(1500))));
+    let sent = block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &payment_bytes,
+        &spend_bytes,
+        Some(&secret),
+    ))
+    .unwrap();
+    assert_eq!(sent.outputs, spend.outputs);
+}
+
+#[test]
+fn test_swap_v2_watcher_preimages_reward_and_dust() {
+    use crate::utxo::swap_proto_v2_scripts;
+    use crate::{MakerPaymentSpendForWatcherArgs, RefundPaymentArgs, SwapTxTypeWithSecretHash};
+
+    let coin = utxo_coin_for_test(UtxoRpcClientEnum::Native(native_client_for_test()), None, false);
+    let taker_pub = *coin.derive_htlc_key_pair(&[]).public();
+    let maker_pub = *key_pair_from_seed("swap v2 counterparty").unwrap().public();
+    let funding_script = swap_proto_v2_scripts::taker_funding_script(1000, &[1; 20], &taker_pub, &maker_pub);
+    let payment_script = swap_proto_v2_scripts::maker_payment_script(1000, &[2; 20], &[1; 20], &maker_pub, &taker_pub);
+    let htlc_bytes = |script: &[u8], value: u64| {
+        let tx = UtxoTx {
+            outputs: vec![TransactionOutput {
+                value,
+                script_pubkey: Builder::build_p2sh(&dhash160(script).into()).to_bytes(),
+            }],
+            ..UtxoTx::default()
+        };
+        serialize(&tx).take()
+    };
+
+    UtxoStandardCoin::get_current_mtp.mock_safe(|_| MockResult::Return(Box::pin(futures::future::ok(2000))));
+    UtxoStandardCoin::get_htlc_spend_fee.mock_safe(|_, _, _| MockResult::Return(Box::pin(futures::future::ok(1000))));
+    UtxoStandardCoin::broadcast_tx
+        .mock_safe(|_, tx| MockResult::Return(Box::pin(futures::future::ok(tx.hash().reversed().into()))));
+
+    let sign_refund = |payment_tx: &[u8]| {
+        block_on(utxo_common::sign_refund_for_watcher_v2(&coin, RefundPaymentArgs {
+            payment_tx,
+            time_lock: 1000,
+            other_pubkey: &maker_pub,
+            tx_type_with_secret_hash: SwapTxTypeWithSecretHash::TakerFunding {
+                taker_secret_hash: &[1; 20],
+            },
+            swap_contract_address: &None,
+            swap_unique_data: &[],
+            watcher_reward: false,
+        }))
+    };
+    let sign_spend = |maker_payment_tx: &[u8]| {
+        block_on(utxo_common::sign_maker_payment_spend_for_watcher_v2(
+            &coin,
+            MakerPaymentSpendForWatcherArgs {
+                maker_payment_tx,
+                time_lock: 1000,
+                taker_secret_hash: &[1; 20],
+                maker_secret_hash: &[2; 20],
+                maker_pub: &maker_pub,
+                swap_unique_data: &[],
+            },
+        ))
+    };
+
+    // The miner fee, the watcher reward and the dust owner output don't fit into the HTLC value.
+    let err = sign_refund(&htlc_bytes(&funding_script, 2999)).unwrap_err();
+    assert!(err.get_plain_text_format().contains("too large"));
+    let err = sign_spend(&htlc_bytes(&payment_script, 2999)).unwrap_err();
+    assert!(err.get_plain_text_format().contains("too large"));
+
+    // The owner output may be as small as the dust amount.
+    let funding_bytes = htlc_bytes(&funding_script, 3000);
+    let refund = sign_refund(&funding_bytes).unwrap();
+    assert_eq!(refund.outputs.len(), 1);
+    assert_eq!(refund.outputs[0].value, 1000);
+    let payment_bytes = htlc_bytes(&payment_script, 3000);
+    let spend = sign_spend(&payment_bytes).unwrap();
+    assert_eq!(spend.outputs.len(), 1);
+    assert_eq!(spend.outputs[0].value, 1000);
+
+    // The watcher gets the whole amount left above the current miner fee once the fee drops.
+    UtxoStandardCoin::get_htlc_spend_fee.mock_safe(|_, _, _| MockResult::Return(Box::pin(futures::future::ok(500))));
+    let my_script = output_script(&block_on(coin.as_ref().derivation_method.single_addr_or_err()).unwrap()).unwrap();
+    let sent = block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &funding_bytes,
+        &serialize(&refund).take(),
+        None,
+    ))
+    .unwrap();
+    assert_eq!(sent.outputs.len(), 2);
+    assert_eq!(sent.outputs[0], refund.outputs[0]);
+    assert_eq!(sent.outputs[1].value, 1500);
+    assert_eq!(sent.outputs[1].script_pubkey, my_script.to_bytes());
+    check_watcher_v2_signature(&coin, &sent, &funding_script, &taker_pub, 3000);
+
+    // The reward output is skipped when the current fee exceeds the amount left for it.
+    UtxoStandardCoin::get_htlc_spend_fee.mock_safe(|_, _, _| MockResult::Return(Box::pin(futures::future::ok(2500))));
+    let sent = block_on(utxo_common::send_preimage_as_watcher_v2(
+        &coin,
+        &payment_bytes,
+        &serialize(&spend).take(),
+        Some(&[3; 32]),
+    ))
+    .unwrap();
+    assert_eq!(sent.outputs, spend.outputs);
+    check_watcher_v2_signature(&coin, &sent, &payment_script, &taker_pub, 3000);
+}
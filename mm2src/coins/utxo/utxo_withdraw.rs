@@ -1,12 +1,19 @@
 use crate::rpc_command::init_withdraw::{WithdrawInProgressStatus, WithdrawTaskHandleShared};
+use crate::utxo::rpc_clients::UnspentInfo;
+use crate::utxo::utxo_coin_control::{lock_unspents_until, unlock_expiring_unspents, UnspentOutPoint};
 use crate::utxo::utxo_common::{big_decimal_from_sat, UtxoTxBuilder};
-use crate::utxo::{output_script, sat_from_big_decimal, ActualTxFee, Address, FeePolicy, GetUtxoListOps, PrivKeyPolicy,
-                  UtxoAddressFormat, UtxoCoinFields, UtxoCommonOps, UtxoFeeDetails, UtxoTx, UTXO_LOCK};
+use crate::utxo::{output_script, sat_from_big_decimal, ActualTxFee, AdditionalTxData, Address, FeePolicy,
+                  GetUtxoListOps, PrivKeyPolicy, UtxoAddressFormat, UtxoCoinFields, UtxoCommonOps, UtxoFeeDetails,
+                  UtxoTx, UTXO_LOCK};
 use crate::{CoinWithDerivationMethod, GetWithdrawSenderAddress, MarketCoinOps, TransactionData, TransactionDetails,
             UnexpectedDerivationMethod, WithdrawError, WithdrawFee, WithdrawRequest, WithdrawResult};
 use async_trait::async_trait;
-use chain::TransactionOutput;
-use common::log::info;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bitcrypto::dhash160;
+use chain::constants::SEQUENCE_RBF_ENABLED;
+use chain::{OutPoint, TransactionOutput};
+use common::log::{info, LogOnError};
 use common::now_sec;
 use crypto::hw_rpc_task::HwRpcTaskAwaitingStatus;
 use crypto::trezor::trezor_rpc_task::{TrezorRequestStatuses, TrezorRpcTaskProcessor};
@@ -15,13 +22,16 @@ use crypto::{from_hw_error, CryptoCtx, CryptoCtxError, DerivationPath, HwError,
 use keys::{AddressFormat, KeyPair, Private, Public as PublicKey};
 use mm2_core::mm_ctx::MmArc;
 use mm2_err_handle::prelude::*;
+use mm2_number::BigDecimal;
 use rpc::v1::types::ToTxHash;
 use rpc_task::RpcTaskError;
 use script::{SignatureVersion, TransactionInputSigner};
 use serialization::{serialize, serialize_with_flags, SERIALIZE_TRANSACTION_WITNESS};
+use std::collections::HashSet;
 use std::iter::once;
 use std::sync::Arc;
-use utxo_signer::sign_params::{OutputDestination, SendingOutputInfo, SpendingInputInfo, UtxoSignTxParamsBuilder};
+use utxo_signer::sign_params::{OutputDestination, SendingOutputInfo, SpendingInputInfo, UtxoSignTxParams,
+                               UtxoSignTxParamsBuilder};
 use utxo_signer::{with_key_pair, UtxoSignTxError};
 use utxo_signer::{SignPolicy, UtxoSignerOps};
 
@@ -31,6 +41,7 @@ impl From<UtxoSignTxError> for WithdrawError {
             UtxoSignTxError::TrezorError(trezor) => WithdrawError::from(trezor),
             UtxoSignTxError::Transport(transport) => WithdrawError::Transport(transport),
             UtxoSignTxError::Internal(internal) => WithdrawError::InternalError(internal),
+            UtxoSignTxError::PsbtError(psbt) => WithdrawError::UnsupportedError(psbt.to_string()),
             sign_err => WithdrawError::InternalError(sign_err.to_string()),
         }
     }
@@ -91,7 +102,7 @@ impl From<keys::Error> for WithdrawError {
     fn from(e: keys::Error) -> Self { WithdrawError::InternalError(e.to_string()) }
 }
 
-fn derive_hd_key_pair<Coin>(
+pub(crate) fn derive_hd_key_pair<Coin>(
     coin: &Coin,
     derivation_path: &DerivationPath,
 ) -> Result<KeyPair, MmError<UnexpectedDerivationMethod>>
@@ -113,6 +124,296 @@ where
     KeyPair::from_private(private).map_to_mm(|err| UnexpectedDerivationMethod::InternalError(err.to_string()))
 }
 
+/// Checks that either the single `to` recipient or multiple `outputs` are requested.
+#[allow(clippy::result_large_err)]
+fn validate_withdraw_outputs(req: &WithdrawRequest) -> Result<(), MmError<WithdrawError>> {
+    if req.outputs.is_empty() {
+        return Ok(());
+    }
+    if !req.to.is_empty() || req.max {
+        let error = "'to', 'amount' and 'max' must not be set along with 'outputs'".to_owned();
+        return MmError::err(WithdrawError::InvalidOutputs(error));
+    }
+    Ok(())
+}
+
+/// Returns the recipients of the withdrawal with the amounts in the order of the transaction outputs.
+pub(crate) fn withdraw_recipients(req: &WithdrawRequest) -> Vec<(&String, &BigDecimal)> {
+    if req.outputs.is_empty() {
+        vec![(&req.to, &req.amount)]
+    } else {
+        req.outputs.iter().map(|output| (&output.to, &output.amount)).collect()
+    }
+}
+
+/// Splits the spendable `unspents` into the inputs selected by [`WithdrawRequest::inputs`]
+/// and the rest of the available ones except [`WithdrawRequest::exclude_inputs`].
+/// If `max` is requested along with the selected inputs, only the selected ones are spent.
+#[allow(clippy::result_large_err)]
+fn select_withdraw_inputs(
+    req: &WithdrawRequest,
+    unspents: Vec<UnspentInfo>,
+) -> Result<(Vec<UnspentInfo>, Vec<UnspentInfo>), MmError<WithdrawError>> {
+    let included: HashSet<OutPoint> = req.inputs.iter().copied().map(OutPoint::from).collect();
+    let excluded: HashSet<OutPoint> = req.exclude_inputs.iter().copied().map(OutPoint::from).collect();
+    if let Some(outpoint) = included.intersection(&excluded).next() {
+        let error = format!(
+            "{}:{} is both included and excluded",
+            UnspentOutPoint::from(*outpoint).tx_hash,
+            outpoint.index
+        );
+        return MmError::err(WithdrawError::InvalidInputs(error));
+    }
+
+    let (selected, mut available): (Vec<_>, Vec<_>) = unspents
+        .into_iter()
+        .filter(|unspent| !excluded.contains(&unspent.outpoint))
+        .partition(|unspent| included.contains(&unspent.outpoint));
+    if selected.len() != included.len() {
+        let not_spendable: Vec<_> = req
+            .inputs
+            .iter()
+            .filter(|outpoint| {
+                let outpoint = OutPoint::from(**outpoint);
+                !selected.iter().any(|unspent| unspent.outpoint == outpoint)
+            })
+            .map(|outpoint| format!("{}:{}", outpoint.tx_hash, outpoint.index))
+            .collect();
+        let error = format!(
+            "The following outputs are either spent, locked or immature: {}",
+            not_spendable.join(", ")
+        );
+        return MmError::err(WithdrawError::InvalidInputs(error));
+    }
+
+    if req.max && !selected.is_empty() {
+        available.clear();
+    }
+    Ok((selected, available))
+}
+
+/// Returns the signature version of the inputs spent from the `sender_address`.
+fn withdraw_signature_version<Coin>(coin: &Coin, sender_address: &Address) -> SignatureVersion
+where
+    Coin: AsRef<UtxoCoinFields>,
+{
+    match sender_address.addr_format() {
+        UtxoAddressFormat::Segwit => SignatureVersion::WitnessV0,
+        UtxoAddressFormat::Taproot => SignatureVersion::Taproot,
+        UtxoAddressFormat::Standard | UtxoAddressFormat::CashAddress { .. } => coin.as_ref().conf.signature_version,
+    }
+}
+
+/// Generates the unsigned transaction spending the outputs of the `sender_address` as it's requested by `req`.
+async fn generate_withdraw_tx<Coin>(
+    coin: &Coin,
+    sender_address: &Address,
+    req: &WithdrawRequest,
+) -> Result<(TransactionInputSigner, AdditionalTxData), MmError<WithdrawError>>
+where
+    Coin: UtxoCommonOps + GetUtxoListOps,
+{
+    let _utxo_lock = UTXO_LOCK.lock().await;
+    generate_withdraw_tx_locked(coin, sender_address, req).await
+}
+
+/// The same as [`generate_withdraw_tx`], but expects the caller to hold [`UTXO_LOCK`].
+async fn generate_withdraw_tx_locked<Coin>(
+    coin: &Coin,
+    sender_address: &Address,
+    req: &WithdrawRequest,
+) -> Result<(TransactionInputSigner, AdditionalTxData), MmError<WithdrawError>>
+where
+    Coin: UtxoCommonOps + GetUtxoListOps,
+{
+    let (unspents, _) = coin.get_unspent_ordered_list(sender_address).await?;
+    let tx_builder = UtxoTxBuilder::new(coin).await.with_from_address(sender_address.clone());
+    build_withdraw_tx(coin, tx_builder, unspents, req).await
+}
+
+/// Builds the transaction spending the `unspents` as it's requested by `req`.
+/// `tx_builder` is expected to have the change address set, the `unspents` must be locked by the caller.
+pub(crate) async fn build_withdraw_tx<Coin>(
+    coin: &Coin,
+    mut tx_builder: UtxoTxBuilder<'_, Coin>,
+    unspents: Vec<UnspentInfo>,
+    req: &WithdrawRequest,
+) -> Result<(TransactionInputSigner, AdditionalTxData), MmError<WithdrawError>>
+where
+    Coin: UtxoCommonOps,
+{
+    let ticker = coin.as_ref().conf.ticker.clone();
+    let decimals = coin.as_ref().decimals;
+
+    validate_withdraw_outputs(req)?;
+    let recipients = withdraw_recipients(req);
+    let scripts = recipients
+        .iter()
+        .map(|(to, _amount)| {
+            let to = coin.address_from_str(to)?;
+            Ok(output_script(&to).map(|script| script.to_bytes())?)
+        })
+        .collect::<Result<Vec<_>, MmError<WithdrawError>>>()?;
+
+    let (selected, unspents) = select_withdraw_inputs(req, unspents)?;
+    let (outputs, fee_policy) = if req.max {
+        let value = selected
+            .iter()
+            .chain(unspents.iter())
+            .fold(0, |sum, unspent| sum + unspent.value);
+        let outputs = scripts
+            .into_iter()
+            .map(|script_pubkey| TransactionOutput { value, script_pubkey })
+            .collect();
+        (outputs, FeePolicy::DeductFromOutput(0))
+    } else {
+        let mut outputs = Vec::with_capacity(scripts.len());
+        for ((_to, amount), script_pubkey) in recipients.iter().zip(scripts) {
+            let value = sat_from_big_decimal(amount, decimals)?;
+            outputs.push(TransactionOutput { value, script_pubkey });
+        }
+        (outputs, FeePolicy::SendExact)
+    };
+
+    tx_builder = tx_builder
+        .add_selected_inputs(selected)
+        .add_available_inputs(unspents)
+        .add_outputs(outputs)
+        .with_fee_policy(fee_policy);
+    if coin.as_ref().conf.signal_rbf {
+        tx_builder = tx_builder.with_input_sequence(SEQUENCE_RBF_ENABLED);
+    }
+
+    match req.fee {
+        Some(WithdrawFee::UtxoFixed { ref amount }) => {
+            let fixed = sat_from_big_decimal(amount, decimals)?;
+            tx_builder = tx_builder.with_fee(ActualTxFee::FixedPerKb(fixed));
+        },
+        Some(WithdrawFee::UtxoPerKbyte { ref amount }) => {
+            let dynamic = sat_from_big_decimal(amount, decimals)?;
+            tx_builder = tx_builder.with_fee(ActualTxFee::Dynamic(dynamic));
+        },
+        Some(ref fee_policy) => {
+            let error = format!(
+                "Expected 'UtxoFixed' or 'UtxoPerKbyte' fee types, found {:?}",
+                fee_policy
+            );
+            return MmError::err(WithdrawError::InvalidFeePolicy(error));
+        },
+        None => (),
+    };
+    tx_builder
+        .build()
+        .await
+        .mm_err(|gen_tx_error| WithdrawError::from_generate_tx_error(gen_tx_error, ticker, decimals))
+}
+
+/// Generates `TransactionDetails` of the withdrawal transaction, `tx` is either signed or the unsigned PSBT.
+pub(crate) fn withdraw_tx_details<Coin>(
+    coin: &Coin,
+    req: &WithdrawRequest,
+    from: String,
+    data: AdditionalTxData,
+    tx: TransactionData,
+) -> TransactionDetails
+where
+    Coin: AsRef<UtxoCoinFields>,
+{
+    let ticker = coin.as_ref().conf.ticker.clone();
+    let decimals = coin.as_ref().decimals;
+
+    let fee_amount = data.fee_amount + data.unused_change;
+    let fee_details = UtxoFeeDetails {
+        coin: Some(ticker.clone()),
+        amount: big_decimal_from_sat(fee_amount as i64, decimals),
+    };
+    let recipients = withdraw_recipients(req);
+    let mut to = Vec::with_capacity(recipients.len());
+    for (address, _amount) in recipients {
+        if !to.contains(address) {
+            to.push(address.clone());
+        }
+    }
+    TransactionDetails {
+        from: vec![from],
+        to,
+        total_amount: big_decimal_from_sat(data.spent_by_me as i64, decimals),
+        spent_by_me: big_decimal_from_sat(data.spent_by_me as i64, decimals),
+        received_by_me: big_decimal_from_sat(data.received_by_me as i64, decimals),
+        my_balance_change: big_decimal_from_sat(data.received_by_me as i64 - data.spent_by_me as i64, decimals),
+        tx,
+        fee_details: Some(fee_details.into()),
+        block_height: 0,
+        coin: ticker,
+        internal_id: vec![].into(),
+        timestamp: now_sec(),
+        kmd_rewards: data.kmd_rewards,
+        transaction_type: Default::default(),
+        memo: None,
+    }
+}
+
+/// Collects the information about the inputs and the outputs of the withdrawal `unsigned_tx`
+/// that is required to sign it with a hardware wallet or an external signer.
+#[allow(clippy::result_large_err)]
+fn withdraw_sign_params(
+    unsigned_tx: TransactionInputSigner,
+    req: &WithdrawRequest,
+    from_address: &Address,
+    from_derivation_path: &DerivationPath,
+    from_pubkey: PublicKey,
+    signature_version: SignatureVersion,
+) -> Result<UtxoSignTxParams, MmError<WithdrawError>> {
+    let mut sign_params = UtxoSignTxParamsBuilder::new();
+
+    // TODO refactor [`UtxoTxBuilder::build`] to return `SpendingInputInfo` and `SendingOutputInfo` within `AdditionalTxData`.
+    sign_params.add_inputs_infos(
+        unsigned_tx
+            .inputs
+            .iter()
+            .map(|_input| match from_address.addr_format() {
+                AddressFormat::Segwit => SpendingInputInfo::P2WPKH {
+                    address_derivation_path: from_derivation_path.clone(),
+                    address_pubkey: from_pubkey,
+                },
+                AddressFormat::Taproot => SpendingInputInfo::P2TR {
+                    address_derivation_path: from_derivation_path.clone(),
+                    address_pubkey: from_pubkey,
+                },
+                AddressFormat::Standard | AddressFormat::CashAddress { .. } => SpendingInputInfo::P2PKH {
+                    address_derivation_path: from_derivation_path.clone(),
+                    address_pubkey: from_pubkey,
+                },
+            }),
+    );
+    let recipients = withdraw_recipients(req);
+    sign_params.add_outputs_infos(recipients.iter().map(|(to, _amount)| SendingOutputInfo {
+        destination_address: OutputDestination::plain(to.to_string()),
+    }));
+    match unsigned_tx.outputs.len().checked_sub(recipients.len()) {
+        // There is no change output.
+        Some(0) => (),
+        // There is a change output.
+        Some(1) => {
+            sign_params.add_outputs_infos(once(SendingOutputInfo {
+                destination_address: OutputDestination::change(
+                    from_derivation_path.clone(),
+                    from_address.addr_format().clone(),
+                ),
+            }));
+        },
+        _ => {
+            let error = format!("Unexpected number of outputs: {}", unsigned_tx.outputs.len());
+            return MmError::err(WithdrawError::InternalError(error));
+        },
+    }
+
+    sign_params
+        .with_signature_version(signature_version)
+        .with_unsigned_tx(unsigned_tx);
+    Ok(sign_params.build()?)
+}
+
 #[async_trait]
 pub trait UtxoWithdraw<Coin>
 where
@@ -127,14 +428,7 @@ where
 
     fn request(&self) -> &WithdrawRequest;
 
-    fn signature_version(&self) -> SignatureVersion {
-        match self.sender_address().addr_format() {
-            UtxoAddressFormat::Segwit => SignatureVersion::WitnessV0,
-            UtxoAddressFormat::Standard | UtxoAddressFormat::CashAddress { .. } => {
-                self.coin().as_ref().conf.signature_version
-            },
-        }
-    }
+    fn signature_version(&self) -> SignatureVersion { withdraw_signature_version(self.coin(), &self.sender_address()) }
 
     #[allow(clippy::result_large_err)]
     fn on_generating_transaction(&self) -> Result<(), MmError<WithdrawError>>;
@@ -146,59 +440,11 @@ where
 
     async fn build(self) -> WithdrawResult {
         let coin = self.coin();
-        let ticker = coin.as_ref().conf.ticker.clone();
-        let decimals = coin.as_ref().decimals;
-        let req = self.request();
-
-        let to = coin.address_from_str(&req.to)?;
 
         // Generate unsigned transaction.
         self.on_generating_transaction()?;
 
-        let script_pubkey = output_script(&to).map(|script| script.to_bytes())?;
-
-        let _utxo_lock = UTXO_LOCK.lock().await;
-        let (unspents, _) = coin.get_unspent_ordered_list(&self.sender_address()).await?;
-        let (value, fee_policy) = if req.max {
-            (
-                unspents.iter().fold(0, |sum, unspent| sum + unspent.value),
-                FeePolicy::DeductFromOutput(0),
-            )
-        } else {
-            let value = sat_from_big_decimal(&req.amount, decimals)?;
-            (value, FeePolicy::SendExact)
-        };
-        let outputs = vec![TransactionOutput { value, script_pubkey }];
-
-        let mut tx_builder = UtxoTxBuilder::new(coin)
-            .await
-            .with_from_address(self.sender_address())
-            .add_available_inputs(unspents)
-            .add_outputs(outputs)
-            .with_fee_policy(fee_policy);
-
-        match req.fee {
-            Some(WithdrawFee::UtxoFixed { ref amount }) => {
-                let fixed = sat_from_big_decimal(amount, decimals)?;
-                tx_builder = tx_builder.with_fee(ActualTxFee::FixedPerKb(fixed));
-            },
-            Some(WithdrawFee::UtxoPerKbyte { ref amount }) => {
-                let dynamic = sat_from_big_decimal(amount, decimals)?;
-                tx_builder = tx_builder.with_fee(ActualTxFee::Dynamic(dynamic));
-            },
-            Some(ref fee_policy) => {
-                let error = format!(
-                    "Expected 'UtxoFixed' or 'UtxoPerKbyte' fee types, found {:?}",
-                    fee_policy
-                );
-                return MmError::err(WithdrawError::InvalidFeePolicy(error));
-            },
-            None => (),
-        };
-        let (unsigned, data) = tx_builder
-            .build()
-            .await
-            .mm_err(|gen_tx_error| WithdrawError::from_generate_tx_error(gen_tx_error, ticker.clone(), decimals))?;
+        let (unsigned, data) = generate_withdraw_tx(coin, &self.sender_address(), self.request()).await?;
 
         // Sign the `unsigned` transaction.
         let signed = self.sign_tx(unsigned).await?;
@@ -206,32 +452,20 @@ where
         // Finish by generating `TransactionDetails` from the signed transaction.
         self.on_finishing()?;
 
-        let fee_amount = data.fee_amount + data.unused_change;
-        let fee_details = UtxoFeeDetails {
-            coin: Some(ticker.clone()),
-            amount: big_decimal_from_sat(fee_amount as i64, decimals),
-        };
         let tx_hex = match coin.addr_format() {
-            UtxoAddressFormat::Segwit => serialize_with_flags(&signed, SERIALIZE_TRANSACTION_WITNESS).into(),
+            UtxoAddressFormat::Segwit | UtxoAddressFormat::Taproot => {
+                serialize_with_flags(&signed, SERIALIZE_TRANSACTION_WITNESS).into()
+            },
             _ => serialize(&signed).into(),
         };
-        Ok(TransactionDetails {
-            from: vec![self.sender_address_string()],
-            to: vec![req.to.clone()],
-            total_amount: big_decimal_from_sat(data.spent_by_me as i64, decimals),
-            spent_by_me: big_decimal_from_sat(data.spent_by_me as i64, decimals),
-            received_by_me: big_decimal_from_sat(data.received_by_me as i64, decimals),
-            my_balance_change: big_decimal_from_sat(data.received_by_me as i64 - data.spent_by_me as i64, decimals),
-            tx: TransactionData::new_signed(tx_hex, signed.hash().reversed().to_vec().to_tx_hash()),
-            fee_details: Some(fee_details.into()),
-            block_height: 0,
-            coin: ticker,
-            internal_id: vec![].into(),
-            timestamp: now_sec(),
-            kmd_rewards: data.kmd_rewards,
-            transaction_type: Default::default(),
-            memo: None,
-        })
+        let tx = TransactionData::new_signed(tx_hex, signed.hash().reversed().to_vec().to_tx_hash());
+        Ok(withdraw_tx_details(
+            coin,
+            self.request(),
+            self.sender_address_string(),
+            data,
+            tx,
+        ))
     }
 }
 
@@ -263,16 +497,25 @@ where
     fn request(&self) -> &WithdrawRequest { &self.req }
 
     fn on_generating_transaction(&self) -> Result<(), MmError<WithdrawError>> {
+        let recipients = withdraw_recipients(&self.req);
         let amount_display = if self.req.max {
             "MAX".to_owned()
         } else {
-            self.req.amount.to_string()
+            let total = recipients
+                .iter()
+                .fold(BigDecimal::from(0), |total, (_to, amount)| total + *amount);
+            total.to_string()
         };
+        let to_display = recipients
+            .iter()
+            .map(|(to, _amount)| to.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
 
         // Display the address from which we are trying to withdraw funds.
         info!(
             "Trying to withdraw {} {} from {} to {}",
-            amount_display, self.req.coin, self.from_address_string, self.req.to,
+            amount_display, self.req.coin, self.from_address_string, to_display,
         );
 
         Ok(self
@@ -290,49 +533,14 @@ where
         self.task_handle
             .update_in_progress_status(WithdrawInProgressStatus::SigningTransaction)?;
 
-        let mut sign_params = UtxoSignTxParamsBuilder::new();
-
-        // TODO refactor [`UtxoTxBuilder::build`] to return `SpendingInputInfo` and `SendingOutputInfo` within `AdditionalTxData`.
-        sign_params.add_inputs_infos(
-            unsigned_tx
-                .inputs
-                .iter()
-                .map(|_input| match self.from_address.addr_format() {
-                    AddressFormat::Segwit => SpendingInputInfo::P2WPKH {
-                        address_derivation_path: self.from_derivation_path.clone(),
-                        address_pubkey: self.from_pubkey,
-                    },
-                    AddressFormat::Standard | AddressFormat::CashAddress { .. } => SpendingInputInfo::P2PKH {
-                        address_derivation_path: self.from_derivation_path.clone(),
-                        address_pubkey: self.from_pubkey,
-                    },
-                }),
-        );
-        sign_params.add_outputs_infos(once(SendingOutputInfo {
-            destination_address: OutputDestination::plain(self.req.to.clone()),
-        }));
-        match unsigned_tx.outputs.len() {
-            // There is no change output.
-            1 => (),
-            // There is a change output.
-            2 => {
-                sign_params.add_outputs_infos(once(SendingOutputInfo {
-                    destination_address: OutputDestination::change(
-                        self.from_derivation_path.clone(),
-                        self.from_address.addr_format().clone(),
-                    ),
-                }));
-            },
-            unexpected => {
-                let error = format!("Unexpected number of outputs: {}", unexpected);
-                return MmError::err(WithdrawError::InternalError(error));
-            },
-        }
-
-        sign_params
-            .with_signature_version(self.signature_version())
-            .with_unsigned_tx(unsigned_tx);
-        let sign_params = sign_params.build()?;
+        let sign_params = withdraw_sign_params(
+            unsigned_tx,
+            &self.req,
+            &self.from_address,
+            &self.from_derivation_path,
+            self.from_pubkey,
+            self.signature_version(),
+        )?;
 
         let signed = match self.coin.as_ref().priv_key_policy {
             PrivKeyPolicy::Iguana(ref key_pair) => {
@@ -458,7 +666,7 @@ where
         + GetWithdrawSenderAddress<Address = Address, Pubkey = PublicKey>,
 {
     #[allow(clippy::result_large_err)]
-    pub async fn new(coin: Coin, req: WithdrawRequest) -> Result<Self, MmError<WithdrawError>> {
+    pub async fn new(ctx: MmArc, coin: Coin, req: WithdrawRequest) -> Result<Self, MmError<WithdrawError>> {
         let from = coin.get_withdraw_sender_address(&req).await?;
         let from_address_string = from.address.display_address().map_to_mm(WithdrawError::InternalError)?;
 
@@ -481,3 +689,130 @@ where
         })
     }
 }
+
+/// How long the inputs of a generated PSBT are kept locked if the PSBT isn't finalized (in seconds).
+pub const PSBT_INPUTS_LOCK_DURATION: u64 = 60 * 60;
+
+/// Generates the withdrawal transaction as an unsigned PSBT to be signed by an external signer.
+pub struct PsbtUtxoWithdraw<Coin> {
+    ctx: MmArc,
+    coin: Coin,
+    req: WithdrawRequest,
+    from_address: Address,
+    from_address_string: String,
+    /// Derivation path from which [`PsbtUtxoWithdraw::from_address`] was derived.
+    from_derivation_path: DerivationPath,
+    /// Public key corresponding to [`PsbtUtxoWithdraw::from_address`].
+    from_pubkey: PublicKey,
+}
+
+impl<Coin> PsbtUtxoWithdraw<Coin>
+where
+    Coin: UtxoCommonOps
+        + GetUtxoListOps
+        + UtxoSignerOps
+        + CoinWithDerivationMethod
+        + GetWithdrawSenderAddress<Address = Address, Pubkey = PublicKey>,
+{
+    #[allow(clippy::result_large_err)]
+    pub async fn new(ctx: MmArc, coin: Coin, req: WithdrawRequest) -> Result<Self, MmError<WithdrawError>> {
+        let from = coin.get_withdraw_sender_address(&req).await?;
+        let from_address_string = from.address.display_address().map_to_mm(WithdrawError::InternalError)?;
+
+        let from_derivation_path = match from.derivation_path {
+            Some(der_path) => der_path,
+            // [`WithdrawSenderAddress::derivation_path`] is not set, but the coin is initialized with an HD wallet derivation method.
+            None if coin.has_hd_wallet_derivation_method() => {
+                let error = "Cannot determine 'from' address derivation path".to_owned();
+                return MmError::err(WithdrawError::UnexpectedFromAddress(error));
+            },
+            // The Iguana key isn't derived from a master key.
+            None => DerivationPath::default(),
+        };
+
+        Ok(PsbtUtxoWithdraw {
+            ctx,
+            coin,
+            req,
+            from_address: from.address,
+            from_address_string,
+            from_derivation_path,
+            from_pubkey: from.pubkey,
+        })
+    }
+
+    /// Returns the withdrawal details with the base64 encoded PSBT instead of the signed transaction.
+    /// The inputs of the PSBT are locked until it's finalized or for [`PSBT_INPUTS_LOCK_DURATION`],
+    /// so they aren't spent by other transactions while the PSBT is being signed.
+    pub async fn build(self) -> WithdrawResult {
+        // The inputs are locked before `UTXO_LOCK` is released, so a concurrent withdrawal can't select them.
+        let utxo_lock = UTXO_LOCK.lock().await;
+        let (unsigned, data) = generate_withdraw_tx_locked(&self.coin, &self.from_address, &self.req).await?;
+        let inputs: Vec<OutPoint> = unsigned.inputs.iter().map(|input| input.previous_output).collect();
+        lock_unspents_until(
+            &self.ctx,
+            &self.coin,
+            inputs.clone(),
+            now_sec() + PSBT_INPUTS_LOCK_DURATION,
+        )
+        .await
+        .mm_err(|e| WithdrawError::InternalError(e.to_string()))?;
+        drop(utxo_lock);
+
+        let psbt = match self.unsigned_psbt(unsigned).await {
+            Ok(psbt) => psbt,
+            Err(e) => {
+                unlock_expiring_unspents(&self.ctx, &self.coin, &inputs)
+                    .await
+                    .error_log();
+                return Err(e);
+            },
+        };
+        let tx = TransactionData::new_unsigned(json!({ "psbt": STANDARD.encode(psbt) }));
+        Ok(withdraw_tx_details(
+            &self.coin,
+            &self.req,
+            self.from_address_string,
+            data,
+            tx,
+        ))
+    }
+
+    /// Returns the serialized PSBT of the `unsigned` transaction.
+    async fn unsigned_psbt(&self, unsigned: TransactionInputSigner) -> Result<Vec<u8>, MmError<WithdrawError>> {
+        let sign_params = withdraw_sign_params(
+            unsigned,
+            &self.req,
+            &self.from_address,
+            &self.from_derivation_path,
+            self.from_pubkey,
+            withdraw_signature_version(&self.coin, &self.from_address),
+        )?;
+        let psbt = self.coin.unsigned_psbt(sign_params, self.master_fingerprint()?).await?;
+        psbt.serialize().mm_err(|e| WithdrawError::InternalError(e.to_string()))
+    }
+
+    /// Returns the fingerprint of the master key the derivation paths start from,
+    /// or `None` if the keys aren't derived from a master key.
+    #[allow(clippy::result_large_err)]
+    fn master_fingerprint(&self) -> Result<Option<[u8; 4]>, MmError<WithdrawError>> {
+        match self.coin.as_ref().priv_key_policy {
+            PrivKeyPolicy::Iguana(_) => Ok(None),
+            PrivKeyPolicy::HDWallet {
+                ref bip39_secp_priv_key,
+                ..
+            } => {
+                let master_pubkey = bip39_secp_priv_key.public_key().public_key().serialize();
+                let mut fingerprint = [0; 4];
+                fingerprint.copy_from_slice(&dhash160(&master_pubkey).as_slice()[..4]);
+                Ok(Some(fingerprint))
+            },
+            // The master key is kept by the device, signers treat the zero fingerprint as unknown.
+            PrivKeyPolicy::Trezor => Ok(Some([0; 4])),
+            #[cfg(target_arch = "wasm32")]
+            PrivKeyPolicy::Metamask(_) => MmError::err(WithdrawError::UnsupportedError(
+                "`PrivKeyPolicy::Metamask` is not supported for UTXO coins!".to_string(),
+            )),
+        }
+    }
+}
@@ -8,11 +8,13 @@ use mm2_err_handle::prelude::*;
 use rpc::v1::types::{Transaction as RpcTransaction, H256 as H256Json};
 use script::Script;
 
+pub mod psbt;
 mod sign_common;
 pub mod sign_params;
 pub mod with_key_pair;
 pub mod with_trezor;
 
+use crate::psbt::{Psbt, PsbtBuilder, PsbtError};
 use crate::with_key_pair::UtxoSignWithKeyPairError;
 use sign_params::UtxoSignTxParams;
 
@@ -32,6 +34,8 @@ pub enum UtxoSignTxError {
     CoinNotSupportedWithTrezor { coin: String },
     #[display(fmt = "Trezor doesn't support P2WPKH outputs yet")]
     TrezorDoesntSupportP2WPKH,
+    #[display(fmt = "Trezor doesn't support P2TR outputs yet")]
+    TrezorDoesntSupportP2TR,
     #[display(fmt = "Trezor client error: {}", _0)]
     TrezorError(TrezorError),
     #[display(fmt = "Encountered invalid parameter '{}': {}", param, description)]
@@ -60,6 +64,8 @@ pub enum UtxoSignTxError {
         script
     )]
     UnspendableUTXO { script: Script },
+    #[display(fmt = "PSBT error: {}", _0)]
+    PsbtError(PsbtError),
     #[display(fmt = "Transport error: {}", _0)]
     Transport(String),
     #[display(fmt = "Internal error: {}", _0)]
@@ -93,6 +99,10 @@ impl From<UtxoSignWithKeyPairError> for UtxoSignTxError {
     }
 }
 
+impl From<PsbtError> for UtxoSignTxError {
+    fn from(e: PsbtError) -> Self { UtxoSignTxError::PsbtError(e) }
+}
+
 impl From<keys::Error> for UtxoSignTxError {
     fn from(e: keys::Error) -> Self { UtxoSignTxError::ErrorSigning(e) }
 }
@@ -152,4 +162,20 @@ pub trait UtxoSignerOps {
             },
         }
     }
+
+    /// Builds an unsigned PSBT to be signed by an external signer.
+    /// `master_fingerprint` is the fingerprint of the master key the inputs' derivation paths start from.
+    async fn unsigned_psbt(
+        &self,
+        params: UtxoSignTxParams,
+        master_fingerprint: Option<[u8; 4]>,
+    ) -> UtxoSignTxResult<Psbt> {
+        let builder = PsbtBuilder {
+            tx_provider: self.tx_provider(),
+            params,
+            fork_id: self.fork_id(),
+            master_fingerprint,
+        };
+        builder.build().await
+    }
 }
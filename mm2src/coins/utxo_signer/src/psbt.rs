@@ -0,0 +1,1314 @@
+//! Partially Signed Bitcoin Transactions
+//! as described in [BIP-174](https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki).
+//!
+//! The PSBTs are encoded and decoded by the `bitcoin` crate, the inputs are signed and finalized here
+//! using the transaction types of this workspace. The inputs spending P2PKH, P2WPKH, P2TR (key path)
+//! and m-of-n multisig P2SH/P2WSH outputs can be finalized.
+//! The fields that aren't interpreted here are kept as is, so a decoded PSBT is encoded back without losses.
+
+use crate::sign_common::complete_tx;
+use crate::sign_params::{MultisigInputInfo, OutputDestination, SendingOutputInfo, SpendingInputInfo, UtxoSignTxParams};
+use crate::with_key_pair::SIGHASH_ALL;
+use crate::{TxProvider, UtxoSignTxError, UtxoSignTxResult};
+use bitcoin::consensus::encode::{deserialize as ext_deserialize, serialize as ext_serialize, Error as ExtEncodeError};
+use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::{PublicKey as ExtSecpPublicKey, XOnlyPublicKey};
+use bitcoin::util::bip32::{ChildNumber, DerivationPath as ExtDerivationPath, ExtendedPubKey, Fingerprint,
+                           KeySource as ExtKeySource};
+use bitcoin::util::psbt::raw::{Key as RawKey, ProprietaryKey};
+use bitcoin::util::psbt::{Error as ExtPsbtError, Input as ExtInput, Output as ExtOutput,
+                          PartiallySignedTransaction as ExtPsbt, PsbtSighashType};
+use bitcoin::util::taproot::TapLeafHash;
+use bitcoin::{EcdsaSig, PublicKey as ExtPublicKey, SchnorrSig, Script as ExtScript, Transaction as ExtTx, TxOut,
+              Witness};
+use bitcrypto::{dhash160, sha256};
+use chain::{OutPoint, Transaction as UtxoTx, TransactionInput, TransactionOutput};
+use crypto::DerivationPath;
+use derive_more::Display;
+use keys::bytes::Bytes;
+use keys::{AddressFormat, AddressHashEnum, KeyPair, Public as PublicKey, Signature};
+use mm2_err_handle::prelude::*;
+use primitives::hash::{H160, H256, H264};
+use rpc::v1::types::H256 as H256Json;
+use script::{Builder, Opcode, Script, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+use serialization::deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Display, PartialEq)]
+pub enum PsbtError {
+    #[display(fmt = "Invalid PSBT magic bytes")]
+    InvalidMagic,
+    #[display(fmt = "Error decoding PSBT: {}", _0)]
+    ErrorDecoding(String),
+    #[display(fmt = "Error encoding PSBT: {}", _0)]
+    ErrorEncoding(String),
+    #[display(fmt = "Transaction format is not supported by PSBT: {}", _0)]
+    UnsupportedTxFormat(String),
+    #[display(fmt = "Input '{}' doesn't contain the output it spends", input_index)]
+    NoPreviousOutput { input_index: usize },
+    #[display(
+        fmt = "Input '{}' spends an output with unsupported script '{}'",
+        input_index,
+        script
+    )]
+    UnsupportedScript { input_index: usize, script: Script },
+    #[display(fmt = "PSBT is not complete, the following inputs are not signed: {:?}", _0)]
+    NotSigned(Vec<usize>),
+    #[display(
+        fmt = "Input '{}' spending a script hash output doesn't contain the script",
+        input_index
+    )]
+    NoScript { input_index: usize },
+    #[display(
+        fmt = "The script of input '{}' doesn't match the script hash of the spent output",
+        input_index
+    )]
+    ScriptHashMismatch { input_index: usize },
+    #[display(fmt = "Input index '{}' is out of bound. Total length = {}", input_index, len)]
+    InputIndexOutOfBound { input_index: usize, len: usize },
+    #[display(fmt = "The key isn't allowed to sign input '{}'", input_index)]
+    KeyNotAllowed { input_index: usize },
+    #[display(
+        fmt = "The signature of input '{}' doesn't belong to any of the input keys",
+        input_index
+    )]
+    InvalidSignature { input_index: usize },
+    #[display(fmt = "Error signing input '{}': {}", input_index, error)]
+    ErrorSigning { input_index: usize, error: String },
+    #[display(fmt = "PSBTs of different transactions can't be combined")]
+    DifferentTransactions,
+}
+
+impl From<serialization::Error> for PsbtError {
+    fn from(e: serialization::Error) -> Self { PsbtError::ErrorDecoding(e.to_string()) }
+}
+
+impl From<ExtEncodeError> for PsbtError {
+    fn from(e: ExtEncodeError) -> Self {
+        match e {
+            ExtEncodeError::Psbt(ExtPsbtError::InvalidMagic | ExtPsbtError::InvalidSeparator) => {
+                PsbtError::InvalidMagic
+            },
+            e => PsbtError::ErrorDecoding(e.to_string()),
+        }
+    }
+}
+
+/// The origin of a public key: the fingerprint of the master key and the derivation path from it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeySource {
+    pub fingerprint: [u8; 4],
+    pub path: Vec<u32>,
+}
+
+impl KeySource {
+    fn to_ext(&self) -> ExtKeySource {
+        let path: Vec<_> = self.path.iter().map(|child| ChildNumber::from(*child)).collect();
+        (Fingerprint::from(&self.fingerprint[..]), ExtDerivationPath::from(path))
+    }
+
+    fn from_ext((fingerprint, path): ExtKeySource) -> KeySource {
+        let mut fingerprint_bytes = [0; 4];
+        fingerprint_bytes.copy_from_slice(&fingerprint[..]);
+        KeySource {
+            fingerprint: fingerprint_bytes,
+            path: path.as_ref().iter().map(|child| u32::from(*child)).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PsbtInput {
+    /// The transaction the spent output belongs to.
+    pub non_witness_utxo: Option<UtxoTx>,
+    /// The spent output, required for the Segwit inputs.
+    pub witness_utxo: Option<TransactionOutput>,
+    /// Signatures followed by the sighash type byte by the public keys.
+    pub partial_sigs: BTreeMap<Vec<u8>, Bytes>,
+    pub sighash_type: Option<u32>,
+    /// The redeem script of a P2SH output.
+    pub redeem_script: Option<Bytes>,
+    /// The witness script of a P2WSH output.
+    pub witness_script: Option<Bytes>,
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    pub final_script_sig: Option<Bytes>,
+    pub final_script_witness: Option<Vec<Bytes>>,
+    /// The Schnorr signature of the key path spending.
+    pub tap_key_sig: Option<Bytes>,
+    /// The leaf hashes and the key sources by the x-only public keys.
+    pub tap_bip32_derivation: BTreeMap<Vec<u8>, (Vec<H256>, KeySource)>,
+    pub tap_internal_key: Option<H256>,
+    /// The fields that aren't interpreted here.
+    pub other: ExtInput,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PsbtOutput {
+    pub bip32_derivation: BTreeMap<Vec<u8>, KeySource>,
+    pub tap_internal_key: Option<H256>,
+    /// The leaf hashes and the key sources by the x-only public keys.
+    pub tap_bip32_derivation: BTreeMap<Vec<u8>, (Vec<H256>, KeySource)>,
+    /// The fields that aren't interpreted here.
+    pub other: ExtOutput,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Psbt {
+    /// The transaction with empty `script_sig` and `script_witness` of every input.
+    pub unsigned_tx: UtxoTx,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+    pub version: u32,
+    pub xpub: BTreeMap<ExtendedPubKey, ExtKeySource>,
+    pub proprietary: BTreeMap<ProprietaryKey, Vec<u8>>,
+    pub unknown: BTreeMap<RawKey, Vec<u8>>,
+}
+
+impl Psbt {
+    pub fn new(unsigned_tx: UtxoTx, inputs: Vec<PsbtInput>, outputs: Vec<PsbtOutput>) -> Psbt {
+        Psbt {
+            unsigned_tx,
+            inputs,
+            outputs,
+            version: 0,
+            xpub: BTreeMap::new(),
+            proprietary: BTreeMap::new(),
+            unknown: BTreeMap::new(),
+        }
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>, MmError<PsbtError>> {
+        let psbt = ExtPsbt {
+            unsigned_tx: ExtTx::from(self.unsigned_tx.clone()),
+            version: self.version,
+            xpub: self.xpub.clone(),
+            proprietary: self.proprietary.clone(),
+            unknown: self.unknown.clone(),
+            inputs: self.inputs.iter().map(PsbtInput::to_ext).collect::<Result<_, _>>()?,
+            outputs: self.outputs.iter().map(PsbtOutput::to_ext).collect::<Result<_, _>>()?,
+        };
+        Ok(ext_serialize(&psbt))
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Result<Psbt, MmError<PsbtError>> {
+        let psbt: ExtPsbt = ext_deserialize(bytes).map_err(PsbtError::from)?;
+        Ok(Psbt {
+            unsigned_tx: from_ext_tx(&psbt.unsigned_tx)?,
+            inputs: psbt
+                .inputs
+                .into_iter()
+                .map(PsbtInput::from_ext)
+                .collect::<Result<_, _>>()?,
+            outputs: psbt.outputs.into_iter().map(PsbtOutput::from_ext).collect(),
+            version: psbt.version,
+            xpub: psbt.xpub,
+            proprietary: psbt.proprietary,
+            unknown: psbt.unknown,
+        })
+    }
+
+    /// Finalizes every input using the signatures it contains and extracts the signed transaction.
+    /// The inputs that are finalized already are left as is.
+    pub fn finalize(self) -> Result<UtxoTx, MmError<PsbtError>> {
+        let mut not_signed = Vec::new();
+        let mut signed_inputs = Vec::with_capacity(self.inputs.len());
+        for (input_index, (unsigned_input, input)) in self.unsigned_tx.inputs.iter().zip(self.inputs.iter()).enumerate()
+        {
+            let (script_sig, script_witness) =
+                if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+                    (
+                        input.final_script_sig.clone().unwrap_or_default(),
+                        input.final_script_witness.clone().unwrap_or_default(),
+                    )
+                } else {
+                    // The signatures are checked, so a signer can't make the transaction invalid.
+                    for (pubkey, signature) in input.partial_sigs.iter() {
+                        self.verify_partial_sig(input_index, pubkey, signature)?;
+                    }
+                    match input.finalize(input_index, &unsigned_input.previous_output)? {
+                        Some(finalized) => finalized,
+                        None => {
+                            not_signed.push(input_index);
+                            continue;
+                        },
+                    }
+                };
+            signed_inputs.push(TransactionInput {
+                previous_output: unsigned_input.previous_output,
+                script_sig,
+                sequence: unsigned_input.sequence,
+                script_witness,
+            });
+        }
+        if !not_signed.is_empty() {
+            return MmError::err(PsbtError::NotSigned(not_signed));
+        }
+
+        Ok(UtxoTx {
+            inputs: signed_inputs,
+            ..self.unsigned_tx
+        })
+    }
+
+    /// Merges the signatures and the metadata of another PSBT of the same transaction, e.g. signed by a cosigner.
+    /// Every new partial signature is verified against the input signature hash before it's kept.
+    pub fn combine(&mut self, other: Psbt) -> Result<(), MmError<PsbtError>> {
+        if self.unsigned_tx.hash() != other.unsigned_tx.hash() {
+            return MmError::err(PsbtError::DifferentTransactions);
+        }
+        let mut other_sigs = Vec::with_capacity(other.inputs.len());
+        for (input, mut other_input) in self.inputs.iter_mut().zip(other.inputs) {
+            other_sigs.push(std::mem::take(&mut other_input.partial_sigs));
+            input.combine(other_input);
+        }
+        for (input_index, partial_sigs) in other_sigs.into_iter().enumerate() {
+            for (pubkey, signature) in partial_sigs {
+                if self.inputs[input_index].partial_sigs.contains_key(&pubkey) {
+                    continue;
+                }
+                self.verify_partial_sig(input_index, &pubkey, &signature)?;
+                self.inputs[input_index].partial_sigs.insert(pubkey, signature);
+            }
+        }
+        for (output, other_output) in self.outputs.iter_mut().zip(other.outputs) {
+            output.combine(other_output);
+        }
+        for (xpub, source) in other.xpub {
+            self.xpub.entry(xpub).or_insert(source);
+        }
+        for (key, value) in other.proprietary {
+            self.proprietary.entry(key).or_insert(value);
+        }
+        for (key, value) in other.unknown {
+            self.unknown.entry(key).or_insert(value);
+        }
+        Ok(())
+    }
+
+    /// Signs the input by the key pair and adds the signature to the input partial signatures.
+    /// The input must spend a P2PKH, P2WPKH or an m-of-n multisig P2SH/P2WSH output the key is allowed to sign.
+    pub fn sign_input(&mut self, input_index: usize, key_pair: &KeyPair) -> Result<(), MmError<PsbtError>> {
+        let (sighash, sighash_type, signers) = self.input_sighash(input_index)?;
+        if !signers.is_allowed(key_pair.public()) {
+            return MmError::err(PsbtError::KeyNotAllowed { input_index });
+        }
+        let signature = key_pair
+            .private()
+            .sign_low_r(&sighash)
+            .map_to_mm(|e| PsbtError::ErrorSigning {
+                input_index,
+                error: e.to_string(),
+            })?;
+        self.inputs[input_index].add_partial_sig(key_pair.public(), &signature, sighash_type);
+        Ok(())
+    }
+
+    /// Adds the DER encoded `signature` made by an external signer, e.g. a hardware wallet.
+    /// The public key the signature belongs to is looked up among the keys allowed to sign the input.
+    pub fn insert_signature(&mut self, input_index: usize, signature: &[u8]) -> Result<(), MmError<PsbtError>> {
+        let (sighash, sighash_type, signers) = self.input_sighash(input_index)?;
+        let signature = Signature::from(signature);
+        let pubkey = signers
+            .candidates(&self.inputs[input_index])
+            .into_iter()
+            .find(|pubkey| pubkey.verify(&sighash, &signature).unwrap_or_default())
+            .or_mm_err(|| PsbtError::InvalidSignature { input_index })?;
+        self.inputs[input_index].add_partial_sig(&pubkey, &signature, sighash_type);
+        Ok(())
+    }
+
+    /// Checks that the partial `signature` followed by the sighash type byte is made by the `pubkey`
+    /// allowed to sign the input.
+    fn verify_partial_sig(
+        &self,
+        input_index: usize,
+        pubkey: &[u8],
+        signature: &[u8],
+    ) -> Result<(), MmError<PsbtError>> {
+        let (sighash, sighash_type, signers) = self.input_sighash(input_index)?;
+        let invalid = || PsbtError::InvalidSignature { input_index };
+        let pubkey = PublicKey::from_slice(pubkey).map_to_mm(|_| invalid())?;
+        let (sighash_type_byte, der_signature) = signature.split_last().or_mm_err(invalid)?;
+        if !signers.is_allowed(&pubkey) || *sighash_type_byte != sighash_type as u8 {
+            return MmError::err(invalid());
+        }
+        if !pubkey
+            .verify(&sighash, &Signature::from(der_signature))
+            .unwrap_or_default()
+        {
+            return MmError::err(invalid());
+        }
+        Ok(())
+    }
+
+    /// Returns the unsigned transaction with the amounts and the script pubkeys of the spent outputs,
+    /// it's required to compute the signature hashes or to pass the transaction to a hardware wallet.
+    pub fn input_signer(&self) -> Result<TransactionInputSigner, MmError<PsbtError>> {
+        let mut signer = TransactionInputSigner::from(self.unsigned_tx.clone());
+        for (input_index, (unsigned_input, input)) in signer.inputs.iter_mut().zip(self.inputs.iter()).enumerate() {
+            let prev_output = input
+                .previous_output(&unsigned_input.previous_output)
+                .or_mm_err(|| PsbtError::NoPreviousOutput { input_index })?;
+            unsigned_input.amount = prev_output.value;
+            unsigned_input.prev_script = prev_output.script_pubkey.clone().into();
+        }
+        Ok(signer)
+    }
+
+    /// Returns the signature hash of the input, its sighash type and the keys allowed to sign it.
+    fn input_sighash(&self, input_index: usize) -> Result<(H256, u32, InputSigners), MmError<PsbtError>> {
+        let len = self.inputs.len();
+        let (unsigned_input, input) = self
+            .unsigned_tx
+            .inputs
+            .get(input_index)
+            .zip(self.inputs.get(input_index))
+            .or_mm_err(|| PsbtError::InputIndexOutOfBound { input_index, len })?;
+        let prev_output = input
+            .previous_output(&unsigned_input.previous_output)
+            .or_mm_err(|| PsbtError::NoPreviousOutput { input_index })?;
+        let prev_script = Script::from(prev_output.script_pubkey.clone());
+        let script_pubkey = prev_output.script_pubkey.as_slice();
+
+        let sighash_type = input.sighash_type.unwrap_or(SIGHASH_ALL);
+        let base_version = if sighash_type & SIGHASH_FORKID != 0 {
+            SignatureVersion::ForkId
+        } else {
+            SignatureVersion::Base
+        };
+        let (script_code, signature_version, signers) = if prev_script.is_pay_to_public_key_hash() {
+            let signers = InputSigners::PubkeyHash(script_pubkey[3..23].to_vec());
+            (prev_script.clone(), base_version, signers)
+        } else if prev_script.is_pay_to_witness_key_hash() {
+            // The scriptCode of a P2WPKH input is the corresponding P2PKH script by BIP-143.
+            let hash = H160::from_slice(&script_pubkey[2..22]).expect("P2WPKH script contains a 20 bytes hash");
+            let script_code = Builder::build_p2pkh(&AddressHashEnum::AddressHash(hash));
+            let signers = InputSigners::PubkeyHash(script_pubkey[2..22].to_vec());
+            (script_code, SignatureVersion::WitnessV0, signers)
+        } else if prev_script.is_pay_to_script_hash() {
+            let redeem_script = input.redeem_script_checked(input_index, script_pubkey)?;
+            let signers = InputSigners::multisig(input_index, &redeem_script)?;
+            (redeem_script, base_version, signers)
+        } else if prev_script.is_pay_to_witness_script_hash() {
+            let witness_script = input.witness_script_checked(input_index, script_pubkey)?;
+            let signers = InputSigners::multisig(input_index, &witness_script)?;
+            (witness_script, SignatureVersion::WitnessV0, signers)
+        } else {
+            return MmError::err(PsbtError::UnsupportedScript {
+                input_index,
+                script: prev_script,
+            });
+        };
+
+        let signer = self.input_signer()?;
+        let sighash = signer.signature_hash(
+            input_index,
+            prev_output.value,
+            &script_code,
+            signature_version,
+            sighash_type,
+        );
+        Ok((sighash, sighash_type, signers))
+    }
+}
+
+/// The bit of the sighash type that signals the replay protected signature hash algorithm of Bitcoin Cash forks.
+const SIGHASH_FORKID: u32 = 0x40;
+
+/// The keys allowed to sign an input.
+enum InputSigners {
+    /// The `hash160` of the only key allowed to sign the input.
+    PubkeyHash(Vec<u8>),
+    /// The keys of an m-of-n multisig script.
+    Multisig(Vec<PublicKey>),
+}
+
+impl InputSigners {
+    fn multisig(input_index: usize, script: &Script) -> Result<InputSigners, MmError<PsbtError>> {
+        script
+            .multisig_pubkeys()
+            .map(InputSigners::Multisig)
+            .or_mm_err(|| PsbtError::UnsupportedScript {
+                input_index,
+                script: script.clone(),
+            })
+    }
+
+    fn is_allowed(&self, pubkey: &PublicKey) -> bool {
+        match self {
+            InputSigners::PubkeyHash(hash) => pubkey.address_hash().as_slice() == hash.as_slice(),
+            InputSigners::Multisig(pubkeys) => pubkeys.contains(pubkey),
+        }
+    }
+
+    /// Returns the keys that may have made a signature of the input.
+    fn candidates(&self, input: &PsbtInput) -> Vec<PublicKey> {
+        match self {
+            InputSigners::PubkeyHash(_) => input
+                .bip32_derivation
+                .keys()
+                .chain(input.partial_sigs.keys())
+                .filter_map(|pubkey| PublicKey::from_slice(pubkey).ok())
+                .filter(|pubkey| self.is_allowed(pubkey))
+                .collect(),
+            InputSigners::Multisig(pubkeys) => pubkeys.clone(),
+        }
+    }
+}
+
+impl PsbtInput {
+    fn to_ext(&self) -> Result<ExtInput, MmError<PsbtError>> {
+        let mut input = self.other.clone();
+        input.non_witness_utxo = self.non_witness_utxo.clone().map(ExtTx::from);
+        input.witness_utxo = self.witness_utxo.clone().map(TxOut::from);
+        input.partial_sigs = self
+            .partial_sigs
+            .iter()
+            .map(|(pubkey, signature)| {
+                let pubkey =
+                    ExtPublicKey::from_slice(pubkey).map_to_mm(|e| encoding_error("partial signature key", e))?;
+                let signature =
+                    EcdsaSig::from_slice(signature).map_to_mm(|e| encoding_error("partial signature", e))?;
+                Ok((pubkey, signature))
+            })
+            .collect::<Result<_, MmError<PsbtError>>>()?;
+        input.sighash_type = self.sighash_type.map(PsbtSighashType::from_u32);
+        input.redeem_script = self.redeem_script.clone().map(to_ext_script);
+        input.witness_script = self.witness_script.clone().map(to_ext_script);
+        input.bip32_derivation = to_ext_key_sources(&self.bip32_derivation)?;
+        input.final_script_sig = self.final_script_sig.clone().map(to_ext_script);
+        input.final_script_witness = self
+            .final_script_witness
+            .as_ref()
+            .map(|witness| Witness::from_vec(witness.iter().map(|item| item.to_vec()).collect()));
+        input.tap_key_sig = self
+            .tap_key_sig
+            .as_ref()
+            .map(|signature| SchnorrSig::from_slice(signature))
+            .transpose()
+            .map_to_mm(|e| encoding_error("Taproot key signature", e))?;
+        input.tap_key_origins = to_ext_tap_key_sources(&self.tap_bip32_derivation)?;
+        input.tap_internal_key = self.tap_internal_key.as_ref().map(to_x_only).transpose()?;
+        Ok(input)
+    }
+
+    fn from_ext(mut other: ExtInput) -> Result<PsbtInput, MmError<PsbtError>> {
+        let non_witness_utxo = match other.non_witness_utxo.take() {
+            Some(tx) => Some(from_ext_tx(&tx)?),
+            None => None,
+        };
+        Ok(PsbtInput {
+            non_witness_utxo,
+            witness_utxo: other.witness_utxo.take().map(from_ext_tx_out),
+            partial_sigs: std::mem::take(&mut other.partial_sigs)
+                .into_iter()
+                .map(|(pubkey, signature)| (pubkey.to_bytes(), signature.to_vec().into()))
+                .collect(),
+            sighash_type: other.sighash_type.take().map(PsbtSighashType::to_u32),
+            redeem_script: other.redeem_script.take().map(from_ext_script),
+            witness_script: other.witness_script.take().map(from_ext_script),
+            bip32_derivation: from_ext_key_sources(std::mem::take(&mut other.bip32_derivation)),
+            final_script_sig: other.final_script_sig.take().map(from_ext_script),
+            final_script_witness: other
+                .final_script_witness
+                .take()
+                .map(|witness| witness.to_vec().into_iter().map(Bytes::from).collect()),
+            tap_key_sig: other.tap_key_sig.take().map(|signature| signature.to_vec().into()),
+            tap_bip32_derivation: from_ext_tap_key_sources(std::mem::take(&mut other.tap_key_origins)),
+            tap_internal_key: other.tap_internal_key.take().map(|key| H256::from(key.serialize())),
+            other,
+        })
+    }
+
+    /// Returns the output spent by this input.
+    fn previous_output(&self, outpoint: &OutPoint) -> Option<&TransactionOutput> {
+        if let Some(ref output) = self.witness_utxo {
+            return Some(output);
+        }
+        self.non_witness_utxo
+            .as_ref()
+            .filter(|prev_tx| prev_tx.hash() == outpoint.hash)
+            .and_then(|prev_tx| prev_tx.outputs.get(outpoint.index as usize))
+    }
+
+    fn combine(&mut self, other: PsbtInput) {
+        if self.non_witness_utxo.is_none() {
+            self.non_witness_utxo = other.non_witness_utxo;
+        }
+        if self.witness_utxo.is_none() {
+            self.witness_utxo = other.witness_utxo;
+        }
+        if self.sighash_type.is_none() {
+            self.sighash_type = other.sighash_type;
+        }
+        if self.redeem_script.is_none() {
+            self.redeem_script = other.redeem_script;
+        }
+        if self.witness_script.is_none() {
+            self.witness_script = other.witness_script;
+        }
+        for (pubkey, source) in other.bip32_derivation {
+            self.bip32_derivation.entry(pubkey).or_insert(source);
+        }
+        if self.final_script_sig.is_none() {
+            self.final_script_sig = other.final_script_sig;
+        }
+        if self.final_script_witness.is_none() {
+            self.final_script_witness = other.final_script_witness;
+        }
+        if self.tap_key_sig.is_none() {
+            self.tap_key_sig = other.tap_key_sig;
+        }
+        for (x_only, tap_source) in other.tap_bip32_derivation {
+            self.tap_bip32_derivation.entry(x_only).or_insert(tap_source);
+        }
+        if self.tap_internal_key.is_none() {
+            self.tap_internal_key = other.tap_internal_key;
+        }
+        self.other.combine(other.other);
+    }
+
+    /// Adds the DER encoded signature followed by the sighash type byte.
+    fn add_partial_sig(&mut self, pubkey: &PublicKey, signature: &[u8], sighash_type: u32) {
+        let mut signature = signature.to_vec();
+        signature.push(sighash_type as u8);
+        self.partial_sigs.insert(pubkey.to_vec(), signature.into());
+    }
+
+    fn script_to_sign(&self, input_index: usize, script: &Option<Bytes>) -> Result<Script, MmError<PsbtError>> {
+        script
+            .clone()
+            .map(Script::from)
+            .or_mm_err(|| PsbtError::NoScript { input_index })
+    }
+
+    /// Returns the redeem script checking that `hash160` of it is committed to by the P2SH `script_pubkey`.
+    fn redeem_script_checked(&self, input_index: usize, script_pubkey: &[u8]) -> Result<Script, MmError<PsbtError>> {
+        let redeem_script = self.script_to_sign(input_index, &self.redeem_script)?;
+        if dhash160(&redeem_script).as_slice() != &script_pubkey[2..22] {
+            return MmError::err(PsbtError::ScriptHashMismatch { input_index });
+        }
+        Ok(redeem_script)
+    }
+
+    /// Returns the witness script checking that `sha256` of it is committed to by the P2WSH `script_pubkey`.
+    fn witness_script_checked(&self, input_index: usize, script_pubkey: &[u8]) -> Result<Script, MmError<PsbtError>> {
+        let witness_script = self.script_to_sign(input_index, &self.witness_script)?;
+        if sha256(&witness_script).as_slice() != &script_pubkey[2..34] {
+            return MmError::err(PsbtError::ScriptHashMismatch { input_index });
+        }
+        Ok(witness_script)
+    }
+
+    /// Returns the signatures of an m-of-n multisig script in the order of the script public keys,
+    /// or `None` if the input doesn't contain `m` signatures yet.
+    fn multisig_signatures(
+        &self,
+        input_index: usize,
+        script: &Script,
+    ) -> Result<Option<Vec<Bytes>>, MmError<PsbtError>> {
+        let unsupported = || PsbtError::UnsupportedScript {
+            input_index,
+            script: script.clone(),
+        };
+        let pubkeys = script.multisig_pubkeys().or_mm_err(unsupported)?;
+        let required = match script.get_opcode(0) {
+            Ok(opcode) if opcode >= Opcode::OP_1 && opcode <= Opcode::OP_16 => {
+                (opcode as u8 - (Opcode::OP_1 as u8 - 1)) as usize
+            },
+            _ => return MmError::err(unsupported()),
+        };
+        let signatures: Vec<_> = pubkeys
+            .iter()
+            .filter_map(|pubkey| self.partial_sigs.get(pubkey.to_vec().as_slice()).cloned())
+            .take(required)
+            .collect();
+        if signatures.len() < required {
+            return Ok(None);
+        }
+        Ok(Some(signatures))
+    }
+
+    /// Returns the partial signature made by the public key with the given `hash`.
+    fn partial_sig_by_pubkey_hash(&self, hash: &[u8]) -> Option<(&Vec<u8>, &Bytes)> {
+        self.partial_sigs.iter().find(|(pubkey, _signature)| {
+            PublicKey::from_slice(pubkey)
+                .map(|pubkey| pubkey.address_hash().as_slice() == hash)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the `script_sig` and `script_witness` of the input spending the `outpoint`,
+    /// or `None` if the input doesn't contain the required signature yet.
+    #[allow(clippy::type_complexity)]
+    fn finalize(
+        &self,
+        input_index: usize,
+        outpoint: &OutPoint,
+    ) -> Result<Option<(Bytes, Vec<Bytes>)>, MmError<PsbtError>> {
+        let prev_output = self
+            .previous_output(outpoint)
+            .or_mm_err(|| PsbtError::NoPreviousOutput { input_index })?;
+        let prev_script = Script::from(prev_output.script_pubkey.clone());
+        let script_pubkey = prev_output.script_pubkey.as_slice();
+
+        if prev_script.is_pay_to_public_key_hash() {
+            let finalized = self
+                .partial_sig_by_pubkey_hash(&script_pubkey[3..23])
+                .map(|(pubkey, signature)| {
+                    let script_sig = Builder::default().push_data(signature).push_data(pubkey).into_bytes();
+                    (script_sig, Vec::new())
+                });
+            Ok(finalized)
+        } else if prev_script.is_pay_to_witness_key_hash() {
+            let finalized = self
+                .partial_sig_by_pubkey_hash(&script_pubkey[2..22])
+                .map(|(pubkey, signature)| (Bytes::default(), vec![signature.clone(), pubkey.clone().into()]));
+            Ok(finalized)
+        } else if prev_script.is_pay_to_taproot() {
+            Ok(self
+                .tap_key_sig
+                .clone()
+                .map(|signature| (Bytes::default(), vec![signature])))
+        } else if prev_script.is_pay_to_script_hash() {
+            let redeem_script = self.redeem_script_checked(input_index, script_pubkey)?;
+            let finalized = self
+                .multisig_signatures(input_index, &redeem_script)?
+                .map(|signatures| {
+                    // `OP_0` is consumed by the `OP_CHECKMULTISIG` off-by-one bug.
+                    let builder = signatures
+                        .iter()
+                        .fold(Builder::default().push_opcode(Opcode::OP_0), |builder, signature| {
+                            builder.push_data(signature)
+                        });
+                    (builder.push_data(&redeem_script).into_bytes(), Vec::new())
+                });
+            Ok(finalized)
+        } else if prev_script.is_pay_to_witness_script_hash() {
+            let witness_script = self.witness_script_checked(input_index, script_pubkey)?;
+            let finalized = self
+                .multisig_signatures(input_index, &witness_script)?
+                .map(|signatures| {
+                    let mut witness = Vec::with_capacity(signatures.len() + 2);
+                    witness.push(Bytes::default());
+                    witness.extend(signatures);
+                    witness.push(witness_script.to_bytes());
+                    (Bytes::default(), witness)
+                });
+            Ok(finalized)
+        } else {
+            MmError::err(PsbtError::UnsupportedScript {
+                input_index,
+                script: prev_script,
+            })
+        }
+    }
+}
+
+impl PsbtOutput {
+    fn combine(&mut self, other: PsbtOutput) {
+        for (pubkey, source) in other.bip32_derivation {
+            self.bip32_derivation.entry(pubkey).or_insert(source);
+        }
+        if self.tap_internal_key.is_none() {
+            self.tap_internal_key = other.tap_internal_key;
+        }
+        for (x_only, tap_source) in other.tap_bip32_derivation {
+            self.tap_bip32_derivation.entry(x_only).or_insert(tap_source);
+        }
+        self.other.combine(other.other);
+    }
+
+    fn to_ext(&self) -> Result<ExtOutput, MmError<PsbtError>> {
+        let mut output = self.other.clone();
+        output.bip32_derivation = to_ext_key_sources(&self.bip32_derivation)?;
+        output.tap_internal_key = self.tap_internal_key.as_ref().map(to_x_only).transpose()?;
+        output.tap_key_origins = to_ext_tap_key_sources(&self.tap_bip32_derivation)?;
+        Ok(output)
+    }
+
+    fn from_ext(mut other: ExtOutput) -> PsbtOutput {
+        PsbtOutput {
+            bip32_derivation: from_ext_key_sources(std::mem::take(&mut other.bip32_derivation)),
+            tap_internal_key: other.tap_internal_key.take().map(|key| H256::from(key.serialize())),
+            tap_bip32_derivation: from_ext_tap_key_sources(std::mem::take(&mut other.tap_key_origins)),
+            other,
+        }
+    }
+}
+
+/// Builds an unsigned PSBT containing the metadata required by an external signer:
+/// the spent outputs and the derivation paths of the keys the inputs and the change outputs belong to.
+pub struct PsbtBuilder<TxP> {
+    pub tx_provider: TxP,
+    pub params: UtxoSignTxParams,
+    pub fork_id: u32,
+    /// The fingerprint of the master key the derivation paths start from.
+    /// `None` if the keys aren't derived from a master key, then the fingerprint of the key itself is used.
+    pub master_fingerprint: Option<[u8; 4]>,
+}
+
+impl<TxP: TxProvider + Send + Sync> PsbtBuilder<TxP> {
+    pub async fn build(self) -> UtxoSignTxResult<Psbt> {
+        check_tx_format(&self.params.unsigned_tx)?;
+        // The `bitcoin` crate accepts only the standard sighash types of the partial signatures.
+        if self.fork_id != 0 {
+            let error = "transactions signed with a fork ID".to_owned();
+            return MmError::err(PsbtError::UnsupportedTxFormat(error).into());
+        }
+
+        let mut inputs = Vec::with_capacity(self.params.inputs_count());
+        for (unsigned_input, input_info) in self.params.inputs() {
+            inputs.push(self.psbt_input(unsigned_input, input_info).await?);
+        }
+        let outputs = self
+            .params
+            .outputs()
+            .map(|(_tx_output, output_info)| self.psbt_output(output_info))
+            .collect::<UtxoSignTxResult<_>>()?;
+
+        let unsigned_inputs = self
+            .params
+            .unsigned_tx
+            .inputs
+            .iter()
+            .map(|unsigned_input| TransactionInput {
+                previous_output: unsigned_input.previous_output,
+                script_sig: Bytes::default(),
+                sequence: unsigned_input.sequence,
+                script_witness: Vec::new(),
+            })
+            .collect();
+        Ok(Psbt::new(
+            complete_tx(self.params.unsigned_tx, unsigned_inputs),
+            inputs,
+            outputs,
+        ))
+    }
+
+    async fn psbt_input(
+        &self,
+        unsigned_input: &UnsignedTransactionInput,
+        input_info: &SpendingInputInfo,
+    ) -> UtxoSignTxResult<PsbtInput> {
+        let witness_utxo = TransactionOutput {
+            value: unsigned_input.amount,
+            script_pubkey: unsigned_input.prev_script.to_bytes(),
+        };
+        let derivation_path = input_info.address_derivation_path();
+
+        let mut input = PsbtInput::default();
+        match input_info {
+            SpendingInputInfo::P2PKH { address_pubkey, .. } => {
+                input.non_witness_utxo = Some(self.get_prev_tx(&unsigned_input.previous_output).await?);
+                let key_source = self.key_source(derivation_path, address_pubkey);
+                input.bip32_derivation.insert(address_pubkey.to_vec(), key_source);
+            },
+            SpendingInputInfo::P2WPKH { address_pubkey, .. } => {
+                // The previous transaction is required by most of the hardware wallets to verify the input amount.
+                input.non_witness_utxo = Some(self.get_prev_tx(&unsigned_input.previous_output).await?);
+                input.witness_utxo = Some(witness_utxo);
+                let key_source = self.key_source(derivation_path, address_pubkey);
+                input.bip32_derivation.insert(address_pubkey.to_vec(), key_source);
+            },
+            SpendingInputInfo::P2TR { address_pubkey, .. } => {
+                let internal_key = x_only_pubkey(address_pubkey)?;
+                let key_source = self.key_source(derivation_path, address_pubkey);
+                input.witness_utxo = Some(witness_utxo);
+                input.tap_internal_key = Some(internal_key);
+                input
+                    .tap_bip32_derivation
+                    .insert(internal_key.to_vec(), (Vec::new(), key_source));
+            },
+            SpendingInputInfo::P2SH { multisig, .. } => {
+                input.non_witness_utxo = Some(self.get_prev_tx(&unsigned_input.previous_output).await?);
+                input.redeem_script = Some(multisig.script.to_bytes());
+                input.bip32_derivation = cosigners_key_sources(multisig);
+            },
+            SpendingInputInfo::P2WSH { multisig, .. } => {
+                input.non_witness_utxo = Some(self.get_prev_tx(&unsigned_input.previous_output).await?);
+                input.witness_utxo = Some(witness_utxo);
+                input.witness_script = Some(multisig.script.to_bytes());
+                input.bip32_derivation = cosigners_key_sources(multisig);
+            },
+        }
+        Ok(input)
+    }
+
+    fn psbt_output(&self, output_info: &SendingOutputInfo) -> UtxoSignTxResult<PsbtOutput> {
+        let (derivation_path, addr_format) = match output_info.destination_address {
+            OutputDestination::Plain { .. } => return Ok(PsbtOutput::default()),
+            OutputDestination::Change {
+                ref derivation_path,
+                ref addr_format,
+            } => (derivation_path, addr_format),
+        };
+        // The change is sent back to one of the spending addresses, so its public key is known.
+        let address_pubkey = match self
+            .params
+            .inputs_infos
+            .iter()
+            .find(|input_info| input_info.address_derivation_path() == derivation_path)
+            .and_then(SpendingInputInfo::address_pubkey)
+        {
+            Some(address_pubkey) => address_pubkey,
+            None => return Ok(PsbtOutput::default()),
+        };
+        let key_source = self.key_source(derivation_path, address_pubkey);
+
+        let mut output = PsbtOutput::default();
+        if *addr_format == AddressFormat::Taproot {
+            let internal_key = x_only_pubkey(address_pubkey)?;
+            output.tap_internal_key = Some(internal_key);
+            output
+                .tap_bip32_derivation
+                .insert(internal_key.to_vec(), (Vec::new(), key_source));
+        } else {
+            output.bip32_derivation.insert(address_pubkey.to_vec(), key_source);
+        }
+        Ok(output)
+    }
+
+    fn key_source(&self, derivation_path: &DerivationPath, pubkey: &PublicKey) -> KeySource {
+        match self.master_fingerprint {
+            Some(fingerprint) => KeySource {
+                fingerprint,
+                path: derivation_path
+                    .iter()
+                    .map(|child| child.index() | if child.is_hardened() { HARDENED_BIT } else { 0 })
+                    .collect(),
+            },
+            None => KeySource {
+                fingerprint: key_fingerprint(pubkey),
+                path: Vec::new(),
+            },
+        }
+    }
+
+    async fn get_prev_tx(&self, outpoint: &OutPoint) -> UtxoSignTxResult<UtxoTx> {
+        let prev_tx_hash = H256Json::from(outpoint.hash.reversed());
+        let prev_verbose = self.tx_provider.get_rpc_transaction(&prev_tx_hash).await?;
+        deserialize(prev_verbose.hex.as_slice()).map_to_mm(|e| UtxoSignTxError::Transport(e.to_string()))
+    }
+}
+
+const HARDENED_BIT: u32 = 0x8000_0000;
+
+/// PSBT requires the transaction to be serialized in the Bitcoin format.
+fn check_tx_format(unsigned_tx: &TransactionInputSigner) -> Result<(), MmError<PsbtError>> {
+    let unsupported = if unsigned_tx.overwintered || unsigned_tx.zcash {
+        "Zcash transactions"
+    } else if unsigned_tx.posv || unsigned_tx.n_time.is_some() {
+        "transactions with a timestamp"
+    } else if unsigned_tx.str_d_zeel.is_some() {
+        "transactions with a comment"
+    } else if unsigned_tx.v_extra_payload.is_some() {
+        "transactions with an extra payload"
+    } else {
+        return Ok(());
+    };
+    MmError::err(PsbtError::UnsupportedTxFormat(unsupported.to_owned()))
+}
+
+/// The cosigners' keys are described by the fingerprints of their account extended public keys,
+/// since the master keys of the other cosigners are unknown.
+fn cosigners_key_sources(multisig: &MultisigInputInfo) -> BTreeMap<Vec<u8>, KeySource> {
+    multisig
+        .cosigners
+        .iter()
+        .map(|cosigner| {
+            let xpub_pubkey = PublicKey::Compressed(H264::from(cosigner.xpub.public_key().serialize()));
+            let key_source = KeySource {
+                fingerprint: key_fingerprint(&xpub_pubkey),
+                path: multisig.address_path.clone(),
+            };
+            (cosigner.pubkey.to_vec(), key_source)
+        })
+        .collect()
+}
+
+/// Returns the first 4 bytes of the `hash160` of the public key.
+fn key_fingerprint(pubkey: &PublicKey) -> [u8; 4] {
+    let mut fingerprint = [0; 4];
+    fingerprint.copy_from_slice(&pubkey.address_hash().as_slice()[..4]);
+    fingerprint
+}
+
+fn x_only_pubkey(pubkey: &PublicKey) -> UtxoSignTxResult<H256> {
+    pubkey
+        .compressed_unprefixed()
+        .map(H256::from)
+        .or_mm_err(|| UtxoSignTxError::Internal("Taproot requires a compressed public key".to_owned()))
+}
+
+fn encoding_error(field: &str, error: impl std::fmt::Display) -> PsbtError {
+    PsbtError::ErrorEncoding(format!("Invalid {}: {}", field, error))
+}
+
+fn to_x_only(key: &H256) -> Result<XOnlyPublicKey, MmError<PsbtError>> {
+    XOnlyPublicKey::from_slice(key.as_slice()).map_to_mm(|e| encoding_error("x-only public key", e))
+}
+
+fn to_ext_script(script: Bytes) -> ExtScript { ExtScript::from(script.take()) }
+
+fn from_ext_script(script: ExtScript) -> Bytes { script.into_bytes().into() }
+
+fn from_ext_tx(tx: &ExtTx) -> Result<UtxoTx, MmError<PsbtError>> { Ok(deserialize(ext_serialize(tx).as_slice())?) }
+
+fn from_ext_tx_out(output: TxOut) -> TransactionOutput {
+    TransactionOutput {
+        value: output.value,
+        script_pubkey: output.script_pubkey.into_bytes().into(),
+    }
+}
+
+fn to_ext_key_sources(
+    sources: &BTreeMap<Vec<u8>, KeySource>,
+) -> Result<BTreeMap<ExtSecpPublicKey, ExtKeySource>, MmError<PsbtError>> {
+    sources
+        .iter()
+        .map(|(pubkey, source)| {
+            let pubkey = ExtSecpPublicKey::from_slice(pubkey).map_to_mm(|e| encoding_error("BIP-32 public key", e))?;
+            Ok((pubkey, source.to_ext()))
+        })
+        .collect()
+}
+
+fn from_ext_key_sources(sources: BTreeMap<ExtSecpPublicKey, ExtKeySource>) -> BTreeMap<Vec<u8>, KeySource> {
+    sources
+        .into_iter()
+        .map(|(pubkey, source)| (pubkey.serialize().to_vec(), KeySource::from_ext(source)))
+        .collect()
+}
+
+#[allow(clippy::type_complexity)]
+fn to_ext_tap_key_sources(
+    sources: &BTreeMap<Vec<u8>, (Vec<H256>, KeySource)>,
+) -> Result<BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, ExtKeySource)>, MmError<PsbtError>> {
+    sources
+        .iter()
+        .map(|(x_only, (leaf_hashes, source))| {
+            let x_only = XOnlyPublicKey::from_slice(x_only).map_to_mm(|e| encoding_error("x-only public key", e))?;
+            let leaf_hashes = leaf_hashes
+                .iter()
+                .map(|leaf_hash| TapLeafHash::from_inner(leaf_hash.take()))
+                .collect();
+            Ok((x_only, (leaf_hashes, source.to_ext())))
+        })
+        .collect()
+}
+
+fn from_ext_tap_key_sources(
+    sources: BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, ExtKeySource)>,
+) -> BTreeMap<Vec<u8>, (Vec<H256>, KeySource)> {
+    sources
+        .into_iter()
+        .map(|(x_only, (leaf_hashes, source))| {
+            let leaf_hashes = leaf_hashes
+                .into_iter()
+                .map(|leaf_hash| H256::from(leaf_hash.into_inner()))
+                .collect();
+            (x_only.serialize().to_vec(), (leaf_hashes, KeySource::from_ext(source)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keys::AddressHashEnum;
+    use serialization::serialize;
+
+    // The valid PSBTs of the BIP-174 test vectors.
+    const BIP174_PSBT_P2PKH: &str = concat!(
+        "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000",
+        "0000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f505000000",
+        "0017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d",
+        "20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f",
+        "53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b4010000001716",
+        "0014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb",
+        "34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d0587024730",
+        "4402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c927",
+        "6bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe3",
+        "9c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d02",
+        "2067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778ef",
+        "ecd61fcac6f79a4ea169393380734464f84f2ab300000000000000",
+    );
+    const BIP174_PSBT_FINALIZED_INPUT: &str = concat!(
+        "70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be4000000",
+        "0000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02",
+        "603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f46",
+        "20b553fa095e721b9ee0efe9fa039cca459788ac000000000001076a47304402204759661797c01b036b259289486862",
+        "18347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceed",
+        "af93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa882920001012000e1f5050000",
+        "000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01f",
+        "b82308000000",
+    );
+    const BIP174_PSBT_SIGHASH_TYPE: &str = concat!(
+        "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000",
+        "0000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f505000000",
+        "0017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d",
+        "20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f",
+        "53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b4010000001716",
+        "0014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb",
+        "34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d0587024730",
+        "4402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c927",
+        "6bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe3",
+        "9c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d02",
+        "2067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778ef",
+        "ecd61fcac6f79a4ea169393380734464f84f2ab30000000001030401000000000000",
+    );
+    const BIP174_PSBT_BIP32_DERIVATIONS: &str = concat!(
+        "70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be4000000",
+        "0000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02",
+        "603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f46",
+        "20b553fa095e721b9ee0efe9fa039cca459788ac00000000000100df0200000001268171371edff285e937adeea4b37b",
+        "78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435",
+        "f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102",
+        "657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a9",
+        "14d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb9",
+        "3c9c03948bc787b32e13000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc78701",
+        "0416001485d13537f2e265405a34dbafa9e3dda01fb8230800220202ead596687ca806043edc3de116cdf29d5e9257c1",
+        "96cd055cf698c8d02bf24e9910b4a6ba670000008000000080020000800022020394f62be9df19952c5587768aeb7698",
+        "061ad2c4a25c894f47d8c162b4d7213d0510b4a6ba6700000080010000800200008000",
+    );
+    const BIP174_PSBT_P2SH_P2WSH_PARTIAL_SIG: &str = concat!(
+        "70736274ff0100550200000001279a2323a5dfb51fc45f220fa58b0fc13e1e3342792a85d7e36cd6333b5cbc39000000",
+        "0000ffffffff01a05aea0b000000001976a914ffe9c0061097cc3b636f2cb0460fa4fc427d2b4588ac00000000000101",
+        "20955eea0b0000000017a9146345200f68d189e1adc0df1c4d16ea8f14c0dbeb87220203b1341ccba7683b6af4f1238c",
+        "d6e97e7167d569fac47f1e48d47541844355bd4646304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cd",
+        "f070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf86151db9a9a010104220020",
+        "771fd18ad459666dd49f3d564e3dbc42f4c84774e360ada16816a8ed488d5681010547522103b1341ccba7683b6af4f1",
+        "238cd6e97e7167d569fac47f1e48d47541844355bd462103de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefc",
+        "a4995f26f82083bd52ae220603b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4610b4a6",
+        "ba67000000800000008004000080220603de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083",
+        "bd10b4a6ba670000008000000080050000800000",
+    );
+    const BIP174_PSBT_UNKNOWN_KEYS: &str = concat!(
+        "70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff000000",
+        "0000ffffffff010000000000000000036a010000000000000a0f0102030405060708090f0102030405060708090a0b0c",
+        "0d0e0f0000",
+    );
+
+    fn unsigned_tx(outpoint: OutPoint) -> UtxoTx {
+        UtxoTx {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: outpoint,
+                script_sig: Bytes::default(),
+                sequence: 0xfffffffd,
+                script_witness: Vec::new(),
+            }],
+            outputs: vec![TransactionOutput {
+                value: 90000,
+                script_pubkey: Builder::build_p2pkh(&AddressHashEnum::default_address_hash()).to_bytes(),
+            }],
+            ..UtxoTx::default()
+        }
+    }
+
+    #[test]
+    fn test_psbt_serialize_deserialize_finalize() {
+        let key_pair = KeyPair::random_compressed();
+        let pubkey = *key_pair.public();
+        let prev_script = Builder::build_p2wpkh(&AddressHashEnum::AddressHash(pubkey.address_hash())).unwrap();
+        let outpoint = OutPoint {
+            hash: H256::from([1; 32]),
+            index: 1,
+        };
+        let key_source = KeySource {
+            fingerprint: [0xde, 0xad, 0xbe, 0xef],
+            path: vec![84 | HARDENED_BIT, HARDENED_BIT, HARDENED_BIT, 0, 5],
+        };
+
+        let mut psbt = Psbt::new(
+            unsigned_tx(outpoint),
+            vec![PsbtInput {
+                witness_utxo: Some(TransactionOutput {
+                    value: 100000,
+                    script_pubkey: prev_script.to_bytes(),
+                }),
+                bip32_derivation: BTreeMap::from([(pubkey.to_vec(), key_source)]),
+                ..PsbtInput::default()
+            }],
+            vec![PsbtOutput::default()],
+        );
+        let unknown_key = RawKey {
+            type_value: 0x0f,
+            key: vec![0x01],
+        };
+        psbt.unknown.insert(unknown_key, vec![0x02, 0x03]);
+
+        let serialized = psbt.serialize().unwrap();
+        assert!(serialized.starts_with(b"psbt\xff"));
+        let deserialized = Psbt::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized, psbt);
+
+        let error = psbt.clone().finalize().unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::NotSigned(vec![0]));
+
+        // A signature that doesn't match the signature hash doesn't get into the transaction.
+        let mut forged = psbt.clone();
+        let forged_signature = key_pair.private().sign_low_r(&H256::from([2; 32])).unwrap();
+        forged.inputs[0].add_partial_sig(&pubkey, &forged_signature, SIGHASH_ALL);
+        let forged = Psbt::deserialize(&forged.serialize().unwrap()).unwrap();
+        let error = forged.finalize().unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::InvalidSignature { input_index: 0 });
+
+        psbt.sign_input(0, &key_pair).unwrap();
+        let signature = psbt.inputs[0].partial_sigs[&pubkey.to_vec()].clone();
+        let psbt = Psbt::deserialize(&psbt.serialize().unwrap()).unwrap();
+        let signed = psbt.finalize().unwrap();
+        assert!(signed.inputs[0].script_sig.is_empty());
+        assert_eq!(signed.inputs[0].script_witness, vec![
+            signature,
+            Bytes::from(pubkey.to_vec())
+        ]);
+        assert_eq!(signed.outputs, unsigned_tx(outpoint).outputs);
+    }
+
+    #[test]
+    fn test_psbt_sign_combine_finalize_multisig() {
+        let key_pairs: Vec<_> = (0..3).map(|_| KeyPair::random_compressed()).collect();
+        let pubkeys: Vec<_> = key_pairs.iter().map(|key_pair| *key_pair.public()).collect();
+        let witness_script = Builder::build_multisig(2, &pubkeys).unwrap();
+        let script_hash = AddressHashEnum::WitnessScriptHash(sha256(&witness_script));
+        let prev_script = Builder::build_p2wsh(&script_hash).unwrap();
+        let outpoint = OutPoint {
+            hash: H256::from([1; 32]),
+            index: 0,
+        };
+
+        let psbt = Psbt::new(
+            unsigned_tx(outpoint),
+            vec![PsbtInput {
+                witness_utxo: Some(TransactionOutput {
+                    value: 100000,
+                    script_pubkey: prev_script.to_bytes(),
+                }),
+                witness_script: Some(witness_script.to_bytes()),
+                ..PsbtInput::default()
+            }],
+            vec![PsbtOutput::default()],
+        );
+
+        // The cosigners sign their copies of the PSBT independently.
+        let mut first = Psbt::deserialize(&psbt.serialize().unwrap()).unwrap();
+        first.sign_input(0, &key_pairs[2]).unwrap();
+        let error = first.clone().finalize().unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::NotSigned(vec![0]));
+
+        let mut second = psbt.clone();
+        let (sighash, _, _) = second.input_sighash(0).unwrap();
+        let signature = key_pairs[0].private().sign_low_r(&sighash).unwrap();
+        second.insert_signature(0, &signature).unwrap();
+
+        let unknown_key = KeyPair::random_compressed();
+        let error = second.sign_input(0, &unknown_key).unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::KeyNotAllowed { input_index: 0 });
+
+        // A signature that doesn't match the signature hash isn't combined.
+        let mut forged = psbt.clone();
+        let forged_signature = key_pairs[1].private().sign_low_r(&H256::from([2; 32])).unwrap();
+        forged.inputs[0].add_partial_sig(&pubkeys[1], &forged_signature, SIGHASH_ALL);
+        let error = first.clone().combine(forged).unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::InvalidSignature { input_index: 0 });
+
+        first.combine(second).unwrap();
+        let signed_psbt_sigs = first.inputs[0].partial_sigs.clone();
+        let signed = first.finalize().unwrap();
+        let witness = &signed.inputs[0].script_witness;
+        assert_eq!(witness.len(), 4);
+        assert!(witness[0].is_empty());
+        // The signatures are ordered as the public keys in the script.
+        assert_eq!(witness[1], psbt_signature(&signature));
+        assert!(pubkeys[2]
+            .verify(&sighash, &witness[2][..witness[2].len() - 1].into())
+            .unwrap());
+        assert_eq!(witness[3], witness_script.to_bytes());
+
+        // The witness script must be the one the spent output commits to.
+        let mut mismatched = psbt;
+        mismatched.inputs[0].witness_script = Some(Builder::build_multisig(1, &pubkeys).unwrap().to_bytes());
+        let error = mismatched.sign_input(0, &key_pairs[0]).unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::ScriptHashMismatch { input_index: 0 });
+        mismatched.inputs[0].partial_sigs = signed_psbt_sigs;
+        let error = mismatched.finalize().unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::ScriptHashMismatch { input_index: 0 });
+    }
+
+    fn psbt_signature(signature: &[u8]) -> Bytes {
+        let mut signature = signature.to_vec();
+        signature.push(SIGHASH_ALL as u8);
+        signature.into()
+    }
+
+    #[test]
+    fn test_psbt_deserialize_invalid() {
+        let error = Psbt::deserialize(b"psbt\x00\x00").unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::InvalidMagic);
+
+        // The global map doesn't contain the unsigned transaction.
+        let error = Psbt::deserialize(b"psbt\xff\x00").unwrap_err().into_inner();
+        assert!(matches!(error, PsbtError::ErrorDecoding(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn test_psbt_bip174_vectors() {
+        let vectors = [
+            BIP174_PSBT_P2PKH,
+            BIP174_PSBT_FINALIZED_INPUT,
+            BIP174_PSBT_SIGHASH_TYPE,
+            BIP174_PSBT_BIP32_DERIVATIONS,
+            BIP174_PSBT_P2SH_P2WSH_PARTIAL_SIG,
+            BIP174_PSBT_UNKNOWN_KEYS,
+        ];
+        for vector in vectors {
+            let bytes = hex::decode(vector).unwrap();
+            let psbt = Psbt::deserialize(&bytes).unwrap();
+            assert_eq!(psbt.serialize().unwrap(), bytes);
+        }
+
+        // The input spends a P2PKH output of a Segwit transaction, but isn't signed yet.
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_P2PKH).unwrap()).unwrap();
+        assert!(psbt.inputs[0].non_witness_utxo.as_ref().unwrap().has_witness());
+        let error = psbt.finalize().unwrap_err().into_inner();
+        assert_eq!(error, PsbtError::NotSigned(vec![0]));
+
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_FINALIZED_INPUT).unwrap()).unwrap();
+        assert!(psbt.inputs[0].final_script_sig.is_some());
+        assert!(psbt.inputs[1].witness_utxo.is_some());
+        assert!(psbt.inputs[1].redeem_script.is_some());
+
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_SIGHASH_TYPE).unwrap()).unwrap();
+        assert_eq!(psbt.inputs[0].sighash_type, Some(SIGHASH_ALL));
+
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_BIP32_DERIVATIONS).unwrap()).unwrap();
+        assert_eq!(psbt.outputs[0].bip32_derivation.len(), 1);
+        assert_eq!(psbt.outputs[1].bip32_derivation.len(), 1);
+
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_P2SH_P2WSH_PARTIAL_SIG).unwrap()).unwrap();
+        let input = &psbt.inputs[0];
+        assert_eq!(input.partial_sigs.len(), 1);
+        assert!(input.redeem_script.is_some());
+        assert!(input.witness_script.is_some());
+        assert_eq!(input.bip32_derivation.len(), 2);
+
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_UNKNOWN_KEYS).unwrap()).unwrap();
+        assert_eq!(psbt.inputs[0].other.unknown.len(), 1);
+        assert!(psbt.inputs[0].other.unknown.keys().all(|key| key.type_value == 0x0f));
+
+        // The output maps are cut off.
+        let mut bytes = hex::decode(BIP174_PSBT_P2PKH).unwrap();
+        bytes.pop();
+        let error = Psbt::deserialize(&bytes).unwrap_err().into_inner();
+        assert!(matches!(error, PsbtError::ErrorDecoding(_)), "{:?}", error);
+
+        // A network transaction instead of a PSBT.
+        let psbt = Psbt::deserialize(&hex::decode(BIP174_PSBT_P2PKH).unwrap()).unwrap();
+        let error = Psbt::deserialize(&serialize(&psbt.unsigned_tx))
+            .unwrap_err()
+            .into_inner();
+        assert_eq!(error, PsbtError::InvalidMagic);
+    }
+}
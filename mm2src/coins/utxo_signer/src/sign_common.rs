@@ -106,6 +106,20 @@ pub(crate) fn p2wpkh_spend_with_signature(
     }
 }
 
+/// Create TransactionInput spending P2TR output by the key path, adding script witness with the Schnorr signature.
+/// The signature isn't followed by a sighash type since `SIGHASH_DEFAULT` is used.
+pub(crate) fn p2tr_spend_with_signature(
+    unsigned_input: &UnsignedTransactionInput,
+    signature: Signature,
+) -> TransactionInput {
+    TransactionInput {
+        previous_output: unsigned_input.previous_output,
+        script_sig: Bytes::from(Vec::new()),
+        sequence: unsigned_input.sequence,
+        script_witness: vec![signature],
+    }
+}
+
 pub(crate) fn script_sig_with_pub(public_key: &PublicKey, fork_id: u32, signature: Signature) -> Bytes {
     let script_sig = script_sig(signature, fork_id);
     let builder = Builder::default();
@@ -1,10 +1,10 @@
 use crate::{UtxoSignTxError, UtxoSignTxResult};
 use chain::TransactionOutput;
 use crypto::trezor::utxo::TrezorOutputScriptType;
-use crypto::DerivationPath;
+use crypto::{DerivationPath, Secp256k1ExtendedPublicKey};
 use keys::{AddressFormat, Public as PublicKey};
 use mm2_err_handle::prelude::*;
-use script::{SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+use script::{Script, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
 
 impl UtxoSignTxError {
     fn no_param(param: &str) -> UtxoSignTxError {
@@ -16,6 +16,7 @@ impl UtxoSignTxError {
 }
 
 /// An additional info of a spending input.
+#[derive(Clone)]
 pub enum SpendingInputInfo {
     P2PKH {
         address_derivation_path: DerivationPath,
@@ -25,11 +26,84 @@ pub enum SpendingInputInfo {
         address_derivation_path: DerivationPath,
         address_pubkey: PublicKey,
     },
-    // The fields are used to generate `trezor::proto::messages_bitcoin::MultisigRedeemScriptType`
-    // P2SH {}
+    P2TR {
+        address_derivation_path: DerivationPath,
+        address_pubkey: PublicKey,
+    },
+    /// `address_derivation_path` is the derivation path of the signer's own key of the multisig address.
+    P2SH {
+        address_derivation_path: DerivationPath,
+        multisig: MultisigInputInfo,
+    },
+    /// `address_derivation_path` is the derivation path of the signer's own key of the multisig address.
+    P2WSH {
+        address_derivation_path: DerivationPath,
+        multisig: MultisigInputInfo,
+    },
+}
+
+impl SpendingInputInfo {
+    pub fn address_derivation_path(&self) -> &DerivationPath {
+        match self {
+            SpendingInputInfo::P2PKH {
+                address_derivation_path,
+                ..
+            }
+            | SpendingInputInfo::P2WPKH {
+                address_derivation_path,
+                ..
+            }
+            | SpendingInputInfo::P2TR {
+                address_derivation_path,
+                ..
+            }
+            | SpendingInputInfo::P2SH {
+                address_derivation_path,
+                ..
+            }
+            | SpendingInputInfo::P2WSH {
+                address_derivation_path,
+                ..
+            } => address_derivation_path,
+        }
+    }
+
+    /// Returns the public key of a single-key input, or `None` if the input spends a multisig output.
+    pub fn address_pubkey(&self) -> Option<&PublicKey> {
+        match self {
+            SpendingInputInfo::P2PKH { address_pubkey, .. }
+            | SpendingInputInfo::P2WPKH { address_pubkey, .. }
+            | SpendingInputInfo::P2TR { address_pubkey, .. } => Some(address_pubkey),
+            SpendingInputInfo::P2SH { .. } | SpendingInputInfo::P2WSH { .. } => None,
+        }
+    }
+}
+
+/// A cosigner of a multisig address.
+#[derive(Clone)]
+pub struct MultisigCosigner {
+    /// The account extended public key of the cosigner.
+    pub xpub: Secp256k1ExtendedPublicKey,
+    /// The public key derived from `xpub` by the [`MultisigInputInfo::address_path`].
+    pub pubkey: PublicKey,
+}
+
+/// An additional info of an input spending an m-of-n multisig output.
+/// The fields are used to generate `trezor::proto::messages_bitcoin::MultisigRedeemScriptType`.
+#[derive(Clone)]
+pub struct MultisigInputInfo {
+    /// The number of signatures required to spend the output.
+    pub required: usize,
+    /// The cosigners in the order their public keys are pushed to the multisig script.
+    pub cosigners: Vec<MultisigCosigner>,
+    /// The non-hardened path from the account extended public keys to the address public keys: `[chain, address_id]`.
+    pub address_path: Vec<u32>,
+    /// The redeem script of a P2SH output or the witness script of a P2WSH output.
+    pub script: Script,
 }
 
 /// Either plain destination address or derivation path of a change address.
+#[derive(Clone)]
 pub enum OutputDestination {
     Plain {
         address: String,
@@ -53,6 +127,7 @@ impl OutputDestination {
 }
 
 /// An additional info of a sending output.
+#[derive(Clone)]
 pub struct SendingOutputInfo {
     pub destination_address: OutputDestination,
 }
@@ -156,6 +231,7 @@ impl UtxoSignTxParamsBuilder {
     }
 }
 
+#[derive(Clone)]
 pub struct UtxoSignTxParams {
     pub(crate) signature_version: SignatureVersion,
     pub(crate) unsigned_tx: TransactionInputSigner,
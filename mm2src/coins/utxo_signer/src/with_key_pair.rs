@@ -1,17 +1,20 @@
 use crate::sign_common::{complete_tx, p2pk_spend_with_signature, p2pkh_spend_with_signature,
-                         p2sh_spend_with_signature, p2wpkh_spend_with_signature};
+                         p2sh_spend_with_signature, p2tr_spend_with_signature, p2wpkh_spend_with_signature};
 use crate::Signature;
 use chain::{Transaction as UtxoTx, TransactionInput};
 use derive_more::Display;
 use keys::bytes::Bytes;
-use keys::KeyPair;
+use keys::{sign_taproot_key_path, AddressHashEnum, KeyPair};
 use mm2_err_handle::prelude::*;
 use primitives::hash::H256;
 use script::{Builder, Script, ScriptType, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
 
+/// BIP-341 default sighash type, it's equivalent to `SIGHASH_ALL` but isn't appended to the signature.
+pub const SIGHASH_DEFAULT: u32 = 0;
 pub const SIGHASH_ALL: u32 = 1;
 pub const _SIGHASH_NONE: u32 = 2;
 pub const SIGHASH_SINGLE: u32 = 3;
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
 
 pub type UtxoSignWithKeyPairResult<T> = Result<T, MmError<UtxoSignWithKeyPairError>>;
 
@@ -56,6 +59,7 @@ pub fn sign_tx(
         .map(|(i, input)| {
             match input.prev_script.script_type() {
                 ScriptType::WitnessKey => p2wpkh_spend(&unsigned, i, key_pair, SignatureVersion::WitnessV0, fork_id),
+                ScriptType::Taproot => p2tr_spend(&unsigned, i, key_pair),
                 ScriptType::PubKeyHash => p2pkh_spend(&unsigned, i, key_pair, signature_version, fork_id),
                 // Allow spending legacy P2PK utxos.
                 ScriptType::PubKey => p2pk_spend(&unsigned, i, key_pair, signature_version, fork_id),
@@ -202,6 +206,40 @@ pub fn p2wpkh_spend(
     ))
 }
 
+/// Creates signed input spending p2tr output using the key path.
+/// The output key is expected to be tweaked from the `key_pair` public key without a script tree (BIP-86).
+pub fn p2tr_spend(
+    signer: &TransactionInputSigner,
+    input_index: usize,
+    key_pair: &KeyPair,
+) -> UtxoSignWithKeyPairResult<TransactionInput> {
+    let unsigned_input = get_input(signer, input_index)?;
+
+    let output_key = keys::taproot_output_key(key_pair.public())?;
+    let script_pub_key = Builder::build_p2tr(&AddressHashEnum::TaprootOutputKey(output_key))?;
+    if script_pub_key != unsigned_input.prev_script {
+        return MmError::err(UtxoSignWithKeyPairError::MismatchScript {
+            script_type: "P2TR".to_owned(),
+            script: script_pub_key,
+            prev_script: unsigned_input.prev_script.clone(),
+        });
+    }
+
+    let sighash = signature_hash_to_sign(
+        signer,
+        input_index,
+        &script_pub_key,
+        SignatureVersion::Taproot,
+        SIGHASH_DEFAULT,
+        0,
+    )?;
+    let signature = sign_taproot_key_path(key_pair.private(), &sighash)?;
+    Ok(p2tr_spend_with_signature(
+        unsigned_input,
+        Bytes::from(signature.to_vec()),
+    ))
+}
+
 /// Calculates the input script hash and sign it using `key_pair`.
 pub fn calc_and_sign_sighash(
     signer: &TransactionInputSigner,
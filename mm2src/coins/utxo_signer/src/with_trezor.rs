@@ -1,10 +1,11 @@
-use crate::sign_common::{complete_tx, p2pkh_spend_with_signature, p2wpkh_spend_with_signature};
-use crate::sign_params::{OutputDestination, SendingOutputInfo, SpendingInputInfo, UtxoSignTxParams};
+use crate::sign_common::{complete_tx, p2pkh_spend_with_signature, p2tr_spend_with_signature,
+                         p2wpkh_spend_with_signature};
+use crate::sign_params::{MultisigInputInfo, OutputDestination, SendingOutputInfo, SpendingInputInfo, UtxoSignTxParams};
 use crate::{TxProvider, UtxoSignTxError, UtxoSignTxResult};
 use chain::{Transaction as UtxoTx, TransactionOutput};
 use common::log::debug;
-use crypto::trezor::utxo::{PrevTx, PrevTxInput, PrevTxOutput, TrezorInputScriptType, TxOutput, TxSignResult,
-                           UnsignedTxInput, UnsignedUtxoTx};
+use crypto::trezor::utxo::{PrevTx, PrevTxInput, PrevTxOutput, TrezorInputScriptType, TrezorMultisig, TxOutput,
+                           TxSignResult, UnsignedTxInput, UnsignedUtxoTx};
 use crypto::trezor::TrezorSession;
 use keys::bytes::Bytes;
 use mm2_err_handle::prelude::*;
@@ -23,20 +24,17 @@ pub struct TrezorTxSigner<'a, TxP> {
 
 impl<'a, TxP: TxProvider + Send + Sync> TrezorTxSigner<'a, TxP> {
     pub async fn sign_tx(mut self) -> UtxoSignTxResult<UtxoTx> {
-        let trezor_unsigned_tx = self.get_trezor_unsigned_tx().await?;
-
-        let TxSignResult {
-            signatures,
-            serialized_tx,
-        } = self.trezor.sign_utxo_tx(trezor_unsigned_tx).await?;
-        debug!("Transaction signed by Trezor: {}", hex::encode(serialized_tx));
-        if signatures.len() != self.params.inputs_count() {
-            return MmError::err(UtxoSignTxError::InvalidSignaturesNumber {
-                actual: signatures.len(),
-                expected: self.params.inputs_count(),
-            });
+        // A multisig input requires the signatures of the other cosigners, so the transaction can't be completed here.
+        if let Some(script) = self.params.inputs_infos.iter().find_map(|input_info| match input_info {
+            SpendingInputInfo::P2SH { multisig, .. } | SpendingInputInfo::P2WSH { multisig, .. } => {
+                Some(multisig.script.clone())
+            },
+            _ => None,
+        }) {
+            return MmError::err(UtxoSignTxError::UnspendableUTXO { script });
         }
 
+        let signatures = self.request_signatures().await?;
         let signed_inputs = self
             .params
             .inputs()
@@ -48,11 +46,39 @@ impl<'a, TxP: TxProvider + Send + Sync> TrezorTxSigner<'a, TxP> {
                 SpendingInputInfo::P2WPKH { address_pubkey, .. } => {
                     p2wpkh_spend_with_signature(unsigned_input, address_pubkey, self.fork_id, Bytes::from(signature))
                 },
+                SpendingInputInfo::P2TR { .. } => p2tr_spend_with_signature(unsigned_input, Bytes::from(signature)),
+                SpendingInputInfo::P2SH { .. } | SpendingInputInfo::P2WSH { .. } => {
+                    unreachable!("Multisig inputs are checked above")
+                },
             })
             .collect();
         Ok(complete_tx(self.params.unsigned_tx, signed_inputs))
     }
 
+    /// Signs every input of the transaction and returns the DER encoded signatures without the sighash type.
+    /// Unlike [`TrezorTxSigner::sign_tx`], it allows to sign the inputs spending multisig outputs.
+    pub async fn signatures(mut self) -> UtxoSignTxResult<Vec<Bytes>> {
+        let signatures = self.request_signatures().await?;
+        Ok(signatures.into_iter().map(Bytes::from).collect())
+    }
+
+    async fn request_signatures(&mut self) -> UtxoSignTxResult<Vec<Vec<u8>>> {
+        let trezor_unsigned_tx = self.get_trezor_unsigned_tx().await?;
+
+        let TxSignResult {
+            signatures,
+            serialized_tx,
+        } = self.trezor.sign_utxo_tx(trezor_unsigned_tx).await?;
+        debug!("Transaction signed by Trezor: {}", hex::encode(serialized_tx));
+        if signatures.len() != self.params.inputs_count() {
+            return MmError::err(UtxoSignTxError::InvalidSignaturesNumber {
+                actual: signatures.len(),
+                expected: self.params.inputs_count(),
+            });
+        }
+        Ok(signatures)
+    }
+
     async fn get_trezor_unsigned_tx(&self) -> UtxoSignTxResult<UnsignedUtxoTx> {
         let mut inputs = Vec::with_capacity(self.params.unsigned_tx.inputs.len());
         for (unsigned_input, input_info) in self.params.inputs() {
@@ -101,13 +127,14 @@ impl<'a, TxP: TxProvider + Send + Sync> TrezorTxSigner<'a, TxP> {
         let prev_tx_hash_json = H256Json::from(unsigned_input.previous_output.hash.reversed());
         let prev_tx = self.get_trezor_prev_tx(&prev_tx_hash_json).await?;
 
-        let (address_derivation_path, input_script_type) = match input_info {
+        let (address_derivation_path, input_script_type, multisig) = match input_info {
             SpendingInputInfo::P2PKH {
                 address_derivation_path,
                 ..
             } => (
                 Some(address_derivation_path.clone()),
                 TrezorInputScriptType::SpendAddress,
+                None,
             ),
             SpendingInputInfo::P2WPKH {
                 address_derivation_path,
@@ -115,6 +142,24 @@ impl<'a, TxP: TxProvider + Send + Sync> TrezorTxSigner<'a, TxP> {
             } => (
                 Some(address_derivation_path.clone()),
                 TrezorInputScriptType::SpendWitness,
+                None,
+            ),
+            SpendingInputInfo::P2TR { .. } => return MmError::err(UtxoSignTxError::TrezorDoesntSupportP2TR),
+            SpendingInputInfo::P2SH {
+                address_derivation_path,
+                multisig,
+            } => (
+                Some(address_derivation_path.clone()),
+                TrezorInputScriptType::SpendMultiSig,
+                Some(trezor_multisig(multisig)),
+            ),
+            SpendingInputInfo::P2WSH {
+                address_derivation_path,
+                multisig,
+            } => (
+                Some(address_derivation_path.clone()),
+                TrezorInputScriptType::SpendWitness,
+                Some(trezor_multisig(multisig)),
             ),
         };
 
@@ -126,6 +171,7 @@ impl<'a, TxP: TxProvider + Send + Sync> TrezorTxSigner<'a, TxP> {
             sequence: unsigned_input.sequence,
             input_script_type,
             amount: unsigned_input.amount,
+            multisig,
         })
     }
 
@@ -208,3 +254,15 @@ impl<'a, TxP: TxProvider + Send + Sync> TrezorTxSigner<'a, TxP> {
     /// https://github.com/trezor/trezor-utxo-lib/blob/trezor/src/coins.js#L55
     fn is_zcash_type(&self) -> bool { matches!(self.trezor_coin.as_str(), "Komodo" | "Zcash" | "Zcash Testnet") }
 }
+
+fn trezor_multisig(multisig: &MultisigInputInfo) -> TrezorMultisig {
+    TrezorMultisig {
+        required: multisig.required as u32,
+        xpubs: multisig
+            .cosigners
+            .iter()
+            .map(|cosigner| cosigner.xpub.clone())
+            .collect(),
+        address_path: multisig.address_path.clone(),
+    }
+}
@@ -28,22 +28,26 @@ use crate::z_coin::storage::{BlockDbImpl, WalletDbShared};
 
 use crate::z_coin::z_tx_history::{fetch_tx_history_from_db, ZCoinTxHistoryItem};
 use crate::{BalanceError, BalanceFut, CheckIfMyPaymentSentArgs, CoinBalance, ConfirmPaymentInput, DexFee,
-            FeeApproxStage, FoundSwapTxSpend, HistorySyncState, MarketCoinOps, MmCoin, NegotiateSwapContractAddrErr,
-            NumConversError, PrivKeyActivationPolicy, PrivKeyBuildPolicy, PrivKeyPolicyNotAllowed, RawTransactionFut,
-            RawTransactionRequest, RawTransactionResult, RefundPaymentArgs, SearchForSwapTxSpendInput,
-            SendPaymentArgs, SignRawTransactionRequest, SignatureError, SignatureResult, SpendPaymentArgs, SwapOps,
-            TradeFee, TradePreimageFut, TradePreimageResult, TradePreimageValue, Transaction, TransactionData,
-            TransactionDetails, TransactionEnum, TransactionResult, TxFeeDetails, TxMarshalingErr,
+            FeeApproxStage, FoundSwapTxSpend, HistorySyncState, MarketCoinOps, MmCoin, MmCoinEnum,
+            NegotiateSwapContractAddrErr, NumConversError, PrivKeyActivationPolicy, PrivKeyBuildPolicy,
+            PrivKeyPolicyNotAllowed, RawTransactionFut, RawTransactionRequest, RawTransactionResult,
+            RefundPaymentArgs, SearchForSwapTxSpendInput, SendMakerPaymentSpendPreimageInput, SendPaymentArgs,
+            SignRawTransactionRequest, SignatureError, SignatureResult, SpendPaymentArgs, SwapOps, TradeFee,
+            TradePreimageFut, TradePreimageResult, TradePreimageValue, Transaction, TransactionData,
+            TransactionDetails, TransactionEnum, TransactionFut, TransactionResult, TxFeeDetails, TxMarshalingErr,
             UnexpectedDerivationMethod, ValidateAddressResult, ValidateFeeArgs, ValidateOtherPubKeyErr,
-            ValidatePaymentError, ValidatePaymentInput, VerificationError, VerificationResult, WaitForHTLCTxSpendArgs,
-            WatcherOps, WeakSpawner, WithdrawError, WithdrawFut, WithdrawRequest};
+            ValidatePaymentError, ValidatePaymentFut, ValidatePaymentInput, ValidateWatcherSpendInput,
+            VerificationError, VerificationResult, WaitForHTLCTxSpendArgs, WatcherOps, WatcherReward,
+            WatcherRewardError, WatcherSearchForSwapTxSpendInput, WatcherValidatePaymentInput,
+            WatcherValidateTakerFeeInput, WeakSpawner, WithdrawError, WithdrawFut, WithdrawRequest,
+            EARLY_CONFIRMATION_ERR_LOG, INVALID_RECEIVER_ERR_LOG, OLD_TRANSACTION_ERR_LOG};
 
 use async_trait::async_trait;
 use bitcrypto::dhash256;
 use chain::constants::SEQUENCE_FINAL;
 use chain::{Transaction as UtxoTx, TransactionOutput};
 use common::executor::{AbortableSystem, AbortedError};
-use common::{calc_total_pages, log};
+use common::{calc_total_pages, log, now_sec_u32};
 use crypto::privkey::{key_pair_from_secret, secp_privkey_from_hash};
 use crypto::HDPathToCoin;
 use crypto::{Bip32DerPathOps, GlobalHDAccountArc};
@@ -691,6 +695,58 @@ impl ZCoin {
         }
         Ok(true)
     }
+
+    /// Validates the taker fee on behalf of a watcher.
+    /// The watcher knows neither the swap uuid (memo) nor the fee amount, and the shielded inputs
+    /// can't be attributed to the taker pubkey, so only the fee recipient, the confirmation height
+    /// and the age of the transaction are checked.
+    async fn watcher_validate_dex_fee(&self, input: WatcherValidateTakerFeeInput) -> ValidatePaymentResult<()> {
+        let taker_fee_hash: [u8; 32] = input.taker_fee_hash.as_slice().try_into().map_to_mm(|_| {
+            ValidatePaymentError::InternalError(format!(
+                "Invalid taker_fee_hash length: expected 32 bytes, got {} bytes",
+                input.taker_fee_hash.len()
+            ))
+        })?;
+        let tx_from_rpc = self
+            .utxo_rpc_client()
+            .get_verbose_transaction(&taker_fee_hash.into())
+            .compat()
+            .await
+            .map_err(|e| MmError::new(ValidatePaymentError::InvalidRpcResponse(e.into_inner().to_string())))?;
+        let z_tx = ZTransaction::read(tx_from_rpc.hex.0.as_slice())
+            .map_to_mm(|e| ValidatePaymentError::TxDeserializationError(e.to_string()))?;
+
+        let block_height = match tx_from_rpc.height {
+            Some(h) if h <= input.min_block_number => {
+                return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                    "{}: Fee tx {:?} confirmed before min_block {}",
+                    EARLY_CONFIRMATION_ERR_LOG, tx_from_rpc, input.min_block_number
+                )))
+            },
+            Some(h) => BlockHeight::from_u32(h as u32),
+            None => H0,
+        };
+
+        // `time` is only known once the transaction is mined, a mempool transaction is fresh by definition.
+        if tx_from_rpc.time != 0 && now_sec_u32().saturating_sub(tx_from_rpc.time) > input.lock_duration as u32 {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: Taker fee {:?} is too old",
+                OLD_TRANSACTION_ERR_LOG, tx_from_rpc
+            )));
+        }
+
+        let pays_dex_fee_addr = z_tx.shielded_outputs.iter().any(|shielded_out| {
+            try_sapling_output_recovery(self.consensus_params_ref(), block_height, &DEX_FEE_OVK, shielded_out)
+                .map_or(false, |(_, address, _)| address == self.z_fields.dex_fee_addr)
+        });
+        if !pays_dex_fee_addr {
+            return MmError::err(ValidatePaymentError::WrongPaymentTx(format!(
+                "{}: The dex fee tx {:?} has no shielded output to the dex fee address",
+                INVALID_RECEIVER_ERR_LOG, tx_from_rpc
+            )));
+        }
+        Ok(())
+    }
 }
 
 impl AsRef<UtxoCoinFields> for ZCoin {
@@ -1024,6 +1080,7 @@ impl<'a> ZCoinBuilder<'a> {
             check_utxo_maturity: None,
             // This is not used for Zcoin so we just provide a default value
             path_to_address: HDPathAccountToAddressId::default(),
+            multisig: None,
         };
         ZCoinBuilder {
             ctx,
@@ -1595,10 +1652,119 @@ impl SwapOps for ZCoin {
     fn validate_other_pubkey(&self, raw_pubkey: &[u8]) -> MmResult<(), ValidateOtherPubKeyErr> {
         utxo_common::validate_other_pubkey(raw_pubkey)
     }
+
+    fn is_supported_by_watchers(&self) -> bool { true }
 }
 
+/// The swap HTLCs are transparent P2SH outputs, so the preimages are built and validated by the UTXO
+/// watcher functions. Unlike the Sapling builder used by `z_p2sh_spend`, they leave `nExpiryHeight` at 0,
+/// so the signed preimages don't expire while the watcher waits for the lock time.
+/// The spent or refunded funds are paid to the transparent address of the coin.
 #[async_trait]
-impl WatcherOps for ZCoin {}
+impl WatcherOps for ZCoin {
+    #[inline]
+    fn create_taker_payment_refund_preimage(
+        &self,
+        taker_tx: &[u8],
+        time_lock: u64,
+        maker_pub: &[u8],
+        secret_hash: &[u8],
+        _swap_contract_address: &Option<BytesJson>,
+        swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        utxo_common::create_taker_payment_refund_preimage(
+            self,
+            taker_tx,
+            try_tx_fus!(time_lock.try_into()),
+            maker_pub,
+            secret_hash,
+            swap_unique_data,
+        )
+    }
+
+    #[inline]
+    fn create_maker_payment_spend_preimage(
+        &self,
+        maker_payment_tx: &[u8],
+        time_lock: u64,
+        maker_pub: &[u8],
+        secret_hash: &[u8],
+        swap_unique_data: &[u8],
+    ) -> TransactionFut {
+        utxo_common::create_maker_payment_spend_preimage(
+            self,
+            maker_payment_tx,
+            try_tx_fus!(time_lock.try_into()),
+            maker_pub,
+            secret_hash,
+            swap_unique_data,
+        )
+    }
+
+    #[inline]
+    fn send_taker_payment_refund_preimage(&self, refund_payment_args: RefundPaymentArgs) -> TransactionFut {
+        utxo_common::send_taker_payment_refund_preimage(self, refund_payment_args)
+    }
+
+    #[inline]
+    fn send_maker_payment_spend_preimage(&self, input: SendMakerPaymentSpendPreimageInput) -> TransactionFut {
+        utxo_common::send_maker_payment_spend_preimage(self, input)
+    }
+
+    fn watcher_validate_taker_fee(&self, input: WatcherValidateTakerFeeInput) -> ValidatePaymentFut<()> {
+        let coin = self.clone();
+        let fut = async move { coin.watcher_validate_dex_fee(input).await };
+        Box::new(fut.boxed().compat())
+    }
+
+    /// The taker payment is funded from shielded notes, so it has no transparent inputs
+    /// the sender check could be applied to.
+    #[inline]
+    fn watcher_validate_taker_payment(&self, input: WatcherValidatePaymentInput) -> ValidatePaymentFut<()> {
+        utxo_common::watcher_validate_taker_payment(self, input, utxo_common::DEFAULT_SWAP_VOUT)
+    }
+
+    #[inline]
+    fn taker_validates_payment_spend_or_refund(&self, input: ValidateWatcherSpendInput) -> ValidatePaymentFut<()> {
+        utxo_common::validate_payment_spend_or_refund(self, input, utxo_common::DEFAULT_SWAP_VOUT)
+    }
+
+    #[inline]
+    async fn watcher_search_for_swap_tx_spend(
+        &self,
+        input: WatcherSearchForSwapTxSpendInput<'_>,
+    ) -> Result<Option<FoundSwapTxSpend>, String> {
+        utxo_common::watcher_search_for_swap_tx_spend(self, input, utxo_common::DEFAULT_SWAP_VOUT).await
+    }
+
+    async fn get_taker_watcher_reward(
+        &self,
+        other_coin: &MmCoinEnum,
+        coin_amount: Option<BigDecimal>,
+        other_coin_amount: Option<BigDecimal>,
+        reward_amount: Option<BigDecimal>,
+        wait_until: u64,
+    ) -> Result<WatcherReward, MmError<WatcherRewardError>> {
+        utxo_common::get_taker_watcher_reward(
+            self,
+            other_coin,
+            coin_amount,
+            other_coin_amount,
+            reward_amount,
+            wait_until,
+        )
+        .await
+    }
+
+    async fn get_maker_watcher_reward(
+        &self,
+        _other_coin: &MmCoinEnum,
+        _reward_amount: Option<BigDecimal>,
+        _wait_until: u64,
+    ) -> Result<Option<WatcherReward>, MmError<WatcherRewardError>> {
+        Ok(None)
+    }
+}
 
 #[async_trait]
 impl MmCoin for ZCoin {
@@ -1900,6 +2066,7 @@ impl InitWithdrawCoin for ZCoin {
                 "Withdraw from a specific address is not supported for ZCoin yet".to_owned(),
             ));
         }
+        req.ensure_no_utxo_specific_fields(self.ticker())?;
 
         let to_addr = decode_payment_address(z_mainnet_constants::HRP_SAPLING_PAYMENT_ADDRESS, &req.to)
             .map_to_mm(|e| WithdrawError::InvalidAddress(format!("{}", e)))?
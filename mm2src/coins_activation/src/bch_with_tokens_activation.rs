@@ -345,6 +345,7 @@ impl PlatformCoinWithTokensActivationOps for BchCoin {
         ctx: MmArc,
         storage: impl TxHistoryStorage + Send + 'static,
         initial_balance: Option<BigDecimal>,
+        _activation_request: &Self::ActivationRequest,
     ) {
         let fut = bch_and_slp_history_loop(
             self.clone(),
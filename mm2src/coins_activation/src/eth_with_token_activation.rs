@@ -9,6 +9,7 @@ use crate::platform_coin_with_tokens::{platform_coin_xpub_extractor_rpc_statuses
 use crate::prelude::*;
 use async_trait::async_trait;
 use coins::coin_balance::{CoinBalanceReport, EnableCoinBalanceOps};
+use coins::eth::eth_tx_history_v2::eth_history_loop;
 use coins::eth::v2_activation::{eth_coin_from_conf_and_request_v2, Erc20Protocol, Erc20TokenActivationRequest,
                                 EthActivationV2Error, EthActivationV2Request, EthPrivKeyActivationPolicy};
 use coins::eth::v2_activation::{EthTokenActivationError, NftActivationRequest, NftProviderEnum};
@@ -20,6 +21,7 @@ use coins::{CoinBalance, CoinBalanceMap, CoinProtocol, CoinWithDerivationMethod,
             MmCoin, MmCoinEnum};
 
 use crate::platform_coin_with_tokens::InitPlatformCoinWithTokensTask;
+use common::executor::{AbortSettings, SpawnAbortable};
 use common::Future01CompatExt;
 use common::{drop_mutability, true_f};
 use crypto::HwRpcError;
@@ -169,10 +171,18 @@ pub struct EthWithTokensActivationRequest {
     #[serde(default = "true_f")]
     pub get_balances: bool,
     nft_req: Option<NftActivationRequest>,
+    #[serde(default)]
+    tx_history: bool,
+    /// The block to start scanning the tx history from if the history is empty.
+    /// If not set, the history starts from the current block.
+    tx_history_start_block: Option<u64>,
+    /// The number of confirmations a block needs to be scanned for the tx history.
+    /// If not set, [`coins::eth::eth_tx_history_v2::ETH_HISTORY_DEFAULT_CONFIRMATIONS`] is used.
+    tx_history_confirmations: Option<u64>,
 }
 
 impl TxHistory for EthWithTokensActivationRequest {
-    fn tx_history(&self) -> bool { false }
+    fn tx_history(&self) -> bool { self.tx_history }
 }
 
 impl ActivationRequestInfo for EthWithTokensActivationRequest {
@@ -439,10 +449,21 @@ impl PlatformCoinWithTokensActivationOps for EthCoin {
 
     fn start_history_background_fetching(
         &self,
-        _ctx: MmArc,
-        _storage: impl TxHistoryStorage + Send + 'static,
+        ctx: MmArc,
+        storage: impl TxHistoryStorage + Send + 'static,
         _initial_balance: Option<BigDecimal>,
+        activation_request: &Self::ActivationRequest,
     ) {
+        let fut = eth_history_loop(
+            self.clone(),
+            storage,
+            ctx.event_stream_manager.clone(),
+            activation_request.tx_history_start_block,
+            activation_request.tx_history_confirmations,
+        );
+
+        let settings = AbortSettings::info_on_abort(format!("eth_history_loop stopped for {}", self.ticker()));
+        self.spawner().spawn_with_settings(fut, settings);
     }
 
     fn rpc_task_manager(
@@ -218,6 +218,7 @@ pub trait PlatformCoinWithTokensActivationOps: Into<MmCoinEnum> + Clone + Send +
         ctx: MmArc,
         storage: impl TxHistoryStorage,
         initial_balance: Option<BigDecimal>,
+        activation_request: &Self::ActivationRequest,
     );
 
     fn rpc_task_manager(activation_ctx: &CoinsActivationContext) -> &InitPlatformCoinWithTokensTaskManagerShared<Self>
@@ -479,6 +480,7 @@ where
             ctx.clone(),
             TxHistoryStorageBuilder::new(&ctx).build()?,
             activation_result.get_platform_balance(),
+            &req.request,
         );
     }
 
@@ -7,10 +7,12 @@ use async_trait::async_trait;
 use coins::coin_balance::{CoinBalanceReport, IguanaWalletBalance};
 use coins::coin_errors::MyAddressError;
 use coins::my_tx_history_v2::TxHistoryStorage;
+use coins::siacoin::sia_tx_history::sia_history_loop;
 use coins::siacoin::{sia_coin_from_conf_and_params, SiaCoin, SiaCoinActivationParams, SiaCoinBuildError,
                      SiaCoinProtocolInfo};
 use coins::tx_history_storage::CreateTxHistoryStorageError;
-use coins::{BalanceError, CoinBalance, CoinProtocol, MarketCoinOps, PrivKeyBuildPolicy, RegisterCoinError};
+use coins::{BalanceError, CoinBalance, CoinProtocol, MarketCoinOps, MmCoin, PrivKeyBuildPolicy, RegisterCoinError};
+use common::executor::{AbortSettings, SpawnAbortable};
 use crypto::hw_rpc_task::{HwRpcTaskAwaitingStatus, HwRpcTaskUserAction};
 use crypto::CryptoCtxError;
 use derive_more::Display;
@@ -234,13 +236,16 @@ impl InitStandaloneCoinActivationOps for SiaCoin {
         })
     }
 
-    /// Transaction history is fetching from a wallet database for `SiaCoin`.
     fn start_history_background_fetching(
         &self,
         _metrics: MetricsArc,
-        _storage: impl TxHistoryStorage,
-        _streaming_manager: StreamingManager,
+        storage: impl TxHistoryStorage,
+        streaming_manager: StreamingManager,
         _current_balances: HashMap<String, BigDecimal>,
     ) {
+        let fut = sia_history_loop(self.clone(), storage, streaming_manager);
+
+        let settings = AbortSettings::info_on_abort(format!("sia_history_loop stopped for {}", self.ticker()));
+        self.spawner().spawn_with_settings(fut, settings);
     }
 }
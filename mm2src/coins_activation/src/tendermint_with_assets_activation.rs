@@ -366,6 +366,7 @@ impl PlatformCoinWithTokensActivationOps for TendermintCoin {
         ctx: MmArc,
         storage: impl TxHistoryStorage,
         initial_balance: Option<BigDecimal>,
+        _activation_request: &Self::ActivationRequest,
     ) {
         let fut = tendermint_history_loop(self.clone(), storage, ctx, initial_balance);
 
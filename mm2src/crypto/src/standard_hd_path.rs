@@ -8,7 +8,8 @@ use std::convert::TryFrom;
 
 /// Standard HD Path for [BIP-44](https://github.com/bitcoin/bips/blob/master/bip-0044.mediawiki),
 /// [BIP-49](https://github.com/bitcoin/bips/blob/master/bip-0049.mediawiki),
-/// [BIP-84](https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki)
+/// [BIP-84](https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki),
+/// [BIP-86](https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki)
 /// and similar.
 /// For path as `m/purpose'/coin_type'/account'/change/address_index`.
 #[rustfmt::skip]
@@ -186,6 +187,7 @@ pub enum Bip43Purpose {
     Bip44 = 44,
     Bip49 = 49,
     Bip84 = 84,
+    Bip86 = 86,
 }
 
 #[derive(Clone, PartialEq)]
@@ -196,7 +198,7 @@ pub struct Bip32PurposeValue {
 impl Bip32ChildValue for Bip32PurposeValue {
     type Value = Bip43Purpose;
 
-    /// `purpose` is always a hardened child as it's described in the BIP44/BIP49/BIP84 standards.
+    /// `purpose` is always a hardened child as it's described in the BIP44/BIP49/BIP84/BIP86 standards.
     fn hardened() -> bool { true }
 
     fn number(&self) -> u32 { self.purpose as u32 }
@@ -213,11 +215,12 @@ impl Bip32ChildValue for Bip32PurposeValue {
             44 => Bip43Purpose::Bip44,
             49 => Bip43Purpose::Bip49,
             84 => Bip43Purpose::Bip84,
+            86 => Bip43Purpose::Bip86,
             _chain => {
                 return Err(Bip32DerPathError::UnexpectedChildValue {
                     child_at,
                     actual: child_number.0,
-                    expected: "one of the following: 32, 44, 49, 84".to_string(),
+                    expected: "one of the following: 32, 44, 49, 84, 86".to_string(),
                 })
             },
         };
@@ -299,5 +302,8 @@ mod tests {
 
         let path = StandardHDPath::from_str("m/84'/141'/0'/0/0").unwrap();
         assert_eq!(path.purpose(), Bip43Purpose::Bip84);
+
+        let path = StandardHDPath::from_str("m/86'/0'/0'/0/0").unwrap();
+        assert_eq!(path.purpose(), Bip43Purpose::Bip86);
     }
 }
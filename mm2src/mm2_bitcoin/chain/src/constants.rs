@@ -7,6 +7,14 @@ pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1u32 << 31;
 // disables nLockTime.
 pub const SEQUENCE_FINAL: u32 = 0xffffffff;
 
+// A transaction is replaceable by a transaction paying a higher fee
+// if at least one of its inputs has nSequence below this value (BIP 125).
+pub const SEQUENCE_RBF_THRESHOLD: u32 = 0xfffffffe;
+
+// nSequence that signals replaceability (BIP 125) but keeps nLockTime enabled
+// and doesn't encode a relative lock-time (BIP 68).
+pub const SEQUENCE_RBF_ENABLED: u32 = 0xfffffffd;
+
 // If CTxIn::nSequence encodes a relative lock-time and this flag
 // is set, the relative lock-time has units of 512 seconds,
 // otherwise it specifies blocks with a granularity of 1.
@@ -2,7 +2,7 @@
 //! https://en.bitcoin.it/wiki/Protocol_documentation#tx
 
 use bytes::Bytes;
-use constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL};
+use constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_RBF_THRESHOLD};
 use crypto::{dhash256, sha256};
 #[cfg(not(target_arch = "wasm32"))]
 use ext_bitcoin::blockdata::transaction::{OutPoint as ExtOutpoint, Transaction as ExtTransaction, TxIn, TxOut};
@@ -72,6 +72,9 @@ impl TransactionInput {
 
     pub fn is_final(&self) -> bool { self.sequence == SEQUENCE_FINAL }
 
+    /// Whether the input opts in to the replace-by-fee (BIP 125).
+    pub fn signals_rbf(&self) -> bool { self.sequence < SEQUENCE_RBF_THRESHOLD }
+
     pub fn has_witness(&self) -> bool { !self.script_witness.is_empty() }
 }
 
@@ -316,6 +319,9 @@ impl Transaction {
 
     pub fn has_witness(&self) -> bool { self.inputs.iter().any(TransactionInput::has_witness) }
 
+    /// The transaction is replaceable if at least one of its inputs signals the replace-by-fee (BIP 125).
+    pub fn signals_rbf(&self) -> bool { self.inputs.iter().any(TransactionInput::signals_rbf) }
+
     pub fn total_spends(&self) -> u64 {
         let mut result = 0u64;
         for output in self.outputs.iter() {
@@ -621,6 +627,7 @@ impl Deserializable for Transaction {
 #[cfg(test)]
 mod tests {
     use super::{Bytes, ExtTransaction, OutPoint, Transaction, TransactionInput, TransactionOutput};
+    use constants::{SEQUENCE_RBF_ENABLED, SEQUENCE_RBF_THRESHOLD};
     use hash::{H256, H512};
     use hex::ToHex;
     use ser::{deserialize, serialize, serialize_with_flags, Serializable, SERIALIZE_TRANSACTION_WITNESS};
@@ -846,6 +853,19 @@ mod tests {
         assert_eq!(t.hash(), hash);
     }
 
+    #[test]
+    fn test_transaction_signals_rbf() {
+        let mut t: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+        assert!(!t.signals_rbf());
+
+        // `nSequence = 0xfffffffe` enables `nLockTime` but doesn't signal the replaceability.
+        t.inputs[0].sequence = SEQUENCE_RBF_THRESHOLD;
+        assert!(!t.signals_rbf());
+
+        t.inputs[0].sequence = SEQUENCE_RBF_ENABLED;
+        assert!(t.signals_rbf());
+    }
+
     #[test]
     fn test_transaction_serialized_len() {
         let raw_tx: &'static str = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000";
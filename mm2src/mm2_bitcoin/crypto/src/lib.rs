@@ -86,6 +86,18 @@ pub fn dhash160(input: &[u8]) -> H160 { ripemd160(&*sha256(input)) }
 #[inline]
 pub fn dhash256(input: &[u8]) -> H256 { sha256(&*sha256(input)) }
 
+/// BIP-340 tagged hash: `SHA256(SHA256(tag) || SHA256(tag) || input)`
+#[inline]
+pub fn tagged_hash(tag: &[u8], input: &[u8]) -> H256 {
+    let tag_hash = sha256(tag);
+    let mut hasher = Sha256::new();
+    hasher.update(&*tag_hash);
+    hasher.update(&*tag_hash);
+    hasher.update(input);
+    let array: [u8; 32] = hasher.finalize().into();
+    array.into()
+}
+
 /// SipHash-2-4
 #[inline]
 pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
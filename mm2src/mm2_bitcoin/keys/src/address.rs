@@ -41,6 +41,11 @@ pub enum AddressScriptType {
     /// as the scripthash, eg: bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3.
     /// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
     P2WSH,
+    /// Pay to Taproot
+    /// Segwit v1 output which begins with the human readable part followed by 1p followed by 58 base32 characters
+    /// as the output key, eg: bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki
+    P2TR,
 }
 
 #[derive(Clone, Debug, Default, Display, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -56,6 +61,10 @@ pub enum AddressFormat {
     /// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
     #[serde(rename = "segwit")]
     Segwit,
+    /// Taproot Address, a segwit v1 address encoded with bech32m
+    /// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+    #[serde(rename = "taproot")]
+    Taproot,
     /// Bitcoin Cash specific address format.
     /// https://github.com/bitcoincashorg/bitcoincash.org/blob/master/spec/cashaddr.md
     #[serde(rename = "cashaddress")]
@@ -72,6 +81,11 @@ pub enum AddressFormat {
 impl AddressFormat {
     pub fn is_segwit(&self) -> bool { matches!(*self, AddressFormat::Segwit) }
 
+    pub fn is_taproot(&self) -> bool { matches!(*self, AddressFormat::Taproot) }
+
+    /// Returns true if the addresses of this format are bech32/bech32m encoded witness programs.
+    pub fn is_witness(&self) -> bool { matches!(*self, AddressFormat::Segwit | AddressFormat::Taproot) }
+
     pub fn is_cashaddress(&self) -> bool { matches!(*self, AddressFormat::CashAddress { .. }) }
 
     pub fn is_legacy(&self) -> bool { matches!(*self, AddressFormat::Standard) }
@@ -163,7 +177,7 @@ impl Address {
             AddressFormat::Standard => {
                 Ok(LegacyAddress::new(&self.hash, self.prefix.clone(), self.checksum_type).to_string())
             },
-            AddressFormat::Segwit => match &self.hrp {
+            AddressFormat::Segwit | AddressFormat::Taproot => match &self.hrp {
                 Some(hrp) => Ok(SegwitAddress::new(&self.hash, hrp.clone()).to_string()),
                 None => Err("Cannot display segwit address for a coin with no bech32_hrp in config".into()),
             },
@@ -261,6 +275,19 @@ impl Address {
 
     pub fn from_segwitaddress(segaddr: &str, checksum_type: ChecksumType) -> Result<Address, String> {
         let address = SegwitAddress::from_str(segaddr).map_err(|e| e.to_string())?;
+        if address.version() == 1 {
+            let mut hash = AddressHashEnum::default_taproot_output_key();
+            hash.copy_from_slice(address.program.as_slice());
+            return Ok(Address {
+                prefix: AddressPrefix::default(),
+                hash,
+                checksum_type,
+                hrp: Some(address.hrp),
+                pubkey: None,
+                addr_format: AddressFormat::Taproot,
+                script_type: AddressScriptType::P2TR,
+            });
+        }
 
         let (script_type, mut hash) = if address.program.len() == 20 {
             (AddressScriptType::P2WPKH, AddressHashEnum::default_address_hash())
@@ -295,7 +322,7 @@ impl Address {
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.addr_format {
-            AddressFormat::Segwit => {
+            AddressFormat::Segwit | AddressFormat::Taproot => {
                 SegwitAddress::new(&self.hash, self.hrp.clone().expect("Segwit address should have an hrp")).fmt(f)
             },
             AddressFormat::CashAddress {
@@ -318,9 +345,10 @@ impl fmt::Display for Address {
 
 #[cfg(test)]
 mod tests {
-    use super::{Address, AddressBuilder, AddressFormat, AddressHashEnum, CashAddrType, CashAddress, ChecksumType};
+    use super::{Address, AddressBuilder, AddressFormat, AddressHashEnum, AddressScriptType, CashAddrType, CashAddress,
+                ChecksumType};
     use crate::address_prefixes::prefixes::*;
-    use crate::{NetworkAddressPrefixes, NetworkPrefix};
+    use crate::{NetworkAddressPrefixes, NetworkPrefix, Public};
 
     #[test]
     fn test_address_to_string() {
@@ -516,6 +544,41 @@ mod tests {
         assert_eq!(address.to_string(), "SVCbBs6FvPYxJrYoJc4TdCe47QNCgmTabv".to_owned());
     }
 
+    #[test]
+    // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+    fn test_taproot_address_from_pubkey() {
+        let internal_key =
+            Public::Compressed("02cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115".into());
+        let address = AddressBuilder::new(
+            AddressFormat::Taproot,
+            ChecksumType::DSHA256,
+            (*BTC_PREFIXES).clone(),
+            Some("bc".to_owned()),
+        )
+        .as_pkh_from_pk(internal_key)
+        .build()
+        .expect("valid address props");
+
+        let expected = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+        assert_eq!(address.to_string(), expected);
+        assert_eq!(address.script_type(), &AddressScriptType::P2TR);
+        assert_eq!(
+            address,
+            Address::from_segwitaddress(expected, ChecksumType::DSHA256).unwrap()
+        );
+
+        let error = AddressBuilder::new(
+            AddressFormat::Taproot,
+            ChecksumType::DSHA256,
+            (*BTC_PREFIXES).clone(),
+            Some("bc".to_owned()),
+        )
+        .as_sh(AddressHashEnum::default_witness_script_hash())
+        .build()
+        .expect_err("script path isn't supported");
+        assert_eq!(error, "invalid hash for taproot address");
+    }
+
     #[test]
     fn test_from_to_cashaddress() {
         let cashaddresses = vec![
@@ -1,4 +1,4 @@
-use crate::Public;
+use crate::{taproot_output_key, Public};
 use crypto::ChecksumType;
 
 use {Address, AddressFormat, AddressHashEnum, AddressPrefix, AddressScriptType, NetworkAddressPrefixes};
@@ -7,10 +7,12 @@ use {Address, AddressFormat, AddressHashEnum, AddressPrefix, AddressScriptType,
 #[derive(PartialEq)]
 pub enum AddressBuilderOption {
     /// build for pay to pubkey hash output (witness or legacy)
+    /// or for pay to taproot output if the hash is a taproot output key
     PubkeyHash(AddressHashEnum),
     /// build for pay to script hash output (witness or legacy)
     ScriptHash(AddressHashEnum),
     /// build for pay to pubkey hash but using a public key as an input (not pubkey hash)
+    /// or for pay to taproot output using the public key as the internal key
     FromPubKey(Public),
 }
 
@@ -93,6 +95,18 @@ impl AddressBuilder {
                     script_type: self.get_segwit_script_type(build_option),
                 })
             },
+            AddressFormat::Taproot => {
+                self.check_segwit_hrp()?;
+                Ok(Address {
+                    prefix: AddressPrefix::default(),
+                    hrp: self.hrp.clone(),
+                    hash: self.get_taproot_output_key(build_option)?,
+                    pubkey: self.get_pubkey(build_option),
+                    checksum_type: self.checksum_type,
+                    addr_format: self.addr_format.clone(),
+                    script_type: AddressScriptType::P2TR,
+                })
+            },
             AddressFormat::CashAddress { .. } => Ok(Address {
                 prefix: self.get_address_prefix(build_option)?,
                 hrp: None,
@@ -138,6 +152,17 @@ impl AddressBuilder {
         }
     }
 
+    /// Only the key path spending is supported, so a taproot address can't be built from a script hash.
+    fn get_taproot_output_key(&self, build_option: &AddressBuilderOption) -> Result<AddressHashEnum, String> {
+        match build_option {
+            AddressBuilderOption::PubkeyHash(hash) if hash.is_taproot_output_key() => Ok(hash.clone()),
+            AddressBuilderOption::FromPubKey(pubkey) => taproot_output_key(pubkey)
+                .map(AddressHashEnum::TaprootOutputKey)
+                .map_err(|e| e.to_string()),
+            _ => Err("invalid hash for taproot address".to_owned()),
+        }
+    }
+
     fn get_pubkey(&self, build_option: &AddressBuilderOption) -> Option<Public> {
         match build_option {
             AddressBuilderOption::FromPubKey(pubkey) => Some(*pubkey),
@@ -23,6 +23,7 @@ mod private;
 mod public;
 mod segwitaddress;
 mod signature;
+mod taproot;
 
 pub use primitives::{bytes, hash};
 
@@ -39,6 +40,7 @@ pub use private::Private;
 pub use public::Public;
 pub use segwitaddress::SegwitAddress;
 pub use signature::{CompactSignature, Signature};
+pub use taproot::{sign_taproot_key_path, taproot_output_key, verify_taproot_key_path, TAP_SIGHASH_TAG, TAP_TWEAK_TAG};
 
 use hash::{H160, H256};
 use lazy_static::lazy_static;
@@ -56,6 +58,8 @@ pub enum AddressHashEnum {
     AddressHash(H160),
     /// 32 bytes long hash derived from script `sha256(script)` used in P2WSH
     WitnessScriptHash(H256),
+    /// 32 bytes long x-only tweaked public key used in P2TR
+    TaprootOutputKey(H256),
 }
 
 impl AddressHashEnum {
@@ -63,10 +67,13 @@ impl AddressHashEnum {
 
     pub fn default_witness_script_hash() -> Self { AddressHashEnum::WitnessScriptHash(H256::default()) }
 
+    pub fn default_taproot_output_key() -> Self { AddressHashEnum::TaprootOutputKey(H256::default()) }
+
     pub fn copy_from_slice(&mut self, src: &[u8]) {
         match self {
             AddressHashEnum::AddressHash(h) => h.copy_from_slice(src),
             AddressHashEnum::WitnessScriptHash(s) => s.copy_from_slice(src),
+            AddressHashEnum::TaprootOutputKey(k) => k.copy_from_slice(src),
         }
     }
 
@@ -74,12 +81,15 @@ impl AddressHashEnum {
         match self {
             AddressHashEnum::AddressHash(h) => h.to_vec(),
             AddressHashEnum::WitnessScriptHash(s) => s.to_vec(),
+            AddressHashEnum::TaprootOutputKey(k) => k.to_vec(),
         }
     }
 
     pub fn is_address_hash(&self) -> bool { matches!(*self, AddressHashEnum::AddressHash(_)) }
 
     pub fn is_witness_script_hash(&self) -> bool { matches!(*self, AddressHashEnum::WitnessScriptHash(_)) }
+
+    pub fn is_taproot_output_key(&self) -> bool { matches!(*self, AddressHashEnum::TaprootOutputKey(_)) }
 }
 
 impl fmt::Display for AddressHashEnum {
@@ -87,6 +97,7 @@ impl fmt::Display for AddressHashEnum {
         match self {
             AddressHashEnum::AddressHash(h) => f.write_str(&h.to_string()),
             AddressHashEnum::WitnessScriptHash(s) => f.write_str(&s.to_string()),
+            AddressHashEnum::TaprootOutputKey(k) => f.write_str(&k.to_string()),
         }
     }
 }
@@ -25,6 +25,12 @@ pub enum Error {
     UnsupportedAddressVariant(String),
     /// A script version that is not supported yet was used.
     UnsupportedWitnessVersion(u8),
+    /// The checksum variant doesn't match the witness version,
+    /// see [BIP-0350](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki)
+    InvalidBech32Variant {
+        expected: bech32::Variant,
+        found: bech32::Variant,
+    },
 }
 
 impl fmt::Display for Error {
@@ -47,6 +53,13 @@ impl fmt::Display for Error {
             Error::UncompressedPubkey => write!(f, "an uncompressed pubkey was used where it is not allowed",),
             Error::UnsupportedAddressVariant(ref v) => write!(f, "address variant/format {} is not supported yet!", v),
             Error::UnsupportedWitnessVersion(v) => write!(f, "witness script version: {} is not supported yet!", v),
+            Error::InvalidBech32Variant { expected, found } => {
+                write!(
+                    f,
+                    "invalid bech32 checksum variant: expected {:?}, found {:?}",
+                    expected, found
+                )
+            },
         }
     }
 }
@@ -62,6 +75,17 @@ pub enum SegwitAddrType {
     P2wpkh,
     /// pay-to-witness-script-hash
     P2wsh,
+    /// pay-to-taproot
+    P2tr,
+}
+
+/// Returns the checksum variant that must be used for the witness `version`.
+fn bech32_variant(version: u8) -> bech32::Variant {
+    if version == 0 {
+        bech32::Variant::Bech32
+    } else {
+        bech32::Variant::Bech32m
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -76,14 +100,22 @@ pub struct SegwitAddress {
 }
 
 impl SegwitAddress {
+    /// Creates a v0 address for a pubkey/script hash or a v1 (taproot) address for a taproot output key.
     pub fn new(hash: &AddressHashEnum, hrp: String) -> SegwitAddress {
+        let version = match hash {
+            AddressHashEnum::AddressHash(_) | AddressHashEnum::WitnessScriptHash(_) => 0,
+            AddressHashEnum::TaprootOutputKey(_) => 1,
+        };
         SegwitAddress {
             hrp,
-            version: bech32::u5::try_from_u8(0).expect("0<32"),
+            version: bech32::u5::try_from_u8(version).expect("version<32"),
             program: hash.to_vec(),
         }
     }
 
+    /// Get the witness program version.
+    pub fn version(&self) -> u8 { self.version.to_u8() }
+
     /// Get the address type of the address.
     /// None if unknown or non-standard.
     pub fn address_type(&self) -> Option<SegwitAddrType> {
@@ -94,6 +126,8 @@ impl SegwitAddress {
                 32 => Some(SegwitAddrType::P2wsh),
                 _ => None,
             },
+            // BIP-341 p2tr address.
+            1 if self.program.len() == 32 => Some(SegwitAddrType::P2tr),
             _ => None,
         }
     }
@@ -128,7 +162,8 @@ impl fmt::Display for SegwitAddress {
         } else {
             fmt as &mut dyn fmt::Write
         };
-        let mut bech32_writer = bech32::Bech32Writer::new(self.hrp.as_str(), bech32::Variant::Bech32, writer)?;
+        let variant = bech32_variant(self.version.to_u8());
+        let mut bech32_writer = bech32::Bech32Writer::new(self.hrp.as_str(), variant, writer)?;
         bech32::WriteBase32::write_u5(&mut bech32_writer, self.version)?;
         bech32::ToBase32::write_base32(&self.program, &mut bech32_writer)
     }
@@ -138,18 +173,13 @@ impl FromStr for SegwitAddress {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<SegwitAddress, Error> {
-        // decode as bech32, should use Variant if Bech32m is used alongside Bech32
-        // The improved Bech32m variant described in [BIP-0350](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki)
+        // decode as bech32 or bech32m, v0 addresses must use Bech32 while v1+ addresses must use
+        // the improved Bech32m variant described in [BIP-0350](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki)
         // hrp checks (mixed case not allowed, allowed length and characters) are part of the decode function
         let (hrp, payload, variant) = bech32::decode(s)?;
         if payload.is_empty() {
             return Err(Error::EmptyBech32Payload);
         }
-        match variant {
-            bech32::Variant::Bech32 => (),
-            bech32::Variant::Bech32m => return Err(Error::UnsupportedAddressVariant("Bech32m".into())),
-            // Important: If a new variant is added we should return an error until we support the new variant
-        }
 
         // Get the script version and program (converted from 5-bit to 8-bit)
         let (version, program): (bech32::u5, Vec<u8>) = {
@@ -165,11 +195,23 @@ impl FromStr for SegwitAddress {
             return Err(Error::InvalidWitnessProgramLength(program.len()));
         }
 
-        // Specific segwit v0 check.
-        if version.to_u8() != 0 {
+        // Only segwit v0 and taproot (v1 with a 32 bytes long output key) are supported.
+        let is_taproot = version.to_u8() == 1 && program.len() == 32;
+        if version.to_u8() != 0 && !is_taproot {
             return Err(Error::UnsupportedWitnessVersion(version.to_u8()));
         }
 
+        let expected_variant = bech32_variant(version.to_u8());
+        if variant != expected_variant {
+            return Err(Error::InvalidBech32Variant {
+                expected: expected_variant,
+                found: variant,
+            });
+        }
+        if is_taproot {
+            return Ok(SegwitAddress { hrp, version, program });
+        }
+
         // Bech32 length check.
         // Important: we should be careful when using new program lengths since a valid Bech32 string can be modified according to
         // the below 2 links while still having a valid checksum.
@@ -228,6 +270,57 @@ mod tests {
         assert_eq!(addr.address_type(), Some(SegwitAddrType::P2wsh));
     }
 
+    #[test]
+    // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+    fn test_p2tr_address() {
+        let output_key = "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c".into();
+        let addr = SegwitAddress::new(&AddressHashEnum::TaprootOutputKey(output_key), "bc".to_string());
+        assert_eq!(
+            &addr.to_string(),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+        assert_eq!(addr.address_type(), Some(SegwitAddrType::P2tr));
+        assert_eq!(SegwitAddress::from_str(&addr.to_string()).unwrap(), addr);
+    }
+
+    #[test]
+    // https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#test-vectors-for-v0-v16-native-segregated-witness-addresses
+    fn test_valid_taproot() {
+        let addr = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+        let segwit_addr = SegwitAddress::from_str(addr).unwrap();
+        assert_eq!(1, segwit_addr.version());
+        assert_eq!(
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            segwit_addr.program.to_hex::<String>()
+        );
+        assert_eq!(segwit_addr.to_string(), addr);
+    }
+
+    #[test]
+    fn test_invalid_bech32_variant() {
+        let program = hex_to_bytes("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+
+        // Taproot address encoded with Bech32 instead of Bech32m
+        let mut data = vec![bech32::u5::try_from_u8(1).unwrap()];
+        data.extend(bech32::ToBase32::to_base32(&program));
+        let invalid_address = bech32::encode("bc", data, bech32::Variant::Bech32).unwrap();
+        let err = SegwitAddress::from_str(&invalid_address).unwrap_err();
+        assert_eq!(err, Error::InvalidBech32Variant {
+            expected: bech32::Variant::Bech32m,
+            found: bech32::Variant::Bech32,
+        });
+
+        // Segwit v0 address encoded with Bech32m instead of Bech32
+        let mut data = vec![bech32::u5::try_from_u8(0).unwrap()];
+        data.extend(bech32::ToBase32::to_base32(&program));
+        let invalid_address = bech32::encode("bc", data, bech32::Variant::Bech32m).unwrap();
+        let err = SegwitAddress::from_str(&invalid_address).unwrap_err();
+        assert_eq!(err, Error::InvalidBech32Variant {
+            expected: bech32::Variant::Bech32,
+            found: bech32::Variant::Bech32m,
+        });
+    }
+
     #[test]
     // https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki#test-vectors
     fn test_valid_segwit() {
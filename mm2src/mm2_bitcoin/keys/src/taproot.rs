@@ -0,0 +1,119 @@
+//! Taproot (BIP-341) key path helpers.
+//!
+//! Only the key path spending is supported, i.e. the output key commits to the internal key only without a script tree
+//! as it's described in [BIP-86](https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki).
+
+use crate::{SECP_SIGN, SECP_VERIFY};
+use crypto::tagged_hash;
+use hash::{H256, H512};
+use secp256k1::schnorrsig::{KeyPair as SchnorrKeyPair, PublicKey as XOnlyPublicKey, Signature as SchnorrSignature};
+use secp256k1::{Message as SecpMessage, PublicKey};
+use {Error, Message, Private, Public};
+
+pub const TAP_TWEAK_TAG: &[u8] = b"TapTweak";
+pub const TAP_SIGHASH_TAG: &[u8] = b"TapSighash";
+
+/// Returns the x-only representation of the `public` key.
+fn x_only_public(public: &Public) -> Result<XOnlyPublicKey, Error> {
+    let public = PublicKey::from_slice(public)?;
+    Ok(XOnlyPublicKey::from_slice(&public.serialize()[1..])?)
+}
+
+/// Calculates the tweaked output key of a P2TR output that can be spent by the `internal_key` only:
+/// `Q = P + int(hashTapTweak(bytes(P)))G`.
+pub fn taproot_output_key(internal_key: &Public) -> Result<H256, Error> {
+    let mut output_key = x_only_public(internal_key)?;
+    let tweak = tagged_hash(TAP_TWEAK_TAG, &output_key.serialize());
+    output_key.tweak_add_assign(&SECP_VERIFY, &*tweak)?;
+    Ok(output_key.serialize().into())
+}
+
+/// Signs the BIP-341 signature hash with the `private` key tweaked the same way as in [`taproot_output_key`].
+/// Returns a 64 bytes long BIP-340 Schnorr signature.
+pub fn sign_taproot_key_path(private: &Private, message: &Message) -> Result<H512, Error> {
+    let mut key_pair = SchnorrKeyPair::from_seckey_slice(&SECP_SIGN, &*private.secret)?;
+    let internal_key = XOnlyPublicKey::from_keypair(&SECP_SIGN, &key_pair);
+    let tweak = tagged_hash(TAP_TWEAK_TAG, &internal_key.serialize());
+    key_pair.tweak_add_assign(&SECP_VERIFY, &*tweak)?;
+
+    let message = SecpMessage::from_slice(&**message)?;
+    let signature = SECP_SIGN.schnorrsig_sign_no_aux_rand(&message, &key_pair);
+    let mut result = H512::default();
+    result.copy_from_slice(&signature[..]);
+    Ok(result)
+}
+
+/// Verifies the BIP-340 Schnorr `signature` of the `message` against the P2TR `output_key`.
+pub fn verify_taproot_key_path(output_key: &H256, message: &Message, signature: &H512) -> Result<bool, Error> {
+    let output_key = XOnlyPublicKey::from_slice(&**output_key)?;
+    let signature = SchnorrSignature::from_slice(&**signature)?;
+    let message = SecpMessage::from_slice(&**message)?;
+    Ok(SECP_VERIFY.schnorrsig_verify(&signature, &message, &output_key).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::ChecksumType;
+    use KeyPair;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+    #[test]
+    fn test_bip86_output_key() {
+        let internal_key =
+            Public::Compressed("02cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115".into());
+        let expected: H256 = "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c".into();
+        assert_eq!(taproot_output_key(&internal_key).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_sign_verify_taproot_key_path() {
+        let private = Private {
+            prefix: 128,
+            secret: "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35".into(),
+            compressed: true,
+            checksum_type: ChecksumType::DSHA256,
+        };
+        let key_pair = KeyPair::from_private(private).unwrap();
+        let output_key = taproot_output_key(key_pair.public()).unwrap();
+        let message: Message = "4c1e3bbbd4e1ddab1fb6a4fca3c6c46a5d8e5e6d61b1e8b3f1b0e8b2c07f1c2a".into();
+
+        let signature = sign_taproot_key_path(&private, &message).unwrap();
+        assert!(verify_taproot_key_path(&output_key, &message, &signature).unwrap());
+
+        // The signature doesn't match the untweaked internal key.
+        let internal_key = H256::from(key_pair.public().compressed_unprefixed().unwrap());
+        assert!(!verify_taproot_key_path(&internal_key, &message, &signature).unwrap());
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json `keyPathSpending`, input 0
+    #[test]
+    fn test_taproot_key_path_bip341_vector() {
+        let private = Private {
+            prefix: 128,
+            secret: "6b973d88838f27366ed61c9ad6367663045cb456e28335c109e30717ae0c6baa".into(),
+            compressed: true,
+            checksum_type: ChecksumType::DSHA256,
+        };
+        let key_pair = KeyPair::from_private(private).unwrap();
+        let internal_key = H256::from(key_pair.public().compressed_unprefixed().unwrap());
+        let expected_internal_key: H256 = "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d".into();
+        assert_eq!(internal_key, expected_internal_key);
+
+        let output_key = taproot_output_key(key_pair.public()).unwrap();
+        let expected_output_key: H256 = "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343".into();
+        assert_eq!(output_key, expected_output_key);
+
+        let sighash: Message = "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555".into();
+        let expected_signature: H512 = concat!(
+            "ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff1",
+            "4d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c",
+        )
+        .into();
+        assert!(verify_taproot_key_path(&output_key, &sighash, &expected_signature).unwrap());
+
+        // The vector is signed with the zero auxiliary randomness, so only the validity of our signature is checked.
+        let signature = sign_taproot_key_path(&private, &sighash).unwrap();
+        assert!(verify_taproot_key_path(&output_key, &sighash, &signature).unwrap());
+    }
+}
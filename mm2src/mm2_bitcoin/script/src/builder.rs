@@ -2,7 +2,7 @@
 
 use bytes::Bytes;
 use keys::{AddressHashEnum, Error, Public};
-use {Num, Opcode, Script};
+use {Error as ScriptError, Num, Opcode, Script};
 
 /// Script builder
 #[derive(Default)]
@@ -46,7 +46,9 @@ impl Builder {
                 .push_opcode(Opcode::OP_0)
                 .push_data(wpkh_hash.as_ref())
                 .into_script()),
-            AddressHashEnum::WitnessScriptHash(_) => Err(Error::WitnessHashMismatched),
+            AddressHashEnum::WitnessScriptHash(_) | AddressHashEnum::TaprootOutputKey(_) => {
+                Err(Error::WitnessHashMismatched)
+            },
         }
     }
 
@@ -57,10 +59,43 @@ impl Builder {
                 .push_opcode(Opcode::OP_0)
                 .push_data(wsh_hash.as_ref())
                 .into_script()),
-            AddressHashEnum::AddressHash(_) => Err(Error::WitnessHashMismatched),
+            AddressHashEnum::AddressHash(_) | AddressHashEnum::TaprootOutputKey(_) => Err(Error::WitnessHashMismatched),
         }
     }
 
+    /// Builds p2tr script pubkey
+    pub fn build_p2tr(output_key: &AddressHashEnum) -> Result<Script, Error> {
+        match output_key {
+            AddressHashEnum::TaprootOutputKey(output_key) => Ok(Builder::default()
+                .push_opcode(Opcode::OP_1)
+                .push_data(output_key.as_ref())
+                .into_script()),
+            AddressHashEnum::AddressHash(_) | AddressHashEnum::WitnessScriptHash(_) => {
+                Err(Error::WitnessHashMismatched)
+            },
+        }
+    }
+
+    /// Builds m-of-n multisig script, the public keys are pushed in the given order.
+    /// Up to 16 public keys are supported, so `m` and `n` are always pushed as `OP_1`..`OP_16`.
+    pub fn build_multisig(required: usize, pubkeys: &[Public]) -> Result<Script, ScriptError> {
+        if pubkeys.is_empty() || pubkeys.len() > 16 {
+            return Err(ScriptError::PubkeyCount);
+        }
+        if required == 0 || required > pubkeys.len() {
+            return Err(ScriptError::SigCount);
+        }
+
+        let mut builder = Builder::default().push_opcode(Builder::op_n(required));
+        for pubkey in pubkeys {
+            builder = builder.push_data(pubkey);
+        }
+        Ok(builder
+            .push_opcode(Builder::op_n(pubkeys.len()))
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script())
+    }
+
     /// Builds op_return script
     pub fn build_nulldata(bytes: &[u8]) -> Script {
         Builder::default()
@@ -69,6 +104,11 @@ impl Builder {
             .into_script()
     }
 
+    /// Returns `OP_1`..`OP_16` opcode, `n` must be within the `[1; 16]` interval.
+    fn op_n(n: usize) -> Opcode {
+        Opcode::from_u8(Opcode::OP_1 as u8 + n as u8 - 1).expect("value is within [OP_1; OP_16] interval; qed")
+    }
+
     /// Pushes opcode to the end of script
     pub fn push_opcode(mut self, opcode: Opcode) -> Self {
         self.data.push(opcode as u8);
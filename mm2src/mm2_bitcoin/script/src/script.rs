@@ -72,6 +72,14 @@ impl ScriptAddress {
             hash,
         }
     }
+
+    /// Creates P2TR-type ScriptAddress
+    pub fn new_p2tr(output_key: AddressHashEnum) -> Self {
+        ScriptAddress {
+            kind: keys::AddressScriptType::P2TR,
+            hash: output_key,
+        }
+    }
 }
 
 /// Serialized script, used inside transaction inputs and outputs.
@@ -164,6 +172,11 @@ impl Script {
         self.data.len() == 34 && self.data[0] == Opcode::OP_0 as u8 && self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
     }
 
+    /// Extra-fast test for pay-to-taproot scripts.
+    pub fn is_pay_to_taproot(&self) -> bool {
+        self.data.len() == 34 && self.data[0] == Opcode::OP_1 as u8 && self.data[1] == Opcode::OP_PUSHBYTES_32 as u8
+    }
+
     /// Extra-fast test for multisig scripts.
     pub fn is_multisig_script(&self) -> bool {
         if self.data.len() < 3 {
@@ -352,6 +365,8 @@ impl Script {
             ScriptType::WitnessKey
         } else if self.is_pay_to_witness_script_hash() {
             ScriptType::WitnessScript
+        } else if self.is_pay_to_taproot() {
+            ScriptType::Taproot
             // TODO add Call
         } else {
             ScriptType::NonStandard
@@ -412,6 +427,23 @@ impl Script {
         1
     }
 
+    /// Returns the public keys of a multisig script in the order they're pushed,
+    /// or `None` if the script isn't a multisig one.
+    pub fn multisig_pubkeys(&self) -> Option<Vec<Public>> {
+        if !self.is_multisig_script() {
+            return None;
+        }
+
+        let mut pubkeys = Vec::new();
+        let mut pc = 1;
+        while pc < self.len() - 2 {
+            let instruction = self.get_instruction_at(pc).ok()?;
+            pubkeys.push(Public::from_slice(instruction.data?).ok()?);
+            pc += instruction.step;
+        }
+        Some(pubkeys)
+    }
+
     pub fn extract_destinations(&self) -> Result<Vec<ScriptAddress>, keys::Error> {
         match self.script_type() {
             ScriptType::NonStandard => Ok(vec![]),
@@ -469,7 +501,10 @@ impl Script {
                 Ok(vec![ScriptAddress::new_p2wpkh(address_hash)])
             },
             ScriptType::Taproot => {
-                Ok(vec![]) // TODO
+                let bytes = self.data.get(2..34).ok_or(keys::Error::InvalidAddress)?;
+                let output_key: [u8; 32] = bytes.try_into().map_err(|_| keys::Error::InvalidAddress)?;
+                let output_key = AddressHashEnum::TaprootOutputKey(output_key.into());
+                Ok(vec![ScriptAddress::new_p2tr(output_key)])
             },
             ScriptType::CallSender => {
                 Ok(vec![]) // TODO
@@ -630,6 +665,7 @@ pub fn is_witness_commitment_script(script: &[u8]) -> bool {
 #[cfg(test)]
 mod tests {
     use super::{Script, ScriptAddress, ScriptType};
+    use bytes::Bytes;
     use crypto::ChecksumType;
     use keys::{prefixes::BTC_PREFIXES, Address, Public};
     use {Builder, Error, Opcode};
@@ -661,6 +697,28 @@ mod tests {
         assert!(!script2.is_pay_to_witness_script_hash());
     }
 
+    #[test]
+    fn test_build_multisig() {
+        let pubkeys: Vec<Public> = [
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+            "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        ]
+        .iter()
+        .map(|pubkey| Public::from_slice(&Bytes::from(*pubkey)).unwrap())
+        .collect();
+
+        let script = Builder::build_multisig(2, &pubkeys).unwrap();
+        assert_eq!(script.script_type(), ScriptType::Multisig);
+        assert_eq!(script.num_signatures_required(), 2);
+        assert_eq!(script.multisig_pubkeys(), Some(pubkeys.clone()));
+
+        assert_eq!(Builder::build_multisig(0, &pubkeys), Err(Error::SigCount));
+        assert_eq!(Builder::build_multisig(4, &pubkeys), Err(Error::SigCount));
+        assert_eq!(Builder::build_multisig(1, &[]), Err(Error::PubkeyCount));
+        assert_eq!(Script::from("0103010293").multisig_pubkeys(), None);
+    }
+
     #[test]
     fn test_script_debug() {
         use std::fmt::Write;
@@ -848,6 +906,27 @@ OP_ADD
         );
     }
 
+    #[test]
+    fn test_extract_destinations_taproot() {
+        let output_key = Address::from_segwitaddress(
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            ChecksumType::DSHA256,
+        )
+        .unwrap()
+        .hash()
+        .clone();
+        let script = Builder::build_p2tr(&output_key).expect("build p2tr ok");
+        assert_eq!(
+            script,
+            Script::from("5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c")
+        );
+        assert_eq!(script.script_type(), ScriptType::Taproot);
+        assert_eq!(
+            script.extract_destinations(),
+            Ok(vec![ScriptAddress::new_p2tr(output_key),])
+        );
+    }
+
     #[test]
     fn test_extract_destinations_witness_script_hash() {
         let address_hash = Address::from_segwitaddress(
@@ -4,9 +4,9 @@ use blake2b_simd::Params as Blake2b;
 use bytes::Bytes;
 use chain::{JoinSplit, OutPoint, ShieldedOutput, ShieldedSpend, Transaction, TransactionInput, TransactionOutput,
             TxHashAlgo};
-use crypto::{dhash256, sha256};
+use crypto::{dhash256, sha256, tagged_hash};
 use hash::{H256, H512};
-use keys::KeyPair;
+use keys::{KeyPair, TAP_SIGHASH_TAG};
 use ser::Stream;
 use serde::Deserialize;
 use std::convert::TryInto;
@@ -28,6 +28,8 @@ pub enum SignatureVersion {
     WitnessV0,
     #[serde(rename = "fork_id")]
     ForkId,
+    #[serde(rename = "taproot")]
+    Taproot,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -78,6 +80,8 @@ impl Sighash {
         // reset anyone_can_pay && fork_id (if applicable) bits
         let u = match version {
             SignatureVersion::ForkId => u & !(0x40 | 0x80),
+            // SIGHASH_DEFAULT is defined for taproot signatures only
+            SignatureVersion::Taproot if u == 0 => return true,
             _ => u & !(0x80),
         };
 
@@ -250,6 +254,7 @@ impl TransactionInputSigner {
             SignatureVersion::WitnessV0 => {
                 self.signature_hash_witness0(input_index, input_amount, script_pubkey, sighashtype, sighash)
             },
+            SignatureVersion::Taproot => self.signature_hash_taproot(input_index, sighashtype, sighash),
         }
     }
 
@@ -414,6 +419,73 @@ impl TransactionInputSigner {
         dhash256(&out)
     }
 
+    /// BIP-341 signature hash of a key path spending without an annex.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#common-signature-message
+    /// Unlike the previous versions, it commits to the amounts and the script pubkeys of all the inputs,
+    /// so `prev_script` and `amount` must be set for every input.
+    fn signature_hash_taproot(&self, input_index: usize, sighashtype: u32, sighash: Sighash) -> H256 {
+        if input_index >= self.inputs.len() {
+            return 1u8.into();
+        }
+
+        let mut stream = Stream::default();
+        // The sighash epoch.
+        stream.append(&0u8);
+        stream.append(&(sighashtype as u8));
+        stream.append(&self.version);
+        stream.append(&self.lock_time);
+
+        if !sighash.anyone_can_pay {
+            let mut prevouts = Stream::default();
+            let mut amounts = Stream::default();
+            let mut script_pubkeys = Stream::default();
+            let mut sequences = Stream::default();
+            for input in self.inputs.iter() {
+                prevouts.append(&input.previous_output);
+                amounts.append(&input.amount);
+                script_pubkeys.append_list(&input.prev_script);
+                sequences.append(&input.sequence);
+            }
+            stream.append(&sha256(&prevouts.out()));
+            stream.append(&sha256(&amounts.out()));
+            stream.append(&sha256(&script_pubkeys.out()));
+            stream.append(&sha256(&sequences.out()));
+        }
+
+        if sighash.base == SighashBase::All {
+            let mut outputs = Stream::default();
+            for output in self.outputs.iter() {
+                outputs.append(output);
+            }
+            stream.append(&sha256(&outputs.out()));
+        }
+
+        // The spend type: no extension (key path spending) and no annex.
+        stream.append(&0u8);
+
+        let input = &self.inputs[input_index];
+        if sighash.anyone_can_pay {
+            stream.append(&input.previous_output);
+            stream.append(&input.amount);
+            stream.append_list(&input.prev_script);
+            stream.append(&input.sequence);
+        } else {
+            stream.append(&(input_index as u32));
+        }
+
+        if sighash.base == SighashBase::Single {
+            let output = match self.outputs.get(input_index) {
+                Some(output) => output,
+                None => return 1u8.into(),
+            };
+            let mut single_output = Stream::default();
+            single_output.append(output);
+            stream.append(&sha256(&single_output.out()));
+        }
+
+        tagged_hash(TAP_SIGHASH_TAG, &stream.out())
+    }
+
     fn signature_hash_fork_id(
         &self,
         input_index: usize,
@@ -632,9 +704,9 @@ mod tests {
                 UnsignedTransactionInput};
     use bytes::Bytes;
     use chain::{OutPoint, Transaction, TransactionOutput};
-    use hash::{H160, H256};
+    use hash::{H160, H256, H512};
     use keys::{prefixes::{BTC_PREFIXES, T_BTC_PREFIXES},
-               Address, AddressHashEnum, Private};
+               verify_taproot_key_path, Address, AddressHashEnum, Private};
     use script::Script;
     use ser::deserialize;
     use sign::SignerHashAlgo;
@@ -1268,4 +1340,170 @@ mod tests {
 
         assert_eq!(expected_sighash, sig_hash);
     }
+
+    #[test]
+    fn test_signature_hash_taproot_commits_to_all_inputs() {
+        let prev_script: Script = "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c".into();
+        let unsigned_input = |index: u32, amount: u64| UnsignedTransactionInput {
+            sequence: 0xffff_fffd,
+            previous_output: OutPoint {
+                index,
+                hash: H256::from_reversed_str("81b4c832d70cb56ff957589752eb4125a4cab78a25a8fc52d6a09e5bd4404d48"),
+            },
+            prev_script: prev_script.clone(),
+            amount,
+        };
+        let mut input_signer = TransactionInputSigner {
+            version: 2,
+            n_time: None,
+            overwintered: false,
+            version_group_id: 0,
+            consensus_branch_id: 0,
+            expiry_height: 0,
+            value_balance: 0,
+            lock_time: 0,
+            inputs: vec![unsigned_input(0, 10000), unsigned_input(1, 20000)],
+            outputs: vec![TransactionOutput {
+                value: 25000,
+                script_pubkey: "76a914c8e90996c7c6080ee06284600c684ed904d14c5c88ac".into(),
+            }],
+            join_splits: vec![],
+            shielded_spends: vec![],
+            shielded_outputs: vec![],
+            zcash: false,
+            posv: false,
+            str_d_zeel: None,
+            hash_algo: SignerHashAlgo::DSHA256,
+            v_extra_payload: None,
+        };
+
+        let sighash_default = input_signer.signature_hash(0, 10000, &prev_script, SignatureVersion::Taproot, 0);
+        let sighash_all = input_signer.signature_hash(0, 10000, &prev_script, SignatureVersion::Taproot, 1);
+        // The sighash type is committed to, so SIGHASH_DEFAULT and SIGHASH_ALL signatures aren't interchangeable.
+        assert_ne!(sighash_default, sighash_all);
+        assert_ne!(
+            sighash_default,
+            input_signer.signature_hash(1, 20000, &prev_script, SignatureVersion::Taproot, 0)
+        );
+
+        // Unlike BIP-143, the amount of every input is committed to.
+        input_signer.inputs[1].amount = 20001;
+        assert_ne!(
+            sighash_default,
+            input_signer.signature_hash(0, 10000, &prev_script, SignatureVersion::Taproot, 0)
+        );
+        // But not with SIGHASH_ANYONECANPAY.
+        let sighash_anyone_can_pay =
+            input_signer.signature_hash(0, 10000, &prev_script, SignatureVersion::Taproot, 0x81);
+        input_signer.inputs[1].amount = 20000;
+        assert_eq!(
+            sighash_anyone_can_pay,
+            input_signer.signature_hash(0, 10000, &prev_script, SignatureVersion::Taproot, 0x81)
+        );
+    }
+
+    /// https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json `keyPathSpending`
+    #[test]
+    fn test_signature_hash_taproot_bip341_vectors() {
+        let tx: Transaction = concat!(
+            "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab5",
+            "7b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28e",
+            "ac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece7",
+            "4310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba",
+            "6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd0500000000000000000",
+            "00e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de676",
+            "19e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a996",
+            "67720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa1",
+            "1f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b",
+            "0065cd1d",
+        )
+        .into();
+        let utxos_spent = [
+            (
+                "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+                420000000,
+            ),
+            (
+                "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                462000000,
+            ),
+            ("76a914751e76e8199196d454941c45d1b3a323f1433bd688ac", 294000000),
+            (
+                "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                504000000,
+            ),
+            (
+                "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+                630000000,
+            ),
+            ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378000000),
+            (
+                "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                672000000,
+            ),
+            (
+                "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+                546000000,
+            ),
+            (
+                "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                588000000,
+            ),
+        ];
+        let mut signer = TransactionInputSigner::from(tx);
+        for (input, (script_pubkey, amount)) in signer.inputs.iter_mut().zip(utxos_spent.iter()) {
+            input.prev_script = (*script_pubkey).into();
+            input.amount = *amount;
+        }
+
+        // (txinIndex, hashType, sigHash)
+        let input_spending = [
+            (0, 3, "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555"),
+            (
+                1,
+                0x83,
+                "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d",
+            ),
+            (3, 1, "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669"),
+            (4, 0, "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef"),
+            (6, 2, "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85"),
+            (
+                7,
+                0x82,
+                "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10",
+            ),
+            (
+                8,
+                0x81,
+                "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2",
+            ),
+        ];
+        for (input_index, hash_type, expected) in input_spending {
+            let (script_pubkey, amount) = utxos_spent[input_index];
+            let sighash = signer.signature_hash(
+                input_index,
+                amount,
+                &script_pubkey.into(),
+                SignatureVersion::Taproot,
+                hash_type,
+            );
+            assert_eq!(sighash, H256::from(expected), "input {}", input_index);
+        }
+
+        // The witness of the input 0 which is spent with the key path without a script tree.
+        let sighash = signer.signature_hash(
+            0,
+            utxos_spent[0].1,
+            &utxos_spent[0].0.into(),
+            SignatureVersion::Taproot,
+            3,
+        );
+        let output_key: H256 = "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343".into();
+        let signature: H512 = concat!(
+            "ed7c1647cb97379e76892be0cacff57ec4a7102aa24296ca39af7541246d8ff1",
+            "4d38958d4cc1e2e478e4d4a764bbfd835b16d4e314b72937b29833060b87276c",
+        )
+        .into();
+        assert!(verify_taproot_key_path(&output_key, &sighash, &signature).unwrap());
+    }
 }
@@ -36,7 +36,7 @@ pub(crate) type CustomTypes = HashMap<String, Vec<ObjectProperty>>;
 /// let mut mail_type = ObjectType::new("Mail");
 /// mail_type.property("message", PropertyType::String);
 /// mail_type.property("from", PropertyType::Custom("Person".into()));
-/// mail_type.property_array("to", PropertyType::Custom("Person".into()));
+/// mail_type.property("to", PropertyType::Array(Box::new(PropertyType::Custom("Person".into())), None));
 ///
 /// let mut person_type = ObjectType::new("Person");
 /// person_type.property("address", PropertyType::Address);
@@ -77,15 +77,25 @@ impl ObjectType {
     }
 }
 
-/// Add `Int64`, `Uint64`, `Int256`, `Array` types if required.
+/// Types of the typed structured data properties:
 /// https://github.com/ethereum/EIPs/blob/master/EIPS/eip-712.md#definition-of-typed-structured-data-%F0%9D%95%8A
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum PropertyType {
     Bool,
     String,
     Uint256,
     Address,
     Bytes32,
+    /// `uint8` up to `uint248`, the size is in bits.
+    Uint(usize),
+    /// `int8` up to `int256`, the size is in bits.
+    Int(usize),
+    /// `bytes1` up to `bytes31`, the size is in bytes.
+    FixedBytes(usize),
+    /// Dynamic `bytes`.
+    Bytes,
+    /// `Type[]` if the length is `None`, `Type[length]` otherwise.
+    Array(Box<PropertyType>, Option<usize>),
     Custom(String),
 }
 
@@ -97,6 +107,12 @@ impl fmt::Display for PropertyType {
             PropertyType::Uint256 => write!(f, "uint256"),
             PropertyType::Address => write!(f, "address"),
             PropertyType::Bytes32 => write!(f, "bytes32"),
+            PropertyType::Uint(bits) => write!(f, "uint{bits}"),
+            PropertyType::Int(bits) => write!(f, "int{bits}"),
+            PropertyType::FixedBytes(size) => write!(f, "bytes{size}"),
+            PropertyType::Bytes => write!(f, "bytes"),
+            PropertyType::Array(entry, Some(len)) => write!(f, "{entry}[{len}]"),
+            PropertyType::Array(entry, None) => write!(f, "{entry}[]"),
             PropertyType::Custom(custom) => write!(f, "{custom}"),
         }
     }
@@ -106,18 +122,51 @@ impl FromStr for PropertyType {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(entry) = s.strip_suffix(']') {
+            let open = entry.rfind('[').ok_or_else(|| format!("Invalid array type '{s}'"))?;
+            let len = match &entry[open + 1..] {
+                "" => None,
+                len => Some(len.parse().map_err(|_| format!("Invalid array length in '{s}'"))?),
+            };
+            let entry_type = PropertyType::from_str(&entry[..open])?;
+            return Ok(PropertyType::Array(Box::new(entry_type), len));
+        }
+
         let property_type = match s {
             "bool" => PropertyType::Bool,
             "string" => PropertyType::String,
             "uint256" => PropertyType::Uint256,
             "address" => PropertyType::Address,
             "bytes32" => PropertyType::Bytes32,
-            custom => PropertyType::Custom(custom.to_string()),
+            "bytes" => PropertyType::Bytes,
+            _ => {
+                if let Some(bits) = s.strip_prefix("uint").and_then(|bits| bits.parse().ok()) {
+                    check_int_size(s, bits)?;
+                    PropertyType::Uint(bits)
+                } else if let Some(bits) = s.strip_prefix("int").and_then(|bits| bits.parse().ok()) {
+                    check_int_size(s, bits)?;
+                    PropertyType::Int(bits)
+                } else if let Some(size) = s.strip_prefix("bytes").and_then(|size| size.parse().ok()) {
+                    if !(1..=32).contains(&size) {
+                        return Err(format!("Invalid '{s}' type size"));
+                    }
+                    PropertyType::FixedBytes(size)
+                } else {
+                    PropertyType::Custom(s.to_string())
+                }
+            },
         };
         Ok(property_type)
     }
 }
 
+fn check_int_size(type_name: &str, bits: usize) -> Result<(), String> {
+    if bits == 0 || bits > 256 || bits % 8 != 0 {
+        return Err(format!("Invalid '{type_name}' type size"));
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ObjectProperty {
     pub(crate) name: String,
@@ -125,7 +174,7 @@ pub struct ObjectProperty {
     pub(crate) property_type: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Eip712<Domain, SignData> {
     /// Defines the types of the domain and data you will be signing.
     pub types: CustomTypes,
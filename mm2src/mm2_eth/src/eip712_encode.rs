@@ -15,36 +15,75 @@ use web3::{Error, Result};
 
 type H256Bytes = Vec<u8>;
 
+/// Returns the hash of the typed data to be signed, as `eth_signTypedData_v4` does.
 pub fn hash_typed_data<Domain, SignData>(data: Eip712<Domain, SignData>) -> Result<H256>
 where
     Domain: Serialize,
     SignData: Serialize,
 {
-    let data_raw = Eip712Raw::try_from(data)?;
-    hash_typed_data_raw(data_raw)
+    Ok(typed_data_hashes(data)?.signing_hash())
+}
+
+/// Hashes of the typed data parts.
+/// Some hardware wallets sign these hashes instead of the typed data itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypedDataHashes {
+    pub domain_separator: H256,
+    /// `None` if the primary type is `EIP712Domain`, i.e. only the domain is signed.
+    pub message_hash: Option<H256>,
+}
+
+impl TypedDataHashes {
+    /// `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`
+    pub fn signing_hash(&self) -> H256 {
+        /// EIP-191 compliant.
+        const PREFIX: &[u8; 2] = b"\x19\x01";
+
+        let mut concat = [PREFIX.as_slice(), self.domain_separator.as_bytes()].concat();
+        if let Some(message_hash) = self.message_hash {
+            concat.extend_from_slice(message_hash.as_bytes());
+        }
+        H256::from(keccak256(&concat))
+    }
 }
 
-fn hash_typed_data_raw(data: Eip712Raw) -> Result<H256> {
-    /// EIP-191 compliant.
-    const PREFIX: &[u8; 2] = b"\x19\x01";
+pub fn typed_data_hashes<Domain, SignData>(data: Eip712<Domain, SignData>) -> Result<TypedDataHashes>
+where
+    Domain: Serialize,
+    SignData: Serialize,
+{
+    let data_raw = Eip712Raw::try_from(data)?;
+    typed_data_hashes_raw(data_raw)
+}
 
+fn typed_data_hashes_raw(data: Eip712Raw) -> Result<TypedDataHashes> {
     let domain_hash = encode_data(
         &data.types,
         PropertyType::Custom(EIP712_DOMAIN.to_string()),
         &data.domain,
         None,
     )?;
-    let data_hash = encode_data(
-        &data.types,
-        PropertyType::Custom(data.primary_type.clone()),
-        &data.message,
-        None,
-    )?;
+    let message_hash = if data.primary_type == EIP712_DOMAIN {
+        None
+    } else {
+        let data_hash = encode_data(
+            &data.types,
+            PropertyType::Custom(data.primary_type.clone()),
+            &data.message,
+            None,
+        )?;
+        Some(H256::from_slice(&data_hash))
+    };
 
-    let concat = [PREFIX.as_slice(), domain_hash.as_slice(), data_hash.as_slice()].concat();
-    Ok(H256::from(keccak256(&concat)))
+    Ok(TypedDataHashes {
+        domain_separator: H256::from_slice(&domain_hash),
+        message_hash,
+    })
 }
 
+#[cfg(test)]
+fn hash_typed_data_raw(data: Eip712Raw) -> Result<H256> { Ok(typed_data_hashes_raw(data)?.signing_hash()) }
+
 #[derive(Debug, Deserialize, Serialize)]
 struct Eip712Raw {
     types: CustomTypes,
@@ -80,9 +119,16 @@ fn encode_data(
     match data_type {
         PropertyType::Bool => encode_bool(data, field_name),
         PropertyType::String => encode_string(data, field_name),
-        PropertyType::Uint256 => encode_u256(data, field_name),
+        PropertyType::Uint256 => encode_uint(data, 256, field_name),
+        PropertyType::Uint(bits) => encode_uint(data, bits, field_name),
+        PropertyType::Int(bits) => encode_int(data, bits, field_name),
         PropertyType::Address => encode_address(data, field_name),
-        PropertyType::Bytes32 => encode_bytes32(data, field_name),
+        PropertyType::Bytes32 => encode_fixed_bytes(data, 32, field_name),
+        PropertyType::FixedBytes(size) => encode_fixed_bytes(data, size, field_name),
+        PropertyType::Bytes => encode_bytes(data, field_name),
+        PropertyType::Array(entry_type, len) => encode_array(custom_types, *entry_type, len, data, field_name),
+        // A missing struct is encoded as zeroes, as MetaMask does.
+        PropertyType::Custom(_) if data.is_null() && field_name.is_some() => Ok(vec![0; 32]),
         PropertyType::Custom(custom) => encode_custom(custom_types, &custom, data, field_name),
     }
 }
@@ -102,7 +148,7 @@ fn encode_custom(
 
     for field in data_properties.iter() {
         let field_value = &data[&field.name];
-        let field_type = PropertyType::from_str(&field.property_type)?;
+        let field_type = PropertyType::from_str(&field.property_type).map_err(|e| decode_error(e, field_name))?;
         let mut encoded = encode_data(custom_types, field_type, field_value, Some(&*field.name))?;
         encoded_tokens.append(&mut encoded);
     }
@@ -110,13 +156,57 @@ fn encode_custom(
     Ok(keccak256(&encoded_tokens).as_ref().to_vec())
 }
 
-fn encode_bytes32(value: &Json, field_name: Option<&str>) -> Result<Vec<u8>> {
+/// Arrays are encoded as the hash of the concatenated encodings of their items.
+fn encode_array(
+    custom_types: &CustomTypes,
+    entry_type: PropertyType,
+    len: Option<usize>,
+    value: &Json,
+    field_name: Option<&str>,
+) -> Result<Vec<u8>> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| expected_type_error("array", value, field_name))?;
+    if let Some(len) = len {
+        if items.len() != len {
+            let error = format!("Expected {len} array items, found {}", items.len());
+            return Err(decode_error(error, field_name));
+        }
+    }
+
+    let mut encoded_items = Vec::with_capacity(items.len() * 32);
+    for item in items {
+        let mut encoded = encode_data(custom_types, entry_type.clone(), item, field_name)?;
+        encoded_items.append(&mut encoded);
+    }
+    Ok(encode(&[Token::FixedBytes(keccak256(&encoded_items).to_vec())]))
+}
+
+fn encode_fixed_bytes(value: &Json, size: usize, field_name: Option<&str>) -> Result<Vec<u8>> {
     let string = value
         .as_str()
-        .ok_or_else(|| expected_type_error("bytes32", value, field_name))?;
+        .ok_or_else(|| expected_type_error(&format!("bytes{size}"), value, field_name))?;
     check_hex(string, field_name)?;
 
-    let bytes = hex::decode(string).map_err(|e| decode_error(e, field_name))?;
+    let bytes = hex::decode(&string[2..]).map_err(|e| decode_error(e, field_name))?;
+    if bytes.len() > size {
+        let error = format!("Expected at most {size} bytes, found {}", bytes.len());
+        return Err(decode_error(error, field_name));
+    }
+    // `Token::FixedBytes` is right-padded with zeroes.
+    Ok(encode(&[Token::FixedBytes(bytes)]))
+}
+
+/// Dynamic bytes are encoded as the hash of the value.
+/// A non-hex string is treated as UTF-8 bytes, as MetaMask does.
+fn encode_bytes(value: &Json, field_name: Option<&str>) -> Result<Vec<u8>> {
+    let string = value
+        .as_str()
+        .ok_or_else(|| expected_type_error("bytes", value, field_name))?;
+    let bytes = match string.strip_prefix("0x") {
+        Some(hex_str) => hex::decode(hex_str).map_err(|e| decode_error(e, field_name))?,
+        None => string.as_bytes().to_vec(),
+    };
     let hash = keccak256(&bytes).to_vec();
 
     Ok(encode(&[Token::FixedBytes(hash)]))
@@ -150,14 +240,57 @@ fn encode_address(value: &Json, field_name: Option<&str>) -> Result<Vec<u8>> {
     Ok(encode(&[Token::Address(address)]))
 }
 
-fn encode_u256(value: &Json, field_name: Option<&str>) -> Result<Vec<u8>> {
+fn encode_uint(value: &Json, bits: usize, field_name: Option<&str>) -> Result<Vec<u8>> {
+    let (negative, uint) = parse_integer(value, field_name)?;
+    if negative || uint.bits() > bits {
+        return Err(decode_error(
+            format!("'{value}' is out of 'uint{bits}' range"),
+            field_name,
+        ));
+    }
+    Ok(encode(&[Token::Uint(uint)]))
+}
+
+fn encode_int(value: &Json, bits: usize, field_name: Option<&str>) -> Result<Vec<u8>> {
+    let (negative, abs) = parse_integer(value, field_name)?;
+    // The range is `[-2^(bits-1), 2^(bits-1) - 1]`.
+    let max_abs = U256::one() << (bits - 1);
+    if (negative && abs > max_abs) || (!negative && abs >= max_abs) {
+        return Err(decode_error(
+            format!("'{value}' is out of 'int{bits}' range"),
+            field_name,
+        ));
+    }
+    // Two's complement.
+    let int = if negative {
+        (!abs).overflowing_add(U256::one()).0
+    } else {
+        abs
+    };
+    Ok(encode(&[Token::Int(int)]))
+}
+
+/// Parses a JSON number, a decimal string or a 0x-prefixed hex string into the sign and the absolute value.
+fn parse_integer(value: &Json, field_name: Option<&str>) -> Result<(bool, U256)> {
+    if let Some(uint) = value.as_u64() {
+        return Ok((false, U256::from(uint)));
+    }
+    if let Some(int) = value.as_i64() {
+        return Ok((int < 0, U256::from(int.unsigned_abs())));
+    }
+
     let string = value
         .as_str()
-        .ok_or_else(|| expected_type_error("str(u256)", value, field_name))?;
-    check_hex(string, field_name)?;
-
-    let uint = U256::from_str(&string[2..]).map_err(|e| decode_error(e, field_name))?;
-    Ok(encode(&[Token::Uint(uint)]))
+        .ok_or_else(|| expected_type_error("integer", value, field_name))?;
+    let (negative, abs) = match string.strip_prefix('-') {
+        Some(abs) => (true, abs),
+        None => (false, string),
+    };
+    let abs = match abs.strip_prefix("0x") {
+        Some(hex_str) => U256::from_str(hex_str).map_err(|e| decode_error(e, field_name))?,
+        None => U256::from_dec_str(abs).map_err(|e| decode_error(e, field_name))?,
+    };
+    Ok((negative && !abs.is_zero(), abs))
 }
 
 fn encode_type(custom_types: &CustomTypes, data_type: &str) -> Result<String> {
@@ -214,7 +347,7 @@ fn build_dependencies<'a>(data_type: &'a str, custom_types: &'a CustomTypes) ->
                     &field.property_type
                 };
                 // seen this type before? or not a custom type skip
-                if !deps.contains(field_type) || custom_types.contains_key(field_type) {
+                if !deps.contains(field_type) && custom_types.contains_key(field_type) {
                     types_stack.insert(field_type);
                 }
             }
@@ -253,6 +386,7 @@ fn decode_error<E: fmt::Display>(error: E, field_name: Option<&str>) -> Error {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_build_dependencies() {
@@ -391,4 +525,147 @@ mod tests {
             "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2",
         );
     }
+
+    #[test]
+    fn test_hash_data_with_arrays() {
+        const JSON: &str = r#"{
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": {
+                    "name": "Cow",
+                    "wallets": [
+                        "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                        "0xDeaDbeefdEAdbeefdEadbEEFdeadbeEFdEaDbeeF"
+                    ]
+                },
+                "to": [{
+                    "name": "Bob",
+                    "wallets": [
+                        "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB",
+                        "0xB0BdaBea57B0BDABeA57b0bdABEA57b0BDabEa57",
+                        "0xB0B0b0b0b0b0B000000000000000000000000000"
+                    ]
+                }],
+                "contents": "Hello, Bob!"
+            },
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Group": [
+                    { "name": "name", "type": "string" },
+                    { "name": "members", "type": "Person[]" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person[]" },
+                    { "name": "contents", "type": "string" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallets", "type": "address[]" }
+                ]
+            }
+        }"#;
+
+        let typed_data = serde_json::from_str::<Eip712Raw>(JSON).expect("alas error!");
+        let hash = hash_typed_data_raw(typed_data).expect("alas error!");
+        assert_eq!(
+            format!("{:02x}", hash),
+            "a85c2e2b118698e88db68a8105b794a8cc7cec074e89ef991cb4f5f533819cc2",
+        );
+    }
+
+    #[test]
+    fn test_typed_data_hashes_with_atomic_types() {
+        const JSON: &str = r#"{
+            "primaryType": "Order",
+            "domain": {
+                "name": "Exchange",
+                "version": "2",
+                "chainId": 137,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "maker": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826",
+                "amount": "1000000000000000000",
+                "delta": -42,
+                "side": 1,
+                "salt": "0x0102030405060708",
+                "data": "0xdeadbeef",
+                "note": "hello",
+                "nonces": [1, "0x2", "3"]
+            },
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Order": [
+                    { "name": "maker", "type": "address" },
+                    { "name": "amount", "type": "uint128" },
+                    { "name": "delta", "type": "int32" },
+                    { "name": "side", "type": "uint8" },
+                    { "name": "salt", "type": "bytes8" },
+                    { "name": "data", "type": "bytes" },
+                    { "name": "note", "type": "bytes" },
+                    { "name": "nonces", "type": "uint64[3]" }
+                ]
+            }
+        }"#;
+
+        let typed_data = serde_json::from_str::<Eip712Raw>(JSON).expect("alas error!");
+        let hashes = typed_data_hashes_raw(typed_data).expect("alas error!");
+        assert_eq!(
+            format!("{:02x}", hashes.domain_separator),
+            "f00e444e8209491518d245d86cffaf0f7af6ddc063225fef0fecda2aa9be9ca1",
+        );
+        assert_eq!(
+            format!("{:02x}", hashes.message_hash.expect("alas error!")),
+            "cb29dab67f532e2955602568231225407d60f550837ef21c7503160fee5c7bd3",
+        );
+        assert_eq!(
+            format!("{:02x}", hashes.signing_hash()),
+            "86d85bc03bfd9f0f03fe6af0e8a1e4913346836c0c0f83687bda3a9706bd42a1",
+        );
+    }
+
+    #[test]
+    fn test_encode_integers_out_of_range() {
+        assert!(encode_uint(&json!(256), 8, None).is_err());
+        assert!(encode_uint(&json!(-1), 256, None).is_err());
+        assert!(encode_int(&json!(128), 8, None).is_err());
+        assert!(encode_int(&json!(-129), 8, None).is_err());
+
+        let encoded = encode_int(&json!("-128"), 8, None).expect("alas error!");
+        let mut expected = vec![0xff; 31];
+        expected.push(0x80);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn test_property_type_from_str() {
+        let actual = PropertyType::from_str("Person[][2]").expect("alas error!");
+        let person = PropertyType::Custom("Person".to_string());
+        let expected = PropertyType::Array(Box::new(PropertyType::Array(Box::new(person), None)), Some(2));
+        assert_eq!(actual, expected);
+        assert_eq!(actual.to_string(), "Person[][2]");
+
+        assert_eq!(PropertyType::from_str("uint256"), Ok(PropertyType::Uint256));
+        assert_eq!(PropertyType::from_str("int256"), Ok(PropertyType::Int(256)));
+        assert_eq!(PropertyType::from_str("bytes4"), Ok(PropertyType::FixedBytes(4)));
+        assert!(PropertyType::from_str("uint7").is_err());
+        assert!(PropertyType::from_str("bytes33").is_err());
+    }
 }
@@ -7,6 +7,9 @@ use serde::Deserialize;
 pub struct EventStreamingConfiguration {
     pub worker_path: String,
     pub access_control_allow_origin: String,
+    /// Journals the streamed events, so the clients can receive the events they missed while disconnected.
+    /// The events aren't journaled if not set.
+    pub journal: Option<EventJournalConfiguration>,
 }
 
 impl Default for EventStreamingConfiguration {
@@ -14,6 +17,26 @@ impl Default for EventStreamingConfiguration {
         Self {
             worker_path: "event_streaming_worker.js".to_string(),
             access_control_allow_origin: "*".to_string(),
+            journal: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+/// The retention of the event journal.
+pub struct EventJournalConfiguration {
+    /// The maximum number of the journaled events.
+    pub max_events: usize,
+    /// The events journaled earlier than this number of seconds ago are dropped.
+    pub max_age_secs: u64,
+}
+
+impl Default for EventJournalConfiguration {
+    fn default() -> Self {
+        Self {
+            max_events: 10_000,
+            max_age_secs: 24 * 60 * 60,
         }
     }
 }
@@ -3,15 +3,19 @@ use serde_json::Value as Json;
 // Note `Event` shouldn't be `Clone`able, but rather Arc/Rc wrapped and then shared.
 // This is only for testing.
 /// Multi-purpose/generic event type that can easily be used over the event streaming
-#[cfg_attr(any(test, target_arch = "wasm32"), derive(Clone, Debug, PartialEq))]
-#[derive(Default)]
+#[cfg_attr(any(test, target_arch = "wasm32"), derive(Clone, PartialEq))]
+#[derive(Debug, Default)]
 pub struct Event {
     /// The type of the event (balance, network, swap, etc...).
-    event_type: String,
+    pub(crate) event_type: String,
     /// The message to be sent to the client.
-    message: Json,
+    pub(crate) message: Json,
     /// Indicating whether this event is an error event or a normal one.
-    error: bool,
+    pub(crate) error: bool,
+    /// The ID assigned to the event by the [`crate::EventJournal`], if the journal is enabled.
+    pub(crate) id: Option<u64>,
+    /// The time the event was journaled at.
+    pub(crate) timestamp: u64,
 }
 
 impl Event {
@@ -22,6 +26,8 @@ impl Event {
             event_type: streamer_id,
             message,
             error: false,
+            id: None,
+            timestamp: 0,
         }
     }
 
@@ -32,6 +38,8 @@ impl Event {
             event_type: streamer_id,
             message,
             error: true,
+            id: None,
+            timestamp: 0,
         }
     }
 
@@ -39,6 +47,14 @@ impl Event {
     #[inline(always)]
     pub fn origin(&self) -> &str { &self.event_type }
 
+    /// Whether this is an error event.
+    #[inline(always)]
+    pub fn is_error(&self) -> bool { self.error }
+
+    /// Returns the ID of the journaled event.
+    #[inline(always)]
+    pub fn id(&self) -> Option<u64> { self.id }
+
     /// Returns the event type and message as a pair.
     pub fn get(&self) -> (String, &Json) {
         let prefix = if self.error { "ERROR:" } else { "" };
@@ -0,0 +1,450 @@
+use crate::configuration::EventJournalConfiguration;
+use crate::Event;
+use common::{now_ms, now_sec};
+#[cfg(not(target_arch = "wasm32"))]
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use serde_json::Value as Json;
+use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An event as it's stored in the journal.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Deserialize, Serialize)]
+struct JournalRecord {
+    id: u64,
+    timestamp: u64,
+    event_type: String,
+    message: Json,
+    error: bool,
+}
+
+/// The streamers a client listens to, persisted with the journal, so the events of these streamers can be replayed
+/// to the client reconnecting after a restart of the node.
+#[cfg_attr(not(target_arch = "wasm32"), derive(Deserialize, Serialize))]
+#[derive(Clone, Debug)]
+struct ClientSubscriptions {
+    listening_to: HashSet<String>,
+    /// When the subscriptions were changed or the client disconnected the last time.
+    updated_at: u64,
+}
+
+/// A bounded journal of the broadcasted events, so the clients that reconnect can receive the events they missed.
+///
+/// Every journaled event gets an ID greater than the IDs of all the events journaled before.
+/// The IDs are derived from the current time in milliseconds, so they keep increasing across restarts
+/// even if all the journaled events were dropped by the retention.
+#[derive(Debug)]
+pub struct EventJournal {
+    config: EventJournalConfiguration,
+    /// Writes the journal to its file, the journal is kept in memory only if not set.
+    #[cfg(not(target_arch = "wasm32"))]
+    writer: Option<JournalWriter>,
+    events: VecDeque<Arc<Event>>,
+    subscriptions: HashMap<u64, ClientSubscriptions>,
+    last_id: u64,
+    /// The number of the events appended to the file since it was compacted the last time.
+    appended: usize,
+}
+
+impl EventJournal {
+    /// Creates a journal kept in memory only.
+    pub fn in_memory(config: EventJournalConfiguration) -> Self {
+        Self {
+            config,
+            #[cfg(not(target_arch = "wasm32"))]
+            writer: None,
+            events: VecDeque::new(),
+            subscriptions: HashMap::new(),
+            last_id: 0,
+            appended: 0,
+        }
+    }
+
+    /// Creates a journal persisted to the file at `path` loading the events journaled before from it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open(config: EventJournalConfiguration, path: PathBuf) -> Result<Self, String> {
+        use std::io::{BufRead, BufReader};
+
+        let mut journal = Self::in_memory(config);
+        match std::fs::File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line.map_err(|e| format!("Error reading {}: {e}", path.display()))?;
+                    // The last line may be incomplete if the node was killed while writing it.
+                    let Ok(record) = serde_json::from_str::<JournalRecord>(&line) else {
+                        continue;
+                    };
+                    journal.last_id = journal.last_id.max(record.id);
+                    journal.events.push_back(Arc::new(record.into()));
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(format!("Error opening {}: {e}", path.display())),
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {e}", dir.display()))?;
+        }
+        journal.apply_retention();
+        // Nothing else has access to the journal yet, so the file is rewritten right away.
+        rewrite_file(&path, &journal.serialize_events()?)?;
+
+        let subscriptions_path = subscriptions_file_path(&path);
+        match std::fs::read(&subscriptions_path) {
+            Ok(content) => {
+                journal.subscriptions = serde_json::from_slice(&content)
+                    .map_err(|e| format!("Error parsing {}: {e}", subscriptions_path.display()))?;
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => return Err(format!("Error opening {}: {e}", subscriptions_path.display())),
+        }
+        // The clients connected before the restart are disconnected since then.
+        let now = now_sec();
+        journal
+            .subscriptions
+            .values_mut()
+            .for_each(|client| client.updated_at = now);
+
+        journal.writer = Some(JournalWriter::spawn(path, subscriptions_path)?);
+        Ok(journal)
+    }
+
+    /// The events journaled earlier than this number of seconds ago are dropped.
+    pub fn max_age_secs(&self) -> u64 { self.config.max_age_secs }
+
+    /// Assigns an ID to the `event` and journals it.
+    pub fn append(&mut self, mut event: Event) -> Arc<Event> {
+        self.last_id = (self.last_id + 1).max(now_ms());
+        event.id = Some(self.last_id);
+        event.timestamp = now_sec();
+        let event = Arc::new(event);
+
+        self.events.push_back(event.clone());
+        self.apply_retention();
+        self.persist(&event);
+        event
+    }
+
+    /// Saves the streamers the client listens to, or listened to before it disconnected.
+    pub fn save_subscriptions(&mut self, client_id: u64, listening_to: HashSet<String>) {
+        let subscriptions = ClientSubscriptions {
+            listening_to,
+            updated_at: now_sec(),
+        };
+        self.subscriptions.insert(client_id, subscriptions);
+        self.persist_subscriptions();
+    }
+
+    /// Forgets the subscriptions of the client.
+    pub fn remove_subscriptions(&mut self, client_id: u64) {
+        if self.subscriptions.remove(&client_id).is_some() {
+            self.persist_subscriptions();
+        }
+    }
+
+    /// Forgets the subscriptions of the disconnected clients whose missed events are already dropped.
+    pub fn prune_subscriptions(&mut self, is_connected: impl Fn(u64) -> bool) {
+        let oldest = now_sec().saturating_sub(self.config.max_age_secs);
+        let len_before = self.subscriptions.len();
+        self.subscriptions
+            .retain(|client_id, client| client.updated_at >= oldest || is_connected(*client_id));
+        if self.subscriptions.len() != len_before {
+            self.persist_subscriptions();
+        }
+    }
+
+    /// Returns the streamers the client listened to.
+    pub fn subscriptions(&self, client_id: u64) -> Option<HashSet<String>> {
+        self.subscriptions
+            .get(&client_id)
+            .map(|client| client.listening_to.clone())
+    }
+
+    /// Returns the journaled events with the IDs greater than `last_event_id`.
+    pub fn events_after(&self, last_event_id: u64) -> Vec<Arc<Event>> {
+        self.events
+            .iter()
+            .filter(|event| event.id().map_or(false, |id| id > last_event_id))
+            .cloned()
+            .collect()
+    }
+
+    fn apply_retention(&mut self) {
+        while self.events.len() > self.config.max_events {
+            self.events.pop_front();
+        }
+        let oldest_timestamp = now_sec().saturating_sub(self.config.max_age_secs);
+        while self
+            .events
+            .front()
+            .map_or(false, |event| event.timestamp < oldest_timestamp)
+        {
+            self.events.pop_front();
+        }
+    }
+
+    /// Queues the `event` to be written to the file. The file I/O is done by the writer thread,
+    /// so the journal (and the streaming manager) isn't locked while the file is written.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn persist(&mut self, event: &Event) {
+        use common::log::error;
+
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        // Rewrite the file from time to time to drop the events removed by the retention.
+        let write = if self.appended >= self.config.max_events {
+            match self.serialize_events() {
+                Ok(content) => JournalWrite::Rewrite(content),
+                Err(e) => return error!("Error serializing the event journal: {e}"),
+            }
+        } else {
+            match serde_json::to_string(&JournalRecord::from(event)) {
+                Ok(line) => JournalWrite::Append(line),
+                Err(e) => return error!("Error serializing a journaled event: {e}"),
+            }
+        };
+        let is_rewrite = matches!(write, JournalWrite::Rewrite(_));
+        if writer.send(write) {
+            self.appended = if is_rewrite { 0 } else { self.appended + 1 };
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn persist(&mut self, _event: &Event) {}
+
+    /// Queues the subscriptions of all the clients to be written to their file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn persist_subscriptions(&self) {
+        use common::log::error;
+
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        match serde_json::to_string(&self.subscriptions) {
+            Ok(content) => {
+                writer.send(JournalWrite::Subscriptions(content));
+            },
+            Err(e) => error!("Error serializing the client subscriptions: {e}"),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn persist_subscriptions(&self) {}
+
+    /// Serializes the events currently kept in the journal as the content of the journal file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn serialize_events(&self) -> Result<String, String> {
+        let mut content = String::new();
+        for event in self.events.iter() {
+            let line = serde_json::to_string(&JournalRecord::from(event.as_ref())).map_err(|e| e.to_string())?;
+            content.push_str(&line);
+            content.push('\n');
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+enum JournalWrite {
+    /// Appends the serialized record to the file.
+    Append(String),
+    /// Replaces the content of the file.
+    Rewrite(String),
+    /// Replaces the content of the client subscriptions file.
+    Subscriptions(String),
+}
+
+/// A dedicated thread writing the journal file in the order the writes were queued.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct JournalWriter {
+    tx: Option<std::sync::mpsc::Sender<JournalWrite>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl JournalWriter {
+    fn spawn(path: PathBuf, subscriptions_path: PathBuf) -> Result<Self, String> {
+        use common::log::error;
+        use std::io::Write;
+
+        let (tx, rx) = std::sync::mpsc::channel::<JournalWrite>();
+        let thread = std::thread::Builder::new()
+            .name("event_journal".to_string())
+            .spawn(move || {
+                for write in rx {
+                    let result = match write {
+                        JournalWrite::Append(line) => std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(&path)
+                            .and_then(|mut file| writeln!(file, "{line}"))
+                            .map_err(|e| format!("Error appending to {}: {e}", path.display())),
+                        JournalWrite::Rewrite(content) => rewrite_file(&path, &content),
+                        JournalWrite::Subscriptions(content) => rewrite_file(&subscriptions_path, &content),
+                    };
+                    if let Err(e) = result {
+                        error!("Error writing the event journal: {e}");
+                    }
+                }
+            })
+            .map_err(|e| format!("Error spawning the event journal writer: {e}"))?;
+        Ok(Self {
+            tx: Some(tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns false if the writer thread is gone.
+    fn send(&self, write: JournalWrite) -> bool { self.tx.as_ref().map_or(false, |tx| tx.send(write).is_ok()) }
+}
+
+/// Flushes the queued writes, so the journal can be reopened right after it's dropped.
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for JournalWriter {
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// The client subscriptions are kept next to the journal file at `journal_path`.
+#[cfg(not(target_arch = "wasm32"))]
+fn subscriptions_file_path(journal_path: &Path) -> PathBuf { journal_path.with_extension("subscriptions.json") }
+
+/// Replaces the content of the file at `path` atomically.
+#[cfg(not(target_arch = "wasm32"))]
+fn rewrite_file(path: &Path, content: &str) -> Result<(), String> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, content).map_err(|e| format!("Error writing {}: {e}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| format!("Error renaming {}: {e}", tmp_path.display()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<&Event> for JournalRecord {
+    fn from(event: &Event) -> Self {
+        Self {
+            id: event.id.unwrap_or_default(),
+            timestamp: event.timestamp,
+            event_type: event.event_type.clone(),
+            message: event.message.clone(),
+            error: event.error,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<JournalRecord> for Event {
+    fn from(record: JournalRecord) -> Self {
+        Self {
+            event_type: record.event_type,
+            message: record.message,
+            error: record.error,
+            id: Some(record.id),
+            timestamp: record.timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config(max_events: usize) -> EventJournalConfiguration {
+        EventJournalConfiguration {
+            max_events,
+            max_age_secs: 3600,
+        }
+    }
+
+    #[test]
+    fn test_journal_ids_and_retention() {
+        let mut journal = EventJournal::in_memory(config(2));
+        let first = journal.append(Event::new("test".to_string(), json!(1)));
+        let second = journal.append(Event::new("test".to_string(), json!(2)));
+        let third = journal.append(Event::new("test".to_string(), json!(3)));
+        assert!(first.id() < second.id() && second.id() < third.id());
+
+        // The first event is dropped by the retention.
+        let events = journal.events_after(0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].get().1, &json!(2));
+
+        let events = journal.events_after(second.id().unwrap());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), third.id());
+        assert!(journal.events_after(third.id().unwrap()).is_empty());
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_journal_reopen() {
+        let path = std::env::temp_dir().join(format!("event_journal_test_{}.jsonl", now_ms()));
+        let mut journal = EventJournal::open(config(10), path.clone()).unwrap();
+        journal.append(Event::new("test".to_string(), json!("a")));
+        let last = journal.append(Event::err("test".to_string(), json!("b")));
+        drop(journal);
+
+        let mut journal = EventJournal::open(config(10), path.clone()).unwrap();
+        let events = journal.events_after(0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(*events[1], *last);
+        let next = journal.append(Event::new("test".to_string(), json!("c")));
+        assert!(next.id() > last.id());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_journal_subscriptions_reopen() {
+        let path = std::env::temp_dir().join(format!("event_journal_subscriptions_test_{}.jsonl", now_ms()));
+        let mut journal = EventJournal::open(config(10), path.clone()).unwrap();
+        journal.save_subscriptions(1, HashSet::from(["streamer".to_string()]));
+        journal.save_subscriptions(2, HashSet::from(["other_streamer".to_string()]));
+        journal.remove_subscriptions(2);
+        drop(journal);
+
+        let mut journal = EventJournal::open(config(10), path.clone()).unwrap();
+        assert_eq!(journal.subscriptions(1), Some(HashSet::from(["streamer".to_string()])));
+        assert_eq!(journal.subscriptions(2), None);
+
+        // The subscriptions of the clients disconnected for longer than the retention period are forgotten.
+        journal.subscriptions.get_mut(&1).unwrap().updated_at = 0;
+        journal.prune_subscriptions(|client_id| client_id == 1);
+        assert!(journal.subscriptions(1).is_some());
+        journal.prune_subscriptions(|_| false);
+        assert_eq!(journal.subscriptions(1), None);
+        drop(journal);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(subscriptions_file_path(&path)).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_journal_file_compaction() {
+        let path = std::env::temp_dir().join(format!("event_journal_compaction_test_{}.jsonl", now_ms()));
+        let mut journal = EventJournal::open(config(2), path.clone()).unwrap();
+        for i in 0..5 {
+            journal.append(Event::new("test".to_string(), json!(i)));
+        }
+        drop(journal);
+
+        // The writes are flushed on drop and the file is compacted once `max_events` events are appended.
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines < 5, "the journal file isn't compacted: {lines} lines");
+
+        let journal = EventJournal::open(config(2), path.clone()).unwrap();
+        let events = journal.events_after(0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].get().1, &json!(3));
+        assert_eq!(events[1].get().1, &json!(4));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,10 +1,12 @@
 pub mod configuration;
 pub mod event;
+pub mod journal;
 pub mod manager;
 pub mod streamer;
 
 // Re-export important types.
-pub use configuration::EventStreamingConfiguration;
+pub use configuration::{EventJournalConfiguration, EventStreamingConfiguration};
 pub use event::Event;
+pub use journal::EventJournal;
 pub use manager::{StreamingManager, StreamingManagerError};
 pub use streamer::{Broadcaster, EventStreamer, NoDataIn, StreamHandlerInput};
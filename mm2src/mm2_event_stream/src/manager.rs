@@ -4,14 +4,15 @@ use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
 
 use crate::streamer::spawn;
-use crate::{Event, EventStreamer};
+use crate::{Event, EventJournal, EventStreamer};
 use common::executor::abortable_queue::WeakSpawner;
 use common::log::{error, LogOnError};
+use common::now_sec;
 
 use common::on_drop_callback::OnDropCallback;
 use futures::channel::mpsc::UnboundedSender;
 use futures::channel::oneshot;
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use tokio::sync::mpsc;
 
 /// The errors that could originate from the streaming manager.
@@ -94,12 +95,84 @@ impl ClientInfo {
     }
 }
 
+/// The subscriptions of a client that disconnected while the journal is enabled.
+#[derive(Debug)]
+struct DisconnectedClient {
+    /// The streamers the client was listening to.
+    listening_to: HashSet<String>,
+    disconnected_at: u64,
+}
+
 #[derive(Default, Debug)]
 struct StreamingManagerInner {
     /// A map from streamer IDs to their communication channels (if present) and shutdown handles.
     streamers: HashMap<String, StreamerInfo>,
     /// An inverse map from client IDs to the streamers they are listening to and the communication channel with the client.
     clients: HashMap<u64, ClientInfo>,
+    /// The journal of the broadcasted events, if enabled.
+    journal: Option<Mutex<EventJournal>>,
+    /// The streamers the disconnected clients were listening to.
+    /// Only used if the journal is enabled, the subscriptions are restored once the client reconnects.
+    /// The clients which don't reconnect within the journal retention period are forgotten.
+    disconnected_clients: HashMap<u64, DisconnectedClient>,
+}
+
+impl StreamingManagerInner {
+    /// Removes the client from the streamers it was listening to, terminating the streamers left without clients.
+    fn unsubscribe_client(&mut self, client_id: u64, listening_to: HashSet<String>) {
+        for streamer_id in listening_to {
+            if let Some(streamer_info) = self.streamers.get_mut(&streamer_id) {
+                streamer_info.remove_client(&client_id);
+            } else {
+                error!("Client {client_id} was listening to a non-existent streamer {streamer_id}. This is a bug!");
+            }
+            // If there are no more listening clients, terminate the streamer.
+            if self.streamers.get(&streamer_id).map(|info| info.clients.len()) == Some(0) {
+                self.streamers.remove(&streamer_id);
+            }
+        }
+    }
+
+    /// Saves the streamers the client listens to, or listened to before it disconnected, with the journal.
+    fn save_subscriptions(&self, client_id: u64) {
+        let Some(journal) = &self.journal else {
+            return;
+        };
+        let listening_to = match (self.clients.get(&client_id), self.disconnected_clients.get(&client_id)) {
+            (Some(client), _) => Some(client.listening_to.clone()),
+            (None, Some(client)) => Some(client.listening_to.clone()),
+            (None, None) => None,
+        };
+        match listening_to {
+            Some(listening_to) => journal.lock().save_subscriptions(client_id, listening_to),
+            None => journal.lock().remove_subscriptions(client_id),
+        }
+    }
+
+    /// Forgets the disconnected clients whose missed events are already dropped from the journal.
+    fn prune_disconnected_clients(&mut self) {
+        let Some(max_age_secs) = self.journal.as_ref().map(|journal| journal.lock().max_age_secs()) else {
+            return;
+        };
+        let oldest = now_sec().saturating_sub(max_age_secs);
+        let expired: Vec<u64> = self
+            .disconnected_clients
+            .iter()
+            .filter(|(_, client)| client.disconnected_at < oldest)
+            .map(|(client_id, _)| *client_id)
+            .collect();
+        for client_id in expired {
+            if let Some(client) = self.disconnected_clients.remove(&client_id) {
+                self.unsubscribe_client(client_id, client.listening_to);
+                self.save_subscriptions(client_id);
+            }
+        }
+        if let Some(journal) = &self.journal {
+            journal.lock().prune_subscriptions(|client_id| {
+                self.clients.contains_key(&client_id) || self.disconnected_clients.contains_key(&client_id)
+            });
+        }
+    }
 }
 
 #[derive(Clone, Default, Debug)]
@@ -112,6 +185,12 @@ impl StreamingManager {
     /// Returns a write guard over the streaming manager.
     fn write(&self) -> RwLockWriteGuard<StreamingManagerInner> { self.0.write() }
 
+    /// Enables journaling of the broadcasted events.
+    ///
+    /// Once enabled, the streamers of the disconnected clients keep running and their events are journaled,
+    /// so the clients reconnecting with [`StreamingManager::reconnect_client`] receive the events they missed.
+    pub fn enable_journal(&self, journal: EventJournal) { self.write().journal = Some(Mutex::new(journal)); }
+
     /// Spawns and adds a new streamer `streamer` to the manager.
     pub async fn add(
         &self,
@@ -144,6 +223,7 @@ impl StreamingManager {
                 if let Some(client_info) = this.clients.get_mut(&client_id) {
                     client_info.add_streamer(streamer_id.clone());
                 }
+                this.save_subscriptions(client_id);
                 return Ok(streamer_id);
             }
         }
@@ -164,6 +244,7 @@ impl StreamingManager {
                 .entry(streamer_id.clone())
                 .or_insert(streamer_info)
                 .add_client(client_id);
+            this.save_subscriptions(client_id);
         } else {
             // The client was removed while we were spawning the streamer.
             // We no longer have a connection for it.
@@ -214,6 +295,7 @@ impl StreamingManager {
             .get_mut(&client_id)
             .ok_or(StreamingManagerError::UnknownClient)?;
         client_info.remove_streamer(streamer_id);
+        this.save_subscriptions(client_id);
 
         this.streamers
             .get_mut(streamer_id)
@@ -233,8 +315,12 @@ impl StreamingManager {
     /// this method broadcasts an event to the listening *clients* directly, independently
     /// of any streamer (i.e. bypassing any streamer).
     pub fn broadcast(&self, event: Event) {
-        let event = Arc::new(event);
         let this = self.read();
+        // The event is journaled even if no client is connected at the moment.
+        let event = match &this.journal {
+            Some(journal) => journal.lock().append(event),
+            None => Arc::new(event),
+        };
         if let Some(client_ids) = this.streamers.get(event.origin()).map(|info| &info.clients) {
             client_ids.iter().for_each(|client_id| {
                 if let Some(info) = this.clients.get(client_id) {
@@ -267,14 +353,69 @@ impl StreamingManager {
     /// Creates a new client and returns the event receiver for this client.
     pub fn new_client(&self, client_id: u64) -> Result<ClientHandle, StreamingManagerError> {
         let mut this = self.write();
+        self.register_client(&mut this, client_id)
+    }
+
+    /// Same as [`StreamingManager::new_client`], but also returns the journaled events with the IDs greater than
+    /// `last_event_id`, i.e. the events the client missed while it was disconnected.
+    ///
+    /// The client is registered while the journal is locked, so no event is either missed or received twice.
+    /// The client keeps listening to the streamers it was listening to before the disconnection,
+    /// and only the events of these streamers are returned. The subscriptions are persisted with the journal,
+    /// so the missed events are also replayed to the clients that were connected before the node restarted,
+    /// though these clients have to subscribe to the streamers again.
+    pub fn reconnect_client(
+        &self,
+        client_id: u64,
+        last_event_id: u64,
+    ) -> Result<(ClientHandle, Vec<Arc<Event>>), StreamingManagerError> {
+        let mut this = self.write();
+        this.prune_disconnected_clients();
+        // Only the events of the streamers the client was listening to are replayed.
+        let missed_events = match &this.journal {
+            Some(journal) => {
+                let journal = journal.lock();
+                let listening_to = match this.disconnected_clients.get(&client_id) {
+                    Some(client) => Some(client.listening_to.clone()),
+                    // The client isn't known since the node restarted, its subscriptions are loaded from the journal.
+                    None => journal.subscriptions(client_id),
+                };
+                match listening_to {
+                    Some(listening_to) => journal
+                        .events_after(last_event_id)
+                        .into_iter()
+                        .filter(|event| listening_to.contains(event.origin()))
+                        .collect(),
+                    None => Vec::new(),
+                }
+            },
+            None => Vec::new(),
+        };
+        let handle = self.register_client(&mut this, client_id)?;
+        Ok((handle, missed_events))
+    }
+
+    fn register_client(
+        &self,
+        this: &mut StreamingManagerInner,
+        client_id: u64,
+    ) -> Result<ClientHandle, StreamingManagerError> {
         if this.clients.contains_key(&client_id) {
             return Err(StreamingManagerError::ClientExists);
         }
         // Note that events queued in the channel are `Arc<` shared.
         // So a 1024 long buffer isn't actually heavy on memory.
         let (tx, rx) = mpsc::channel(1024);
-        let client_info = ClientInfo::new(tx);
+        let mut client_info = ClientInfo::new(tx);
+        if let Some(client) = this.disconnected_clients.remove(&client_id) {
+            client
+                .listening_to
+                .into_iter()
+                .filter(|streamer_id| this.streamers.contains_key(streamer_id))
+                .for_each(|streamer_id| client_info.add_streamer(streamer_id));
+        }
         this.clients.insert(client_id, client_info);
+        this.save_subscriptions(client_id);
         let manager = self.clone();
         Ok(ClientHandle {
             rx,
@@ -292,18 +433,18 @@ impl StreamingManager {
             .clients
             .remove(&client_id)
             .ok_or(StreamingManagerError::UnknownClient)?;
-        // Remove the client from all the streamers it was listening to.
-        for streamer_id in client_info.listening_to {
-            if let Some(streamer_info) = this.streamers.get_mut(&streamer_id) {
-                streamer_info.remove_client(&client_id);
-            } else {
-                error!("Client {client_id} was listening to a non-existent streamer {streamer_id}. This is a bug!");
-            }
-            // If there are no more listening clients, terminate the streamer.
-            if this.streamers.get(&streamer_id).map(|info| info.clients.len()) == Some(0) {
-                this.streamers.remove(&streamer_id);
-            }
+        // Keep the streamers running, so their events are journaled until the client reconnects.
+        if this.journal.is_some() {
+            this.prune_disconnected_clients();
+            this.disconnected_clients.insert(client_id, DisconnectedClient {
+                listening_to: client_info.listening_to,
+                disconnected_at: now_sec(),
+            });
+            this.save_subscriptions(client_id);
+            return Ok(());
         }
+        // Remove the client from all the streamers it was listening to.
+        this.unsubscribe_client(client_id, client_info.listening_to);
         Ok(())
     }
 
@@ -329,6 +470,10 @@ impl StreamingManager {
             if let Some(info) = this.clients.get_mut(&client_id) {
                 info.remove_streamer(streamer_id);
             }
+            if let Some(client) = this.disconnected_clients.get_mut(&client_id) {
+                client.listening_to.remove(streamer_id);
+            }
+            this.save_subscriptions(client_id);
         }
     }
 }
@@ -550,4 +695,128 @@ mod tests {
             .unwrap()
             .listens_to(&streamer_id));
     });
+
+    cross_test!(test_reconnect_client_with_journal, {
+        let manager = StreamingManager::default();
+        manager.enable_journal(EventJournal::in_memory(Default::default()));
+        let system = AbortableQueue::default();
+        let client_id = 1;
+        let mut client = manager.new_client(client_id).unwrap();
+        let streamer_id = manager
+            .add(client_id, ReactiveStreamer, system.weak_spawner())
+            .await
+            .unwrap();
+
+        manager.send(&streamer_id, "send1".to_string()).unwrap();
+        Timer::sleep(0.1).await;
+        let last_event_id = client.try_recv().unwrap().id().unwrap();
+
+        // The streamer keeps running while the client is disconnected.
+        drop(client);
+        manager.send(&streamer_id, "send2".to_string()).unwrap();
+        Timer::sleep(0.1).await;
+
+        let (mut client, missed_events) = manager.reconnect_client(client_id, last_event_id).unwrap();
+        assert_eq!(missed_events.len(), 1);
+        assert_eq!(missed_events[0].get().1, &json!("send2"));
+
+        // The client is still listening to the streamer.
+        manager.send(&streamer_id, "send3".to_string()).unwrap();
+        Timer::sleep(0.1).await;
+        let event = client.try_recv().unwrap();
+        assert_eq!(event.get().1, &json!("send3"));
+        assert!(event.id() > missed_events[0].id());
+    });
+
+    cross_test!(test_reconnect_client_replays_own_streamers_only, {
+        let manager = StreamingManager::default();
+        manager.enable_journal(EventJournal::in_memory(Default::default()));
+        let system = AbortableQueue::default();
+        let client_id = 1;
+        let client = manager.new_client(client_id).unwrap();
+        let streamer_id = manager
+            .add(client_id, ReactiveStreamer, system.weak_spawner())
+            .await
+            .unwrap();
+        drop(client);
+
+        // An event of a streamer the client never listened to is journaled as well.
+        manager.broadcast(Event::new("other_streamer".to_string(), json!("other")));
+        manager.send(&streamer_id, "send1".to_string()).unwrap();
+        Timer::sleep(0.1).await;
+
+        let (_client, missed_events) = manager.reconnect_client(client_id, 0).unwrap();
+        assert_eq!(missed_events.len(), 1);
+        assert_eq!(missed_events[0].origin(), streamer_id);
+
+        // A client that wasn't connected before doesn't receive the journaled events.
+        let (_client, missed_events) = manager.reconnect_client(2, 0).unwrap();
+        assert!(missed_events.is_empty());
+    });
+
+    cross_test!(test_disconnected_clients_pruned, {
+        let manager = StreamingManager::default();
+        manager.enable_journal(EventJournal::in_memory(Default::default()));
+        let system = AbortableQueue::default();
+        let client_id = 1;
+        let client = manager.new_client(client_id).unwrap();
+        let streamer_id = manager
+            .add(client_id, ReactiveStreamer, system.weak_spawner())
+            .await
+            .unwrap();
+        drop(client);
+        assert!(manager.read().streamers.contains_key(&streamer_id));
+
+        // The client disconnected earlier than the journal retention period.
+        manager
+            .write()
+            .disconnected_clients
+            .get_mut(&client_id)
+            .unwrap()
+            .disconnected_at = 0;
+        let other_client = manager.new_client(2).unwrap();
+        drop(other_client);
+
+        // The client is forgotten and the streamer it was listening to is terminated.
+        assert!(!manager.read().disconnected_clients.contains_key(&client_id));
+        assert!(!manager.read().streamers.contains_key(&streamer_id));
+        let this = manager.read();
+        assert_eq!(this.journal.as_ref().unwrap().lock().subscriptions(client_id), None);
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    cross_test!(test_reconnect_client_after_restart, {
+        let path = std::env::temp_dir().join(format!("event_journal_restart_test_{}.jsonl", common::now_ms()));
+        let manager = StreamingManager::default();
+        manager.enable_journal(EventJournal::open(Default::default(), path.clone()).unwrap());
+        let system = AbortableQueue::default();
+        let client_id = 1;
+        let client = manager.new_client(client_id).unwrap();
+        let streamer_id = manager
+            .add(client_id, ReactiveStreamer, system.weak_spawner())
+            .await
+            .unwrap();
+        drop(client);
+
+        manager.broadcast(Event::new("other_streamer".to_string(), json!("other")));
+        manager.send(&streamer_id, "send1".to_string()).unwrap();
+        Timer::sleep(0.1).await;
+        // Replacing the journal drops the old one, which flushes its writes to the files.
+        manager.enable_journal(EventJournal::in_memory(Default::default()));
+
+        // The node restarted, the new manager knows nothing about the clients connected before.
+        let manager = StreamingManager::default();
+        manager.enable_journal(EventJournal::open(Default::default(), path.clone()).unwrap());
+        let (_client, missed_events) = manager.reconnect_client(client_id, 0).unwrap();
+        assert_eq!(missed_events.len(), 1);
+        assert_eq!(missed_events[0].origin(), streamer_id);
+        assert_eq!(missed_events[0].get().1, &json!("send1"));
+
+        let (_client, missed_events) = manager.reconnect_client(2, 0).unwrap();
+        assert!(missed_events.is_empty());
+
+        manager.enable_journal(EventJournal::in_memory(Default::default()));
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("subscriptions.json")).unwrap();
+    });
 }
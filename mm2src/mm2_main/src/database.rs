@@ -1,9 +1,11 @@
 /// The module responsible to work with SQLite database
 ///
+pub mod banned_pubkeys;
 pub mod my_orders;
 pub mod my_swaps;
 pub mod stats_nodes;
 pub mod stats_swaps;
+pub mod swap_v2_watcher_guards;
 
 use crate::CREATE_MY_SWAPS_TABLE;
 use common::log::{debug, error, info};
@@ -126,6 +128,17 @@ fn migration_13() -> Vec<(&'static str, Vec<String>)> {
     ]
 }
 
+fn migration_14() -> Vec<(&'static str, Vec<String>)> {
+    vec![
+        (banned_pubkeys::CREATE_BANNED_PUBKEYS_TABLE, vec![]),
+        (banned_pubkeys::ADD_PUBKEY_INDEX, vec![]),
+    ]
+}
+
+fn migration_15() -> Vec<(&'static str, Vec<String>)> {
+    vec![(swap_v2_watcher_guards::CREATE_SWAP_V2_WATCHER_GUARDS_TABLE, vec![])]
+}
+
 async fn statements_for_migration(ctx: &MmArc, current_migration: i64) -> Option<Vec<(&'static str, Vec<String>)>> {
     match current_migration {
         1 => Some(migration_1(ctx).await),
@@ -141,6 +154,8 @@ async fn statements_for_migration(ctx: &MmArc, current_migration: i64) -> Option
         11 => Some(migration_11()),
         12 => Some(migration_12()),
         13 => Some(migration_13()),
+        14 => Some(migration_14()),
+        15 => Some(migration_15()),
         _ => None,
     }
 }
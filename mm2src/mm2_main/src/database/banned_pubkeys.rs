@@ -0,0 +1,92 @@
+/// This module contains code to work with banned_pubkeys table in MM2 SQLite DB
+use crate::lp_swap::{BanReason, BanRecord, UnbanReason};
+use common::log::debug;
+use db_common::sqlite::rusqlite::types::Type as SqlType;
+use db_common::sqlite::rusqlite::{params_from_iter, Error as SqlError, Result as SqlResult, Row};
+use mm2_core::mm_ctx::MmArc;
+use std::str::FromStr;
+
+pub const CREATE_BANNED_PUBKEYS_TABLE: &str = "CREATE TABLE IF NOT EXISTS banned_pubkeys (
+    id INTEGER NOT NULL PRIMARY KEY,
+    pubkey VARCHAR(255) NOT NULL,
+    reason_type VARCHAR(255) NOT NULL,
+    reason TEXT NOT NULL,
+    banned_at INTEGER NOT NULL,
+    expires_at INTEGER,
+    unbanned_at INTEGER,
+    unban_reason VARCHAR(255)
+);";
+
+pub const ADD_PUBKEY_INDEX: &str = "CREATE INDEX IF NOT EXISTS banned_pubkeys_pubkey ON banned_pubkeys (pubkey);";
+
+const INSERT_BAN: &str =
+    "INSERT INTO banned_pubkeys (pubkey, reason_type, reason, banned_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)";
+
+/// Closes the active ban record of the pubkey. Older records have `unbanned_at` set already and stay untouched.
+const UPDATE_UNBANNED: &str =
+    "UPDATE banned_pubkeys SET unbanned_at = ?2, unban_reason = ?3 WHERE pubkey = ?1 AND unbanned_at IS NULL";
+
+const SELECT_BAN_RECORDS: &str = "SELECT pubkey, reason, banned_at, expires_at, unbanned_at, unban_reason FROM banned_pubkeys ORDER BY banned_at DESC, id DESC";
+
+const SELECT_ACTIVE_BAN_RECORDS: &str = "SELECT pubkey, reason, banned_at, expires_at, unbanned_at, unban_reason FROM banned_pubkeys WHERE unbanned_at IS NULL ORDER BY banned_at DESC, id DESC";
+
+pub fn insert_ban(ctx: &MmArc, record: &BanRecord) -> SqlResult<()> {
+    debug!("Inserting the ban of pubkey {} to the SQLite database", record.pubkey);
+    let reason = serde_json::to_string(&record.reason).map_err(|e| SqlError::ToSqlConversionFailure(Box::new(e)))?;
+    let params = vec![
+        Some(record.pubkey.to_string()),
+        Some(record.reason.reason_type().to_string()),
+        Some(reason),
+        Some(record.banned_at.to_string()),
+        record.expires_at.map(|expires_at| expires_at.to_string()),
+    ];
+    let conn = ctx.sqlite_connection();
+    conn.execute(INSERT_BAN, params_from_iter(params.iter())).map(|_| ())
+}
+
+pub fn update_unbanned(ctx: &MmArc, pubkey: &str, unbanned_at: u64, unban_reason: UnbanReason) -> SqlResult<()> {
+    debug!("Marking pubkey {} as unbanned in the SQLite database", pubkey);
+    let params = vec![pubkey.to_owned(), unbanned_at.to_string(), unban_reason.to_string()];
+    let conn = ctx.sqlite_connection();
+    conn.execute(UPDATE_UNBANNED, params_from_iter(params.iter()))
+        .map(|_| ())
+}
+
+pub fn select_ban_records(ctx: &MmArc, only_active: bool) -> SqlResult<Vec<BanRecord>> {
+    let query = if only_active {
+        SELECT_ACTIVE_BAN_RECORDS
+    } else {
+        SELECT_BAN_RECORDS
+    };
+    let conn = ctx.sqlite_connection();
+    let mut stmt = conn.prepare(query)?;
+    let records = stmt
+        .query_map([], ban_record_from_row)?
+        .collect::<SqlResult<Vec<BanRecord>>>()?;
+    Ok(records)
+}
+
+fn ban_record_from_row(row: &Row<'_>) -> SqlResult<BanRecord> {
+    let pubkey: String = row.get(0)?;
+    let reason: String = row.get(1)?;
+    let unban_reason: Option<String> = row.get(5)?;
+
+    let pubkey = pubkey
+        .parse()
+        .map_err(|e| SqlError::FromSqlConversionFailure(0, SqlType::Text, Box::new(e)))?;
+    let reason: BanReason =
+        serde_json::from_str(&reason).map_err(|e| SqlError::FromSqlConversionFailure(1, SqlType::Text, Box::new(e)))?;
+    let unban_reason = unban_reason
+        .map(|s| UnbanReason::from_str(&s))
+        .transpose()
+        .map_err(|e| SqlError::FromSqlConversionFailure(5, SqlType::Text, e.into()))?;
+
+    Ok(BanRecord {
+        pubkey,
+        reason,
+        banned_at: row.get::<_, i64>(2)? as u64,
+        expires_at: row.get::<_, Option<i64>>(3)?.map(|t| t as u64),
+        unbanned_at: row.get::<_, Option<i64>>(4)?.map(|t| t as u64),
+        unban_reason,
+    })
+}
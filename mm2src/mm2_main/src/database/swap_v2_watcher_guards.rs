@@ -0,0 +1,80 @@
+/// This module contains code to work with swap_v2_watcher_guards table in MM2 SQLite DB
+use crate::lp_swap::SwapV2WatcherData;
+use common::log::debug;
+use db_common::sqlite::rusqlite::types::Type as SqlType;
+use db_common::sqlite::rusqlite::{params_from_iter, Error as SqlError, Result as SqlResult, Row};
+use mm2_core::mm_ctx::MmArc;
+
+pub const CREATE_SWAP_V2_WATCHER_GUARDS_TABLE: &str = "CREATE TABLE IF NOT EXISTS swap_v2_watcher_guards (
+    id INTEGER NOT NULL PRIMARY KEY,
+    payment_txid VARCHAR(255) NOT NULL,
+    guard_type VARCHAR(255) NOT NULL,
+    uuid VARCHAR(255) NOT NULL,
+    data TEXT NOT NULL,
+    added_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    UNIQUE(payment_txid, guard_type)
+);";
+
+/// A guard is identified by the guarded payment rather than by the swap uuid which anyone can reuse.
+/// The guard received again (e.g. after the swap side restarted) is ignored, so its `finished_at` isn't reset.
+const INSERT_GUARD: &str = "INSERT OR IGNORE INTO swap_v2_watcher_guards \
+    (payment_txid, guard_type, uuid, data, added_at) VALUES (?1, ?2, ?3, ?4, ?5)";
+
+const UPDATE_FINISHED: &str =
+    "UPDATE swap_v2_watcher_guards SET finished_at = ?3 WHERE payment_txid = ?1 AND guard_type = ?2";
+
+const SELECT_ACTIVE_GUARDS: &str =
+    "SELECT payment_txid, data FROM swap_v2_watcher_guards WHERE finished_at IS NULL ORDER BY id";
+
+/// Returns false if the guard of the payment has been inserted already.
+pub fn insert_guard(ctx: &MmArc, payment_txid: &str, data: &SwapV2WatcherData, added_at: u64) -> SqlResult<bool> {
+    debug!(
+        "Inserting the {} guard of swap {} to the SQLite database",
+        data.guard.guard_type(),
+        data.uuid
+    );
+    let json = serde_json::to_string(data).map_err(|e| SqlError::ToSqlConversionFailure(Box::new(e)))?;
+    let params = vec![
+        payment_txid.to_owned(),
+        data.guard.guard_type().to_owned(),
+        data.uuid.to_string(),
+        json,
+        added_at.to_string(),
+    ];
+    let conn = ctx.sqlite_connection();
+    conn.execute(INSERT_GUARD, params_from_iter(params.iter()))
+        .map(|inserted| inserted > 0)
+}
+
+pub fn update_finished(ctx: &MmArc, payment_txid: &str, data: &SwapV2WatcherData, finished_at: u64) -> SqlResult<()> {
+    debug!(
+        "Marking the {} guard of swap {} as finished in the SQLite database",
+        data.guard.guard_type(),
+        data.uuid
+    );
+    let params = vec![
+        payment_txid.to_owned(),
+        data.guard.guard_type().to_owned(),
+        finished_at.to_string(),
+    ];
+    let conn = ctx.sqlite_connection();
+    conn.execute(UPDATE_FINISHED, params_from_iter(params.iter()))
+        .map(|_| ())
+}
+
+/// Returns the active guards along with the txids of their payments.
+pub fn select_active_guards(ctx: &MmArc) -> SqlResult<Vec<(String, SwapV2WatcherData)>> {
+    let conn = ctx.sqlite_connection();
+    let mut stmt = conn.prepare(SELECT_ACTIVE_GUARDS)?;
+    let guards = stmt.query_map([], guard_from_row)?.collect::<SqlResult<Vec<_>>>()?;
+    Ok(guards)
+}
+
+fn guard_from_row(row: &Row<'_>) -> SqlResult<(String, SwapV2WatcherData)> {
+    let payment_txid = row.get(0)?;
+    let json: String = row.get(1)?;
+    let data =
+        serde_json::from_str(&json).map_err(|e| SqlError::FromSqlConversionFailure(1, SqlType::Text, Box::new(e)))?;
+    Ok((payment_txid, data))
+}
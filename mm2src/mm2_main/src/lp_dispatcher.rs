@@ -1,5 +1,5 @@
 use crate::lp_ordermatch::TradingBotEvent;
-use crate::lp_swap::MakerSwapStatusChanged;
+use crate::lp_swap::{MakerSwapStatusChanged, SwapStatusChanged};
 use async_std::sync::RwLock;
 use mm2_core::{event_dispatcher::{Dispatcher, EventUniqueId},
                mm_ctx::{from_ctx, MmArc}};
@@ -17,6 +17,7 @@ impl StopCtxEvent {
 pub enum LpEvents {
     MakerSwapStatusChanged(MakerSwapStatusChanged),
     StopCtxEvent(StopCtxEvent),
+    SwapStatusChanged(SwapStatusChanged),
     TradingBotEvent(TradingBotEvent),
 }
 
@@ -33,6 +34,7 @@ impl EventUniqueId for LpEvents {
         match self {
             LpEvents::MakerSwapStatusChanged(_) => MakerSwapStatusChanged::event_id(),
             LpEvents::StopCtxEvent(_) => StopCtxEvent::event_id(),
+            LpEvents::SwapStatusChanged(_) => SwapStatusChanged::event_id(),
             LpEvents::TradingBotEvent(event) => event.event_id(),
         }
     }
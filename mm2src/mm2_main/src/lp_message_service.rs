@@ -1,57 +1,96 @@
 #[path = "notification/telegram/telegram.rs"] pub mod telegram;
+#[path = "notification/webhook/webhook.rs"] pub mod webhook;
 
 use crate::lp_message_service::telegram::{ChatIdRegistry, TelegramError, TgClient};
+use crate::lp_message_service::webhook::{WebhookCfg, WebhookClient, WebhookError};
+use crate::lp_ordermatch::order_events::OrderStatusStreamer;
+use crate::lp_swap::swap_events::SwapStatusStreamer;
 use async_trait::async_trait;
+use common::executor::SpawnFuture;
+use common::log::{error, info};
 use derive_more::Display;
+use futures::future::join_all;
 use futures::lock::Mutex as AsyncMutex;
 use mm2_core::mm_ctx::from_ctx;
 use mm2_core::mm_ctx::MmArc;
 use mm2_err_handle::prelude::*;
+use mm2_event_stream::Event;
 use serde_json::{self as json};
 use std::sync::Arc;
 
 pub type MessageResult<T> = Result<T, MmError<MessageError>>;
 pub const MAKER_BOT_ROOM_ID: &str = "maker_bot";
 pub const DEFAULT_ROOM_ID: &str = "default";
+/// The streaming client ID the events routed to the message services are received with.
+/// The streamers that need parameters (e.g. `stream::balance::enable`) are enabled for this client via RPC.
+pub const NOTIFICATION_CLIENT_ID: u64 = u32::MAX as u64;
 
 #[derive(Debug, Deserialize, Display, Serialize, SerializeErrorType)]
 #[serde(tag = "error_type", content = "error_data")]
 pub enum MessageError {
     #[display(fmt = "{}", _0)]
     TelegramError(TelegramError),
+    #[display(fmt = "{}", _0)]
+    WebhookError(WebhookError),
 }
 
 impl From<TelegramError> for MessageError {
     fn from(e: TelegramError) -> Self { MessageError::TelegramError(e) }
 }
 
+impl From<WebhookError> for MessageError {
+    fn from(e: WebhookError) -> Self { MessageError::WebhookError(e) }
+}
+
 #[async_trait]
 pub trait MessageServiceTraits {
     async fn send_message(&self, message: String, room_id: &str, disable_notification: bool) -> MessageResult<bool>;
+
+    /// Sends a streamer event, the services that can't carry structured data send it as a text message.
+    async fn send_event(&self, event: &Event, room_id: &str, disable_notification: bool) -> MessageResult<bool> {
+        let (event_type, message) = event.get();
+        self.send_message(format!("{}: {}", event_type, message), room_id, disable_notification)
+            .await
+    }
 }
 
-#[derive(Default)]
+/// The services are shared, so the [`MessageService`] can be cloned to send the messages without holding
+/// the [`MessageServiceContext::message_service`] lock.
+#[derive(Clone, Default)]
 pub struct MessageService {
-    services: Vec<Box<dyn MessageServiceTraits + Send + Sync>>,
+    services: Vec<Arc<dyn MessageServiceTraits + Send + Sync>>,
 }
 
 impl MessageService {
+    /// Sends the message with every service concurrently even if some of them fail, the first error is returned.
     pub async fn send_message(
         &self,
         message: String,
         room_id: &str,
         disable_notification: bool,
     ) -> MessageResult<bool> {
-        for service in self.services.iter() {
-            service
-                .send_message(message.clone(), room_id, disable_notification)
-                .await?;
-        }
-        Ok(true)
+        let results = join_all(
+            self.services
+                .iter()
+                .map(|service| service.send_message(message.clone(), room_id, disable_notification)),
+        )
+        .await;
+        first_error(results)
+    }
+
+    /// Sends the event with every service concurrently even if some of them fail, the first error is returned.
+    pub async fn send_event(&self, event: &Event, room_id: &str, disable_notification: bool) -> MessageResult<bool> {
+        let results = join_all(
+            self.services
+                .iter()
+                .map(|service| service.send_event(event, room_id, disable_notification)),
+        )
+        .await;
+        first_error(results)
     }
 
     pub fn attach_service(&mut self, service: Box<dyn MessageServiceTraits + Send + Sync>) -> &MessageService {
-        self.services.push(service);
+        self.services.push(Arc::from(service));
         self
     }
 
@@ -62,6 +101,10 @@ impl MessageService {
     pub fn new() -> Self { Default::default() }
 }
 
+fn first_error(results: Vec<MessageResult<bool>>) -> MessageResult<bool> {
+    results.into_iter().find_map(Result::err).map_or(Ok(true), Err)
+}
+
 #[derive(Default)]
 pub struct MessageServiceContext {
     pub message_service: AsyncMutex<MessageService>,
@@ -79,6 +122,11 @@ impl MessageServiceContext {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MessageServiceCfg {
     telegram: Option<Telegram>,
+    #[serde(default)]
+    webhooks: Vec<WebhookCfg>,
+    /// Routes the streamer events to the message services.
+    #[serde(default)]
+    event_routes: Vec<EventRoute>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,10 +135,90 @@ pub struct Telegram {
     chat_registry: ChatIdRegistry,
 }
 
+fn default_room_id() -> String { DEFAULT_ROOM_ID.to_string() }
+
+/// Sends the events matching the filter to the `room_id` of the message services.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct EventRoute {
+    /// The IDs of the streamers the events of which are routed, e.g. `SWAP_STATUS`, `ORDER_STATUS` or `BALANCE:KMD`.
+    /// A trailing `*` matches any suffix, e.g. `BALANCE:*`.
+    event_types: Vec<String>,
+    /// Only the error events are routed if set.
+    #[serde(default)]
+    errors_only: bool,
+    #[serde(default = "default_room_id")]
+    room_id: String,
+    #[serde(default)]
+    disable_notification: bool,
+}
+
+impl EventRoute {
+    fn matches_event_type(&self, event_type: &str) -> bool {
+        self.event_types.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => event_type.starts_with(prefix),
+            None => event_type == pattern,
+        })
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        (!self.errors_only || event.is_error()) && self.matches_event_type(event.origin())
+    }
+}
+
 #[derive(Display)]
 pub enum InitMessageServiceError {
     #[display(fmt = "Error deserializing '{}' config field: {}", field, error)]
     ErrorDeserializingConfig { field: String, error: String },
+    #[display(fmt = "Error starting the event notifications: {}", _0)]
+    EventRouterError(String),
+}
+
+/// Subscribes the notification client to the events of the `routes` and sends the matching events to the message services.
+///
+/// The swap and order status streamers are enabled here, the rest are expected to be enabled
+/// for [`NOTIFICATION_CLIENT_ID`] with the `stream::*::enable` RPCs.
+async fn start_event_router(ctx: &MmArc, routes: Vec<EventRoute>) -> Result<(), MmError<InitMessageServiceError>> {
+    let manager = &ctx.event_stream_manager;
+    let mut events = manager
+        .new_client(NOTIFICATION_CLIENT_ID)
+        .map_to_mm(|e| InitMessageServiceError::EventRouterError(format!("{:?}", e)))?;
+
+    let routed = |streamer_id: &str| routes.iter().any(|route| route.matches_event_type(streamer_id));
+    if routed(SwapStatusStreamer::derive_streamer_id()) {
+        manager
+            .add(NOTIFICATION_CLIENT_ID, SwapStatusStreamer::new(), ctx.spawner())
+            .await
+            .map_to_mm(|e| InitMessageServiceError::EventRouterError(format!("{:?}", e)))?;
+    }
+    if routed(OrderStatusStreamer::derive_streamer_id()) {
+        manager
+            .add(NOTIFICATION_CLIENT_ID, OrderStatusStreamer::new(), ctx.spawner())
+            .await
+            .map_to_mm(|e| InitMessageServiceError::EventRouterError(format!("{:?}", e)))?;
+    }
+
+    let router_ctx = ctx.clone();
+    ctx.spawner().spawn(async move {
+        let message_service_ctx = MessageServiceContext::from_ctx(&router_ctx).unwrap();
+        while let Some(event) = events.recv().await {
+            // Don't hold the lock while the webhooks are retried.
+            let message_service = message_service_ctx.message_service.lock().await.clone();
+            let deliveries = routes
+                .iter()
+                .filter(|route| route.matches(&event))
+                .map(|route| message_service.send_event(&event, &route.room_id, route.disable_notification));
+            for result in join_all(deliveries).await {
+                if let Err(e) = result {
+                    error!("Error sending the '{}' event notification: {}", event.origin(), e);
+                }
+            }
+        }
+    });
+    info!(
+        "Event notifications are started for the client {}",
+        NOTIFICATION_CLIENT_ID
+    );
+    Ok(())
 }
 
 pub async fn init_message_service(ctx: &MmArc) -> Result<(), MmError<InitMessageServiceError>> {
@@ -104,6 +232,9 @@ pub async fn init_message_service(ctx: &MmArc) -> Result<(), MmError<InitMessage
     if let Some(message_service_cfg) = maybe_cfg {
         let message_service_ctx = MessageServiceContext::from_ctx(ctx).unwrap();
         let mut message_service = message_service_ctx.message_service.lock().await;
+        for webhook_cfg in message_service_cfg.webhooks {
+            message_service.attach_service(Box::new(WebhookClient::new(webhook_cfg)));
+        }
         if let Some(telegram) = message_service_cfg.telegram {
             let tg_client = TgClient::new(telegram.api_key, None, telegram.chat_registry);
             message_service.attach_service(Box::new(tg_client));
@@ -115,6 +246,11 @@ pub async fn init_message_service(ctx: &MmArc) -> Result<(), MmError<InitMessage
                 )
                 .await;
         }
+        drop(message_service);
+
+        if !message_service_cfg.event_routes.is_empty() {
+            start_event_router(ctx, message_service_cfg.event_routes).await?;
+        }
     }
     Ok(())
 }
@@ -122,10 +258,55 @@ pub async fn init_message_service(ctx: &MmArc) -> Result<(), MmError<InitMessage
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod message_service_tests {
     use crate::lp_message_service::telegram::{ChatIdRegistry, TgClient};
-    use crate::lp_message_service::MessageService;
+    use crate::lp_message_service::webhook::WebhookError;
+    use crate::lp_message_service::{EventRoute, MessageError, MessageResult, MessageService, MessageServiceCfg,
+                                    MessageServiceTraits, DEFAULT_ROOM_ID};
+    use async_trait::async_trait;
     use common::block_on;
+    use mm2_err_handle::prelude::*;
+    use mm2_event_stream::Event;
+    use serde_json::{self as json, json};
     use std::collections::HashMap;
     use std::env::var;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the sent messages, fails every send if `fail` is set.
+    struct MockService {
+        sent: Arc<Mutex<Vec<String>>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl MessageServiceTraits for MockService {
+        async fn send_message(
+            &self,
+            message: String,
+            _room_id: &str,
+            _disable_notification: bool,
+        ) -> MessageResult<bool> {
+            if self.fail {
+                return MmError::err(MessageError::WebhookError(WebhookError::InvalidHmacSecret(
+                    "mock error".to_string(),
+                )));
+            }
+            self.sent.lock().unwrap().push(message);
+            Ok(true)
+        }
+    }
+
+    fn message_service_with_failing_first() -> (MessageService, Arc<Mutex<Vec<String>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut message_service = MessageService::new();
+        message_service.attach_service(Box::new(MockService {
+            sent: sent.clone(),
+            fail: true,
+        }));
+        message_service.attach_service(Box::new(MockService {
+            sent: sent.clone(),
+            fail: false,
+        }));
+        (message_service, sent)
+    }
 
     #[test]
     fn test_attach_service() {
@@ -154,4 +335,55 @@ mod message_service_tests {
             assert!(res.unwrap());
         }
     }
+
+    #[test]
+    fn test_send_event_continues_after_service_error() {
+        let (message_service, sent) = message_service_with_failing_first();
+        let event = Event::new("SWAP_STATUS".to_string(), json!({"uuid": "1"}));
+        let res = block_on(message_service.send_event(&event, DEFAULT_ROOM_ID, false));
+        assert!(matches!(
+            res.unwrap_err().into_inner(),
+            MessageError::WebhookError(WebhookError::InvalidHmacSecret(_))
+        ));
+        // The service attached after the failed one still receives the event.
+        assert_eq!(*sent.lock().unwrap(), vec![r#"SWAP_STATUS: {"uuid":"1"}"#.to_string()]);
+    }
+
+    #[test]
+    fn test_send_message_continues_after_service_error() {
+        let (message_service, sent) = message_service_with_failing_first();
+        let res = block_on(message_service.send_message("hello".to_string(), DEFAULT_ROOM_ID, false));
+        assert!(res.is_err());
+        assert_eq!(*sent.lock().unwrap(), vec!["hello".to_string()]);
+
+        // The clone shares the services, so it can be used without holding the context lock.
+        let cloned = message_service.clone();
+        assert_eq!(cloned.nb_services(), 2);
+        assert!(block_on(cloned.send_message("again".to_string(), DEFAULT_ROOM_ID, false)).is_err());
+        assert_eq!(sent.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_event_route_matches() {
+        let cfg: MessageServiceCfg = json::from_value(json!({
+            "webhooks": [{ "url": "http://localhost:8080/hook" }],
+            "event_routes": [
+                { "event_types": ["SWAP_STATUS", "BALANCE:*"] },
+                { "event_types": ["ORDER_STATUS"], "errors_only": true, "room_id": "alerts" },
+            ],
+        }))
+        .unwrap();
+        assert_eq!(cfg.webhooks.len(), 1);
+        let route: &EventRoute = &cfg.event_routes[0];
+        let errors_route: &EventRoute = &cfg.event_routes[1];
+        assert_eq!(route.room_id, DEFAULT_ROOM_ID);
+
+        assert!(route.matches(&Event::new("SWAP_STATUS".to_string(), json!({}))));
+        assert!(route.matches(&Event::err("BALANCE:KMD".to_string(), json!({}))));
+        assert!(!route.matches(&Event::new("SWAP_STATUS_V2".to_string(), json!({}))));
+        assert!(!route.matches(&Event::new("ORDER_STATUS".to_string(), json!({}))));
+
+        assert!(errors_route.matches(&Event::err("ORDER_STATUS".to_string(), json!({}))));
+        assert!(!errors_route.matches(&Event::new("ORDER_STATUS".to_string(), json!({}))));
+    }
 }
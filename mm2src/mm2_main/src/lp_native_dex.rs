@@ -24,9 +24,11 @@ use crate::lp_healthcheck::peer_healthcheck_topic;
 use crate::lp_message_service::{init_message_service, InitMessageServiceError};
 use crate::lp_network::{lp_network_ports, p2p_event_process_loop, subscribe_to_topic, NetIdError};
 use crate::lp_ordermatch::{broadcast_maker_orders_keep_alive_loop, clean_memory_loop, init_ordermatch_context,
-                           lp_ordermatch_loop, orders_kick_start, BalanceUpdateOrdermatchHandler, OrdermatchInitError};
-use crate::lp_swap::swap_kick_starts;
+                           lp_ordermatch_loop, orders_kick_start, resume_simple_market_maker_bot, trigger_orders_loop,
+                           BalanceUpdateOrdermatchHandler, OrdermatchInitError};
+use crate::lp_swap::{expired_bans_sweeper_loop, swap_kick_starts};
 use crate::lp_wallet::{initialize_wallet_passphrase, WalletInitError};
+use crate::rpc::api_keys::ApiKeys;
 use crate::rpc::spawn_rpc;
 use bitcrypto::sha256;
 use coins::register_balance_update_handler;
@@ -54,6 +56,7 @@ use std::{fs, usize};
 
 cfg_native! {
     use db_common::sqlite::rusqlite::Error as SqlError;
+    use mm2_event_stream::EventJournal;
     use mm2_io::fs::{ensure_dir_is_writable, ensure_file_is_writable};
     use mm2_net::ip_addr::myipaddr;
     use rustls_pemfile as pemfile;
@@ -253,6 +256,7 @@ impl From<InitMessageServiceError> for MmInitError {
             InitMessageServiceError::ErrorDeserializingConfig { field, error } => {
                 MmInitError::ErrorDeserializingConfig { field, error }
             },
+            InitMessageServiceError::EventRouterError(e) => MmInitError::EventStreamerInitFailed(e),
         }
     }
 }
@@ -368,6 +372,12 @@ pub fn fix_directories(ctx: &MmCtx) -> MmInitResult<()> {
     if !ensure_dir_is_writable(&dbdir.join("ORDERS").join("MY").join("HISTORY")) {
         return MmError::err(MmInitError::db_directory_is_not_writable("ORDERS/MY/HISTORY"));
     }
+    if !ensure_dir_is_writable(&dbdir.join("ORDERS").join("MY").join("TRIGGER")) {
+        return MmError::err(MmInitError::db_directory_is_not_writable("ORDERS/MY/TRIGGER"));
+    }
+    if !ensure_dir_is_writable(&dbdir.join("SIMPLE_MM_BOT").join("LEDGER")) {
+        return MmError::err(MmInitError::db_directory_is_not_writable("SIMPLE_MM_BOT/LEDGER"));
+    }
     if !ensure_dir_is_writable(&dbdir.join("TX_CACHE")) {
         return MmError::err(MmInitError::db_directory_is_not_writable("TX_CACHE"));
     }
@@ -416,6 +426,20 @@ fn migrate_db(ctx: &MmArc) -> MmInitResult<()> {
 #[cfg(not(target_arch = "wasm32"))]
 fn migration_1(_ctx: &MmArc) {}
 
+#[cfg(not(target_arch = "wasm32"))]
+fn init_event_journal(ctx: &MmArc) -> MmInitResult<()> {
+    let Some(journal_config) = ctx
+        .event_streaming_configuration()
+        .and_then(|event_streaming_config| event_streaming_config.journal)
+    else {
+        return Ok(());
+    };
+    let journal_path = ctx.dbdir().join("EVENTS").join("journal.jsonl");
+    let journal = EventJournal::open(journal_config, journal_path).map_to_mm(MmInitError::EventStreamerInitFailed)?;
+    ctx.event_stream_manager.enable_journal(journal);
+    Ok(())
+}
+
 #[cfg(target_arch = "wasm32")]
 fn init_wasm_event_streaming(ctx: &MmArc) {
     if let Some(event_streaming_config) = ctx.event_streaming_configuration() {
@@ -444,6 +468,7 @@ pub async fn lp_init_continue(ctx: MmArc) -> MmInitResult<()> {
             .map_to_mm(MmInitError::ErrorSqliteInitializing)?;
         init_and_migrate_sql_db(&ctx).await?;
         migrate_db(&ctx)?;
+        init_event_journal(&ctx)?;
         #[cfg(feature = "new-db-arch")]
         {
             let global_dir = ctx.global_dir();
@@ -477,11 +502,17 @@ pub async fn lp_init_continue(ctx: MmArc) -> MmInitResult<()> {
 
     ctx.spawner().spawn(broadcast_maker_orders_keep_alive_loop(ctx.clone()));
 
+    ctx.spawner().spawn(trigger_orders_loop(ctx.weak()));
+
+    ctx.spawner().spawn(resume_simple_market_maker_bot(ctx.clone()));
+
     #[cfg(target_arch = "wasm32")]
     init_wasm_event_streaming(&ctx);
 
     ctx.spawner().spawn(clean_memory_loop(ctx.weak()));
 
+    ctx.spawner().spawn(expired_bans_sweeper_loop(ctx.weak()));
+
     Ok(())
 }
 
@@ -504,6 +535,11 @@ pub async fn lp_init(ctx: MmArc, version: String, datetime: String) -> MmInitRes
 
     lp_init_continue(ctx.clone()).await?;
 
+    // Validate the API keys before the RPC is started.
+    ApiKeys::from_conf(&ctx.conf).map_to_mm(|error| MmInitError::ErrorDeserializingConfig {
+        field: "rpc_api_keys".to_owned(),
+        error,
+    })?;
     let ctx_id = ctx.ffi_handle().map_to_mm(MmInitError::Internal)?;
 
     spawn_rpc(ctx_id);
@@ -72,6 +72,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use timed_map::{MapKind, TimedMap};
 use trie_db::NodeCodec as NodeCodecT;
+use trigger_orders::{trigger_orders_kick_start, TriggerOrder};
 use uuid::Uuid;
 
 use crate::lp_network::{broadcast_p2p_msg, request_any_relay, request_one_peer, subscribe_to_topic, P2PRequestError};
@@ -80,10 +81,10 @@ use crate::lp_swap::taker_swap_v2::{self, TakerSwapStateMachine, TakerSwapStorag
 use crate::lp_swap::{calc_max_maker_vol, check_balance_for_maker_swap, check_balance_for_taker_swap,
                      check_other_coin_balance_for_swap, detect_secret_hash_algo_v2, generate_secret,
                      get_max_maker_vol, insert_new_swap_to_db, is_pubkey_banned, lp_atomic_locktime,
-                     p2p_keypair_and_peer_id_to_broadcast, p2p_private_and_peer_id_to_broadcast, run_maker_swap,
-                     run_taker_swap, swap_v2_topic, AtomicLocktimeVersion, CheckBalanceError, CheckBalanceResult,
-                     CoinVolumeInfo, MakerSwap, RunMakerSwapInput, RunTakerSwapInput, SwapConfirmationsSettings,
-                     TakerSwap, LEGACY_SWAP_TYPE};
+                     match_swap_v2_coins, p2p_keypair_and_peer_id_to_broadcast, p2p_private_and_peer_id_to_broadcast,
+                     run_maker_swap, run_taker_swap, swap_v2_topic, AtomicLocktimeVersion, CheckBalanceError,
+                     CheckBalanceResult, CoinVolumeInfo, MakerSwap, RunMakerSwapInput, RunTakerSwapInput,
+                     SwapConfirmationsSettings, TakerSwap, LEGACY_SWAP_TYPE};
 use crate::swap_versioning::{legacy_swap_version, SwapVersion};
 
 #[cfg(any(test, feature = "run-docker-tests"))]
@@ -103,8 +104,8 @@ cfg_wasm32! {
 
 mod best_orders;
 mod lp_bot;
-pub use lp_bot::{start_simple_market_maker_bot, stop_simple_market_maker_bot, StartSimpleMakerBotRequest,
-                 TradingBotEvent};
+pub use lp_bot::{resume_simple_market_maker_bot, simple_market_maker_bot_pnl, start_simple_market_maker_bot,
+                 stop_simple_market_maker_bot, StartSimpleMakerBotRequest, TradingBotEvent};
 use primitives::hash::{H256, H264};
 
 mod my_orders_storage;
@@ -117,6 +118,8 @@ mod orderbook_rpc;
 #[cfg(all(test, not(target_arch = "wasm32")))]
 #[path = "ordermatch_tests.rs"]
 pub mod ordermatch_tests;
+mod trigger_orders;
+pub use trigger_orders::{add_trigger_order_rpc, cancel_trigger_order_rpc, my_trigger_orders_rpc, trigger_orders_loop};
 
 #[cfg(target_arch = "wasm32")] mod ordermatch_wasm_db;
 
@@ -409,6 +412,33 @@ fn is_my_order(order_pubkey: &str, my_pub: &Option<String>, my_p2p_pubkeys: &Has
     my_pub.as_deref() == Some(order_pubkey) || my_p2p_pubkeys.contains(order_pubkey)
 }
 
+/// Returns the mid price between the best ask and the best bid of the `base/rel` orderbook.
+/// Both sides must have at least one order of another node, otherwise the price is considered unknown.
+fn orderbook_mid_price(
+    ordermatch_ctx: &OrdermatchContext,
+    my_pubsecp: &Option<String>,
+    base: String,
+    rel: String,
+) -> Option<MmNumber> {
+    let orderbook = ordermatch_ctx.orderbook.lock();
+    // The maker orders are always "sell" and the `ordered` sets are sorted by price ascending.
+    let best_price = |pair: (String, String)| {
+        orderbook.ordered.get(&pair)?.iter().find_map(|ordered| {
+            let order = orderbook.order_set.get(&ordered.uuid)?;
+            if is_my_order(&order.pubkey, my_pubsecp, &orderbook.my_p2p_pubkeys) {
+                None
+            } else {
+                Some(ordered.price.clone())
+            }
+        })
+    };
+
+    let best_ask = best_price((base.clone(), rel.clone()))?;
+    // The best `rel/base` ask is the best `base/rel` bid with the inverted price.
+    let best_bid = MmNumber::from(1) / best_price((rel, base))?;
+    Some((best_ask + best_bid) / MmNumber::from(2))
+}
+
 /// Request best asks and bids for the given `base` and `rel` coins from relays.
 /// Set `asks_num` and/or `bids_num` to get corresponding number of best asks and bids or None to get all of the available orders.
 ///
@@ -1314,6 +1344,10 @@ pub enum TakerOrderBuildError {
     },
     SenderPubkeyIsZero,
     ConfsSettingsNotSet,
+    /// The good-till-time order expires before it's created
+    ExpirationTimeInPast {
+        expires_at: u64,
+    },
 }
 
 impl fmt::Display for TakerOrderBuildError {
@@ -1346,6 +1380,9 @@ impl fmt::Display for TakerOrderBuildError {
             ),
             TakerOrderBuildError::SenderPubkeyIsZero => write!(f, "Sender pubkey can not be zero"),
             TakerOrderBuildError::ConfsSettingsNotSet => write!(f, "Confirmation settings must be set"),
+            TakerOrderBuildError::ExpirationTimeInPast { expires_at } => {
+                write!(f, "Expiration time {} is in the past", expires_at)
+            },
         }
     }
 }
@@ -1489,6 +1526,12 @@ impl<'a> TakerOrderBuilder<'a> {
             });
         }
 
+        if let Some(expires_at) = self.order_type.expires_at() {
+            if expires_at <= now_sec() {
+                return Err(TakerOrderBuildError::ExpirationTimeInPast { expires_at });
+            }
+        }
+
         let my_coin = match &self.action {
             TakerAction::Buy => &self.rel_coin,
             TakerAction::Sell => &self.base_coin,
@@ -1611,6 +1654,14 @@ enum MatchReservedResult {
 impl TakerOrder {
     fn is_cancellable(&self) -> bool { self.matches.is_empty() }
 
+    /// Whether the good-till-time order has expired and can be cancelled.
+    fn is_expired(&self, now_sec: u64) -> bool {
+        match self.order_type.expires_at() {
+            Some(expires_at) => self.is_cancellable() && expires_at <= now_sec,
+            None => false,
+        }
+    }
+
     fn match_reserved(&self, reserved: &MakerReserved) -> MatchReservedResult {
         match &self.request.match_by {
             MatchBy::Any => (),
@@ -1730,6 +1781,9 @@ pub struct MakerOrder {
     p2p_privkey: Option<SerializableSecp256k1Keypair>,
     #[serde(default, skip_serializing_if = "SwapVersion::is_legacy")]
     pub swap_version: SwapVersion,
+    /// Timestamp (in seconds) after which the order is cancelled automatically, `None` means good-till-cancelled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
 }
 
 pub struct MakerOrderBuilder<'a> {
@@ -1743,6 +1797,7 @@ pub struct MakerOrderBuilder<'a> {
     conf_settings: Option<OrderConfirmationsSettings>,
     save_in_history: bool,
     swap_version: u8,
+    expires_at: Option<u64>,
 }
 
 pub enum MakerOrderBuildError {
@@ -1772,6 +1827,10 @@ pub enum MakerOrderBuildError {
         min: MmNumber,
         max: MmNumber,
     },
+    /// The order expires before it's created
+    ExpirationTimeInPast {
+        expires_at: u64,
+    },
 }
 
 impl fmt::Display for MakerOrderBuildError {
@@ -1809,6 +1868,9 @@ impl fmt::Display for MakerOrderBuildError {
                 max.to_decimal(),
                 min.to_decimal()
             ),
+            MakerOrderBuildError::ExpirationTimeInPast { expires_at } => {
+                write!(f, "Expiration time {} is in the past", expires_at)
+            },
         }
     }
 }
@@ -1893,6 +1955,7 @@ impl<'a> MakerOrderBuilder<'a> {
             conf_settings: None,
             save_in_history: true,
             swap_version: SWAP_VERSION_DEFAULT,
+            expires_at: None,
         }
     }
 
@@ -1931,6 +1994,11 @@ impl<'a> MakerOrderBuilder<'a> {
         self
     }
 
+    pub fn with_expires_at(mut self, expires_at: Option<u64>) -> Self {
+        self.expires_at = expires_at;
+        self
+    }
+
     /// When a new [MakerOrderBuilder::new] is created, it sets [SWAP_VERSION_DEFAULT].
     /// However, if user has not specified in the config to use TPU V2,
     /// the MakerOrderBuilder's swap_version is changed to legacy.
@@ -1968,6 +2036,12 @@ impl<'a> MakerOrderBuilder<'a> {
             self.price.clone(),
         )?;
 
+        if let Some(expires_at) = self.expires_at {
+            if expires_at <= now_sec() {
+                return Err(MakerOrderBuildError::ExpirationTimeInPast { expires_at });
+            }
+        }
+
         let created_at = now_ms();
 
         let p2p_privkey = if self.base_coin.is_privacy() {
@@ -1994,6 +2068,7 @@ impl<'a> MakerOrderBuilder<'a> {
             rel_orderbook_ticker: self.rel_orderbook_ticker,
             p2p_privkey,
             swap_version: SwapVersion::from(self.swap_version),
+            expires_at: self.expires_at,
         })
     }
 
@@ -2019,6 +2094,7 @@ impl<'a> MakerOrderBuilder<'a> {
             rel_orderbook_ticker: None,
             p2p_privkey: None,
             swap_version: SwapVersion::from(self.swap_version),
+            expires_at: self.expires_at,
         }
     }
 }
@@ -2038,6 +2114,8 @@ impl MakerOrder {
 
     fn is_cancellable(&self) -> bool { !self.has_ongoing_matches() }
 
+    fn is_expired(&self, now_sec: u64) -> bool { self.expires_at.map_or(false, |expires_at| expires_at <= now_sec) }
+
     fn has_ongoing_matches(&self) -> bool {
         for (_, order_match) in self.matches.iter() {
             // if there's at least 1 ongoing match the order is not cancellable
@@ -2150,6 +2228,7 @@ impl From<TakerOrder> for MakerOrder {
                 rel_orderbook_ticker: taker_order.rel_orderbook_ticker,
                 p2p_privkey: taker_order.p2p_privkey,
                 swap_version: taker_order.request.swap_version,
+                expires_at: taker_order.order_type.expires_at(),
             },
             // The "buy" taker order is recreated with reversed pair as Maker order is always considered as "sell"
             TakerAction::Buy => {
@@ -2173,6 +2252,7 @@ impl From<TakerOrder> for MakerOrder {
                     rel_orderbook_ticker: taker_order.base_orderbook_ticker,
                     p2p_privkey: taker_order.p2p_privkey,
                     swap_version: taker_order.request.swap_version,
+                    expires_at: taker_order.order_type.expires_at(),
                 }
             },
         }
@@ -2830,6 +2910,8 @@ struct OrdermatchContext {
     /// Pending MakerReserved messages for a specific TakerOrder UUID
     /// Used to select a trade with the best price upon matching
     pending_maker_reserved: AsyncMutex<HashMap<Uuid, Vec<MakerReserved>>>,
+    /// Stop-limit and trailing orders waiting for their triggers to fire
+    pub my_trigger_orders: AsyncMutex<HashMap<Uuid, TriggerOrder>>,
     #[cfg(target_arch = "wasm32")]
     ordermatch_db: ConstructibleDb<OrdermatchDb>,
 }
@@ -2864,6 +2946,7 @@ pub fn init_ordermatch_context(ctx: &MmArc) -> OrdermatchInitResult<()> {
         my_taker_orders: Default::default(),
         orderbook: PaMutex::new(Orderbook::new(ctx.event_stream_manager.clone())),
         pending_maker_reserved: Default::default(),
+        my_trigger_orders: Default::default(),
         orderbook_tickers,
         original_tickers,
         #[cfg(target_arch = "wasm32")]
@@ -2894,6 +2977,7 @@ impl OrdermatchContext {
                 my_taker_orders: Default::default(),
                 orderbook: PaMutex::new(Orderbook::new(ctx.event_stream_manager.clone())),
                 pending_maker_reserved: Default::default(),
+                my_trigger_orders: Default::default(),
                 orderbook_tickers: Default::default(),
                 original_tickers: Default::default(),
                 #[cfg(target_arch = "wasm32")]
@@ -3124,18 +3208,11 @@ fn lp_connect_start_bob(ctx: MmArc, maker_match: MakerMatch, maker_order: MakerO
         };
 
         // TODO try to handle it more gracefully during project redesign
-        match (&maker_coin, &taker_coin) {
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                start_maker_swap_state_machine(&ctx, &maker_order, &taker_p2p_pubkey, &secret, m, t, &params).await;
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::EthCoin(t)) => {
-                start_maker_swap_state_machine(&ctx, &maker_order, &taker_p2p_pubkey, &secret, m, t, &params).await;
-            },
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::EthCoin(t)) => {
-                start_maker_swap_state_machine(&ctx, &maker_order, &taker_p2p_pubkey, &secret, m, t, &params).await;
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                start_maker_swap_state_machine(&ctx, &maker_order, &taker_p2p_pubkey, &secret, m, t, &params).await;
+        match_swap_v2_coins!(
+            &maker_coin,
+            &taker_coin,
+            |m, t| {
+                start_maker_swap_state_machine(&ctx, &maker_order, &taker_p2p_pubkey, &secret, m, t, &params).await
             },
             _ => {
                 let params = LegacySwapParams {
@@ -3149,8 +3226,8 @@ fn lp_connect_start_bob(ctx: MmArc, maker_match: MakerMatch, maker_order: MakerO
                     locktime: &lock_time,
                 };
                 start_maker_legacy_swap(&ctx, maker_order, taker_pubkey, secret, params).await
-            },
-        }
+            }
+        )
     };
 
     let settings = AbortSettings::info_on_abort(format!("swap {uuid} stopped!"));
@@ -3230,6 +3307,7 @@ async fn start_maker_swap_state_machine<
         taker_p2p_pubkey: *taker_p2p_pubkey,
         require_taker_payment_spend_confirm: true,
         swap_version: maker_order.swap_version.version,
+        my_order_uuid: Some(maker_order.uuid),
     };
     #[allow(clippy::box_default)]
     maker_swap_state_machine
@@ -3356,22 +3434,12 @@ fn lp_connected_alice(ctx: MmArc, taker_order: TakerOrder, taker_match: TakerMat
         };
 
         // TODO try to handle it more gracefully during project redesign
-        match (&maker_coin, &taker_coin) {
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                start_taker_swap_state_machine(&ctx, &taker_order, &maker_p2p_pubkey, &taker_secret, m, t, &params)
-                    .await;
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::EthCoin(t)) => {
-                start_taker_swap_state_machine(&ctx, &taker_order, &maker_p2p_pubkey, &taker_secret, m, t, &params)
-                    .await;
-            },
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::EthCoin(t)) => {
-                start_taker_swap_state_machine(&ctx, &taker_order, &maker_p2p_pubkey, &taker_secret, m, t, &params)
-                    .await;
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::UtxoCoin(t)) => {
+        match_swap_v2_coins!(
+            &maker_coin,
+            &taker_coin,
+            |m, t| {
                 start_taker_swap_state_machine(&ctx, &taker_order, &maker_p2p_pubkey, &taker_secret, m, t, &params)
-                    .await;
+                    .await
             },
             _ => {
                 let params = LegacySwapParams {
@@ -3385,8 +3453,8 @@ fn lp_connected_alice(ctx: MmArc, taker_order: TakerOrder, taker_match: TakerMat
                     locktime: &locktime,
                 };
                 start_taker_legacy_swap(&ctx, taker_order, maker_pubkey, params).await;
-            },
-        }
+            }
+        )
     };
 
     let settings = AbortSettings::info_on_abort(format!("swap {uuid} stopped!"));
@@ -3470,6 +3538,7 @@ async fn start_taker_swap_state_machine<
         require_maker_payment_confirm_before_funding_spend: true,
         require_maker_payment_spend_confirm: true,
         swap_version: taker_order.request.swap_version.version,
+        my_order_uuid: Some(taker_order.request.uuid),
     };
     #[allow(clippy::box_default)]
     taker_swap_state_machine
@@ -3493,6 +3562,7 @@ pub async fn lp_ordermatch_loop(ctx: MmArc) {
 
         handle_timed_out_taker_orders(ctx.clone(), &ordermatch_ctx).await;
         handle_timed_out_maker_matches(ctx.clone(), &ordermatch_ctx).await;
+        handle_expired_maker_orders(ctx.clone(), &ordermatch_ctx).await;
         check_balance_for_maker_orders(ctx.clone(), &ordermatch_ctx).await;
 
         {
@@ -3625,7 +3695,8 @@ pub async fn clean_memory_loop(ctx_weak: MmWeak) {
     }
 }
 
-/// Transforms the timed out and unmatched GTC taker orders to maker.
+/// Transforms the timed out and unmatched GTC and GTT taker orders to maker.
+/// Cancels the unmatched GTT taker orders which have expired.
 ///
 /// # Safety
 ///
@@ -3636,12 +3707,20 @@ async fn handle_timed_out_taker_orders(ctx: MmArc, ordermatch_ctx: &OrdermatchCo
     let mut my_actual_taker_orders = HashMap::with_capacity(my_taker_orders.len());
 
     for (uuid, order) in my_taker_orders.drain() {
+        if order.is_expired(now_sec()) {
+            delete_my_taker_order(ctx.clone(), order, TakerOrderCancellationReason::Expired)
+                .compat()
+                .await
+                .ok();
+            continue;
+        }
+
         if order.created_at + order.timeout * 1000 >= now_ms() {
             my_actual_taker_orders.insert(uuid, order);
             continue;
         }
 
-        if !order.matches.is_empty() || order.order_type != OrderType::GoodTillCancelled {
+        if !order.matches.is_empty() || order.order_type == OrderType::FillOrKill {
             delete_my_taker_order(ctx.clone(), order, TakerOrderCancellationReason::TimedOut)
                 .compat()
                 .await
@@ -3686,6 +3765,35 @@ async fn handle_timed_out_taker_orders(ctx: MmArc, ordermatch_ctx: &OrdermatchCo
     *my_taker_orders = my_actual_taker_orders;
 }
 
+/// Cancels the maker orders which have reached their `expires_at`.
+/// Orders with ongoing matches are kept until the matches are finished or timed out.
+///
+/// # Safety
+///
+/// The function locks the [`OrdermatchContext::my_maker_orders`] mutex.
+async fn handle_expired_maker_orders(ctx: MmArc, ordermatch_ctx: &OrdermatchContext) {
+    let my_maker_orders = ordermatch_ctx.maker_orders_ctx.lock().orders.clone();
+    let now = now_sec();
+
+    for (uuid, order) in my_maker_orders {
+        let order = order.lock().await;
+        if !order.is_expired(now) || order.has_ongoing_matches() {
+            continue;
+        }
+
+        let removed_order_mutex = ordermatch_ctx.maker_orders_ctx.lock().remove_order(&uuid);
+        // This checks that the order hasn't been removed by another process
+        if removed_order_mutex.is_some() {
+            log::info!("Maker order {} has expired, cancelling", uuid);
+            maker_order_cancelled_p2p_notify(&ctx, &order);
+            delete_my_maker_order(ctx.clone(), order.clone(), MakerOrderCancellationReason::Expired)
+                .compat()
+                .await
+                .ok();
+        }
+    }
+}
+
 /// # Safety
 ///
 /// The function locks the [`OrdermatchContext::my_maker_orders`] mutex.
@@ -4701,7 +4809,7 @@ impl OrderbookItem {
 
 fn get_true() -> bool { true }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SetPriceReq {
     base: String,
     rel: String,
@@ -4719,6 +4827,9 @@ pub struct SetPriceReq {
     rel_nota: Option<bool>,
     #[serde(default = "get_true")]
     save_in_history: bool,
+    /// Timestamp (in seconds) after which the order is cancelled automatically.
+    #[serde(default)]
+    expires_at: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -4853,6 +4964,8 @@ struct MakerOrderForRpc<'a> {
     changes_history: &'a Option<Vec<HistoricalOrder>>,
     base_orderbook_ticker: &'a Option<String>,
     rel_orderbook_ticker: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
 }
 
 impl<'a> From<&'a MakerOrder> for MakerOrderForRpc<'a> {
@@ -4879,6 +4992,7 @@ impl<'a> From<&'a MakerOrder> for MakerOrderForRpc<'a> {
             changes_history: &order.changes_history,
             base_orderbook_ticker: &order.base_orderbook_ticker,
             rel_orderbook_ticker: &order.rel_orderbook_ticker,
+            expires_at: order.expires_at,
         }
     }
 }
@@ -4986,7 +5100,8 @@ pub async fn create_maker_order(ctx: &MmArc, req: SetPriceReq) -> Result<MakerOr
         .with_conf_settings(conf_settings)
         .with_save_in_history(req.save_in_history)
         .with_base_orderbook_ticker(ordermatch_ctx.orderbook_ticker(base_coin.ticker()))
-        .with_rel_orderbook_ticker(ordermatch_ctx.orderbook_ticker(rel_coin.ticker()));
+        .with_rel_orderbook_ticker(ordermatch_ctx.orderbook_ticker(rel_coin.ticker()))
+        .with_expires_at(req.expires_at);
     if !ctx.use_trading_proto_v2() {
         builder.set_legacy_swap_v();
     }
@@ -5267,6 +5382,7 @@ pub enum MakerOrderCancellationReason {
     Fulfilled,
     InsufficientBalance,
     Cancelled,
+    Expired,
 }
 
 #[derive(Display)]
@@ -5275,6 +5391,7 @@ pub enum TakerOrderCancellationReason {
     ToMaker,
     TimedOut,
     Cancelled,
+    Expired,
 }
 
 #[derive(Debug, Deserialize)]
@@ -5658,6 +5775,15 @@ fn my_taker_orders_dir(ctx: &MmArc) -> PathBuf { ctx.dbdir().join("ORDERS").join
 #[cfg(not(target_arch = "wasm32"))]
 fn my_orders_history_dir(ctx: &MmArc) -> PathBuf { ctx.dbdir().join("ORDERS").join("MY").join("HISTORY") }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn my_trigger_orders_dir(ctx: &MmArc) -> PathBuf { ctx.dbdir().join("ORDERS").join("MY").join("TRIGGER") }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn trading_bot_dir(ctx: &MmArc) -> PathBuf { ctx.dbdir().join("SIMPLE_MM_BOT") }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn trading_bot_ledger_dir(ctx: &MmArc) -> PathBuf { trading_bot_dir(ctx).join("LEDGER") }
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn my_maker_order_file_path(ctx: &MmArc, uuid: &Uuid) -> PathBuf {
     my_maker_orders_dir(ctx).join(format!("{}.json", uuid))
@@ -5673,6 +5799,19 @@ fn my_order_history_file_path(ctx: &MmArc, uuid: &Uuid) -> PathBuf {
     my_orders_history_dir(ctx).join(format!("{}.json", uuid))
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn my_trigger_order_file_path(ctx: &MmArc, uuid: &Uuid) -> PathBuf {
+    my_trigger_orders_dir(ctx).join(format!("{}.json", uuid))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn trading_bot_state_file_path(ctx: &MmArc) -> PathBuf { trading_bot_dir(ctx).join("state.json") }
+
+#[cfg(not(target_arch = "wasm32"))]
+fn trading_bot_ledger_file_path(ctx: &MmArc, swap_uuid: &Uuid) -> PathBuf {
+    trading_bot_ledger_dir(ctx).join(format!("{}.json", swap_uuid))
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct HistoricalOrder {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -5704,12 +5843,16 @@ pub async fn orders_kick_start(ctx: &MmArc) -> Result<HashSet<String>, String> {
         }
     }
 
-    let mut taker_orders = ordermatch_ctx.my_taker_orders.lock().await;
-    for order in saved_taker_orders {
-        coins.insert(order.request.base.clone());
-        coins.insert(order.request.rel.clone());
-        taker_orders.insert(order.request.uuid, order);
+    {
+        let mut taker_orders = ordermatch_ctx.my_taker_orders.lock().await;
+        for order in saved_taker_orders {
+            coins.insert(order.request.base.clone());
+            coins.insert(order.request.rel.clone());
+            taker_orders.insert(order.request.uuid, order);
+        }
     }
+
+    coins.extend(try_s!(trigger_orders_kick_start(ctx).await));
     Ok(coins)
 }
 
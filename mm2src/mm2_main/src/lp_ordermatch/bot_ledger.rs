@@ -0,0 +1,235 @@
+//! The persistent state and the swap ledger of the simple market maker bot.
+//!
+//! The bot config is saved on start and stop, so a running bot is resumed after the node restarts.
+//! Every swap (maker or taker, v1 or v2) started from a bot order is recorded in the ledger together with the rates
+//! the order was quoted with, the realized P&L of the pairs is calculated from the succeeded swaps of the ledger.
+
+use crate::lp_ordermatch::lp_bot::SimpleMakerBotRegistry;
+use crate::lp_ordermatch::my_orders_storage::{MyOrdersError, MyOrdersStorage, TradingBotStorage};
+use crate::lp_swap::SwapStatusChanged;
+use coins::lp_price::RateInfos;
+use common::{now_sec, HttpStatusCode, StatusCode};
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_number::{BigDecimal, MmNumber};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// The orders which weren't seen for this period are forgotten.
+pub const BOT_ORDER_RETENTION_SEC: u64 = 3600;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PersistedTradingBotState {
+    pub trading_bot_cfg: SimpleMakerBotRegistry,
+    pub bot_refresh_rate: f64,
+    pub price_urls: Vec<String>,
+    pub is_running: bool,
+    /// The orders created or updated by the bot with the timestamp they were seen at the last time.
+    #[serde(default)]
+    pub bot_orders: HashMap<Uuid, u64>,
+    #[serde(default)]
+    pub ladder_orders: HashMap<String, Vec<Uuid>>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum BotSwapStatus {
+    Ongoing,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BotLedgerEntry {
+    pub swap_uuid: Uuid,
+    pub order_uuid: Uuid,
+    /// The coin sold in the swap.
+    pub base: String,
+    /// The coin received in the swap.
+    pub rel: String,
+    pub base_amount: MmNumber,
+    pub rel_amount: MmNumber,
+    /// The pair price of the price feed the order was quoted with.
+    pub price: Option<MmNumber>,
+    pub base_usd_price: Option<MmNumber>,
+    pub rel_usd_price: Option<MmNumber>,
+    pub status: BotSwapStatus,
+    pub last_event: String,
+    pub started_at: u64,
+    pub updated_at: u64,
+}
+
+fn non_zero(number: &MmNumber) -> Option<MmNumber> { Some(number.clone()).filter(|number| !number.is_zero()) }
+
+impl BotLedgerEntry {
+    pub fn new(swap_infos: &SwapStatusChanged, order_uuid: Uuid, rates: Option<&RateInfos>) -> BotLedgerEntry {
+        let now = now_sec();
+        let mut entry = BotLedgerEntry {
+            swap_uuid: swap_infos.uuid,
+            order_uuid,
+            base: swap_infos.my_coin.clone(),
+            rel: swap_infos.other_coin.clone(),
+            base_amount: swap_infos.my_amount.clone().into(),
+            rel_amount: swap_infos.other_amount.clone().into(),
+            price: rates.and_then(|rates| non_zero(&rates.price)),
+            base_usd_price: rates.and_then(|rates| non_zero(&rates.base_price)),
+            rel_usd_price: rates.and_then(|rates| non_zero(&rates.rel_price)),
+            status: BotSwapStatus::Ongoing,
+            last_event: String::new(),
+            started_at: now,
+            updated_at: now,
+        };
+        entry.apply_event(swap_infos);
+        entry
+    }
+
+    /// Updates the status of the swap. The swap is failed as soon as any error event occurs.
+    pub fn apply_event(&mut self, swap_infos: &SwapStatusChanged) {
+        self.last_event = swap_infos.event_status.clone();
+        self.updated_at = now_sec();
+        if swap_infos.is_error {
+            self.status = BotSwapStatus::Failed;
+        } else if swap_infos.is_finished && self.status == BotSwapStatus::Ongoing {
+            self.status = BotSwapStatus::Succeeded;
+        }
+    }
+
+    pub fn pair(&self) -> String { format!("{}/{}", self.base, self.rel) }
+
+    /// The received rel amount minus the value of the sold base amount at the price of the feed.
+    fn pnl_rel(&self) -> Option<MmNumber> {
+        let price = self.price.as_ref()?;
+        Some(&self.rel_amount - &(&self.base_amount * price))
+    }
+
+    fn pnl_usd(&self) -> Option<MmNumber> {
+        let base_usd_price = self.base_usd_price.as_ref()?;
+        let rel_usd_price = self.rel_usd_price.as_ref()?;
+        Some(&(&self.rel_amount * rel_usd_price) - &(&self.base_amount * base_usd_price))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct PairPnl {
+    pub pair: String,
+    pub swaps_succeeded: usize,
+    pub swaps_failed: usize,
+    pub swaps_ongoing: usize,
+    /// The base coin amount sold in the succeeded swaps.
+    pub base_volume: BigDecimal,
+    /// The rel coin amount received in the succeeded swaps.
+    pub rel_volume: BigDecimal,
+    pub realized_pnl_rel: BigDecimal,
+    pub realized_pnl_usd: BigDecimal,
+    /// The number of succeeded swaps not included into `realized_pnl_rel` since the pair price was unknown.
+    pub swaps_without_price: usize,
+    /// The number of succeeded swaps not included into `realized_pnl_usd` since the USD prices were unknown.
+    pub swaps_without_usd_price: usize,
+}
+
+#[derive(Default)]
+struct PairPnlAccumulator {
+    swaps_succeeded: usize,
+    swaps_failed: usize,
+    swaps_ongoing: usize,
+    base_volume: MmNumber,
+    rel_volume: MmNumber,
+    realized_pnl_rel: MmNumber,
+    realized_pnl_usd: MmNumber,
+    swaps_without_price: usize,
+    swaps_without_usd_price: usize,
+}
+
+impl PairPnlAccumulator {
+    fn add(&mut self, entry: &BotLedgerEntry) {
+        match entry.status {
+            BotSwapStatus::Ongoing => self.swaps_ongoing += 1,
+            BotSwapStatus::Failed => self.swaps_failed += 1,
+            BotSwapStatus::Succeeded => {
+                self.swaps_succeeded += 1;
+                self.base_volume = &self.base_volume + &entry.base_amount;
+                self.rel_volume = &self.rel_volume + &entry.rel_amount;
+                match entry.pnl_rel() {
+                    Some(pnl) => self.realized_pnl_rel = &self.realized_pnl_rel + &pnl,
+                    None => self.swaps_without_price += 1,
+                }
+                match entry.pnl_usd() {
+                    Some(pnl) => self.realized_pnl_usd = &self.realized_pnl_usd + &pnl,
+                    None => self.swaps_without_usd_price += 1,
+                }
+            },
+        }
+    }
+
+    fn into_pair_pnl(self, pair: String) -> PairPnl {
+        PairPnl {
+            pair,
+            swaps_succeeded: self.swaps_succeeded,
+            swaps_failed: self.swaps_failed,
+            swaps_ongoing: self.swaps_ongoing,
+            base_volume: self.base_volume.to_decimal(),
+            rel_volume: self.rel_volume.to_decimal(),
+            realized_pnl_rel: self.realized_pnl_rel.to_decimal(),
+            realized_pnl_usd: self.realized_pnl_usd.to_decimal(),
+            swaps_without_price: self.swaps_without_price,
+            swaps_without_usd_price: self.swaps_without_usd_price,
+        }
+    }
+}
+
+/// Calculates the P&L of the ledger `entries` per pair, the result is sorted by the pair.
+pub fn calculate_pnl<'a>(entries: impl IntoIterator<Item = &'a BotLedgerEntry>) -> Vec<PairPnl> {
+    let mut pairs: BTreeMap<String, PairPnlAccumulator> = BTreeMap::new();
+    for entry in entries {
+        pairs.entry(entry.pair()).or_default().add(entry);
+    }
+    pairs
+        .into_iter()
+        .map(|(pair, accumulator)| accumulator.into_pair_pnl(pair))
+        .collect()
+}
+
+#[derive(Deserialize)]
+pub struct SimpleMakerBotPnlRequest {
+    /// Reports the only pair in the `BASE/REL` format if set.
+    #[serde(default)]
+    pair: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimpleMakerBotPnlResponse {
+    pairs: Vec<PairPnl>,
+}
+
+#[derive(Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum SimpleMakerBotPnlError {
+    #[display(fmt = "Error loading the bot ledger: {}", _0)]
+    StorageError(String),
+}
+
+impl From<MyOrdersError> for SimpleMakerBotPnlError {
+    fn from(e: MyOrdersError) -> Self { SimpleMakerBotPnlError::StorageError(e.to_string()) }
+}
+
+impl HttpStatusCode for SimpleMakerBotPnlError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SimpleMakerBotPnlError::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub async fn simple_market_maker_bot_pnl(
+    ctx: MmArc,
+    req: SimpleMakerBotPnlRequest,
+) -> Result<SimpleMakerBotPnlResponse, MmError<SimpleMakerBotPnlError>> {
+    let storage = MyOrdersStorage::new(ctx);
+    let entries = storage.load_bot_ledger().await?;
+    let entries = entries
+        .iter()
+        .filter(|entry| req.pair.as_ref().map_or(true, |pair| *pair == entry.pair()));
+    Ok(SimpleMakerBotPnlResponse {
+        pairs: calculate_pnl(entries),
+    })
+}
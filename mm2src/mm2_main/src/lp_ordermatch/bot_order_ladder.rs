@@ -0,0 +1,111 @@
+//! Multi-level order ladders of the simple market maker bot.
+//!
+//! A ladder pair is quoted with `levels` maker orders instead of a single one.
+//! The first level is quoted as a regular bot order, every next level is priced `spread_step` further from it
+//! and gets `volume_multiplier` times the volume of the previous level.
+//! The total volume of the ladder is the volume the bot would put into the single order.
+
+use mm2_number::MmNumber;
+
+/// The maximum number of the orders in a ladder.
+pub const MAX_LADDER_LEVELS: usize = 20;
+/// The maximum `volume_multiplier`, a larger one puts almost the whole volume into the last level.
+const MAX_VOLUME_MULTIPLIER: u64 = 10;
+
+fn default_volume_multiplier() -> MmNumber { MmNumber::from(1) }
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OrderLadder {
+    /// The number of orders.
+    pub levels: usize,
+    /// The price of the level `i` is the price of the first level multiplied by `1 + i * spread_step`.
+    pub spread_step: MmNumber,
+    /// The volume of every next level relatively to the previous one, 1 (equal volumes) by default.
+    #[serde(default = "default_volume_multiplier")]
+    pub volume_multiplier: MmNumber,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LadderLevel {
+    pub price: MmNumber,
+    pub volume: MmNumber,
+}
+
+impl OrderLadder {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.levels == 0 || self.levels > MAX_LADDER_LEVELS {
+            return Err(format!("'levels' must be from 1 to {}", MAX_LADDER_LEVELS));
+        }
+        if self.spread_step < MmNumber::default() {
+            return Err("'spread_step' must not be negative".to_owned());
+        }
+        if self.volume_multiplier <= MmNumber::default()
+            || self.volume_multiplier > MmNumber::from(MAX_VOLUME_MULTIPLIER)
+        {
+            return Err(format!(
+                "'volume_multiplier' must be positive and not greater than {}",
+                MAX_VOLUME_MULTIPLIER
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that every level can be placed as an order of `base/rel`,
+    /// i.e. the base and the rel volumes of the levels are not less than the min trading volumes of the coins.
+    pub fn validate_levels(
+        levels: &[LadderLevel],
+        base_min_vol: &MmNumber,
+        rel_min_vol: &MmNumber,
+    ) -> Result<(), String> {
+        for (index, level) in levels.iter().enumerate() {
+            if level.volume < *base_min_vol {
+                return Err(format!(
+                    "The volume {} of the level {} is less than the base coin min trading volume {}",
+                    level.volume, index, base_min_vol
+                ));
+            }
+            let rel_volume = &level.volume * &level.price;
+            if rel_volume < *rel_min_vol {
+                return Err(format!(
+                    "The rel volume {} of the level {} is less than the rel coin min trading volume {}",
+                    rel_volume, index, rel_min_vol
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Splits the `total_volume` into the ladder levels starting from the `price`.
+    /// The volumes of the levels sum up to `total_volume` exactly.
+    pub fn levels(&self, price: &MmNumber, total_volume: &MmNumber) -> Vec<LadderLevel> {
+        let one = MmNumber::from(1);
+        let levels = self.levels.min(MAX_LADDER_LEVELS);
+        let mut weights = Vec::with_capacity(levels);
+        let mut weight = one.clone();
+        for _ in 0..levels {
+            weights.push(weight.clone());
+            weight = &weight * &self.volume_multiplier;
+        }
+        let total_weight = weights.iter().fold(MmNumber::default(), |sum, weight| &sum + weight);
+
+        let mut volume_left = total_volume.clone();
+        weights
+            .into_iter()
+            .enumerate()
+            .map(|(level, weight)| {
+                let price_factor = &one + &(&MmNumber::from(level as u64) * &self.spread_step);
+                // The last level takes the rest, so the levels never exceed the total volume.
+                let volume = if level + 1 == levels {
+                    volume_left.clone()
+                } else {
+                    &(total_volume * &weight) / &total_weight
+                };
+                volume_left = &volume_left - &volume;
+                LadderLevel {
+                    price: price * &price_factor,
+                    volume,
+                }
+            })
+            .collect()
+    }
+}
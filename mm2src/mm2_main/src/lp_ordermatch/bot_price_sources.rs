@@ -0,0 +1,427 @@
+//! Price sources of the simple market maker bot.
+//!
+//! By default a pair is quoted from the tickers registry configured with `price_url`/`price_urls`.
+//! A pair may also list several `price_sources`. Their quotes are aggregated with the median:
+//! quotes deviating from the median by more than `max_price_deviation` are rejected as outliers,
+//! and the pair is not quoted at all if most of the sources don't agree on the price.
+
+use crate::lp_ordermatch::{mm2_internal_pubkey_hex, orderbook_mid_price, subscribe_to_orderbook_topic,
+                           OrdermatchContext};
+use async_trait::async_trait;
+use coins::lp_price::{Provider, RateInfos, TickerInfosRegistry};
+use common::log::warn;
+use common::{now_sec, StatusCode};
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_net::transport::SlurpError;
+use mm2_number::MmNumber;
+use serde_json::{self as json, Value as Json};
+#[cfg(not(target_arch = "wasm32"))]
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The default maximum relative deviation of a quote from the median (5%).
+const DEFAULT_MAX_PRICE_DEVIATION: &str = "0.05";
+
+pub type PriceSourceResult<T> = Result<T, MmError<PriceSourceError>>;
+
+#[derive(Debug, Display)]
+pub enum PriceSourceError {
+    #[display(fmt = "Price of {} is not provided", _0)]
+    PriceNotAvailable(String),
+    #[display(fmt = "Transport error: {}", _0)]
+    Transport(String),
+    #[display(fmt = "Invalid response: {}", _0)]
+    InvalidResponse(String),
+    #[display(fmt = "Price source is not supported on this platform: {}", _0)]
+    NotSupported(String),
+}
+
+impl From<SlurpError> for PriceSourceError {
+    fn from(e: SlurpError) -> Self {
+        match e {
+            SlurpError::ErrorDeserializing { .. } => PriceSourceError::InvalidResponse(e.to_string()),
+            _ => PriceSourceError::Transport(e.to_string()),
+        }
+    }
+}
+
+impl From<json::Error> for PriceSourceError {
+    fn from(e: json::Error) -> Self { PriceSourceError::InvalidResponse(e.to_string()) }
+}
+
+#[derive(Debug, Display, PartialEq)]
+pub enum PriceAggregationError {
+    #[display(
+        fmt = "Only {} price sources answered while at least {} are required",
+        available,
+        required
+    )]
+    NotEnoughSources { available: usize, required: usize },
+    #[display(
+        fmt = "Price sources disagree: only {} of {} quotes are within {} of the median price {}",
+        agreed,
+        total,
+        max_deviation,
+        median
+    )]
+    SourcesDisagree {
+        agreed: usize,
+        total: usize,
+        median: String,
+        max_deviation: String,
+    },
+}
+
+/// A price source configured for a trading pair.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum PriceSourceCfg {
+    /// The tickers registry fetched from the `price_url`/`price_urls` of the bot.
+    TickersRegistry,
+    /// A local JSON file re-read on every bot iteration, e.g. `{"KMD/LTC": "0.004", "BTC": 60000, "LTC": 70}`.
+    /// The `BASE/REL` price is used if present, otherwise the price is computed from the USD prices of both coins.
+    /// The modification time of the file is used as the last update timestamp of the price.
+    StaticFile { path: String },
+    /// A custom HTTP endpoint answering with JSON.
+    /// `{base}` and `{rel}` placeholders of the `url` are replaced with the coin tickers.
+    /// `price_pointer` is a JSON pointer (RFC 6901) to the `base/rel` price, e.g. `/data/price`.
+    HttpJson {
+        url: String,
+        price_pointer: String,
+        /// Whether the endpoint returns the `rel/base` price.
+        #[serde(default)]
+        inverted: bool,
+    },
+    /// The mid price of the best ask and the best bid of the node's own orderbook (our orders are excluded).
+    OrderbookMid,
+}
+
+impl PriceSourceCfg {
+    pub fn build(&self, tickers_registry: Option<Arc<TickerInfosRegistry>>) -> Box<dyn PriceSource> {
+        match self {
+            PriceSourceCfg::TickersRegistry => Box::new(TickersRegistrySource(tickers_registry)),
+            PriceSourceCfg::StaticFile { path } => Box::new(StaticFileSource { path: path.clone() }),
+            PriceSourceCfg::HttpJson {
+                url,
+                price_pointer,
+                inverted,
+            } => Box::new(HttpJsonSource {
+                url: url.clone(),
+                price_pointer: price_pointer.clone(),
+                inverted: *inverted,
+            }),
+            PriceSourceCfg::OrderbookMid => Box::new(OrderbookMidSource),
+        }
+    }
+}
+
+/// A single `base/rel` price reported by a price source.
+#[derive(Clone, Debug)]
+pub struct PriceQuote {
+    pub source: String,
+    pub price: MmNumber,
+    pub base_usd_price: Option<MmNumber>,
+    pub rel_usd_price: Option<MmNumber>,
+    pub last_updated_timestamp: u64,
+}
+
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    /// The name of the source used in logs.
+    fn name(&self) -> String;
+
+    async fn fetch_quote(&self, ctx: &MmArc, base: &str, rel: &str) -> PriceSourceResult<PriceQuote>;
+}
+
+struct TickersRegistrySource(Option<Arc<TickerInfosRegistry>>);
+
+#[async_trait]
+impl PriceSource for TickersRegistrySource {
+    fn name(&self) -> String { "tickers_registry".to_owned() }
+
+    async fn fetch_quote(&self, _ctx: &MmArc, base: &str, rel: &str) -> PriceSourceResult<PriceQuote> {
+        let registry = self
+            .0
+            .as_ref()
+            .or_mm_err(|| PriceSourceError::Transport("Tickers registry couldn't be fetched".to_owned()))?;
+        let rates = registry
+            .get_cex_rates(base, rel)
+            .or_mm_err(|| PriceSourceError::PriceNotAvailable(format!("{}/{}", base, rel)))?;
+        Ok(PriceQuote {
+            source: self.name(),
+            price: rates.price,
+            base_usd_price: Some(rates.base_price),
+            rel_usd_price: Some(rates.rel_price),
+            last_updated_timestamp: rates.last_updated_timestamp.unwrap_or_default(),
+        })
+    }
+}
+
+struct StaticFileSource {
+    path: String,
+}
+
+#[async_trait]
+impl PriceSource for StaticFileSource {
+    fn name(&self) -> String { format!("static_file:{}", self.path) }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn fetch_quote(&self, _ctx: &MmArc, base: &str, rel: &str) -> PriceSourceResult<PriceQuote> {
+        use std::path::Path;
+        use std::time::UNIX_EPOCH;
+
+        let path = Path::new(&self.path);
+        let prices: HashMap<String, MmNumber> = mm2_io::fs::read_json(path)
+            .await
+            .mm_err(|e| PriceSourceError::InvalidResponse(e.to_string()))?
+            .or_mm_err(|| PriceSourceError::Transport(format!("File {} is not found", self.path)))?;
+        let last_updated_timestamp = std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        let mut quote = quote_from_prices(&prices, base, rel)?;
+        quote.source = self.name();
+        quote.last_updated_timestamp = last_updated_timestamp;
+        Ok(quote)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    async fn fetch_quote(&self, _ctx: &MmArc, _base: &str, _rel: &str) -> PriceSourceResult<PriceQuote> {
+        MmError::err(PriceSourceError::NotSupported(self.name()))
+    }
+}
+
+/// Extracts the `base/rel` price from a `ticker or pair => price` map.
+#[cfg(not(target_arch = "wasm32"))]
+fn quote_from_prices(prices: &HashMap<String, MmNumber>, base: &str, rel: &str) -> PriceSourceResult<PriceQuote> {
+    let base_usd_price = prices.get(base).cloned();
+    let rel_usd_price = prices.get(rel).cloned();
+    let price = match (
+        prices.get(&format!("{}/{}", base, rel)),
+        &base_usd_price,
+        &rel_usd_price,
+    ) {
+        (Some(pair_price), _, _) => pair_price.clone(),
+        (None, Some(base_price), Some(rel_price)) if !rel_price.is_zero() => base_price / rel_price,
+        _ => return MmError::err(PriceSourceError::PriceNotAvailable(format!("{}/{}", base, rel))),
+    };
+    Ok(PriceQuote {
+        source: String::new(),
+        price,
+        base_usd_price,
+        rel_usd_price,
+        last_updated_timestamp: now_sec(),
+    })
+}
+
+struct HttpJsonSource {
+    url: String,
+    price_pointer: String,
+    inverted: bool,
+}
+
+#[async_trait]
+impl PriceSource for HttpJsonSource {
+    fn name(&self) -> String { format!("http_json:{}", self.url) }
+
+    async fn fetch_quote(&self, _ctx: &MmArc, base: &str, rel: &str) -> PriceSourceResult<PriceQuote> {
+        let url = self.url.replace("{base}", base).replace("{rel}", rel);
+        #[cfg(not(target_arch = "wasm32"))]
+        let (status, _headers, body) = mm2_net::native_http::slurp_url(&url).await?;
+        #[cfg(target_arch = "wasm32")]
+        let (status, _headers, body) = mm2_net::wasm::http::slurp_url(&url).await?;
+        if status != StatusCode::OK {
+            let error = format!("{} answered with {}: {}", url, status, String::from_utf8_lossy(&body));
+            return MmError::err(PriceSourceError::Transport(error));
+        }
+
+        let response: Json = json::from_slice(&body)?;
+        let price = price_at_pointer(&response, &self.price_pointer, self.inverted)?;
+        Ok(PriceQuote {
+            source: self.name(),
+            price,
+            base_usd_price: None,
+            rel_usd_price: None,
+            last_updated_timestamp: now_sec(),
+        })
+    }
+}
+
+/// Reads the price at the JSON `pointer`, the price must be a finite positive number.
+pub fn price_at_pointer(response: &Json, pointer: &str, inverted: bool) -> PriceSourceResult<MmNumber> {
+    let value = response
+        .pointer(pointer)
+        .or_mm_err(|| PriceSourceError::InvalidResponse(format!("No value at {}", pointer)))?;
+    // Some APIs return the unknown prices as "NaN" or "Infinity" strings.
+    let is_non_finite = value
+        .as_str()
+        .and_then(|price| price.parse::<f64>().ok())
+        .map_or(false, |price| !price.is_finite());
+    if is_non_finite {
+        return MmError::err(PriceSourceError::InvalidResponse(format!(
+            "Non-finite price {} at {}",
+            value, pointer
+        )));
+    }
+    // `MmNumber` can be deserialized from a string only, not from `serde_json::Value`.
+    let price: MmNumber = json::from_str(&value.to_string())?;
+    if price <= MmNumber::from(0) {
+        return MmError::err(PriceSourceError::InvalidResponse(format!(
+            "Non-positive price {} at {}",
+            price, pointer
+        )));
+    }
+    if inverted {
+        return Ok(MmNumber::from(1) / price);
+    }
+    Ok(price)
+}
+
+struct OrderbookMidSource;
+
+#[async_trait]
+impl PriceSource for OrderbookMidSource {
+    fn name(&self) -> String { "orderbook_mid".to_owned() }
+
+    async fn fetch_quote(&self, ctx: &MmArc, base: &str, rel: &str) -> PriceSourceResult<PriceQuote> {
+        let pair = format!("{}/{}", base, rel);
+        let ordermatch_ctx = OrdermatchContext::from_ctx(ctx).map_to_mm(PriceSourceError::NotSupported)?;
+        let (base, rel) = ordermatch_ctx.orderbook_pair_bypass(&(base.to_owned(), rel.to_owned()));
+        subscribe_to_orderbook_topic(ctx, &base, &rel, true)
+            .await
+            .map_to_mm(PriceSourceError::Transport)?;
+        let my_pubsecp = mm2_internal_pubkey_hex(ctx, PriceSourceError::NotSupported)?;
+        let price = orderbook_mid_price(&ordermatch_ctx, &my_pubsecp, base, rel)
+            .or_mm_err(|| PriceSourceError::PriceNotAvailable(pair))?;
+        Ok(PriceQuote {
+            source: self.name(),
+            price,
+            base_usd_price: None,
+            rel_usd_price: None,
+            last_updated_timestamp: now_sec(),
+        })
+    }
+}
+
+/// Fetches the quotes of all sources, the failed sources are logged and skipped.
+pub async fn fetch_quotes(ctx: &MmArc, sources: &[Box<dyn PriceSource>], base: &str, rel: &str) -> Vec<PriceQuote> {
+    let futures = sources.iter().map(|source| source.fetch_quote(ctx, base, rel));
+    let results = futures::future::join_all(futures).await;
+    sources
+        .iter()
+        .zip(results)
+        .filter_map(|(source, result)| match result {
+            Ok(quote) => Some(quote),
+            Err(e) => {
+                warn!("Price source {} failed for {}/{}: {}", source.name(), base, rel, e);
+                None
+            },
+        })
+        .collect()
+}
+
+pub struct AggregationSettings {
+    /// The minimum number of quotes agreeing on the price.
+    pub min_sources: usize,
+    /// The maximum relative deviation of an accepted quote from the median.
+    pub max_deviation: MmNumber,
+}
+
+impl AggregationSettings {
+    pub fn new(min_sources: Option<usize>, max_deviation: Option<MmNumber>) -> AggregationSettings {
+        AggregationSettings {
+            min_sources: min_sources.unwrap_or(1).max(1),
+            max_deviation: max_deviation.unwrap_or_else(|| MmNumber::from(DEFAULT_MAX_PRICE_DEVIATION)),
+        }
+    }
+}
+
+fn median(mut values: Vec<MmNumber>) -> Option<MmNumber> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((&values[middle - 1] + &values[middle]) / MmNumber::from(2))
+    } else {
+        Some(values.swap_remove(middle))
+    }
+}
+
+/// The relative deviation of the `price` from the non-zero `median`.
+fn deviation(price: &MmNumber, median: &MmNumber) -> MmNumber {
+    let diff = if price > median { price - median } else { median - price };
+    &diff / median
+}
+
+/// Aggregates the quotes into the `base/rel` rates.
+///
+/// The quotes deviating from the median price more than `settings.max_deviation` are rejected as outliers.
+/// The remaining quotes must be a majority of the answered sources and at least `settings.min_sources`.
+/// The resulting price is the median of the remaining quotes, the timestamp is the oldest one of them.
+pub fn aggregate_quotes(
+    base: &str,
+    rel: &str,
+    quotes: Vec<PriceQuote>,
+    settings: &AggregationSettings,
+) -> Result<RateInfos, MmError<PriceAggregationError>> {
+    let quotes: Vec<_> = quotes.into_iter().filter(|quote| !quote.price.is_zero()).collect();
+    if quotes.is_empty() || quotes.len() < settings.min_sources {
+        return MmError::err(PriceAggregationError::NotEnoughSources {
+            available: quotes.len(),
+            required: settings.min_sources,
+        });
+    }
+
+    let total = quotes.len();
+    // `quotes` are not empty and their prices are not zero.
+    let median_price = median(quotes.iter().map(|quote| quote.price.clone()).collect()).unwrap_or_default();
+    let (accepted, outliers): (Vec<_>, Vec<_>) = quotes
+        .into_iter()
+        .partition(|quote| deviation(&quote.price, &median_price) <= settings.max_deviation);
+    if accepted.len() < settings.min_sources || accepted.len() * 2 <= total {
+        return MmError::err(PriceAggregationError::SourcesDisagree {
+            agreed: accepted.len(),
+            total,
+            median: median_price.to_decimal().to_string(),
+            max_deviation: settings.max_deviation.to_decimal().to_string(),
+        });
+    }
+    for outlier in outliers {
+        warn!(
+            "Price {} of {}/{} from {} deviates from the median {} - rejected",
+            outlier.price.to_decimal(),
+            base,
+            rel,
+            outlier.source,
+            median_price.to_decimal()
+        );
+    }
+
+    let mut rates = RateInfos::new(base.to_owned(), rel.to_owned());
+    rates.price = median(accepted.iter().map(|quote| quote.price.clone()).collect()).unwrap_or_default();
+    rates.base_price = median(
+        accepted
+            .iter()
+            .filter_map(|quote| quote.base_usd_price.clone())
+            .collect(),
+    )
+    .unwrap_or_default();
+    rates.rel_price = median(
+        accepted
+            .iter()
+            .filter_map(|quote| quote.rel_usd_price.clone())
+            .collect(),
+    )
+    .unwrap_or_default();
+    rates.last_updated_timestamp = accepted.iter().map(|quote| quote.last_updated_timestamp).min();
+    rates.base_provider = Provider::Custom;
+    rates.rel_provider = Provider::Custom;
+    Ok(rates)
+}
@@ -0,0 +1,160 @@
+//! Spread strategies of the simple market maker bot.
+//!
+//! The fixed `spread` of the pair config is used if no strategy is set.
+
+use mm2_number::{BigDecimal, MmNumber};
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::collections::VecDeque;
+
+fn default_target_base_ratio() -> MmNumber { MmNumber::from("0.5") }
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum SpreadStrategy {
+    /// Skews the spread and the volume with the inventory imbalance.
+    /// The more base coin we hold compared to `target_base_ratio`, the tighter the spread and the bigger the order,
+    /// and vice versa.
+    Inventory {
+        /// The desired share of the base coin value in the total base + rel value, 0.5 by default.
+        #[serde(default = "default_target_base_ratio")]
+        target_base_ratio: MmNumber,
+        /// The relative change of the spread margin at the maximum imbalance, from 0 to 1.
+        spread_skew: MmNumber,
+        /// The relative change of the volume at the maximum imbalance, from 0 to 1.
+        #[serde(default)]
+        volume_skew: MmNumber,
+    },
+    /// Widens the spread margin by the standard deviation of the relative price changes over the last `window` prices
+    /// fetched by the bot, multiplied by `multiplier`.
+    Volatility {
+        window: usize,
+        multiplier: MmNumber,
+        /// The upper bound of the resulting spread.
+        max_spread: Option<MmNumber>,
+    },
+}
+
+impl SpreadStrategy {
+    pub fn validate(&self) -> Result<(), String> {
+        let zero = MmNumber::default();
+        let one = MmNumber::from(1);
+        match self {
+            SpreadStrategy::Inventory {
+                target_base_ratio,
+                spread_skew,
+                volume_skew,
+            } => {
+                if *target_base_ratio <= zero || *target_base_ratio >= one {
+                    return Err("'target_base_ratio' must be between 0 and 1".to_owned());
+                }
+                if *spread_skew < zero || *spread_skew > one || *volume_skew < zero || *volume_skew > one {
+                    return Err("'spread_skew' and 'volume_skew' must be from 0 to 1".to_owned());
+                }
+            },
+            SpreadStrategy::Volatility { window, multiplier, .. } => {
+                if *window < 3 {
+                    return Err("'window' must be at least 3".to_owned());
+                }
+                if *multiplier < zero {
+                    return Err("'multiplier' must not be negative".to_owned());
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Returns the inventory imbalance from -1 (no base coin at all) through 0 (the base coin share equals the target)
+/// to 1 (no rel coin at all). The base coin balance is valued in the rel coin with the `price`.
+pub fn inventory_imbalance(
+    base_balance: &MmNumber,
+    rel_balance: &MmNumber,
+    price: &MmNumber,
+    target_base_ratio: &MmNumber,
+) -> MmNumber {
+    let base_value = base_balance * price;
+    let total_value = &base_value + rel_balance;
+    if total_value.is_zero() {
+        return MmNumber::default();
+    }
+    let base_ratio = base_value / total_value;
+    let deviation = &base_ratio - target_base_ratio;
+    if base_ratio >= *target_base_ratio {
+        &deviation / &(&MmNumber::from(1) - target_base_ratio)
+    } else {
+        &deviation / target_base_ratio
+    }
+}
+
+/// Tightens the spread margin if the `imbalance` is positive (too much base coin) and widens it otherwise.
+pub fn skew_spread(spread: &MmNumber, imbalance: &MmNumber, spread_skew: &MmNumber) -> MmNumber {
+    let one = MmNumber::from(1);
+    let margin = spread - &one;
+    &one + &(&margin * &(&one - &(spread_skew * imbalance)))
+}
+
+/// Increases the volume if the `imbalance` is positive (too much base coin) and decreases it otherwise.
+/// Returns the new volume and whether the whole `base_balance` should be used instead.
+pub fn skew_volume(
+    volume: &MmNumber,
+    is_max: bool,
+    base_balance: &MmNumber,
+    imbalance: &MmNumber,
+    volume_skew: &MmNumber,
+) -> (MmNumber, bool) {
+    let volume = if is_max { base_balance } else { volume };
+    let skewed = volume * &(&MmNumber::from(1) + &(volume_skew * imbalance));
+    if skewed >= *base_balance {
+        return (MmNumber::default(), true);
+    }
+    (skewed, false)
+}
+
+/// Returns the standard deviation of the relative changes of the consecutive `prices`.
+/// At least 2 changes are required.
+pub fn price_volatility<'a>(prices: impl IntoIterator<Item = &'a MmNumber>) -> Option<MmNumber> {
+    let prices: Vec<f64> = prices
+        .into_iter()
+        .map(|price| price.to_decimal().to_f64())
+        .collect::<Option<_>>()?;
+    let changes: Vec<f64> = prices
+        .windows(2)
+        .filter(|pair| pair[0] != 0.)
+        .map(|pair| (pair[1] - pair[0]) / pair[0])
+        .collect();
+    if changes.len() < 2 {
+        return None;
+    }
+    let mean = changes.iter().sum::<f64>() / changes.len() as f64;
+    let variance = changes.iter().map(|change| (change - mean).powi(2)).sum::<f64>() / changes.len() as f64;
+    BigDecimal::from_f64(variance.sqrt()).map(MmNumber::from)
+}
+
+/// Widens the spread margin by `multiplier * volatility` not exceeding the `max_spread`.
+pub fn volatility_spread(
+    spread: &MmNumber,
+    volatility: &MmNumber,
+    multiplier: &MmNumber,
+    max_spread: Option<&MmNumber>,
+) -> MmNumber {
+    let widened = spread + &(multiplier * volatility);
+    match max_spread {
+        Some(max_spread) if widened > *max_spread => max_spread.clone().max(spread.clone()),
+        _ => widened,
+    }
+}
+
+/// The last prices of a pair fetched by the bot.
+#[derive(Default)]
+pub struct PriceHistory(VecDeque<MmNumber>);
+
+impl PriceHistory {
+    pub fn push(&mut self, price: MmNumber, window: usize) {
+        self.0.push_back(price);
+        while self.0.len() > window {
+            self.0.pop_front();
+        }
+    }
+
+    pub fn volatility(&self) -> Option<MmNumber> { price_volatility(self.0.iter()) }
+}
@@ -4,7 +4,8 @@
 //
 
 use async_trait::async_trait;
-use common::log::info;
+use coins::lp_price::RateInfos;
+use common::log::{error, info};
 use derive_more::Display;
 use futures::lock::Mutex as AsyncMutex;
 use mm2_core::{event_dispatcher::{EventListener, EventUniqueId},
@@ -14,15 +15,25 @@ use mm2_number::MmNumber;
 use std::any::TypeId;
 use std::ops::Deref;
 use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
 
+#[path = "bot_ledger.rs"] mod bot_ledger;
+#[path = "bot_order_ladder.rs"] mod bot_order_ladder;
+#[path = "bot_price_sources.rs"] mod bot_price_sources;
+#[path = "bot_spread_strategy.rs"] mod bot_spread_strategy;
 #[path = "simple_market_maker.rs"] mod simple_market_maker_bot;
 use crate::lp_dispatcher::{LpEvents, StopCtxEvent};
 use crate::lp_message_service::{MessageServiceContext, MAKER_BOT_ROOM_ID};
+use crate::lp_ordermatch::lp_bot::bot_order_ladder::OrderLadder;
+use crate::lp_ordermatch::lp_bot::bot_price_sources::PriceSourceCfg;
+use crate::lp_ordermatch::lp_bot::bot_spread_strategy::{PriceHistory, SpreadStrategy};
 use crate::lp_ordermatch::lp_bot::simple_market_maker_bot::{tear_down_bot, BOT_DEFAULT_REFRESH_RATE,
                                                             PRECISION_FOR_NOTIFICATION};
-use crate::lp_swap::MakerSwapStatusChanged;
-pub use simple_market_maker_bot::{start_simple_market_maker_bot, stop_simple_market_maker_bot,
-                                  StartSimpleMakerBotRequest};
+use crate::lp_ordermatch::my_orders_storage::{MyOrdersResult, MyOrdersStorage, TradingBotStorage};
+use crate::lp_swap::{MakerSwapStatusChanged, SwapStatusChanged};
+pub use bot_ledger::{simple_market_maker_bot_pnl, BotLedgerEntry, PersistedTradingBotState};
+pub use simple_market_maker_bot::{resume_simple_market_maker_bot, start_simple_market_maker_bot,
+                                  stop_simple_market_maker_bot, StartSimpleMakerBotRequest};
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
 #[path = "simple_market_maker_tests.rs"]
@@ -94,6 +105,10 @@ pub struct RunningState {
     trading_bot_cfg: SimpleMakerBotRegistry,
     bot_refresh_rate: f64,
     price_urls: Vec<String>,
+    /// The last prices of the pairs using the [`SpreadStrategy::Volatility`].
+    price_history: HashMap<String, PriceHistory>,
+    /// The last rates of the pairs, the swaps of the bot orders are valued with.
+    last_rates: HashMap<String, RateInfos>,
 }
 
 pub struct StoppingState {
@@ -155,11 +170,29 @@ pub struct SimpleCoinMarketMakerCfg {
     pub min_base_price: Option<MmNumber>,
     pub min_rel_price: Option<MmNumber>,
     pub min_pair_price: Option<MmNumber>,
+    /// The sources the pair price is aggregated from.
+    /// The tickers registry of `price_url`/`price_urls` is used alone if not set.
+    pub price_sources: Option<Vec<PriceSourceCfg>>,
+    /// The minimum number of `price_sources` that must agree on the price, 1 by default.
+    pub min_price_sources: Option<usize>,
+    /// The maximum relative deviation of a source price from the median, 0.05 (5%) by default.
+    /// The prices deviating more are rejected as outliers.
+    pub max_price_deviation: Option<MmNumber>,
+    /// Adjusts the `spread` (and the volume) on every bot iteration, the fixed `spread` is used if not set.
+    pub spread_strategy: Option<SpreadStrategy>,
+    /// Quotes the pair with several orders instead of a single one.
+    pub ladder: Option<OrderLadder>,
 }
 
 #[derive(Default)]
 pub struct TradingBotContext {
     trading_bot_states: AsyncMutex<TradingBotState>,
+    /// The orders of the ladders placed by the bot per trading pair, ordered by level.
+    ladder_orders: AsyncMutex<HashMap<String, Vec<Uuid>>>,
+    /// The orders created or updated by the bot with the timestamp they were seen at the last time.
+    bot_orders: AsyncMutex<HashMap<Uuid, u64>>,
+    /// Serializes the load-apply-save updates of the ledger entries, the swap events are dispatched concurrently.
+    ledger_lock: AsyncMutex<()>,
 }
 
 impl TradingBotContext {
@@ -214,6 +247,42 @@ impl TradingBotContext {
         }
     }
 
+    async fn on_swap_status_changed(&self, ctx: &MmArc, swap_infos: &SwapStatusChanged) {
+        if let Err(e) = self.record_swap_in_ledger(ctx, swap_infos).await {
+            error!("Error recording swap {} in the bot ledger: {}", swap_infos.uuid, e);
+        }
+    }
+
+    /// Records the swap in the ledger if it's started from an order of the bot.
+    /// Maker and taker swaps of both protocol versions are recorded, the ledger entry is created on the first event
+    /// which carries the order uuid and is found by the swap uuid afterwards.
+    async fn record_swap_in_ledger(&self, ctx: &MmArc, swap_infos: &SwapStatusChanged) -> MyOrdersResult<()> {
+        let _ledger_guard = self.ledger_lock.lock().await;
+        let storage = MyOrdersStorage::new(ctx.clone());
+        let entry = match storage.load_bot_ledger_entry(swap_infos.uuid).await? {
+            Some(mut entry) => {
+                entry.apply_event(swap_infos);
+                entry
+            },
+            None => {
+                let order_uuid = match swap_infos.my_order_uuid {
+                    Some(uuid) => uuid,
+                    None => return Ok(()),
+                };
+                if !self.bot_orders.lock().await.contains_key(&order_uuid) {
+                    return Ok(());
+                }
+                let trading_pair = format!("{}/{}", swap_infos.my_coin, swap_infos.other_coin);
+                let rates = match &*self.trading_bot_states.lock().await {
+                    TradingBotState::Running(running_state) => running_state.last_rates.get(&trading_pair).cloned(),
+                    TradingBotState::Stopping(_) | TradingBotState::Stopped(_) => None,
+                };
+                BotLedgerEntry::new(swap_infos, order_uuid, rates.as_ref())
+            },
+        };
+        storage.save_bot_ledger_entry(&entry).await
+    }
+
     async fn on_ctx_stop(&self, ctx: &MmArc) {
         info!("on_ctx_stop event received");
         let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(ctx).unwrap();
@@ -240,6 +309,7 @@ impl EventListener for ArcTradingBotContext {
         match &event {
             LpEvents::MakerSwapStatusChanged(swap_infos) => self.on_maker_swap_status_changed(&ctx, swap_infos).await,
             LpEvents::StopCtxEvent(_) => self.on_ctx_stop(&ctx).await,
+            LpEvents::SwapStatusChanged(swap_infos) => self.on_swap_status_changed(&ctx, swap_infos).await,
             LpEvents::TradingBotEvent(trading_bot_event) => self.on_trading_bot_event(&ctx, trading_bot_event).await,
         }
     }
@@ -248,6 +318,7 @@ impl EventListener for ArcTradingBotContext {
         vec![
             MakerSwapStatusChanged::event_id(),
             StopCtxEvent::event_id(),
+            SwapStatusChanged::event_id(),
             TradingBotStopping::event_id(),
             TradingBotStarted::event_id(),
             TradingBotStopped::event_id(),
@@ -1,3 +1,5 @@
+use super::lp_bot::{BotLedgerEntry, PersistedTradingBotState};
+use super::trigger_orders::TriggerOrder;
 use super::{MakerOrder, MakerOrderCancellationReason, MyOrdersFilter, Order, RecentOrdersSelectResult, TakerOrder,
             TakerOrderCancellationReason};
 use async_trait::async_trait;
@@ -202,14 +204,44 @@ pub trait MyOrdersFilteringHistory {
     async fn update_was_taker_in_filtering_history(&self, uuid: Uuid) -> MyOrdersResult<()>;
 }
 
+/// Stop-limit and trailing orders waiting for their triggers to fire.
+#[async_trait]
+pub trait MyTriggerOrders {
+    async fn load_trigger_orders(&self) -> MyOrdersResult<Vec<TriggerOrder>>;
+
+    async fn save_new_trigger_order(&self, order: &TriggerOrder) -> MyOrdersResult<()>;
+
+    async fn update_trigger_order(&self, order: &TriggerOrder) -> MyOrdersResult<()>;
+
+    /// Succeeds if the order doesn't exist, so the deletion can be retried safely.
+    async fn delete_trigger_order(&self, uuid: Uuid) -> MyOrdersResult<()>;
+}
+
+/// The state of the simple market maker bot and the ledger of the swaps started from its orders.
+#[async_trait]
+pub trait TradingBotStorage {
+    async fn load_trading_bot_state(&self) -> MyOrdersResult<Option<PersistedTradingBotState>>;
+
+    async fn save_trading_bot_state(&self, state: &PersistedTradingBotState) -> MyOrdersResult<()>;
+
+    async fn load_bot_ledger(&self) -> MyOrdersResult<Vec<BotLedgerEntry>>;
+
+    async fn load_bot_ledger_entry(&self, swap_uuid: Uuid) -> MyOrdersResult<Option<BotLedgerEntry>>;
+
+    async fn save_bot_ledger_entry(&self, entry: &BotLedgerEntry) -> MyOrdersResult<()>;
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 mod native_impl {
     use super::*;
     use crate::database::my_orders::{insert_maker_order, insert_taker_order, select_orders_by_filter,
                                      select_status_by_uuid, update_maker_order, update_order_status, update_was_taker};
     use crate::lp_ordermatch::{my_maker_order_file_path, my_maker_orders_dir, my_order_history_file_path,
-                               my_taker_order_file_path, my_taker_orders_dir};
+                               my_taker_order_file_path, my_taker_orders_dir, my_trigger_order_file_path,
+                               my_trigger_orders_dir, trading_bot_ledger_dir, trading_bot_ledger_file_path,
+                               trading_bot_state_file_path};
     use mm2_io::fs::{read_dir_json, read_json, remove_file_async, write_json, FsJsonError};
+    use std::io;
 
     const USE_TMP_FILE: bool = true;
 
@@ -344,6 +376,64 @@ mod native_impl {
             update_was_taker(&self.ctx, uuid).map_to_mm(|e| MyOrdersError::ErrorSaving(e.to_string()))
         }
     }
+
+    #[async_trait]
+    impl MyTriggerOrders for MyOrdersStorage {
+        async fn load_trigger_orders(&self) -> MyOrdersResult<Vec<TriggerOrder>> {
+            let dir_path = my_trigger_orders_dir(&self.ctx);
+            Ok(read_dir_json(&dir_path).await?)
+        }
+
+        async fn save_new_trigger_order(&self, order: &TriggerOrder) -> MyOrdersResult<()> {
+            let path = my_trigger_order_file_path(&self.ctx, &order.uuid);
+            write_json(order, &path, USE_TMP_FILE).await?;
+            Ok(())
+        }
+
+        async fn update_trigger_order(&self, order: &TriggerOrder) -> MyOrdersResult<()> {
+            self.save_new_trigger_order(order).await
+        }
+
+        async fn delete_trigger_order(&self, uuid: Uuid) -> MyOrdersResult<()> {
+            let path = my_trigger_order_file_path(&self.ctx, &uuid);
+            match remove_file_async(&path).await {
+                Ok(()) => Ok(()),
+                // The order has been deleted by a previous attempt already.
+                Err(e) if e.get_inner().kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(e) => MmError::err(MyOrdersError::ErrorSaving(e.to_string())),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl TradingBotStorage for MyOrdersStorage {
+        async fn load_trading_bot_state(&self) -> MyOrdersResult<Option<PersistedTradingBotState>> {
+            let path = trading_bot_state_file_path(&self.ctx);
+            Ok(read_json(&path).await?)
+        }
+
+        async fn save_trading_bot_state(&self, state: &PersistedTradingBotState) -> MyOrdersResult<()> {
+            let path = trading_bot_state_file_path(&self.ctx);
+            write_json(state, &path, USE_TMP_FILE).await?;
+            Ok(())
+        }
+
+        async fn load_bot_ledger(&self) -> MyOrdersResult<Vec<BotLedgerEntry>> {
+            let dir_path = trading_bot_ledger_dir(&self.ctx);
+            Ok(read_dir_json(&dir_path).await?)
+        }
+
+        async fn load_bot_ledger_entry(&self, swap_uuid: Uuid) -> MyOrdersResult<Option<BotLedgerEntry>> {
+            let path = trading_bot_ledger_file_path(&self.ctx, &swap_uuid);
+            Ok(read_json(&path).await?)
+        }
+
+        async fn save_bot_ledger_entry(&self, entry: &BotLedgerEntry) -> MyOrdersResult<()> {
+            let path = trading_bot_ledger_file_path(&self.ctx, &entry.swap_uuid);
+            write_json(entry, &path, USE_TMP_FILE).await?;
+            Ok(())
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -351,7 +441,8 @@ mod wasm_impl {
     use super::*;
     use crate::lp_ordermatch::ordermatch_wasm_db::{DbTransactionError, InitDbError, MyActiveMakerOrdersTable,
                                                    MyActiveTakerOrdersTable, MyFilteringHistoryOrdersTable,
-                                                   MyHistoryOrdersTable};
+                                                   MyHistoryOrdersTable, MyTriggerOrdersTable, TradingBotLedgerTable,
+                                                   TradingBotStateTable, TRADING_BOT_STATE_NAME};
     use crate::lp_ordermatch::OrdermatchContext;
     use common::log::warn;
     use mm2_rpc::data::legacy::TakerAction;
@@ -625,6 +716,118 @@ mod wasm_impl {
         }
     }
 
+    #[async_trait]
+    impl MyTriggerOrders for MyOrdersStorage {
+        async fn load_trigger_orders(&self) -> MyOrdersResult<Vec<TriggerOrder>> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<MyTriggerOrdersTable>().await?;
+            let trigger_orders = table.get_all_items().await?;
+            Ok(trigger_orders
+                .into_iter()
+                .map(|(_item_id, MyTriggerOrdersTable { order_payload, .. })| order_payload)
+                .collect())
+        }
+
+        async fn save_new_trigger_order(&self, order: &TriggerOrder) -> MyOrdersResult<()> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<MyTriggerOrdersTable>().await?;
+
+            let item = MyTriggerOrdersTable {
+                uuid: order.uuid,
+                order_payload: order.clone(),
+            };
+            table.add_item(&item).await?;
+            Ok(())
+        }
+
+        async fn update_trigger_order(&self, order: &TriggerOrder) -> MyOrdersResult<()> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<MyTriggerOrdersTable>().await?;
+
+            let item = MyTriggerOrdersTable {
+                uuid: order.uuid,
+                order_payload: order.clone(),
+            };
+            table.replace_item_by_unique_index("uuid", order.uuid, &item).await?;
+            Ok(())
+        }
+
+        async fn delete_trigger_order(&self, uuid: Uuid) -> MyOrdersResult<()> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<MyTriggerOrdersTable>().await?;
+            table.delete_item_by_unique_index("uuid", uuid).await?;
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl TradingBotStorage for MyOrdersStorage {
+        async fn load_trading_bot_state(&self) -> MyOrdersResult<Option<PersistedTradingBotState>> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<TradingBotStateTable>().await?;
+            Ok(table
+                .get_item_by_unique_index("name", TRADING_BOT_STATE_NAME)
+                .await?
+                .map(|(_item_id, TradingBotStateTable { state_payload, .. })| state_payload))
+        }
+
+        async fn save_trading_bot_state(&self, state: &PersistedTradingBotState) -> MyOrdersResult<()> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<TradingBotStateTable>().await?;
+
+            let item = TradingBotStateTable {
+                name: TRADING_BOT_STATE_NAME.to_owned(),
+                state_payload: state.clone(),
+            };
+            table
+                .replace_item_by_unique_index("name", TRADING_BOT_STATE_NAME, &item)
+                .await?;
+            Ok(())
+        }
+
+        async fn load_bot_ledger(&self) -> MyOrdersResult<Vec<BotLedgerEntry>> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<TradingBotLedgerTable>().await?;
+            let entries = table.get_all_items().await?;
+            Ok(entries
+                .into_iter()
+                .map(|(_item_id, TradingBotLedgerTable { entry_payload, .. })| entry_payload)
+                .collect())
+        }
+
+        async fn load_bot_ledger_entry(&self, swap_uuid: Uuid) -> MyOrdersResult<Option<BotLedgerEntry>> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<TradingBotLedgerTable>().await?;
+            Ok(table
+                .get_item_by_unique_index("swap_uuid", swap_uuid)
+                .await?
+                .map(|(_item_id, TradingBotLedgerTable { entry_payload, .. })| entry_payload))
+        }
+
+        async fn save_bot_ledger_entry(&self, entry: &BotLedgerEntry) -> MyOrdersResult<()> {
+            let db = self.ctx.ordermatch_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<TradingBotLedgerTable>().await?;
+
+            let item = TradingBotLedgerTable {
+                swap_uuid: entry.swap_uuid,
+                entry_payload: entry.clone(),
+            };
+            table
+                .replace_item_by_unique_index("swap_uuid", entry.swap_uuid, &item)
+                .await?;
+            Ok(())
+        }
+    }
+
     pub(super) fn maker_order_to_filtering_history_item(
         order: &MakerOrder,
         status: String,
@@ -726,6 +929,7 @@ mod tests {
             rel_orderbook_ticker: None,
             p2p_privkey: None,
             swap_version: SwapVersion::default(),
+            expires_at: None,
         }
     }
 
@@ -1,15 +1,16 @@
 use async_trait::async_trait;
-use mm2_db::indexed_db::{DbIdentifier, DbInstance, DbUpgrader, IndexedDb, IndexedDbBuilder, OnUpgradeResult,
-                         TableSignature};
+use mm2_db::indexed_db::{DbIdentifier, DbInstance, DbUpgrader, IndexedDb, IndexedDbBuilder, OnUpgradeError,
+                         OnUpgradeResult, TableSignature};
 use std::ops::Deref;
 use uuid::Uuid;
 
 pub use mm2_db::indexed_db::{cursor_prelude, DbTransactionError, DbTransactionResult, InitDbError, InitDbResult,
                              ItemId};
 pub use tables::{MyActiveMakerOrdersTable, MyActiveTakerOrdersTable, MyFilteringHistoryOrdersTable,
-                 MyHistoryOrdersTable};
+                 MyHistoryOrdersTable, MyTriggerOrdersTable, TradingBotLedgerTable, TradingBotStateTable,
+                 TRADING_BOT_STATE_NAME};
 
-const DB_VERSION: u32 = 1;
+const DB_VERSION: u32 = 3;
 
 pub struct OrdermatchDb {
     inner: IndexedDb,
@@ -26,6 +27,9 @@ impl DbInstance for OrdermatchDb {
             .with_table::<MyActiveTakerOrdersTable>()
             .with_table::<MyHistoryOrdersTable>()
             .with_table::<MyFilteringHistoryOrdersTable>()
+            .with_table::<MyTriggerOrdersTable>()
+            .with_table::<TradingBotStateTable>()
+            .with_table::<TradingBotLedgerTable>()
             .build()
             .await?;
         Ok(OrdermatchDb { inner })
@@ -40,7 +44,10 @@ impl Deref for OrdermatchDb {
 
 pub mod tables {
     use super::*;
+    use crate::lp_ordermatch::lp_bot::{BotLedgerEntry, PersistedTradingBotState};
+    use crate::lp_ordermatch::trigger_orders::TriggerOrder;
     use crate::lp_ordermatch::{MakerOrder, Order, TakerOrder};
+    use mm2_err_handle::prelude::MmError;
     use serde_json::Value as Json;
 
     #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -103,26 +110,160 @@ pub mod tables {
     impl TableSignature for MyFilteringHistoryOrdersTable {
         const TABLE_NAME: &'static str = "my_filtering_history_orders";
 
-        fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
-            if let (0, 1) = (old_version, new_version) {
-                let table = upgrader.create_table(Self::TABLE_NAME)?;
-                table.create_index("uuid", true)?;
-                // TODO add other indexes during [`MyOrdersStorage::select_orders_by_filter`] implementation.
+        fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+            while old_version < new_version {
+                match old_version {
+                    0 => {
+                        let table = upgrader.create_table(Self::TABLE_NAME)?;
+                        table.create_index("uuid", true)?;
+                        // TODO add other indexes during [`MyOrdersStorage::select_orders_by_filter`] implementation.
+                    },
+                    1 | 2 => {
+                        // do nothing explicitly because no action is required for MyFilteringHistoryOrdersTable
+                    },
+                    unsupported_version => {
+                        return MmError::err(OnUpgradeError::UnsupportedVersion {
+                            unsupported_version,
+                            old_version,
+                            new_version,
+                        })
+                    },
+                }
+
+                old_version += 1;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct MyTriggerOrdersTable {
+        pub uuid: Uuid,
+        pub order_payload: TriggerOrder,
+    }
+
+    impl TableSignature for MyTriggerOrdersTable {
+        const TABLE_NAME: &'static str = "my_trigger_orders";
+
+        fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+            while old_version < new_version {
+                match old_version {
+                    0 => {
+                        // do nothing explicitly because the table should be created on upgrade
+                        // from version 1 to 2 in order to avoid breaking existing databases
+                    },
+                    1 => {
+                        let table = upgrader.create_table(Self::TABLE_NAME)?;
+                        table.create_index("uuid", true)?;
+                    },
+                    2 => {
+                        // do nothing explicitly because no action is required for MyTriggerOrdersTable
+                    },
+                    unsupported_version => {
+                        return MmError::err(OnUpgradeError::UnsupportedVersion {
+                            unsupported_version,
+                            old_version,
+                            new_version,
+                        })
+                    },
+                }
+
+                old_version += 1;
             }
             Ok(())
         }
     }
 
+    /// The only item of [`TradingBotStateTable`] is stored by this name.
+    pub const TRADING_BOT_STATE_NAME: &str = "simple_market_maker_bot";
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct TradingBotStateTable {
+        pub name: String,
+        pub state_payload: PersistedTradingBotState,
+    }
+
+    impl TableSignature for TradingBotStateTable {
+        const TABLE_NAME: &'static str = "trading_bot_state";
+
+        fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+            on_upgrade_trading_bot_table_v3(upgrader, old_version, new_version, Self::TABLE_NAME, "name")
+        }
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct TradingBotLedgerTable {
+        pub swap_uuid: Uuid,
+        pub entry_payload: BotLedgerEntry,
+    }
+
+    impl TableSignature for TradingBotLedgerTable {
+        const TABLE_NAME: &'static str = "trading_bot_ledger";
+
+        fn on_upgrade_needed(upgrader: &DbUpgrader, old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+            on_upgrade_trading_bot_table_v3(upgrader, old_version, new_version, Self::TABLE_NAME, "swap_uuid")
+        }
+    }
+
+    /// [`TableSignature::on_upgrade_needed`] implementation common for the trading bot tables
+    /// introduced in the version 3 with the only unique index.
+    fn on_upgrade_trading_bot_table_v3(
+        upgrader: &DbUpgrader,
+        mut old_version: u32,
+        new_version: u32,
+        table_name: &'static str,
+        unique_index: &'static str,
+    ) -> OnUpgradeResult<()> {
+        while old_version < new_version {
+            match old_version {
+                0 | 1 => {
+                    // do nothing explicitly because the table should be created on upgrade
+                    // from version 2 to 3 in order to avoid breaking existing databases
+                },
+                2 => {
+                    let table = upgrader.create_table(table_name)?;
+                    table.create_index(unique_index, true)?;
+                },
+                unsupported_version => {
+                    return MmError::err(OnUpgradeError::UnsupportedVersion {
+                        unsupported_version,
+                        old_version,
+                        new_version,
+                    })
+                },
+            }
+
+            old_version += 1;
+        }
+        Ok(())
+    }
+
     /// [`TableSignature::on_upgrade_needed`] implementation common for the most tables with the only `uuid` unique index.
     fn on_upgrade_swap_table_by_uuid_v1(
         upgrader: &DbUpgrader,
-        old_version: u32,
+        mut old_version: u32,
         new_version: u32,
         table_name: &'static str,
     ) -> OnUpgradeResult<()> {
-        if let (0, 1) = (old_version, new_version) {
-            let table = upgrader.create_table(table_name)?;
-            table.create_index("uuid", true)?;
+        while old_version < new_version {
+            match old_version {
+                0 => {
+                    let table = upgrader.create_table(table_name)?;
+                    table.create_index("uuid", true)?;
+                },
+                1 | 2 => {
+                    // do nothing explicitly because no action is required for the active and history orders tables
+                },
+                unsupported_version => {
+                    return MmError::err(OnUpgradeError::UnsupportedVersion {
+                        unsupported_version,
+                        old_version,
+                        new_version,
+                    })
+                },
+            }
+
+            old_version += 1;
         }
         Ok(())
     }
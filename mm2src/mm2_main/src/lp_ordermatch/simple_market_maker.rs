@@ -1,6 +1,13 @@
 use crate::lp_dispatcher::{dispatch_lp_event, DispatcherContext};
+use crate::lp_ordermatch::lp_bot::bot_ledger::{PersistedTradingBotState, BOT_ORDER_RETENTION_SEC};
+use crate::lp_ordermatch::lp_bot::bot_order_ladder::{LadderLevel, OrderLadder};
+use crate::lp_ordermatch::lp_bot::bot_price_sources::{aggregate_quotes, fetch_quotes, AggregationSettings,
+                                                      PriceSourceCfg};
+use crate::lp_ordermatch::lp_bot::bot_spread_strategy::{inventory_imbalance, skew_spread, skew_volume,
+                                                        volatility_spread, SpreadStrategy};
 use crate::lp_ordermatch::lp_bot::{RunningState, StoppedState, StoppingState, TradingBotStarted, TradingBotStopped,
                                    TradingBotStopping, VolumeSettings};
+use crate::lp_ordermatch::my_orders_storage::{MyOrdersStorage, TradingBotStorage};
 use crate::lp_ordermatch::{cancel_all_orders, CancelBy, TradingBotEvent};
 use crate::lp_swap::SavedSwap;
 use crate::{lp_ordermatch::{cancel_order, create_maker_order,
@@ -8,24 +15,27 @@ use crate::{lp_ordermatch::{cancel_order, create_maker_order,
                                      TradingBotState},
                             update_maker_order, CancelOrderReq, MakerOrder, MakerOrderUpdateReq, OrdermatchContext,
                             SetPriceReq},
-            lp_swap::{latest_swaps_for_pair, LatestSwapsErr}};
-use coins::lp_price::{fetch_price_tickers, Provider, RateInfos, PRICE_ENDPOINTS};
+            lp_swap::{get_max_maker_vol, latest_swaps_for_pair, LatestSwapsErr}};
+use coins::lp_price::{fetch_price_tickers, Provider, RateInfos, TickerInfosRegistry, PRICE_ENDPOINTS};
 use coins::{lp_coinfind, GetNonZeroBalance};
 use common::{executor::{SpawnFuture, Timer},
              log::{debug, error, info, warn},
-             Future01CompatExt, HttpStatusCode, StatusCode};
+             now_sec, Future01CompatExt, HttpStatusCode, StatusCode};
 use derive_more::Display;
 use mm2_core::mm_ctx::MmArc;
 use mm2_err_handle::prelude::*;
 use mm2_number::MmNumber;
 use serde_json::Value as Json;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use uuid::Uuid;
 
 // !< constants
 pub const BOT_DEFAULT_REFRESH_RATE: f64 = 30.0;
 pub const PRECISION_FOR_NOTIFICATION: u64 = 8;
 const LATEST_SWAPS_LIMIT: usize = 1000;
+/// How often the resumed bot checks if the coins of its pairs are enabled.
+const BOT_RESUME_COINS_CHECK_INTERVAL: f64 = 5.0;
 
 // !< Type definitions
 pub type StartSimpleMakerBotResult = Result<StartSimpleMakerBotRes, MmError<StartSimpleMakerBotError>>;
@@ -33,14 +43,26 @@ pub type StopSimpleMakerBotResult = Result<StopSimpleMakerBotRes, MmError<StopSi
 pub type OrderProcessingResult = Result<bool, MmError<OrderProcessingError>>;
 pub type VwapProcessingResult = Result<MmNumber, MmError<OrderProcessingError>>;
 pub type OrderPreparationResult = Result<(Option<MmNumber>, MmNumber, MmNumber, bool), MmError<OrderProcessingError>>;
+type PairRatesResult = Result<RateInfos, OrderProcessingError>;
 
-#[derive(Debug, Deserialize, Display, Serialize, SerializeErrorType)]
+#[derive(Clone, Debug, Deserialize, Display, Serialize, SerializeErrorType)]
 #[serde(tag = "error_type", content = "error_data")]
 pub enum OrderProcessingError {
     #[display(fmt = "Rates from provider are Unknown - skipping for {}", key_trade_pair)]
     ProviderUnknown { key_trade_pair: String },
     #[display(fmt = "Price from provider is zero - skipping for {}", key_trade_pair)]
     PriceIsZero { key_trade_pair: String },
+    #[display(
+        fmt = "Price sources can't be aggregated: {} - skipping for {}",
+        reason,
+        key_trade_pair
+    )]
+    PriceAggregationFailed { key_trade_pair: String, reason: String },
+    #[display(
+        fmt = "USD price of base coin is unknown while the volume is set in USD - skipping for {}",
+        key_trade_pair
+    )]
+    UsdPriceUnknown { key_trade_pair: String },
     #[display(fmt = "Last updated price timestamp is invalid - skipping for {}", key_trade_pair)]
     LastUpdatedTimestampInvalid { key_trade_pair: String },
     #[display(
@@ -88,6 +110,8 @@ pub enum OrderProcessingError {
     MyRecentSwapsError(String),
     #[display(fmt = "Base balance is less than the min_vol_usd - skipping")]
     MinVolUsdAboveBalanceUsd,
+    #[display(fmt = "Invalid ladder: {} - skipping", _0)]
+    InvalidLadder(String),
     #[display(fmt = "Legacy error - skipping")]
     LegacyError(String),
 }
@@ -187,8 +211,8 @@ pub enum StopSimpleMakerBotError {
 pub enum StartSimpleMakerBotError {
     #[display(fmt = "The bot is already started")]
     AlreadyStarted,
-    #[display(fmt = "Invalid bot configuration")]
-    InvalidBotConfiguration,
+    #[display(fmt = "Invalid bot configuration: {}", _0)]
+    InvalidBotConfiguration(String),
     #[display(fmt = "Transport error: {}", _0)]
     Transport(String),
     #[display(fmt = "Cannot start the bot if it's currently stopping")]
@@ -214,7 +238,7 @@ impl HttpStatusCode for StartSimpleMakerBotError {
     fn status_code(&self) -> StatusCode {
         match self {
             StartSimpleMakerBotError::AlreadyStarted
-            | StartSimpleMakerBotError::InvalidBotConfiguration
+            | StartSimpleMakerBotError::InvalidBotConfiguration(_)
             | StartSimpleMakerBotError::CannotStartFromStopping => StatusCode::BAD_REQUEST,
             StartSimpleMakerBotError::Transport(_) | StartSimpleMakerBotError::InternalError(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
@@ -253,7 +277,8 @@ pub async fn tear_down_bot(ctx: MmArc) {
     let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(&ctx).unwrap();
     let mut state = simple_market_maker_bot_ctx.trading_bot_states.lock().await;
     if let TradingBotState::Stopped(ref mut stopped_state) = *state {
-        let nb_orders = cancel_pending_orders(&ctx, &stopped_state.trading_bot_cfg.clone()).await;
+        let nb_ladder_orders = cancel_ladder_orders(&ctx, &simple_market_maker_bot_ctx).await;
+        let nb_orders = nb_ladder_orders + cancel_pending_orders(&ctx, &stopped_state.trading_bot_cfg.clone()).await;
         let event: TradingBotEvent = TradingBotStopped { nb_orders }.into();
         dispatch_lp_event(ctx.clone(), event.into()).await;
         stopped_state.trading_bot_cfg.clear();
@@ -383,6 +408,25 @@ async fn cancel_pending_orders(ctx: &MmArc, cfg_registry: &HashMap<String, Simpl
     nb_orders
 }
 
+async fn cancel_ladder_orders(ctx: &MmArc, simple_market_maker_bot_ctx: &TradingBotContext) -> usize {
+    let ladder_orders: Vec<Uuid> = simple_market_maker_bot_ctx
+        .ladder_orders
+        .lock()
+        .await
+        .drain()
+        .flat_map(|(_, uuids)| uuids)
+        .collect();
+    let mut nb_orders = 0;
+    for uuid in ladder_orders {
+        match cancel_order(ctx.clone(), CancelOrderReq { uuid }).await {
+            Ok(_) => nb_orders += 1,
+            // The order could be matched or cancelled by the user already.
+            Err(err) => debug!("Couldn't cancel the ladder order with uuid: {} - err: {}", uuid, err),
+        }
+    }
+    nb_orders
+}
+
 async fn cancel_single_order(ctx: &MmArc, uuid: Uuid) {
     match cancel_order(ctx.clone(), CancelOrderReq { uuid }).await {
         Ok(_) => info!("Order with uuid: {} successfully cancelled", uuid),
@@ -407,6 +451,15 @@ async fn checks_order_prerequisites(
         });
     }
 
+    // Custom price sources may provide the pair price only.
+    let is_usd_volume = matches!(cfg.max_volume, Some(VolumeSettings::Usd(_)))
+        || matches!(cfg.min_volume, Some(VolumeSettings::Usd(_)));
+    if is_usd_volume && rates.base_price.is_zero() {
+        return MmError::err(OrderProcessingError::UsdPriceUnknown {
+            key_trade_pair: key_trade_pair.to_string(),
+        });
+    }
+
     if let Some(min_base_price) = &cfg.min_base_price {
         if &rates.base_price < min_base_price {
             return MmError::err(OrderProcessingError::PriceBelowMinBasePrice {
@@ -467,13 +520,42 @@ async fn prepare_order(
         .await?
         .ok_or_else(|| MmError::new(OrderProcessingError::AssetNotEnabled))?;
     let base_balance = base_coin.get_non_zero_balance().compat().await?;
-    lp_coinfind(ctx, cfg.rel.as_str())
+    let rel_coin = lp_coinfind(ctx, cfg.rel.as_str())
         .await?
         .ok_or_else(|| MmError::new(OrderProcessingError::AssetNotEnabled))?;
 
     debug!("balance for {} is {}", cfg.base, base_balance);
 
-    let mut calculated_price = &rates.price * &cfg.spread;
+    let mut spread = cfg.spread.clone();
+    let mut inventory_volume_skew = None;
+    if let Some(SpreadStrategy::Inventory {
+        target_base_ratio,
+        spread_skew,
+        volume_skew,
+    }) = &cfg.spread_strategy
+    {
+        let rel_balance = rel_coin
+            .my_spendable_balance()
+            .compat()
+            .await
+            .mm_err(|_| OrderProcessingError::BalanceInternalError)?;
+        let imbalance = inventory_imbalance(
+            &base_balance,
+            &MmNumber::from(rel_balance),
+            &rates.price,
+            target_base_ratio,
+        );
+        spread = skew_spread(&cfg.spread, &imbalance, spread_skew);
+        debug!(
+            "inventory imbalance for {} is {:.4} - spread skewed to {:.8}",
+            key_trade_pair,
+            imbalance.to_decimal(),
+            spread.to_decimal()
+        );
+        inventory_volume_skew = Some((imbalance, volume_skew));
+    }
+
+    let mut calculated_price = &rates.price * &spread;
     debug!("calculated price is: {}", calculated_price);
     if cfg.check_last_bidirectional_trade_thresh_hold.unwrap_or(false) {
         calculated_price = vwap_calculator(calculated_price.clone(), ctx, cfg).await?;
@@ -500,6 +582,15 @@ async fn prepare_order(
         _ => MmNumber::default(),
     };
 
+    let volume = match inventory_volume_skew {
+        Some((imbalance, volume_skew)) => {
+            let (volume, skewed_is_max) = skew_volume(&volume, is_max, &base_balance, &imbalance, volume_skew);
+            is_max = skewed_is_max;
+            volume
+        },
+        None => volume,
+    };
+
     let min_vol = match &cfg.min_volume {
         Some(VolumeSettings::Percentage(min_volume_percentage)) => {
             if is_max {
@@ -543,6 +634,7 @@ async fn update_single_order(
     let resp = update_maker_order(ctx, req)
         .await
         .map_to_mm(OrderProcessingError::OrderUpdateError)?;
+    remember_bot_orders(ctx, [resp.uuid]).await;
 
     let vol_info = if is_max {
         "max volume".to_string()
@@ -562,9 +654,17 @@ async fn update_single_order(
 async fn execute_update_order(
     uuid: Uuid,
     order: MakerOrder,
-    cloned_infos: (MmArc, RateInfos, TradingPair, SimpleCoinMarketMakerCfg),
+    cloned_infos: (MmArc, PairRatesResult, TradingPair, SimpleCoinMarketMakerCfg),
 ) -> bool {
     let (ctx, rates, key_trade_pair, cfg) = cloned_infos;
+    let rates = match rates {
+        Ok(rates) => rates,
+        Err(err) => {
+            error!("Order with uuid: {} cannot be updated - err: {err}", order.uuid);
+            cancel_single_order(&ctx, order.uuid).await;
+            return false;
+        },
+    };
     match update_single_order(&rates, cfg, uuid, key_trade_pair.as_combination(), &ctx).await {
         Ok(resp) => resp,
         Err(err) => {
@@ -601,11 +701,13 @@ async fn create_single_order(
         rel_confs: cfg.rel_confs,
         rel_nota: cfg.rel_nota,
         save_in_history: true,
+        expires_at: None,
     };
 
     let resp = create_maker_order(&ctx, req)
         .await
         .map_to_mm(OrderProcessingError::OrderUpdateError)?;
+    remember_bot_orders(&ctx, [resp.uuid]).await;
     let vol_info = if is_max {
         "max volume".to_string()
     } else {
@@ -622,11 +724,18 @@ async fn create_single_order(
 }
 
 async fn execute_create_single_order(
-    rates: RateInfos,
+    rates: PairRatesResult,
     cfg: SimpleCoinMarketMakerCfg,
     key_trade_pair: String,
     ctx: &MmArc,
 ) -> bool {
+    let rates = match rates {
+        Ok(rates) => rates,
+        Err(err) => {
+            error!("{err} - order cannot be created for: {key_trade_pair}.");
+            return false;
+        },
+    };
     match create_single_order(&rates, cfg, key_trade_pair.clone(), ctx.clone()).await {
         Ok(resp) => resp,
         Err(err) => {
@@ -639,6 +748,270 @@ async fn execute_create_single_order(
     }
 }
 
+async fn prepare_ladder(
+    rates: PairRatesResult,
+    cfg: &SimpleCoinMarketMakerCfg,
+    ladder: &OrderLadder,
+    key_trade_pair: &str,
+    ctx: &MmArc,
+) -> Result<Vec<(LadderLevel, Option<MmNumber>)>, MmError<OrderProcessingError>> {
+    let rates = rates.map_err(MmError::new)?;
+    let (min_vol, volume, calculated_price, is_max) = prepare_order(&rates, cfg, key_trade_pair, ctx).await?;
+    let base_coin = lp_coinfind(ctx, cfg.base.as_str())
+        .await?
+        .ok_or_else(|| MmError::new(OrderProcessingError::AssetNotEnabled))?;
+    let rel_coin = lp_coinfind(ctx, cfg.rel.as_str())
+        .await?
+        .ok_or_else(|| MmError::new(OrderProcessingError::AssetNotEnabled))?;
+    let total_volume = if is_max {
+        get_max_maker_vol(ctx, &base_coin)
+            .await
+            .mm_err(|e| OrderProcessingError::OrderCreationError(e.to_string()))?
+            .volume
+    } else {
+        volume
+    };
+
+    let levels = ladder.levels(&calculated_price, &total_volume);
+    OrderLadder::validate_levels(&levels, &base_coin.min_trading_vol(), &rel_coin.min_trading_vol())
+        .map_to_mm(OrderProcessingError::InvalidLadder)?;
+    let levels = levels
+        .into_iter()
+        .map(|level| {
+            // The min volume can't exceed the volume of the level.
+            let min_vol = min_vol.clone().map(|min_vol| min_vol.min(level.volume.clone()));
+            (level, min_vol)
+        })
+        .collect();
+    Ok(levels)
+}
+
+async fn update_ladder_order(
+    ctx: &MmArc,
+    cfg: &SimpleCoinMarketMakerCfg,
+    order: &MakerOrder,
+    level: LadderLevel,
+    min_vol: Option<MmNumber>,
+) -> Result<Uuid, String> {
+    let volume_delta = &level.volume - &order.available_amount();
+    let req = MakerOrderUpdateReq {
+        uuid: order.uuid,
+        new_price: Some(level.price),
+        max: None,
+        volume_delta: Some(volume_delta).filter(|delta| !delta.is_zero()),
+        min_volume: min_vol,
+        base_confs: cfg.base_confs,
+        base_nota: cfg.base_nota,
+        rel_confs: cfg.rel_confs,
+        rel_nota: cfg.rel_nota,
+    };
+    update_maker_order(ctx, req).await.map(|order| order.uuid)
+}
+
+async fn create_ladder_order(
+    ctx: &MmArc,
+    cfg: &SimpleCoinMarketMakerCfg,
+    level: LadderLevel,
+    min_vol: Option<MmNumber>,
+) -> Result<Uuid, String> {
+    let req = SetPriceReq {
+        base: cfg.base.clone(),
+        rel: cfg.rel.clone(),
+        price: level.price,
+        max: false,
+        volume: level.volume,
+        min_volume: min_vol,
+        // The other levels of the ladder must be kept.
+        cancel_previous: false,
+        base_confs: cfg.base_confs,
+        base_nota: cfg.base_nota,
+        rel_confs: cfg.rel_confs,
+        rel_nota: cfg.rel_nota,
+        save_in_history: true,
+        expires_at: None,
+    };
+    create_maker_order(ctx, req).await.map(|order| order.uuid)
+}
+
+/// Places the levels of the ladder updating the existing `ladder_orders` first.
+/// Cancels the whole ladder if the pair can't be quoted. Returns the orders of the ladder.
+async fn execute_ladder(
+    ctx: &MmArc,
+    rates: PairRatesResult,
+    cfg: SimpleCoinMarketMakerCfg,
+    ladder: OrderLadder,
+    key_trade_pair: &str,
+    ladder_orders: Vec<MakerOrder>,
+) -> Vec<Uuid> {
+    let levels = match prepare_ladder(rates, &cfg, &ladder, key_trade_pair, ctx).await {
+        Ok(levels) => levels,
+        Err(err) => {
+            error!(
+                "{err} - ladder cannot be placed for: {key_trade_pair} - cancel {} orders",
+                ladder_orders.len()
+            );
+            for order in ladder_orders {
+                cancel_single_order(ctx, order.uuid).await;
+            }
+            return Vec::new();
+        },
+    };
+
+    let mut ladder_orders = ladder_orders.into_iter();
+    let mut placed = Vec::with_capacity(levels.len());
+    for (level_index, (level, min_vol)) in levels.into_iter().enumerate() {
+        let level_info = format!(
+            "rate: ({:.8} {key_trade_pair}) - volume: {:.8}",
+            level.price.to_decimal(),
+            level.volume.to_decimal()
+        );
+        match ladder_orders.next() {
+            Some(order) => match update_ladder_order(ctx, &cfg, &order, level, min_vol).await {
+                Ok(uuid) => {
+                    info!("Successfully update level {level_index} of the ladder for {key_trade_pair} - uuid: {uuid} - {level_info}");
+                    placed.push(uuid);
+                },
+                Err(err) => {
+                    error!("Level {level_index} of the ladder for {key_trade_pair} cannot be updated - {level_info} - err: {err}");
+                    cancel_single_order(ctx, order.uuid).await;
+                },
+            },
+            None => match create_ladder_order(ctx, &cfg, level, min_vol).await {
+                Ok(uuid) => {
+                    info!("Successfully create level {level_index} of the ladder for {key_trade_pair} - uuid: {uuid} - {level_info}");
+                    placed.push(uuid);
+                },
+                Err(err) => {
+                    error!("Level {level_index} of the ladder for {key_trade_pair} cannot be created - {level_info} - err: {err}")
+                },
+            },
+        }
+    }
+
+    // The remaining orders don't fit the ladder anymore.
+    for order in ladder_orders {
+        cancel_single_order(ctx, order.uuid).await;
+    }
+    remember_bot_orders(ctx, placed.iter().copied()).await;
+    placed
+}
+
+fn uses_tickers_registry(cfg: &SimpleCoinMarketMakerCfg) -> bool {
+    match &cfg.price_sources {
+        Some(price_sources) => cfg.enable && price_sources.contains(&PriceSourceCfg::TickersRegistry),
+        None => cfg.enable,
+    }
+}
+
+/// Returns the rates of the pair from the tickers registry or aggregated from the custom price sources if they're set.
+async fn fetch_pair_rates(
+    ctx: &MmArc,
+    rates_registry: &Option<Arc<TickerInfosRegistry>>,
+    cfg: &SimpleCoinMarketMakerCfg,
+    key_trade_pair: &str,
+) -> PairRatesResult {
+    let price_sources = match &cfg.price_sources {
+        Some(price_sources) => price_sources,
+        None => {
+            return Ok(rates_registry
+                .as_ref()
+                .and_then(|registry| registry.get_cex_rates(&cfg.base, &cfg.rel))
+                .unwrap_or_default())
+        },
+    };
+
+    let sources: Vec<_> = price_sources
+        .iter()
+        .map(|source| source.build(rates_registry.clone()))
+        .collect();
+    let quotes = fetch_quotes(ctx, &sources, &cfg.base, &cfg.rel).await;
+    let settings = AggregationSettings::new(cfg.min_price_sources, cfg.max_price_deviation.clone());
+    aggregate_quotes(&cfg.base, &cfg.rel, quotes, &settings).map_err(|e| OrderProcessingError::PriceAggregationFailed {
+        key_trade_pair: key_trade_pair.to_owned(),
+        reason: e.into_inner().to_string(),
+    })
+}
+
+/// Checks that the custom price sources of the pair can ever satisfy the aggregation settings.
+fn check_price_sources_cfg(cfg: &SimpleCoinMarketMakerCfg) -> Result<(), String> {
+    let price_sources = match &cfg.price_sources {
+        Some(price_sources) => price_sources,
+        None => return Ok(()),
+    };
+    if price_sources.is_empty() {
+        return Err("'price_sources' must not be empty".to_owned());
+    }
+    if cfg.min_price_sources.unwrap_or(1) > price_sources.len() {
+        return Err("'min_price_sources' is greater than the number of 'price_sources'".to_owned());
+    }
+    if let Some(max_price_deviation) = &cfg.max_price_deviation {
+        if *max_price_deviation <= MmNumber::default() {
+            return Err("'max_price_deviation' must be positive".to_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Records the fetched rates of the pairs the swaps of the bot orders are valued with.
+/// Also records the prices of the pairs using the [`SpreadStrategy::Volatility`]
+/// and returns the spreads widened by the volatility of their price history.
+async fn record_pair_rates(
+    simple_market_maker_bot_ctx: &TradingBotContext,
+    pair_rates: &HashMap<String, PairRatesResult>,
+) -> HashMap<String, MmNumber> {
+    let mut state = simple_market_maker_bot_ctx.trading_bot_states.lock().await;
+    let running_state = match &mut *state {
+        TradingBotState::Running(running_state) => running_state,
+        TradingBotState::Stopping(_) | TradingBotState::Stopped(_) => return HashMap::new(),
+    };
+
+    for (trading_pair, rates) in pair_rates.iter() {
+        if let Ok(rates) = rates {
+            running_state.last_rates.insert(trading_pair.clone(), rates.clone());
+        }
+    }
+
+    let mut spreads = HashMap::new();
+    for (trading_pair, cfg) in running_state.trading_bot_cfg.iter() {
+        let (window, multiplier, max_spread) = match &cfg.spread_strategy {
+            Some(SpreadStrategy::Volatility {
+                window,
+                multiplier,
+                max_spread,
+            }) => (*window, multiplier, max_spread),
+            _ => continue,
+        };
+        let history = running_state.price_history.entry(trading_pair.clone()).or_default();
+        if let Some(Ok(rates)) = pair_rates.get(trading_pair) {
+            if !rates.price.is_zero() {
+                history.push(rates.price.clone(), window);
+            }
+        }
+        if let Some(volatility) = history.volatility() {
+            let spread = volatility_spread(&cfg.spread, &volatility, multiplier, max_spread.as_ref());
+            debug!(
+                "price volatility for {} is {:.6} - spread widened to {:.8}",
+                trading_pair,
+                volatility.to_decimal(),
+                spread.to_decimal()
+            );
+            spreads.insert(trading_pair.clone(), spread);
+        }
+    }
+    spreads
+}
+
+fn with_volatility_spread(
+    mut cfg: SimpleCoinMarketMakerCfg,
+    volatility_spreads: &HashMap<String, MmNumber>,
+    trading_pair: &str,
+) -> SimpleCoinMarketMakerCfg {
+    if let Some(spread) = volatility_spreads.get(trading_pair) {
+        cfg.spread = spread.clone();
+    }
+    cfg
+}
+
 async fn process_bot_logic(ctx: &MmArc) {
     let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(ctx).unwrap();
     let mut state = simple_market_maker_bot_ctx.trading_bot_states.lock().await;
@@ -648,22 +1021,49 @@ async fn process_bot_logic(ctx: &MmArc) {
     };
 
     let cfg = running_state.trading_bot_cfg.clone();
-    let rates_registry = match fetch_price_tickers(&mut running_state.price_urls).await {
-        Ok(model) => model,
-        Err(err) => {
-            let nb_orders = cancel_pending_orders(ctx, &cfg).await;
-            error!("error fetching price: {err:?} - cancel {nb_orders} orders");
-            return;
-        },
+    let rates_registry = if cfg.values().any(uses_tickers_registry) {
+        match fetch_price_tickers(&mut running_state.price_urls).await {
+            Ok(model) => Some(Arc::new(model)),
+            // The pairs with custom price sources may still be quoted without the tickers registry.
+            Err(err) if cfg.values().any(|cur_cfg| cur_cfg.price_sources.is_some()) => {
+                error!("error fetching price: {err:?}");
+                None
+            },
+            Err(err) => {
+                let nb_orders = cancel_pending_orders(ctx, &cfg).await;
+                error!("error fetching price: {err:?} - cancel {nb_orders} orders");
+                return;
+            },
+        }
+    } else {
+        None
     };
 
     drop(state);
 
+    let rates_registry = &rates_registry;
+    let pair_rates_futures =
+        cfg.iter()
+            .filter(|(_, cur_cfg)| cur_cfg.enable)
+            .map(|(trading_pair, cur_cfg)| async move {
+                let rates = fetch_pair_rates(ctx, rates_registry, cur_cfg, trading_pair).await;
+                (trading_pair.clone(), rates)
+            });
+    let pair_rates: HashMap<String, PairRatesResult> = futures::future::join_all(pair_rates_futures)
+        .await
+        .into_iter()
+        .collect();
+
+    let volatility_spreads = record_pair_rates(&simple_market_maker_bot_ctx, &pair_rates).await;
+
+    let ladder_orders = simple_market_maker_bot_ctx.ladder_orders.lock().await.clone();
     let mut memoization_pair_registry: HashSet<String> = HashSet::new();
     let ordermatch_ctx = OrdermatchContext::from_ctx(ctx).unwrap();
     let maker_orders = ordermatch_ctx.maker_orders_ctx.lock().orders.clone();
     let mut futures_order_update = Vec::with_capacity(maker_orders.len());
-    for (uuid, order_mutex) in maker_orders {
+    let mut stray_ladder_pair_orders = Vec::new();
+    for (uuid, order_mutex) in maker_orders.iter() {
+        let uuid = *uuid;
         let order = order_mutex.lock().await;
         let key_trade_pair = TradingPair::new(order.base.clone(), order.rel.clone());
 
@@ -671,13 +1071,25 @@ async fn process_bot_logic(ctx: &MmArc) {
             if !coin_cfg.enable {
                 continue;
             }
+            // The ladder orders are processed as a group below, the other orders of the pair are cancelled
+            // the same way `setprice` with `cancel_previous` does for the single order pairs.
+            if coin_cfg.ladder.is_some() {
+                let is_ladder_order = ladder_orders
+                    .get(&key_trade_pair.as_combination())
+                    .map_or(false, |uuids| uuids.contains(&uuid));
+                if !is_ladder_order {
+                    stray_ladder_pair_orders.push(uuid);
+                }
+                continue;
+            }
             let cloned_infos = (
                 ctx.clone(),
-                rates_registry
-                    .get_cex_rates(&coin_cfg.base, &coin_cfg.rel)
-                    .unwrap_or_default(),
+                pair_rates
+                    .get(&key_trade_pair.as_combination())
+                    .cloned()
+                    .unwrap_or_else(|| Ok(RateInfos::default())),
                 key_trade_pair.clone(),
-                coin_cfg.clone(),
+                with_volatility_spread(coin_cfg.clone(), &volatility_spreads, &key_trade_pair.as_combination()),
             );
             futures_order_update.push(execute_update_order(uuid, order.clone(), cloned_infos));
             memoization_pair_registry.insert(key_trade_pair.as_combination());
@@ -686,15 +1098,51 @@ async fn process_bot_logic(ctx: &MmArc) {
 
     let _results_order_updates = futures::future::join_all(futures_order_update).await;
 
+    for uuid in stray_ladder_pair_orders {
+        cancel_single_order(ctx, uuid).await;
+    }
+
+    let mut futures_ladders = Vec::new();
+    for (trading_pair, cur_cfg) in cfg.iter() {
+        let ladder = match &cur_cfg.ladder {
+            Some(ladder) if cur_cfg.enable => ladder.clone(),
+            _ => continue,
+        };
+        let mut orders = Vec::new();
+        for uuid in ladder_orders.get(trading_pair).into_iter().flatten() {
+            if let Some(order_mutex) = maker_orders.get(uuid) {
+                orders.push(order_mutex.lock().await.clone());
+            }
+        }
+        let rates_infos = pair_rates
+            .get(trading_pair)
+            .cloned()
+            .unwrap_or_else(|| Ok(RateInfos::default()));
+        let cur_cfg = with_volatility_spread(cur_cfg.clone(), &volatility_spreads, trading_pair);
+        futures_ladders.push(async move {
+            let placed = execute_ladder(ctx, rates_infos, cur_cfg, ladder, trading_pair, orders).await;
+            (trading_pair.clone(), placed)
+        });
+        memoization_pair_registry.insert(trading_pair.clone());
+    }
+    let placed_ladders = futures::future::join_all(futures_ladders).await;
+    simple_market_maker_bot_ctx
+        .ladder_orders
+        .lock()
+        .await
+        .extend(placed_ladders);
+
     let mut futures_order_creation = Vec::with_capacity(cfg.len());
     // Now iterate over the registry and for every pairs that are not hit let's create an order
     for (trading_pair, cur_cfg) in cfg {
         if memoization_pair_registry.get(&trading_pair).is_some() || !cur_cfg.enable {
             continue;
         }
-        let rates_infos = rates_registry
-            .get_cex_rates(&cur_cfg.base, &cur_cfg.rel)
-            .unwrap_or_default();
+        let rates_infos = pair_rates
+            .get(&trading_pair)
+            .cloned()
+            .unwrap_or_else(|| Ok(RateInfos::default()));
+        let cur_cfg = with_volatility_spread(cur_cfg, &volatility_spreads, &trading_pair);
         futures_order_creation.push(execute_create_single_order(
             rates_infos,
             cur_cfg,
@@ -703,6 +1151,55 @@ async fn process_bot_logic(ctx: &MmArc) {
         ));
     }
     let _results_order_creations = futures::future::join_all(futures_order_creation).await;
+
+    let now = now_sec();
+    simple_market_maker_bot_ctx
+        .bot_orders
+        .lock()
+        .await
+        .retain(|_, last_seen| *last_seen + BOT_ORDER_RETENTION_SEC >= now);
+    let persisted = match &*simple_market_maker_bot_ctx.trading_bot_states.lock().await {
+        TradingBotState::Running(running_state) => {
+            Some(persisted_state(&simple_market_maker_bot_ctx, running_state, true).await)
+        },
+        TradingBotState::Stopping(_) | TradingBotState::Stopped(_) => None,
+    };
+    if let Some(persisted) = persisted {
+        save_bot_state(ctx, &persisted).await;
+    }
+}
+
+/// Remembers the orders of the bot, so the swaps started from them are recorded in the ledger.
+async fn remember_bot_orders(ctx: &MmArc, uuids: impl IntoIterator<Item = Uuid>) {
+    let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(ctx).unwrap();
+    let now = now_sec();
+    simple_market_maker_bot_ctx
+        .bot_orders
+        .lock()
+        .await
+        .extend(uuids.into_iter().map(|uuid| (uuid, now)));
+}
+
+async fn persisted_state(
+    simple_market_maker_bot_ctx: &TradingBotContext,
+    running_state: &RunningState,
+    is_running: bool,
+) -> PersistedTradingBotState {
+    PersistedTradingBotState {
+        trading_bot_cfg: running_state.trading_bot_cfg.clone(),
+        bot_refresh_rate: running_state.bot_refresh_rate,
+        price_urls: running_state.price_urls.clone(),
+        is_running,
+        bot_orders: simple_market_maker_bot_ctx.bot_orders.lock().await.clone(),
+        ladder_orders: simple_market_maker_bot_ctx.ladder_orders.lock().await.clone(),
+    }
+}
+
+async fn save_bot_state(ctx: &MmArc, state: &PersistedTradingBotState) {
+    let storage = MyOrdersStorage::new(ctx.clone());
+    if let Err(e) = storage.save_trading_bot_state(state).await {
+        error!("Error saving simple_market_maker_bot state: {e}");
+    }
 }
 
 pub async fn lp_bot_loop(ctx: MmArc) {
@@ -735,27 +1232,134 @@ pub async fn lp_bot_loop(ctx: MmArc) {
 }
 
 pub async fn start_simple_market_maker_bot(ctx: MmArc, req: StartSimpleMakerBotRequest) -> StartSimpleMakerBotResult {
+    let persisted = PersistedTradingBotState {
+        trading_bot_cfg: req.cfg,
+        bot_refresh_rate: req.bot_refresh_rate.unwrap_or(BOT_DEFAULT_REFRESH_RATE),
+        price_urls: req.price_sources.unwrap_or_default().get_urls(),
+        is_running: true,
+        bot_orders: HashMap::new(),
+        ladder_orders: HashMap::new(),
+    };
+    start_bot(ctx, persisted).await
+}
+
+/// Resumes the bot if it was running when the node stopped.
+pub async fn resume_simple_market_maker_bot(ctx: MmArc) {
+    let storage = MyOrdersStorage::new(ctx.clone());
+    let persisted = match storage.load_trading_bot_state().await {
+        Ok(Some(persisted)) => persisted,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Error loading simple_market_maker_bot state: {e}");
+            return;
+        },
+    };
+    // The swaps of the bot orders started before the restart are still recorded in the ledger.
+    let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(&ctx).unwrap();
+    let dispatcher_ctx = DispatcherContext::from_ctx(&ctx).unwrap();
+    dispatcher_ctx
+        .dispatcher
+        .write()
+        .await
+        .add_listener(simple_market_maker_bot_ctx.clone());
+    if !persisted.is_running {
+        return;
+    }
+    if !wait_for_bot_coins(&ctx, &persisted.trading_bot_cfg).await {
+        return;
+    }
+    info!(
+        "Resuming simple_market_maker_bot with {} pairs",
+        persisted.trading_bot_cfg.len()
+    );
+    if let Err(e) = start_bot(ctx, persisted).await {
+        error!("Error resuming simple_market_maker_bot: {e}");
+    }
+}
+
+/// Waits until the coins of the enabled pairs are activated since the bot can't place the orders before.
+/// Returns false if the node is stopping or the bot was started by the user in the meantime.
+async fn wait_for_bot_coins(ctx: &MmArc, cfg: &SimpleMakerBotRegistry) -> bool {
+    let tickers: HashSet<&str> = cfg
+        .values()
+        .filter(|pair_cfg| pair_cfg.enable)
+        .flat_map(|pair_cfg| [pair_cfg.base.as_str(), pair_cfg.rel.as_str()])
+        .collect();
+    let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(ctx).unwrap();
+    let mut logged = false;
+    loop {
+        if ctx.is_stopping() {
+            return false;
+        }
+        if !matches!(
+            *simple_market_maker_bot_ctx.trading_bot_states.lock().await,
+            TradingBotState::Stopped(_)
+        ) {
+            return false;
+        }
+        let mut missing = Vec::new();
+        for ticker in tickers.iter() {
+            if !matches!(lp_coinfind(ctx, ticker).await, Ok(Some(_))) {
+                missing.push(*ticker);
+            }
+        }
+        if missing.is_empty() {
+            return true;
+        }
+        if !logged {
+            info!("simple_market_maker_bot waits for the coins to be enabled: {missing:?}");
+            logged = true;
+        }
+        Timer::sleep(BOT_RESUME_COINS_CHECK_INTERVAL).await;
+    }
+}
+
+async fn start_bot(ctx: MmArc, persisted: PersistedTradingBotState) -> StartSimpleMakerBotResult {
     let simple_market_maker_bot_ctx = TradingBotContext::from_ctx(&ctx).unwrap();
     let mut state = simple_market_maker_bot_ctx.trading_bot_states.lock().await;
     match *state {
         TradingBotState::Running { .. } => MmError::err(StartSimpleMakerBotError::AlreadyStarted),
         TradingBotState::Stopping(_) => MmError::err(StartSimpleMakerBotError::CannotStartFromStopping),
         TradingBotState::Stopped(_) => {
+            for (trading_pair, cfg) in persisted.trading_bot_cfg.iter() {
+                let spread_strategy_check = match &cfg.spread_strategy {
+                    Some(spread_strategy) => spread_strategy.validate(),
+                    None => Ok(()),
+                };
+                let ladder_check = match &cfg.ladder {
+                    Some(ladder) => ladder.validate(),
+                    None => Ok(()),
+                };
+                if let Err(e) = check_price_sources_cfg(cfg)
+                    .and(spread_strategy_check)
+                    .and(ladder_check)
+                {
+                    let error = format!("Invalid configuration for {trading_pair}: {e}");
+                    error!("{error}");
+                    return MmError::err(StartSimpleMakerBotError::InvalidBotConfiguration(error));
+                }
+            }
             let dispatcher_ctx = DispatcherContext::from_ctx(&ctx).unwrap();
             let mut dispatcher = dispatcher_ctx.dispatcher.write().await;
             dispatcher.add_listener(simple_market_maker_bot_ctx.clone());
-            let mut refresh_rate = req.bot_refresh_rate.unwrap_or(BOT_DEFAULT_REFRESH_RATE);
+            let mut refresh_rate = persisted.bot_refresh_rate;
             if refresh_rate < BOT_DEFAULT_REFRESH_RATE {
                 refresh_rate = BOT_DEFAULT_REFRESH_RATE;
             }
-            let nb_pairs = req.cfg.len();
-            *state = RunningState {
-                trading_bot_cfg: req.cfg,
+            let nb_pairs = persisted.trading_bot_cfg.len();
+            *simple_market_maker_bot_ctx.bot_orders.lock().await = persisted.bot_orders;
+            *simple_market_maker_bot_ctx.ladder_orders.lock().await = persisted.ladder_orders;
+            let running_state = RunningState {
+                trading_bot_cfg: persisted.trading_bot_cfg,
                 bot_refresh_rate: refresh_rate,
-                price_urls: req.price_sources.unwrap_or_default().get_urls(),
-            }
-            .into();
+                price_urls: persisted.price_urls,
+                price_history: HashMap::new(),
+                last_rates: HashMap::new(),
+            };
+            let persisted = persisted_state(&simple_market_maker_bot_ctx, &running_state, true).await;
+            *state = running_state.into();
             drop(state);
+            save_bot_state(&ctx, &persisted).await;
             let event: TradingBotEvent = TradingBotStarted { nb_pairs }.into();
             dispatcher.dispatch_async(ctx.clone(), event.into()).await;
             ctx.spawner().spawn(lp_bot_loop(ctx.clone()));
@@ -777,11 +1381,13 @@ pub async fn stop_simple_market_maker_bot(ctx: MmArc, _req: Json) -> StopSimpleM
                 bot_refresh_rate: running_state.bot_refresh_rate,
             }
             .into();
+            let persisted = persisted_state(&simple_market_maker_bot_ctx, running_state, false).await;
             *state = StoppingState {
                 trading_bot_cfg: running_state.trading_bot_cfg.clone(),
             }
             .into();
             drop(state);
+            save_bot_state(&ctx, &persisted).await;
             dispatch_lp_event(ctx.clone(), event.into()).await;
             Ok(StopSimpleMakerBotRes {
                 result: "Success".to_string(),
@@ -1,8 +1,21 @@
+use crate::lp_ordermatch::lp_bot::bot_ledger::{calculate_pnl, BotLedgerEntry, BotSwapStatus};
+use crate::lp_ordermatch::lp_bot::bot_order_ladder::{LadderLevel, OrderLadder, MAX_LADDER_LEVELS};
+use crate::lp_ordermatch::lp_bot::bot_price_sources::{aggregate_quotes, price_at_pointer, AggregationSettings,
+                                                      PriceAggregationError, PriceQuote};
+use crate::lp_ordermatch::lp_bot::bot_spread_strategy::{inventory_imbalance, price_volatility, skew_spread,
+                                                        skew_volume, volatility_spread, PriceHistory, SpreadStrategy};
+use crate::lp_ordermatch::lp_bot::TradingBotContext;
+use crate::lp_ordermatch::my_orders_storage::{MyOrdersStorage, TradingBotStorage};
+use crate::lp_ordermatch::trading_bot_ledger_dir;
 use crate::{lp_ordermatch::lp_bot::simple_market_maker_bot::vwap,
             lp_ordermatch::lp_bot::SimpleCoinMarketMakerCfg,
-            lp_swap::{MakerSavedSwap, SavedSwap}};
-use common::{block_on, log::UnifiedLoggerBuilder};
-use mm2_number::MmNumber;
+            lp_swap::{MakerSavedSwap, SavedSwap, SwapStatusChanged}};
+use coins::lp_price::RateInfos;
+use common::{block_on, log::UnifiedLoggerBuilder, now_sec};
+use mm2_number::{BigDecimal, MmNumber};
+use mm2_test_helpers::for_tests::mm_ctx_with_iguana;
+use num_traits::ToPrimitive;
+use uuid::Uuid;
 
 fn generate_swaps_from_values(swaps_value: Vec<(MmNumber, MmNumber)>) -> Vec<SavedSwap> {
     swaps_value
@@ -29,9 +42,60 @@ fn generate_cfg_from_params(base: String, rel: String, spread: MmNumber) -> Simp
         min_base_price: None,
         min_rel_price: None,
         min_pair_price: None,
+        price_sources: None,
+        min_price_sources: None,
+        max_price_deviation: None,
+        spread_strategy: None,
+        ladder: None,
     }
 }
 
+fn generate_quotes_from_prices(prices: &[&str]) -> Vec<PriceQuote> {
+    prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| PriceQuote {
+            source: format!("source_{}", i),
+            price: MmNumber::from(*price),
+            base_usd_price: None,
+            rel_usd_price: None,
+            last_updated_timestamp: 1000 + i as u64,
+        })
+        .collect()
+}
+
+fn generate_swap_status(my_amount: &str, other_amount: &str, event_status: &str) -> SwapStatusChanged {
+    SwapStatusChanged {
+        uuid: Uuid::new_v4(),
+        my_order_uuid: Some(Uuid::new_v4()),
+        my_coin: "FIRO".to_string(),
+        other_coin: "KMD".to_string(),
+        my_amount: MmNumber::from(my_amount).to_decimal(),
+        other_amount: MmNumber::from(other_amount).to_decimal(),
+        event_status: event_status.to_string(),
+        is_error: false,
+        is_finished: false,
+    }
+}
+
+fn generate_rates(price: &str, base_price: &str, rel_price: &str) -> RateInfos {
+    RateInfos {
+        price: MmNumber::from(price),
+        base_price: MmNumber::from(base_price),
+        rel_price: MmNumber::from(rel_price),
+        ..Default::default()
+    }
+}
+
+fn generate_finished_entry(my_amount: &str, other_amount: &str, rates: Option<&RateInfos>) -> BotLedgerEntry {
+    let mut swap_infos = generate_swap_status(my_amount, other_amount, "Started");
+    let mut entry = BotLedgerEntry::new(&swap_infos, swap_infos.my_order_uuid.unwrap(), rates);
+    swap_infos.event_status = "Finished".to_string();
+    swap_infos.is_finished = true;
+    entry.apply_event(&swap_infos);
+    entry
+}
+
 mod tests {
     use super::*;
 
@@ -192,4 +256,361 @@ mod tests {
         );
         assert_eq!(calculated_price.to_decimal(), expected_price.to_decimal());
     }
+
+    #[test]
+    fn test_aggregate_quotes_median() {
+        let quotes = generate_quotes_from_prices(&["1.01", "1", "0.99", "1.02"]);
+        let settings = AggregationSettings::new(None, None);
+        let rates = aggregate_quotes("FIRO", "KMD", quotes, &settings).unwrap();
+        assert_eq!(rates.price, MmNumber::from("1.005"));
+        assert_eq!(rates.last_updated_timestamp, Some(1000));
+        assert!(rates.base_price.is_zero());
+    }
+
+    #[test]
+    fn test_aggregate_quotes_rejects_outlier() {
+        let mut quotes = generate_quotes_from_prices(&["1", "1.5", "1.02"]);
+        quotes[0].base_usd_price = Some(MmNumber::from("10"));
+        quotes[1].base_usd_price = Some(MmNumber::from("15"));
+        let settings = AggregationSettings::new(Some(2), None);
+        let rates = aggregate_quotes("FIRO", "KMD", quotes, &settings).unwrap();
+        assert_eq!(rates.price, MmNumber::from("1.01"));
+        // The USD price of the outlier is ignored as well.
+        assert_eq!(rates.base_price, MmNumber::from("10"));
+        assert_eq!(rates.last_updated_timestamp, Some(1000));
+    }
+
+    #[test]
+    fn test_aggregate_quotes_sources_disagree() {
+        let quotes = generate_quotes_from_prices(&["1", "1.2"]);
+        let settings = AggregationSettings::new(None, None);
+        let err = aggregate_quotes("FIRO", "KMD", quotes, &settings).unwrap_err();
+        assert!(matches!(err.into_inner(), PriceAggregationError::SourcesDisagree {
+            agreed: 0,
+            total: 2,
+            ..
+        }));
+
+        // Only a minority agrees on the price.
+        let quotes = generate_quotes_from_prices(&["1", "1.1", "1.2", "1.3", "1.4"]);
+        let err = aggregate_quotes("FIRO", "KMD", quotes, &settings).unwrap_err();
+        assert!(matches!(err.into_inner(), PriceAggregationError::SourcesDisagree {
+            agreed: 1,
+            total: 5,
+            ..
+        }));
+    }
+
+    #[test]
+    fn test_aggregate_quotes_not_enough_sources() {
+        let quotes = generate_quotes_from_prices(&["1", "0"]);
+        let settings = AggregationSettings::new(Some(2), None);
+        let err = aggregate_quotes("FIRO", "KMD", quotes, &settings).unwrap_err();
+        assert_eq!(err.into_inner(), PriceAggregationError::NotEnoughSources {
+            available: 1,
+            required: 2
+        });
+    }
+
+    #[test]
+    fn test_price_at_pointer() {
+        let response = serde_json::json!({
+            "number": 2.5,
+            "string": "4",
+            "zero": "0",
+            "negative": -1,
+            "nan": "NaN",
+            "infinity": "Infinity",
+            "text": "unknown",
+        });
+        assert_eq!(
+            price_at_pointer(&response, "/number", false).unwrap(),
+            MmNumber::from("2.5")
+        );
+        assert_eq!(
+            price_at_pointer(&response, "/string", true).unwrap(),
+            MmNumber::from("0.25")
+        );
+        for pointer in ["/zero", "/negative", "/nan", "/infinity", "/text", "/missing"] {
+            price_at_pointer(&response, pointer, false).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_inventory_imbalance() {
+        let price = MmNumber::from(2);
+        let target = MmNumber::from("0.5");
+        let imbalance = |base: i32, rel: i32| inventory_imbalance(&base.into(), &rel.into(), &price, &target);
+        assert_eq!(imbalance(5, 10), MmNumber::from(0));
+        assert_eq!(imbalance(15, 10), MmNumber::from("0.5"));
+        assert_eq!(imbalance(0, 10), MmNumber::from(-1));
+        assert_eq!(imbalance(10, 0), MmNumber::from(1));
+        assert_eq!(imbalance(0, 0), MmNumber::from(0));
+
+        // 3/4 of the value in base while 1/4 is desired.
+        let target = MmNumber::from("0.25");
+        assert_eq!(
+            inventory_imbalance(&15.into(), &10.into(), &price, &target),
+            MmNumber::from((2, 3))
+        );
+    }
+
+    #[test]
+    fn test_skew_spread_and_volume() {
+        let spread = MmNumber::from("1.02");
+        let spread_skew = MmNumber::from("0.5");
+        // Too much base coin - the spread is tightened.
+        assert_eq!(
+            skew_spread(&spread, &MmNumber::from("0.5"), &spread_skew),
+            MmNumber::from("1.015")
+        );
+        // No base coin at all - the spread is widened.
+        assert_eq!(
+            skew_spread(&spread, &MmNumber::from(-1), &spread_skew),
+            MmNumber::from("1.03")
+        );
+
+        let base_balance = MmNumber::from(100);
+        let volume_skew = MmNumber::from("0.4");
+        assert_eq!(
+            skew_volume(&10.into(), false, &base_balance, &MmNumber::from("-0.5"), &volume_skew),
+            (MmNumber::from(8), false)
+        );
+        assert_eq!(
+            skew_volume(
+                &MmNumber::default(),
+                true,
+                &base_balance,
+                &MmNumber::from(-1),
+                &volume_skew
+            ),
+            (MmNumber::from(60), false)
+        );
+        // The skewed volume can't exceed the balance.
+        assert_eq!(
+            skew_volume(&90.into(), false, &base_balance, &MmNumber::from("0.5"), &volume_skew),
+            (MmNumber::default(), true)
+        );
+    }
+
+    #[test]
+    fn test_price_volatility() {
+        let prices: Vec<MmNumber> = vec![100.into(), 110.into(), 99.into()];
+        let volatility = price_volatility(&prices).unwrap().to_decimal().to_f64().unwrap();
+        assert!((volatility - 0.1).abs() < 1e-9);
+
+        let prices: Vec<MmNumber> = vec![100.into(), 100.into(), 100.into()];
+        assert_eq!(price_volatility(&prices), Some(MmNumber::default()));
+
+        let prices: Vec<MmNumber> = vec![100.into(), 110.into()];
+        assert_eq!(price_volatility(&prices), None);
+
+        let mut history = PriceHistory::default();
+        for price in [1000, 100, 110, 99] {
+            history.push(price.into(), 3);
+        }
+        let volatility = history.volatility().unwrap().to_decimal().to_f64().unwrap();
+        assert!((volatility - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_volatility_spread() {
+        let spread = MmNumber::from("1.01");
+        let volatility = MmNumber::from("0.1");
+        let multiplier = MmNumber::from("0.2");
+        assert_eq!(
+            volatility_spread(&spread, &volatility, &multiplier, None),
+            MmNumber::from("1.03")
+        );
+        let max_spread = MmNumber::from("1.02");
+        assert_eq!(
+            volatility_spread(&spread, &volatility, &multiplier, Some(&max_spread)),
+            max_spread
+        );
+    }
+
+    #[test]
+    fn test_spread_strategy_deserialize_and_validate() {
+        let strategy: SpreadStrategy =
+            serde_json::from_str(r#"{"type":"Inventory","data":{"spread_skew":"0.5"}}"#).unwrap();
+        let expected = SpreadStrategy::Inventory {
+            target_base_ratio: MmNumber::from("0.5"),
+            spread_skew: MmNumber::from("0.5"),
+            volume_skew: MmNumber::default(),
+        };
+        assert_eq!(strategy, expected);
+        strategy.validate().unwrap();
+
+        let strategy: SpreadStrategy =
+            serde_json::from_str(r#"{"type":"Inventory","data":{"spread_skew":2}}"#).unwrap();
+        strategy.validate().unwrap_err();
+
+        let strategy: SpreadStrategy =
+            serde_json::from_str(r#"{"type":"Volatility","data":{"window":2,"multiplier":1}}"#).unwrap();
+        strategy.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_order_ladder_levels() {
+        let ladder = OrderLadder {
+            levels: 3,
+            spread_step: MmNumber::from("0.01"),
+            volume_multiplier: MmNumber::from(2),
+        };
+        ladder.validate().unwrap();
+        let levels = ladder.levels(&MmNumber::from(10), &MmNumber::from(70));
+        let expected = vec![
+            LadderLevel {
+                price: MmNumber::from(10),
+                volume: MmNumber::from(10),
+            },
+            LadderLevel {
+                price: MmNumber::from("10.1"),
+                volume: MmNumber::from(20),
+            },
+            LadderLevel {
+                price: MmNumber::from("10.2"),
+                volume: MmNumber::from(40),
+            },
+        ];
+        assert_eq!(levels, expected);
+    }
+
+    #[test]
+    fn test_order_ladder_deserialize_and_validate() {
+        let ladder: OrderLadder = serde_json::from_str(r#"{"levels":4,"spread_step":"0.005"}"#).unwrap();
+        ladder.validate().unwrap();
+        let levels = ladder.levels(&MmNumber::from(1), &MmNumber::from(10));
+        assert_eq!(levels.len(), 4);
+        assert!(levels.iter().all(|level| level.volume == MmNumber::from("2.5")));
+        assert_eq!(levels[3].price, MmNumber::from("1.015"));
+
+        let ladder: OrderLadder = serde_json::from_str(r#"{"levels":0,"spread_step":"0.005"}"#).unwrap();
+        ladder.validate().unwrap_err();
+        let ladder: OrderLadder =
+            serde_json::from_str(r#"{"levels":2,"spread_step":"0.005","volume_multiplier":0}"#).unwrap();
+        ladder.validate().unwrap_err();
+        let ladder: OrderLadder =
+            serde_json::from_str(r#"{"levels":2,"spread_step":"0.005","volume_multiplier":11}"#).unwrap();
+        ladder.validate().unwrap_err();
+        let ladder: OrderLadder = serde_json::from_str(r#"{"levels":21,"spread_step":"0.005"}"#).unwrap();
+        ladder.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_order_ladder_volume_bounds() {
+        let ladder = OrderLadder {
+            levels: MAX_LADDER_LEVELS,
+            spread_step: MmNumber::from("0.01"),
+            volume_multiplier: MmNumber::from(10),
+        };
+        ladder.validate().unwrap();
+        let total_volume = MmNumber::from("1.23456789");
+        let levels = ladder.levels(&MmNumber::from(2), &total_volume);
+        assert_eq!(levels.len(), MAX_LADDER_LEVELS);
+        let volume_sum = levels
+            .iter()
+            .fold(MmNumber::default(), |sum, level| &sum + &level.volume);
+        assert_eq!(volume_sum, total_volume);
+
+        // The first levels are too small to be placed.
+        let min_vol = MmNumber::from("0.0001");
+        OrderLadder::validate_levels(&levels, &min_vol, &min_vol).unwrap_err();
+        let levels = OrderLadder { levels: 3, ..ladder }.levels(&MmNumber::from(2), &total_volume);
+        OrderLadder::validate_levels(&levels, &min_vol, &min_vol).unwrap();
+        // The rel volume of the first level is 2 * 1.23456789 / 111.
+        OrderLadder::validate_levels(&levels, &min_vol, &MmNumber::from("0.023")).unwrap_err();
+    }
+
+    #[test]
+    fn test_bot_ledger_entry_status() {
+        let mut swap_infos = generate_swap_status("1", "7", "Started");
+        let mut entry = BotLedgerEntry::new(&swap_infos, swap_infos.my_order_uuid.unwrap(), None);
+        assert_eq!(entry.status, BotSwapStatus::Ongoing);
+        assert_eq!(entry.last_event, "Started");
+
+        swap_infos.event_status = "MakerPaymentWaitRefundStarted".to_string();
+        swap_infos.is_error = true;
+        entry.apply_event(&swap_infos);
+        assert_eq!(entry.status, BotSwapStatus::Failed);
+
+        // The refunded swap is still failed when it's finished.
+        swap_infos.event_status = "Finished".to_string();
+        swap_infos.is_error = false;
+        swap_infos.is_finished = true;
+        entry.apply_event(&swap_infos);
+        assert_eq!(entry.status, BotSwapStatus::Failed);
+
+        let entry = generate_finished_entry("1", "7", None);
+        assert_eq!(entry.status, BotSwapStatus::Succeeded);
+    }
+
+    #[test]
+    fn test_record_swap_in_ledger_concurrently() {
+        let ctx = mm_ctx_with_iguana(Some("test_record_swap_in_ledger_concurrently"));
+        std::fs::create_dir_all(trading_bot_ledger_dir(&ctx)).unwrap();
+        let bot_ctx = TradingBotContext::default();
+        let started = generate_swap_status("1", "7", "Started");
+        block_on(bot_ctx.bot_orders.lock()).insert(started.my_order_uuid.unwrap(), now_sec());
+        block_on(bot_ctx.record_swap_in_ledger(&ctx, &started)).unwrap();
+
+        let mut failed = started.clone();
+        failed.event_status = "MakerPaymentTransactionFailed".to_string();
+        failed.is_error = true;
+        let mut finished = started.clone();
+        finished.event_status = "Finished".to_string();
+        finished.is_finished = true;
+        // Both updates are applied whatever order they are processed in, so the error isn't overwritten.
+        let (failed_res, finished_res) = block_on(futures::future::join(
+            bot_ctx.record_swap_in_ledger(&ctx, &failed),
+            bot_ctx.record_swap_in_ledger(&ctx, &finished),
+        ));
+        failed_res.unwrap();
+        finished_res.unwrap();
+
+        let entry = block_on(MyOrdersStorage::new(ctx).load_bot_ledger_entry(started.uuid))
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.status, BotSwapStatus::Failed);
+    }
+
+    #[test]
+    fn test_calculate_pnl() {
+        let rates = generate_rates("7", "10", "1.5");
+        let mut failed = generate_finished_entry("5", "40", Some(&rates));
+        failed.status = BotSwapStatus::Failed;
+        let entries = vec![
+            // +0.5 KMD, +1.25 USD
+            generate_finished_entry("1", "7.5", Some(&rates)),
+            // -1 KMD, -0.5 USD
+            generate_finished_entry("2", "13", Some(&rates)),
+            // the price feed is unknown
+            generate_finished_entry("1", "8", None),
+            failed,
+        ];
+        let pnl = calculate_pnl(&entries);
+        assert_eq!(pnl.len(), 1);
+        let pair_pnl = &pnl[0];
+        assert_eq!(pair_pnl.pair, "FIRO/KMD");
+        assert_eq!(pair_pnl.swaps_succeeded, 3);
+        assert_eq!(pair_pnl.swaps_failed, 1);
+        assert_eq!(pair_pnl.swaps_ongoing, 0);
+        assert_eq!(pair_pnl.base_volume, BigDecimal::from(4));
+        assert_eq!(pair_pnl.rel_volume, MmNumber::from("28.5").to_decimal());
+        assert_eq!(pair_pnl.realized_pnl_rel, MmNumber::from("-0.5").to_decimal());
+        assert_eq!(pair_pnl.realized_pnl_usd, MmNumber::from("0.75").to_decimal());
+        assert_eq!(pair_pnl.swaps_without_price, 1);
+        assert_eq!(pair_pnl.swaps_without_usd_price, 1);
+    }
+
+    #[test]
+    fn test_calculate_pnl_zero_rates_are_unknown() {
+        let rates = generate_rates("0", "0", "1");
+        let entry = generate_finished_entry("1", "7", Some(&rates));
+        assert_eq!(entry.price, None);
+        assert_eq!(entry.base_usd_price, None);
+        let pnl = calculate_pnl(&[entry]);
+        assert_eq!(pnl[0].swaps_without_price, 1);
+        assert_eq!(pnl[0].realized_pnl_rel, BigDecimal::from(0));
+    }
 }
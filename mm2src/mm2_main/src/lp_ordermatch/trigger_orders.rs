@@ -0,0 +1,872 @@
+//! Stop-limit and trailing orders.
+//!
+//! A trigger order is kept locally and is not broadcasted to the network until the watched `base/rel` price
+//! crosses the trigger. Then the wrapped `setprice` (maker) or `buy`/`sell` (taker) request is issued as a regular order.
+//! The price is taken either from the price service used by the simple market maker bot or from the local orderbook.
+
+use super::my_orders_storage::{MyOrdersError, MyOrdersStorage, MyTriggerOrders};
+use super::{buy, create_maker_order, mm2_internal_pubkey_hex, orderbook_mid_price, sell, subscribe_to_orderbook_topic,
+            OrdermatchContext, SetPriceReq};
+use coins::lp_coinfind;
+use coins::lp_price::{fetch_price_tickers, TickerInfosRegistry, PRICE_ENDPOINTS};
+use common::executor::Timer;
+use common::log::{debug, error, info, LogOnError};
+use common::{new_uuid, now_sec, HttpStatusCode};
+use derive_more::Display;
+use http::StatusCode;
+use mm2_core::mm_ctx::{MmArc, MmWeak};
+use mm2_err_handle::prelude::*;
+use mm2_number::MmNumber;
+use mm2_rpc::data::legacy::{Mm2RpcResult, SellBuyRequest, SellBuyResponse};
+use serde_json as json;
+use uuid::Uuid;
+
+const TRIGGER_ORDERS_CHECK_INTERVAL: f64 = 10.;
+/// How many times the order of a fired trigger order is tried to be placed before giving up.
+const MAX_PLACE_ATTEMPTS: u32 = 5;
+/// The delay in seconds before the first retry of placing the order, it's doubled after every failed attempt.
+const PLACE_RETRY_BASE_DELAY: u64 = 30;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum TriggerCondition {
+    /// The trigger fires once the price rises to the trigger price or above.
+    PriceAbove,
+    /// The trigger fires once the price falls to the trigger price or below.
+    PriceBelow,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum Trigger {
+    /// Fires once the price crosses the fixed `stop_price`.
+    StopLimit {
+        stop_price: MmNumber,
+        condition: TriggerCondition,
+    },
+    /// Follows the price and fires once it moves back from the best price seen by `trail_percent`.
+    /// `PriceBelow` tracks the highest price (trailing stop on sell), `PriceAbove` tracks the lowest price (trailing buy).
+    Trailing {
+        trail_percent: MmNumber,
+        condition: TriggerCondition,
+        /// The highest or the lowest price seen since the order was created.
+        #[serde(default)]
+        extremum: Option<MmNumber>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+enum TriggerUpdate {
+    Fired,
+    /// The trailing extremum has moved, so the order has to be saved.
+    Moved,
+    Unchanged,
+}
+
+impl Trigger {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Trigger::StopLimit { stop_price, .. } => {
+                if *stop_price <= MmNumber::from(0) {
+                    return Err(format!("Stop price {} must be positive", stop_price));
+                }
+            },
+            Trigger::Trailing { trail_percent, .. } => {
+                if *trail_percent <= MmNumber::from(0) || *trail_percent >= MmNumber::from(100) {
+                    return Err(format!(
+                        "Trail percent {} must be greater than 0 and less than 100",
+                        trail_percent
+                    ));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// The price which fires the trigger, `None` if the trailing trigger hasn't seen any price yet.
+    pub fn trigger_price(&self) -> Option<MmNumber> {
+        match self {
+            Trigger::StopLimit { stop_price, .. } => Some(stop_price.clone()),
+            Trigger::Trailing {
+                trail_percent,
+                condition,
+                extremum,
+            } => {
+                let hundred = MmNumber::from(100);
+                let multiplier = match condition {
+                    TriggerCondition::PriceAbove => &hundred + trail_percent,
+                    TriggerCondition::PriceBelow => &hundred - trail_percent,
+                } / hundred;
+                extremum.as_ref().map(|extremum| extremum * &multiplier)
+            },
+        }
+    }
+
+    fn update(&mut self, price: &MmNumber) -> TriggerUpdate {
+        let mut update = TriggerUpdate::Unchanged;
+        if let Trigger::Trailing {
+            condition, extremum, ..
+        } = self
+        {
+            let is_new_extremum = match (condition, extremum.as_ref()) {
+                (_, None) => true,
+                (TriggerCondition::PriceAbove, Some(lowest)) => price < lowest,
+                (TriggerCondition::PriceBelow, Some(highest)) => price > highest,
+            };
+            if is_new_extremum {
+                *extremum = Some(price.clone());
+                update = TriggerUpdate::Moved;
+            }
+        }
+
+        let condition = match self {
+            Trigger::StopLimit { condition, .. } | Trigger::Trailing { condition, .. } => *condition,
+        };
+        let is_fired = match (condition, self.trigger_price()) {
+            (TriggerCondition::PriceAbove, Some(trigger_price)) => *price >= trigger_price,
+            (TriggerCondition::PriceBelow, Some(trigger_price)) => *price <= trigger_price,
+            (_, None) => false,
+        };
+        if is_fired {
+            TriggerUpdate::Fired
+        } else {
+            update
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PriceSource {
+    /// The `base/rel` rate calculated from the USD prices of the price service, see [`PRICE_ENDPOINTS`].
+    #[default]
+    PriceFeed,
+    /// The mid price between the best ask and the best bid of the local orderbook, own orders are ignored.
+    Orderbook,
+}
+
+/// The order issued when the trigger fires.
+/// The `base/rel` pair of the request is the pair whose price is watched.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum TriggeredOrderRequest {
+    /// Same params as the `setprice` RPC has.
+    Maker(SetPriceReq),
+    /// Same params as the `buy` and `sell` RPCs have, the `method` field selects the action.
+    Taker(SellBuyRequest),
+}
+
+impl TriggeredOrderRequest {
+    fn base(&self) -> &str {
+        match self {
+            TriggeredOrderRequest::Maker(req) => &req.base,
+            TriggeredOrderRequest::Taker(req) => &req.base,
+        }
+    }
+
+    fn rel(&self) -> &str {
+        match self {
+            TriggeredOrderRequest::Maker(req) => &req.rel,
+            TriggeredOrderRequest::Taker(req) => &req.rel,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum TriggerOrderState {
+    /// The trigger hasn't fired yet.
+    #[default]
+    Waiting,
+    /// The trigger has fired and the order is being placed.
+    /// If the node stops in this state, the order may have been placed already, so it isn't placed again on restart.
+    Placing { attempt: u32 },
+    /// Placing the order has failed, it's retried at `retry_at`.
+    RetryScheduled {
+        attempts: u32,
+        retry_at: u64,
+        error: String,
+    },
+    /// The order is placed, the trigger order is kept only until it's deleted from the storage.
+    Placed { order_uuid: Uuid },
+    /// The order couldn't be placed, the trigger order is kept until it's cancelled.
+    Failed { error: String },
+}
+
+impl TriggerOrderState {
+    fn failed_attempts(&self) -> u32 {
+        match self {
+            TriggerOrderState::RetryScheduled { attempts, .. } => *attempts,
+            _ => 0,
+        }
+    }
+}
+
+/// The delay before the next attempt to place the order after `failed_attempts` attempts have failed.
+fn place_retry_delay(failed_attempts: u32) -> u64 {
+    PLACE_RETRY_BASE_DELAY << failed_attempts.saturating_sub(1).min(MAX_PLACE_ATTEMPTS)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TriggerOrder {
+    pub uuid: Uuid,
+    pub created_at: u64,
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub price_source: PriceSource,
+    pub order: TriggeredOrderRequest,
+    #[serde(default)]
+    pub state: TriggerOrderState,
+}
+
+#[derive(Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum TriggerOrderRpcError {
+    #[display(fmt = "Base and rel must be different coins")]
+    BaseEqualRel,
+    #[display(fmt = "Coin {} is not found or inactive", _0)]
+    NoSuchCoin(String),
+    #[display(fmt = "Coin {} is wallet only", _0)]
+    CoinIsWalletOnly(String),
+    #[display(fmt = "Invalid request: {}", _0)]
+    InvalidRequest(String),
+    #[display(fmt = "Trigger order {} is not found", _0)]
+    NoSuchTriggerOrder(Uuid),
+    #[display(fmt = "Storage error: {}", _0)]
+    StorageError(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+impl HttpStatusCode for TriggerOrderRpcError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TriggerOrderRpcError::BaseEqualRel
+            | TriggerOrderRpcError::NoSuchCoin(_)
+            | TriggerOrderRpcError::CoinIsWalletOnly(_)
+            | TriggerOrderRpcError::InvalidRequest(_)
+            | TriggerOrderRpcError::NoSuchTriggerOrder(_) => StatusCode::BAD_REQUEST,
+            TriggerOrderRpcError::StorageError(_) | TriggerOrderRpcError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            },
+        }
+    }
+}
+
+impl From<MyOrdersError> for TriggerOrderRpcError {
+    fn from(e: MyOrdersError) -> Self { TriggerOrderRpcError::StorageError(e.to_string()) }
+}
+
+#[derive(Deserialize)]
+pub struct AddTriggerOrderRequest {
+    trigger: Trigger,
+    #[serde(default)]
+    price_source: PriceSource,
+    order: TriggeredOrderRequest,
+}
+
+#[derive(Serialize)]
+pub struct TriggerOrderForRpc {
+    #[serde(flatten)]
+    order: TriggerOrder,
+    trigger_price: Option<MmNumber>,
+}
+
+impl From<TriggerOrder> for TriggerOrderForRpc {
+    fn from(order: TriggerOrder) -> Self {
+        TriggerOrderForRpc {
+            trigger_price: order.trigger.trigger_price(),
+            order,
+        }
+    }
+}
+
+pub async fn add_trigger_order_rpc(
+    ctx: MmArc,
+    req: AddTriggerOrderRequest,
+) -> MmResult<TriggerOrderForRpc, TriggerOrderRpcError> {
+    req.trigger.validate().map_to_mm(TriggerOrderRpcError::InvalidRequest)?;
+    if let TriggeredOrderRequest::Taker(taker_req) = &req.order {
+        if taker_req.method != "buy" && taker_req.method != "sell" {
+            return MmError::err(TriggerOrderRpcError::InvalidRequest(format!(
+                "Taker order method must be 'buy' or 'sell', got '{}'",
+                taker_req.method
+            )));
+        }
+    }
+
+    let (base, rel) = (req.order.base(), req.order.rel());
+    if base == rel {
+        return MmError::err(TriggerOrderRpcError::BaseEqualRel);
+    }
+    for ticker in [base, rel] {
+        let coin = lp_coinfind(&ctx, ticker)
+            .await
+            .map_to_mm(TriggerOrderRpcError::Internal)?
+            .or_mm_err(|| TriggerOrderRpcError::NoSuchCoin(ticker.to_owned()))?;
+        if coin.wallet_only(&ctx) {
+            return MmError::err(TriggerOrderRpcError::CoinIsWalletOnly(ticker.to_owned()));
+        }
+    }
+
+    let order = TriggerOrder {
+        uuid: new_uuid(),
+        created_at: now_sec(),
+        trigger: req.trigger,
+        price_source: req.price_source,
+        order: req.order,
+        state: TriggerOrderState::Waiting,
+    };
+    if order.price_source == PriceSource::Orderbook {
+        subscribe_to_trigger_orderbook(&ctx, &order).await;
+    }
+
+    let ordermatch_ctx = OrdermatchContext::from_ctx(&ctx).map_to_mm(TriggerOrderRpcError::Internal)?;
+    MyOrdersStorage::new(ctx.clone()).save_new_trigger_order(&order).await?;
+    ordermatch_ctx
+        .my_trigger_orders
+        .lock()
+        .await
+        .insert(order.uuid, order.clone());
+    Ok(order.into())
+}
+
+#[derive(Deserialize)]
+pub struct MyTriggerOrdersRequest;
+
+#[derive(Serialize)]
+pub struct MyTriggerOrdersResponse {
+    orders: Vec<TriggerOrderForRpc>,
+}
+
+pub async fn my_trigger_orders_rpc(
+    ctx: MmArc,
+    _req: MyTriggerOrdersRequest,
+) -> MmResult<MyTriggerOrdersResponse, TriggerOrderRpcError> {
+    let ordermatch_ctx = OrdermatchContext::from_ctx(&ctx).map_to_mm(TriggerOrderRpcError::Internal)?;
+    let mut orders: Vec<_> = ordermatch_ctx
+        .my_trigger_orders
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect();
+    orders.sort_by_key(|order| order.created_at);
+    Ok(MyTriggerOrdersResponse {
+        orders: orders.into_iter().map(TriggerOrderForRpc::from).collect(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct CancelTriggerOrderRequest {
+    uuid: Uuid,
+}
+
+pub async fn cancel_trigger_order_rpc(
+    ctx: MmArc,
+    req: CancelTriggerOrderRequest,
+) -> MmResult<TriggerOrderForRpc, TriggerOrderRpcError> {
+    let ordermatch_ctx = OrdermatchContext::from_ctx(&ctx).map_to_mm(TriggerOrderRpcError::Internal)?;
+    let order = {
+        let mut trigger_orders = ordermatch_ctx.my_trigger_orders.lock().await;
+        if let Some(TriggerOrderState::Placing { .. }) = trigger_orders.get(&req.uuid).map(|order| &order.state) {
+            return MmError::err(TriggerOrderRpcError::InvalidRequest(format!(
+                "The order of the trigger order {} is being placed",
+                req.uuid
+            )));
+        }
+        trigger_orders
+            .remove(&req.uuid)
+            .or_mm_err(|| TriggerOrderRpcError::NoSuchTriggerOrder(req.uuid))?
+    };
+    if let Err(e) = MyOrdersStorage::new(ctx.clone()).delete_trigger_order(req.uuid).await {
+        // Keep the order active as it would be loaded from the storage on the next start anyway.
+        ordermatch_ctx.my_trigger_orders.lock().await.insert(order.uuid, order);
+        return Err(e.map(TriggerOrderRpcError::from));
+    }
+    Ok(order.into())
+}
+
+/// Loads the saved trigger orders and returns the tickers they use.
+pub(super) async fn trigger_orders_kick_start(ctx: &MmArc) -> Result<Vec<String>, String> {
+    let ordermatch_ctx = try_s!(OrdermatchContext::from_ctx(ctx));
+    let storage = MyOrdersStorage::new(ctx.clone());
+    let saved_orders = try_s!(storage.load_trigger_orders().await);
+
+    let mut coins = Vec::with_capacity(saved_orders.len() * 2);
+    let mut trigger_orders = ordermatch_ctx.my_trigger_orders.lock().await;
+    for mut order in saved_orders {
+        if let TriggerOrderState::Placing { .. } = order.state {
+            // It's unknown whether the order was placed before the node stopped, so it mustn't be placed again.
+            order.state = TriggerOrderState::Failed {
+                error: "The node stopped while the order was being placed, check the active orders".to_owned(),
+            };
+            storage
+                .update_trigger_order(&order)
+                .await
+                .error_log_with_msg("!update_trigger_order");
+        }
+        coins.push(order.order.base().to_owned());
+        coins.push(order.order.rel().to_owned());
+        trigger_orders.insert(order.uuid, order);
+    }
+    Ok(coins)
+}
+
+pub async fn trigger_orders_loop(ctx_weak: MmWeak) {
+    let mut price_urls: Vec<String> = PRICE_ENDPOINTS.iter().map(|url| url.to_string()).collect();
+    loop {
+        {
+            let ctx = match MmArc::from_weak(&ctx_weak) {
+                Some(ctx) => ctx,
+                None => return,
+            };
+            if ctx.is_stopping() {
+                break;
+            }
+
+            process_trigger_orders(&ctx, &mut price_urls).await;
+        }
+        Timer::sleep(TRIGGER_ORDERS_CHECK_INTERVAL).await;
+    }
+}
+
+async fn process_trigger_orders(ctx: &MmArc, price_urls: &mut [String]) {
+    let ordermatch_ctx = match OrdermatchContext::from_ctx(ctx) {
+        Ok(ordermatch_ctx) => ordermatch_ctx,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        },
+    };
+
+    // Do not hold the trigger orders lock while the prices are being fetched and the orders are being placed.
+    let all_orders: Vec<_> = ordermatch_ctx
+        .my_trigger_orders
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect();
+    let now = now_sec();
+    let mut orders = Vec::with_capacity(all_orders.len());
+    for order in all_orders {
+        match order.state {
+            TriggerOrderState::Waiting => orders.push(order),
+            TriggerOrderState::RetryScheduled { retry_at, .. } if retry_at <= now => {
+                fire_trigger_order(ctx, &ordermatch_ctx, order).await
+            },
+            TriggerOrderState::Placed { .. } => remove_fired_trigger_order(ctx, &ordermatch_ctx, order.uuid).await,
+            // Not due yet, being placed or failed.
+            _ => (),
+        }
+    }
+    if orders.is_empty() {
+        return;
+    }
+    let price_registry = if orders.iter().any(|order| order.price_source == PriceSource::PriceFeed) {
+        fetch_price_tickers(price_urls).await.error_log_passthrough().ok()
+    } else {
+        None
+    };
+
+    let mut prices = Vec::with_capacity(orders.len());
+    for order in orders.iter() {
+        match trigger_order_price(ctx, &ordermatch_ctx, price_registry.as_ref(), order).await {
+            Some(price) => prices.push((order.uuid, price)),
+            None => debug!(
+                "No {:?} price available for the trigger order {}",
+                order.price_source, order.uuid
+            ),
+        }
+    }
+
+    let TriggerPricesApplied { moved, fired } = apply_trigger_prices(&ordermatch_ctx, prices).await;
+    let storage = MyOrdersStorage::new(ctx.clone());
+    for order in moved {
+        storage
+            .update_trigger_order(&order)
+            .await
+            .error_log_with_msg("!update_trigger_order");
+    }
+    for (order, price) in fired {
+        info!("Trigger order {} fired at price {}", order.uuid, price.to_decimal());
+        fire_trigger_order(ctx, &ordermatch_ctx, order).await;
+    }
+}
+
+#[derive(Debug, Default)]
+struct TriggerPricesApplied {
+    /// The orders whose trailing extremum has moved, so they have to be saved.
+    moved: Vec<TriggerOrder>,
+    /// The fired orders with the prices that fired them.
+    fired: Vec<(TriggerOrder, MmNumber)>,
+}
+
+/// Updates the triggers of the orders with the given prices.
+/// The orders that have been cancelled or fired since the prices were requested are skipped.
+async fn apply_trigger_prices(
+    ordermatch_ctx: &OrdermatchContext,
+    prices: Vec<(Uuid, MmNumber)>,
+) -> TriggerPricesApplied {
+    let mut applied = TriggerPricesApplied::default();
+    let mut trigger_orders = ordermatch_ctx.my_trigger_orders.lock().await;
+    for (uuid, price) in prices {
+        let order = match trigger_orders.get_mut(&uuid) {
+            Some(order) if order.state == TriggerOrderState::Waiting => order,
+            _ => continue,
+        };
+        match order.trigger.update(&price) {
+            TriggerUpdate::Fired => applied.fired.push((order.clone(), price)),
+            TriggerUpdate::Moved => applied.moved.push(order.clone()),
+            TriggerUpdate::Unchanged => (),
+        }
+    }
+    applied
+}
+
+/// Places the order of the fired trigger order.
+/// The `Placing` state is saved first, so the order isn't placed twice if the node stops before the trigger order
+/// is deleted. A failed placement is retried with an increasing delay up to [`MAX_PLACE_ATTEMPTS`] times.
+async fn fire_trigger_order(ctx: &MmArc, ordermatch_ctx: &OrdermatchContext, order: TriggerOrder) {
+    let uuid = order.uuid;
+    let attempt = order.state.failed_attempts() + 1;
+    match set_trigger_order_state(ctx, ordermatch_ctx, uuid, TriggerOrderState::Placing { attempt }).await {
+        Ok(true) => (),
+        // The trigger order has been cancelled.
+        Ok(false) => return,
+        Err(e) => {
+            error!(
+                "Error {} on saving the fired trigger order {}, retrying on the next check",
+                e, uuid
+            );
+            if let Some(order_in_memory) = ordermatch_ctx.my_trigger_orders.lock().await.get_mut(&uuid) {
+                order_in_memory.state = order.state;
+            }
+            return;
+        },
+    }
+
+    let state = match place_triggered_order(ctx, order.order).await {
+        Ok(order_uuid) => {
+            info!("The order {} of the trigger order {} is placed", order_uuid, uuid);
+            TriggerOrderState::Placed { order_uuid }
+        },
+        Err(e) if attempt < MAX_PLACE_ATTEMPTS => {
+            let retry_at = now_sec() + place_retry_delay(attempt);
+            error!(
+                "Error placing the order of the fired trigger order {} (attempt {}): {}, retrying at {}",
+                uuid, attempt, e, retry_at
+            );
+            TriggerOrderState::RetryScheduled {
+                attempts: attempt,
+                retry_at,
+                error: e,
+            }
+        },
+        Err(e) => {
+            error!(
+                "Error placing the order of the fired trigger order {} (attempt {}): {}, giving up",
+                uuid, attempt, e
+            );
+            TriggerOrderState::Failed { error: e }
+        },
+    };
+    let is_placed = matches!(state, TriggerOrderState::Placed { .. });
+    // If the state isn't saved, the order is kept as `Placing` in the storage and isn't placed again on restart.
+    if let Err(e) = set_trigger_order_state(ctx, ordermatch_ctx, uuid, state).await {
+        error!("Error {} on saving the state of the trigger order {}", e, uuid);
+    }
+    if is_placed {
+        remove_fired_trigger_order(ctx, ordermatch_ctx, uuid).await;
+    }
+}
+
+/// Sets the state of the trigger order in memory and saves the order.
+/// Returns `false` if the order has been cancelled.
+async fn set_trigger_order_state(
+    ctx: &MmArc,
+    ordermatch_ctx: &OrdermatchContext,
+    uuid: Uuid,
+    state: TriggerOrderState,
+) -> MmResult<bool, MyOrdersError> {
+    // The lock is held while saving, so the order can't be cancelled and then saved back here.
+    let mut trigger_orders = ordermatch_ctx.my_trigger_orders.lock().await;
+    let order = match trigger_orders.get_mut(&uuid) {
+        Some(order) => order,
+        None => return Ok(false),
+    };
+    order.state = state;
+    MyOrdersStorage::new(ctx.clone()).update_trigger_order(order).await?;
+    Ok(true)
+}
+
+/// Deletes the placed trigger order.
+/// It's kept in memory if the deletion fails, so the next check retries it.
+async fn remove_fired_trigger_order(ctx: &MmArc, ordermatch_ctx: &OrdermatchContext, uuid: Uuid) {
+    if let Err(e) = MyOrdersStorage::new(ctx.clone()).delete_trigger_order(uuid).await {
+        error!("Error {} on deleting the placed trigger order {}", e, uuid);
+        return;
+    }
+    ordermatch_ctx.my_trigger_orders.lock().await.remove(&uuid);
+}
+
+async fn trigger_order_price(
+    ctx: &MmArc,
+    ordermatch_ctx: &OrdermatchContext,
+    price_registry: Option<&TickerInfosRegistry>,
+    order: &TriggerOrder,
+) -> Option<MmNumber> {
+    let (base, rel) = (order.order.base(), order.order.rel());
+    match order.price_source {
+        PriceSource::PriceFeed => price_registry?.get_cex_rates(base, rel).map(|rates| rates.price),
+        PriceSource::Orderbook => {
+            subscribe_to_trigger_orderbook(ctx, order).await;
+            let my_pubsecp = mm2_internal_pubkey_hex(ctx, String::from).ok()?;
+            let (base, rel) = ordermatch_ctx.orderbook_pair_bypass(&(base.to_owned(), rel.to_owned()));
+            orderbook_mid_price(ordermatch_ctx, &my_pubsecp, base, rel)
+        },
+    }
+}
+
+async fn subscribe_to_trigger_orderbook(ctx: &MmArc, order: &TriggerOrder) {
+    let ordermatch_ctx = match OrdermatchContext::from_ctx(ctx) {
+        Ok(ordermatch_ctx) => ordermatch_ctx,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        },
+    };
+    let base = ordermatch_ctx.orderbook_ticker_bypass(order.order.base());
+    let rel = ordermatch_ctx.orderbook_ticker_bypass(order.order.rel());
+    subscribe_to_orderbook_topic(ctx, &base, &rel, true)
+        .await
+        .error_log_with_msg("!subscribe_to_orderbook_topic");
+}
+
+async fn place_triggered_order(ctx: &MmArc, request: TriggeredOrderRequest) -> Result<Uuid, String> {
+    match request {
+        TriggeredOrderRequest::Maker(req) => create_maker_order(ctx, req).await.map(|order| order.uuid),
+        TriggeredOrderRequest::Taker(req) => {
+            let method = req.method.clone();
+            let req = try_s!(json::to_value(req));
+            let response = match method.as_str() {
+                "buy" => buy(ctx.clone(), req).await?,
+                "sell" => sell(ctx.clone(), req).await?,
+                _ => return ERR!("Unexpected taker order method '{}'", method),
+            };
+            let response: Mm2RpcResult<SellBuyResponse> = try_s!(json::from_slice(response.body()));
+            Ok(response.result.request.uuid)
+        },
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::lp_ordermatch::my_trigger_orders_dir;
+    use common::block_on;
+    use mm2_test_helpers::for_tests::mm_ctx_with_iguana;
+
+    fn num(n: &'static str) -> MmNumber { MmNumber::from(n) }
+
+    fn stop_limit_order(stop_price: &'static str, condition: TriggerCondition) -> TriggerOrder {
+        let set_price_req = json::from_value(json!({
+            "base": "BASE",
+            "rel": "REL",
+            "price": "1",
+            "volume": "1",
+        }))
+        .unwrap();
+        TriggerOrder {
+            uuid: new_uuid(),
+            created_at: now_sec(),
+            trigger: Trigger::StopLimit {
+                stop_price: num(stop_price),
+                condition,
+            },
+            price_source: PriceSource::PriceFeed,
+            order: TriggeredOrderRequest::Maker(set_price_req),
+            state: TriggerOrderState::Waiting,
+        }
+    }
+
+    #[test]
+    fn test_stop_limit_trigger() {
+        let mut trigger = Trigger::StopLimit {
+            stop_price: num("10"),
+            condition: TriggerCondition::PriceBelow,
+        };
+        assert_eq!(trigger.update(&num("11")), TriggerUpdate::Unchanged);
+        assert_eq!(trigger.update(&num("10")), TriggerUpdate::Fired);
+
+        let mut trigger = Trigger::StopLimit {
+            stop_price: num("10"),
+            condition: TriggerCondition::PriceAbove,
+        };
+        assert_eq!(trigger.update(&num("9.99")), TriggerUpdate::Unchanged);
+        assert_eq!(trigger.update(&num("10.01")), TriggerUpdate::Fired);
+    }
+
+    #[test]
+    fn test_trailing_trigger_below() {
+        let mut trigger = Trigger::Trailing {
+            trail_percent: num("10"),
+            condition: TriggerCondition::PriceBelow,
+            extremum: None,
+        };
+        assert_eq!(trigger.update(&num("100")), TriggerUpdate::Moved);
+        assert_eq!(trigger.trigger_price(), Some(num("90")));
+        assert_eq!(trigger.update(&num("95")), TriggerUpdate::Unchanged);
+        assert_eq!(trigger.update(&num("120")), TriggerUpdate::Moved);
+        assert_eq!(trigger.trigger_price(), Some(num("108")));
+        assert_eq!(trigger.update(&num("109")), TriggerUpdate::Unchanged);
+        assert_eq!(trigger.update(&num("108")), TriggerUpdate::Fired);
+    }
+
+    #[test]
+    fn test_trailing_trigger_above() {
+        let mut trigger = Trigger::Trailing {
+            trail_percent: num("5"),
+            condition: TriggerCondition::PriceAbove,
+            extremum: Some(num("100")),
+        };
+        assert_eq!(trigger.update(&num("80")), TriggerUpdate::Moved);
+        assert_eq!(trigger.trigger_price(), Some(num("84")));
+        assert_eq!(trigger.update(&num("83")), TriggerUpdate::Unchanged);
+        assert_eq!(trigger.update(&num("85")), TriggerUpdate::Fired);
+    }
+
+    #[test]
+    fn test_trigger_validation() {
+        let trigger = Trigger::Trailing {
+            trail_percent: num("100"),
+            condition: TriggerCondition::PriceBelow,
+            extremum: None,
+        };
+        assert!(trigger.validate().is_err());
+
+        let trigger = Trigger::StopLimit {
+            stop_price: num("0"),
+            condition: TriggerCondition::PriceBelow,
+        };
+        assert!(trigger.validate().is_err());
+    }
+
+    #[test]
+    fn test_apply_trigger_prices() {
+        let ctx = mm_ctx_with_iguana(None);
+        let ordermatch_ctx = OrdermatchContext::from_ctx(&ctx).unwrap();
+
+        let fired = stop_limit_order("10", TriggerCondition::PriceBelow);
+        let unchanged = stop_limit_order("10", TriggerCondition::PriceAbove);
+        let trailing = TriggerOrder {
+            trigger: Trigger::Trailing {
+                trail_percent: num("10"),
+                condition: TriggerCondition::PriceBelow,
+                extremum: None,
+            },
+            ..stop_limit_order("1", TriggerCondition::PriceBelow)
+        };
+        let cancelled = stop_limit_order("10", TriggerCondition::PriceBelow);
+        {
+            let mut trigger_orders = block_on(ordermatch_ctx.my_trigger_orders.lock());
+            for order in [&fired, &unchanged, &trailing] {
+                trigger_orders.insert(order.uuid, order.clone());
+            }
+        }
+
+        let prices = vec![
+            (fired.uuid, num("9")),
+            (unchanged.uuid, num("9")),
+            (trailing.uuid, num("100")),
+            (cancelled.uuid, num("9")),
+        ];
+        let applied = block_on(apply_trigger_prices(&ordermatch_ctx, prices));
+
+        let fired_uuids: Vec<_> = applied.fired.iter().map(|(order, _)| order.uuid).collect();
+        assert_eq!(fired_uuids, vec![fired.uuid]);
+        assert_eq!(applied.fired[0].1, num("9"));
+        let moved_uuids: Vec<_> = applied.moved.iter().map(|order| order.uuid).collect();
+        assert_eq!(moved_uuids, vec![trailing.uuid]);
+
+        // The trailing extremum is updated in memory too.
+        let trigger_orders = block_on(ordermatch_ctx.my_trigger_orders.lock());
+        assert_eq!(trigger_orders[&trailing.uuid].trigger.trigger_price(), Some(num("90")));
+        assert!(trigger_orders.contains_key(&fired.uuid));
+    }
+
+    #[test]
+    fn test_fire_trigger_order_keeps_order_on_error() {
+        let ctx = mm_ctx_with_iguana(None);
+        let ordermatch_ctx = OrdermatchContext::from_ctx(&ctx).unwrap();
+        let storage = MyOrdersStorage::new(ctx.clone());
+        std::fs::create_dir_all(my_trigger_orders_dir(&ctx)).unwrap();
+
+        // The coins aren't enabled, so the order can't be placed.
+        let order = stop_limit_order("10", TriggerCondition::PriceBelow);
+        block_on(storage.save_new_trigger_order(&order)).unwrap();
+        block_on(ordermatch_ctx.my_trigger_orders.lock()).insert(order.uuid, order.clone());
+
+        block_on(fire_trigger_order(&ctx, &ordermatch_ctx, order.clone()));
+        let state = block_on(ordermatch_ctx.my_trigger_orders.lock())[&order.uuid]
+            .state
+            .clone();
+        assert!(matches!(state, TriggerOrderState::RetryScheduled { attempts: 1, .. }));
+        let saved = block_on(storage.load_trigger_orders()).unwrap();
+        assert_eq!(
+            saved.iter().find(|saved| saved.uuid == order.uuid).unwrap().state,
+            state
+        );
+
+        // The retries are limited.
+        for _ in 1..MAX_PLACE_ATTEMPTS {
+            let order = block_on(ordermatch_ctx.my_trigger_orders.lock())[&order.uuid].clone();
+            block_on(fire_trigger_order(&ctx, &ordermatch_ctx, order));
+        }
+        let state = block_on(ordermatch_ctx.my_trigger_orders.lock())[&order.uuid]
+            .state
+            .clone();
+        assert!(matches!(state, TriggerOrderState::Failed { .. }));
+
+        // The deletion is idempotent.
+        block_on(remove_fired_trigger_order(&ctx, &ordermatch_ctx, order.uuid));
+        assert!(!block_on(ordermatch_ctx.my_trigger_orders.lock()).contains_key(&order.uuid));
+        let saved = block_on(storage.load_trigger_orders()).unwrap();
+        assert!(!saved.iter().any(|saved| saved.uuid == order.uuid));
+        block_on(storage.delete_trigger_order(order.uuid)).unwrap();
+    }
+
+    #[test]
+    fn test_place_retry_delay() {
+        assert_eq!(place_retry_delay(1), PLACE_RETRY_BASE_DELAY);
+        assert_eq!(place_retry_delay(2), 2 * PLACE_RETRY_BASE_DELAY);
+        assert_eq!(place_retry_delay(4), 8 * PLACE_RETRY_BASE_DELAY);
+    }
+
+    #[test]
+    fn test_kick_start_doesnt_place_interrupted_order() {
+        let ctx = mm_ctx_with_iguana(Some("test_kick_start_doesnt_place_interrupted_order"));
+        let storage = MyOrdersStorage::new(ctx.clone());
+        std::fs::remove_dir_all(my_trigger_orders_dir(&ctx)).ok();
+        std::fs::create_dir_all(my_trigger_orders_dir(&ctx)).unwrap();
+
+        let order = TriggerOrder {
+            state: TriggerOrderState::Placing { attempt: 1 },
+            ..stop_limit_order("10", TriggerCondition::PriceBelow)
+        };
+        block_on(storage.save_new_trigger_order(&order)).unwrap();
+        block_on(trigger_orders_kick_start(&ctx)).unwrap();
+
+        let ordermatch_ctx = OrdermatchContext::from_ctx(&ctx).unwrap();
+        let state = block_on(ordermatch_ctx.my_trigger_orders.lock())[&order.uuid]
+            .state
+            .clone();
+        assert!(matches!(state, TriggerOrderState::Failed { .. }));
+        let saved = block_on(storage.load_trigger_orders()).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].state, state);
+
+        // The order isn't fired by the price anymore.
+        let applied = block_on(apply_trigger_prices(&ordermatch_ctx, vec![(order.uuid, num("9"))]));
+        assert!(applied.fired.is_empty());
+    }
+}
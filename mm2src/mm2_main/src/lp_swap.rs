@@ -70,6 +70,7 @@ use common::{bits256, calc_total_pages,
              log::{error, info},
              HttpStatusCode, PagingOptions, StatusCode};
 use derive_more::Display;
+use futures::lock::Mutex as AsyncMutex;
 use http::Response;
 use mm2_core::mm_ctx::{from_ctx, MmArc};
 use mm2_err_handle::prelude::*;
@@ -93,6 +94,7 @@ use uuid::Uuid;
 #[cfg(any(feature = "custom-swap-locktime", test, feature = "run-docker-tests"))]
 use std::sync::atomic::{AtomicU64, Ordering};
 
+mod banned_pubkeys_storage;
 mod check_balance;
 mod maker_swap;
 pub mod maker_swap_v2;
@@ -108,6 +110,8 @@ mod swap_v2_pb;
 pub(crate) mod swap_events;
 mod swap_v2_common;
 pub(crate) mod swap_v2_rpcs;
+pub(crate) mod swap_v2_watcher;
+mod swap_v2_watcher_storage;
 pub(crate) mod swap_watcher;
 pub(crate) mod taker_restart;
 pub(crate) mod taker_swap;
@@ -126,14 +130,21 @@ pub use maker_swap::{calc_max_maker_vol, check_balance_for_maker_swap, get_max_m
                      MakerSwapStatusChanged, MakerTradePreimage, RunMakerSwapInput, MAKER_PAYMENT_SENT_LOG};
 pub use max_maker_vol_rpc::max_maker_vol;
 use my_swaps_storage::{MySwapsOps, MySwapsStorage};
-use pubkey_banning::BanReason;
-pub use pubkey_banning::{ban_pubkey_rpc, is_pubkey_banned, list_banned_pubkeys_rpc, unban_pubkeys_rpc};
+use pubkey_banning::BannedPubkey;
+pub use pubkey_banning::{ban_pubkey_rpc, ban_pubkey_v2_rpc, expired_bans_sweeper_loop, is_pubkey_banned,
+                         list_banned_pubkeys_rpc, list_banned_pubkeys_v2_rpc, load_banned_pubkeys, unban_pubkeys_rpc,
+                         unban_pubkeys_v2_rpc, BanReason, BanRecord, UnbanReason};
 pub use recreate_swap_data::recreate_swap_data;
 pub use saved_swap::{SavedSwap, SavedSwapError, SavedSwapIo, SavedSwapResult};
+pub use swap_events::SwapStatusChanged;
+pub(crate) use swap_v2_common::match_swap_v2_coins;
 use swap_v2_common::{get_unfinished_swaps_uuids, swap_kickstart_handler_for_maker, swap_kickstart_handler_for_taker,
                      ActiveSwapV2Info};
 use swap_v2_pb::*;
 use swap_v2_rpcs::{get_maker_swap_data_for_rpc, get_swap_type, get_taker_swap_data_for_rpc};
+use swap_v2_watcher::swap_v2_watchers_kick_start;
+pub use swap_v2_watcher::{SwapV2WatcherData, SWAP_V2_WATCHER_MAKER_PAYMENT_SPEND_SENT_LOG,
+                          SWAP_V2_WATCHER_REFUND_SENT_LOG};
 pub use swap_watcher::{process_watcher_msg, watcher_topic, TakerSwapWatcherData, MAKER_PAYMENT_SPEND_FOUND_LOG,
                        MAKER_PAYMENT_SPEND_SENT_LOG, TAKER_PAYMENT_REFUND_SENT_LOG, TAKER_SWAP_ENTRY_TIMEOUT_SEC,
                        WATCHER_PREFIX};
@@ -509,7 +520,7 @@ pub trait AtomicSwap: Send + Sync {
     fn unique_swap_data(&self) -> Vec<u8>;
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "type", content = "event")]
 pub enum SwapEvent {
     Maker(MakerSwapEvent),
@@ -532,7 +543,9 @@ struct LockedAmountInfo {
 struct SwapsContext {
     running_swaps: Mutex<HashMap<Uuid, Arc<dyn AtomicSwap>>>,
     active_swaps_v2_infos: Mutex<HashMap<Uuid, ActiveSwapV2Info>>,
-    banned_pubkeys: Mutex<HashMap<H256Json, BanReason>>,
+    banned_pubkeys: Mutex<HashMap<H256Json, BannedPubkey>>,
+    /// Held while a ban is being persisted, so the storage and `banned_pubkeys` are updated in the same order.
+    banned_pubkeys_update_lock: AsyncMutex<()>,
     swap_msgs: Mutex<HashMap<Uuid, SwapMsgStore>>,
     swap_v2_msgs: Mutex<HashMap<Uuid, SwapV2MsgStore>>,
     taker_swap_watchers: PaMutex<TimedMap<Vec<u8>, ()>>,
@@ -549,6 +562,7 @@ impl SwapsContext {
                 running_swaps: Mutex::new(HashMap::new()),
                 active_swaps_v2_infos: Mutex::new(HashMap::new()),
                 banned_pubkeys: Mutex::new(HashMap::new()),
+                banned_pubkeys_update_lock: AsyncMutex::new(()),
                 swap_msgs: Mutex::new(HashMap::new()),
                 swap_v2_msgs: Mutex::new(HashMap::new()),
                 taker_swap_watchers: PaMutex::new(TimedMap::new_with_map_kind(MapKind::FxHashMap)),
@@ -1326,6 +1340,8 @@ pub async fn swap_kick_starts(ctx: MmArc) -> Result<HashSet<String>, String> {
     #[cfg(target_arch = "wasm32")]
     try_s!(migrate_swaps_data(&ctx).await);
 
+    try_s!(load_banned_pubkeys(&ctx).await);
+
     let mut coins = HashSet::new();
     let legacy_unfinished_uuids = try_s!(get_unfinished_swaps_uuids(ctx.clone(), LEGACY_SWAP_TYPE).await);
     for uuid in legacy_unfinished_uuids {
@@ -1403,6 +1419,10 @@ pub async fn swap_kick_starts(ctx: MmArc) -> Result<HashSet<String>, String> {
             swap_kickstart_handler_for_taker(ctx.clone(), taker_swap_repr, taker_swap_storage.clone(), taker_uuid);
         ctx.spawner().spawn(fut);
     }
+
+    if ctx.is_watcher() {
+        coins.extend(try_s!(swap_v2_watchers_kick_start(ctx.clone()).await));
+    }
     Ok(coins)
 }
 
@@ -1595,6 +1615,8 @@ pub fn detect_secret_hash_algo(maker_coin: &MmCoinEnum, taker_coin: &MmCoinEnum)
         },
         // If taker is lightning coin the SHA256 of the secret will be sent as part of the maker signed invoice
         (_, MmCoinEnum::Tendermint(_) | MmCoinEnum::TendermintToken(_)) => SecretHashAlgo::SHA256,
+        #[cfg(feature = "enable-sia")]
+        (MmCoinEnum::SiaCoin(_), _) | (_, MmCoinEnum::SiaCoin(_)) => SecretHashAlgo::SHA256,
         (_, _) => SecretHashAlgo::DHASH160,
     }
 }
@@ -1605,6 +1627,8 @@ pub fn detect_secret_hash_algo(maker_coin: &MmCoinEnum, taker_coin: &MmCoinEnum)
     match (maker_coin, taker_coin) {
         (MmCoinEnum::Tendermint(_) | MmCoinEnum::TendermintToken(_), _) => SecretHashAlgo::SHA256,
         (_, MmCoinEnum::Tendermint(_) | MmCoinEnum::TendermintToken(_)) => SecretHashAlgo::SHA256,
+        #[cfg(feature = "enable-sia")]
+        (MmCoinEnum::SiaCoin(_), _) | (_, MmCoinEnum::SiaCoin(_)) => SecretHashAlgo::SHA256,
         (_, _) => SecretHashAlgo::DHASH160,
     }
 }
@@ -2033,6 +2057,45 @@ mod lp_swap_tests {
         assert_eq!(deserialized, v3);
     }
 
+    #[test]
+    fn check_swap_v2_watcher_msg_serde() {
+        use swap_v2_watcher::{MakerPaymentSpendGuard, RefundGuard, SwapV2WatcherGuard};
+        use swap_watcher::SwapWatcherMsg;
+
+        let refund = SwapV2WatcherData {
+            uuid: new_uuid(),
+            guard: SwapV2WatcherGuard::TakerFundingRefund(RefundGuard {
+                coin: "RICK".into(),
+                payment_tx: vec![1; 200].into(),
+                search_from_block: 100,
+                time_lock: 1000,
+                refund_preimage: vec![2; 200].into(),
+            }),
+        };
+        let spend = SwapV2WatcherData {
+            uuid: new_uuid(),
+            guard: SwapV2WatcherGuard::MakerPaymentSpend(MakerPaymentSpendGuard {
+                maker_coin: "MORTY".into(),
+                maker_payment: vec![1; 200].into(),
+                maker_coin_start_block: 100,
+                maker_payment_time_lock: 2000,
+                spend_preimage: vec![2; 200].into(),
+                taker_coin: "RICK".into(),
+                taker_payment: vec![3; 200].into(),
+                taker_coin_start_block: 200,
+                maker_secret_hash: vec![4; 20].into(),
+            }),
+        };
+
+        for data in [refund, spend] {
+            let serialized = rmp_serde::to_vec(&SwapWatcherMsg::TakerSwapV2WatcherMsg(data.clone())).unwrap();
+            match rmp_serde::from_slice(serialized.as_slice()).unwrap() {
+                SwapWatcherMsg::TakerSwapV2WatcherMsg(deserialized) => assert_eq!(deserialized, data),
+                msg => panic!("Unexpected message {:?}", msg),
+            }
+        }
+    }
+
     #[test]
     fn check_payment_data_serde() {
         const MSG_DATA_INSTRUCTIONS: [u8; 300] = [1; 300];
@@ -2128,6 +2191,7 @@ mod lp_swap_tests {
             priv_key_policy: PrivKeyActivationPolicy::ContextPrivKey,
             check_utxo_maturity: None,
             path_to_address: HDPathAccountToAddressId::default(),
+            multisig: None,
         }
     }
 
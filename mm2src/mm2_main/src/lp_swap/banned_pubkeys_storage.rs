@@ -0,0 +1,211 @@
+use super::pubkey_banning::{BanRecord, UnbanReason};
+use async_trait::async_trait;
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use rpc::v1::types::H256 as H256Json;
+
+pub type BannedPubkeysResult<T> = Result<T, MmError<BannedPubkeysStorageError>>;
+
+#[derive(Debug, Display)]
+pub enum BannedPubkeysStorageError {
+    #[display(fmt = "Error serializing ban reason: {}", _0)]
+    ErrorSerializingItem(String),
+    #[display(fmt = "Error deserializing ban reason: {}", _0)]
+    ErrorDeserializingItem(String),
+    #[display(fmt = "Unknown SQL error: {}", _0)]
+    UnknownSqlError(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    InternalError(String),
+}
+
+#[async_trait]
+pub trait BannedPubkeysOps {
+    /// Saves a new ban record. The previous ban of the same pubkey (if any) must be closed by [`BannedPubkeysOps::set_unbanned`] beforehand.
+    async fn save_ban(&self, record: &BanRecord) -> BannedPubkeysResult<()>;
+
+    /// Closes the active ban record of the given pubkey keeping it in the audit trail.
+    async fn set_unbanned(
+        &self,
+        pubkey: &H256Json,
+        unbanned_at: u64,
+        unban_reason: UnbanReason,
+    ) -> BannedPubkeysResult<()>;
+
+    /// Loads ban records ordered from the most recent to the oldest.
+    /// If `only_active` is false, the records of the expired and lifted bans are returned as well.
+    async fn load_ban_records(&self, only_active: bool) -> BannedPubkeysResult<Vec<BanRecord>>;
+}
+
+pub struct BannedPubkeysStorage {
+    ctx: MmArc,
+}
+
+impl BannedPubkeysStorage {
+    pub fn new(ctx: MmArc) -> BannedPubkeysStorage { BannedPubkeysStorage { ctx } }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native_impl {
+    use super::*;
+    use crate::database::banned_pubkeys::{insert_ban, select_ban_records, update_unbanned};
+    use db_common::sqlite::rusqlite::Error as SqlError;
+
+    impl From<SqlError> for BannedPubkeysStorageError {
+        fn from(e: SqlError) -> Self {
+            match e {
+                SqlError::ToSqlConversionFailure(e) => BannedPubkeysStorageError::ErrorSerializingItem(e.to_string()),
+                SqlError::FromSqlConversionFailure(_, _, e) => {
+                    BannedPubkeysStorageError::ErrorDeserializingItem(e.to_string())
+                },
+                e => BannedPubkeysStorageError::UnknownSqlError(e.to_string()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl BannedPubkeysOps for BannedPubkeysStorage {
+        async fn save_ban(&self, record: &BanRecord) -> BannedPubkeysResult<()> { Ok(insert_ban(&self.ctx, record)?) }
+
+        async fn set_unbanned(
+            &self,
+            pubkey: &H256Json,
+            unbanned_at: u64,
+            unban_reason: UnbanReason,
+        ) -> BannedPubkeysResult<()> {
+            Ok(update_unbanned(
+                &self.ctx,
+                &pubkey.to_string(),
+                unbanned_at,
+                unban_reason,
+            )?)
+        }
+
+        async fn load_ban_records(&self, only_active: bool) -> BannedPubkeysResult<Vec<BanRecord>> {
+            Ok(select_ban_records(&self.ctx, only_active)?)
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm_impl {
+    use super::*;
+    use crate::lp_swap::swap_wasm_db::{BannedPubkeysTable, DbTransactionError, InitDbError};
+    use crate::lp_swap::SwapsContext;
+    use std::str::FromStr;
+
+    impl From<DbTransactionError> for BannedPubkeysStorageError {
+        fn from(e: DbTransactionError) -> Self {
+            let stringified_error = e.to_string();
+            match e {
+                DbTransactionError::ErrorSerializingItem(_) => {
+                    BannedPubkeysStorageError::ErrorSerializingItem(stringified_error)
+                },
+                DbTransactionError::ErrorDeserializingItem(_) => {
+                    BannedPubkeysStorageError::ErrorDeserializingItem(stringified_error)
+                },
+                _ => BannedPubkeysStorageError::InternalError(stringified_error),
+            }
+        }
+    }
+
+    impl From<InitDbError> for BannedPubkeysStorageError {
+        fn from(e: InitDbError) -> Self { BannedPubkeysStorageError::InternalError(e.to_string()) }
+    }
+
+    impl TryFrom<&BanRecord> for BannedPubkeysTable {
+        type Error = MmError<BannedPubkeysStorageError>;
+
+        fn try_from(record: &BanRecord) -> Result<Self, Self::Error> {
+            let reason = serde_json::to_value(&record.reason)
+                .map_to_mm(|e| BannedPubkeysStorageError::ErrorSerializingItem(e.to_string()))?;
+            Ok(BannedPubkeysTable {
+                pubkey: record.pubkey.to_string(),
+                reason_type: record.reason.reason_type().to_string(),
+                reason,
+                banned_at: record.banned_at,
+                expires_at: record.expires_at,
+                unbanned_at: record.unbanned_at,
+                unban_reason: record.unban_reason.map(|reason| reason.to_string()),
+            })
+        }
+    }
+
+    impl TryFrom<BannedPubkeysTable> for BanRecord {
+        type Error = MmError<BannedPubkeysStorageError>;
+
+        fn try_from(item: BannedPubkeysTable) -> Result<Self, Self::Error> {
+            let pubkey = H256Json::from_str(&item.pubkey)
+                .map_to_mm(|e| BannedPubkeysStorageError::ErrorDeserializingItem(e.to_string()))?;
+            let reason = serde_json::from_value(item.reason)
+                .map_to_mm(|e| BannedPubkeysStorageError::ErrorDeserializingItem(e.to_string()))?;
+            let unban_reason = item
+                .unban_reason
+                .map(|reason| UnbanReason::from_str(&reason))
+                .transpose()
+                .map_to_mm(BannedPubkeysStorageError::ErrorDeserializingItem)?;
+            Ok(BanRecord {
+                pubkey,
+                reason,
+                banned_at: item.banned_at,
+                expires_at: item.expires_at,
+                unbanned_at: item.unbanned_at,
+                unban_reason,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl BannedPubkeysOps for BannedPubkeysStorage {
+        async fn save_ban(&self, record: &BanRecord) -> BannedPubkeysResult<()> {
+            let swap_ctx = SwapsContext::from_ctx(&self.ctx).map_to_mm(BannedPubkeysStorageError::InternalError)?;
+            let db = swap_ctx.swap_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<BannedPubkeysTable>().await?;
+
+            let item = BannedPubkeysTable::try_from(record)?;
+            table.add_item(&item).await?;
+            Ok(())
+        }
+
+        async fn set_unbanned(
+            &self,
+            pubkey: &H256Json,
+            unbanned_at: u64,
+            unban_reason: UnbanReason,
+        ) -> BannedPubkeysResult<()> {
+            let swap_ctx = SwapsContext::from_ctx(&self.ctx).map_to_mm(BannedPubkeysStorageError::InternalError)?;
+            let db = swap_ctx.swap_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<BannedPubkeysTable>().await?;
+
+            let items = table.get_items("pubkey", pubkey.to_string()).await?;
+            for (item_id, mut item) in items {
+                if item.unbanned_at.is_some() {
+                    continue;
+                }
+                item.unbanned_at = Some(unbanned_at);
+                item.unban_reason = Some(unban_reason.to_string());
+                table.replace_item(item_id, &item).await?;
+            }
+            Ok(())
+        }
+
+        async fn load_ban_records(&self, only_active: bool) -> BannedPubkeysResult<Vec<BanRecord>> {
+            let swap_ctx = SwapsContext::from_ctx(&self.ctx).map_to_mm(BannedPubkeysStorageError::InternalError)?;
+            let db = swap_ctx.swap_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<BannedPubkeysTable>().await?;
+
+            let mut items = table.get_all_items().await?;
+            // Emulate `ORDER BY banned_at DESC, id DESC` of the SQLite implementation.
+            items.sort_by(|(id_a, a), (id_b, b)| (b.banned_at, id_b).cmp(&(a.banned_at, id_a)));
+            items
+                .into_iter()
+                .map(|(_item_id, item)| item)
+                .filter(|item| !only_active || item.unbanned_at.is_none())
+                .map(BanRecord::try_from)
+                .collect()
+        }
+    }
+}
@@ -13,7 +13,7 @@ use super::{broadcast_my_swap_status, broadcast_p2p_tx_msg, broadcast_swap_msg_e
 use crate::lp_dispatcher::{DispatcherContext, LpEvents};
 use crate::lp_network::subscribe_to_topic;
 use crate::lp_ordermatch::MakerOrderBuilder;
-use crate::lp_swap::swap_events::{SwapStatusEvent, SwapStatusStreamer};
+use crate::lp_swap::swap_events::{SwapStatusChanged, SwapStatusEvent, SwapStatusStreamer};
 use crate::lp_swap::swap_v2_common::mark_swap_as_finished;
 use crate::lp_swap::{broadcast_swap_message, taker_payment_spend_duration, MAX_STARTED_AT_DIFF};
 use coins::lp_price::fetch_swap_coins_price;
@@ -1852,6 +1852,22 @@ impl MakerSwapStatusChanged {
     }
 }
 
+impl SwapStatusChanged {
+    fn from_maker_swap(maker_swap: &MakerSwap, saved_swap: &MakerSavedEvent) -> Self {
+        SwapStatusChanged {
+            uuid: maker_swap.uuid,
+            my_order_uuid: maker_swap.my_order_uuid,
+            my_coin: maker_swap.maker_coin.ticker().to_string(),
+            other_coin: maker_swap.taker_coin.ticker().to_string(),
+            my_amount: maker_swap.maker_amount.clone(),
+            other_amount: maker_swap.taker_amount.clone(),
+            event_status: saved_swap.event.status_str(),
+            is_error: saved_swap.event.is_error(),
+            is_finished: matches!(saved_swap.event, MakerSwapEvent::Finished),
+        }
+    }
+}
+
 #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct MakerSavedSwap {
     pub uuid: Uuid,
@@ -2179,6 +2195,10 @@ pub async fn run_maker_swap(swap: RunMakerSwapInput, ctx: MmArc) {
                     dispatcher
                         .dispatch_async(ctx.clone(), LpEvents::MakerSwapStatusChanged(event_to_send))
                         .await;
+                    let event_to_send = SwapStatusChanged::from_maker_swap(&running_swap, &to_save);
+                    dispatcher
+                        .dispatch_async(ctx.clone(), LpEvents::SwapStatusChanged(event_to_send))
+                        .await;
                     drop(dispatcher);
                     // Send a notification to the swap status streamer about a new event.
                     ctx.event_stream_manager
@@ -2197,6 +2217,7 @@ pub async fn run_maker_swap(swap: RunMakerSwapInput, ctx: MmArc) {
                             &running_swap.uuid,
                             event.clone().into(),
                         )
+                        .await;
                     }
 
                     if event.is_error() {
@@ -1,7 +1,9 @@
-use super::swap_events::{SwapStatusEvent, SwapStatusStreamer};
+use super::swap_events::{SwapStatusChanged, SwapStatusEvent, SwapStatusStreamer};
 use super::swap_v2_common::*;
+use super::swap_v2_watcher::{broadcast_swap_v2_watcher_msg, RefundGuard, SwapV2WatcherGuard};
 use super::{swap_v2_topic, LockedAmount, LockedAmountInfo, SavedTradeFee, SwapsContext, NEGOTIATE_SEND_INTERVAL,
             NEGOTIATION_TIMEOUT_SEC};
+use crate::lp_dispatcher::{dispatch_lp_event, LpEvents};
 use crate::lp_swap::maker_swap::MakerSwapPreparedParams;
 use crate::lp_swap::swap_lock::SwapLock;
 use crate::lp_swap::{broadcast_swap_v2_msg_every, check_balance_for_maker_swap, recv_swap_v2_msg,
@@ -12,10 +14,11 @@ use bitcrypto::{dhash160, sha256};
 use coins::hd_wallet::AddrToString;
 use coins::{CanRefundHtlc, ConfirmPaymentInput, DexFee, FeeApproxStage, FundingTxSpend, GenTakerFundingSpendArgs,
             GenTakerPaymentSpendArgs, MakerCoinSwapOpsV2, MmCoin, ParseCoinAssocTypes, RefundMakerPaymentSecretArgs,
-            RefundMakerPaymentTimelockArgs, SearchForFundingSpendErr, SendMakerPaymentArgs, SwapTxTypeWithSecretHash,
-            TakerCoinSwapOpsV2, ToBytes, TradePreimageValue, Transaction, TxPreimageWithSig, ValidateTakerFundingArgs};
+            RefundMakerPaymentTimelockArgs, RefundPaymentArgs, SearchForFundingSpendErr, SendMakerPaymentArgs,
+            SwapTxTypeWithSecretHash, TakerCoinSwapOpsV2, ToBytes, TradePreimageValue, Transaction, TxPreimageWithSig,
+            ValidateTakerFundingArgs};
 use common::executor::abortable_queue::AbortableQueue;
-use common::executor::{AbortableSystem, Timer};
+use common::executor::{AbortableSystem, SpawnFuture, Timer};
 use common::log::{debug, error, info, warn};
 use common::{now_sec, Future01CompatExt};
 use crypto::privkey::SerializableSecp256k1Keypair;
@@ -141,6 +144,42 @@ pub enum MakerSwapEvent {
     Completed,
 }
 
+impl MakerSwapEvent {
+    fn status_str(&self) -> &'static str {
+        match self {
+            MakerSwapEvent::Initialized { .. } => "Initialized",
+            MakerSwapEvent::WaitingForTakerFunding { .. } => "WaitingForTakerFunding",
+            MakerSwapEvent::TakerFundingReceived { .. } => "TakerFundingReceived",
+            MakerSwapEvent::MakerPaymentSentFundingSpendGenerated { .. } => "MakerPaymentSentFundingSpendGenerated",
+            MakerSwapEvent::MakerPaymentRefundRequired { .. } => "MakerPaymentRefundRequired",
+            MakerSwapEvent::MakerPaymentRefunded { .. } => "MakerPaymentRefunded",
+            MakerSwapEvent::TakerPaymentReceived { .. } => "TakerPaymentReceived",
+            MakerSwapEvent::TakerPaymentReceivedAndPreimageValidationSkipped { .. } => {
+                "TakerPaymentReceivedAndPreimageValidationSkipped"
+            },
+            MakerSwapEvent::TakerPaymentSpent { .. } => "TakerPaymentSpent",
+            MakerSwapEvent::Aborted { .. } => "Aborted",
+            MakerSwapEvent::Completed => "Completed",
+        }
+    }
+
+    fn is_error(&self) -> bool {
+        matches!(
+            self,
+            MakerSwapEvent::MakerPaymentRefundRequired { .. }
+                | MakerSwapEvent::MakerPaymentRefunded { .. }
+                | MakerSwapEvent::Aborted { .. }
+        )
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            MakerSwapEvent::MakerPaymentRefunded { .. } | MakerSwapEvent::Aborted { .. } | MakerSwapEvent::Completed
+        )
+    }
+}
+
 /// Storage for maker swaps.
 #[derive(Clone)]
 pub struct MakerSwapStorage {
@@ -407,6 +446,8 @@ pub struct MakerSwapStateMachine<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCo
     pub require_taker_payment_spend_confirm: bool,
     /// Swap protocol version
     pub swap_version: u8,
+    /// The uuid of the maker order the swap was started from. It isn't persisted, so it's None for restored swaps.
+    pub my_order_uuid: Option<Uuid>,
 }
 
 impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOpsV2>
@@ -456,6 +497,57 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
             )
         }
     }
+
+    /// Whether the HTLCs of this swap can be guarded by watchers.
+    fn use_watchers(&self) -> bool {
+        !self.ctx.disable_watchers_globally()
+            && self.maker_coin.is_supported_by_watchers_v2()
+            && self.taker_coin.is_supported_by_watchers_v2()
+    }
+
+    /// Signs the timelock refund of maker payment and sends it to watchers.
+    /// Errors are only logged as the watchers are an additional safety measure.
+    async fn send_maker_payment_refund_to_watchers(
+        &self,
+        maker_payment: &MakerCoin::Tx,
+        maker_coin_start_block: u64,
+        negotiation_data: &NegotiationData<MakerCoin, TakerCoin>,
+    ) {
+        let payment_tx_bytes = maker_payment.tx_hex();
+        let unique_data = self.unique_data();
+        let args = RefundPaymentArgs {
+            payment_tx: &payment_tx_bytes,
+            time_lock: self.maker_payment_locktime(),
+            other_pubkey: &negotiation_data.maker_coin_htlc_pub_from_taker.to_bytes(),
+            tx_type_with_secret_hash: SwapTxTypeWithSecretHash::MakerPaymentV2 {
+                maker_secret_hash: &self.secret_hash(),
+                taker_secret_hash: &negotiation_data.taker_secret_hash,
+            },
+            swap_contract_address: &negotiation_data.maker_coin_swap_contract.clone().map(Into::into),
+            swap_unique_data: &unique_data,
+            watcher_reward: false,
+        };
+        let refund_preimage = match self.maker_coin.sign_refund_for_watcher_v2(args).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!(
+                    "Failed to sign maker payment refund for watchers during swap {}: {}",
+                    self.uuid,
+                    e.get_plain_text_format()
+                );
+                return;
+            },
+        };
+
+        let guard = SwapV2WatcherGuard::MakerPaymentRefund(RefundGuard {
+            coin: self.maker_coin.ticker().to_owned(),
+            payment_tx: payment_tx_bytes.into(),
+            search_from_block: maker_coin_start_block,
+            time_lock: self.maker_payment_locktime(),
+            refund_preimage: refund_preimage.tx_hex().into(),
+        });
+        broadcast_swap_v2_watcher_msg(&self.ctx, self.uuid, guard, false, &self.p2p_keypair);
+    }
 }
 
 #[async_trait]
@@ -715,6 +807,7 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
             taker_p2p_pubkey: repr.taker_p2p_pub.into(),
             require_taker_payment_spend_confirm: true,
             swap_version: repr.swap_version,
+            my_order_uuid: None,
         };
 
         Ok((RestoredMachine::new(machine), current_state))
@@ -789,6 +882,21 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
             | MakerSwapEvent::Aborted { .. }
             | MakerSwapEvent::Completed => (),
         }
+        let status_changed = SwapStatusChanged {
+            uuid: self.uuid,
+            my_order_uuid: self.my_order_uuid,
+            my_coin: self.maker_coin.ticker().to_string(),
+            other_coin: self.taker_coin.ticker().to_string(),
+            my_amount: self.maker_volume.to_decimal(),
+            other_amount: self.taker_volume.to_decimal(),
+            event_status: event.status_str().to_string(),
+            is_error: event.is_error(),
+            is_finished: event.is_finished(),
+        };
+        self.ctx.spawner().spawn(dispatch_lp_event(
+            self.ctx.clone(),
+            LpEvents::SwapStatusChanged(status_changed),
+        ));
         // Send a notification to the swap status streamer about a new event.
         self.ctx
             .event_stream_manager
@@ -1348,6 +1456,16 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
     type StateMachine = MakerSwapStateMachine<MakerCoin, TakerCoin>;
 
     async fn on_changed(self: Box<Self>, state_machine: &mut Self::StateMachine) -> StateResult<Self::StateMachine> {
+        if state_machine.use_watchers() {
+            state_machine
+                .send_maker_payment_refund_to_watchers(
+                    &self.maker_payment,
+                    self.maker_coin_start_block,
+                    &self.negotiation_data,
+                )
+                .await;
+        }
+
         let maker_payment_info = MakerPaymentInfo {
             tx_bytes: self.maker_payment.tx_hex(),
             next_step_instructions: None,
@@ -1,13 +1,24 @@
+use super::banned_pubkeys_storage::{BannedPubkeysOps, BannedPubkeysStorage, BannedPubkeysStorageError};
 use super::{SwapEvent, SwapsContext};
 use chain::hash::H256;
+use common::executor::Timer;
+use common::log::{error, info};
+use common::{calc_total_pages, now_sec, one, ten, HttpStatusCode, StatusCode};
+use derive_more::Display;
 use http::Response;
-use mm2_core::mm_ctx::MmArc;
+use mm2_core::mm_ctx::{MmArc, MmWeak};
+use mm2_err_handle::prelude::*;
 use rpc::v1::types::H256 as H256Json;
 use serde_json::{self as json, Value as Json};
-use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
 use uuid::Uuid;
 
-#[derive(Serialize)]
+/// How often [`expired_bans_sweeper_loop`] lifts the bans whose TTL has passed.
+const EXPIRED_BANS_SWEEP_INTERVAL: f64 = 60.;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "type")]
 #[allow(clippy::large_enum_variant)]
 pub enum BanReason {
@@ -20,25 +31,299 @@ pub enum BanReason {
     },
 }
 
-pub fn ban_pubkey_on_failed_swap(ctx: &MmArc, pubkey: H256, swap_uuid: &Uuid, event: SwapEvent) {
-    let ctx = SwapsContext::from_ctx(ctx).unwrap();
-    let mut banned = ctx.banned_pubkeys.lock().unwrap();
-    banned.insert(pubkey.into(), BanReason::FailedSwap {
+impl BanReason {
+    pub fn reason_type(&self) -> BanReasonType {
+        match self {
+            BanReason::Manual { .. } => BanReasonType::Manual,
+            BanReason::FailedSwap { .. } => BanReasonType::FailedSwap,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, PartialEq, Serialize)]
+pub enum BanReasonType {
+    Manual,
+    FailedSwap,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, PartialEq, Serialize)]
+pub enum UnbanReason {
+    /// The ban was lifted by the `unban_pubkeys` RPC.
+    Manual,
+    /// The TTL of the ban has passed.
+    Expired,
+    /// The pubkey was banned again, so the previous ban is replaced with the new one.
+    Superseded,
+}
+
+impl FromStr for UnbanReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Manual" => Ok(UnbanReason::Manual),
+            "Expired" => Ok(UnbanReason::Expired),
+            "Superseded" => Ok(UnbanReason::Superseded),
+            _ => Err(format!("Unknown unban reason: {}", s)),
+        }
+    }
+}
+
+/// TTLs of the bans in seconds per [`BanReasonType`] read from the `banned_pubkeys_ttl` MM2 config field, e.g.
+/// `"banned_pubkeys_ttl": {"failed_swap": 604800}`.
+/// A ban without TTL lasts until it's lifted manually.
+#[derive(Debug, Default, Deserialize)]
+struct BanTtlConfig {
+    #[serde(default)]
+    manual: Option<u64>,
+    #[serde(default)]
+    failed_swap: Option<u64>,
+}
+
+impl BanTtlConfig {
+    fn from_ctx(ctx: &MmArc) -> BanTtlConfig {
+        let ttl_conf = &ctx.conf["banned_pubkeys_ttl"];
+        if ttl_conf.is_null() {
+            return BanTtlConfig::default();
+        }
+        json::from_value(ttl_conf.clone()).unwrap_or_else(|e| {
+            error!(
+                "Error parsing 'banned_pubkeys_ttl' config field: {}, bans won't expire",
+                e
+            );
+            BanTtlConfig::default()
+        })
+    }
+
+    fn ttl(&self, reason_type: BanReasonType) -> Option<u64> {
+        match reason_type {
+            BanReasonType::Manual => self.manual,
+            BanReasonType::FailedSwap => self.failed_swap,
+        }
+    }
+}
+
+/// An active ban kept in memory to check the incoming orders and swap requests quickly.
+#[derive(Clone, Debug, Serialize)]
+pub struct BannedPubkey {
+    #[serde(flatten)]
+    pub reason: BanReason,
+    pub banned_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl BannedPubkey {
+    fn is_expired(&self, now: u64) -> bool { self.expires_at.map_or(false, |expires_at| expires_at <= now) }
+}
+
+/// A ban as it's persisted in the storage.
+/// The records are never deleted, the lifted and expired bans get `unbanned_at` and `unban_reason` set instead.
+#[derive(Clone, Debug, Serialize)]
+pub struct BanRecord {
+    pub pubkey: H256Json,
+    pub reason: BanReason,
+    pub banned_at: u64,
+    pub expires_at: Option<u64>,
+    pub unbanned_at: Option<u64>,
+    pub unban_reason: Option<UnbanReason>,
+}
+
+impl BanRecord {
+    fn new(pubkey: H256Json, banned: &BannedPubkey) -> BanRecord {
+        BanRecord {
+            pubkey,
+            reason: banned.reason.clone(),
+            banned_at: banned.banned_at,
+            expires_at: banned.expires_at,
+            unbanned_at: None,
+            unban_reason: None,
+        }
+    }
+}
+
+/// Persists the ban and then bans the pubkey in memory.
+/// If the pubkey is banned already, the previous ban is replaced if `replace_active` is true,
+/// otherwise `None` is returned and nothing is changed.
+async fn ban_pubkey(
+    ctx: &MmArc,
+    pubkey: H256Json,
+    reason: BanReason,
+    ttl: Option<u64>,
+    replace_active: bool,
+) -> Result<Option<BannedPubkey>, MmError<BannedPubkeysStorageError>> {
+    let swap_ctx = SwapsContext::from_ctx(ctx).map_to_mm(BannedPubkeysStorageError::InternalError)?;
+    // Serializes the ban updates, so the check below and the storage writes can't interleave with another update.
+    let _bans_update_guard = swap_ctx.banned_pubkeys_update_lock.lock().await;
+
+    let banned_at = now_sec();
+    let previous = swap_ctx.banned_pubkeys.lock().unwrap().get(&pubkey).cloned();
+    if let Some(ref previous) = previous {
+        if !replace_active && !previous.is_expired(banned_at) {
+            return Ok(None);
+        }
+    }
+
+    let banned = BannedPubkey {
+        reason,
+        banned_at,
+        expires_at: ttl.map(|ttl| banned_at + ttl),
+    };
+    let storage = BannedPubkeysStorage::new(ctx.clone());
+    if let Some(ref previous) = previous {
+        // An expired ban that hasn't been swept yet is closed as expired, not as replaced by the new one.
+        let (unbanned_at, unban_reason) = match previous.expires_at {
+            Some(expires_at) if previous.is_expired(banned_at) => (expires_at, UnbanReason::Expired),
+            _ => (banned_at, UnbanReason::Superseded),
+        };
+        storage.set_unbanned(&pubkey, unbanned_at, unban_reason).await?;
+    }
+    if let Err(e) = storage.save_ban(&BanRecord::new(pubkey.clone(), &banned)).await {
+        if previous.is_some() {
+            // The previous ban is closed in the storage already, so it mustn't stay active in memory.
+            swap_ctx.banned_pubkeys.lock().unwrap().remove(&pubkey);
+        }
+        return Err(e);
+    }
+
+    swap_ctx.banned_pubkeys.lock().unwrap().insert(pubkey, banned.clone());
+    Ok(Some(banned))
+}
+
+/// Closes the ban records of the given pubkeys in the storage and removes them from the in-memory ban list.
+/// A pubkey is removed from memory only after its record is closed, so they stay in sync if the storage fails.
+async fn unban_pubkeys(
+    ctx: &MmArc,
+    pubkeys: Vec<H256Json>,
+    unban_reason: UnbanReason,
+) -> Result<HashMap<H256Json, BannedPubkey>, MmError<BannedPubkeysStorageError>> {
+    let swap_ctx = SwapsContext::from_ctx(ctx).map_to_mm(BannedPubkeysStorageError::InternalError)?;
+    let _bans_update_guard = swap_ctx.banned_pubkeys_update_lock.lock().await;
+
+    let storage = BannedPubkeysStorage::new(ctx.clone());
+    let unbanned_at = now_sec();
+    let mut unbanned = HashMap::new();
+    for pubkey in pubkeys {
+        if !swap_ctx.banned_pubkeys.lock().unwrap().contains_key(&pubkey) {
+            continue;
+        }
+        storage.set_unbanned(&pubkey, unbanned_at, unban_reason).await?;
+        if let Some(banned) = swap_ctx.banned_pubkeys.lock().unwrap().remove(&pubkey) {
+            unbanned.insert(pubkey, banned);
+        }
+    }
+    Ok(unbanned)
+}
+
+fn all_banned_pubkeys(ctx: &MmArc) -> Result<Vec<H256Json>, String> {
+    let swap_ctx = try_s!(SwapsContext::from_ctx(ctx));
+    let banned_pubs = try_s!(swap_ctx.banned_pubkeys.lock());
+    Ok(banned_pubs.keys().cloned().collect())
+}
+
+/// Returns the active bans skipping those that have expired but haven't been swept yet.
+fn active_bans(ctx: &MmArc) -> Result<HashMap<H256Json, BannedPubkey>, String> {
+    let swap_ctx = try_s!(SwapsContext::from_ctx(ctx));
+    let banned_pubs = try_s!(swap_ctx.banned_pubkeys.lock());
+    let now = now_sec();
+    Ok(banned_pubs
+        .iter()
+        .filter(|(_, banned)| !banned.is_expired(now))
+        .map(|(pubkey, banned)| (pubkey.clone(), banned.clone()))
+        .collect())
+}
+
+pub async fn ban_pubkey_on_failed_swap(ctx: &MmArc, pubkey: H256, swap_uuid: &Uuid, event: SwapEvent) {
+    let reason = BanReason::FailedSwap {
         caused_by_swap: *swap_uuid,
         caused_by_event: event,
-    });
+    };
+    let ttl = BanTtlConfig::from_ctx(ctx).ttl(BanReasonType::FailedSwap);
+    if let Err(e) = ban_pubkey(ctx, pubkey.into(), reason, ttl, true).await {
+        error!("Error {} on banning the counterparty of the swap {}", e, swap_uuid);
+    }
 }
 
 pub fn is_pubkey_banned(ctx: &MmArc, pubkey: &H256Json) -> bool {
     let ctx = SwapsContext::from_ctx(ctx).unwrap();
     let banned = ctx.banned_pubkeys.lock().unwrap();
-    banned.contains_key(pubkey)
+    banned.get(pubkey).map_or(false, |banned| !banned.is_expired(now_sec()))
+}
+
+/// Restores the active bans from the storage. Should be called once on the node start.
+pub async fn load_banned_pubkeys(ctx: &MmArc) -> Result<(), MmError<BannedPubkeysStorageError>> {
+    let storage = BannedPubkeysStorage::new(ctx.clone());
+    let records = storage.load_ban_records(true).await?;
+    let now = now_sec();
+
+    let mut expired = Vec::new();
+    let mut active = HashMap::with_capacity(records.len());
+    for record in records {
+        let banned = BannedPubkey {
+            reason: record.reason,
+            banned_at: record.banned_at,
+            expires_at: record.expires_at,
+        };
+        if banned.is_expired(now) {
+            expired.push((record.pubkey, banned));
+            continue;
+        }
+        active.insert(record.pubkey, banned);
+    }
+
+    for (pubkey, banned) in expired {
+        let expired_at = banned.expires_at.unwrap_or(now);
+        storage.set_unbanned(&pubkey, expired_at, UnbanReason::Expired).await?;
+    }
+
+    info!("Loaded {} banned pubkeys", active.len());
+    let swap_ctx = SwapsContext::from_ctx(ctx).map_to_mm(BannedPubkeysStorageError::InternalError)?;
+    swap_ctx.banned_pubkeys.lock().unwrap().extend(active);
+    Ok(())
+}
+
+/// Periodically lifts the bans whose TTL has passed.
+pub async fn expired_bans_sweeper_loop(ctx_weak: MmWeak) {
+    loop {
+        {
+            let ctx = match MmArc::from_weak(&ctx_weak) {
+                Some(ctx) => ctx,
+                None => return,
+            };
+            if ctx.is_stopping() {
+                break;
+            }
+
+            let swap_ctx = SwapsContext::from_ctx(&ctx).unwrap();
+            let _bans_update_guard = swap_ctx.banned_pubkeys_update_lock.lock().await;
+            let now = now_sec();
+            let expired: Vec<_> = swap_ctx
+                .banned_pubkeys
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, banned)| banned.is_expired(now))
+                .map(|(pubkey, banned)| (pubkey.clone(), banned.clone()))
+                .collect();
+
+            let storage = BannedPubkeysStorage::new(ctx.clone());
+            for (pubkey, banned) in expired {
+                let expired_at = banned.expires_at.unwrap_or(now);
+                // Keep the ban in memory if it can't be closed in the storage, the next sweep will retry.
+                if let Err(e) = storage.set_unbanned(&pubkey, expired_at, UnbanReason::Expired).await {
+                    error!("Error {} on saving the expired ban of pubkey {}", e, pubkey);
+                    continue;
+                }
+                info!("The ban of pubkey {} has expired", pubkey);
+                swap_ctx.banned_pubkeys.lock().unwrap().remove(&pubkey);
+            }
+        }
+        Timer::sleep(EXPIRED_BANS_SWEEP_INTERVAL).await;
+    }
 }
 
 pub async fn list_banned_pubkeys_rpc(ctx: MmArc) -> Result<Response<Vec<u8>>, String> {
-    let ctx = try_s!(SwapsContext::from_ctx(&ctx));
     let res = try_s!(json::to_vec(&json!({
-        "result": *try_s!(ctx.banned_pubkeys.lock()),
+        "result": try_s!(active_bans(&ctx)),
     })));
     Ok(try_s!(Response::builder().body(res)))
 }
@@ -51,19 +336,15 @@ struct BanPubkeysReq {
 
 pub async fn ban_pubkey_rpc(ctx: MmArc, req: Json) -> Result<Response<Vec<u8>>, String> {
     let req: BanPubkeysReq = try_s!(json::from_value(req));
-    let ctx = try_s!(SwapsContext::from_ctx(&ctx));
-    let mut banned_pubs = try_s!(ctx.banned_pubkeys.lock());
-
-    match banned_pubs.entry(req.pubkey) {
-        Entry::Occupied(_) => ERR!("Pubkey is banned already"),
-        Entry::Vacant(entry) => {
-            entry.insert(BanReason::Manual { reason: req.reason });
-            let res = try_s!(json::to_vec(&json!({
-                "result": "success",
-            })));
-            Ok(try_s!(Response::builder().body(res)))
-        },
+    let ttl = BanTtlConfig::from_ctx(&ctx).ttl(BanReasonType::Manual);
+    let reason = BanReason::Manual { reason: req.reason };
+    if try_s!(ban_pubkey(&ctx, req.pubkey, reason, ttl, false).await).is_none() {
+        return ERR!("Pubkey is banned already");
     }
+    let res = try_s!(json::to_vec(&json!({
+        "result": "success",
+    })));
+    Ok(try_s!(Response::builder().body(res)))
 }
 
 #[derive(Deserialize)]
@@ -75,31 +356,383 @@ enum UnbanPubkeysReq {
 
 pub async fn unban_pubkeys_rpc(ctx: MmArc, req: Json) -> Result<Response<Vec<u8>>, String> {
     let req: UnbanPubkeysReq = try_s!(json::from_value(req["unban_by"].clone()));
-    let ctx = try_s!(SwapsContext::from_ctx(&ctx));
-    let mut banned_pubs = try_s!(ctx.banned_pubkeys.lock());
-    let mut unbanned = HashMap::new();
-    let mut were_not_banned = vec![];
-    match req {
-        UnbanPubkeysReq::All => {
-            unbanned = banned_pubs.drain().collect();
-        },
+    let (to_unban, were_not_banned) = match req {
+        UnbanPubkeysReq::All => (try_s!(all_banned_pubkeys(&ctx)), vec![]),
         UnbanPubkeysReq::Few(pubkeys) => {
-            for pubkey in pubkeys {
-                match banned_pubs.remove(&pubkey) {
-                    Some(removed) => {
-                        unbanned.insert(pubkey, removed);
-                    },
-                    None => were_not_banned.push(pubkey),
-                }
-            }
+            let banned = try_s!(all_banned_pubkeys(&ctx));
+            pubkeys.into_iter().partition(|pubkey| banned.contains(pubkey))
         },
-    }
+    };
+    let unbanned = try_s!(unban_pubkeys(&ctx, to_unban, UnbanReason::Manual).await);
     let res = try_s!(json::to_vec(&json!({
         "result": {
-            "still_banned": *banned_pubs,
+            "still_banned": try_s!(active_bans(&ctx)),
             "unbanned": unbanned,
             "were_not_banned": were_not_banned,
         },
     })));
     Ok(try_s!(Response::builder().body(res)))
 }
+
+#[derive(Debug, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum BannedPubkeysRpcError {
+    #[display(fmt = "Pubkey {} is banned already", _0)]
+    AlreadyBanned(H256Json),
+    #[display(fmt = "'from_timestamp' must not be greater than 'to_timestamp'")]
+    InvalidTimestampRange,
+    #[display(fmt = "Storage error: {}", _0)]
+    StorageError(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(String),
+}
+
+impl HttpStatusCode for BannedPubkeysRpcError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            BannedPubkeysRpcError::AlreadyBanned(_) | BannedPubkeysRpcError::InvalidTimestampRange => {
+                StatusCode::BAD_REQUEST
+            },
+            BannedPubkeysRpcError::StorageError(_) | BannedPubkeysRpcError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            },
+        }
+    }
+}
+
+impl From<BannedPubkeysStorageError> for BannedPubkeysRpcError {
+    fn from(e: BannedPubkeysStorageError) -> Self {
+        match e {
+            BannedPubkeysStorageError::InternalError(internal) => BannedPubkeysRpcError::Internal(internal),
+            storage => BannedPubkeysRpcError::StorageError(storage.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BannedPubkeysFilter {
+    /// Return the bans of this reason only.
+    pub reason_type: Option<BanReasonType>,
+    /// Return the bans that were set at or after this timestamp.
+    pub from_timestamp: Option<u64>,
+    /// Return the bans that were set at or before this timestamp.
+    pub to_timestamp: Option<u64>,
+    /// Include the expired and lifted bans, so the whole audit trail is returned.
+    #[serde(default)]
+    pub include_unbanned: bool,
+}
+
+impl BannedPubkeysFilter {
+    fn matches(&self, record: &BanRecord) -> bool {
+        if let Some(reason_type) = self.reason_type {
+            if record.reason.reason_type() != reason_type {
+                return false;
+            }
+        }
+        let from_timestamp = self.from_timestamp.unwrap_or_default();
+        let to_timestamp = self.to_timestamp.unwrap_or(u64::MAX);
+        from_timestamp <= record.banned_at && record.banned_at <= to_timestamp
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListBannedPubkeysRequest {
+    #[serde(flatten)]
+    filter: BannedPubkeysFilter,
+    #[serde(default = "ten")]
+    limit: usize,
+    #[serde(default = "one")]
+    page_number: NonZeroUsize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListBannedPubkeysResponse {
+    bans: Vec<BanRecord>,
+    skipped: usize,
+    limit: usize,
+    total: usize,
+    page_number: NonZeroUsize,
+    total_pages: usize,
+}
+
+/// Selects the requested page of the records matching the filter.
+/// The records are expected to be ordered from the most recent to the oldest.
+fn paginate_ban_records(
+    records: Vec<BanRecord>,
+    filter: &BannedPubkeysFilter,
+    limit: usize,
+    page_number: NonZeroUsize,
+) -> ListBannedPubkeysResponse {
+    let now = now_sec();
+    let matching: Vec<_> = records
+        .into_iter()
+        .filter(|record| filter.include_unbanned || record.expires_at.map_or(true, |expires_at| expires_at > now))
+        .filter(|record| filter.matches(record))
+        .collect();
+    let total = matching.len();
+    let skipped = (page_number.get() - 1) * limit;
+    let bans = matching.into_iter().skip(skipped).take(limit).collect();
+
+    ListBannedPubkeysResponse {
+        bans,
+        skipped,
+        limit,
+        total,
+        page_number,
+        total_pages: calc_total_pages(total, limit),
+    }
+}
+
+pub async fn list_banned_pubkeys_v2_rpc(
+    ctx: MmArc,
+    req: ListBannedPubkeysRequest,
+) -> MmResult<ListBannedPubkeysResponse, BannedPubkeysRpcError> {
+    if req.filter.from_timestamp.unwrap_or_default() > req.filter.to_timestamp.unwrap_or(u64::MAX) {
+        return MmError::err(BannedPubkeysRpcError::InvalidTimestampRange);
+    }
+
+    let records = BannedPubkeysStorage::new(ctx)
+        .load_ban_records(!req.filter.include_unbanned)
+        .await?;
+    Ok(paginate_ban_records(records, &req.filter, req.limit, req.page_number))
+}
+
+#[derive(Deserialize)]
+pub struct BanPubkeyRequest {
+    pubkey: H256Json,
+    reason: String,
+    /// Overrides the `banned_pubkeys_ttl.manual` config value for this ban.
+    ttl_sec: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BanPubkeyResponse {
+    pubkey: H256Json,
+    #[serde(flatten)]
+    ban: BannedPubkey,
+}
+
+pub async fn ban_pubkey_v2_rpc(
+    ctx: MmArc,
+    req: BanPubkeyRequest,
+) -> MmResult<BanPubkeyResponse, BannedPubkeysRpcError> {
+    let ttl = req
+        .ttl_sec
+        .or_else(|| BanTtlConfig::from_ctx(&ctx).ttl(BanReasonType::Manual));
+    let reason = BanReason::Manual { reason: req.reason };
+    // The check is done by `ban_pubkey` under the update lock, so concurrent requests can't both pass it.
+    let ban = match ban_pubkey(&ctx, req.pubkey.clone(), reason, ttl, false).await? {
+        Some(ban) => ban,
+        None => return MmError::err(BannedPubkeysRpcError::AlreadyBanned(req.pubkey)),
+    };
+    Ok(BanPubkeyResponse {
+        pubkey: req.pubkey,
+        ban,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct UnbanPubkeysRequest {
+    unban_by: UnbanPubkeysReq,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnbanPubkeysResponse {
+    unbanned: HashMap<H256Json, BannedPubkey>,
+    were_not_banned: Vec<H256Json>,
+}
+
+pub async fn unban_pubkeys_v2_rpc(
+    ctx: MmArc,
+    req: UnbanPubkeysRequest,
+) -> MmResult<UnbanPubkeysResponse, BannedPubkeysRpcError> {
+    let banned = all_banned_pubkeys(&ctx).map_to_mm(BannedPubkeysRpcError::Internal)?;
+    let (to_unban, were_not_banned) = match req.unban_by {
+        UnbanPubkeysReq::All => (banned, vec![]),
+        UnbanPubkeysReq::Few(pubkeys) => pubkeys.into_iter().partition(|pubkey| banned.contains(pubkey)),
+    };
+    let unbanned = unban_pubkeys(&ctx, to_unban, UnbanReason::Manual).await?;
+    Ok(UnbanPubkeysResponse {
+        unbanned,
+        were_not_banned,
+    })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::database::banned_pubkeys::{ADD_PUBKEY_INDEX, CREATE_BANNED_PUBKEYS_TABLE};
+    use common::block_on;
+    use db_common::sqlite::rusqlite::Connection;
+    use mm2_core::mm_ctx::MmCtxBuilder;
+    use std::sync::{Arc, Mutex};
+
+    /// Creates a context with an in-memory SQLite database.
+    /// The `banned_pubkeys` table is created only if `with_table` is true, so the storage fails otherwise.
+    fn ctx_with_ban_storage(with_table: bool) -> MmArc {
+        let ctx = MmCtxBuilder::new().into_mm_arc();
+        let connection = Connection::open_in_memory().unwrap();
+        if with_table {
+            connection.execute(CREATE_BANNED_PUBKEYS_TABLE, []).unwrap();
+            connection.execute(ADD_PUBKEY_INDEX, []).unwrap();
+        }
+        ctx.sqlite_connection
+            .set(Arc::new(Mutex::new(connection)))
+            .ok()
+            .unwrap();
+        ctx
+    }
+
+    fn manual_reason() -> BanReason {
+        BanReason::Manual {
+            reason: "test".to_owned(),
+        }
+    }
+
+    fn manual_record(pubkey: H256Json, banned_at: u64, expires_at: Option<u64>) -> BanRecord {
+        BanRecord {
+            pubkey,
+            reason: BanReason::Manual {
+                reason: "test".to_owned(),
+            },
+            banned_at,
+            expires_at,
+            unbanned_at: None,
+            unban_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_paginate_ban_records() {
+        let now = now_sec();
+        let records: Vec<_> = (0..25u8)
+            .rev()
+            .map(|i| manual_record([i; 32].into(), now - 100 + i as u64, None))
+            .collect();
+
+        let filter = BannedPubkeysFilter::default();
+        let actual = paginate_ban_records(records.clone(), &filter, 10, NonZeroUsize::new(3).unwrap());
+        assert_eq!(actual.total, 25);
+        assert_eq!(actual.total_pages, 3);
+        assert_eq!(actual.skipped, 20);
+        let actual_pubkeys: Vec<_> = actual.bans.into_iter().map(|record| record.pubkey).collect();
+        let expected_pubkeys: Vec<H256Json> = (0..5u8).rev().map(|i| [i; 32].into()).collect();
+        assert_eq!(actual_pubkeys, expected_pubkeys);
+
+        let filter = BannedPubkeysFilter {
+            from_timestamp: Some(now - 100 + 20),
+            ..Default::default()
+        };
+        let actual = paginate_ban_records(records.clone(), &filter, 10, NonZeroUsize::new(1).unwrap());
+        assert_eq!(actual.total, 5);
+
+        let filter = BannedPubkeysFilter {
+            reason_type: Some(BanReasonType::FailedSwap),
+            ..Default::default()
+        };
+        let actual = paginate_ban_records(records, &filter, 10, NonZeroUsize::new(1).unwrap());
+        assert_eq!(actual.total, 0);
+        assert!(actual.bans.is_empty());
+    }
+
+    #[test]
+    fn test_paginate_ban_records_skips_expired() {
+        let now = now_sec();
+        let records = vec![
+            manual_record([1; 32].into(), now - 10, Some(now + 100)),
+            manual_record([2; 32].into(), now - 20, Some(now - 1)),
+            manual_record([3; 32].into(), now - 30, None),
+        ];
+
+        let filter = BannedPubkeysFilter::default();
+        let actual = paginate_ban_records(records.clone(), &filter, 10, NonZeroUsize::new(1).unwrap());
+        let actual_pubkeys: Vec<_> = actual.bans.into_iter().map(|record| record.pubkey).collect();
+        assert_eq!(actual_pubkeys, vec![[1; 32].into(), [3; 32].into()]);
+
+        let filter = BannedPubkeysFilter {
+            include_unbanned: true,
+            ..Default::default()
+        };
+        let actual = paginate_ban_records(records, &filter, 10, NonZeroUsize::new(1).unwrap());
+        assert_eq!(actual.total, 3);
+    }
+
+    #[test]
+    fn test_ban_pubkey_persists_before_memory() {
+        let ctx = ctx_with_ban_storage(false);
+        let pubkey: H256Json = [1; 32].into();
+
+        block_on(ban_pubkey(&ctx, pubkey.clone(), manual_reason(), None, false)).unwrap_err();
+        assert!(!is_pubkey_banned(&ctx, &pubkey));
+    }
+
+    #[test]
+    fn test_ban_pubkey_already_banned() {
+        let ctx = ctx_with_ban_storage(true);
+        let pubkey: H256Json = [1; 32].into();
+
+        let ban = block_on(ban_pubkey(&ctx, pubkey.clone(), manual_reason(), Some(100), false)).unwrap();
+        assert!(ban.is_some());
+        assert!(is_pubkey_banned(&ctx, &pubkey));
+
+        let ban = block_on(ban_pubkey(&ctx, pubkey.clone(), manual_reason(), None, false)).unwrap();
+        assert!(ban.is_none());
+        let ban = block_on(ban_pubkey(&ctx, pubkey.clone(), manual_reason(), None, true))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ban.expires_at, None);
+
+        let storage = BannedPubkeysStorage::new(ctx.clone());
+        let active = block_on(storage.load_ban_records(true)).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].expires_at, None);
+        let all = block_on(storage.load_ban_records(false)).unwrap();
+        assert_eq!(all.len(), 2);
+        assert!(all
+            .iter()
+            .any(|record| record.unban_reason == Some(UnbanReason::Superseded)));
+    }
+
+    #[test]
+    fn test_ban_pubkey_closes_expired_ban_as_expired() {
+        let ctx = ctx_with_ban_storage(true);
+        let pubkey: H256Json = [1; 32].into();
+
+        let expired = block_on(ban_pubkey(&ctx, pubkey.clone(), manual_reason(), Some(0), false))
+            .unwrap()
+            .unwrap();
+        assert!(!is_pubkey_banned(&ctx, &pubkey));
+        let ban = block_on(ban_pubkey(&ctx, pubkey.clone(), manual_reason(), None, false)).unwrap();
+        assert!(ban.is_some());
+        assert!(is_pubkey_banned(&ctx, &pubkey));
+
+        let storage = BannedPubkeysStorage::new(ctx);
+        let all = block_on(storage.load_ban_records(false)).unwrap();
+        assert_eq!(all.len(), 2);
+        let closed = all.iter().find(|record| record.unbanned_at.is_some()).unwrap();
+        assert_eq!(closed.unban_reason, Some(UnbanReason::Expired));
+        assert_eq!(closed.unbanned_at, expired.expires_at);
+    }
+
+    #[test]
+    fn test_unban_pubkeys_keeps_storage_in_sync() {
+        let ctx = ctx_with_ban_storage(true);
+        let banned: H256Json = [1; 32].into();
+        let not_banned: H256Json = [2; 32].into();
+        block_on(ban_pubkey(&ctx, banned.clone(), manual_reason(), None, false)).unwrap();
+
+        let unbanned = block_on(unban_pubkeys(
+            &ctx,
+            vec![banned.clone(), not_banned],
+            UnbanReason::Manual,
+        ))
+        .unwrap();
+        assert_eq!(unbanned.keys().collect::<Vec<_>>(), vec![&banned]);
+        assert!(!is_pubkey_banned(&ctx, &banned));
+
+        let storage = BannedPubkeysStorage::new(ctx);
+        assert!(block_on(storage.load_ban_records(true)).unwrap().is_empty());
+        let all = block_on(storage.load_ban_records(false)).unwrap();
+        assert_eq!(all[0].unban_reason, Some(UnbanReason::Manual));
+    }
+}
@@ -7,6 +7,8 @@ use mm2_event_stream::{Broadcaster, Event, EventStreamer, StreamHandlerInput};
 use async_trait::async_trait;
 use futures::channel::oneshot;
 use futures::StreamExt;
+use mm2_number::BigDecimal;
+use std::any::TypeId;
 use uuid::Uuid;
 
 pub struct SwapStatusStreamer;
@@ -51,3 +53,25 @@ impl EventStreamer for SwapStatusStreamer {
         }
     }
 }
+
+/// The swap status change dispatched through the `lp_dispatcher` for every swap kind (maker and taker, v1 and v2).
+/// The amounts and coins are given from the point of view of this node.
+#[derive(Clone)]
+pub struct SwapStatusChanged {
+    pub uuid: Uuid,
+    /// The uuid of the order the swap was started from, it's unknown for the restored v2 swaps.
+    pub my_order_uuid: Option<Uuid>,
+    /// The coin sent by this node.
+    pub my_coin: String,
+    /// The coin received by this node.
+    pub other_coin: String,
+    pub my_amount: BigDecimal,
+    pub other_amount: BigDecimal,
+    pub event_status: String,
+    pub is_error: bool,
+    pub is_finished: bool,
+}
+
+impl SwapStatusChanged {
+    pub fn event_id() -> TypeId { TypeId::of::<SwapStatusChanged>() }
+}
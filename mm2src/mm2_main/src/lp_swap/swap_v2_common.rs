@@ -29,6 +29,36 @@ cfg_wasm32!(
     use mm2_db::indexed_db::{DbTransactionError, InitDbError, MultiIndex};
 );
 
+/// Matches the maker and taker [`MmCoinEnum`]s against every pair of the coin types supporting the trading protocol v2
+/// and evaluates `$body` with the concrete coins bound to `$maker` and `$taker`, or `$fallback` for any other pair.
+macro_rules! match_swap_v2_coins {
+    (@variants $maker_coin:expr, $taker_coin:expr, $maker:ident, $taker:ident, $body:expr, $fallback:expr,
+        $variants:tt) => {
+        match_swap_v2_coins!(@maker $maker_coin, $taker_coin, $maker, $taker, $body, $fallback, $variants, $variants)
+    };
+    (@maker $maker_coin:expr, $taker_coin:expr, $maker:ident, $taker:ident, $body:expr, $fallback:expr,
+        [$($variant:ident),+], $taker_variants:tt) => {
+        match $maker_coin {
+            $(coins::MmCoinEnum::$variant($maker) => {
+                match_swap_v2_coins!(@taker $taker_coin, $taker, $body, $fallback, $taker_variants)
+            },)+
+            _ => $fallback,
+        }
+    };
+    (@taker $taker_coin:expr, $taker:ident, $body:expr, $fallback:expr, [$($variant:ident),+]) => {
+        match $taker_coin {
+            $(coins::MmCoinEnum::$variant($taker) => $body,)+
+            _ => $fallback,
+        }
+    };
+    ($maker_coin:expr, $taker_coin:expr, |$maker:ident, $taker:ident| $body:expr, _ => $fallback:expr) => {
+        match_swap_v2_coins!(@variants $maker_coin, $taker_coin, $maker, $taker, $body, $fallback,
+            [UtxoCoin, EthCoin, Tendermint, TendermintToken, Bch, SlpToken, QtumCoin, Qrc20Coin])
+    };
+}
+
+pub(crate) use match_swap_v2_coins;
+
 /// Information about active swap to be stored in swaps context
 pub struct ActiveSwapV2Info {
     pub uuid: Uuid,
@@ -380,27 +410,16 @@ pub(super) async fn swap_kickstart_handler_for_maker(
     uuid: Uuid,
 ) {
     if let Some((maker_coin, taker_coin)) = swap_kickstart_coins(&ctx, &swap_repr, &uuid).await {
-        match (maker_coin, taker_coin) {
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                swap_kickstart_handler::<MakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::EthCoin(t)) => {
-                swap_kickstart_handler::<MakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::EthCoin(t)) => {
-                swap_kickstart_handler::<MakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                swap_kickstart_handler::<MakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            _ => {
-                error!(
-                    "V2 swaps are not currently supported for {}/{} pair",
-                    swap_repr.maker_coin(),
-                    swap_repr.taker_coin()
-                );
-            },
-        }
+        match_swap_v2_coins!(
+            maker_coin,
+            taker_coin,
+            |m, t| swap_kickstart_handler::<MakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await,
+            _ => error!(
+                "V2 swaps are not currently supported for {}/{} pair",
+                swap_repr.maker_coin(),
+                swap_repr.taker_coin()
+            )
+        )
     }
 }
 
@@ -411,26 +430,15 @@ pub(super) async fn swap_kickstart_handler_for_taker(
     uuid: Uuid,
 ) {
     if let Some((maker_coin, taker_coin)) = swap_kickstart_coins(&ctx, &swap_repr, &uuid).await {
-        match (maker_coin, taker_coin) {
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                swap_kickstart_handler::<TakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::EthCoin(t)) => {
-                swap_kickstart_handler::<TakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            (MmCoinEnum::UtxoCoin(m), MmCoinEnum::EthCoin(t)) => {
-                swap_kickstart_handler::<TakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            (MmCoinEnum::EthCoin(m), MmCoinEnum::UtxoCoin(t)) => {
-                swap_kickstart_handler::<TakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await
-            },
-            _ => {
-                error!(
-                    "V2 swaps are not currently supported for {}/{} pair",
-                    swap_repr.maker_coin(),
-                    swap_repr.taker_coin()
-                );
-            },
-        }
+        match_swap_v2_coins!(
+            maker_coin,
+            taker_coin,
+            |m, t| swap_kickstart_handler::<TakerSwapStateMachine<_, _>, _, _>(swap_repr, storage, uuid, m, t).await,
+            _ => error!(
+                "V2 swaps are not currently supported for {}/{} pair",
+                swap_repr.maker_coin(),
+                swap_repr.taker_coin()
+            )
+        )
     }
 }
@@ -0,0 +1,427 @@
+//! Watchers of the swap v2 (trading protocol upgrade) protocol.
+//!
+//! A swap side sends the transactions it presigned for its HTLCs to the watchers, so they can be broadcast
+//! on its behalf while it's offline:
+//! * taker funding and taker payment are refunded by timelock if the maker doesn't spend them,
+//! * maker payment is spent by taker once the maker reveals its secret by spending the taker payment,
+//! * maker payment is refunded by timelock if the taker doesn't spend it.
+//!
+//! The presigned spends and refunds leave a part of the HTLC value to the watcher as a reward.
+//! Every guard is persisted, so a watcher keeps guarding the HTLCs after restart.
+
+use super::swap_v2_watcher_storage::{SwapV2WatcherOps, SwapV2WatcherStorage};
+use super::swap_watcher::{watcher_topic, SwapWatcherMsg, WatcherConf};
+use super::{broadcast_swap_message, get_payment_locktime, lp_coinfind, SwapsContext};
+use coins::{CanRefundHtlc, MmCoinEnum};
+use common::executor::{AbortSettings, SpawnAbortable, Timer};
+use common::log::{debug, error, info};
+use common::now_sec;
+use keys::KeyPair;
+use mm2_core::mm_ctx::MmArc;
+use rpc::v1::types::Bytes as BytesJson;
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+pub const SWAP_V2_WATCHER_REFUND_SENT_LOG: &str = "Swap v2 payment refund sent by watcher";
+pub const SWAP_V2_WATCHER_MAKER_PAYMENT_SPEND_SENT_LOG: &str = "Maker payment v2 spend sent by watcher";
+
+/// The guard is dropped if it couldn't be completed within this period after its timelock has expired.
+const GUARD_EXPIRATION_SEC: u64 = 86400;
+const WAIT_FOR_COIN_ACTIVATION_INTERVAL_SEC: f64 = 30.;
+
+/// Data required to refund a swap v2 HTLC by timelock.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RefundGuard {
+    pub coin: String,
+    pub payment_tx: BytesJson,
+    /// The block to start the payment spend search from.
+    pub search_from_block: u64,
+    pub time_lock: u64,
+    /// Refund presigned by [`coins::WatcherOps::sign_refund_for_watcher_v2`].
+    pub refund_preimage: BytesJson,
+}
+
+/// Data required to spend the maker payment on behalf of taker.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MakerPaymentSpendGuard {
+    pub maker_coin: String,
+    pub maker_payment: BytesJson,
+    pub maker_coin_start_block: u64,
+    /// The maker can refund the payment after this timestamp, so the spend isn't attempted after it.
+    pub maker_payment_time_lock: u64,
+    /// Spend presigned by [`coins::WatcherOps::sign_maker_payment_spend_for_watcher_v2`].
+    pub spend_preimage: BytesJson,
+    pub taker_coin: String,
+    /// The maker secret is extracted from the spend of this payment.
+    pub taker_payment: BytesJson,
+    pub taker_coin_start_block: u64,
+    pub maker_secret_hash: BytesJson,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum SwapV2WatcherGuard {
+    TakerFundingRefund(RefundGuard),
+    TakerPaymentRefund(RefundGuard),
+    MakerPaymentRefund(RefundGuard),
+    MakerPaymentSpend(MakerPaymentSpendGuard),
+}
+
+impl SwapV2WatcherGuard {
+    /// Identifies the guard among the guards of the same swap.
+    pub fn guard_type(&self) -> &'static str {
+        match self {
+            SwapV2WatcherGuard::TakerFundingRefund(_) => "TakerFundingRefund",
+            SwapV2WatcherGuard::TakerPaymentRefund(_) => "TakerPaymentRefund",
+            SwapV2WatcherGuard::MakerPaymentRefund(_) => "MakerPaymentRefund",
+            SwapV2WatcherGuard::MakerPaymentSpend(_) => "MakerPaymentSpend",
+        }
+    }
+
+    /// The coin the guard broadcasts the presigned transaction to.
+    /// The guard is sent to the watchers of this coin.
+    pub fn coin(&self) -> &str {
+        match self {
+            SwapV2WatcherGuard::TakerFundingRefund(guard)
+            | SwapV2WatcherGuard::TakerPaymentRefund(guard)
+            | SwapV2WatcherGuard::MakerPaymentRefund(guard) => &guard.coin,
+            SwapV2WatcherGuard::MakerPaymentSpend(guard) => &guard.maker_coin,
+        }
+    }
+
+    /// The payment the guard refunds or spends.
+    fn payment_tx(&self) -> &BytesJson {
+        match self {
+            SwapV2WatcherGuard::TakerFundingRefund(guard)
+            | SwapV2WatcherGuard::TakerPaymentRefund(guard)
+            | SwapV2WatcherGuard::MakerPaymentRefund(guard) => &guard.payment_tx,
+            SwapV2WatcherGuard::MakerPaymentSpend(guard) => &guard.maker_payment,
+        }
+    }
+
+    /// The presigned transaction and whether it's a timelock refund.
+    fn preimage(&self) -> (&BytesJson, bool) {
+        match self {
+            SwapV2WatcherGuard::TakerFundingRefund(guard)
+            | SwapV2WatcherGuard::TakerPaymentRefund(guard)
+            | SwapV2WatcherGuard::MakerPaymentRefund(guard) => (&guard.refund_preimage, true),
+            SwapV2WatcherGuard::MakerPaymentSpend(guard) => (&guard.spend_preimage, false),
+        }
+    }
+
+    fn coins(&self) -> Vec<&str> {
+        match self {
+            SwapV2WatcherGuard::TakerFundingRefund(guard)
+            | SwapV2WatcherGuard::TakerPaymentRefund(guard)
+            | SwapV2WatcherGuard::MakerPaymentRefund(guard) => vec![&guard.coin],
+            SwapV2WatcherGuard::MakerPaymentSpend(guard) => vec![&guard.maker_coin, &guard.taker_coin],
+        }
+    }
+
+    /// The latest timestamp the guard may still have to act at.
+    fn deadline(&self) -> u64 {
+        match self {
+            SwapV2WatcherGuard::TakerFundingRefund(guard)
+            | SwapV2WatcherGuard::TakerPaymentRefund(guard)
+            | SwapV2WatcherGuard::MakerPaymentRefund(guard) => guard.time_lock,
+            SwapV2WatcherGuard::MakerPaymentSpend(guard) => guard.maker_payment_time_lock,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SwapV2WatcherData {
+    pub uuid: Uuid,
+    pub guard: SwapV2WatcherGuard,
+}
+
+#[derive(Debug)]
+enum GuardSuccess {
+    /// The HTLC was spent or refunded by its owner, counterparty or another watcher.
+    SpentAlready,
+    Refunded,
+    MakerPaymentSpent,
+}
+
+/// Sends the guard to the watchers of its coin.
+/// The message of taker is signed by the same key as its swap messages, and the same for maker.
+pub(super) fn broadcast_swap_v2_watcher_msg(
+    ctx: &MmArc,
+    uuid: Uuid,
+    guard: SwapV2WatcherGuard,
+    is_taker: bool,
+    p2p_keypair: &Option<KeyPair>,
+) {
+    let topic = watcher_topic(guard.coin());
+    let data = SwapV2WatcherData { uuid, guard };
+    let msg = if is_taker {
+        SwapWatcherMsg::TakerSwapV2WatcherMsg(data)
+    } else {
+        SwapWatcherMsg::MakerSwapV2WatcherMsg(data)
+    };
+    broadcast_swap_message(ctx, topic, msg, p2p_keypair);
+}
+
+/// Validates the guard received from a swap side, saves and starts it.
+/// The guard is saved only if its payment is on chain and the preimage is signed by the party it protects,
+/// so the guard of a payment can't be taken over by anyone else.
+pub(super) fn spawn_swap_v2_watcher(ctx: MmArc, data: SwapV2WatcherData) {
+    let swap_ctx = SwapsContext::from_ctx(&ctx).unwrap();
+    // This node is a side of the swap itself.
+    if swap_ctx.swap_v2_msgs.lock().unwrap().contains_key(&data.uuid) {
+        return;
+    }
+
+    // The taker funding has the longest timelock which is 3 lock durations,
+    // and the lock duration can't exceed 10 default ones.
+    let max_deadline = now_sec() + get_payment_locktime() * 10 * 3;
+    if data.guard.deadline() > max_deadline {
+        error!(
+            "The {} guard of swap {} has too distant deadline {}",
+            data.guard.guard_type(),
+            data.uuid,
+            data.guard.deadline()
+        );
+        return;
+    }
+
+    let spawner = ctx.spawner();
+    let settings = AbortSettings::info_on_abort(format!("swap v2 watcher guard of swap {} stopped!", data.uuid));
+    let fut = async move {
+        let mut coins = Vec::new();
+        for ticker in data.guard.coins() {
+            match lp_coinfind(&ctx, ticker).await {
+                Ok(Some(coin)) if coin.is_supported_by_watchers_v2() => coins.push(coin),
+                Ok(Some(_)) => {
+                    debug!("Coin {} does not support swap v2 watchers", ticker);
+                    return;
+                },
+                Ok(None) => {
+                    debug!("Coin {} is not found/enabled", ticker);
+                    return;
+                },
+                Err(e) => {
+                    error!("!lp_coinfind({}): {}", ticker, e);
+                    return;
+                },
+            }
+        }
+
+        // The first coin is the one the guard broadcasts the preimage to.
+        let coin = &coins[0];
+        let payment_tx = data.guard.payment_tx();
+        let (preimage, is_refund) = data.guard.preimage();
+        if let Err(e) = coin.watcher_validate_preimage_v2(payment_tx, preimage, is_refund).await {
+            error!("Invalid {} guard of swap {}: {}", data.guard.guard_type(), data.uuid, e);
+            return;
+        }
+        let payment_txid = match coin.tx_enum_from_bytes(payment_tx) {
+            Ok(tx) => format!("{:02x}", tx.tx_hash_as_bytes()),
+            Err(e) => {
+                error!("Error {} parsing the payment of swap {}", e, data.uuid);
+                return;
+            },
+        };
+
+        let storage = SwapV2WatcherStorage::new(ctx.clone());
+        match storage.save_guard(&payment_txid, &data, now_sec()).await {
+            Ok(true) => (),
+            // The guard of the payment was received before, it's running already or has been finished.
+            Ok(false) => return,
+            Err(e) => {
+                error!(
+                    "Error {} saving the {} guard of swap {}",
+                    e,
+                    data.guard.guard_type(),
+                    data.uuid
+                );
+                return;
+            },
+        }
+        run_guard(ctx, storage, payment_txid, data).await;
+    };
+    spawner.spawn_with_settings(fut, settings);
+}
+
+/// Restarts the guards that weren't finished before the watcher stopped.
+/// Returns the tickers of coins required to run them.
+pub(super) async fn swap_v2_watchers_kick_start(ctx: MmArc) -> Result<HashSet<String>, String> {
+    let storage = SwapV2WatcherStorage::new(ctx.clone());
+    let guards = try_s!(storage.load_active_guards().await);
+
+    let mut coins = HashSet::new();
+    for (payment_txid, data) in guards {
+        info!(
+            "Kick starting the {} watcher guard of swap {}",
+            data.guard.guard_type(),
+            data.uuid
+        );
+        coins.extend(data.guard.coins().into_iter().map(String::from));
+        let settings = AbortSettings::info_on_abort(format!("swap v2 watcher guard of swap {} stopped!", data.uuid));
+        let fut = run_guard(ctx.clone(), storage.clone(), payment_txid, data);
+        ctx.spawner().spawn_with_settings(fut, settings);
+    }
+    Ok(coins)
+}
+
+async fn run_guard(ctx: MmArc, storage: SwapV2WatcherStorage, payment_txid: String, data: SwapV2WatcherData) {
+    let conf = json::from_value::<WatcherConf>(ctx.conf["watcher_conf"].clone()).unwrap_or_default();
+    let result = match &data.guard {
+        SwapV2WatcherGuard::TakerFundingRefund(guard)
+        | SwapV2WatcherGuard::TakerPaymentRefund(guard)
+        | SwapV2WatcherGuard::MakerPaymentRefund(guard) => guard_refund(&ctx, guard, conf.search_interval).await,
+        SwapV2WatcherGuard::MakerPaymentSpend(guard) => {
+            guard_maker_payment_spend(&ctx, guard, conf.search_interval).await
+        },
+    };
+    match result {
+        Ok(success) => info!(
+            "The {} watcher guard of swap {} finished: {:?}",
+            data.guard.guard_type(),
+            data.uuid,
+            success
+        ),
+        Err(e) => error!(
+            "The {} watcher guard of swap {} failed: {}",
+            data.guard.guard_type(),
+            data.uuid,
+            e
+        ),
+    }
+
+    if let Err(e) = storage.set_guard_finished(&payment_txid, &data, now_sec()).await {
+        error!(
+            "Error {} marking the {} guard of swap {} as finished",
+            e,
+            data.guard.guard_type(),
+            data.uuid
+        );
+    }
+}
+
+/// Waits until the coin is activated, the guard may be kick started before it.
+async fn wait_for_coin(ctx: &MmArc, ticker: &str) -> Result<MmCoinEnum, String> {
+    loop {
+        match lp_coinfind(ctx, ticker).await {
+            Ok(Some(coin)) => return Ok(coin),
+            Ok(None) => {
+                info!(
+                    "Can't run the swap v2 watcher guard until the coin {} is activated",
+                    ticker
+                );
+                Timer::sleep(WAIT_FOR_COIN_ACTIVATION_INTERVAL_SEC).await;
+            },
+            Err(e) => return ERR!("!lp_coinfind({}): {}", ticker, e),
+        }
+    }
+}
+
+async fn guard_refund(ctx: &MmArc, guard: &RefundGuard, search_interval: f64) -> Result<GuardSuccess, String> {
+    let coin = wait_for_coin(ctx, &guard.coin).await?;
+    loop {
+        match coin
+            .watcher_search_for_payment_spend_v2(&guard.payment_tx, guard.search_from_block)
+            .await
+        {
+            Ok(Some(_)) => return Ok(GuardSuccess::SpentAlready),
+            Ok(None) => (),
+            Err(e) => error!("Error {} searching for {} payment spend", e, guard.coin),
+        }
+
+        if now_sec() > guard.time_lock + GUARD_EXPIRATION_SEC {
+            return ERR!("Couldn't refund the payment until the guard expired");
+        }
+
+        match coin.can_refund_htlc(guard.time_lock).await {
+            Ok(CanRefundHtlc::CanRefundNow) => {
+                match coin
+                    .send_preimage_as_watcher_v2(&guard.payment_tx, &guard.refund_preimage, None)
+                    .await
+                {
+                    Ok(refund) => {
+                        info!(
+                            "{} {} tx {:02x}",
+                            SWAP_V2_WATCHER_REFUND_SENT_LOG,
+                            guard.coin,
+                            refund.tx_hash_as_bytes()
+                        );
+                        return Ok(GuardSuccess::Refunded);
+                    },
+                    // The payment may have been spent in the meantime, it's checked on the next iteration.
+                    Err(e) => error!(
+                        "Error {} sending {} payment refund",
+                        e.get_plain_text_format(),
+                        guard.coin
+                    ),
+                }
+            },
+            Ok(CanRefundHtlc::HaveToWait(_)) => (),
+            Err(e) => error!("Error {} on can_refund_htlc", e),
+        }
+        Timer::sleep(search_interval).await;
+    }
+}
+
+async fn guard_maker_payment_spend(
+    ctx: &MmArc,
+    guard: &MakerPaymentSpendGuard,
+    search_interval: f64,
+) -> Result<GuardSuccess, String> {
+    let maker_coin = wait_for_coin(ctx, &guard.maker_coin).await?;
+    let taker_coin = wait_for_coin(ctx, &guard.taker_coin).await?;
+    loop {
+        match maker_coin
+            .watcher_search_for_payment_spend_v2(&guard.maker_payment, guard.maker_coin_start_block)
+            .await
+        {
+            Ok(Some(_)) => return Ok(GuardSuccess::SpentAlready),
+            Ok(None) => (),
+            Err(e) => error!("Error {} searching for {} maker payment spend", e, guard.maker_coin),
+        }
+
+        if now_sec() >= guard.maker_payment_time_lock {
+            return ERR!("Maker payment timelock has expired, the maker can refund it");
+        }
+
+        let taker_payment_spend = match taker_coin
+            .watcher_search_for_payment_spend_v2(&guard.taker_payment, guard.taker_coin_start_block)
+            .await
+        {
+            Ok(spend) => spend,
+            Err(e) => {
+                error!("Error {} searching for {} taker payment spend", e, guard.taker_coin);
+                None
+            },
+        };
+
+        if let Some(taker_payment_spend) = taker_payment_spend {
+            // The taker payment is either spent by maker revealing the secret, or refunded by taker.
+            let secret = try_s!(
+                taker_coin
+                    .extract_secret(&guard.maker_secret_hash, &taker_payment_spend.tx_hex(), false)
+                    .await
+            );
+            match maker_coin
+                .send_preimage_as_watcher_v2(&guard.maker_payment, &guard.spend_preimage, Some(&secret))
+                .await
+            {
+                Ok(spend) => {
+                    info!(
+                        "{} {} tx {:02x}",
+                        SWAP_V2_WATCHER_MAKER_PAYMENT_SPEND_SENT_LOG,
+                        guard.maker_coin,
+                        spend.tx_hash_as_bytes()
+                    );
+                    return Ok(GuardSuccess::MakerPaymentSpent);
+                },
+                Err(e) => error!(
+                    "Error {} sending {} maker payment spend",
+                    e.get_plain_text_format(),
+                    guard.maker_coin
+                ),
+            }
+        }
+        Timer::sleep(search_interval).await;
+    }
+}
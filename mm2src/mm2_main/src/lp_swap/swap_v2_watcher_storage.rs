@@ -0,0 +1,209 @@
+use super::swap_v2_watcher::SwapV2WatcherData;
+use async_trait::async_trait;
+use derive_more::Display;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+
+pub type SwapV2WatcherStorageResult<T> = Result<T, MmError<SwapV2WatcherStorageError>>;
+
+#[derive(Debug, Display)]
+pub enum SwapV2WatcherStorageError {
+    #[display(fmt = "Error serializing watcher guard: {}", _0)]
+    ErrorSerializingItem(String),
+    #[display(fmt = "Error deserializing watcher guard: {}", _0)]
+    ErrorDeserializingItem(String),
+    #[display(fmt = "Unknown SQL error: {}", _0)]
+    UnknownSqlError(String),
+    #[display(fmt = "Internal error: {}", _0)]
+    InternalError(String),
+}
+
+#[async_trait]
+pub trait SwapV2WatcherOps {
+    /// Saves the guard received from a swap side. Guards are identified by the guarded payment and the guard type.
+    /// Returns false if the same guard of the payment is saved already.
+    async fn save_guard(
+        &self,
+        payment_txid: &str,
+        data: &SwapV2WatcherData,
+        added_at: u64,
+    ) -> SwapV2WatcherStorageResult<bool>;
+
+    /// Marks the guard as finished, so it isn't loaded on restart anymore.
+    async fn set_guard_finished(
+        &self,
+        payment_txid: &str,
+        data: &SwapV2WatcherData,
+        finished_at: u64,
+    ) -> SwapV2WatcherStorageResult<()>;
+
+    /// Loads the guards that are not finished yet along with the txids of their payments,
+    /// in the order they were received.
+    async fn load_active_guards(&self) -> SwapV2WatcherStorageResult<Vec<(String, SwapV2WatcherData)>>;
+}
+
+#[derive(Clone)]
+pub struct SwapV2WatcherStorage {
+    ctx: MmArc,
+}
+
+impl SwapV2WatcherStorage {
+    pub fn new(ctx: MmArc) -> SwapV2WatcherStorage { SwapV2WatcherStorage { ctx } }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native_impl {
+    use super::*;
+    use crate::database::swap_v2_watcher_guards::{insert_guard, select_active_guards, update_finished};
+    use db_common::sqlite::rusqlite::Error as SqlError;
+
+    impl From<SqlError> for SwapV2WatcherStorageError {
+        fn from(e: SqlError) -> Self {
+            match e {
+                SqlError::ToSqlConversionFailure(e) => SwapV2WatcherStorageError::ErrorSerializingItem(e.to_string()),
+                SqlError::FromSqlConversionFailure(_, _, e) => {
+                    SwapV2WatcherStorageError::ErrorDeserializingItem(e.to_string())
+                },
+                e => SwapV2WatcherStorageError::UnknownSqlError(e.to_string()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl SwapV2WatcherOps for SwapV2WatcherStorage {
+        async fn save_guard(
+            &self,
+            payment_txid: &str,
+            data: &SwapV2WatcherData,
+            added_at: u64,
+        ) -> SwapV2WatcherStorageResult<bool> {
+            Ok(insert_guard(&self.ctx, payment_txid, data, added_at)?)
+        }
+
+        async fn set_guard_finished(
+            &self,
+            payment_txid: &str,
+            data: &SwapV2WatcherData,
+            finished_at: u64,
+        ) -> SwapV2WatcherStorageResult<()> {
+            Ok(update_finished(&self.ctx, payment_txid, data, finished_at)?)
+        }
+
+        async fn load_active_guards(&self) -> SwapV2WatcherStorageResult<Vec<(String, SwapV2WatcherData)>> {
+            Ok(select_active_guards(&self.ctx)?)
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm_impl {
+    use super::*;
+    use crate::lp_swap::swap_wasm_db::{DbTransactionError, InitDbError, SwapV2WatcherGuardsTable};
+    use crate::lp_swap::SwapsContext;
+    use mm2_db::indexed_db::MultiIndex;
+
+    impl From<DbTransactionError> for SwapV2WatcherStorageError {
+        fn from(e: DbTransactionError) -> Self {
+            let stringified_error = e.to_string();
+            match e {
+                DbTransactionError::ErrorSerializingItem(_) => {
+                    SwapV2WatcherStorageError::ErrorSerializingItem(stringified_error)
+                },
+                DbTransactionError::ErrorDeserializingItem(_) => {
+                    SwapV2WatcherStorageError::ErrorDeserializingItem(stringified_error)
+                },
+                _ => SwapV2WatcherStorageError::InternalError(stringified_error),
+            }
+        }
+    }
+
+    impl From<InitDbError> for SwapV2WatcherStorageError {
+        fn from(e: InitDbError) -> Self { SwapV2WatcherStorageError::InternalError(e.to_string()) }
+    }
+
+    fn payment_guard_type_index(
+        payment_txid: &str,
+        data: &SwapV2WatcherData,
+    ) -> SwapV2WatcherStorageResult<MultiIndex> {
+        let index = MultiIndex::new(SwapV2WatcherGuardsTable::PAYMENT_TXID_GUARD_TYPE_INDEX)
+            .with_value(payment_txid)?
+            .with_value(data.guard.guard_type())?;
+        Ok(index)
+    }
+
+    #[async_trait]
+    impl SwapV2WatcherOps for SwapV2WatcherStorage {
+        async fn save_guard(
+            &self,
+            payment_txid: &str,
+            data: &SwapV2WatcherData,
+            added_at: u64,
+        ) -> SwapV2WatcherStorageResult<bool> {
+            let swap_ctx = SwapsContext::from_ctx(&self.ctx).map_to_mm(SwapV2WatcherStorageError::InternalError)?;
+            let db = swap_ctx.swap_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<SwapV2WatcherGuardsTable>().await?;
+
+            if table
+                .get_item_by_unique_multi_index(payment_guard_type_index(payment_txid, data)?)
+                .await?
+                .is_some()
+            {
+                return Ok(false);
+            }
+            let guard = serde_json::to_value(data)
+                .map_to_mm(|e| SwapV2WatcherStorageError::ErrorSerializingItem(e.to_string()))?;
+            let item = SwapV2WatcherGuardsTable {
+                payment_txid: payment_txid.to_owned(),
+                guard_type: data.guard.guard_type().to_owned(),
+                uuid: data.uuid,
+                guard,
+                added_at,
+                finished_at: None,
+            };
+            table.add_item(&item).await?;
+            Ok(true)
+        }
+
+        async fn set_guard_finished(
+            &self,
+            payment_txid: &str,
+            data: &SwapV2WatcherData,
+            finished_at: u64,
+        ) -> SwapV2WatcherStorageResult<()> {
+            let swap_ctx = SwapsContext::from_ctx(&self.ctx).map_to_mm(SwapV2WatcherStorageError::InternalError)?;
+            let db = swap_ctx.swap_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<SwapV2WatcherGuardsTable>().await?;
+
+            if let Some((item_id, mut item)) = table
+                .get_item_by_unique_multi_index(payment_guard_type_index(payment_txid, data)?)
+                .await?
+            {
+                item.finished_at = Some(finished_at);
+                table.replace_item(item_id, &item).await?;
+            }
+            Ok(())
+        }
+
+        async fn load_active_guards(&self) -> SwapV2WatcherStorageResult<Vec<(String, SwapV2WatcherData)>> {
+            let swap_ctx = SwapsContext::from_ctx(&self.ctx).map_to_mm(SwapV2WatcherStorageError::InternalError)?;
+            let db = swap_ctx.swap_db().await?;
+            let transaction = db.transaction().await?;
+            let table = transaction.table::<SwapV2WatcherGuardsTable>().await?;
+
+            let mut items = table.get_all_items().await?;
+            // Emulate `ORDER BY id` of the SQLite implementation.
+            items.sort_by_key(|(item_id, _)| *item_id);
+            items
+                .into_iter()
+                .filter(|(_item_id, item)| item.finished_at.is_none())
+                .map(|(_item_id, item)| {
+                    let data = serde_json::from_value(item.guard)
+                        .map_to_mm(|e| SwapV2WatcherStorageError::ErrorDeserializingItem(e.to_string()))?;
+                    Ok((item.payment_txid, data))
+                })
+                .collect()
+        }
+    }
+}
@@ -6,10 +6,11 @@ use uuid::Uuid;
 
 pub use mm2_db::indexed_db::{cursor_prelude, DbTransactionError, DbTransactionResult, InitDbError, InitDbResult,
                              ItemId};
-pub use tables::{MySwapsFiltersTable, SavedSwapTable, SwapLockTable, SwapsMigrationTable};
+pub use tables::{BannedPubkeysTable, MySwapsFiltersTable, SavedSwapTable, SwapLockTable, SwapV2WatcherGuardsTable,
+                 SwapsMigrationTable};
 
 const DB_NAME: &str = "swap";
-const DB_VERSION: u32 = 2;
+const DB_VERSION: u32 = 4;
 
 pub const IS_FINISHED_SWAP_TYPE_INDEX: &str = "is_finished_swap_type";
 
@@ -28,6 +29,8 @@ impl DbInstance for SwapDb {
             .with_table::<SavedSwapTable>()
             .with_table::<MySwapsFiltersTable>()
             .with_table::<SwapsMigrationTable>()
+            .with_table::<BannedPubkeysTable>()
+            .with_table::<SwapV2WatcherGuardsTable>()
             .build()
             .await?;
         Ok(SwapDb { inner })
@@ -111,6 +114,9 @@ pub mod tables {
                         let table = upgrader.open_table(Self::TABLE_NAME)?;
                         table.create_multi_index(IS_FINISHED_SWAP_TYPE_INDEX, &["is_finished", "swap_type"], false)?;
                     },
+                    2 | 3 => {
+                        // do nothing explicitly because no action is required for MySwapsFiltersTable
+                    },
                     unsupported_version => {
                         return MmError::err(OnUpgradeError::UnsupportedVersion {
                             unsupported_version,
@@ -139,7 +145,7 @@ pub mod tables {
                     let table = upgrader.create_table(table_name)?;
                     table.create_index("uuid", true)?;
                 },
-                1 => {
+                1..=3 => {
                     // do nothing explicitly because no action is required for SwapLockTable and SavedSwapTable
                 },
                 unsupported_version => {
@@ -175,6 +181,104 @@ pub mod tables {
                         let table = upgrader.create_table(Self::TABLE_NAME)?;
                         table.create_index("migration", true)?;
                     },
+                    2 | 3 => {
+                        // do nothing explicitly because no action is required for SwapsMigrationTable
+                    },
+                    unsupported_version => {
+                        return MmError::err(OnUpgradeError::UnsupportedVersion {
+                            unsupported_version,
+                            old_version,
+                            new_version,
+                        })
+                    },
+                }
+
+                old_version += 1;
+            }
+            Ok(())
+        }
+    }
+
+    /// Every ban and unban of a pubkey is kept in this table, so it serves as an audit trail too.
+    /// The active ban of a pubkey is the only record with `unbanned_at` not set.
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct BannedPubkeysTable {
+        pub pubkey: String,
+        pub reason_type: String,
+        pub reason: Json,
+        pub banned_at: u64,
+        pub expires_at: Option<u64>,
+        pub unbanned_at: Option<u64>,
+        pub unban_reason: Option<String>,
+    }
+
+    impl TableSignature for BannedPubkeysTable {
+        const TABLE_NAME: &'static str = "banned_pubkeys";
+
+        fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+            while old_version < new_version {
+                match old_version {
+                    0 | 1 => {
+                        // do nothing explicitly because the table should be created on upgrade
+                        // from version 2 to 3 in order to avoid breaking existing databases
+                    },
+                    2 => {
+                        let table = upgrader.create_table(Self::TABLE_NAME)?;
+                        table.create_index("pubkey", false)?;
+                        table.create_index("banned_at", false)?;
+                    },
+                    3 => {
+                        // do nothing explicitly because no action is required for BannedPubkeysTable
+                    },
+                    unsupported_version => {
+                        return MmError::err(OnUpgradeError::UnsupportedVersion {
+                            unsupported_version,
+                            old_version,
+                            new_version,
+                        })
+                    },
+                }
+
+                old_version += 1;
+            }
+            Ok(())
+        }
+    }
+
+    /// Swap v2 HTLCs guarded by this node as a watcher.
+    /// A guard is identified by the guarded payment and its type, `finished_at` is set once nothing is left to guard.
+    #[derive(Debug, Deserialize, Serialize)]
+    pub struct SwapV2WatcherGuardsTable {
+        pub payment_txid: String,
+        pub guard_type: String,
+        pub uuid: Uuid,
+        pub guard: Json,
+        pub added_at: u64,
+        pub finished_at: Option<u64>,
+    }
+
+    impl SwapV2WatcherGuardsTable {
+        pub const PAYMENT_TXID_GUARD_TYPE_INDEX: &'static str = "payment_txid_guard_type";
+    }
+
+    impl TableSignature for SwapV2WatcherGuardsTable {
+        const TABLE_NAME: &'static str = "swap_v2_watcher_guards";
+
+        fn on_upgrade_needed(upgrader: &DbUpgrader, mut old_version: u32, new_version: u32) -> OnUpgradeResult<()> {
+            while old_version < new_version {
+                match old_version {
+                    0..=2 => {
+                        // do nothing explicitly because the table should be created on upgrade
+                        // from version 3 to 4 in order to avoid breaking existing databases
+                    },
+                    3 => {
+                        let table = upgrader.create_table(Self::TABLE_NAME)?;
+                        table.create_multi_index(
+                            Self::PAYMENT_TXID_GUARD_TYPE_INDEX,
+                            &["payment_txid", "guard_type"],
+                            true,
+                        )?;
+                    },
                     unsupported_version => {
                         return MmError::err(OnUpgradeError::UnsupportedVersion {
                             unsupported_version,
@@ -1,3 +1,4 @@
+use super::swap_v2_watcher::{spawn_swap_v2_watcher, SwapV2WatcherData};
 use super::{broadcast_p2p_tx_msg, get_payment_locktime, lp_coinfind, taker_payment_spend_deadline, tx_helper_topic,
             H256Json, SwapsContext, TAKER_FEE_VALIDATION_ATTEMPTS, TAKER_FEE_VALIDATION_RETRY_DELAY_SECS,
             WAIT_CONFIRM_INTERVAL_SEC};
@@ -73,7 +74,7 @@ pub struct WatcherConf {
     #[serde(default = "default_watcher_refund_factor")]
     refund_start_factor: f64,
     #[serde(default = "common::three_hundred_f64")]
-    search_interval: f64,
+    pub(super) search_interval: f64,
 }
 
 impl Default for WatcherConf {
@@ -94,6 +95,8 @@ pub fn default_watcher_refund_factor() -> f64 { common::one_and_half_f64() }
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum SwapWatcherMsg {
     TakerSwapWatcherMsg(TakerSwapWatcherData),
+    TakerSwapV2WatcherMsg(SwapV2WatcherData),
+    MakerSwapV2WatcherMsg(SwapV2WatcherData),
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
@@ -533,6 +536,9 @@ pub fn process_watcher_msg(ctx: MmArc, msg: &[u8]) -> P2PRequestResult<()> {
         SwapWatcherMsg::TakerSwapWatcherMsg(watcher_data) => {
             spawn_taker_swap_watcher(ctx, watcher_data, verified_pubkey.to_bytes())
         },
+        SwapWatcherMsg::TakerSwapV2WatcherMsg(watcher_data) | SwapWatcherMsg::MakerSwapV2WatcherMsg(watcher_data) => {
+            spawn_swap_v2_watcher(ctx, watcher_data)
+        },
     };
 
     Ok(())
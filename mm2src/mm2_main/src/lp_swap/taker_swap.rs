@@ -10,9 +10,10 @@ use super::{broadcast_my_swap_status, broadcast_swap_message, broadcast_swap_msg
             NegotiationDataV2, NegotiationDataV3, RecoveredSwap, RecoveredSwapAction, SavedSwap, SavedSwapIo,
             SavedTradeFee, SwapConfirmationsSettings, SwapError, SwapMsg, SwapPubkeys, SwapTxDataMsg, SwapsContext,
             TransactionIdentifier, INCLUDE_REFUND_FEE, NO_REFUND_FEE, WAIT_CONFIRM_INTERVAL_SEC};
+use crate::lp_dispatcher::{dispatch_lp_event, LpEvents};
 use crate::lp_network::subscribe_to_topic;
 use crate::lp_ordermatch::TakerOrderBuilder;
-use crate::lp_swap::swap_events::{SwapStatusEvent, SwapStatusStreamer};
+use crate::lp_swap::swap_events::{SwapStatusChanged, SwapStatusEvent, SwapStatusStreamer};
 use crate::lp_swap::swap_v2_common::mark_swap_as_finished;
 use crate::lp_swap::taker_restart::get_command_based_on_maker_or_watcher_activity;
 use crate::lp_swap::{broadcast_p2p_tx_msg, broadcast_swap_msg_every_delayed, tx_helper_topic,
@@ -483,6 +484,11 @@ pub async fn run_taker_swap(swap: RunTakerSwapInput, ctx: MmArc) {
                         event: event.clone(),
                     };
 
+                    dispatch_lp_event(
+                        ctx.clone(),
+                        LpEvents::SwapStatusChanged(SwapStatusChanged::from_taker_swap(&running_swap, &to_save)),
+                    )
+                    .await;
                     // Send a notification to the swap status streamer about a new event.
                     ctx.event_stream_manager
                         .send_fn(SwapStatusStreamer::derive_streamer_id(), || SwapStatusEvent::TakerV1 {
@@ -500,6 +506,7 @@ pub async fn run_taker_swap(swap: RunTakerSwapInput, ctx: MmArc) {
                             &running_swap.uuid,
                             event.clone().into(),
                         )
+                        .await;
                     }
 
                     if event.is_error() {
@@ -628,6 +635,22 @@ impl From<String> for FailAt {
     }
 }
 
+impl SwapStatusChanged {
+    fn from_taker_swap(taker_swap: &TakerSwap, saved_swap: &TakerSavedEvent) -> Self {
+        SwapStatusChanged {
+            uuid: taker_swap.uuid,
+            my_order_uuid: taker_swap.my_order_uuid,
+            my_coin: taker_swap.taker_coin.ticker().to_string(),
+            other_coin: taker_swap.maker_coin.ticker().to_string(),
+            my_amount: taker_swap.taker_amount.to_decimal(),
+            other_amount: taker_swap.maker_amount.to_decimal(),
+            event_status: saved_swap.event.status_str(),
+            is_error: saved_swap.event.is_error(),
+            is_finished: matches!(saved_swap.event, TakerSwapEvent::Finished),
+        }
+    }
+}
+
 pub struct TakerSwap {
     ctx: MmArc,
     pub maker_coin: MmCoinEnum,
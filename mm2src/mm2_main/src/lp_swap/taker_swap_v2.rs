@@ -1,7 +1,9 @@
-use super::swap_events::{SwapStatusEvent, SwapStatusStreamer};
+use super::swap_events::{SwapStatusChanged, SwapStatusEvent, SwapStatusStreamer};
 use super::swap_v2_common::*;
+use super::swap_v2_watcher::{broadcast_swap_v2_watcher_msg, MakerPaymentSpendGuard, RefundGuard, SwapV2WatcherGuard};
 use super::{LockedAmount, LockedAmountInfo, SavedTradeFee, SwapsContext, TakerSwapPreparedParams,
             NEGOTIATE_SEND_INTERVAL, NEGOTIATION_TIMEOUT_SEC};
+use crate::lp_dispatcher::{dispatch_lp_event, LpEvents};
 use crate::lp_swap::swap_lock::SwapLock;
 use crate::lp_swap::{broadcast_swap_v2_msg_every, check_balance_for_taker_swap, recv_swap_v2_msg, swap_v2_topic,
                      SwapConfirmationsSettings, TransactionIdentifier, MAX_STARTED_AT_DIFF, TAKER_SWAP_V2_TYPE};
@@ -10,12 +12,12 @@ use async_trait::async_trait;
 use bitcrypto::{dhash160, sha256};
 use coins::hd_wallet::AddrToString;
 use coins::{CanRefundHtlc, ConfirmPaymentInput, DexFee, FeeApproxStage, GenTakerFundingSpendArgs,
-            GenTakerPaymentSpendArgs, MakerCoinSwapOpsV2, MmCoin, ParseCoinAssocTypes, RefundFundingSecretArgs,
-            RefundTakerPaymentArgs, SendTakerFundingArgs, SpendMakerPaymentArgs, SwapTxTypeWithSecretHash,
-            TakerCoinSwapOpsV2, ToBytes, TradeFee, TradePreimageValue, Transaction, TxPreimageWithSig,
-            ValidateMakerPaymentArgs};
+            GenTakerPaymentSpendArgs, MakerCoinSwapOpsV2, MakerPaymentSpendForWatcherArgs, MmCoin,
+            ParseCoinAssocTypes, RefundFundingSecretArgs, RefundPaymentArgs, RefundTakerPaymentArgs,
+            SendTakerFundingArgs, SpendMakerPaymentArgs, SwapTxTypeWithSecretHash, TakerCoinSwapOpsV2, ToBytes,
+            TradeFee, TradePreimageValue, Transaction, TxPreimageWithSig, ValidateMakerPaymentArgs};
 use common::executor::abortable_queue::AbortableQueue;
-use common::executor::{AbortableSystem, Timer};
+use common::executor::{AbortableSystem, SpawnFuture, Timer};
 use common::log::{debug, error, info, warn};
 use common::Future01CompatExt;
 use crypto::privkey::SerializableSecp256k1Keypair;
@@ -172,6 +174,53 @@ pub enum TakerSwapEvent {
     Completed,
 }
 
+impl TakerSwapEvent {
+    fn status_str(&self) -> &'static str {
+        match self {
+            TakerSwapEvent::Initialized { .. } => "Initialized",
+            TakerSwapEvent::Negotiated { .. } => "Negotiated",
+            TakerSwapEvent::TakerFundingSent { .. } => "TakerFundingSent",
+            TakerSwapEvent::TakerFundingRefundRequired { .. } => "TakerFundingRefundRequired",
+            TakerSwapEvent::MakerPaymentAndFundingSpendPreimgReceived { .. } => {
+                "MakerPaymentAndFundingSpendPreimgReceived"
+            },
+            TakerSwapEvent::TakerPaymentSent { .. } => "TakerPaymentSent",
+            TakerSwapEvent::TakerPaymentSentAndPreimageSendingSkipped { .. } => {
+                "TakerPaymentSentAndPreimageSendingSkipped"
+            },
+            TakerSwapEvent::TakerPaymentRefundRequired { .. } => "TakerPaymentRefundRequired",
+            TakerSwapEvent::MakerPaymentConfirmed { .. } => "MakerPaymentConfirmed",
+            TakerSwapEvent::TakerPaymentSpent { .. } => "TakerPaymentSpent",
+            TakerSwapEvent::MakerPaymentSpent { .. } => "MakerPaymentSpent",
+            TakerSwapEvent::TakerFundingRefunded { .. } => "TakerFundingRefunded",
+            TakerSwapEvent::TakerPaymentRefunded { .. } => "TakerPaymentRefunded",
+            TakerSwapEvent::Aborted { .. } => "Aborted",
+            TakerSwapEvent::Completed => "Completed",
+        }
+    }
+
+    fn is_error(&self) -> bool {
+        matches!(
+            self,
+            TakerSwapEvent::TakerFundingRefundRequired { .. }
+                | TakerSwapEvent::TakerPaymentRefundRequired { .. }
+                | TakerSwapEvent::TakerFundingRefunded { .. }
+                | TakerSwapEvent::TakerPaymentRefunded { .. }
+                | TakerSwapEvent::Aborted { .. }
+        )
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TakerSwapEvent::TakerFundingRefunded { .. }
+                | TakerSwapEvent::TakerPaymentRefunded { .. }
+                | TakerSwapEvent::Aborted { .. }
+                | TakerSwapEvent::Completed
+        )
+    }
+}
+
 /// Storage for taker swaps.
 #[derive(Clone)]
 pub struct TakerSwapStorage {
@@ -440,6 +489,8 @@ pub struct TakerSwapStateMachine<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCo
     pub require_maker_payment_spend_confirm: bool,
     /// Swap protocol version
     pub swap_version: u8,
+    /// The uuid of the taker order the swap was started from. It isn't persisted, so it's None for restored swaps.
+    pub my_order_uuid: Option<Uuid>,
 }
 
 impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOpsV2>
@@ -483,6 +534,119 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
             DexFee::new_from_taker_coin(&self.taker_coin, self.maker_coin.ticker(), &self.taker_volume)
         }
     }
+
+    /// Whether the HTLCs of this swap can be guarded by watchers.
+    fn use_watchers(&self) -> bool {
+        !self.ctx.disable_watchers_globally()
+            && self.maker_coin.is_supported_by_watchers_v2()
+            && self.taker_coin.is_supported_by_watchers_v2()
+    }
+
+    /// Signs the timelock refund of taker funding or taker payment and sends it to watchers.
+    /// Errors are only logged as the watchers are an additional safety measure.
+    async fn send_taker_refund_to_watchers(
+        &self,
+        payment_tx: &TakerCoin::Tx,
+        time_lock: u64,
+        search_from_block: u64,
+        negotiation_data: &NegotiationData<MakerCoin, TakerCoin>,
+        is_funding: bool,
+    ) {
+        let payment_tx_bytes = payment_tx.tx_hex();
+        let unique_data = self.unique_data();
+        let taker_secret_hash = self.taker_secret_hash();
+        let tx_type_with_secret_hash = if is_funding {
+            SwapTxTypeWithSecretHash::TakerFunding {
+                taker_secret_hash: &taker_secret_hash,
+            }
+        } else {
+            SwapTxTypeWithSecretHash::TakerPaymentV2 {
+                maker_secret_hash: &negotiation_data.maker_secret_hash,
+                taker_secret_hash: &taker_secret_hash,
+            }
+        };
+
+        let args = RefundPaymentArgs {
+            payment_tx: &payment_tx_bytes,
+            time_lock,
+            other_pubkey: &negotiation_data.taker_coin_htlc_pub_from_maker.to_bytes(),
+            tx_type_with_secret_hash,
+            swap_contract_address: &negotiation_data.taker_coin_swap_contract.clone().map(Into::into),
+            swap_unique_data: &unique_data,
+            watcher_reward: false,
+        };
+        let refund_preimage = match self.taker_coin.sign_refund_for_watcher_v2(args).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!(
+                    "Failed to sign refund for watchers during swap {}: {}",
+                    self.uuid,
+                    e.get_plain_text_format()
+                );
+                return;
+            },
+        };
+
+        let refund_guard = RefundGuard {
+            coin: self.taker_coin.ticker().to_owned(),
+            payment_tx: payment_tx_bytes.into(),
+            search_from_block,
+            time_lock,
+            refund_preimage: refund_preimage.tx_hex().into(),
+        };
+        let guard = if is_funding {
+            SwapV2WatcherGuard::TakerFundingRefund(refund_guard)
+        } else {
+            SwapV2WatcherGuard::TakerPaymentRefund(refund_guard)
+        };
+        broadcast_swap_v2_watcher_msg(&self.ctx, self.uuid, guard, true, &self.p2p_keypair);
+    }
+
+    /// Signs the maker payment spend without the maker secret and sends it to watchers,
+    /// so they can complete the swap once the maker spends the taker payment.
+    async fn send_maker_payment_spend_to_watchers(
+        &self,
+        maker_payment: &MakerCoin::Tx,
+        maker_coin_start_block: u64,
+        taker_payment: &TakerCoin::Tx,
+        taker_coin_start_block: u64,
+        negotiation_data: &NegotiationData<MakerCoin, TakerCoin>,
+    ) {
+        let maker_payment_bytes = maker_payment.tx_hex();
+        let unique_data = self.unique_data();
+        let args = MakerPaymentSpendForWatcherArgs {
+            maker_payment_tx: &maker_payment_bytes,
+            time_lock: negotiation_data.maker_payment_locktime,
+            taker_secret_hash: &self.taker_secret_hash(),
+            maker_secret_hash: &negotiation_data.maker_secret_hash,
+            maker_pub: &negotiation_data.maker_coin_htlc_pub_from_maker.to_bytes(),
+            swap_unique_data: &unique_data,
+        };
+        let spend_preimage = match self.maker_coin.sign_maker_payment_spend_for_watcher_v2(args).await {
+            Ok(tx) => tx,
+            Err(e) => {
+                error!(
+                    "Failed to sign maker payment spend for watchers during swap {}: {}",
+                    self.uuid,
+                    e.get_plain_text_format()
+                );
+                return;
+            },
+        };
+
+        let guard = SwapV2WatcherGuard::MakerPaymentSpend(MakerPaymentSpendGuard {
+            maker_coin: self.maker_coin.ticker().to_owned(),
+            maker_payment: maker_payment_bytes.into(),
+            maker_coin_start_block,
+            maker_payment_time_lock: negotiation_data.maker_payment_locktime,
+            spend_preimage: spend_preimage.tx_hex().into(),
+            taker_coin: self.taker_coin.ticker().to_owned(),
+            taker_payment: taker_payment.tx_hex().into(),
+            taker_coin_start_block,
+            maker_secret_hash: negotiation_data.maker_secret_hash.clone().into(),
+        });
+        broadcast_swap_v2_watcher_msg(&self.ctx, self.uuid, guard, true, &self.p2p_keypair);
+    }
 }
 
 #[async_trait]
@@ -829,6 +993,7 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
             require_maker_payment_confirm_before_funding_spend: true,
             require_maker_payment_spend_confirm: true,
             swap_version: repr.swap_version,
+            my_order_uuid: None,
         };
         Ok((RestoredMachine::new(machine), current_state))
     }
@@ -906,6 +1071,21 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
             | TakerSwapEvent::Aborted { .. }
             | TakerSwapEvent::Completed => (),
         }
+        let status_changed = SwapStatusChanged {
+            uuid: self.uuid,
+            my_order_uuid: self.my_order_uuid,
+            my_coin: self.taker_coin.ticker().to_string(),
+            other_coin: self.maker_coin.ticker().to_string(),
+            my_amount: self.taker_volume.to_decimal(),
+            other_amount: self.maker_volume.to_decimal(),
+            event_status: event.status_str().to_string(),
+            is_error: event.is_error(),
+            is_finished: event.is_finished(),
+        };
+        self.ctx.spawner().spawn(dispatch_lp_event(
+            self.ctx.clone(),
+            LpEvents::SwapStatusChanged(status_changed),
+        ));
         // Send a notification to the swap status streamer about a new event.
         self.ctx
             .event_stream_manager
@@ -1363,6 +1543,18 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
     type StateMachine = TakerSwapStateMachine<MakerCoin, TakerCoin>;
 
     async fn on_changed(self: Box<Self>, state_machine: &mut Self::StateMachine) -> StateResult<Self::StateMachine> {
+        if state_machine.use_watchers() {
+            state_machine
+                .send_taker_refund_to_watchers(
+                    &self.taker_funding,
+                    state_machine.taker_funding_locktime(),
+                    self.taker_coin_start_block,
+                    &self.negotiation_data,
+                    true,
+                )
+                .await;
+        }
+
         let taker_funding_info = TakerFundingInfo {
             tx_bytes: self.taker_funding.tx_hex(),
             next_step_instructions: None,
@@ -1701,6 +1893,27 @@ impl<MakerCoin: MmCoin + MakerCoinSwapOpsV2, TakerCoin: MmCoin + TakerCoinSwapOp
     type StateMachine = TakerSwapStateMachine<MakerCoin, TakerCoin>;
 
     async fn on_changed(self: Box<Self>, state_machine: &mut Self::StateMachine) -> StateResult<Self::StateMachine> {
+        if state_machine.use_watchers() {
+            state_machine
+                .send_taker_refund_to_watchers(
+                    &self.taker_payment,
+                    state_machine.taker_payment_locktime(),
+                    self.taker_coin_start_block,
+                    &self.negotiation_data,
+                    false,
+                )
+                .await;
+            state_machine
+                .send_maker_payment_spend_to_watchers(
+                    &self.maker_payment,
+                    self.maker_coin_start_block,
+                    &self.taker_payment,
+                    self.taker_coin_start_block,
+                    &self.negotiation_data,
+                )
+                .await;
+        }
+
         if !state_machine.require_maker_payment_confirm_before_funding_spend {
             let input = ConfirmPaymentInput {
                 payment_tx: self.maker_payment.tx_hex(),
@@ -0,0 +1,361 @@
+use crate::lp_message_service::{MessageResult, MessageServiceTraits};
+use async_trait::async_trait;
+use common::custom_futures::timeout::FutureTimerExt;
+use common::executor::Timer;
+use common::log::warn;
+use common::now_sec;
+use derive_more::Display;
+use hmac::{Hmac, Mac};
+use mm2_event_stream::Event;
+use mm2_net::transport::{slurp_post_json_with_headers, SlurpError};
+use serde_json::Value as Json;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+type HmacSha256 = Hmac<Sha256>;
+
+const fn default_max_retries() -> u32 { 3 }
+
+const fn default_retry_interval() -> f64 { 1. }
+
+const fn default_request_timeout() -> f64 { 10. }
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookCfg {
+    pub url: String,
+    /// The additional headers sent with every request, e.g. `Authorization`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// How many times a failed request is retried.
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// The delay before the first retry in seconds, it's doubled after every retry.
+    #[serde(default = "default_retry_interval")]
+    pub retry_interval: f64,
+    /// How long to wait for the response to a request in seconds, the request is retried once it times out.
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: f64,
+    /// If set, every request is signed with HMAC-SHA256 of `{timestamp}.{body}` using this secret.
+    /// The signature is sent as `sha256=<hex>` in the `X-Webhook-Signature` header,
+    /// the timestamp is sent in the `X-Webhook-Timestamp` header.
+    pub hmac_secret: Option<String>,
+    /// The rooms the messages of which are sent to the webhook, all the rooms if not set.
+    pub rooms: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Display, Serialize, SerializeErrorType)]
+#[serde(tag = "error_type", content = "error_data")]
+pub enum WebhookError {
+    #[display(fmt = "{}", _0)]
+    RequestError(SlurpError),
+    #[display(fmt = "Webhook '{}' responded with status {}: {}", url, status, body)]
+    UnexpectedStatus { url: String, status: u16, body: String },
+    #[display(fmt = "Webhook '{}' didn't respond within {}s", url, timeout)]
+    Timeout { url: String, timeout: f64 },
+    #[display(fmt = "Invalid HMAC secret: {}", _0)]
+    InvalidHmacSecret(String),
+}
+
+impl From<SlurpError> for WebhookError {
+    fn from(err: SlurpError) -> Self { WebhookError::RequestError(err) }
+}
+
+/// Posts the messages as JSON objects to an HTTP endpoint.
+#[derive(Clone)]
+pub struct WebhookClient {
+    cfg: WebhookCfg,
+}
+
+impl WebhookClient {
+    pub fn new(cfg: WebhookCfg) -> Self { WebhookClient { cfg } }
+
+    fn serves_room(&self, room_id: &str) -> bool {
+        self.cfg
+            .rooms
+            .as_ref()
+            .map_or(true, |rooms| rooms.iter().any(|room| room == room_id))
+    }
+
+    /// Posts the `payload` retrying with an exponential backoff on the transport errors, the timeouts
+    /// and on the `408`, `429` and `5xx` statuses.
+    async fn post(&self, payload: Json) -> Result<(), WebhookError> {
+        let body = payload.to_string();
+        let timestamp = now_sec().to_string();
+        let signature = match &self.cfg.hmac_secret {
+            Some(secret) => Some(format!("sha256={}", sign_payload(secret, &timestamp, &body)?)),
+            None => None,
+        };
+
+        let mut headers: Vec<(&str, &str)> = self
+            .cfg
+            .headers
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        headers.push((WEBHOOK_TIMESTAMP_HEADER, &timestamp));
+        if let Some(signature) = &signature {
+            headers.push((WEBHOOK_SIGNATURE_HEADER, signature));
+        }
+
+        let mut retry_interval = self.cfg.retry_interval;
+        let mut attempt = 0;
+        loop {
+            let request = Box::pin(slurp_post_json_with_headers(
+                &self.cfg.url,
+                body.clone(),
+                headers.clone(),
+            ));
+            let error = match request.timeout_secs(self.cfg.request_timeout).await {
+                Ok(Ok((status, _, _))) if status.is_success() => return Ok(()),
+                Ok(Ok((status, _, response))) => {
+                    let error = WebhookError::UnexpectedStatus {
+                        url: self.cfg.url.clone(),
+                        status: status.as_u16(),
+                        body: String::from_utf8_lossy(&response).into_owned(),
+                    };
+                    let is_retriable = status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429;
+                    if !is_retriable {
+                        return Err(error);
+                    }
+                    error
+                },
+                Ok(Err(e)) => WebhookError::from(e.into_inner()),
+                Err(_) => WebhookError::Timeout {
+                    url: self.cfg.url.clone(),
+                    timeout: self.cfg.request_timeout,
+                },
+            };
+
+            if attempt >= self.cfg.max_retries {
+                return Err(error);
+            }
+            attempt += 1;
+            warn!(
+                "Webhook request failed: {}, retrying in {}s ({}/{})",
+                error, retry_interval, attempt, self.cfg.max_retries
+            );
+            Timer::sleep(retry_interval).await;
+            retry_interval *= 2.;
+        }
+    }
+}
+
+/// Returns the hex-encoded HMAC-SHA256 of `{timestamp}.{body}`.
+fn sign_payload(secret: &str, timestamp: &str, body: &str) -> Result<String, WebhookError> {
+    hmac_sha256_hex(secret, &format!("{}.{}", timestamp, body))
+}
+
+fn hmac_sha256_hex(secret: &str, message: &str) -> Result<String, WebhookError> {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).map_err(|e| WebhookError::InvalidHmacSecret(e.to_string()))?;
+    mac.update(message.as_bytes());
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+#[async_trait]
+impl MessageServiceTraits for WebhookClient {
+    async fn send_message(&self, message: String, room_id: &str, disable_notification: bool) -> MessageResult<bool> {
+        if !self.serves_room(room_id) {
+            return Ok(false);
+        }
+        let payload = json!({
+            "room_id": room_id,
+            "message": message,
+            "disable_notification": disable_notification,
+        });
+        self.post(payload).await?;
+        Ok(true)
+    }
+
+    async fn send_event(&self, event: &Event, room_id: &str, disable_notification: bool) -> MessageResult<bool> {
+        if !self.serves_room(room_id) {
+            return Ok(false);
+        }
+        let (event_type, message) = event.get();
+        let payload = json!({
+            "room_id": room_id,
+            "event": {
+                "_type": event_type,
+                "message": message,
+                "id": event.id(),
+            },
+            "disable_notification": disable_notification,
+        });
+        self.post(payload).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod webhook_tests {
+    use super::*;
+    use crate::lp_message_service::MessageError;
+    use common::block_on;
+    use serde_json as json;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// A request received by the test server: the lowercase headers and the body.
+    type ReceivedRequest = (HashMap<String, String>, String);
+
+    /// Serves one request per status of `statuses` responding with these statuses in order.
+    fn spawn_webhook_server(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<ReceivedRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server_received = received.clone();
+        std::thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut headers = HashMap::new();
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let Some((key, value)) = line.trim_end().split_once(": ") else {
+                        break;
+                    };
+                    headers.insert(key.to_lowercase(), value.to_string());
+                }
+                let content_length = headers.get("content-length").map_or(0, |len| len.parse().unwrap());
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                server_received
+                    .lock()
+                    .unwrap()
+                    .push((headers, String::from_utf8(body).unwrap()));
+                let response = format!("HTTP/1.1 {status} Test\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok");
+                (&stream).write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    fn test_client(url: String, hmac_secret: Option<&str>) -> WebhookClient {
+        WebhookClient::new(
+            json::from_value(json!({
+                "url": url,
+                "max_retries": 2,
+                "retry_interval": 0.01,
+                "hmac_secret": hmac_secret,
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn webhook_cfg(rooms: Option<Vec<String>>) -> WebhookCfg {
+        json::from_value(json!({
+            "url": "http://localhost:8080/hook",
+            "rooms": rooms,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_webhook_cfg_defaults() {
+        let cfg = webhook_cfg(None);
+        assert!(cfg.headers.is_empty());
+        assert_eq!(cfg.max_retries, 3);
+        assert_eq!(cfg.retry_interval, 1.);
+        assert_eq!(cfg.request_timeout, 10.);
+        assert!(cfg.hmac_secret.is_none());
+    }
+
+    #[test]
+    fn test_webhook_serves_room() {
+        let client = WebhookClient::new(webhook_cfg(None));
+        assert!(client.serves_room("maker_bot"));
+
+        let client = WebhookClient::new(webhook_cfg(Some(vec!["maker_bot".to_string()])));
+        assert!(client.serves_room("maker_bot"));
+        assert!(!client.serves_room("default"));
+    }
+
+    #[test]
+    fn test_webhook_retries_server_errors() {
+        let (url, received) = spawn_webhook_server(vec![500, 200]);
+        let client = test_client(url, Some("secret"));
+        let event = Event::new("SWAP_STATUS".to_string(), json!({"uuid": "1"}));
+        assert!(block_on(client.send_event(&event, "default", false)).unwrap());
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        let payload: Json = json::from_str(body).unwrap();
+        assert_eq!(payload["event"]["_type"], "SWAP_STATUS");
+        assert_eq!(payload["event"]["message"], json!({"uuid": "1"}));
+        let timestamp = &headers[&WEBHOOK_TIMESTAMP_HEADER.to_lowercase()];
+        let expected_signature = format!("sha256={}", sign_payload("secret", timestamp, body).unwrap());
+        assert_eq!(headers[&WEBHOOK_SIGNATURE_HEADER.to_lowercase()], expected_signature);
+    }
+
+    #[test]
+    fn test_webhook_doesnt_retry_client_errors() {
+        let (url, received) = spawn_webhook_server(vec![400]);
+        let client = test_client(url, None);
+        let err = block_on(client.send_message("hello".to_string(), "default", false))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(
+            err,
+            MessageError::WebhookError(WebhookError::UnexpectedStatus { status: 400, .. })
+        ));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(!received[0].0.contains_key(&WEBHOOK_SIGNATURE_HEADER.to_lowercase()));
+    }
+
+    #[test]
+    fn test_webhook_gives_up_after_max_retries() {
+        let (url, received) = spawn_webhook_server(vec![503, 503, 503]);
+        let client = test_client(url, None);
+        assert!(block_on(client.send_message("hello".to_string(), "default", false)).is_err());
+        // The first attempt and two retries.
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_webhook_request_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        // Accepts the connections and never responds.
+        std::thread::spawn(move || {
+            let connections: Vec<_> = listener.incoming().take(2).collect();
+            std::thread::sleep(std::time::Duration::from_secs(5));
+            drop(connections);
+        });
+        let client = WebhookClient::new(
+            json::from_value(json!({
+                "url": url,
+                "max_retries": 1,
+                "retry_interval": 0.01,
+                "request_timeout": 0.2,
+            }))
+            .unwrap(),
+        );
+        let err = block_on(client.send_message("hello".to_string(), "default", false))
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(err, MessageError::WebhookError(WebhookError::Timeout { .. })));
+    }
+
+    #[test]
+    fn test_hmac_sha256_hex() {
+        let signature = hmac_sha256_hex("key", "The quick brown fox jumps over the lazy dog").unwrap();
+        assert_eq!(
+            signature,
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(
+            sign_payload("key", "1700000000", "{}").unwrap(),
+            hmac_sha256_hex("key", "1700000000.{}").unwrap()
+        );
+    }
+}
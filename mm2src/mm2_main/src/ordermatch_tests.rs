@@ -40,6 +40,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let request = TakerRequest {
@@ -80,6 +81,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let request = TakerRequest {
@@ -120,6 +122,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let request = TakerRequest {
@@ -160,6 +163,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let request = TakerRequest {
@@ -200,6 +204,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let request = TakerRequest {
@@ -240,6 +245,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let request = TakerRequest {
@@ -282,6 +288,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
     let request = TakerRequest {
         base: "KMD".to_owned(),
@@ -324,6 +331,7 @@ fn test_match_maker_order_and_taker_request() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
     let request = TakerRequest {
         base: "REL".to_owned(),
@@ -397,6 +405,7 @@ fn test_maker_order_available_amount() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
     maker.matches.insert(new_uuid(), MakerMatch {
         request: TakerRequest {
@@ -1017,6 +1026,7 @@ fn prepare_for_cancel_by(ctx: &MmArc) -> mpsc::Receiver<AdexBehaviourCmd> {
             rel_orderbook_ticker: None,
             p2p_privkey: None,
             swap_version: SwapVersion::default(),
+            expires_at: None,
         },
         None,
     );
@@ -1040,6 +1050,7 @@ fn prepare_for_cancel_by(ctx: &MmArc) -> mpsc::Receiver<AdexBehaviourCmd> {
             rel_orderbook_ticker: None,
             p2p_privkey: None,
             swap_version: SwapVersion::default(),
+            expires_at: None,
         },
         None,
     );
@@ -1063,6 +1074,7 @@ fn prepare_for_cancel_by(ctx: &MmArc) -> mpsc::Receiver<AdexBehaviourCmd> {
             rel_orderbook_ticker: None,
             p2p_privkey: None,
             swap_version: SwapVersion::default(),
+            expires_at: None,
         },
         None,
     );
@@ -1255,6 +1267,7 @@ fn test_maker_order_was_updated() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
     let mut update_msg = MakerOrderUpdated::new(maker_order.uuid);
     update_msg.with_new_price(BigRational::from_integer(2.into()));
@@ -3265,6 +3278,7 @@ fn test_maker_order_balance_loops() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     let morty_order = MakerOrder {
@@ -3285,6 +3299,7 @@ fn test_maker_order_balance_loops() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     assert!(!maker_orders_ctx.balance_loop_exists(rick_ticker));
@@ -3318,6 +3333,7 @@ fn test_maker_order_balance_loops() {
         rel_orderbook_ticker: None,
         p2p_privkey: None,
         swap_version: SwapVersion::default(),
+        expires_at: None,
     };
 
     maker_orders_ctx.add_order(ctx.weak(), rick_order_2.clone(), None);
@@ -3335,3 +3351,71 @@ fn test_maker_order_balance_loops() {
     assert!(!maker_orders_ctx.balance_loop_exists(morty_ticker));
     assert_eq!(*maker_orders_ctx.count_by_tickers.get(morty_ticker).unwrap(), 0);
 }
+
+#[test]
+fn test_good_till_time_orders_expiry() {
+    let base = MmCoinEnum::Test(TestCoin::new("BASE"));
+    let rel = MmCoinEnum::Test(TestCoin::new("REL"));
+    let now = now_sec();
+
+    let maker_builder = || {
+        MakerOrderBuilder::new(&base, &rel)
+            .with_max_base_vol(1.into())
+            .with_price(1.into())
+            .with_conf_settings(OrderConfirmationsSettings::default())
+    };
+    let res = maker_builder().with_expires_at(Some(now - 1)).build();
+    assert!(matches!(res, Err(MakerOrderBuildError::ExpirationTimeInPast { .. })));
+
+    let mut maker_order = maker_builder()
+        .with_expires_at(Some(now + 60))
+        .build()
+        .map_err(|e| e.to_string())
+        .unwrap();
+    assert!(!maker_order.is_expired(now));
+    assert!(maker_order.is_expired(now + 60));
+    maker_order.expires_at = None;
+    assert!(!maker_order.is_expired(u64::MAX));
+
+    let taker_builder = |expires_at| {
+        TakerOrderBuilder::new(&base, &rel)
+            .with_base_amount(1.into())
+            .with_rel_amount(1.into())
+            .with_sender_pubkey(H256Json::from([1; 32]))
+            .with_conf_settings(OrderConfirmationsSettings::default())
+            .with_order_type(OrderType::GoodTillTime { expires_at })
+    };
+    let res = taker_builder(now - 1).build();
+    assert!(matches!(res, Err(TakerOrderBuildError::ExpirationTimeInPast { .. })));
+
+    let mut taker_order = taker_builder(now + 60).build().map_err(|e| e.to_string()).unwrap();
+    assert!(!taker_order.is_expired(now));
+    assert!(taker_order.is_expired(now + 60));
+
+    // A matched taker order isn't cancelled even after its expiration time.
+    taker_order.matches.insert(new_uuid(), TakerMatch {
+        last_updated: now_ms(),
+        reserved: MakerReserved {
+            base: "BASE".into(),
+            rel: "REL".into(),
+            base_amount: 1.into(),
+            rel_amount: 1.into(),
+            sender_pubkey: H256Json::default(),
+            dest_pub_key: H256Json::default(),
+            maker_order_uuid: new_uuid(),
+            taker_order_uuid: taker_order.request.uuid,
+            conf_settings: None,
+            base_protocol_info: None,
+            rel_protocol_info: None,
+            swap_version: SwapVersion::default(),
+        },
+        connect: TakerConnect {
+            sender_pubkey: H256Json::default(),
+            dest_pub_key: H256Json::default(),
+            maker_order_uuid: new_uuid(),
+            taker_order_uuid: taker_order.request.uuid,
+        },
+        connected: None,
+    });
+    assert!(!taker_order.is_expired(now + 60));
+}
@@ -20,6 +20,7 @@
 //  Copyright © 2023 Pampex LTD and TillyHK LTD. All rights reserved.
 //
 
+use crate::rpc::api_keys::ApiKeyScope;
 use crate::rpc::rate_limiter::RateLimitError;
 use common::log::{error, info};
 use common::{err_to_rpc_json_string, err_tp_rpc_json, HttpStatusCode};
@@ -39,14 +40,17 @@ cfg_native! {
     use hyper::{self, Body, Server};
     use futures::channel::oneshot;
     use mm2_net::event_streaming::sse_handler::{handle_sse, SSE_ENDPOINT};
+    use ws_handler::{handle_ws, WS_ENDPOINT};
 }
 
+pub mod api_keys;
 #[path = "rpc/dispatcher/dispatcher.rs"] mod dispatcher;
 #[path = "rpc/dispatcher/dispatcher_legacy.rs"]
 mod dispatcher_legacy;
 pub mod lp_commands;
 mod rate_limiter;
 mod streaming_activations;
+#[cfg(not(target_arch = "wasm32"))] mod ws_handler;
 
 /// Lists the RPC method not requiring the "userpass" authentication.
 /// None is also public to skip auth and display proper error in case of method is missing
@@ -90,6 +94,28 @@ pub enum DispatcherError {
     UserpassIsInvalid(RateLimitError),
     #[display(fmt = "Error parsing mmrpc version: {}", _0)]
     InvalidMmRpcVersion(String),
+    #[display(
+        fmt = "API key '{}' is not permitted to call '{}', the '{}' scope is required",
+        key_name,
+        method,
+        required_scope
+    )]
+    PermissionDenied {
+        key_name: String,
+        method: String,
+        required_scope: ApiKeyScope,
+    },
+    #[display(
+        fmt = "API key '{}' exceeded the rate limit of '{}', retry in {} seconds",
+        key_name,
+        method,
+        retry_after_secs
+    )]
+    RateLimitExceeded {
+        key_name: String,
+        method: String,
+        retry_after_secs: u64,
+    },
 }
 
 impl HttpStatusCode for DispatcherError {
@@ -101,7 +127,9 @@ impl HttpStatusCode for DispatcherError {
             DispatcherError::LocalHostOnly
             | DispatcherError::UserpassIsNotSet
             | DispatcherError::UserpassIsInvalid(_)
-            | DispatcherError::Banned => StatusCode::FORBIDDEN,
+            | DispatcherError::Banned
+            | DispatcherError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+            DispatcherError::RateLimitExceeded { .. } => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 }
@@ -333,6 +361,8 @@ pub extern "C" fn spawn_rpc(ctx_h: u32) {
                 //       Note though that whoever connects via SSE can't enable or disable any events
                 //       without the password as this is done via RPC. (another client with the password can cross-enable events for them though).
                 tx.send(handle_sse(req, ctx_h).await).ok();
+            } else if req.uri().path() == WS_ENDPOINT {
+                tx.send(handle_ws(req, ctx_h, remote_addr).await).ok();
             } else {
                 tx.send(rpc_service(req, ctx_h, remote_addr).await).ok();
             }
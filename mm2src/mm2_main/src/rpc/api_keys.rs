@@ -0,0 +1,418 @@
+//! Named API keys with scopes and per-method rate limits.
+//!
+//! Besides `rpc_password` granting the full access, the RPC may be called with one of the keys configured
+//! in `rpc_api_keys`, the key is passed as `userpass`:
+//!
+//! ```json
+//! "rpc_api_keys": [
+//!     {
+//!         "name": "dashboard",
+//!         "key": "...",
+//!         "scopes": ["read"],
+//!         "rate_limits": {
+//!             "*": { "max_calls": 60, "period_secs": 60 },
+//!             "my_tx_history": { "max_calls": 5, "period_secs": 60 }
+//!         }
+//!     }
+//! ]
+//! ```
+//!
+//! Every method requires a scope, see [`required_scope`]. The calls are counted per key and method.
+
+use super::{DispatcherError, DispatcherResult};
+use derive_more::Display;
+use mm2_err_handle::prelude::*;
+use parking_lot::Mutex as PaMutex;
+use serde_json::{self as json, Value as Json};
+use std::collections::{HashMap, HashSet};
+
+/// The rate limit applied to the methods not listed in the `rate_limits` of a key.
+pub const DEFAULT_RATE_LIMIT_METHOD: &str = "*";
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Querying the balances, the orders, the swaps, the history and the market data.
+    #[display(fmt = "read")]
+    Read,
+    /// Placing and cancelling the orders, running the market maker bot.
+    #[display(fmt = "trade")]
+    Trade,
+    /// Withdrawing and signing the transactions and the messages.
+    #[display(fmt = "withdraw")]
+    Withdraw,
+    /// Exporting the private keys and the mnemonic.
+    #[display(fmt = "secrets")]
+    Secrets,
+    /// The rest of the methods, e.g. enabling the coins or stopping the node. Grants all the other scopes.
+    #[display(fmt = "admin")]
+    Admin,
+}
+
+// Sorted alphanumerically for readability.
+const SECRETS_METHODS: &[&str] = &["change_mnemonic_password", "get_mnemonic", "show_priv_key"];
+
+const WITHDRAW_METHODS: &[&str] = &[
+    "approve_token",
+    "bump_fee",
+    "cancel_eth_transaction",
+    "experimental::staking::claim_rewards",
+    "experimental::staking::delegate",
+    "experimental::staking::undelegate",
+    "finalize_psbt",
+    "lightning::channels::close_channel",
+    "lightning::channels::open_channel",
+    "lightning::payments::send_payment",
+    "lock_unspent",
+    "revoke_token_allowances",
+    "send_raw_transaction",
+    "sign_eth_message",
+    "sign_message",
+    "sign_raw_transaction",
+    "speed_up_eth_transaction",
+    "unlock_unspent",
+    "withdraw",
+    "withdraw_multisig",
+    "withdraw_nft",
+    "withdraw_psbt",
+];
+
+const WITHDRAW_PREFIXES: &[&str] = &[
+    "task::sign_eth_message::",
+    "task::sign_multisig_psbt::",
+    "task::withdraw::",
+];
+
+const TRADE_METHODS: &[&str] = &[
+    "1inch_v6_0_classic_swap_create",
+    "add_trigger_order",
+    "buy",
+    "cancel_all_orders",
+    "cancel_order",
+    "cancel_trigger_order",
+    "recover_funds_of_swap",
+    "sell",
+    "set_swap_transaction_fee_policy",
+    "setprice",
+    "start_simple_market_maker_bot",
+    "stop_simple_market_maker_bot",
+    "update_maker_order",
+];
+
+const READ_METHODS: &[&str] = &[
+    "1inch_v6_0_classic_swap_contract",
+    "1inch_v6_0_classic_swap_liquidity_sources",
+    "1inch_v6_0_classic_swap_quote",
+    "1inch_v6_0_classic_swap_tokens",
+    "account_balance",
+    "active_swaps",
+    "all_swaps_uuids_by_filter",
+    "best_orders",
+    "coins_needed_for_kick_start",
+    "convert_utxo_address",
+    "convertaddress",
+    "experimental::staking::delegations",
+    "experimental::staking::ongoing_undelegations",
+    "experimental::staking::validators",
+    "get_current_mtp",
+    "get_directly_connected_peers",
+    "get_enabled_coins",
+    "get_eth_estimated_fee_per_gas",
+    "get_eth_nodes_health",
+    "get_eth_pending_transactions",
+    "get_gossip_mesh",
+    "get_gossip_peer_topics",
+    "get_gossip_topic_peers",
+    "get_locked_amount",
+    "get_multisig_addresses",
+    "get_my_address",
+    "get_my_peer_id",
+    "get_nft_list",
+    "get_nft_metadata",
+    "get_nft_transfers",
+    "get_public_key",
+    "get_public_key_hash",
+    "get_raw_transaction",
+    "get_relay_mesh",
+    "get_swap_transaction_fee_policy",
+    "get_token_allowance",
+    "get_token_info",
+    "get_trade_fee",
+    "ibc_chains",
+    "ibc_transfer_channels",
+    "kmd_rewards_info",
+    "list_banned_pubkeys",
+    "list_unspent",
+    "max_maker_vol",
+    "max_taker_vol",
+    "metrics",
+    "min_trading_vol",
+    "my_balance",
+    "my_orders",
+    "my_recent_swaps",
+    "my_swap_status",
+    "my_trigger_orders",
+    "my_tx_history",
+    "order_status",
+    "orderbook",
+    "orderbook_depth",
+    "orders_history_by_filter",
+    "simple_market_maker_bot_pnl",
+    "trade_preimage",
+    "trezor_connection_status",
+    "validateaddress",
+    "verify_eth_message",
+    "verify_message",
+    "version",
+    "z_coin_tx_history",
+];
+
+const READ_PREFIXES: &[&str] = &["stream::", "task::get_token_allowances::"];
+
+/// Returns the scope required to call the `method`, the methods not classified require [`ApiKeyScope::Admin`].
+pub fn required_scope(method: &str) -> ApiKeyScope {
+    let has_prefix = |prefixes: &[&str]| prefixes.iter().any(|prefix| method.starts_with(prefix));
+    if SECRETS_METHODS.contains(&method) {
+        ApiKeyScope::Secrets
+    } else if WITHDRAW_METHODS.contains(&method) || has_prefix(WITHDRAW_PREFIXES) {
+        ApiKeyScope::Withdraw
+    } else if TRADE_METHODS.contains(&method) {
+        ApiKeyScope::Trade
+    } else if READ_METHODS.contains(&method) || has_prefix(READ_PREFIXES) {
+        ApiKeyScope::Read
+    } else {
+        ApiKeyScope::Admin
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateLimitCfg {
+    pub max_calls: u32,
+    pub period_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiKeyCfg {
+    pub name: String,
+    pub key: String,
+    pub scopes: HashSet<ApiKeyScope>,
+    /// The rate limits by the method name, the [`DEFAULT_RATE_LIMIT_METHOD`] limit applies to the rest of the methods.
+    #[serde(default)]
+    pub rate_limits: HashMap<String, RateLimitCfg>,
+}
+
+impl ApiKeyCfg {
+    fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&ApiKeyScope::Admin) || self.scopes.contains(&scope)
+    }
+
+    fn rate_limit(&self, method: &str) -> Option<&RateLimitCfg> {
+        self.rate_limits
+            .get(method)
+            .or_else(|| self.rate_limits.get(DEFAULT_RATE_LIMIT_METHOD))
+    }
+}
+
+struct CallWindow {
+    started_at: u64,
+    calls: u32,
+}
+
+#[derive(Default)]
+pub struct ApiKeys {
+    keys: Vec<ApiKeyCfg>,
+    /// The calls made in the current rate limit window by the key name and the method.
+    calls: PaMutex<HashMap<(String, String), CallWindow>>,
+}
+
+impl ApiKeys {
+    /// Parses and validates the `rpc_api_keys` of the MM2 config.
+    pub fn from_conf(conf: &Json) -> Result<ApiKeys, String> {
+        if conf["rpc_api_keys"].is_null() {
+            return Ok(ApiKeys::default());
+        }
+        let keys: Vec<ApiKeyCfg> = json::from_value(conf["rpc_api_keys"].clone()).map_err(|e| e.to_string())?;
+
+        let mut names = HashSet::new();
+        let mut secrets = HashSet::new();
+        for api_key in keys.iter() {
+            if api_key.key.is_empty() {
+                return Err(format!("API key '{}' is empty", api_key.name));
+            }
+            if conf["rpc_password"].as_str() == Some(api_key.key.as_str()) {
+                return Err(format!("API key '{}' is the same as 'rpc_password'", api_key.name));
+            }
+            if !names.insert(api_key.name.as_str()) {
+                return Err(format!("API key name '{}' is duplicated", api_key.name));
+            }
+            if !secrets.insert(api_key.key.as_str()) {
+                return Err(format!("API key '{}' is the same as another key", api_key.name));
+            }
+            if let Some((method, _)) = api_key.rate_limits.iter().find(|(_, limit)| limit.period_secs == 0) {
+                return Err(format!(
+                    "API key '{}' has the zero rate limit period for '{}'",
+                    api_key.name, method
+                ));
+            }
+        }
+
+        Ok(ApiKeys {
+            keys,
+            calls: PaMutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the API key matching the `userpass`.
+    pub fn find(&self, userpass: &str) -> Option<&ApiKeyCfg> {
+        self.keys.iter().find(|api_key| api_key.key == userpass)
+    }
+
+    /// Checks the `api_key` is permitted to call the `method` and counts the call against its rate limit.
+    pub fn authorize(&self, api_key: &ApiKeyCfg, method: &str, now: u64) -> DispatcherResult<()> {
+        let required_scope = required_scope(method);
+        if !api_key.has_scope(required_scope) {
+            return MmError::err(DispatcherError::PermissionDenied {
+                key_name: api_key.name.clone(),
+                method: method.to_owned(),
+                required_scope,
+            });
+        }
+
+        let Some(limit) = api_key.rate_limit(method) else {
+            return Ok(());
+        };
+        let mut calls = self.calls.lock();
+        let window = calls
+            .entry((api_key.name.clone(), method.to_owned()))
+            .or_insert(CallWindow {
+                started_at: now,
+                calls: 0,
+            });
+        let window_end = window.started_at + limit.period_secs;
+        if now >= window_end {
+            *window = CallWindow {
+                started_at: now,
+                calls: 0,
+            };
+        } else if window.calls >= limit.max_calls {
+            return MmError::err(DispatcherError::RateLimitExceeded {
+                key_name: api_key.name.clone(),
+                method: method.to_owned(),
+                retry_after_secs: window_end - now,
+            });
+        }
+        window.calls += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_keys() -> ApiKeys {
+        let conf = json!({
+            "rpc_password": "password",
+            "rpc_api_keys": [
+                {
+                    "name": "dashboard",
+                    "key": "dashboard_key",
+                    "scopes": ["read"],
+                    "rate_limits": {
+                        "*": { "max_calls": 2, "period_secs": 60 },
+                        "my_tx_history": { "max_calls": 1, "period_secs": 10 },
+                    },
+                },
+                { "name": "bot", "key": "bot_key", "scopes": ["read", "trade"] },
+                { "name": "admin", "key": "admin_key", "scopes": ["admin"] },
+            ],
+        });
+        ApiKeys::from_conf(&conf).unwrap()
+    }
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(required_scope("my_balance"), ApiKeyScope::Read);
+        assert_eq!(required_scope("stream::balance::enable"), ApiKeyScope::Read);
+        assert_eq!(required_scope("task::get_token_allowances::status"), ApiKeyScope::Read);
+        assert_eq!(required_scope("setprice"), ApiKeyScope::Trade);
+        assert_eq!(required_scope("task::withdraw::init"), ApiKeyScope::Withdraw);
+        assert_eq!(required_scope("speed_up_eth_transaction"), ApiKeyScope::Withdraw);
+        assert_eq!(required_scope("task::sign_eth_message::init"), ApiKeyScope::Withdraw);
+        assert_eq!(required_scope("show_priv_key"), ApiKeyScope::Secrets);
+        assert_eq!(required_scope("stop"), ApiKeyScope::Admin);
+        assert_eq!(required_scope("unknown_method"), ApiKeyScope::Admin);
+    }
+
+    #[test]
+    fn test_api_key_scopes() {
+        let api_keys = api_keys();
+        assert!(api_keys.find("password").is_none());
+
+        let bot = api_keys.find("bot_key").unwrap();
+        api_keys.authorize(bot, "setprice", 0).unwrap();
+        api_keys.authorize(bot, "orderbook", 0).unwrap();
+        let error = api_keys.authorize(bot, "withdraw", 0).unwrap_err().into_inner();
+        assert!(matches!(error, DispatcherError::PermissionDenied {
+            required_scope: ApiKeyScope::Withdraw,
+            ..
+        }));
+
+        let admin = api_keys.find("admin_key").unwrap();
+        api_keys.authorize(admin, "get_mnemonic", 0).unwrap();
+        api_keys.authorize(admin, "stop", 0).unwrap();
+    }
+
+    #[test]
+    fn test_api_key_rate_limits() {
+        let api_keys = api_keys();
+        let dashboard = api_keys.find("dashboard_key").unwrap();
+
+        api_keys.authorize(dashboard, "my_tx_history", 100).unwrap();
+        let error = api_keys
+            .authorize(dashboard, "my_tx_history", 105)
+            .unwrap_err()
+            .into_inner();
+        assert!(matches!(error, DispatcherError::RateLimitExceeded {
+            retry_after_secs: 5,
+            ..
+        }));
+        api_keys.authorize(dashboard, "my_tx_history", 110).unwrap();
+
+        // The default limit is counted per method.
+        api_keys.authorize(dashboard, "my_balance", 100).unwrap();
+        api_keys.authorize(dashboard, "my_balance", 100).unwrap();
+        api_keys.authorize(dashboard, "orderbook", 100).unwrap();
+        assert!(api_keys.authorize(dashboard, "my_balance", 159).is_err());
+        api_keys.authorize(dashboard, "my_balance", 160).unwrap();
+    }
+
+    #[test]
+    fn test_api_keys_validation() {
+        let conf = json!({
+            "rpc_password": "password",
+            "rpc_api_keys": [{ "name": "key", "key": "password", "scopes": ["read"] }],
+        });
+        assert!(ApiKeys::from_conf(&conf).is_err());
+
+        let conf = json!({
+            "rpc_api_keys": [
+                { "name": "key", "key": "key1", "scopes": ["read"] },
+                { "name": "key", "key": "key2", "scopes": ["read"] },
+            ],
+        });
+        assert!(ApiKeys::from_conf(&conf).is_err());
+
+        let conf = json!({
+            "rpc_api_keys": [{
+                "name": "key",
+                "key": "key",
+                "scopes": ["read"],
+                "rate_limits": { "*": { "max_calls": 1, "period_secs": 0 } },
+            }],
+        });
+        assert!(ApiKeys::from_conf(&conf).is_err());
+
+        assert!(ApiKeys::from_conf(&json!({})).unwrap().find("").is_none());
+    }
+}
@@ -4,14 +4,18 @@ use crate::lp_healthcheck::peer_connection_healthcheck_rpc;
 use crate::lp_native_dex::init_hw::{cancel_init_trezor, init_trezor, init_trezor_status, init_trezor_user_action};
 #[cfg(target_arch = "wasm32")]
 use crate::lp_native_dex::init_metamask::{cancel_connect_metamask, connect_metamask, connect_metamask_status};
-use crate::lp_ordermatch::{best_orders_rpc_v2, orderbook_rpc_v2, start_simple_market_maker_bot,
+use crate::lp_ordermatch::{add_trigger_order_rpc, best_orders_rpc_v2, cancel_trigger_order_rpc, my_trigger_orders_rpc,
+                           orderbook_rpc_v2, simple_market_maker_bot_pnl, start_simple_market_maker_bot,
                            stop_simple_market_maker_bot};
 use crate::lp_stats::{add_node_to_version_stat, remove_node_from_version_stat, start_version_stat_collection,
                       stop_version_stat_collection, update_version_stat_collection};
 use crate::lp_swap::swap_v2_rpcs::{active_swaps_rpc, my_recent_swaps_rpc, my_swap_status_rpc};
-use crate::lp_swap::{get_locked_amount_rpc, max_maker_vol, recreate_swap_data, trade_preimage_rpc};
+use crate::lp_swap::{ban_pubkey_v2_rpc, get_locked_amount_rpc, list_banned_pubkeys_v2_rpc, max_maker_vol,
+                     recreate_swap_data, trade_preimage_rpc, unban_pubkeys_v2_rpc};
 use crate::lp_wallet::{change_mnemonic_password, get_mnemonic_rpc, get_wallet_names_rpc};
+use crate::rpc::lp_commands::bump_fee::bump_fee_rpc;
 use crate::rpc::lp_commands::db_id::get_shared_db_id;
+use crate::rpc::lp_commands::eth_pending_txs::{cancel_eth_transaction, speed_up_eth_transaction};
 use crate::rpc::lp_commands::one_inch::rpcs::{one_inch_v6_0_classic_swap_contract_rpc,
                                               one_inch_v6_0_classic_swap_create_rpc,
                                               one_inch_v6_0_classic_swap_liquidity_sources_rpc,
@@ -19,25 +23,36 @@ use crate::rpc::lp_commands::one_inch::rpcs::{one_inch_v6_0_classic_swap_contrac
                                               one_inch_v6_0_classic_swap_tokens_rpc};
 use crate::rpc::lp_commands::pubkey::*;
 use crate::rpc::lp_commands::tokens::get_token_info;
-use crate::rpc::lp_commands::tokens::{approve_token_rpc, get_token_allowance_rpc};
+use crate::rpc::lp_commands::tokens::{approve_token_rpc, get_token_allowance_rpc, init_get_token_allowances,
+                                      revoke_token_allowances_rpc};
 use crate::rpc::lp_commands::trezor::trezor_connection_status;
-use crate::rpc::rate_limiter::{process_rate_limit, RateLimitContext};
+use crate::rpc::rate_limiter::{authorize_api_key, RateLimitContext};
 use coins::eth::fee_estimation::rpc::get_eth_estimated_fee_per_gas;
+use coins::eth::node_health::rpc::get_eth_nodes_health;
+use coins::eth::nonce_tracker::rpc::get_eth_pending_transactions;
 use coins::eth::EthCoin;
 use coins::my_tx_history_v2::my_tx_history_v2_rpc;
 use coins::rpc_command::tendermint::{ibc_chains, ibc_transfer_channels};
 use coins::rpc_command::{account_balance::account_balance,
+                         coin_control::{list_unspent_rpc, lock_unspent_rpc, unlock_unspent_rpc},
                          get_current_mtp::get_current_mtp_rpc,
                          get_enabled_coins::get_enabled_coins,
                          get_new_address::{cancel_get_new_address, get_new_address, init_get_new_address,
                                            init_get_new_address_status, init_get_new_address_user_action},
+                         get_token_allowances::{cancel_get_token_allowances, get_token_allowances_status},
                          init_account_balance::{cancel_account_balance, init_account_balance,
                                                 init_account_balance_status},
                          init_create_account::{cancel_create_new_account, init_create_new_account,
                                                init_create_new_account_status, init_create_new_account_user_action},
                          init_scan_for_new_addresses::{cancel_scan_for_new_addresses, init_scan_for_new_addresses,
                                                        init_scan_for_new_addresses_status},
-                         init_withdraw::{cancel_withdraw, init_withdraw, withdraw_status, withdraw_user_action}};
+                         init_withdraw::{cancel_withdraw, init_withdraw, withdraw_status, withdraw_user_action},
+                         multisig::{cancel_sign_multisig_psbt, get_multisig_addresses_rpc, init_sign_multisig_psbt,
+                                    sign_multisig_psbt_status, sign_multisig_psbt_user_action, withdraw_multisig_rpc},
+                         psbt::{finalize_psbt_rpc, withdraw_psbt_rpc},
+                         sign_eth_message::{cancel_sign_eth_message, init_sign_eth_message, sign_eth_message_rpc,
+                                            sign_eth_message_status, sign_eth_message_user_action,
+                                            verify_eth_message_rpc}};
 #[cfg(feature = "enable-sia")] use coins::siacoin::SiaCoin;
 use coins::tendermint::{TendermintCoin, TendermintToken};
 use coins::utxo::bch::BchCoin;
@@ -143,7 +158,7 @@ async fn auth(request: &MmRpcRequest, ctx: &MmArc, client: &SocketAddr) -> Dispa
     });
     match request.userpass {
         Some(ref userpass) if userpass == rpc_password => Ok(()),
-        Some(_) => Err(process_rate_limit(ctx, client).await),
+        Some(ref userpass) => authorize_api_key(ctx, client, userpass, &request.method).await,
         None => MmError::err(DispatcherError::UserpassIsNotSet),
     }
 }
@@ -195,9 +210,13 @@ async fn dispatcher_v2(request: MmRpcRequest, ctx: MmArc) -> DispatcherResult<Re
         "account_balance" => handle_mmrpc(ctx, request, account_balance).await,
         "active_swaps" => handle_mmrpc(ctx, request, active_swaps_rpc).await,
         "add_node_to_version_stat" => handle_mmrpc(ctx, request, add_node_to_version_stat).await,
+        "add_trigger_order" => handle_mmrpc(ctx, request, add_trigger_order_rpc).await,
         "approve_token" => handle_mmrpc(ctx, request, approve_token_rpc).await,
+        "ban_pubkey" => handle_mmrpc(ctx, request, ban_pubkey_v2_rpc).await,
         "get_token_allowance" => handle_mmrpc(ctx, request, get_token_allowance_rpc).await,
         "best_orders" => handle_mmrpc(ctx, request, best_orders_rpc_v2).await,
+        "bump_fee" => handle_mmrpc(ctx, request, bump_fee_rpc).await,
+        "cancel_trigger_order" => handle_mmrpc(ctx, request, cancel_trigger_order_rpc).await,
         "clear_nft_db" => handle_mmrpc(ctx, request, clear_nft_db).await,
         "enable_bch_with_tokens" => handle_mmrpc(ctx, request, enable_platform_coin_with_tokens::<BchCoin>).await,
         "enable_slp" => handle_mmrpc(ctx, request, enable_token::<SlpToken>).await,
@@ -208,10 +227,12 @@ async fn dispatcher_v2(request: MmRpcRequest, ctx: MmArc) -> DispatcherResult<Re
             handle_mmrpc(ctx, request, enable_platform_coin_with_tokens::<TendermintCoin>).await
         },
         "enable_tendermint_token" => handle_mmrpc(ctx, request, enable_token::<TendermintToken>).await,
+        "finalize_psbt" => handle_mmrpc(ctx, request, finalize_psbt_rpc).await,
         "get_current_mtp" => handle_mmrpc(ctx, request, get_current_mtp_rpc).await,
         "get_enabled_coins" => handle_mmrpc(ctx, request, get_enabled_coins).await,
         "get_locked_amount" => handle_mmrpc(ctx, request, get_locked_amount_rpc).await,
         "get_mnemonic" => handle_mmrpc(ctx, request, get_mnemonic_rpc).await,
+        "get_multisig_addresses" => handle_mmrpc(ctx, request, get_multisig_addresses_rpc).await,
         "get_my_address" => handle_mmrpc(ctx, request, get_my_address).await,
         "get_new_address" => handle_mmrpc(ctx, request, get_new_address).await,
         "get_nft_list" => handle_mmrpc(ctx, request, get_nft_list).await,
@@ -223,32 +244,48 @@ async fn dispatcher_v2(request: MmRpcRequest, ctx: MmArc) -> DispatcherResult<Re
         "get_shared_db_id" => handle_mmrpc(ctx, request, get_shared_db_id).await,
         "get_token_info" => handle_mmrpc(ctx, request, get_token_info).await,
         "get_wallet_names" => handle_mmrpc(ctx, request, get_wallet_names_rpc).await,
+        "list_banned_pubkeys" => handle_mmrpc(ctx, request, list_banned_pubkeys_v2_rpc).await,
+        "list_unspent" => handle_mmrpc(ctx, request, list_unspent_rpc).await,
+        "lock_unspent" => handle_mmrpc(ctx, request, lock_unspent_rpc).await,
         "max_maker_vol" => handle_mmrpc(ctx, request, max_maker_vol).await,
         "my_recent_swaps" => handle_mmrpc(ctx, request, my_recent_swaps_rpc).await,
         "my_swap_status" => handle_mmrpc(ctx, request, my_swap_status_rpc).await,
+        "my_trigger_orders" => handle_mmrpc(ctx, request, my_trigger_orders_rpc).await,
         "my_tx_history" => handle_mmrpc(ctx, request, my_tx_history_v2_rpc).await,
         "orderbook" => handle_mmrpc(ctx, request, orderbook_rpc_v2).await,
         "recreate_swap_data" => handle_mmrpc(ctx, request, recreate_swap_data).await,
         "refresh_nft_metadata" => handle_mmrpc(ctx, request, refresh_nft_metadata).await,
         "remove_node_from_version_stat" => handle_mmrpc(ctx, request, remove_node_from_version_stat).await,
+        "revoke_token_allowances" => handle_mmrpc(ctx, request, revoke_token_allowances_rpc).await,
         "sign_message" => handle_mmrpc(ctx, request, sign_message).await,
+        "sign_eth_message" => handle_mmrpc(ctx, request, sign_eth_message_rpc).await,
         "sign_raw_transaction" => handle_mmrpc(ctx, request, sign_raw_transaction).await,
+        "simple_market_maker_bot_pnl" => handle_mmrpc(ctx, request, simple_market_maker_bot_pnl).await,
         "start_simple_market_maker_bot" => handle_mmrpc(ctx, request, start_simple_market_maker_bot).await,
         "start_version_stat_collection" => handle_mmrpc(ctx, request, start_version_stat_collection).await,
         "stop_simple_market_maker_bot" => handle_mmrpc(ctx, request, stop_simple_market_maker_bot).await,
         "stop_version_stat_collection" => handle_mmrpc(ctx, request, stop_version_stat_collection).await,
         "trade_preimage" => handle_mmrpc(ctx, request, trade_preimage_rpc).await,
         "trezor_connection_status" => handle_mmrpc(ctx, request, trezor_connection_status).await,
+        "unban_pubkeys" => handle_mmrpc(ctx, request, unban_pubkeys_v2_rpc).await,
+        "unlock_unspent" => handle_mmrpc(ctx, request, unlock_unspent_rpc).await,
         "update_nft" => handle_mmrpc(ctx, request, update_nft).await,
         "change_mnemonic_password" => handle_mmrpc(ctx, request, change_mnemonic_password).await,
         "update_version_stat_collection" => handle_mmrpc(ctx, request, update_version_stat_collection).await,
         "verify_message" => handle_mmrpc(ctx, request, verify_message).await,
+        "verify_eth_message" => handle_mmrpc(ctx, request, verify_eth_message_rpc).await,
         "withdraw" => handle_mmrpc(ctx, request, withdraw).await,
         "ibc_chains" => handle_mmrpc(ctx, request, ibc_chains).await,
         "ibc_transfer_channels" => handle_mmrpc(ctx, request, ibc_transfer_channels).await,
         "peer_connection_healthcheck" => handle_mmrpc(ctx, request, peer_connection_healthcheck_rpc).await,
         "withdraw_nft" => handle_mmrpc(ctx, request, withdraw_nft).await,
+        "withdraw_multisig" => handle_mmrpc(ctx, request, withdraw_multisig_rpc).await,
+        "withdraw_psbt" => handle_mmrpc(ctx, request, withdraw_psbt_rpc).await,
         "get_eth_estimated_fee_per_gas" => handle_mmrpc(ctx, request, get_eth_estimated_fee_per_gas).await,
+        "get_eth_nodes_health" => handle_mmrpc(ctx, request, get_eth_nodes_health).await,
+        "get_eth_pending_transactions" => handle_mmrpc(ctx, request, get_eth_pending_transactions).await,
+        "speed_up_eth_transaction" => handle_mmrpc(ctx, request, speed_up_eth_transaction).await,
+        "cancel_eth_transaction" => handle_mmrpc(ctx, request, cancel_eth_transaction).await,
         "get_swap_transaction_fee_policy" => handle_mmrpc(ctx, request, get_swap_transaction_fee_policy).await,
         "set_swap_transaction_fee_policy" => handle_mmrpc(ctx, request, set_swap_transaction_fee_policy).await,
         "send_asked_data" => handle_mmrpc(ctx, request, send_asked_data_rpc).await,
@@ -334,9 +371,20 @@ async fn rpc_task_dispatcher(
         "get_new_address::init" => handle_mmrpc(ctx, request, init_get_new_address).await,
         "get_new_address::status" => handle_mmrpc(ctx, request, init_get_new_address_status).await,
         "get_new_address::user_action" => handle_mmrpc(ctx, request, init_get_new_address_user_action).await,
+        "get_token_allowances::cancel" => handle_mmrpc(ctx, request, cancel_get_token_allowances).await,
+        "get_token_allowances::init" => handle_mmrpc(ctx, request, init_get_token_allowances).await,
+        "get_token_allowances::status" => handle_mmrpc(ctx, request, get_token_allowances_status).await,
         "scan_for_new_addresses::cancel" => handle_mmrpc(ctx, request, cancel_scan_for_new_addresses).await,
         "scan_for_new_addresses::init" => handle_mmrpc(ctx, request, init_scan_for_new_addresses).await,
         "scan_for_new_addresses::status" => handle_mmrpc(ctx, request, init_scan_for_new_addresses_status).await,
+        "sign_eth_message::cancel" => handle_mmrpc(ctx, request, cancel_sign_eth_message).await,
+        "sign_eth_message::init" => handle_mmrpc(ctx, request, init_sign_eth_message).await,
+        "sign_eth_message::status" => handle_mmrpc(ctx, request, sign_eth_message_status).await,
+        "sign_eth_message::user_action" => handle_mmrpc(ctx, request, sign_eth_message_user_action).await,
+        "sign_multisig_psbt::cancel" => handle_mmrpc(ctx, request, cancel_sign_multisig_psbt).await,
+        "sign_multisig_psbt::init" => handle_mmrpc(ctx, request, init_sign_multisig_psbt).await,
+        "sign_multisig_psbt::status" => handle_mmrpc(ctx, request, sign_multisig_psbt_status).await,
+        "sign_multisig_psbt::user_action" => handle_mmrpc(ctx, request, sign_multisig_psbt_user_action).await,
         "init_trezor::cancel" => handle_mmrpc(ctx, request, cancel_init_trezor).await,
         "init_trezor::init" => handle_mmrpc(ctx, request, init_trezor).await,
         "init_trezor::status" => handle_mmrpc(ctx, request, init_trezor_status).await,
@@ -1,9 +1,10 @@
-use super::PUBLIC_METHODS;
-use common::HyRes;
+use super::{DispatcherError, DispatcherResult, PUBLIC_METHODS};
+use common::{HttpStatusCode, HyRes};
 use futures::compat::Future01CompatExt;
 use futures::{Future as Future03, FutureExt, TryFutureExt};
 use http::Response;
 use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
 use serde_json::{self as json, Value as Json};
 use std::net::SocketAddr;
 
@@ -14,7 +15,7 @@ use crate::lp_ordermatch::{best_orders_rpc, buy, cancel_all_orders_rpc, cancel_o
 use crate::lp_swap::{active_swaps_rpc, all_swaps_uuids_by_filter, ban_pubkey_rpc, coins_needed_for_kick_start,
                      import_swaps, list_banned_pubkeys_rpc, max_taker_vol, my_recent_swaps_rpc, my_swap_status,
                      recover_funds_of_swap, stats_swap_status, unban_pubkeys_rpc};
-use crate::rpc::rate_limiter::{process_rate_limit, RateLimitContext};
+use crate::rpc::rate_limiter::{authorize_api_key, RateLimitContext};
 use coins::{convert_address, convert_utxo_address, get_enabled_coins, get_trade_fee, kmd_rewards_info, my_tx_history,
             send_raw_transaction, set_required_confirmations, set_requires_notarization, show_priv_key,
             validate_address};
@@ -27,19 +28,31 @@ pub enum DispatcherRes {
     NoMatch(Json),
 }
 
-async fn auth(json: &Json, ctx: &MmArc, client: &SocketAddr) -> Result<(), String> {
-    if !PUBLIC_METHODS.contains(&json["method"].as_str()) {
-        if !json["userpass"].is_string() {
-            return Err("Userpass is not set!".to_string());
-        }
+async fn auth(json: &Json, ctx: &MmArc, client: &SocketAddr) -> DispatcherResult<()> {
+    let method = json["method"].as_str();
+    if !PUBLIC_METHODS.contains(&method) {
+        let Some(userpass) = json["userpass"].as_str() else {
+            return MmError::err(DispatcherError::UserpassIsNotSet);
+        };
 
         if json["userpass"] != ctx.conf["rpc_password"] {
-            return Err(format!("{}", process_rate_limit(ctx, client).await));
+            return authorize_api_key(ctx, client, userpass, method.unwrap_or_default()).await;
         }
     }
     Ok(())
 }
 
+/// The legacy `{"error": ...}` response extended with the `error_type` and `error_data` of the `error`.
+fn structured_error_response(error: MmError<DispatcherError>) -> Response<Vec<u8>> {
+    let error = error.into_inner();
+    let mut body = json::to_value(&error).unwrap_or_else(|_| json!({}));
+    body["error"] = Json::from(error.to_string());
+    Response::builder()
+        .status(error.status_code())
+        .body(body.to_string().into_bytes())
+        .expect("ResponseBuilder::body has not to fail")
+}
+
 /// Using async/await (futures 0.3) in `dispatcher`
 /// will pave the way for porting the remaining system threading code to async/await green threads.
 fn hyres(handler: impl Future03<Output = Result<Response<Vec<u8>>, String>> + Send + 'static) -> HyRes {
@@ -131,7 +144,14 @@ pub async fn process_single_request(
     if rate_limit_ctx.is_banned(client.ip()).await {
         return ERR!("Your ip is banned.");
     }
-    try_s!(auth(&req, &ctx, &client).await);
+    if let Err(e) = auth(&req, &ctx, &client).await {
+        return match e.get_inner() {
+            DispatcherError::PermissionDenied { .. } | DispatcherError::RateLimitExceeded { .. } => {
+                Ok(structured_error_response(e))
+            },
+            _ => ERR!("{}", e),
+        };
+    }
 
     let handler = match dispatcher(req, ctx.clone()) {
         DispatcherRes::Match(handler) => handler,
@@ -0,0 +1,27 @@
+use crate::lp_swap::active_swaps_using_coins;
+use coins::rpc_command::bump_fee::{bump_fee_rpc as coin_bump_fee_rpc, BumpFeeError, BumpFeeMethod, BumpFeeRequest,
+                                   BumpFeeResponse, BumpFeeResult};
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use std::collections::HashSet;
+
+/// Speeds up an unconfirmed transaction of my address.
+///
+/// The swaps track their payments by the transaction hashes that are changed by the RBF replacement,
+/// so CPFP is always used while the swaps of the coin are running and the explicit RBF request is rejected.
+pub async fn bump_fee_rpc(ctx: MmArc, mut req: BumpFeeRequest) -> BumpFeeResult<BumpFeeResponse> {
+    let coins = HashSet::from([req.coin.clone()]);
+    let active_swaps = active_swaps_using_coins(&ctx, &coins).map_to_mm(BumpFeeError::InternalError)?;
+    if !active_swaps.is_empty() {
+        if req.method == Some(BumpFeeMethod::Rbf) {
+            let error = format!(
+                "{} swaps of {} are running, use 'cpfp' to speed up the transaction",
+                active_swaps.len(),
+                req.coin
+            );
+            return MmError::err(BumpFeeError::ReplacementNotAllowed(error));
+        }
+        req.method = Some(BumpFeeMethod::Cpfp);
+    }
+    coin_bump_fee_rpc(ctx, req).await
+}
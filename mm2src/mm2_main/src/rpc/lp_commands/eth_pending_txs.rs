@@ -0,0 +1,41 @@
+use crate::lp_swap::active_swaps_using_coins;
+use coins::eth::nonce_tracker::rpc::{cancel_eth_transaction as coin_cancel_eth_transaction, coins_sharing_address,
+                                     speed_up_eth_transaction as coin_speed_up_eth_transaction, EthPendingTxError,
+                                     ReplaceEthTransactionRequest, ReplaceEthTransactionResponse};
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+
+/// Replaces a pending transaction of my address with the same one paying a higher fee.
+pub async fn speed_up_eth_transaction(
+    ctx: MmArc,
+    req: ReplaceEthTransactionRequest,
+) -> MmResult<ReplaceEthTransactionResponse, EthPendingTxError> {
+    ensure_no_running_swaps(&ctx, &req.coin).await?;
+    coin_speed_up_eth_transaction(ctx, req).await
+}
+
+/// Cancels a pending transaction of my address by replacing it with a 0-value transfer to myself.
+pub async fn cancel_eth_transaction(
+    ctx: MmArc,
+    req: ReplaceEthTransactionRequest,
+) -> MmResult<ReplaceEthTransactionResponse, EthPendingTxError> {
+    ensure_no_running_swaps(&ctx, &req.coin).await?;
+    coin_cancel_eth_transaction(ctx, req).await
+}
+
+/// The swaps track their payments by the transaction hashes that are changed by the replacement,
+/// and a cancelled payment would never be mined. The pending transactions aren't linked to the swaps,
+/// so nothing sent from the address is replaced while the swaps of its platform coin or tokens are running.
+async fn ensure_no_running_swaps(ctx: &MmArc, ticker: &str) -> MmResult<(), EthPendingTxError> {
+    let coins = coins_sharing_address(ctx, ticker).await?;
+    let active_swaps = active_swaps_using_coins(ctx, &coins).map_to_mm(EthPendingTxError::Internal)?;
+    if !active_swaps.is_empty() {
+        let error = format!(
+            "{} swaps using the {} address are running, wait for them to finish",
+            active_swaps.len(),
+            ticker
+        );
+        return MmError::err(EthPendingTxError::ReplacementNotAllowed(error));
+    }
+    Ok(())
+}
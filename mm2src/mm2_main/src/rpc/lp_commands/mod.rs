@@ -1,4 +1,6 @@
+pub(crate) mod bump_fee;
 pub(crate) mod db_id;
+pub(crate) mod eth_pending_txs;
 pub mod legacy;
 pub(crate) mod one_inch;
 pub(crate) mod pubkey;
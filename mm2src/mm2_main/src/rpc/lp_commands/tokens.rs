@@ -1,18 +1,24 @@
 //! This source file is for RPCs specific for EVM platform
 use coins::eth::erc20::{get_erc20_ticker_by_contract_address, get_erc20_token_info, Erc20TokenInfo};
 use coins::eth::valid_addr_from_str;
-use coins::eth::{u256_to_big_decimal, wei_from_big_decimal, EthCoin, Web3RpcError};
+use coins::eth::{u256_to_big_decimal, wei_from_big_decimal, EthCoin, EthCoinType, Web3RpcError};
+use coins::hd_wallet::DisplayAddress;
+use coins::rpc_command::get_token_allowances::{find_allowances_platform_coin, spawn_get_token_allowances_task,
+                                               GetTokenAllowancesRequest, TokenAllowancesError};
 use coins::{lp_coinfind_or_err, CoinFindError, CoinProtocol, MmCoin, MmCoinEnum, NumConversError, Transaction,
             TransactionErr};
 use common::HttpStatusCode;
 use derive_more::Display;
 use enum_derives::EnumFromStringify;
-use ethereum_types::Address as EthAddress;
+use ethereum_types::{Address as EthAddress, U256};
 use futures::compat::Future01CompatExt;
 use http::StatusCode;
 use mm2_core::mm_ctx::MmArc;
 use mm2_err_handle::{map_to_mm::MapToMmResult, mm_error::MmError, prelude::MmResult};
 use mm2_number::BigDecimal;
+use rpc_task::rpc_common::InitRpcTaskResponse;
+use rpc_task::RpcInitReq;
+use trading_api::one_inch_api::client::ApiClient;
 
 #[derive(Deserialize)]
 pub struct TokenInfoRequest {
@@ -171,3 +177,108 @@ async fn find_erc20_eth_coin(ctx: &MmArc, coin: &str) -> Result<EthCoin, MmError
         Err(_) => Err(MmError::new(Erc20CallError::NoSuchCoin { coin: coin.to_string() })),
     }
 }
+
+/// Starts listing the non-zero ERC20 allowances granted by my addresses.
+/// The (token, spender) pairs are taken from the `Approval` events of any token contract
+/// and from the enabled tokens approved to the swap contracts and the 1inch router.
+pub async fn init_get_token_allowances(
+    ctx: MmArc,
+    req: RpcInitReq<GetTokenAllowancesRequest>,
+) -> MmResult<InitRpcTaskResponse, TokenAllowancesError> {
+    let (client_id, req) = (req.client_id, req.inner);
+    let eth_coin = find_allowances_platform_coin(&ctx, &req.coin).await?;
+    let other_spenders = one_inch_spenders(eth_coin.chain_id());
+    spawn_get_token_allowances_task(&ctx, client_id, eth_coin, req, other_spenders)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenAllowanceToRevoke {
+    token_contract: EthAddress,
+    /// My address that granted the allowance, any known address of the HD wallet.
+    owner: EthAddress,
+    spender: EthAddress,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevokeTokenAllowancesRequest {
+    /// The platform coin, e.g. ETH.
+    coin: String,
+    allowances: Vec<TokenAllowanceToRevoke>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeTokenAllowanceResult {
+    token_contract: String,
+    owner: String,
+    spender: String,
+    /// The hash of the `approve(spender, 0)` transaction.
+    /// `None` if the allowance is zero already or the revoke has failed.
+    tx_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Sets the allowances of my addresses to zero one by one, every allowance is revoked by its owner.
+/// A failed revoke doesn't stop the others, its error is returned in the result instead.
+pub async fn revoke_token_allowances_rpc(
+    ctx: MmArc,
+    req: RevokeTokenAllowancesRequest,
+) -> MmResult<Vec<RevokeTokenAllowanceResult>, Erc20CallError> {
+    let eth_coin = find_platform_eth_coin(&ctx, &req.coin).await?;
+
+    let mut results = Vec::with_capacity(req.allowances.len());
+    for TokenAllowanceToRevoke {
+        token_contract,
+        owner,
+        spender,
+    } in req.allowances
+    {
+        let result = revoke_token_allowance(&eth_coin, owner, token_contract, spender).await;
+        let (tx_hash, error) = match result {
+            Ok(tx_hash) => (tx_hash, None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        results.push(RevokeTokenAllowanceResult {
+            token_contract: token_contract.display_address(),
+            owner: owner.display_address(),
+            spender: spender.display_address(),
+            tx_hash,
+            error,
+        });
+    }
+    Ok(results)
+}
+
+async fn revoke_token_allowance(
+    eth_coin: &EthCoin,
+    owner: EthAddress,
+    token_contract: EthAddress,
+    spender: EthAddress,
+) -> MmResult<Option<String>, Erc20CallError> {
+    let allowance = eth_coin.erc20_allowance(token_contract, owner, spender).await?;
+    if allowance.is_zero() {
+        return Ok(None);
+    }
+    let tx = eth_coin
+        .erc20_approve_from(owner, token_contract, spender, U256::zero())
+        .await?;
+    Ok(Some(format!("0x{:02x}", tx.tx_hash_as_bytes())))
+}
+
+/// The 1inch router that `classic_swap` approves the tokens to, if the chain is supported.
+fn one_inch_spenders(chain_id: u64) -> Vec<(&'static str, EthAddress)> {
+    if !ApiClient::is_chain_supported(chain_id) {
+        return Vec::new();
+    }
+    valid_addr_from_str(ApiClient::classic_swap_contract())
+        .map(|router| vec![("1inch_v6_0_router", router)])
+        .unwrap_or_default()
+}
+
+async fn find_platform_eth_coin(ctx: &MmArc, coin: &str) -> Result<EthCoin, MmError<Erc20CallError>> {
+    let eth_coin = find_erc20_eth_coin(ctx, coin).await?;
+    if !matches!(eth_coin.coin_type, EthCoinType::Eth) {
+        return MmError::err(Erc20CallError::CoinNotSupported { coin: coin.to_string() });
+    }
+    Ok(eth_coin)
+}
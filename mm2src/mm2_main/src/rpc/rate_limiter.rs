@@ -1,4 +1,6 @@
-use crate::rpc::DispatcherError;
+use crate::rpc::api_keys::ApiKeys;
+use crate::rpc::{DispatcherError, DispatcherResult};
+use common::now_sec;
 use derive_more::Display;
 use futures::lock::Mutex as AsyncMutex;
 use mm2_core::mm_ctx::from_ctx;
@@ -18,18 +20,23 @@ pub enum RateLimitError {
     NbAttemptsLeft(usize),
 }
 
-#[derive(Default)]
-pub struct RateLimitContext(AsyncMutex<RateInfosRegistry>);
+pub struct RateLimitContext {
+    failed_requests: AsyncMutex<RateInfosRegistry>,
+    pub api_keys: ApiKeys,
+}
 
 impl RateLimitContext {
     pub fn from_ctx(ctx: &MmArc) -> Result<Arc<RateLimitContext>, String> {
         Ok(try_s!(from_ctx(&ctx.rate_limit_ctx, move || {
-            Ok(RateLimitContext::default())
+            Ok(RateLimitContext {
+                failed_requests: AsyncMutex::new(RateInfosRegistry::new()),
+                api_keys: try_s!(ApiKeys::from_conf(&ctx.conf)),
+            })
         })))
     }
 
     pub async fn is_banned(&self, client_ip: IpAddr) -> bool {
-        let rate_infos = self.0.lock().await;
+        let rate_infos = self.failed_requests.lock().await;
         if let Some(limit) = rate_infos.get(&client_ip) {
             return *limit >= LIMIT_FAILED_REQUEST;
         }
@@ -39,7 +46,7 @@ impl RateLimitContext {
 
 pub async fn process_rate_limit(ctx: &MmArc, client: &SocketAddr) -> MmError<DispatcherError> {
     let rate_limit_ctx = RateLimitContext::from_ctx(ctx).unwrap();
-    let mut rate_limit_registry = rate_limit_ctx.0.lock().await;
+    let mut rate_limit_registry = rate_limit_ctx.failed_requests.lock().await;
 
     match rate_limit_registry.get_mut(&client.ip()) {
         Some(limit) => {
@@ -59,3 +66,13 @@ pub async fn process_rate_limit(ctx: &MmArc, client: &SocketAddr) -> MmError<Dis
         },
     }
 }
+
+/// Authorizes the call of the `method` with the `userpass` not matching `rpc_password`.
+/// If the `userpass` isn't an API key either, it's counted as a failed attempt.
+pub async fn authorize_api_key(ctx: &MmArc, client: &SocketAddr, userpass: &str, method: &str) -> DispatcherResult<()> {
+    let rate_limit_ctx = RateLimitContext::from_ctx(ctx).unwrap();
+    match rate_limit_ctx.api_keys.find(userpass) {
+        Some(api_key) => rate_limit_ctx.api_keys.authorize(api_key, method, now_sec()),
+        None => Err(process_rate_limit(ctx, client).await),
+    }
+}
@@ -0,0 +1,441 @@
+//! The WebSocket transport of the RPC server.
+//!
+//! A single socket carries the mmrpc requests and their responses as text frames, as well as the events
+//! of the streamers the client enabled with the `stream::*::enable` requests.
+//! The responses are the same JSON objects the HTTP server responds with, the events are sent as
+//! `{"_type": ..., "message": ..., "id": ...}` objects (`id` is only set if the event journal is enabled).
+//!
+//! The socket is authenticated once on the handshake with the `userpass` query parameter,
+//! so the requests sent over it may omit `userpass`. The browsers may only open the socket from the origin
+//! allowed by `rpccors`, the same as for the HTTP requests.
+//! The `id` query parameter is the streaming client ID of the socket, it's used by the `stream::*` requests
+//! which don't specify `client_id`. A random unused ID is assigned to the socket if `id` isn't passed.
+//! Like with SSE, a reconnecting client may pass `last_event_id` along with its `id` to receive the journaled events
+//! it missed.
+
+use super::rate_limiter::{process_rate_limit, RateLimitContext};
+use super::{process_json_request, response_from_dispatcher_error, DispatcherError, DispatcherResult};
+use common::err_to_rpc_json_string;
+use common::executor::SpawnFuture;
+use common::log::{error, warn};
+use futures::channel::mpsc;
+use futures::{select, FutureExt, SinkExt, StreamExt};
+use http::header::{HeaderName, CONNECTION, ORIGIN, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION,
+                   UPGRADE};
+use http::{Method, Request, Response, StatusCode};
+use hyper::upgrade::Upgraded;
+use hyper::Body;
+use mm2_core::mm_ctx::MmArc;
+use mm2_err_handle::prelude::*;
+use mm2_event_stream::manager::{ClientHandle, StreamingManagerError};
+use mm2_event_stream::Event;
+use mm2_net::event_streaming::sse_handler::query_param;
+use mm2_rpc::mm_protocol::MmRpcVersion;
+use serde_json::{self as json, json, Value as Json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+pub const WS_ENDPOINT: &str = "/ws";
+const WEBSOCKET_VERSION: &str = "13";
+
+/// Upgrades the connection to a WebSocket and serves it in the background.
+pub async fn handle_ws(mut request: Request<Body>, ctx_h: u32, client: SocketAddr) -> Response<Body> {
+    let ctx = match MmArc::from_ffi_handle(ctx_h) {
+        Ok(ctx) => ctx,
+        Err(e) => return plain_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    let Some(accept_key) = accept_key(&request) else {
+        return plain_response(StatusCode::BAD_REQUEST, "Expected a WebSocket upgrade request".to_string());
+    };
+
+    // The browsers don't apply CORS to WebSockets, so the origin of the page opening the socket is checked here.
+    let origin = request
+        .headers()
+        .get(ORIGIN)
+        .map(|origin| origin.to_str().unwrap_or_default());
+    if !is_origin_allowed(&allowed_origin(&ctx), origin) {
+        return plain_response(StatusCode::FORBIDDEN, "Origin is not allowed".to_string());
+    }
+
+    let userpass = match authenticate(&ctx, &request, &client).await {
+        Ok(userpass) => userpass,
+        Err(e) => {
+            let (parts, body) = response_from_dispatcher_error(e, MmRpcVersion::V2, None).into_parts();
+            return Response::from_parts(parts, Body::from(body));
+        },
+    };
+
+    let client_id = match query_param(&request, "id").map(str::parse::<u64>) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => return plain_response(StatusCode::BAD_REQUEST, "Invalid client ID".to_string()),
+        None => None,
+    };
+    let last_event_id = query_param(&request, "last_event_id").and_then(|id| id.trim().parse::<u64>().ok());
+    if last_event_id.is_some() && client_id.is_none() {
+        let message = "The client ID is required to receive the missed events".to_string();
+        return plain_response(StatusCode::BAD_REQUEST, message);
+    }
+
+    // The streaming client is registered before the upgrade to report an ID that is already in use with an HTTP error.
+    // The socket carries the RPC requests only if the event streaming is disabled.
+    let (client_id, streaming_client) = if ctx.event_streaming_configuration().is_some() {
+        match register_streaming_client(&ctx, client_id, last_event_id) {
+            Ok((client_id, streaming_client)) => (client_id, Some(streaming_client)),
+            Err(_) => return plain_response(StatusCode::CONFLICT, "ID already in use".to_string()),
+        }
+    } else {
+        (client_id.unwrap_or_else(rand::random), None)
+    };
+
+    let on_upgrade = hyper::upgrade::on(&mut request);
+    let socket_ctx = ctx.clone();
+    // The socket is served by the context spawner, so it's closed once the context is stopped.
+    ctx.spawner().spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => {
+                let socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                let session = WsSession {
+                    ctx: socket_ctx,
+                    client,
+                    client_id,
+                    userpass,
+                };
+                session.serve(socket, streaming_client).await;
+            },
+            Err(e) => error!("Error upgrading the connection of {} to WebSocket: {}", client, e),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, "upgrade")
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_ACCEPT, accept_key)
+        .body(Body::empty())
+        .expect("Returning 101 should never fail.")
+}
+
+/// Registers the streaming client of the socket with the `client_id`, or with a random unused ID if not passed.
+fn register_streaming_client(
+    ctx: &MmArc,
+    client_id: Option<u64>,
+    last_event_id: Option<u64>,
+) -> Result<(u64, (ClientHandle, Vec<Arc<Event>>)), StreamingManagerError> {
+    let manager = &ctx.event_stream_manager;
+    let Some(client_id) = client_id else {
+        loop {
+            let client_id = rand::random();
+            match manager.new_client(client_id) {
+                Ok(handle) => return Ok((client_id, (handle, Vec::new()))),
+                Err(StreamingManagerError::ClientExists) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    };
+    let streaming_client = match last_event_id {
+        Some(last_event_id) => manager.reconnect_client(client_id, last_event_id)?,
+        None => (manager.new_client(client_id)?, Vec::new()),
+    };
+    Ok((client_id, streaming_client))
+}
+
+/// The origin allowed to make the RPC requests, see the `rpccors` handling of the HTTP server.
+fn allowed_origin(ctx: &MmArc) -> String {
+    match ctx.conf["rpccors"].as_str() {
+        Some(origin) => origin.to_string(),
+        None if ctx.is_https() => "https://localhost:3000".to_string(),
+        None => "http://localhost:3000".to_string(),
+    }
+}
+
+/// The requests without `Origin` don't come from a browser, so they are allowed.
+fn is_origin_allowed(allowed_origin: &str, origin: Option<&str>) -> bool {
+    match origin {
+        Some(origin) => allowed_origin == "*" || origin.eq_ignore_ascii_case(allowed_origin.trim_end_matches('/')),
+        None => true,
+    }
+}
+
+/// Checks the `userpass` query parameter is either `rpc_password` or one of the API keys.
+async fn authenticate(ctx: &MmArc, request: &Request<Body>, client: &SocketAddr) -> DispatcherResult<String> {
+    // No RPC method is available over the socket if the request came from outside while the RPC is local only.
+    let local_only = ctx.conf["rpc_local_only"].as_bool().unwrap_or(true);
+    if local_only && !client.ip().is_loopback() {
+        return MmError::err(DispatcherError::LocalHostOnly);
+    }
+
+    let rate_limit_ctx = RateLimitContext::from_ctx(ctx).unwrap();
+    if rate_limit_ctx.is_banned(client.ip()).await {
+        return MmError::err(DispatcherError::Banned);
+    }
+
+    let rpc_password = ctx.conf["rpc_password"].as_str().unwrap_or_else(|| {
+        warn!("'rpc_password' is not set in the config");
+        ""
+    });
+    // The scopes of an API key are checked once the requests are dispatched.
+    let is_valid = |userpass: &str| userpass == rpc_password || rate_limit_ctx.api_keys.find(userpass).is_some();
+    match query_param(request, "userpass").map(percent_decode) {
+        Some(userpass) if is_valid(&userpass) => Ok(userpass),
+        Some(_) => Err(process_rate_limit(ctx, client).await),
+        None => MmError::err(DispatcherError::UserpassIsNotSet),
+    }
+}
+
+/// Validates the WebSocket handshake request and returns the `Sec-WebSocket-Accept` value of the response.
+fn accept_key(request: &Request<Body>) -> Option<String> {
+    let is_upgrade = request.method() == Method::GET
+        && header_contains(request, CONNECTION, "upgrade")
+        && header_contains(request, UPGRADE, "websocket")
+        && request.headers().get(SEC_WEBSOCKET_VERSION)? == WEBSOCKET_VERSION;
+    if !is_upgrade {
+        return None;
+    }
+    let key = request.headers().get(SEC_WEBSOCKET_KEY)?;
+    Some(derive_accept_key(key.as_bytes()))
+}
+
+/// Whether the comma-separated values of the `name` header contain the `token` (case-insensitive).
+fn header_contains(request: &Request<Body>, name: HeaderName, token: &str) -> bool {
+    request
+        .headers()
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim().eq_ignore_ascii_case(token))
+}
+
+/// Decodes the percent-encoded query parameter `value`, the invalid escapes are kept as is.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn plain_response(status: StatusCode, message: String) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(message))
+        .expect("Returning an error response should never fail.")
+}
+
+/// Formats the event as a socket message, the journaled events are sent with their IDs.
+fn event_message(event: &Event) -> Message {
+    let (event_type, message) = event.get();
+    let mut data = json!({
+        "_type": event_type,
+        "message": message,
+    });
+    if let Some(id) = event.id() {
+        data["id"] = id.into();
+    }
+    Message::Text(data.to_string())
+}
+
+/// Fills in the `userpass` of the authenticated socket and the streaming `client_id` of the socket
+/// if the request (or the requests of the batch) doesn't specify them.
+fn complete_request(request: &mut Json, userpass: &str, client_id: u64) {
+    if let Some(requests) = request.as_array_mut() {
+        requests
+            .iter_mut()
+            .for_each(|request| complete_request(request, userpass, client_id));
+        return;
+    }
+    let Some(request) = request.as_object_mut() else {
+        return;
+    };
+
+    request
+        .entry("userpass")
+        .or_insert_with(|| Json::from(userpass.to_string()));
+    let is_streaming_request = request
+        .get("method")
+        .and_then(Json::as_str)
+        .map_or(false, |method| method.starts_with("stream::"));
+    if is_streaming_request {
+        if let Some(params) = request.entry("params").or_insert_with(|| json!({})).as_object_mut() {
+            params.entry("client_id").or_insert_with(|| Json::from(client_id));
+        }
+    }
+}
+
+struct WsSession {
+    ctx: MmArc,
+    client: SocketAddr,
+    client_id: u64,
+    userpass: String,
+}
+
+impl WsSession {
+    async fn serve(self, socket: WebSocketStream<Upgraded>, streaming_client: Option<(ClientHandle, Vec<Arc<Event>>)>) {
+        let (mut sink, mut frames) = socket.split();
+        let (mut events, missed_events) = match streaming_client {
+            Some((handle, missed_events)) => (Some(handle), missed_events),
+            None => (None, Vec::new()),
+        };
+        // The requests are processed concurrently, their responses are sent through this channel.
+        let (responses_tx, mut responses_rx) = mpsc::unbounded();
+
+        for event in missed_events {
+            if let Err(e) = sink.send(event_message(&event)).await {
+                return warn!("Error sending an event to the WebSocket client {}: {}", self.client, e);
+            }
+        }
+
+        loop {
+            let next_event = async {
+                match events.as_mut() {
+                    Some(events) => events.recv().await,
+                    None => futures::future::pending().await,
+                }
+            }
+            .fuse();
+            futures::pin_mut!(next_event);
+
+            let message = select! {
+                frame = frames.next().fuse() => match frame {
+                    Some(Ok(Message::Text(text))) => {
+                        self.process_request(text, responses_tx.clone());
+                        continue;
+                    },
+                    Some(Ok(Message::Close(_))) | None => break,
+                    // The pings are answered by `tungstenite` itself, the rest of the frames are ignored.
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        warn!("Error reading from the WebSocket client {}: {}", self.client, e);
+                        break;
+                    },
+                },
+                event = next_event => match event {
+                    Some(event) => event_message(&event),
+                    // The client was removed from the streaming manager.
+                    None => break,
+                },
+                response = responses_rx.next() => match response {
+                    Some(response) => response,
+                    None => continue,
+                },
+            };
+
+            if let Err(e) = sink.send(message).await {
+                warn!("Error writing to the WebSocket client {}: {}", self.client, e);
+                break;
+            }
+        }
+        sink.close().await.ok();
+    }
+
+    /// Processes the request in the background, so the long-running requests don't hold up the others and the events.
+    fn process_request(&self, text: String, responses_tx: mpsc::UnboundedSender<Message>) {
+        let ctx = self.ctx.clone();
+        let client = self.client;
+        let client_id = self.client_id;
+        let userpass = self.userpass.clone();
+
+        self.ctx.spawner().spawn(async move {
+            let response = match json::from_str::<Json>(&text) {
+                Ok(mut request) => {
+                    complete_request(&mut request, &userpass, client_id);
+                    match process_json_request(ctx, request, client).await {
+                        Ok(response) => String::from_utf8_lossy(response.body()).into_owned(),
+                        Err(e) => {
+                            error!("RPC error response: {}", e);
+                            err_to_rpc_json_string(&e)
+                        },
+                    }
+                },
+                Err(e) => {
+                    let error = MmError::new(DispatcherError::from(e));
+                    let response = response_from_dispatcher_error(error, MmRpcVersion::V2, None);
+                    String::from_utf8_lossy(response.body()).into_owned()
+                },
+            };
+            if responses_tx.unbounded_send(Message::Text(response)).is_err() {
+                warn!(
+                    "The WebSocket client {} disconnected before receiving the response",
+                    client
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("pass"), "pass");
+        assert_eq!(percent_decode("p%40ss%2Fw%25rd"), "p@ss/w%rd");
+        // The invalid escapes are kept as is.
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn test_is_origin_allowed() {
+        assert!(is_origin_allowed(
+            "http://localhost:3000",
+            Some("http://localhost:3000")
+        ));
+        assert!(is_origin_allowed(
+            "http://localhost:3000/",
+            Some("http://localhost:3000")
+        ));
+        assert!(!is_origin_allowed(
+            "http://localhost:3000",
+            Some("https://evil.example")
+        ));
+        assert!(!is_origin_allowed("http://localhost:3000", Some("null")));
+        assert!(is_origin_allowed("*", Some("https://app.example")));
+        // The non-browser clients don't send the origin.
+        assert!(is_origin_allowed("http://localhost:3000", None));
+    }
+
+    #[test]
+    fn test_complete_request() {
+        let mut request = json!({
+            "mmrpc": "2.0",
+            "method": "stream::balance::enable",
+            "params": { "coin": "RICK" },
+        });
+        complete_request(&mut request, "pass", 7);
+        assert_eq!(request["userpass"], "pass");
+        assert_eq!(request["params"]["client_id"], 7);
+
+        // The values set by the client aren't overridden.
+        let mut batch = json!([
+            { "method": "stream::disable", "userpass": "other", "params": { "client_id": 1 } },
+            { "method": "my_balance", "params": { "coin": "RICK" } },
+        ]);
+        complete_request(&mut batch, "pass", 7);
+        assert_eq!(batch[0]["userpass"], "other");
+        assert_eq!(batch[0]["params"]["client_id"], 1);
+        assert_eq!(batch[1]["userpass"], "pass");
+        assert!(batch[1]["params"].get("client_id").is_none());
+    }
+}
@@ -395,6 +395,7 @@ fn global_nft_with_random_privkey(
         enable_params: Default::default(),
         path_to_address: Default::default(),
         gap_limit: None,
+        rpc_quorum: None,
     };
     let coin = block_on(eth_coin_from_conf_and_request_v2(
         &MM_CTX1,
@@ -465,6 +466,7 @@ fn sepolia_coin_from_privkey(ctx: &MmArc, secret: &'static str, ticker: &str, co
         enable_params: Default::default(),
         path_to_address: Default::default(),
         gap_limit: None,
+        rpc_quorum: None,
     };
     let coin = block_on(eth_coin_from_conf_and_request_v2(
         ctx,
@@ -1458,6 +1460,7 @@ fn eth_coin_v2_activation_with_random_privkey(
         enable_params: Default::default(),
         path_to_address: Default::default(),
         gap_limit: None,
+        rpc_quorum: None,
     };
     let coin = block_on(eth_coin_from_conf_and_request_v2(
         ctx,
@@ -1,9 +1,33 @@
 use http::header::{ACCESS_CONTROL_ALLOW_ORIGIN, CACHE_CONTROL, CONTENT_TYPE};
 use hyper::{body::Bytes, Body, Request, Response};
 use mm2_core::mm_ctx::MmArc;
+use mm2_event_stream::Event;
 use serde_json::json;
 
 pub const SSE_ENDPOINT: &str = "/event-stream";
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
+/// Returns the value of the `name` query parameter.
+pub fn query_param<'a>(request: &'a Request<Body>, name: &str) -> Option<&'a str> {
+    request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|param| param.strip_prefix(name).and_then(|rest| rest.strip_prefix('=')))
+    })
+}
+
+/// Formats the event as an SSE message, the journaled events are sent with their IDs.
+fn sse_message(event: &Event) -> Bytes {
+    let (event_type, message) = event.get();
+    let data = json!({
+        "_type": event_type,
+        "message": message,
+    });
+    match event.id() {
+        Some(id) => Bytes::from(format!("id: {id}\ndata: {data} \n\n")),
+        None => Bytes::from(format!("data: {data} \n\n")),
+    }
+}
 
 /// Handles broadcasted messages from `mm2_event_stream` continuously.
 pub async fn handle_sse(request: Request<Body>, ctx_h: u32) -> Response<Body> {
@@ -16,32 +40,36 @@ pub async fn handle_sse(request: Request<Body>, ctx_h: u32) -> Response<Body> {
         return handle_internal_error("Event streaming is disabled".to_string()).await;
     };
 
-    let client_id = match request.uri().query().and_then(|query| {
-        query
-            .split('&')
-            .find(|param| param.starts_with("id="))
-            .map(|id_param| id_param.trim_start_matches("id=").parse::<u64>())
-    }) {
+    let client_id = match query_param(&request, "id").map(str::parse::<u64>) {
         Some(Ok(id)) => id,
         // Default to zero when client ID isn't passed, most of the cases we will have a single user/client.
         _ => 0,
     };
 
+    // The browsers send the ID of the last received event on reconnection, the other clients may pass it as a cursor.
+    let last_event_id = request
+        .headers()
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| query_param(&request, "last_event_id"))
+        .and_then(|id| id.trim().parse::<u64>().ok());
+
     let event_stream_manager = ctx.event_stream_manager.clone();
-    let Ok(mut rx) = event_stream_manager.new_client(client_id) else {
+    let client = match last_event_id {
+        Some(last_event_id) => event_stream_manager.reconnect_client(client_id, last_event_id),
+        None => event_stream_manager.new_client(client_id).map(|rx| (rx, Vec::new())),
+    };
+    let Ok((mut rx, missed_events)) = client else {
         return handle_internal_error("ID already in use".to_string()).await;
     };
     let body = Body::wrap_stream(async_stream::stream! {
+        for event in missed_events {
+            yield Ok::<_, hyper::Error>(sse_message(&event));
+        }
         while let Some(event) = rx.recv().await {
             // The event's filter will decide whether to expose the event data to this client or not.
             // This happens based on the events that this client has subscribed to.
-            let (event_type, message) = event.get();
-            let data = json!({
-                "_type": event_type,
-                "message": message,
-            });
-
-            yield Ok::<_, hyper::Error>(Bytes::from(format!("data: {data} \n\n")));
+            yield Ok::<_, hyper::Error>(sse_message(&event));
         }
     });
 
@@ -122,6 +122,20 @@ pub trait SlurpHttpClient {
         self.slurp_req(request).await
     }
 
+    /// Executes a POST request with additional headers.
+    /// Returning the response status, headers and body.
+    async fn slurp_post_json_with_headers(&self, url: &str, body: String, headers: Vec<(&str, &str)>) -> SlurpResult {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri(url)
+            .header(header::CONTENT_TYPE, APPLICATION_JSON);
+        for (key, value) in headers {
+            request = request.header(key, value);
+        }
+        let request = request.body(body.into())?;
+        self.slurp_req(request).await
+    }
+
     /// Executes a GET request, returning the response status, headers and body.
     async fn slurp_url(&self, url: &str) -> SlurpResult {
         let req = Request::builder().uri(url).body(Vec::new())?;
@@ -206,6 +220,12 @@ pub async fn slurp_url_with_headers(url: &str, headers: Vec<(&'static str, &'sta
 /// Executes a POST request, returning the response status, headers and body.
 pub async fn slurp_post_json(url: &str, body: String) -> SlurpResult { HYPER.slurp_post_json(url, body).await }
 
+/// Executes a POST request with additional headers.
+/// Returning the response status, headers and body.
+pub async fn slurp_post_json_with_headers(url: &str, body: String, headers: Vec<(&str, &str)>) -> SlurpResult {
+    HYPER.slurp_post_json_with_headers(url, body, headers).await
+}
+
 impl From<Canceled> for SlurpError {
     fn from(_: Canceled) -> Self { SlurpError::Internal("Spawned Slurp future has been canceled".to_owned()) }
 }
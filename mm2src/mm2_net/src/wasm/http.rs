@@ -51,6 +51,19 @@ pub async fn slurp_post_json(url: &str, body: String) -> SlurpResult {
         .map(|(status_code, response)| (status_code, HeaderMap::new(), response.into_bytes()))
 }
 
+/// Executes a POST request with additional headers.
+/// Returning the response status, headers and body.
+/// Please note the return header map is empty, because `wasm_bindgen` doesn't provide the way to extract all headers.
+pub async fn slurp_post_json_with_headers(url: &str, body: String, headers: Vec<(&str, &str)>) -> SlurpResult {
+    FetchRequest::post(url)
+        .header(CONTENT_TYPE.as_str(), APPLICATION_JSON)
+        .headers(headers)
+        .body_utf8(body)
+        .request_str()
+        .await
+        .map(|(status_code, response)| (status_code, HeaderMap::new(), response.into_bytes()))
+}
+
 /// Sets the response headers and extracts the content type.
 ///
 /// This function takes a `Builder` for a response and a `JsResponse` from which it extracts
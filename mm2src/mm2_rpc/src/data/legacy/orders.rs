@@ -6,7 +6,7 @@ use uuid::Uuid;
 use common::true_f;
 use mm2_number::{construct_detailed, BigDecimal, BigRational, Fraction, MmNumber};
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct SellBuyRequest {
     pub base: String,
     pub rel: String,
@@ -79,6 +79,20 @@ pub enum OrderType {
     FillOrKill,
     #[default]
     GoodTillCancelled,
+    /// Same as `GoodTillCancelled`, but the order (or the maker order it's converted to) is cancelled
+    /// automatically once `expires_at` (UTC timestamp in seconds) is reached.
+    GoodTillTime {
+        expires_at: u64,
+    },
+}
+
+impl OrderType {
+    pub fn expires_at(&self) -> Option<u64> {
+        match self {
+            OrderType::GoodTillTime { expires_at } => Some(*expires_at),
+            OrderType::FillOrKill | OrderType::GoodTillCancelled => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -159,6 +159,65 @@ impl<'a> TrezorSession<'a> {
             .map_to_mm(|err| TrezorError::Internal(err.to_string()))
     }
 
+    /// Signs a message with the EIP-191 `personal_sign` prefix using the Trezor device.
+    /// The device adds the prefix itself.
+    pub async fn sign_eth_message(
+        &mut self,
+        derivation_path: &DerivationPath,
+        message: Vec<u8>,
+        chain_id: u64,
+    ) -> TrezorResult<Signature> {
+        let processor = self
+            .processor
+            .as_ref()
+            .or_mm_err(|| TrezorError::InternalNoProcessor)?
+            .clone();
+        let req = proto_ethereum::EthereumSignMessage {
+            address_n: serialize_derivation_path(derivation_path),
+            message,
+            encoded_network: get_eth_network_def(chain_id),
+            chunkify: None,
+        };
+        let result_handler = ResultHandler::new(|m: proto_ethereum::EthereumMessageSignature| Ok(m.signature));
+        let signature = self
+            .call(req, result_handler)
+            .await?
+            .process(processor)
+            .await
+            .mm_err(|e| TrezorError::Internal(e.to_string()))?;
+        extract_eth_message_signature(&signature)
+    }
+
+    /// Signs EIP-712 typed data given its domain separator and message hashes using the Trezor device.
+    /// Note that only some of the devices support blind signing of typed data hashes.
+    pub async fn sign_eth_typed_hash(
+        &mut self,
+        derivation_path: &DerivationPath,
+        domain_separator_hash: H256,
+        message_hash: Option<H256>,
+        chain_id: u64,
+    ) -> TrezorResult<Signature> {
+        let processor = self
+            .processor
+            .as_ref()
+            .or_mm_err(|| TrezorError::InternalNoProcessor)?
+            .clone();
+        let req = proto_ethereum::EthereumSignTypedHash {
+            address_n: serialize_derivation_path(derivation_path),
+            domain_separator_hash: domain_separator_hash.as_bytes().to_vec(),
+            message_hash: message_hash.map(|hash| hash.as_bytes().to_vec()),
+            encoded_network: get_eth_network_def(chain_id),
+        };
+        let result_handler = ResultHandler::new(|m: proto_ethereum::EthereumTypedDataSignature| Ok(m.signature));
+        let signature = self
+            .call(req, result_handler)
+            .await?
+            .process(processor)
+            .await
+            .mm_err(|e| TrezorError::Internal(e.to_string()))?;
+        extract_eth_message_signature(&signature)
+    }
+
     async fn send_sign_eth_tx<'b, S>(
         &'b mut self,
         req: S,
@@ -310,6 +369,23 @@ fn extract_eth_signature(tx_request: &proto_ethereum::EthereumTxRequest) -> Trez
     }
 }
 
+/// Converts a 65-byte `r ‖ s ‖ v` message signature with `v` being 27 or 28 to the `Signature` with 0 or 1.
+fn extract_eth_message_signature(signature: &[u8]) -> TrezorResult<Signature> {
+    if signature.len() != 65 {
+        return MmError::err(TrezorError::Failure(OperationFailure::InvalidSignature));
+    }
+    let v = match signature[64] {
+        v @ 0..=1 => v,
+        v @ 27..=28 => v - 27,
+        _ => return MmError::err(TrezorError::Failure(OperationFailure::InvalidSignature)),
+    };
+    Ok(Signature::from_rsv(
+        &H256::from_slice(&signature[..32]),
+        &H256::from_slice(&signature[32..64]),
+        v,
+    ))
+}
+
 fn extract_eth_eip1559_signature(tx_request: &proto_ethereum::EthereumTxRequest) -> TrezorResult<Signature> {
     match (
         tx_request.signature_r.as_ref(),
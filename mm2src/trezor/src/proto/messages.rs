@@ -105,6 +105,8 @@ pub enum MessageType {
     EthereumSignMessage = 64,
     EthereumVerifyMessage = 65,
     EthereumMessageSignature = 66,
+    EthereumTypedDataSignature = 469,
+    EthereumSignTypedHash = 470,
     /// NEM
     NemGetAddress = 67,
     NemAddress = 68,
@@ -196,3 +196,71 @@ pub struct EthereumTxAck {
     #[prost(bytes = "vec", required, tag = "1")]
     pub data_chunk: ::prost::alloc::vec::Vec<u8>,
 }
+
+///*
+/// Request: Ask device to sign message
+/// @start
+/// @next EthereumMessageSignature
+/// @next Failure
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthereumSignMessage {
+    /// BIP-32 path to derive the key from master node
+    #[prost(uint32, repeated, tag = "1")]
+    pub address_n: ::prost::alloc::vec::Vec<u32>,
+    /// message to be signed
+    #[prost(bytes = "vec", required, tag = "2")]
+    pub message: ::prost::alloc::vec::Vec<u8>,
+    /// encoded Ethereum network, see ethereum-definitions.md for details
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub encoded_network: ::std::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// display the address in chunks of 4 characters
+    #[prost(bool, optional, tag = "4")]
+    pub chunkify: ::std::option::Option<bool>,
+}
+
+///*
+/// Response: Signed message
+/// @end
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthereumMessageSignature {
+    /// signature of the message
+    #[prost(bytes = "vec", required, tag = "2")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+    /// address used to sign the message
+    #[prost(string, required, tag = "3")]
+    pub address: ::prost::alloc::string::String,
+}
+
+///*
+/// Request: Ask device to sign hash of typed data
+/// @start
+/// @next EthereumTypedDataSignature
+/// @next Failure
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthereumSignTypedHash {
+    /// BIP-32 path to derive the key from master node
+    #[prost(uint32, repeated, tag = "1")]
+    pub address_n: ::prost::alloc::vec::Vec<u32>,
+    /// Hash of domainSeparator of typed data to be signed
+    #[prost(bytes = "vec", required, tag = "2")]
+    pub domain_separator_hash: ::prost::alloc::vec::Vec<u8>,
+    /// Hash of the data of typed data to be signed (empty if domain-only data)
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub message_hash: ::std::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// encoded Ethereum network, see ethereum-definitions.md for details
+    #[prost(bytes = "vec", optional, tag = "4")]
+    pub encoded_network: ::std::option::Option<::prost::alloc::vec::Vec<u8>>,
+}
+
+///*
+/// Response: Signed typed data
+/// @end
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EthereumTypedDataSignature {
+    /// signature of the typed data
+    #[prost(bytes = "vec", required, tag = "1")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+    /// address used to sign the typed data
+    #[prost(string, required, tag = "2")]
+    pub address: ::prost::alloc::string::String,
+}
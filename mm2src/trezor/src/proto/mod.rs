@@ -113,3 +113,7 @@ trezor_message_impl!(EthereumGetAddress, MessageType::EthereumGetAddress);
 trezor_message_impl!(EthereumAddress, MessageType::EthereumAddress);
 trezor_message_impl!(EthereumGetPublicKey, MessageType::EthereumGetPublicKey);
 trezor_message_impl!(EthereumPublicKey, MessageType::EthereumPublicKey);
+trezor_message_impl!(EthereumSignMessage, MessageType::EthereumSignMessage);
+trezor_message_impl!(EthereumMessageSignature, MessageType::EthereumMessageSignature);
+trezor_message_impl!(EthereumSignTypedHash, MessageType::EthereumSignTypedHash);
+trezor_message_impl!(EthereumTypedDataSignature, MessageType::EthereumTypedDataSignature);
@@ -5,7 +5,8 @@ mod utxo_command;
 
 pub use prev_tx::{PrevTx, PrevTxInput, PrevTxOutput};
 pub use sign_utxo::TxSignResult;
-pub use unsigned_tx::{TrezorInputScriptType, TrezorOutputScriptType, TxOutput, UnsignedTxInput, UnsignedUtxoTx};
+pub use unsigned_tx::{TrezorInputScriptType, TrezorMultisig, TrezorOutputScriptType, TxOutput, UnsignedTxInput,
+                      UnsignedUtxoTx};
 pub use utxo_command::IGNORE_XPUB_MAGIC;
 
 pub type TxHash = Vec<u8>;
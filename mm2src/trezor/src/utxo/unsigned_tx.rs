@@ -1,7 +1,8 @@
 use crate::proto::messages_bitcoin as proto_bitcoin;
+use crate::proto::messages_common as proto_common;
 use crate::utxo::prev_tx::PrevTx;
 use crate::{serialize_derivation_path, TrezorError, TrezorResult};
-use hw_common::primitives::DerivationPath;
+use hw_common::primitives::{DerivationPath, Secp256k1ExtendedPublicKey};
 use mm2_err_handle::prelude::*;
 
 /// https://github.com/trezor/trezor-common/blob/master/protob/messages-bitcoin.proto#L16
@@ -51,9 +52,52 @@ impl From<TrezorOutputScriptType> for proto_bitcoin::OutputScriptType {
     }
 }
 
+/// The m-of-n multisig address an input spends from, it's used by the device to build the multisig script.
+/// https://github.com/trezor/trezor-common/blob/master/protob/messages-bitcoin.proto#L35
+pub struct TrezorMultisig {
+    /// The number of signatures required to spend the output.
+    pub required: u32,
+    /// The account extended public keys of the cosigners
+    /// in the order their public keys are pushed to the multisig script.
+    pub xpubs: Vec<Secp256k1ExtendedPublicKey>,
+    /// The non-hardened path from the account extended public keys to the address public keys.
+    pub address_path: Vec<u32>,
+}
+
+impl TrezorMultisig {
+    fn to_proto(&self) -> proto_bitcoin::MultisigRedeemScriptType {
+        let pubkeys = self
+            .xpubs
+            .iter()
+            .map(|xpub| {
+                let attrs = xpub.attrs();
+                let node = proto_common::HdNodeType {
+                    depth: attrs.depth as u32,
+                    fingerprint: u32::from_be_bytes(attrs.parent_fingerprint),
+                    child_num: u32::from(attrs.child_number),
+                    chain_code: attrs.chain_code.to_vec(),
+                    private_key: None,
+                    public_key: xpub.public_key().serialize().to_vec(),
+                };
+                proto_bitcoin::multisig_redeem_script_type::HdNodePathType {
+                    node,
+                    address_n: self.address_path.clone(),
+                }
+            })
+            .collect();
+        proto_bitcoin::MultisigRedeemScriptType {
+            pubkeys,
+            // The signatures of the other cosigners aren't required to sign the input.
+            signatures: vec![Vec::new(); self.xpubs.len()],
+            m: self.required,
+            nodes: Vec::new(),
+            address_n: Vec::new(),
+        }
+    }
+}
+
 /// Missing fields:
 /// * script_sig - https://docs.trezor.io/trezor-firmware/common/communication/bitcoin-signing.html#external-inputs
-/// * multisig - filled if input is going to spend multisig tx
 /// * decred_tree - only for Decred, 0 is a normal transaction while 1 is a stake transaction
 /// * witness - witness data, only set for EXTERNAL inputs
 /// * ownership_proof - SLIP-0019 proof of ownership, only set for EXTERNAL inputs
@@ -63,7 +107,7 @@ impl From<TrezorOutputScriptType> for proto_bitcoin::OutputScriptType {
 /// * decred_staking_spend - if not None this holds the type of stake spend: revocation or stake generation
 pub struct UnsignedTxInput {
     /// BIP-32 path to derive the key from master node.
-    /// TODO I guess this field shouldn't be set if the input script type is Multisig, for example.
+    /// If the input spends a multisig output, this is the path of the device's own key of the multisig address.
     pub address_derivation_path: Option<DerivationPath>,
     /// Info of previous transaction.
     pub prev_tx: PrevTx,
@@ -77,6 +121,8 @@ pub struct UnsignedTxInput {
     pub input_script_type: TrezorInputScriptType,
     /// Amount of previous transaction output.
     pub amount: u64,
+    /// Filled if the input is going to spend a multisig output.
+    pub multisig: Option<TrezorMultisig>,
 }
 
 impl UnsignedTxInput {
@@ -92,7 +138,7 @@ impl UnsignedTxInput {
             script_sig: None,
             sequence: Some(self.sequence),
             script_type: Some(proto_bitcoin::InputScriptType::from(self.input_script_type) as i32),
-            multisig: None,
+            multisig: self.multisig.as_ref().map(TrezorMultisig::to_proto),
             amount: self.amount,
             decred_tree: None,
             witness: None,